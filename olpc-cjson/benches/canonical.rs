@@ -0,0 +1,73 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Measures how long it takes to serialize a large TUF targets role as canonical JSON.
+//!
+//! Run with `cargo bench -p olpc-cjson`. Pass a number of targets to change the size of the role
+//! (the default is 10,000).
+
+use olpc_cjson::CanonicalFormatter;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Builds a targets role shaped like the ones tough writes, with `count` targets.
+fn targets_role(count: usize) -> Value {
+    let targets: serde_json::Map<String, Value> = (0..count)
+        .map(|i| {
+            (
+                format!("images/variant-{}/image-{i}.img.lz4", i % 7),
+                json!({
+                    "length": 1_048_576 + i,
+                    "hashes": {
+                        "sha256": format!("{i:064x}"),
+                    },
+                    "custom": {
+                        "version": format!("1.{}.{}", i % 13, i % 101),
+                        "variant": format!("variant-{}", i % 7),
+                    },
+                }),
+            )
+        })
+        .collect();
+    json!({
+        "signed": {
+            "_type": "targets",
+            "spec_version": "1.0.0",
+            "version": 1,
+            "expires": "2030-01-01T00:00:00Z",
+            "targets": targets,
+        },
+        "signatures": [],
+    })
+}
+
+fn canonical(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut ser = serde_json::Serializer::with_formatter(&mut buf, CanonicalFormatter::new());
+    value.serialize(&mut ser).unwrap();
+    buf
+}
+
+fn main() {
+    let count = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(10_000);
+    let role = targets_role(count);
+    let size = canonical(&role).len();
+
+    // Run for at least a couple of seconds and at least a few iterations.
+    let mut iterations = 0_u32;
+    let start = Instant::now();
+    while iterations < 5 || start.elapsed() < Duration::from_secs(2) {
+        black_box(canonical(black_box(&role)));
+        iterations += 1;
+    }
+    let per_iteration = start.elapsed() / iterations;
+
+    println!(
+        "canonical targets role ({count} targets, {size} bytes): {per_iteration:?}/iter ({iterations} iterations)"
+    );
+}
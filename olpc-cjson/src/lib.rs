@@ -36,9 +36,8 @@
 
 use serde::Serialize;
 use serde_json::ser::{CharEscape, CompactFormatter, Formatter, Serializer};
-use std::collections::BTreeMap;
 use std::io::{Error, ErrorKind, Result, Write};
-use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 
 /// A [`Formatter`] that produces canonical JSON.
 ///
@@ -52,29 +51,88 @@ pub struct CanonicalFormatter {
 
 /// Internal struct to keep track of an object in progress of being built.
 ///
-/// As keys and values are received by `CanonicalFormatter`, they are written to `next_key` and
-/// `next_value` by using the `CanonicalFormatter::writer` convenience method.
+/// As keys and values are received by `CanonicalFormatter`, they are appended to `buf` by using
+/// the `CanonicalFormatter::writer` convenience method. Each entry only records where its key and
+/// value are in `buf`, so building an object doesn't allocate once per key and value.
 ///
 /// How this struct behaves when `Formatter` methods are called:
 ///
 /// ```plain
 /// [other methods]  // values written to the writer received by method
 /// begin_object     // create this object
-/// /-> begin_object_key    // object.key_done = false;
-/// |   [other methods]     // values written to object.next_key, writer received by method ignored
-/// |   end_object_key      // object.key_done = true;
+/// /-> begin_object_key    // object.key_start = object.buf.len();
+/// |   [other methods]     // key written to object.buf, writer received by method ignored
+/// |   end_object_key      // object.value_start = object.buf.len();
 /// |   begin_object_value  // [nothing]
-/// |   [other methods]     // values written to object.next_value
-/// |   end_object_value    // object.next_key and object.next_value are inserted into object.obj
+/// |   [other methods]     // value written to object.buf
+/// |   end_object_value    // the entry's key and value ranges are pushed to object.entries
 /// \---- // jump back if more values are present
 /// end_object       // write the object (sorted by its keys) to the writer received by the method
 /// ```
 #[derive(Debug, Default)]
 struct Object {
-    obj: BTreeMap<Vec<u8>, Vec<u8>>,
-    next_key: Vec<u8>,
-    next_value: Vec<u8>,
-    key_done: bool,
+    buf: Vec<u8>,
+    entries: Vec<Entry>,
+    key_start: usize,
+    value_start: usize,
+}
+
+/// The position of an object's key and value in `Object::buf`: the key is `key_start..value_start`
+/// and the value is `value_start..end`.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    key_start: usize,
+    value_start: usize,
+    end: usize,
+}
+
+impl Object {
+    fn key(&self, entry: Entry) -> &[u8] {
+        &self.buf[entry.key_start..entry.value_start]
+    }
+
+    fn value(&self, entry: Entry) -> &[u8] {
+        &self.buf[entry.value_start..entry.end]
+    }
+}
+
+/// The writer returned by `CanonicalFormatter::writer`: either the writer received by a
+/// `Formatter` method, or the buffer of the object in progress.
+enum ContextWriter<'a, W: ?Sized> {
+    Writer(&'a mut W),
+    Object(&'a mut Vec<u8>),
+}
+
+impl<W: Write + ?Sized> ContextWriter<'_, W> {
+    /// Reserves space for `additional` more bytes if we are writing to an object's buffer.
+    fn reserve(&mut self, additional: usize) {
+        if let ContextWriter::Object(buf) = self {
+            buf.reserve(additional);
+        }
+    }
+}
+
+impl<W: Write + ?Sized> Write for ContextWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match self {
+            ContextWriter::Writer(writer) => writer.write(buf),
+            ContextWriter::Object(object) => object.write(buf),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        match self {
+            ContextWriter::Writer(writer) => writer.write_all(buf),
+            ContextWriter::Object(object) => object.write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            ContextWriter::Writer(writer) => writer.flush(),
+            ContextWriter::Object(_) => Ok(()),
+        }
+    }
 }
 
 impl CanonicalFormatter {
@@ -86,29 +144,21 @@ impl CanonicalFormatter {
     /// Convenience method to return the appropriate writer given the current context.
     ///
     /// If we are currently writing an object (that is, if `!self.object_stack.is_empty()`), we
-    /// need to write the value to either the next key or next value depending on that state
-    /// machine. See the docstrings for `Object` for more detail.
+    /// need to write to that object's buffer. See the docstrings for `Object` for more detail.
     ///
     /// If we are not currently writing an object, pass through `writer`.
-    fn writer<'a, W: Write + ?Sized>(&'a mut self, writer: &'a mut W) -> Box<dyn Write + 'a> {
+    fn writer<'a, W: Write + ?Sized>(&'a mut self, writer: &'a mut W) -> ContextWriter<'a, W> {
         self.object_stack
             .last_mut()
-            .map_or(Box::new(writer), |object| {
-                if object.key_done {
-                    Box::new(&mut object.next_value)
-                } else {
-                    Box::new(&mut object.next_key)
-                }
+            .map_or(ContextWriter::Writer(writer), |object| {
+                ContextWriter::Object(&mut object.buf)
             })
     }
 
     /// Returns a mutable reference to the top of the object stack.
     fn obj_mut(&mut self) -> Result<&mut Object> {
         self.object_stack.last_mut().ok_or_else(|| {
-            Error::new(
-                ErrorKind::Other,
-                "serde_json called an object method without calling begin_object first",
-            )
+            Error::other("serde_json called an object method without calling begin_object first")
         })
     }
 }
@@ -176,16 +226,19 @@ impl Formatter for CanonicalFormatter {
     wrapper!(end_string);
 
     // Strings are normalized as Normalization Form C (NFC). `str::nfc` is provided by the
-    // `UnicodeNormalization` trait and returns an iterator of `char`s.
+    // `UnicodeNormalization` trait and returns an iterator of `char`s. Most strings (and all ASCII
+    // strings) are already in NFC, so they are written as-is.
     fn write_string_fragment<W: Write + ?Sized>(
         &mut self,
         writer: &mut W,
         fragment: &str,
     ) -> Result<()> {
-        fragment.nfc().try_for_each(|ch| {
-            self.writer(writer)
-                .write_all(ch.encode_utf8(&mut [0; 4]).as_bytes())
-        })
+        if fragment.is_ascii() || is_nfc_quick(fragment.chars()) == IsNormalized::Yes {
+            self.writer(writer).write_all(fragment.as_bytes())
+        } else {
+            let normalized: String = fragment.nfc().collect();
+            self.writer(writer).write_all(normalized.as_bytes())
+        }
     }
 
     // Only quotes and backslashes are escaped in canonical JSON.
@@ -219,7 +272,7 @@ impl Formatter for CanonicalFormatter {
     wrapper!(end_array_value);
 
     // Here are the object methods. Because keys must be sorted, we serialize the object's keys and
-    // values in memory as a `BTreeMap`, then write it all out when `end_object_value` is called.
+    // values in memory, then sort and write it all out when `end_object` is called.
 
     fn begin_object<W: Write + ?Sized>(&mut self, writer: &mut W) -> Result<()> {
         CompactFormatter.begin_object(&mut self.writer(writer))?;
@@ -228,23 +281,35 @@ impl Formatter for CanonicalFormatter {
     }
 
     fn end_object<W: Write + ?Sized>(&mut self, writer: &mut W) -> Result<()> {
-        let object = self.object_stack.pop().ok_or_else(|| {
-            Error::new(
-                ErrorKind::Other,
+        let mut object = self.object_stack.pop().ok_or_else(|| {
+            Error::other(
                 "serde_json called Formatter::end_object object method
                  without calling begin_object first",
             )
         })?;
+        // The sort is stable, so if a key is repeated, the last value written for it wins.
+        let mut entries = std::mem::take(&mut object.entries);
+        entries.sort_by(|a, b| object.key(*a).cmp(object.key(*b)));
+        entries.dedup_by(|next, prev| {
+            let duplicate = object.key(*next) == object.key(*prev);
+            if duplicate {
+                *prev = *next;
+            }
+            duplicate
+        });
+
         let mut writer = self.writer(writer);
+        // Each entry needs a comma or colon on top of its key and value, plus the closing brace.
+        writer.reserve(object.buf.len() + 2 * entries.len() + 1);
         let mut first = true;
 
-        for (key, value) in object.obj {
+        for entry in entries {
             CompactFormatter.begin_object_key(&mut writer, first)?;
-            writer.write_all(&key)?;
+            writer.write_all(object.key(entry))?;
             CompactFormatter.end_object_key(&mut writer)?;
 
             CompactFormatter.begin_object_value(&mut writer)?;
-            writer.write_all(&value)?;
+            writer.write_all(object.value(entry))?;
             CompactFormatter.end_object_value(&mut writer)?;
 
             first = false;
@@ -255,13 +320,13 @@ impl Formatter for CanonicalFormatter {
 
     fn begin_object_key<W: Write + ?Sized>(&mut self, _writer: &mut W, _first: bool) -> Result<()> {
         let object = self.obj_mut()?;
-        object.key_done = false;
+        object.key_start = object.buf.len();
         Ok(())
     }
 
     fn end_object_key<W: Write + ?Sized>(&mut self, _writer: &mut W) -> Result<()> {
         let object = self.obj_mut()?;
-        object.key_done = true;
+        object.value_start = object.buf.len();
         Ok(())
     }
 
@@ -271,9 +336,11 @@ impl Formatter for CanonicalFormatter {
 
     fn end_object_value<W: Write + ?Sized>(&mut self, _writer: &mut W) -> Result<()> {
         let object = self.obj_mut()?;
-        let key = std::mem::take(&mut object.next_key);
-        let value = std::mem::take(&mut object.next_value);
-        object.obj.insert(key, value);
+        object.entries.push(Entry {
+            key_start: object.key_start,
+            value_start: object.value_start,
+            end: object.buf.len(),
+        });
         Ok(())
     }
 
@@ -471,6 +538,46 @@ mod tests {
         assert_eq!(expected, encoded);
     }
 
+    /// Keys that sort differently as strings than as encoded JSON strings (`"a"` sorts after
+    /// `"a!"`) keep their encoded order, and a repeated key keeps its last value.
+    #[test]
+    fn key_order_and_duplicate_keys() {
+        struct Entries(&'static [(&'static str, u8)]);
+
+        impl Serialize for Entries {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                use serde::ser::SerializeMap;
+                let mut map = serializer.serialize_map(Some(self.0.len()))?;
+                for (key, value) in self.0 {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+
+        let mut buf = Vec::new();
+        let mut ser = Serializer::with_formatter(&mut buf, CanonicalFormatter::new());
+        Entries(&[("b", 1), ("a", 2), ("a!", 3), ("b", 4), ("é", 5)])
+            .serialize(&mut ser)
+            .unwrap();
+        assert_eq!(buf, r#"{"a!":3,"a":2,"b":4,"é":5}"#.as_bytes());
+    }
+
+    /// Strings are written in Normalization Form C, whether or not they already were.
+    #[test]
+    fn nfc_strings() -> Result<()> {
+        assert_eq!(encode!("e\u{301}")?, "\"\u{e9}\"".as_bytes());
+        assert_eq!(encode!("\u{e9}")?, "\"\u{e9}\"".as_bytes());
+        assert_eq!(
+            encode!({"e\u{301}": "ascii"})?,
+            "{\"\u{e9}\":\"ascii\"}".as_bytes()
+        );
+        Ok(())
+    }
+
     #[test]
     fn encode_u128_i128() {
         #[derive(serde_derive::Serialize)]
@@ -0,0 +1,289 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! tough-axum serves a TUF repository built with [tough](https://docs.rs/tough) over HTTP, as an
+//! [axum](https://docs.rs/axum) `Router` that services publishing a repository can mount next to
+//! their own routes.
+//!
+//! Metadata is served under `/metadata/` and targets under `/targets/`, so a client loads the
+//! repository with `<base>/metadata/` and `<base>/targets/` as its metadata and targets base URLs.
+//! Files whose names carry a version or a hash, such as `3.snapshot.json` or the hash-prefixed
+//! targets of a repository that uses consistent snapshots, never change and are served as
+//! immutable. Other files, such as `timestamp.json`, change with each publish and may only be
+//! cached for a short time; see [`RepositoryRouter::mutable_max_age`].
+
+#![forbid(missing_debug_implementations, missing_copy_implementations)]
+#![deny(rust_2018_idioms)]
+#![deny(missing_docs)]
+#![warn(clippy::pedantic)]
+#![allow(clippy::result_large_err)]
+
+use axum::body::{Bytes, StreamBody};
+use axum::extract::State;
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use percent_encoding::percent_decode_str;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::io::ReaderStream;
+use tough::editor::signed::SignedRepository;
+
+/// The `Cache-Control` header for files that never change.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// The length of a hex-encoded sha256 digest, which prefixes targets in consistent snapshots.
+const SHA256_HEX_LEN: usize = 64;
+
+/// Builds the [`Router`] that serves a repository's metadata and, optionally, its targets.
+#[derive(Debug, Clone)]
+pub struct RepositoryRouter {
+    metadata: Metadata,
+    targets_dir: Option<PathBuf>,
+    mutable_max_age: Duration,
+}
+
+#[derive(Debug, Clone)]
+enum Metadata {
+    /// Metadata files held in memory, keyed by filename.
+    Files(BTreeMap<String, Bytes>),
+    /// A directory that metadata files are read from when requested.
+    Dir(PathBuf),
+}
+
+impl RepositoryRouter {
+    /// Serves the metadata of `repository`, as `SignedRepository::write` would write it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a delegated role's name can't be turned into a filename.
+    pub fn from_signed(repository: &SignedRepository) -> tough::error::Result<Self> {
+        let files = repository
+            .metadata_files()?
+            .into_iter()
+            .map(|(filename, data)| (filename, Bytes::copy_from_slice(data)))
+            .collect();
+        Ok(Self::new(Metadata::Files(files)))
+    }
+
+    /// Serves the metadata files in `metadata_dir`, reading each file when it is requested so that
+    /// newly published metadata is served without rebuilding the router.
+    pub fn from_dir<P: Into<PathBuf>>(metadata_dir: P) -> Self {
+        Self::new(Metadata::Dir(metadata_dir.into()))
+    }
+
+    fn new(metadata: Metadata) -> Self {
+        Self {
+            metadata,
+            targets_dir: None,
+            mutable_max_age: Duration::from_mins(1),
+        }
+    }
+
+    /// Also serve the targets in `targets_dir`, such as the directory that
+    /// `SignedRepository::link_targets` or `copy_targets` wrote to.
+    #[must_use]
+    pub fn targets_dir<P: Into<PathBuf>>(mut self, targets_dir: P) -> Self {
+        self.targets_dir = Some(targets_dir.into());
+        self
+    }
+
+    /// How long clients and caches may keep `timestamp.json` and the other files whose names carry
+    /// no version or hash, which change whenever the repository is published. Keep this shorter
+    /// than the timestamp role's expiration. The default is 60 seconds; zero disallows caching
+    /// without revalidation.
+    #[must_use]
+    pub fn mutable_max_age(mut self, max_age: Duration) -> Self {
+        self.mutable_max_age = max_age;
+        self
+    }
+
+    /// Builds the router.
+    pub fn into_router(self) -> Router {
+        Router::new()
+            .route("/metadata/*file", get(metadata))
+            .route("/targets/*file", get(target))
+            .with_state(Arc::new(self))
+    }
+
+    fn cache_control(&self, immutable: bool) -> String {
+        if immutable {
+            IMMUTABLE.to_owned()
+        } else if self.mutable_max_age.is_zero() {
+            "no-cache".to_owned()
+        } else {
+            format!("public, max-age={}", self.mutable_max_age.as_secs())
+        }
+    }
+}
+
+async fn metadata(State(router): State<Arc<RepositoryRouter>>, uri: Uri) -> Response {
+    let Some(requested) = uri.path().strip_prefix("/metadata/") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    for name in candidate_names(requested) {
+        if name.contains('/') || !is_safe_component(&name) {
+            continue;
+        }
+        let cache_control = router.cache_control(is_versioned_metadata(&name));
+        let content_type = content_type(&name);
+        match &router.metadata {
+            Metadata::Files(files) => {
+                if let Some(data) = files.get(&name) {
+                    return file_response(content_type, cache_control, data.clone());
+                }
+            }
+            Metadata::Dir(dir) => {
+                if let Some(response) =
+                    read_file(&dir.join(&name), content_type, cache_control).await
+                {
+                    return response;
+                }
+            }
+        }
+    }
+    StatusCode::NOT_FOUND.into_response()
+}
+
+async fn target(State(router): State<Arc<RepositoryRouter>>, uri: Uri) -> Response {
+    let (Some(dir), Some(requested)) = (&router.targets_dir, uri.path().strip_prefix("/targets/"))
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    for name in candidate_names(requested) {
+        if !name.split('/').all(is_safe_component) {
+            continue;
+        }
+        let cache_control = router.cache_control(is_hash_prefixed(&name));
+        if let Some(response) =
+            read_file(&dir.join(&name), content_type(&name), cache_control).await
+        {
+            return response;
+        }
+    }
+    StatusCode::NOT_FOUND.into_response()
+}
+
+/// The names a request path may refer to. tough requests files by their names as written, which
+/// may themselves contain percent-encoded role names, so the path is tried as it is first, and
+/// then decoded for files whose names contain characters that had to be encoded in the URL.
+fn candidate_names(requested: &str) -> Vec<String> {
+    let mut names = vec![requested.to_owned()];
+    if let Ok(decoded) = percent_decode_str(requested).decode_utf8() {
+        if decoded != requested {
+            names.push(decoded.into_owned());
+        }
+    }
+    names
+}
+
+/// Whether `component` is a single path component that stays inside the directory it is joined
+/// to.
+fn is_safe_component(component: &str) -> bool {
+    !component.is_empty()
+        && component != "."
+        && component != ".."
+        && !component.contains(['\\', '\0'])
+}
+
+/// Whether `name` is prefixed with a version, like `3.snapshot.json` or `2.root.json`.
+fn is_versioned_metadata(name: &str) -> bool {
+    name.split_once('.').is_some_and(|(version, _)| {
+        !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+/// Whether `name` is prefixed with a sha256 digest, as targets are in consistent snapshots.
+fn is_hash_prefixed(name: &str) -> bool {
+    name.get(..=SHA256_HEX_LEN).is_some_and(|prefix| {
+        prefix.ends_with('.')
+            && prefix[..SHA256_HEX_LEN]
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    })
+}
+
+fn content_type(name: &str) -> &'static str {
+    match Path::new(name).extension().and_then(|ext| ext.to_str()) {
+        Some("json") => "application/json",
+        Some("gz") => "application/gzip",
+        Some("tsr") => "application/timestamp-reply",
+        _ => "application/octet-stream",
+    }
+}
+
+fn file_response<B: IntoResponse>(
+    content_type: &'static str,
+    cache_control: String,
+    body: B,
+) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_owned()),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Streams the file at `path`, or returns `None` if there is no such file.
+async fn read_file(
+    path: &Path,
+    content_type: &'static str,
+    cache_control: String,
+) -> Option<Response> {
+    let file = tokio::fs::File::open(path).await.ok()?;
+    let metadata = file.metadata().await.ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let mut response = file_response(
+        content_type,
+        cache_control,
+        StreamBody::new(ReaderStream::new(file)),
+    );
+    response
+        .headers_mut()
+        .insert(header::CONTENT_LENGTH, metadata.len().into());
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn immutable_names() {
+        assert!(is_versioned_metadata("3.snapshot.json"));
+        assert!(is_versioned_metadata("12.root.json.gz"));
+        assert!(!is_versioned_metadata("timestamp.json"));
+        assert!(!is_versioned_metadata("v1.role.json"));
+
+        let hash = "a".repeat(SHA256_HEX_LEN);
+        assert!(is_hash_prefixed(&format!("{hash}.file1.txt")));
+        assert!(!is_hash_prefixed(&format!("{hash}file1.txt")));
+        assert!(!is_hash_prefixed(&format!("{}.file1.txt", "A".repeat(64))));
+        assert!(!is_hash_prefixed("file1.txt"));
+    }
+
+    #[test]
+    fn unsafe_components() {
+        for component in ["", ".", "..", "a\\b"] {
+            assert!(!is_safe_component(component), "{}", component);
+        }
+        assert!(is_safe_component("..a"));
+    }
+
+    #[test]
+    fn names_are_tried_as_requested_then_decoded() {
+        assert_eq!(
+            candidate_names("1.caf%C3%A9.json"),
+            ["1.caf%C3%A9.json", "1.café.json"]
+        );
+        assert_eq!(candidate_names("targets.json"), ["targets.json"]);
+    }
+}
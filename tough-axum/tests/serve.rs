@@ -0,0 +1,180 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use chrono::{Duration, Utc};
+use std::net::SocketAddr;
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use tough::editor::signed::{PathExists, SignedRepository};
+use tough::editor::RepositoryEditor;
+use tough::key_source::{KeySource, LocalKeySource};
+use tough::{RepositoryLoader, TargetName};
+use tough_axum::RepositoryRouter;
+use tower::ServiceExt;
+use url::Url;
+
+/// Returns the path to tough's test data directory
+fn test_data() -> PathBuf {
+    let mut p = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    p.pop();
+    p.join("tough").join("tests").join("data")
+}
+
+fn root_path() -> PathBuf {
+    test_data().join("simple-rsa").join("root.json")
+}
+
+fn targets_dir() -> PathBuf {
+    test_data().join("tuf-reference-impl").join("targets")
+}
+
+/// Signs a repository with consistent snapshots that lists the reference implementation's targets.
+async fn signed_repo() -> SignedRepository {
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource {
+        path: test_data().join("snakeoil.pem"),
+    })];
+    let one = NonZeroU64::new(1).unwrap();
+    let expires = Utc::now() + Duration::days(7);
+    let mut editor = RepositoryEditor::new(root_path()).await.unwrap();
+    editor
+        .targets_version(one)
+        .unwrap()
+        .targets_expires(expires)
+        .unwrap()
+        .snapshot_version(one)
+        .snapshot_expires(expires)
+        .timestamp_version(one)
+        .timestamp_expires(expires)
+        .add_target_paths(vec![
+            targets_dir().join("file1.txt"),
+            targets_dir().join("file2.txt"),
+        ])
+        .await
+        .unwrap();
+    editor.sign(&keys).await.unwrap()
+}
+
+async fn serve(router: RepositoryRouter) -> SocketAddr {
+    let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+        .serve(router.into_router().into_make_service());
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+async fn get(router: &RepositoryRouter, path: &str) -> axum::response::Response {
+    router
+        .clone()
+        .into_router()
+        .oneshot(Request::get(path).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn header(response: &axum::response::Response, name: header::HeaderName) -> &str {
+    response.headers()[name].to_str().unwrap()
+}
+
+/// A client loads a signed repository, and reads its targets, from the router over HTTP.
+#[tokio::test]
+async fn client_loads_served_repository() {
+    let signed = signed_repo().await;
+    let outdir = TempDir::new().unwrap();
+    let served_targets = outdir.path().join("targets");
+    signed
+        .copy_targets(targets_dir(), &served_targets, PathExists::Fail)
+        .await
+        .unwrap();
+    let addr = serve(
+        RepositoryRouter::from_signed(&signed)
+            .unwrap()
+            .targets_dir(&served_targets),
+    )
+    .await;
+
+    let base = Url::parse(&format!("http://{addr}/")).unwrap();
+    let repo = RepositoryLoader::new(
+        &std::fs::read(root_path()).unwrap(),
+        base.join("metadata/").unwrap(),
+        base.join("targets/").unwrap(),
+    )
+    .load()
+    .await
+    .unwrap();
+    let name = TargetName::new("file1.txt").unwrap();
+    let data = repo
+        .read_target_bytes(&name, 1 << 20)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        data,
+        std::fs::read(targets_dir().join("file1.txt")).unwrap()
+    );
+}
+
+/// Versioned and hash-prefixed files are immutable; other files may be cached briefly.
+#[tokio::test]
+async fn cache_headers_and_content_types() {
+    let signed = signed_repo().await;
+    let outdir = TempDir::new().unwrap();
+    let metadata_dir = outdir.path().join("metadata");
+    let served_targets = outdir.path().join("targets");
+    signed.write(&metadata_dir).await.unwrap();
+    signed
+        .copy_targets(targets_dir(), &served_targets, PathExists::Fail)
+        .await
+        .unwrap();
+    let router = RepositoryRouter::from_dir(&metadata_dir)
+        .targets_dir(&served_targets)
+        .mutable_max_age(std::time::Duration::from_secs(30));
+
+    let response = get(&router, "/metadata/timestamp.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        header(&response, header::CACHE_CONTROL),
+        "public, max-age=30"
+    );
+    assert_eq!(header(&response, header::CONTENT_TYPE), "application/json");
+
+    let response = get(&router, "/metadata/1.snapshot.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(header(&response, header::CACHE_CONTROL).contains("immutable"));
+
+    let target = first_file(&served_targets);
+    let response = get(&router, &format!("/targets/{target}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(header(&response, header::CACHE_CONTROL).contains("immutable"));
+    assert_eq!(
+        header(&response, header::CONTENT_TYPE),
+        "application/octet-stream"
+    );
+
+    for path in [
+        "/metadata/missing.json",
+        "/metadata/..%2Ftargets%2F",
+        "/targets/..%2Fmetadata%2Ftimestamp.json",
+        "/targets/../metadata/timestamp.json",
+    ] {
+        assert_eq!(
+            get(&router, path).await.status(),
+            StatusCode::NOT_FOUND,
+            "{}",
+            path
+        );
+    }
+}
+
+fn first_file(dir: &Path) -> String {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .file_name()
+        .into_string()
+        .unwrap()
+}
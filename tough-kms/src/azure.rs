@@ -0,0 +1,254 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Implements the `KeySource` trait for keys in Azure Key Vault.
+
+use crate::error::{self, Result};
+use crate::remote::{self, RemoteKey};
+use crate::{ecdsa_tuf_key, rsa_tuf_key};
+use aws_lc_rs::digest::{digest, SHA256};
+use aws_lc_rs::rand::SecureRandom;
+use base64::alphabet::URL_SAFE;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use reqwest::Client;
+use serde::Deserialize;
+use snafu::{ensure, OptionExt, ResultExt};
+use std::fmt;
+use tough::async_trait;
+use tough::key_source::KeySource;
+use tough::schema::key::Key;
+use tough::sign::Sign;
+
+const SERVICE: &str = "Azure Key Vault";
+const API_VERSION: &str = "7.4";
+const P256_COORDINATE_LEN: usize = 32;
+
+/// Key Vault writes base64url without padding, but accepts and may return either form.
+const BASE64URL: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Implements the `KeySource` trait for RSA and P-256 elliptic curve keys in Azure Key Vault,
+/// which are reached through the Key Vault REST API.
+///
+/// RSA keys sign with `PS256` and elliptic curve keys with `ES256`, which match TUF's
+/// `rsassa-pss-sha256` and `ecdsa-sha2-nistp256` schemes.
+pub struct AzureKeyVaultKeySource {
+    /// The key's identifier, `https://{vault}.vault.azure.net/keys/{name}`, optionally followed by
+    /// `/{version}`. Without a version, the key's current version signs.
+    pub key_id: String,
+    /// An access token for the `https://vault.azure.net` resource that may get the key and sign
+    /// with it, such as one printed by
+    /// `az account get-access-token --resource https://vault.azure.net`.
+    pub access_token: String,
+}
+
+impl fmt::Debug for AzureKeyVaultKeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AzureKeyVaultKeySource")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The parts of a `KeyBundle` that tough uses.
+#[derive(Deserialize)]
+struct KeyBundle {
+    key: JsonWebKey,
+}
+
+/// The public parts of a JSON web key.
+#[derive(Deserialize)]
+struct JsonWebKey {
+    kid: String,
+    kty: String,
+    crv: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+/// The parts of a `KeyOperationResult` that tough uses.
+#[derive(Deserialize)]
+struct SignResponse {
+    value: String,
+}
+
+/// A Key Vault key version and how it signs.
+struct FetchedKey {
+    /// The identifier of the key version, which is used to sign so that the signature matches the
+    /// fetched public key even if the key is rotated meanwhile.
+    kid: String,
+    algorithm: &'static str,
+    key: RemoteKey,
+}
+
+impl AzureKeyVaultKeySource {
+    /// Gets the public key from Key Vault and checks that it can sign TUF metadata.
+    async fn fetch_public_key(&self, client: &Client) -> Result<FetchedKey> {
+        let url = format!(
+            "{}?api-version={}",
+            self.key_id.trim_end_matches('/'),
+            API_VERSION
+        );
+        let bundle: KeyBundle = remote::call(client, &url, &self.access_token, None).await?;
+        let jwk = bundle.key;
+        let field = |value: Option<String>, field: &'static str| -> Result<Vec<u8>> {
+            let value = value.context(error::RemoteKeyFieldMissingSnafu {
+                key_id: self.key_id.clone(),
+                field,
+            })?;
+            BASE64URL
+                .decode(value)
+                .context(error::RemoteBase64Snafu { url: url.clone() })
+        };
+        match jwk.kty.as_str() {
+            "RSA" | "RSA-HSM" => {
+                let modulus = field(jwk.n, "n")?;
+                let exponent = field(jwk.e, "e")?;
+                let modulus_size_bytes = modulus.iter().skip_while(|b| **b == 0).count();
+                Ok(FetchedKey {
+                    kid: jwk.kid,
+                    algorithm: "PS256",
+                    key: RemoteKey::Rsa {
+                        key: rsa_tuf_key(remote::rsa_public_key_der(&modulus, &exponent).into()),
+                        modulus_size_bytes,
+                    },
+                })
+            }
+            "EC" | "EC-HSM" if jwk.crv.as_deref() == Some("P-256") => {
+                let x = field(jwk.x, "x")?;
+                let y = field(jwk.y, "y")?;
+                ensure!(
+                    x.len() == P256_COORDINATE_LEN && y.len() == P256_COORDINATE_LEN,
+                    error::UnsupportedRemoteKeySnafu {
+                        key_id: self.key_id.clone(),
+                        algorithm: "EC key with malformed coordinates",
+                    }
+                );
+                // The uncompressed form of the point, as tough expects for ECDSA keys.
+                let point = [&[0x04][..], &x, &y].concat();
+                Ok(FetchedKey {
+                    kid: jwk.kid,
+                    algorithm: "ES256",
+                    key: RemoteKey::Ecdsa {
+                        key: ecdsa_tuf_key(point.into()),
+                    },
+                })
+            }
+            kty => error::UnsupportedRemoteKeySnafu {
+                key_id: self.key_id.clone(),
+                algorithm: match jwk.crv {
+                    Some(crv) => format!("{kty} {crv}"),
+                    None => kty.to_owned(),
+                },
+            }
+            .fail(),
+        }
+    }
+}
+
+#[async_trait]
+impl KeySource for AzureKeyVaultKeySource {
+    async fn as_sign(
+        &self,
+    ) -> std::result::Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        let client = remote::http_client()?;
+        let fetched = self.fetch_public_key(&client).await?;
+        Ok(Box::new(AzureKeyVaultKey {
+            sign_url: format!("{}/sign?api-version={}", fetched.kid, API_VERSION),
+            kid: fetched.kid,
+            algorithm: fetched.algorithm,
+            access_token: self.access_token.clone(),
+            client,
+            key: fetched.key,
+        }))
+    }
+
+    /// Only fetches the public key, without setting up for signing.
+    async fn public_key(
+        &self,
+    ) -> std::result::Result<Key, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let client = remote::http_client()?;
+        Ok(self.fetch_public_key(&client).await?.key.tuf_key())
+    }
+
+    /// Refuses to write a key, since a generated key can't be imported in its place.
+    async fn write(
+        &self,
+        _value: &str,
+        _key_id_hex: &str,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Err(error::RemoteKeyWriteSnafu { service: SERVICE }
+            .build()
+            .into())
+    }
+}
+
+/// Implements the `Sign` trait for an Azure Key Vault key version.
+struct AzureKeyVaultKey {
+    kid: String,
+    sign_url: String,
+    algorithm: &'static str,
+    access_token: String,
+    client: Client,
+    key: RemoteKey,
+}
+
+impl fmt::Debug for AzureKeyVaultKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AzureKeyVaultKey")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Sign for AzureKeyVaultKey {
+    fn tuf_key(&self) -> Key {
+        self.key.tuf_key()
+    }
+
+    async fn sign(
+        &self,
+        msg: &[u8],
+        _rng: &(dyn SecureRandom + Sync),
+    ) -> std::result::Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let body = serde_json::json!({
+            "alg": self.algorithm,
+            "value": BASE64URL.encode(digest(&SHA256, msg)),
+        });
+        let response: SignResponse = remote::call(
+            &self.client,
+            &self.sign_url,
+            &self.access_token,
+            Some(&body),
+        )
+        .await?;
+        let mut signature = BASE64URL
+            .decode(response.value)
+            .context(error::RemoteBase64Snafu {
+                url: self.sign_url.clone(),
+            })?;
+        if let RemoteKey::Ecdsa { .. } = self.key {
+            // Key Vault returns ECDSA signatures as `r || s`, which TUF doesn't use.
+            signature = remote::ecdsa_signature_der(&signature).context(
+                error::RemoteSignatureInvalidSnafu {
+                    service: SERVICE,
+                    key_id: self.kid.clone(),
+                },
+            )?;
+        }
+        Ok(self
+            .key
+            .finish_signature(SERVICE, &self.kid, msg, signature)?)
+    }
+}
@@ -3,17 +3,45 @@
 
 use aws_config::default_provider::credentials::DefaultCredentialsChain;
 use aws_config::default_provider::region::DefaultRegionChain;
-use aws_config::BehaviorVersion;
+use aws_config::{BehaviorVersion, SdkConfig};
 use aws_sdk_kms::Client as KmsClient;
 use aws_smithy_experimental::hyper_1_0::{CryptoMode, HyperClientBuilder};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// The config loaded for each profile, so that the key sources in a process share the HTTP client
+/// and credentials of each profile rather than loading them for every operation.
+static CONFIGS: OnceLock<Mutex<HashMap<Option<String>, SdkConfig>>> = OnceLock::new();
 
 /// Builds a KMS client for a given profile name.
 pub(crate) async fn build_client_kms(profile: Option<&str>) -> KmsClient {
+    let configs = CONFIGS.get_or_init(Mutex::default);
+    let key = profile.map(ToOwned::to_owned);
+    let cached = configs
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&key)
+        .cloned();
+    if let Some(config) = cached {
+        return KmsClient::new(&config);
+    }
+    let config = load_config(profile).await;
+    // If another task loaded this profile meanwhile, keep the config it stored.
+    let config = configs
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(key)
+        .or_insert(config)
+        .clone();
+    KmsClient::new(&config)
+}
+
+async fn load_config(profile: Option<&str>) -> SdkConfig {
     let http_client = HyperClientBuilder::new()
         .crypto_mode(CryptoMode::AwsLc) // Choose a crypto provider.
         .build_https();
     let config = aws_config::defaults(BehaviorVersion::v2024_03_28()).http_client(http_client);
-    let client_config = if let Some(profile) = profile {
+    if let Some(profile) = profile {
         let region = DefaultRegionChain::builder()
             .profile_name(profile)
             .build()
@@ -31,6 +59,5 @@ pub(crate) async fn build_client_kms(profile: Option<&str>) -> KmsClient {
             .await
     } else {
         config.load().await
-    };
-    KmsClient::new(&client_config)
+    }
 }
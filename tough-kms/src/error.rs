@@ -32,6 +32,57 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// The library failed to create a key in AWS KMS
+    #[snafu(display(
+    "Failed to create key for aws-kms://{}/{} : {}",
+    profile.as_deref().unwrap_or(""),
+    key_id,
+    source.source().map_or("unknown".to_string(), std::string::ToString::to_string),
+    ))]
+    KmsCreateKey {
+        profile: Option<String>,
+        key_id: String,
+        source: aws_sdk_kms::error::SdkError<aws_sdk_kms::operation::create_key::CreateKeyError>,
+        backtrace: Backtrace,
+    },
+
+    /// The library failed to point an alias at a newly created key in AWS KMS
+    #[snafu(display(
+    "Created key {} but failed to create alias {} : {}",
+    new_key_id,
+    key_id,
+    source.source().map_or("unknown".to_string(), std::string::ToString::to_string),
+    ))]
+    KmsCreateAlias {
+        key_id: String,
+        new_key_id: String,
+        source:
+            aws_sdk_kms::error::SdkError<aws_sdk_kms::operation::create_alias::CreateAliasError>,
+        backtrace: Backtrace,
+    },
+
+    /// AWS KMS assigns key IDs itself, so a new key can only be named with an alias
+    #[snafu(display(
+        "To create a key, the key ID must be an alias (alias/...), found {}",
+        key_id
+    ))]
+    CreateKeyNeedsAlias { key_id: String },
+
+    /// AWS KMS does not offer keys of this size for the signing algorithm
+    #[snafu(display(
+        "AWS KMS does not support {}-bit keys for {:?}",
+        bits,
+        signing_algorithm
+    ))]
+    UnsupportedKeyBits {
+        bits: u16,
+        signing_algorithm: crate::KmsSigningAlgorithm,
+    },
+
+    /// AWS KMS did not return metadata for the key it created
+    #[snafu(display("AWS KMS did not return an ID for the created key"))]
+    CreatedKeyIdMissing,
+
     /// Empty public key was returned by AWS KMS
     #[snafu(display("Public key does not exist"))]
     PublicKeyNone,
@@ -57,6 +108,10 @@ pub enum Error {
     #[snafu(display("Empty signature returned by AWS KMS"))]
     SignatureNotFound,
 
+    /// AWS KMS returned a signature that doesn't verify with the key's public key
+    #[snafu(display("AWS KMS returned an invalid signature for {}", key_id))]
+    SignatureInvalid { key_id: String },
+
     /// Provided signing algorithm is not valid
     #[snafu(display("Please provide valid signing algorithm"))]
     ValidSignAlgorithm,
@@ -98,4 +153,70 @@ pub enum Error {
         modulus_size_bits: usize,
         spec: String,
     },
+
+    /// The HTTP client for a key service's API could not be built
+    #[snafu(display("Failed to build HTTP client: {}", source))]
+    HttpClient {
+        source: reqwest::Error,
+        backtrace: Backtrace,
+    },
+
+    /// A request to a key service's API failed
+    #[snafu(display("Request to {} failed: {}", url, source))]
+    RemoteRequest {
+        url: String,
+        source: reqwest::Error,
+        backtrace: Backtrace,
+    },
+
+    /// A key service's API returned an error status
+    #[snafu(display("{} returned HTTP {}: {}", url, status, body))]
+    RemoteStatus {
+        url: String,
+        status: u16,
+        body: String,
+        backtrace: Backtrace,
+    },
+
+    /// A key service's API returned a response that could not be parsed
+    #[snafu(display("Failed to parse the response from {}: {}", url, source))]
+    RemoteResponse {
+        url: String,
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
+    /// A key service's API returned a value that is not valid base64
+    #[snafu(display("Failed to decode base64 in the response from {}: {}", url, source))]
+    RemoteBase64 {
+        url: String,
+        source: base64::DecodeError,
+        backtrace: Backtrace,
+    },
+
+    /// A key service returned a public key without a field its key type needs
+    #[snafu(display("The public key of {} has no \"{}\" field", key_id, field))]
+    RemoteKeyFieldMissing { key_id: String, field: &'static str },
+
+    /// The key held by a key service can't sign with a scheme that TUF uses
+    #[snafu(display(
+        "{} is a {} key, but only RSASSA-PSS SHA-256 and ECDSA P-256 SHA-256 keys can sign",
+        key_id,
+        algorithm
+    ))]
+    UnsupportedRemoteKey { key_id: String, algorithm: String },
+
+    /// A key service returned a signature that doesn't verify with the key's public key
+    #[snafu(display("{} returned an invalid signature for {}", service, key_id))]
+    RemoteSignatureInvalid {
+        service: &'static str,
+        key_id: String,
+    },
+
+    /// Keys are created in a key service itself, not written to it
+    #[snafu(display(
+        "Keys can't be written to {}; create the key there and add its public key instead",
+        service
+    ))]
+    RemoteKeyWrite { service: &'static str },
 }
@@ -0,0 +1,196 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Implements the `KeySource` trait for asymmetric signing keys in Google Cloud KMS.
+
+use crate::error::{self, Result};
+use crate::remote::{self, RemoteKey};
+use crate::{ecdsa_tuf_key, rsa_tuf_key};
+use aws_lc_rs::digest::{digest, SHA256};
+use aws_lc_rs::rand::SecureRandom;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::Client;
+use serde::Deserialize;
+use snafu::{ensure, OptionExt, ResultExt};
+use std::fmt;
+use tough::async_trait;
+use tough::key_source::KeySource;
+use tough::schema::key::Key;
+use tough::sign::Sign;
+
+const SERVICE: &str = "Google Cloud KMS";
+const DEFAULT_ENDPOINT: &str = "https://cloudkms.googleapis.com";
+const EC_SIGN_P256_SHA256: &str = "EC_SIGN_P256_SHA256";
+
+/// Implements the `KeySource` trait for asymmetric signing keys in Google Cloud KMS, which are
+/// reached through the Cloud KMS REST API.
+///
+/// The key version's algorithm must be one of the `RSA_SIGN_PSS_*_SHA256` algorithms or
+/// `EC_SIGN_P256_SHA256`, which match TUF's `rsassa-pss-sha256` and `ecdsa-sha2-nistp256`
+/// schemes.
+pub struct GcpKmsKeySource {
+    /// The resource name of the key version, in the form
+    /// `projects/*/locations/*/keyRings/*/cryptoKeys/*/cryptoKeyVersions/*`.
+    pub key_version: String,
+    /// An OAuth 2.0 access token that may get the key version's public key and sign with it, such
+    /// as one printed by `gcloud auth print-access-token`.
+    pub access_token: String,
+    /// The base URL of the Cloud KMS API, if not `https://cloudkms.googleapis.com`.
+    pub endpoint: Option<String>,
+}
+
+impl fmt::Debug for GcpKmsKeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcpKmsKeySource")
+            .field("key_version", &self.key_version)
+            .field("endpoint", &self.endpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The parts of a `PublicKey` resource that tough uses.
+#[derive(Deserialize)]
+struct PublicKeyResponse {
+    pem: String,
+    algorithm: String,
+}
+
+/// The parts of an `AsymmetricSignResponse` that tough uses.
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+impl GcpKmsKeySource {
+    /// The URL of `method` on the key version.
+    fn url(&self, method: &str) -> String {
+        format!(
+            "{}/v1/{}{}",
+            self.endpoint
+                .as_deref()
+                .unwrap_or(DEFAULT_ENDPOINT)
+                .trim_end_matches('/'),
+            self.key_version,
+            method
+        )
+    }
+
+    /// Gets the public key from Cloud KMS and checks that its algorithm can sign TUF metadata.
+    async fn fetch_public_key(&self, client: &Client) -> Result<RemoteKey> {
+        let response: PublicKeyResponse =
+            remote::call(client, &self.url("/publicKey"), &self.access_token, None).await?;
+        if response.algorithm == EC_SIGN_P256_SHA256 {
+            return Ok(RemoteKey::Ecdsa {
+                key: ecdsa_tuf_key(response.pem.parse().context(error::PublicKeyParseSnafu)?),
+            });
+        }
+        let modulus_size_bits = response
+            .algorithm
+            .strip_prefix("RSA_SIGN_PSS_")
+            .and_then(|rest| rest.strip_suffix("_SHA256"))
+            .and_then(|bits| bits.parse::<usize>().ok())
+            .context(error::UnsupportedRemoteKeySnafu {
+                key_id: self.key_version.clone(),
+                algorithm: response.algorithm.clone(),
+            })?;
+        ensure!(
+            modulus_size_bits % 8 == 0,
+            error::UnsupportedModulusSizeSnafu {
+                modulus_size_bits,
+                spec: response.algorithm,
+            }
+        );
+        Ok(RemoteKey::Rsa {
+            key: rsa_tuf_key(response.pem.parse().context(error::PublicKeyParseSnafu)?),
+            modulus_size_bytes: modulus_size_bits / 8,
+        })
+    }
+}
+
+#[async_trait]
+impl KeySource for GcpKmsKeySource {
+    async fn as_sign(
+        &self,
+    ) -> std::result::Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        let client = remote::http_client()?;
+        let key = self.fetch_public_key(&client).await?;
+        Ok(Box::new(GcpKmsKey {
+            key_version: self.key_version.clone(),
+            sign_url: self.url(":asymmetricSign"),
+            access_token: self.access_token.clone(),
+            client,
+            key,
+        }))
+    }
+
+    /// Only fetches the public key, without setting up for signing.
+    async fn public_key(
+        &self,
+    ) -> std::result::Result<Key, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let client = remote::http_client()?;
+        Ok(self.fetch_public_key(&client).await?.tuf_key())
+    }
+
+    /// Refuses to write a key, since a generated key can't be imported in its place.
+    async fn write(
+        &self,
+        _value: &str,
+        _key_id_hex: &str,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Err(error::RemoteKeyWriteSnafu { service: SERVICE }
+            .build()
+            .into())
+    }
+}
+
+/// Implements the `Sign` trait for a Google Cloud KMS key version.
+struct GcpKmsKey {
+    key_version: String,
+    sign_url: String,
+    access_token: String,
+    client: Client,
+    key: RemoteKey,
+}
+
+impl fmt::Debug for GcpKmsKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GcpKmsKey")
+            .field("key_version", &self.key_version)
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Sign for GcpKmsKey {
+    fn tuf_key(&self) -> Key {
+        self.key.tuf_key()
+    }
+
+    async fn sign(
+        &self,
+        msg: &[u8],
+        _rng: &(dyn SecureRandom + Sync),
+    ) -> std::result::Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let body = serde_json::json!({
+            "digest": { "sha256": STANDARD.encode(digest(&SHA256, msg)) },
+        });
+        let response: SignResponse = remote::call(
+            &self.client,
+            &self.sign_url,
+            &self.access_token,
+            Some(&body),
+        )
+        .await?;
+        let signature = STANDARD
+            .decode(response.signature)
+            .context(error::RemoteBase64Snafu {
+                url: self.sign_url.clone(),
+            })?;
+        Ok(self
+            .key
+            .finish_signature(SERVICE, &self.key_version, msg, signature)?)
+    }
+}
@@ -4,6 +4,8 @@
 //! tough-kms implements the `KeySource` trait found in [tough, a Rust TUF client](https://github.com/awslabs/tough).
 //!
 //! By implementing this trait, AWS KMS can become a source of keys used to sign a [TUF repository](https://theupdateframework.github.io/).
+//! [`GcpKmsKeySource`] and [`AzureKeyVaultKeySource`] do the same for keys in Google Cloud KMS and
+//! Azure Key Vault, which they reach through each service's REST API with an access token.
 //!
 //! # Testing
 //!
@@ -17,40 +19,65 @@
 #![allow(
     clippy::module_name_repetitions,
     clippy::must_use_candidate,
-    clippy::missing_errors_doc
+    clippy::missing_errors_doc,
+    clippy::result_large_err
 )]
 
+mod azure;
 mod client;
 pub mod error;
+mod gcp;
+mod remote;
+mod validate;
+
+pub use crate::azure::AzureKeyVaultKeySource;
+pub use crate::gcp::GcpKmsKeySource;
+pub use crate::validate::{KmsCheckResult, KmsKeyReport};
 use aws_lc_rs::digest::{digest, SHA256};
 use aws_lc_rs::rand::SecureRandom;
+use aws_lc_rs::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
 use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::{KeySpec, KeyUsageType};
 use aws_sdk_kms::Client as KmsClient;
 use snafu::{ensure, OptionExt, ResultExt};
 use std::collections::HashMap;
 use std::fmt;
 use tough::async_trait;
 use tough::key_source::KeySource;
-use tough::schema::decoded::{Decoded, RsaPem};
-use tough::schema::key::{Key, RsaKey, RsaScheme};
+use tough::schema::decoded::{Decoded, EcdsaFlex, RsaPem};
+use tough::schema::key::{EcdsaKey, EcdsaScheme, Key, RsaKey, RsaScheme};
 use tough::sign::Sign;
 
 /// Represents a Signing Algorithms for AWS KMS.
 #[non_exhaustive]
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
 pub enum KmsSigningAlgorithm {
-    /// Signing Algorithm `RSASSA_PSS_SHA_256`
+    /// Signing Algorithm `RSASSA_PSS_SHA_256`, for RSA keys
     RsassaPssSha256,
+    /// Signing Algorithm `ECDSA_SHA_256`, for `ECC_NIST_P256` keys
+    EcdsaSha256,
 }
 
 impl KmsSigningAlgorithm {
     fn value(self) -> aws_sdk_kms::types::SigningAlgorithmSpec {
-        // Currently we are supporting only single algorithm, but code stub is added to support
-        // multiple algorithms in future.
         match self {
             KmsSigningAlgorithm::RsassaPssSha256 => {
                 aws_sdk_kms::types::SigningAlgorithmSpec::RsassaPssSha256
             }
+            KmsSigningAlgorithm::EcdsaSha256 => {
+                aws_sdk_kms::types::SigningAlgorithmSpec::EcdsaSha256
+            }
+        }
+    }
+
+    /// The AWS KMS key spec for a new key of `bits` bits that signs with this algorithm.
+    fn key_spec(self, bits: u16) -> Option<KeySpec> {
+        match (self, bits) {
+            (KmsSigningAlgorithm::RsassaPssSha256, 2048) => Some(KeySpec::Rsa2048),
+            (KmsSigningAlgorithm::RsassaPssSha256, 3072) => Some(KeySpec::Rsa3072),
+            (KmsSigningAlgorithm::RsassaPssSha256, 4096) => Some(KeySpec::Rsa4096),
+            (KmsSigningAlgorithm::EcdsaSha256, 256) => Some(KeySpec::EccNistP256),
+            _ => None,
         }
     }
 }
@@ -63,7 +90,9 @@ pub struct KmsKeySource {
     pub key_id: String,
     /// `KmsClient` Object to query AWS KMS
     pub client: Option<KmsClient>,
-    /// Signing Algorithm to be used for the message digest, only `KmsSigningAlgorithm::RsassaPssSha256` is supported at present.
+    /// Signing Algorithm to be used for the message digest, which must suit the key:
+    /// `KmsSigningAlgorithm::RsassaPssSha256` for RSA keys or `KmsSigningAlgorithm::EcdsaSha256`
+    /// for `ECC_NIST_P256` keys.
     pub signing_algorithm: KmsSigningAlgorithm,
 }
 
@@ -76,18 +105,78 @@ impl fmt::Debug for KmsKeySource {
     }
 }
 
-/// Implement the `KeySource` trait.
-#[async_trait]
-impl KeySource for KmsKeySource {
-    async fn as_sign(
-        &self,
-    ) -> std::result::Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>
-    {
+impl KmsKeySource {
+    /// Creates a new asymmetric signing key of `bits` bits in AWS KMS and points the alias
+    /// `key_id` at it, so that this key source can then be used for signing. Returns the ID that
+    /// AWS KMS assigned to the new key.
+    ///
+    /// The key type follows `signing_algorithm`: an RSA key of 2048, 3072 or 4096 bits, or an
+    /// `ECC_NIST_P256` key, for which `bits` must be 256.
+    ///
+    /// AWS KMS chooses key IDs itself, so `key_id` must be an alias such as `alias/tuf-root`.
+    pub async fn create_key(&self, bits: u16) -> Result<String, error::Error> {
+        ensure!(
+            self.key_id.starts_with("alias/"),
+            error::CreateKeyNeedsAliasSnafu {
+                key_id: self.key_id.clone(),
+            }
+        );
+        let key_spec =
+            self.signing_algorithm
+                .key_spec(bits)
+                .context(error::UnsupportedKeyBitsSnafu {
+                    bits,
+                    signing_algorithm: self.signing_algorithm,
+                })?;
         let kms_client = match self.client.clone() {
             Some(value) => value,
             None => client::build_client_kms(self.profile.as_deref()).await,
         };
-        // Get the public key from AWS KMS
+
+        let response = kms_client
+            .create_key()
+            .key_usage(KeyUsageType::SignVerify)
+            .key_spec(key_spec)
+            .description("TUF signing key")
+            .send()
+            .await
+            .context(error::KmsCreateKeySnafu {
+                profile: self.profile.clone(),
+                key_id: self.key_id.clone(),
+            })?;
+        let new_key_id = response
+            .key_metadata
+            .map(|metadata| metadata.key_id)
+            .context(error::CreatedKeyIdMissingSnafu)?;
+
+        kms_client
+            .create_alias()
+            .alias_name(self.key_id.clone())
+            .target_key_id(new_key_id.clone())
+            .send()
+            .await
+            .context(error::KmsCreateAliasSnafu {
+                key_id: self.key_id.clone(),
+                new_key_id: new_key_id.clone(),
+            })?;
+        Ok(new_key_id)
+    }
+}
+
+impl KmsKeySource {
+    async fn kms_client(&self) -> KmsClient {
+        match self.client.clone() {
+            Some(value) => value,
+            None => client::build_client_kms(self.profile.as_deref()).await,
+        }
+    }
+
+    /// Gets the public key, as PEM, and the key spec from AWS KMS, and checks that the key
+    /// supports the signing algorithm.
+    async fn fetch_public_key(
+        &self,
+        kms_client: &KmsClient,
+    ) -> Result<(String, KeySpec), error::Error> {
         let response = kms_client
             .get_public_key()
             .key_id(self.key_id.clone())
@@ -115,20 +204,82 @@ impl KeySource for KmsKeySource {
                 .contains(&self.signing_algorithm.value()),
             error::ValidSignAlgorithmSnafu
         );
-        Ok(Box::new(KmsRsaKey {
-            profile: self.profile.clone(),
-            client: Some(kms_client),
-            key_id: self.key_id.clone(),
-            public_key: key.parse().context(error::PublicKeyParseSnafu)?,
-            signing_algorithm: self.signing_algorithm,
-            modulus_size_bytes: parse_modulus_length_bytes(
-                response
-                    .key_spec
-                    .as_ref()
-                    .context(error::MissingKeySpecSnafu)?
-                    .as_str(),
-            )?,
-        }))
+        Ok((key, response.key_spec.context(error::MissingKeySpecSnafu)?))
+    }
+}
+
+/// The TUF form of an RSA public key held in a key service.
+fn rsa_tuf_key(public: Decoded<RsaPem>) -> Key {
+    Key::Rsa {
+        keyval: RsaKey {
+            public,
+            _extra: HashMap::new(),
+        },
+        scheme: RsaScheme::RsassaPssSha256,
+        _extra: HashMap::new(),
+    }
+}
+
+/// The TUF form of an ECDSA P-256 public key held in a key service.
+fn ecdsa_tuf_key(public: Decoded<EcdsaFlex>) -> Key {
+    Key::Ecdsa {
+        keyval: EcdsaKey {
+            public,
+            _extra: HashMap::new(),
+        },
+        scheme: EcdsaScheme::EcdsaSha2Nistp256,
+        _extra: HashMap::new(),
+    }
+}
+
+/// Implement the `KeySource` trait.
+#[async_trait]
+impl KeySource for KmsKeySource {
+    async fn as_sign(
+        &self,
+    ) -> std::result::Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        let kms_client = self.kms_client().await;
+        let (public_key, key_spec) = self.fetch_public_key(&kms_client).await?;
+        match self.signing_algorithm {
+            KmsSigningAlgorithm::RsassaPssSha256 => Ok(Box::new(KmsRsaKey {
+                profile: self.profile.clone(),
+                client: Some(kms_client),
+                key_id: self.key_id.clone(),
+                public_key: public_key.parse().context(error::PublicKeyParseSnafu)?,
+                signing_algorithm: self.signing_algorithm,
+                modulus_size_bytes: parse_modulus_length_bytes(key_spec.as_str())?,
+            })),
+            KmsSigningAlgorithm::EcdsaSha256 => {
+                ensure!(
+                    key_spec == KeySpec::EccNistP256,
+                    error::BadKeySpecSnafu {
+                        spec: key_spec.as_str()
+                    }
+                );
+                Ok(Box::new(KmsEcdsaKey {
+                    profile: self.profile.clone(),
+                    client: Some(kms_client),
+                    key_id: self.key_id.clone(),
+                    public_key: public_key.parse().context(error::PublicKeyParseSnafu)?,
+                }))
+            }
+        }
+    }
+
+    /// Only fetches the public key, without setting up the client for signing.
+    async fn public_key(
+        &self,
+    ) -> std::result::Result<Key, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let (public_key, _) = self.fetch_public_key(&self.kms_client().await).await?;
+        Ok(match self.signing_algorithm {
+            KmsSigningAlgorithm::RsassaPssSha256 => {
+                rsa_tuf_key(public_key.parse().context(error::PublicKeyParseSnafu)?)
+            }
+            KmsSigningAlgorithm::EcdsaSha256 => {
+                ecdsa_tuf_key(public_key.parse().context(error::PublicKeyParseSnafu)?)
+            }
+        })
     }
 
     async fn write(
@@ -169,15 +320,7 @@ impl fmt::Debug for KmsRsaKey {
 #[async_trait]
 impl Sign for KmsRsaKey {
     fn tuf_key(&self) -> Key {
-        // Create a Key struct for the public key
-        Key::Rsa {
-            keyval: RsaKey {
-                public: self.public_key.clone(),
-                _extra: HashMap::new(),
-            },
-            scheme: RsaScheme::RsassaPssSha256,
-            _extra: HashMap::new(),
-        }
+        rsa_tuf_key(self.public_key.clone())
     }
 
     async fn sign(
@@ -185,41 +328,108 @@ impl Sign for KmsRsaKey {
         msg: &[u8],
         _rng: &(dyn SecureRandom + Sync),
     ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
-        let kms_client = match self.client.clone() {
-            Some(value) => value,
-            None => client::build_client_kms(self.profile.as_deref()).await,
-        };
-        let blob = Blob::new(digest(&SHA256, msg).as_ref().to_vec());
-        let response = kms_client
-            .sign()
-            .key_id(self.key_id.clone())
-            .message(blob)
-            .message_type(aws_sdk_kms::types::MessageType::Digest)
-            .signing_algorithm(self.signing_algorithm.value())
-            .send()
-            .await
-            .context(error::KmsSignMessageSnafu {
-                profile: self.profile.clone(),
-                key_id: self.key_id.clone(),
-            })?;
-        let signature = response
-            .signature
-            .context(error::SignatureNotFoundSnafu)?
-            .into_inner();
+        let signature = kms_sign(
+            self.client.as_ref(),
+            self.profile.as_deref(),
+            &self.key_id,
+            self.signing_algorithm,
+            msg,
+        )
+        .await?;
 
         // sometimes KMS produces a signature that is shorter than the modulus. in those cases,
         // we have observed that openssl and KMS will both validate the signature, but ring will
         // not. if we pad the beginning of the signature with zeros to make the signature exactly
         // the same length as the modulus, then ring will verify the signature.
-        let signature = match &self.signing_algorithm {
-            KmsSigningAlgorithm::RsassaPssSha256 => {
-                pad_signature(signature, self.modulus_size_bytes)?
-            }
-        };
+        Ok(pad_signature(signature, self.modulus_size_bytes)?)
+    }
+}
+
+/// Implements the Sign trait for KMS ECDSA P-256 keys
+pub struct KmsEcdsaKey {
+    /// Key Id of Customer Managed Key in KMS used to sign the message
+    key_id: String,
+    /// Aws account profile
+    profile: Option<String>,
+    /// `KmsClient` Object to query AWS KMS
+    client: Option<KmsClient>,
+    /// Public Key corresponding to Customer Managed Key
+    public_key: Decoded<EcdsaFlex>,
+}
+
+impl fmt::Debug for KmsEcdsaKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KmsEcdsaKey")
+            .field("key_id", &self.key_id)
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Sign for KmsEcdsaKey {
+    fn tuf_key(&self) -> Key {
+        ecdsa_tuf_key(self.public_key.clone())
+    }
+
+    async fn sign(
+        &self,
+        msg: &[u8],
+        _rng: &(dyn SecureRandom + Sync),
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let signature = kms_sign(
+            self.client.as_ref(),
+            self.profile.as_deref(),
+            &self.key_id,
+            KmsSigningAlgorithm::EcdsaSha256,
+            msg,
+        )
+        .await?;
+
+        // KMS returns ECDSA signatures DER encoded, which is the form tough verifies for the
+        // `ecdsa-sha2-nistp256` scheme, so unlike RSA signatures they have no fixed length to pad
+        // to. Check the signature here so that a malformed one is caught before it's written.
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, self.public_key.as_ref())
+            .verify(msg, &signature)
+            .ok()
+            .context(error::SignatureInvalidSnafu {
+                key_id: self.key_id.clone(),
+            })?;
         Ok(signature)
     }
 }
 
+/// Asks AWS KMS to sign the SHA-256 digest of `msg` with `algorithm`.
+async fn kms_sign(
+    client: Option<&KmsClient>,
+    profile: Option<&str>,
+    key_id: &str,
+    algorithm: KmsSigningAlgorithm,
+    msg: &[u8],
+) -> error::Result<Vec<u8>> {
+    let kms_client = match client {
+        Some(value) => value.clone(),
+        None => client::build_client_kms(profile).await,
+    };
+    let blob = Blob::new(digest(&SHA256, msg).as_ref().to_vec());
+    let response = kms_client
+        .sign()
+        .key_id(key_id)
+        .message(blob)
+        .message_type(aws_sdk_kms::types::MessageType::Digest)
+        .signing_algorithm(algorithm.value())
+        .send()
+        .await
+        .context(error::KmsSignMessageSnafu {
+            profile: profile.map(str::to_owned),
+            key_id,
+        })?;
+    Ok(response
+        .signature
+        .context(error::SignatureNotFoundSnafu)?
+        .into_inner())
+}
+
 /// Parses the `KeySpec` string returned by KMS, e.g. `RSA_3072` and returns the size of the modulus
 /// in bytes. For example `RSA_3072` has a modulus of 3072 bits, so the function will return 384 ==
 /// (3072 / 8). If the parsed number is not divisible by 8, an error is returned.
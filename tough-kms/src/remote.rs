@@ -0,0 +1,134 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The parts shared by the key sources that reach a key service through its REST API with a bearer
+//! token: the HTTP client, and the handling of the keys and signatures those services return.
+
+use crate::error::{self, Result};
+use crate::pad_signature;
+use aws_lc_rs::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use rustls::crypto::CryptoProvider;
+use serde::de::DeserializeOwned;
+use snafu::{ensure, OptionExt, ResultExt};
+use tough::der;
+use tough::schema::key::Key;
+
+/// Builds the client for a key service's API.
+pub(crate) fn http_client() -> Result<Client> {
+    // As in tough's `HttpTransport`, install aws-lc-rs as the rustls `CryptoProvider` so that
+    // reqwest doesn't fall back to ring, which matters for FIPS builds.
+    if CryptoProvider::get_default().is_none() {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    }
+    Client::builder().build().context(error::HttpClientSnafu)
+}
+
+/// Sends a GET request to `url`, or a POST request if there is a `body`, authorized by `token`,
+/// and parses the JSON response.
+pub(crate) async fn call<T: DeserializeOwned>(
+    client: &Client,
+    url: &str,
+    token: &str,
+    body: Option<&serde_json::Value>,
+) -> Result<T> {
+    let request = match body {
+        Some(body) => client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string()),
+        None => client.get(url),
+    };
+    let response = request
+        .bearer_auth(token)
+        .send()
+        .await
+        .context(error::RemoteRequestSnafu { url })?;
+    let status = response.status();
+    let text = response
+        .text()
+        .await
+        .context(error::RemoteRequestSnafu { url })?;
+    ensure!(
+        status.is_success(),
+        error::RemoteStatusSnafu {
+            url,
+            status: status.as_u16(),
+            body: text,
+        }
+    );
+    serde_json::from_str(&text).context(error::RemoteResponseSnafu { url })
+}
+
+/// A public key held by a key service, along with what its signatures need before they're returned.
+#[derive(Debug, Clone)]
+pub(crate) enum RemoteKey {
+    /// An RSA key, whose RSASSA-PSS signatures are padded to the length of its modulus.
+    Rsa { key: Key, modulus_size_bytes: usize },
+    /// An ECDSA P-256 key, whose DER signatures are checked against the public key.
+    Ecdsa { key: Key },
+}
+
+impl RemoteKey {
+    pub(crate) fn tuf_key(&self) -> Key {
+        match self {
+            RemoteKey::Rsa { key, .. } | RemoteKey::Ecdsa { key } => key.clone(),
+        }
+    }
+
+    /// Brings a `signature` of `msg` from `service` into the form that tough verifies, in the same
+    /// way `KmsKeySource` treats signatures from AWS KMS.
+    pub(crate) fn finish_signature(
+        &self,
+        service: &'static str,
+        key_id: &str,
+        msg: &[u8],
+        signature: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        match self {
+            RemoteKey::Rsa {
+                modulus_size_bytes, ..
+            } => pad_signature(signature, *modulus_size_bytes),
+            RemoteKey::Ecdsa { key } => {
+                UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, key.public_key())
+                    .verify(msg, &signature)
+                    .ok()
+                    .context(error::RemoteSignatureInvalidSnafu { service, key_id })?;
+                Ok(signature)
+            }
+        }
+    }
+}
+
+/// Encodes an RSA public key's modulus and exponent, as big-endian integers, as a PKCS#1
+/// `RSAPublicKey`.
+pub(crate) fn rsa_public_key_der(modulus: &[u8], exponent: &[u8]) -> Vec<u8> {
+    der::sequence(&[
+        der::unsigned_integer(modulus),
+        der::unsigned_integer(exponent),
+    ])
+}
+
+/// Converts an ECDSA P-256 signature in the fixed-length `r || s` form used by JOSE into the DER
+/// form that TUF uses. Returns `None` if the signature is not 64 bytes long.
+pub(crate) fn ecdsa_signature_der(fixed: &[u8]) -> Option<Vec<u8>> {
+    if fixed.len() != 64 {
+        return None;
+    }
+    let (r, s) = fixed.split_at(32);
+    Some(der::sequence(&[
+        der::unsigned_integer(r),
+        der::unsigned_integer(s),
+    ]))
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+#[test]
+fn ecdsa_signature_der_length() {
+    assert!(ecdsa_signature_der(&[1; 63]).is_none());
+    let encoded = ecdsa_signature_der(&[[0x01; 32], [0x81; 32]].concat()).unwrap();
+    assert_eq!(&encoded[..4], &[0x30, 0x45, 0x02, 0x20]);
+    assert_eq!(&encoded[36..39], &[0x02, 0x21, 0x00]);
+}
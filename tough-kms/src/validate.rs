@@ -0,0 +1,195 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Checks a `KmsKeySource` configuration against AWS KMS without producing a signature.
+
+use crate::{client, KmsKeySource};
+use aws_lc_rs::digest::SHA256_OUTPUT_LEN;
+use aws_sdk_kms::error::DisplayErrorContext;
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::{KeyState, KeyUsageType, MessageType};
+use std::fmt;
+
+/// The outcome of a single check in a `KmsKeyReport`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KmsCheckResult {
+    /// The check succeeded.
+    Passed,
+    /// The check failed, with a human-readable reason.
+    Failed(String),
+    /// The check could not run because an earlier check failed.
+    Skipped,
+}
+
+impl KmsCheckResult {
+    /// Returns `true` if the check succeeded.
+    pub fn passed(&self) -> bool {
+        *self == KmsCheckResult::Passed
+    }
+}
+
+impl fmt::Display for KmsCheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KmsCheckResult::Passed => write!(f, "ok"),
+            KmsCheckResult::Failed(reason) => write!(f, "FAILED: {reason}"),
+            KmsCheckResult::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// The result of `KmsKeySource::validate`, describing whether the key can be used to sign.
+#[derive(Debug, Clone)]
+pub struct KmsKeyReport {
+    /// The key ID that was checked.
+    pub key_id: String,
+    /// `DescribeKey` found the key and it is enabled.
+    pub key_exists: KmsCheckResult,
+    /// The key is an asymmetric key with `SIGN_VERIFY` usage.
+    pub sign_capable: KmsCheckResult,
+    /// The key supports the configured signing algorithm.
+    pub algorithm_supported: KmsCheckResult,
+    /// The caller may call `kms:GetPublicKey` on the key.
+    pub get_public_key_permission: KmsCheckResult,
+    /// The caller may call `kms:Sign` on the key, confirmed with a dry run.
+    pub sign_permission: KmsCheckResult,
+}
+
+impl KmsKeyReport {
+    /// Returns `true` if every check passed.
+    pub fn is_ok(&self) -> bool {
+        [
+            &self.key_exists,
+            &self.sign_capable,
+            &self.algorithm_supported,
+            &self.get_public_key_permission,
+            &self.sign_permission,
+        ]
+        .iter()
+        .all(|check| check.passed())
+    }
+}
+
+impl fmt::Display for KmsKeyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "aws-kms key {}", self.key_id)?;
+        writeln!(f, "  key exists and is enabled: {}", self.key_exists)?;
+        writeln!(f, "  asymmetric sign/verify key: {}", self.sign_capable)?;
+        writeln!(
+            f,
+            "  signing algorithm supported: {}",
+            self.algorithm_supported
+        )?;
+        writeln!(
+            f,
+            "  kms:GetPublicKey permitted: {}",
+            self.get_public_key_permission
+        )?;
+        write!(f, "  kms:Sign permitted: {}", self.sign_permission)
+    }
+}
+
+impl KmsKeySource {
+    /// Confirms that the key exists, is an enabled asymmetric signing key that supports the
+    /// configured algorithm, and that the caller has `kms:GetPublicKey` and `kms:Sign` permissions.
+    ///
+    /// Problems are collected into the returned report rather than returned as an error, so this
+    /// can be run before a publish to catch configuration mistakes up front. The `kms:Sign` check
+    /// uses a KMS dry run, so no signature is produced.
+    pub async fn validate(&self) -> KmsKeyReport {
+        let kms_client = match self.client.clone() {
+            Some(value) => value,
+            None => client::build_client_kms(self.profile.as_deref()).await,
+        };
+
+        let mut report = KmsKeyReport {
+            key_id: self.key_id.clone(),
+            key_exists: KmsCheckResult::Skipped,
+            sign_capable: KmsCheckResult::Skipped,
+            algorithm_supported: KmsCheckResult::Skipped,
+            get_public_key_permission: KmsCheckResult::Skipped,
+            sign_permission: KmsCheckResult::Skipped,
+        };
+
+        match kms_client
+            .describe_key()
+            .key_id(self.key_id.clone())
+            .send()
+            .await
+        {
+            Ok(response) => match response.key_metadata {
+                Some(metadata) => {
+                    report.key_exists = if metadata.key_state() == Some(&KeyState::Enabled) {
+                        KmsCheckResult::Passed
+                    } else {
+                        KmsCheckResult::Failed(format!(
+                            "key state is {}",
+                            metadata.key_state().map_or("unknown", KeyState::as_str)
+                        ))
+                    };
+                    report.sign_capable = if metadata.key_usage() == Some(&KeyUsageType::SignVerify)
+                    {
+                        KmsCheckResult::Passed
+                    } else {
+                        KmsCheckResult::Failed(format!(
+                            "key usage is {}",
+                            metadata.key_usage().map_or("unknown", KeyUsageType::as_str)
+                        ))
+                    };
+                    let algorithm = self.signing_algorithm.value();
+                    report.algorithm_supported =
+                        if metadata.signing_algorithms().contains(&algorithm) {
+                            KmsCheckResult::Passed
+                        } else {
+                            KmsCheckResult::Failed(format!(
+                                "key does not support {}",
+                                algorithm.as_str()
+                            ))
+                        };
+                }
+                None => {
+                    report.key_exists =
+                        KmsCheckResult::Failed("DescribeKey returned no key metadata".to_owned());
+                }
+            },
+            Err(e) => {
+                report.key_exists = KmsCheckResult::Failed(DisplayErrorContext(e).to_string());
+            }
+        }
+
+        report.get_public_key_permission = match kms_client
+            .get_public_key()
+            .key_id(self.key_id.clone())
+            .send()
+            .await
+        {
+            Ok(_) => KmsCheckResult::Passed,
+            Err(e) => KmsCheckResult::Failed(DisplayErrorContext(e).to_string()),
+        };
+
+        // A successful dry run is reported by KMS as a `DryRunOperationException`.
+        report.sign_permission = match kms_client
+            .sign()
+            .key_id(self.key_id.clone())
+            .message(Blob::new(vec![0; SHA256_OUTPUT_LEN]))
+            .message_type(MessageType::Digest)
+            .signing_algorithm(self.signing_algorithm.value())
+            .dry_run(true)
+            .send()
+            .await
+        {
+            Ok(_) => KmsCheckResult::Passed,
+            Err(e) => {
+                if e.as_service_error().is_some_and(
+                    aws_sdk_kms::operation::sign::SignError::is_dry_run_operation_exception,
+                ) {
+                    KmsCheckResult::Passed
+                } else {
+                    KmsCheckResult::Failed(DisplayErrorContext(e).to_string())
+                }
+            }
+        };
+
+        report
+    }
+}
@@ -9,8 +9,8 @@ use std::fs::File;
 use std::io::BufReader;
 use tough::key_source::KeySource;
 use tough::schema::key::Key;
-use tough_kms::KmsKeySource;
-use tough_kms::KmsSigningAlgorithm::RsassaPssSha256;
+use tough_kms::KmsSigningAlgorithm::{EcdsaSha256, RsassaPssSha256};
+use tough_kms::{KmsCheckResult, KmsKeySource};
 
 /// Deserialize base64 to `bytes::Bytes`
 fn de_bytes<'de, D>(deserializer: D) -> Result<bytes::Bytes, D::Error>
@@ -22,6 +22,7 @@ where
     Ok(b.into())
 }
 
+#[allow(dead_code)]
 #[derive(Default, Debug, Clone, PartialEq, Deserialize)]
 struct PublicKeyResp {
     #[serde(rename = "PublicKey")]
@@ -38,6 +39,7 @@ struct SignResp {
     signature: bytes::Bytes,
 }
 
+#[allow(dead_code)]
 #[derive(Default, Debug, Clone, PartialEq, Deserialize)]
 struct CreateKeyResp {
     #[serde(rename = "KeyId")]
@@ -73,6 +75,22 @@ async fn check_tuf_key_success() {
     assert_eq!(key, expected_key);
 }
 
+#[tokio::test]
+// Ensure the public key can be fetched without constructing a signer
+async fn check_public_key_success() {
+    let file = File::open(test_utils::test_data().join("expected_public_key.json")).unwrap();
+    let expected_key: Key = serde_json::from_reader(BufReader::new(file)).unwrap();
+
+    let client = test_utils::mock_client(vec!["response_public_key.json"]);
+    let kms_key = KmsKeySource {
+        profile: None,
+        key_id: String::from("alias/some_alias"),
+        client: Some(client),
+        signing_algorithm: RsassaPssSha256,
+    };
+    assert_eq!(kms_key.public_key().await.unwrap(), expected_key);
+}
+
 #[tokio::test]
 // Ensure message signature is returned on calling sign
 async fn check_sign_success() {
@@ -104,6 +122,57 @@ async fn check_sign_success() {
     assert_eq!(signature, expected_signature);
 }
 
+#[tokio::test]
+// Ensure an ECC_NIST_P256 key is converted to a TUF ECDSA key and its DER signature is returned
+async fn check_ecdsa_sign_success() {
+    let file = File::open(test_utils::test_data().join("expected_public_key_ecdsa.json")).unwrap();
+    let expected_key: Key = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let file = File::open(test_utils::test_data().join("response_signature_ecdsa.json")).unwrap();
+    let expected_json: SignResp = serde_json::from_reader(BufReader::new(file)).unwrap();
+
+    let client = test_utils::mock_client(vec![
+        "response_public_key_ecdsa.json",
+        "response_signature_ecdsa.json",
+    ]);
+    let kms_key = KmsKeySource {
+        profile: None,
+        key_id: String::from("alias/some_alias"),
+        client: Some(client),
+        signing_algorithm: EcdsaSha256,
+    };
+    let kms_sign = kms_key.as_sign().await.unwrap();
+    assert_eq!(kms_sign.tuf_key(), expected_key);
+    let signature = kms_sign
+        .sign("Some message to sign".as_bytes(), &SystemRandom::new())
+        .await
+        .unwrap();
+    assert_eq!(signature, expected_json.signature.to_vec());
+}
+
+#[tokio::test]
+// Ensure an ECDSA signature that doesn't verify with the key's public key is refused
+async fn check_ecdsa_signature_invalid() {
+    let client = test_utils::mock_client(vec![
+        "response_public_key_ecdsa.json",
+        "response_signature.json",
+    ]);
+    let kms_key = KmsKeySource {
+        profile: None,
+        key_id: String::from("alias/some_alias"),
+        client: Some(client),
+        signing_algorithm: EcdsaSha256,
+    };
+    let kms_sign = kms_key.as_sign().await.unwrap();
+    let err = kms_sign
+        .sign("Some message to sign".as_bytes(), &SystemRandom::new())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "AWS KMS returned an invalid signature for alias/some_alias"
+    );
+}
+
 #[tokio::test]
 // Ensure call to tuf_key fails when public key is not available
 async fn check_public_key_failure() {
@@ -194,3 +263,86 @@ async fn check_write_ok() {
     };
     assert!(kms_key.write("", "").await.is_ok());
 }
+
+#[tokio::test]
+// Ensure validate reports success for a usable signing key
+async fn check_validate_ok() {
+    let client = test_utils::mock_client_with_responses(vec![
+        (200, "response_describe_key.json"),
+        (200, "response_public_key.json"),
+        (412, "response_sign_dry_run.json"),
+    ]);
+    let kms_key = KmsKeySource {
+        profile: None,
+        key_id: String::from("alias/some_alias"),
+        client: Some(client),
+        signing_algorithm: RsassaPssSha256,
+    };
+    let report = kms_key.validate().await;
+    assert!(report.is_ok(), "{}", report);
+}
+
+#[tokio::test]
+// Ensure validate reports each problem instead of failing on the first one
+async fn check_validate_failures() {
+    let client = test_utils::mock_client_with_responses(vec![
+        (200, "response_describe_key_encrypt.json"),
+        (200, "response_public_key.json"),
+        (400, "response_access_denied.json"),
+    ]);
+    let kms_key = KmsKeySource {
+        profile: None,
+        key_id: String::from("alias/some_alias"),
+        client: Some(client),
+        signing_algorithm: RsassaPssSha256,
+    };
+    let report = kms_key.validate().await;
+    assert!(!report.is_ok());
+    assert_eq!(report.key_exists, KmsCheckResult::Passed);
+    assert!(!report.sign_capable.passed());
+    assert!(!report.algorithm_supported.passed());
+    assert_eq!(report.get_public_key_permission, KmsCheckResult::Passed);
+    assert!(!report.sign_permission.passed());
+}
+
+#[tokio::test]
+// Ensure a new key is created and the alias is pointed at it
+async fn create_key_success() {
+    let client = test_utils::mock_client(vec![
+        "response_create_key.json",
+        "response_create_alias.json",
+    ]);
+    let kms_key = KmsKeySource {
+        profile: None,
+        key_id: String::from("alias/some_alias"),
+        client: Some(client),
+        signing_algorithm: RsassaPssSha256,
+    };
+    assert_eq!(kms_key.create_key(3072).await.unwrap(), "SomeKeyId");
+}
+
+#[tokio::test]
+// Ensure keys are only created under an alias, and only in sizes AWS KMS offers
+async fn create_key_invalid_request() {
+    let key = |key_id: &str| KmsKeySource {
+        profile: None,
+        key_id: String::from(key_id),
+        client: Some(test_utils::mock_client(vec!["response_create_key.json"])),
+        signing_algorithm: RsassaPssSha256,
+    };
+    let err = key("SomeKeyId").create_key(2048).await.unwrap_err();
+    assert!(
+        matches!(err, tough_kms::error::Error::CreateKeyNeedsAlias { .. }),
+        "{}",
+        err
+    );
+    let err = key("alias/some_alias").create_key(1024).await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            tough_kms::error::Error::UnsupportedKeyBits { bits: 1024, .. }
+        ),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,243 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Tests for the Google Cloud KMS and Azure Key Vault key sources against a mock of each REST API.
+
+use aws_lc_rs::digest::{digest, SHA256};
+use aws_lc_rs::rand::SystemRandom;
+use aws_lc_rs::rsa::{KeyPair as RsaKeyPair, KeySize};
+use aws_lc_rs::signature::{
+    EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING, ECDSA_P256_SHA256_FIXED_SIGNING,
+    RSA_PSS_SHA256,
+};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+use httptest::matchers::{all_of, contains, json_decoded, request};
+use httptest::responders::json_encoded;
+use httptest::{Expectation, Server};
+use serde_json::json;
+use tough::key_source::KeySource;
+use tough::schema::decoded::{EcdsaPem, Encode};
+use tough::schema::key::Key;
+use tough_kms::{AzureKeyVaultKeySource, GcpKmsKeySource};
+
+const MESSAGE: &[u8] = b"Some message to sign";
+const KEY_VERSION: &str =
+    "projects/p/locations/global/keyRings/tuf/cryptoKeys/root/cryptoKeyVersions/1";
+
+fn ecdsa_keypair(alg: &'static aws_lc_rs::signature::EcdsaSigningAlgorithm) -> EcdsaKeyPair {
+    let document = EcdsaKeyPair::generate_pkcs8(alg, &SystemRandom::new()).unwrap();
+    EcdsaKeyPair::from_pkcs8(alg, document.as_ref()).unwrap()
+}
+
+fn gcp_key_source(server: &Server) -> GcpKmsKeySource {
+    GcpKmsKeySource {
+        key_version: KEY_VERSION.to_owned(),
+        access_token: "gcp-token".to_owned(),
+        endpoint: Some(server.url_str("")),
+    }
+}
+
+fn assert_verifies(key: &Key, signature: &[u8]) {
+    assert!(tough::crypto_backend().verify(key, MESSAGE, signature));
+}
+
+#[tokio::test]
+// Ensure a Cloud KMS EC_SIGN_P256_SHA256 key signs the message's digest
+async fn gcp_ecdsa_sign() {
+    let keypair = ecdsa_keypair(&ECDSA_P256_SHA256_ASN1_SIGNING);
+    let signature = keypair.sign(&SystemRandom::new(), MESSAGE).unwrap();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", format!("/v1/{KEY_VERSION}/publicKey")),
+            request::headers(contains(("authorization", "Bearer gcp-token"))),
+        ])
+        .times(2)
+        .respond_with(json_encoded(json!({
+            "pem": EcdsaPem::encode(keypair.public_key().as_ref()),
+            "algorithm": "EC_SIGN_P256_SHA256",
+        }))),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", format!("/v1/{KEY_VERSION}:asymmetricSign")),
+            request::body(json_decoded(httptest::matchers::eq(json!({
+                "digest": { "sha256": STANDARD.encode(digest(&SHA256, MESSAGE)) },
+            })))),
+        ])
+        .respond_with(json_encoded(json!({
+            "signature": STANDARD.encode(signature.as_ref()),
+        }))),
+    );
+
+    let key_source = gcp_key_source(&server);
+    let sign = key_source.as_sign().await.unwrap();
+    let key = sign.tuf_key();
+    assert!(matches!(key, Key::Ecdsa { .. }));
+    assert_eq!(key.public_key(), keypair.public_key().as_ref());
+    assert_eq!(key_source.public_key().await.unwrap(), key);
+
+    let signature = sign.sign(MESSAGE, &SystemRandom::new()).await.unwrap();
+    assert_verifies(&key, &signature);
+}
+
+#[tokio::test]
+// Ensure a Cloud KMS key whose algorithm TUF can't use is refused
+async fn gcp_unsupported_algorithm() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path(
+            "GET",
+            format!("/v1/{KEY_VERSION}/publicKey"),
+        ))
+        .respond_with(json_encoded(json!({
+            "pem": "",
+            "algorithm": "RSA_SIGN_PKCS1_2048_SHA256",
+        }))),
+    );
+    let err = gcp_key_source(&server).as_sign().await.err().unwrap();
+    assert!(
+        err.to_string().contains("RSA_SIGN_PKCS1_2048_SHA256"),
+        "{}",
+        err
+    );
+}
+
+/// Expects one GET of the key at `/keys/root` with `jwk` as the key, which names `/keys/root/1` as
+/// the version that signs.
+fn expect_azure_key(server: &Server, mut jwk: serde_json::Value) {
+    jwk["kid"] = server.url_str("/keys/root/1").into();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/keys/root"),
+            request::query(httptest::matchers::url_decoded(contains((
+                "api-version",
+                "7.4"
+            )))),
+            request::headers(contains(("authorization", "Bearer azure-token"))),
+        ])
+        .respond_with(json_encoded(json!({ "key": jwk }))),
+    );
+}
+
+fn expect_azure_sign(server: &Server, alg: &str, signature: &[u8]) {
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/keys/root/1/sign"),
+            request::body(json_decoded(httptest::matchers::eq(json!({
+                "alg": alg,
+                "value": URL_SAFE_NO_PAD.encode(digest(&SHA256, MESSAGE)),
+            })))),
+        ])
+        .respond_with(json_encoded(json!({
+            "kid": server.url_str("/keys/root/1"),
+            "value": URL_SAFE_NO_PAD.encode(signature),
+        }))),
+    );
+}
+
+fn azure_key_source(server: &Server) -> AzureKeyVaultKeySource {
+    AzureKeyVaultKeySource {
+        key_id: server.url_str("/keys/root"),
+        access_token: "azure-token".to_owned(),
+    }
+}
+
+#[tokio::test]
+// Ensure a Key Vault EC key's `r || s` signature is converted to the DER form TUF verifies
+async fn azure_ecdsa_sign() {
+    let keypair = ecdsa_keypair(&ECDSA_P256_SHA256_FIXED_SIGNING);
+    let point = keypair.public_key().as_ref();
+    let server = Server::run();
+    expect_azure_key(
+        &server,
+        json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+        }),
+    );
+    let signature = keypair.sign(&SystemRandom::new(), MESSAGE).unwrap();
+    expect_azure_sign(&server, "ES256", signature.as_ref());
+
+    let sign = azure_key_source(&server).as_sign().await.unwrap();
+    let key = sign.tuf_key();
+    assert_eq!(key.public_key(), point);
+    let signature = sign.sign(MESSAGE, &SystemRandom::new()).await.unwrap();
+    assert_verifies(&key, &signature);
+}
+
+#[tokio::test]
+// Ensure a Key Vault RSA key signs with PS256 and its signature verifies
+async fn azure_rsa_sign() {
+    let keypair = RsaKeyPair::generate(KeySize::Rsa2048).unwrap();
+    let public = keypair.public_key();
+    let server = Server::run();
+    expect_azure_key(
+        &server,
+        json!({
+            "kty": "RSA-HSM",
+            "n": URL_SAFE_NO_PAD.encode(public.modulus().big_endian_without_leading_zero()),
+            "e": URL_SAFE_NO_PAD.encode(public.exponent().big_endian_without_leading_zero()),
+        }),
+    );
+    let mut signature = vec![0; keypair.public_modulus_len()];
+    keypair
+        .sign(
+            &RSA_PSS_SHA256,
+            &SystemRandom::new(),
+            MESSAGE,
+            &mut signature,
+        )
+        .unwrap();
+    expect_azure_sign(&server, "PS256", &signature);
+
+    let sign = azure_key_source(&server).as_sign().await.unwrap();
+    let key = sign.tuf_key();
+    assert!(matches!(key, Key::Rsa { .. }));
+    let signature = sign.sign(MESSAGE, &SystemRandom::new()).await.unwrap();
+    assert_eq!(signature.len(), 256);
+    assert_verifies(&key, &signature);
+}
+
+#[tokio::test]
+// Ensure an ECDSA signature that doesn't verify with the key's public key is refused
+async fn azure_ecdsa_signature_invalid() {
+    let keypair = ecdsa_keypair(&ECDSA_P256_SHA256_FIXED_SIGNING);
+    let point = keypair.public_key().as_ref();
+    let server = Server::run();
+    expect_azure_key(
+        &server,
+        json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+        }),
+    );
+    expect_azure_sign(&server, "ES256", &[0x11; 64]);
+
+    let sign = azure_key_source(&server).as_sign().await.unwrap();
+    let err = sign.sign(MESSAGE, &SystemRandom::new()).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Azure Key Vault returned an invalid signature for {}",
+            server.url_str("/keys/root/1")
+        )
+    );
+}
+
+#[tokio::test]
+// Ensure an error status from the key service is reported
+async fn azure_error_status() {
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/keys/root"))
+            .respond_with(httptest::responders::status_code(403).body("Forbidden")),
+    );
+    let err = azure_key_source(&server).public_key().await.unwrap_err();
+    assert!(err.to_string().contains("HTTP 403: Forbidden"), "{}", err);
+}
@@ -16,6 +16,12 @@ pub fn test_data() -> PathBuf {
 }
 
 pub fn mock_client(data_files: Vec<&str>) -> Client {
+    mock_client_with_responses(data_files.into_iter().map(|d| (200, d)).collect())
+}
+
+// Create a mock client that replays each data file as a response body with the paired
+// status code, in order.
+pub fn mock_client_with_responses(responses: Vec<(u16, &str)>) -> Client {
     let creds = Credentials::new(
         "ATESTCLIENT",
         "atestsecretkey",
@@ -25,9 +31,9 @@ pub fn mock_client(data_files: Vec<&str>) -> Client {
     );
 
     // Get a vec of events based on the given data_files
-    let events = data_files
+    let events = responses
         .iter()
-        .map(|d| {
+        .map(|(status, d)| {
             let path = std::path::Path::new("tests/data").join(d);
             let data = std::fs::read_to_string(path).unwrap();
 
@@ -39,7 +45,7 @@ pub fn mock_client(data_files: Vec<&str>) -> Client {
                     .unwrap(),
                 // Response
                 http::Response::builder()
-                    .status(200)
+                    .status(*status)
                     .body(SdkBody::from(data))
                     .unwrap(),
             )
@@ -0,0 +1,38 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! The DER that PKCS#11 tokens describe their keys with, beyond what `tough::der` provides.
+
+use tough::der::{read_tlv, OCTET_STRING};
+
+/// The DER encoding of the named curve parameters for P-256 (OID 1.2.840.10045.3.1.7), which is
+/// how tokens describe the curve of a P-256 key in `CKA_EC_PARAMS`.
+pub(crate) const P256_PARAMS: [u8; 10] =
+    [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+
+/// Returns the contents of `data` if it is exactly one OCTET STRING.
+pub(crate) fn octet_string_contents(data: &[u8]) -> Option<&[u8]> {
+    match read_tlv(data)? {
+        (OCTET_STRING, contents, []) => Some(contents),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tough::der::tlv;
+
+    #[test]
+    fn octet_strings() {
+        assert_eq!(
+            octet_string_contents(&[0x04, 0x02, 1, 2]),
+            Some(&[1, 2][..])
+        );
+        let long = tlv(OCTET_STRING, &[7; 200]);
+        assert_eq!(octet_string_contents(&long), Some(&[7; 200][..]));
+        assert_eq!(octet_string_contents(&[0x04, 0x03, 1, 2]), None);
+        assert_eq!(octet_string_contents(&[0x04, 0x01, 1, 2]), None);
+        assert_eq!(octet_string_contents(&[0x02, 0x01, 1]), None);
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Contains the error type for this library.
+
+#![allow(clippy::default_trait_access)]
+
+use snafu::{Backtrace, Snafu};
+use std::path::PathBuf;
+
+/// Alias for `Result<T, Error>`.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The error type for this library.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum Error {
+    /// The PKCS#11 module could not be loaded or initialized
+    #[snafu(display("Failed to load PKCS#11 module '{}': {}", module.display(), source))]
+    ModuleLoad {
+        module: PathBuf,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The PKCS#11 module could not list its slots
+    #[snafu(display("Failed to list the slots of PKCS#11 module '{}': {}", module.display(), source))]
+    ListSlots {
+        module: PathBuf,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// No token is present in the requested slot, or in any slot if none was requested
+    #[snafu(display(
+        "No token found in {} of PKCS#11 module '{}'",
+        slot.map_or_else(|| "any slot".to_owned(), |slot| format!("slot {slot}")),
+        module.display()
+    ))]
+    TokenMissing { module: PathBuf, slot: Option<u64> },
+
+    /// A session with the token could not be opened
+    #[snafu(display("Failed to open a session with the token in slot {}: {}", slot, source))]
+    OpenSession {
+        slot: u64,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The user PIN was not accepted by the token
+    #[snafu(display("Failed to log in to the token in slot {}: {}", slot, source))]
+    Login {
+        slot: u64,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The token could not be searched for the key
+    #[snafu(display("Failed to search for {} key '{}': {}", class, label, source))]
+    FindKey {
+        label: String,
+        class: &'static str,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The token holds no key with the label
+    #[snafu(display("No {} key labeled '{}' found on the token", class, label))]
+    KeyMissing { label: String, class: &'static str },
+
+    /// The token holds more than one key with the label
+    #[snafu(display("{} {} keys labeled '{}' found on the token", count, class, label))]
+    KeyAmbiguous {
+        label: String,
+        class: &'static str,
+        count: usize,
+    },
+
+    /// The attributes of the public key could not be read
+    #[snafu(display("Failed to read the public key labeled '{}': {}", label, source))]
+    KeyAttributes {
+        label: String,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The public key lacks an attribute needed to describe it in TUF metadata
+    #[snafu(display("The public key labeled '{}' has no {} attribute", label, attribute))]
+    KeyAttributeMissing {
+        label: String,
+        attribute: &'static str,
+    },
+
+    /// The key is neither an RSA key nor a P-256 key
+    #[snafu(display(
+        "The key labeled '{}' is not supported: only RSA and P-256 keys can sign",
+        label
+    ))]
+    UnsupportedKey { label: String },
+
+    /// The token failed to sign
+    #[snafu(display("Failed to sign with the key labeled '{}': {}", label, source))]
+    Sign {
+        label: String,
+        source: cryptoki::error::Error,
+        backtrace: Backtrace,
+    },
+
+    /// The token returned a signature that does not verify with the public key
+    #[snafu(display("The token returned an invalid signature for key '{}'", label))]
+    SignatureInvalid { label: String },
+
+    /// Keys can't be written to a token
+    #[snafu(display(
+        "Key '{}' can't be written to a PKCS#11 token; create it with the token's own tools instead",
+        label
+    ))]
+    KeyWrite { label: String },
+}
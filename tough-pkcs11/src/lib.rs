@@ -0,0 +1,513 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! tough-pkcs11 implements the `KeySource` trait found in [tough, a Rust TUF client](https://github.com/awslabs/tough)
+//! for keys held in PKCS#11 tokens, such as `YubiHSM`, `SoftHSM` or a cloud HSM.
+//!
+//! [`Pkcs11KeySource`] loads the token's PKCS#11 module, opens a session with the token in a slot,
+//! logs in with the user PIN, and finds the key pair by its label. The signing mechanism follows
+//! the key type: RSA keys sign with RSASSA-PSS (`CKM_RSA_PKCS_PSS`) and P-256 keys with ECDSA
+//! (`CKM_ECDSA`), in both cases over a SHA-256 digest computed before the token is asked to sign.
+//!
+//! # Testing
+//!
+//! Unit tests are run in the usual manner: `cargo test`. Signing with a token isn't covered by
+//! them, since that needs a PKCS#11 module such as `SoftHSM` to be installed.
+
+#![forbid(missing_debug_implementations, missing_copy_implementations)]
+#![deny(rust_2018_idioms)]
+// missing_docs is on its own line to make it easy to comment out when making changes.
+#![deny(missing_docs)]
+#![warn(clippy::pedantic)]
+#![allow(
+    clippy::module_name_repetitions,
+    clippy::must_use_candidate,
+    clippy::missing_errors_doc,
+    clippy::result_large_err
+)]
+
+mod der;
+pub mod error;
+
+use aws_lc_rs::digest::{digest, SHA256};
+use aws_lc_rs::rand::SecureRandom;
+use aws_lc_rs::signature::{
+    UnparsedPublicKey, VerificationAlgorithm, ECDSA_P256_SHA256_ASN1, RSA_PSS_2048_8192_SHA256,
+};
+use cryptoki::context::{CInitializeArgs, CInitializeFlags, Pkcs11};
+use cryptoki::error::{Error as CryptokiError, RvError};
+use cryptoki::mechanism::rsa::{PkcsMgfType, PkcsPssParams};
+use cryptoki::mechanism::{Mechanism, MechanismType};
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::slot::Slot;
+use cryptoki::types::AuthPin;
+use snafu::{ensure, OptionExt, ResultExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use tough::async_trait;
+use tough::der::{sequence, unsigned_integer};
+use tough::key_source::KeySource;
+use tough::schema::decoded::Decoded;
+use tough::schema::key::{EcdsaKey, EcdsaScheme, Key, RsaKey, RsaScheme};
+use tough::sign::Sign;
+
+/// The length of a SHA-256 digest, which is also the PSS salt length TUF verifiers expect.
+const SHA256_LEN: u64 = 32;
+
+/// Implements the `KeySource` trait for keys held in a PKCS#11 token.
+#[derive(Clone)]
+pub struct Pkcs11KeySource {
+    /// The path to the token's PKCS#11 module, such as `/usr/lib/softhsm/libsofthsm2.so`.
+    pub module: PathBuf,
+    /// The ID of the slot that holds the token. If `None`, the first slot with a token is used.
+    pub slot: Option<u64>,
+    /// The user PIN. If `None`, the session isn't logged in, which only works with tokens that
+    /// allow their private keys to be used without logging in.
+    pub pin: Option<String>,
+    /// The label (`CKA_LABEL`) of the key pair. The token must hold exactly one private key and
+    /// one public key with this label.
+    pub key_label: String,
+}
+
+impl fmt::Debug for Pkcs11KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs11KeySource")
+            .field("module", &self.module)
+            .field("slot", &self.slot)
+            .field("key_label", &self.key_label)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Pkcs11KeySource {
+    /// Opens a session with the token, logged in if a PIN is configured.
+    fn open_session(&self) -> error::Result<Session> {
+        let context = Pkcs11::new(&self.module).context(error::ModuleLoadSnafu {
+            module: &self.module,
+        })?;
+        // A module that another key source in this process loaded is already initialized.
+        match context.initialize(CInitializeArgs::new(CInitializeFlags::OS_LOCKING_OK)) {
+            Ok(()) | Err(CryptokiError::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => {}
+            Err(source) => {
+                return Err(source).context(error::ModuleLoadSnafu {
+                    module: &self.module,
+                })
+            }
+        }
+
+        let slots = context
+            .get_slots_with_token()
+            .context(error::ListSlotsSnafu {
+                module: &self.module,
+            })?;
+        let slot: Slot = slots
+            .into_iter()
+            .find(|slot| self.slot.is_none_or(|id| slot.id() == id))
+            .context(error::TokenMissingSnafu {
+                module: &self.module,
+                slot: self.slot,
+            })?;
+
+        let session = context
+            .open_ro_session(slot)
+            .context(error::OpenSessionSnafu { slot: slot.id() })?;
+        if let Some(pin) = &self.pin {
+            match session.login(UserType::User, Some(&AuthPin::new(pin.as_str().into()))) {
+                Ok(()) | Err(CryptokiError::Pkcs11(RvError::UserAlreadyLoggedIn, _)) => {}
+                Err(source) => {
+                    return Err(source).context(error::LoginSnafu { slot: slot.id() });
+                }
+            }
+        }
+        Ok(session)
+    }
+
+    /// Finds the one key of `class` with the configured label.
+    fn find_key(&self, session: &Session, class: ObjectClass) -> error::Result<ObjectHandle> {
+        let class_name = if class == ObjectClass::PRIVATE_KEY {
+            "private"
+        } else {
+            "public"
+        };
+        let handles = session
+            .find_objects(&[
+                Attribute::Class(class),
+                Attribute::Label(self.key_label.as_bytes().to_vec()),
+            ])
+            .context(error::FindKeySnafu {
+                label: &self.key_label,
+                class: class_name,
+            })?;
+        ensure!(
+            handles.len() <= 1,
+            error::KeyAmbiguousSnafu {
+                label: &self.key_label,
+                class: class_name,
+                count: handles.len(),
+            }
+        );
+        handles.into_iter().next().context(error::KeyMissingSnafu {
+            label: &self.key_label,
+            class: class_name,
+        })
+    }
+
+    /// Reads the public key from the token.
+    fn read_public_key(&self, session: &Session) -> error::Result<TokenPublicKey> {
+        let handle = self.find_key(session, ObjectClass::PUBLIC_KEY)?;
+        let attributes = session
+            .get_attributes(
+                handle,
+                &[
+                    AttributeType::KeyType,
+                    AttributeType::Modulus,
+                    AttributeType::PublicExponent,
+                    AttributeType::EcParams,
+                    AttributeType::EcPoint,
+                ],
+            )
+            .context(error::KeyAttributesSnafu {
+                label: &self.key_label,
+            })?;
+        TokenPublicKey::from_attributes(&self.key_label, &attributes)
+    }
+
+    /// Runs `f` on Tokio's blocking thread pool, since loading the module, logging in and
+    /// searching the token can all take a while.
+    async fn blocking<T, F>(&self, f: F) -> error::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self) -> error::Result<T> + Send + 'static,
+    {
+        let source = self.clone();
+        tokio::task::spawn_blocking(move || f(&source))
+            .await
+            // We do not cancel the task nor do we expect it to panic
+            .unwrap_or_else(|_| unreachable!())
+    }
+}
+
+/// Implement the `KeySource` trait.
+#[async_trait]
+impl KeySource for Pkcs11KeySource {
+    async fn as_sign(
+        &self,
+    ) -> std::result::Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        let (session, public_key, private_key) = self
+            .blocking(|source| {
+                let session = source.open_session()?;
+                let public_key = source.read_public_key(&session)?;
+                let private_key = source.find_key(&session, ObjectClass::PRIVATE_KEY)?;
+                Ok((session, public_key, private_key))
+            })
+            .await?;
+        Ok(Box::new(Pkcs11Key {
+            label: self.key_label.clone(),
+            session: Arc::new(Mutex::new(session)),
+            private_key,
+            public_key,
+        }))
+    }
+
+    /// Only reads the public key, without finding the private key.
+    async fn public_key(
+        &self,
+    ) -> std::result::Result<Key, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let public_key = self
+            .blocking(|source| source.read_public_key(&source.open_session()?))
+            .await?;
+        Ok(public_key.tuf_key())
+    }
+
+    /// Keys are created on the token with its own tools, so they can't be written to it.
+    async fn write(
+        &self,
+        _value: &str,
+        _key_id_hex: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Err(error::KeyWriteSnafu {
+            label: &self.key_label,
+        }
+        .build()
+        .into())
+    }
+}
+
+/// A public key read from a token, in the form TUF metadata lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenPublicKey {
+    /// The DER `RSAPublicKey` of an RSA key.
+    Rsa(Vec<u8>),
+    /// The uncompressed point of a P-256 key.
+    Ecdsa(Vec<u8>),
+}
+
+impl TokenPublicKey {
+    fn from_attributes(label: &str, attributes: &[Attribute]) -> error::Result<Self> {
+        let missing = |attribute| error::KeyAttributeMissingSnafu { label, attribute };
+        let key_type = attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::KeyType(key_type) => Some(*key_type),
+                _ => None,
+            })
+            .context(missing("CKA_KEY_TYPE"))?;
+        if key_type == KeyType::RSA {
+            let modulus = attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::Modulus(modulus) => Some(modulus),
+                    _ => None,
+                })
+                .context(missing("CKA_MODULUS"))?;
+            let exponent = attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::PublicExponent(exponent) => Some(exponent),
+                    _ => None,
+                })
+                .context(missing("CKA_PUBLIC_EXPONENT"))?;
+            Ok(TokenPublicKey::Rsa(sequence(&[
+                unsigned_integer(modulus),
+                unsigned_integer(exponent),
+            ])))
+        } else if key_type == KeyType::EC {
+            let params = attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::EcParams(params) => Some(params),
+                    _ => None,
+                })
+                .context(missing("CKA_EC_PARAMS"))?;
+            ensure!(
+                params[..] == der::P256_PARAMS,
+                error::UnsupportedKeySnafu { label }
+            );
+            let point = attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::EcPoint(point) => Some(point),
+                    _ => None,
+                })
+                .context(missing("CKA_EC_POINT"))?;
+            // PKCS#11 wraps the point in an OCTET STRING, though some tokens return it bare.
+            let point = der::octet_string_contents(point).unwrap_or(point);
+            ensure!(
+                point.len() == 65 && point[0] == 0x04,
+                error::UnsupportedKeySnafu { label }
+            );
+            Ok(TokenPublicKey::Ecdsa(point.to_vec()))
+        } else {
+            error::UnsupportedKeySnafu { label }.fail()
+        }
+    }
+
+    fn tuf_key(&self) -> Key {
+        match self {
+            TokenPublicKey::Rsa(public) => Key::Rsa {
+                keyval: RsaKey {
+                    public: Decoded::from(public.clone()),
+                    _extra: HashMap::new(),
+                },
+                scheme: RsaScheme::RsassaPssSha256,
+                _extra: HashMap::new(),
+            },
+            TokenPublicKey::Ecdsa(public) => Key::Ecdsa {
+                keyval: EcdsaKey {
+                    public: Decoded::from(public.clone()),
+                    _extra: HashMap::new(),
+                },
+                scheme: EcdsaScheme::EcdsaSha2Nistp256,
+                _extra: HashMap::new(),
+            },
+        }
+    }
+
+    /// The mechanism that signs a SHA-256 digest with this kind of key.
+    fn mechanism(&self) -> Mechanism<'static> {
+        match self {
+            TokenPublicKey::Rsa(_) => Mechanism::RsaPkcsPss(PkcsPssParams {
+                hash_alg: MechanismType::SHA256,
+                mgf: PkcsMgfType::MGF1_SHA256,
+                s_len: SHA256_LEN.into(),
+            }),
+            TokenPublicKey::Ecdsa(_) => Mechanism::Ecdsa,
+        }
+    }
+
+    /// Turns the signature the token returned into the form TUF metadata lists, and checks it.
+    fn signature(&self, label: &str, msg: &[u8], signature: Vec<u8>) -> error::Result<Vec<u8>> {
+        let (signature, algorithm, public): (_, &dyn VerificationAlgorithm, _) = match self {
+            TokenPublicKey::Rsa(public) => (signature, &RSA_PSS_2048_8192_SHA256, public),
+            // PKCS#11 returns ECDSA signatures as r and s side by side, while TUF lists them DER
+            // encoded.
+            TokenPublicKey::Ecdsa(public) => {
+                let (r, s) = signature.split_at(signature.len() / 2);
+                (
+                    sequence(&[unsigned_integer(r), unsigned_integer(s)]),
+                    &ECDSA_P256_SHA256_ASN1,
+                    public,
+                )
+            }
+        };
+        UnparsedPublicKey::new(algorithm, public)
+            .verify(msg, &signature)
+            .ok()
+            .context(error::SignatureInvalidSnafu { label })?;
+        Ok(signature)
+    }
+}
+
+/// Implements the `Sign` trait for a key held in a PKCS#11 token. Signatures are made on Tokio's
+/// blocking thread pool, so `sign` must be called from within a Tokio runtime.
+pub struct Pkcs11Key {
+    /// The label of the key pair.
+    label: String,
+    /// The session, logged in if a PIN was configured, that signs with the private key. It is
+    /// shared with the blocking task that each signature is made on.
+    session: Arc<Mutex<Session>>,
+    /// The private key's handle in `session`.
+    private_key: ObjectHandle,
+    /// The public key read from the token.
+    public_key: TokenPublicKey,
+}
+
+impl fmt::Debug for Pkcs11Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs11Key")
+            .field("label", &self.label)
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Sign for Pkcs11Key {
+    fn tuf_key(&self) -> Key {
+        self.public_key.tuf_key()
+    }
+
+    async fn sign(
+        &self,
+        msg: &[u8],
+        _rng: &(dyn SecureRandom + Sync),
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        // The token can take a while to sign, and other signatures wait on the session's lock, so
+        // neither holds up the async runtime's worker threads.
+        let session = Arc::clone(&self.session);
+        let public_key = self.public_key.clone();
+        let private_key = self.private_key;
+        let digest = digest(&SHA256, msg);
+        let signature = tokio::task::spawn_blocking(move || {
+            session.lock().unwrap_or_else(PoisonError::into_inner).sign(
+                &public_key.mechanism(),
+                private_key,
+                digest.as_ref(),
+            )
+        })
+        .await
+        // We do not cancel the task nor do we expect it to panic
+        .unwrap_or_else(|_| unreachable!())
+        .context(error::SignSnafu { label: &self.label })?;
+        Ok(self.public_key.signature(&self.label, msg, signature)?)
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_lc_rs::rand::SystemRandom;
+    use aws_lc_rs::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    fn ec_attributes(point: Vec<u8>) -> Vec<Attribute> {
+        vec![
+            Attribute::KeyType(KeyType::EC),
+            Attribute::EcParams(der::P256_PARAMS.to_vec()),
+            Attribute::EcPoint(point),
+        ]
+    }
+
+    #[test]
+    fn rsa_public_key_from_attributes() {
+        let key = TokenPublicKey::from_attributes(
+            "rsa",
+            &[
+                Attribute::KeyType(KeyType::RSA),
+                Attribute::Modulus(vec![0xc0, 0x01]),
+                Attribute::PublicExponent(vec![0x01, 0x00, 0x01]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            key,
+            TokenPublicKey::Rsa(vec![
+                0x30, 0x0a, 0x02, 0x03, 0x00, 0xc0, 0x01, 0x02, 0x03, 0x01, 0x00, 0x01
+            ])
+        );
+    }
+
+    #[test]
+    fn ec_point_wrapped_or_bare() {
+        let mut point = vec![0x04];
+        point.extend([7; 64]);
+        let mut wrapped = vec![0x04, 65];
+        wrapped.extend(&point);
+        for attribute in [wrapped, point.clone()] {
+            assert_eq!(
+                TokenPublicKey::from_attributes("ec", &ec_attributes(attribute)).unwrap(),
+                TokenPublicKey::Ecdsa(point.clone())
+            );
+        }
+        let err =
+            TokenPublicKey::from_attributes("ec", &ec_attributes(vec![0x04; 33])).unwrap_err();
+        assert!(
+            matches!(err, error::Error::UnsupportedKey { .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn unsupported_key_type() {
+        let err = TokenPublicKey::from_attributes("aes", &[Attribute::KeyType(KeyType::AES)])
+            .unwrap_err();
+        assert!(
+            matches!(err, error::Error::UnsupportedKey { .. }),
+            "{}",
+            err
+        );
+    }
+
+    /// A P-256 signature in the fixed form a token returns is DER encoded and verified.
+    #[test]
+    fn ecdsa_signature_is_der_encoded() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref()).unwrap();
+        let public_key = TokenPublicKey::Ecdsa(pair.public_key().as_ref().to_vec());
+        let msg = b"signed metadata";
+        let fixed = pair.sign(&rng, msg).unwrap().as_ref().to_vec();
+
+        let signature = public_key.signature("ec", msg, fixed.clone()).unwrap();
+        assert_eq!(signature[0], 0x30);
+        assert!(public_key.signature("ec", b"other", fixed).is_err());
+    }
+
+    #[test]
+    fn debug_hides_pin() {
+        let source = Pkcs11KeySource {
+            module: PathBuf::from("/usr/lib/softhsm/libsofthsm2.so"),
+            slot: Some(0),
+            pin: Some("123456".to_owned()),
+            key_label: "tuf-root".to_owned(),
+        };
+        assert!(!format!("{source:?}").contains("123456"));
+    }
+}
@@ -3,35 +3,70 @@
 
 use aws_config::default_provider::credentials::DefaultCredentialsChain;
 use aws_config::default_provider::region::DefaultRegionChain;
-use aws_config::BehaviorVersion;
+use aws_config::{BehaviorVersion, SdkConfig};
+use aws_sdk_secretsmanager::Client as SecretsManagerClient;
 use aws_sdk_ssm::Client as SsmClient;
 use aws_smithy_experimental::hyper_1_0::{CryptoMode, HyperClientBuilder};
 use snafu::ResultExt;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, PoisonError};
 use std::thread;
 
 use crate::error::{self, Result};
 
 /// Builds an SSM client for a given profile name.
 pub(crate) fn build_client(profile: Option<&str>) -> Result<SsmClient> {
+    build(profile, SsmClient::new)
+}
+
+/// Builds a Secrets Manager client for a given profile name.
+pub(crate) fn build_secrets_manager_client(profile: Option<&str>) -> Result<SecretsManagerClient> {
+    build(profile, SecretsManagerClient::new)
+}
+
+/// The config loaded for each profile, so that the key sources in a process share the HTTP client
+/// and credentials of each profile, and only spin up a thread and runtime to load them once.
+static CONFIGS: OnceLock<Mutex<HashMap<Option<String>, SdkConfig>>> = OnceLock::new();
+
+/// Loads the AWS config for a given profile name, unless it was loaded before, and builds a
+/// client from it with `new`.
+fn build<C>(profile: Option<&str>, new: fn(&SdkConfig) -> C) -> Result<C> {
+    let configs = CONFIGS.get_or_init(Mutex::default);
     // We are cloning this so that we can send it across a thread boundary
     let profile = profile.map(|s| s.to_owned());
+    let cached = configs
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&profile)
+        .cloned();
+    if let Some(config) = cached {
+        return Ok(new(&config));
+    }
     // We need to spin up a new thread to deal with the async nature of the
     // AWS SDK Rust
-    let client: Result<SsmClient> = thread::spawn(move || {
+    let thread_profile = profile.clone();
+    let config: Result<SdkConfig> = thread::spawn(move || {
         let runtime = tokio::runtime::Runtime::new().context(error::RuntimeCreationSnafu)?;
-        Ok(runtime.block_on(async_build_client(profile)))
+        Ok(runtime.block_on(async_load_config(thread_profile)))
     })
     .join()
     .map_err(|_| error::Error::ThreadJoin {})?;
-    client
+    // If another caller loaded this profile meanwhile, keep the config it stored.
+    let config = configs
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(profile)
+        .or_insert(config?)
+        .clone();
+    Ok(new(&config))
 }
 
-async fn async_build_client(profile: Option<String>) -> SsmClient {
+async fn async_load_config(profile: Option<String>) -> SdkConfig {
     let http_client = HyperClientBuilder::new()
         .crypto_mode(CryptoMode::AwsLc) // Choose a crypto provider.
         .build_https();
     let config = aws_config::defaults(BehaviorVersion::v2024_03_28()).http_client(http_client);
-    let client_config = if let Some(profile) = profile {
+    if let Some(profile) = profile {
         let region = DefaultRegionChain::builder()
             .profile_name(&profile)
             .build()
@@ -49,6 +84,5 @@ async fn async_build_client(profile: Option<String>) -> SsmClient {
             .await
     } else {
         config.load().await
-    };
-    SsmClient::new(&client_config)
+    }
 }
@@ -64,4 +64,58 @@ pub enum Error {
             aws_sdk_ssm::error::SdkError<aws_sdk_ssm::operation::put_parameter::PutParameterError>,
         backtrace: Backtrace,
     },
+
+    #[snafu(display(
+        "Failed to get aws-secretsmanager://{}/{}: {}",
+        profile.as_deref().unwrap_or(""),
+        secret_id,
+        source.source().map_or("unknown".to_string(), std::string::ToString::to_string),
+    ))]
+    SecretsManagerGetSecretValue {
+        profile: Option<String>,
+        secret_id: String,
+        source: aws_sdk_secretsmanager::error::SdkError<
+            aws_sdk_secretsmanager::operation::get_secret_value::GetSecretValueError,
+        >,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Secrets Manager secret '{}' has neither a string nor a binary value",
+        secret_id
+    ))]
+    SecretsManagerMissingValue {
+        secret_id: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Failed to update aws-secretsmanager://{}/{}: {}",
+        profile.as_deref().unwrap_or(""),
+        secret_id,
+        source.source().map_or("unknown".to_string(), std::string::ToString::to_string),
+    ))]
+    SecretsManagerUpdateSecret {
+        profile: Option<String>,
+        secret_id: String,
+        source: aws_sdk_secretsmanager::error::SdkError<
+            aws_sdk_secretsmanager::operation::update_secret::UpdateSecretError,
+        >,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Failed to create aws-secretsmanager://{}/{}: {}",
+        profile.as_deref().unwrap_or(""),
+        secret_id,
+        source.source().map_or("unknown".to_string(), std::string::ToString::to_string),
+    ))]
+    SecretsManagerCreateSecret {
+        profile: Option<String>,
+        secret_id: String,
+        source: aws_sdk_secretsmanager::error::SdkError<
+            aws_sdk_secretsmanager::operation::create_secret::CreateSecretError,
+        >,
+        backtrace: Backtrace,
+    },
 }
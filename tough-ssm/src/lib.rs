@@ -1,8 +1,13 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+#![allow(clippy::result_large_err)]
+
 mod client;
 pub mod error;
+mod secrets_manager;
+
+pub use secrets_manager::SecretsManagerKeySource;
 
 use snafu::{OptionExt, ResultExt};
 use tough::async_trait;
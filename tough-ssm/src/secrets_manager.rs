@@ -0,0 +1,104 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{client, error};
+use snafu::{OptionExt, ResultExt};
+use tough::async_trait;
+use tough::key_source::KeySource;
+use tough::sign::{parse_keypair, Sign};
+
+/// Implements the KeySource trait for keys that live in AWS Secrets Manager.
+///
+/// The key is read from the secret's string value, or from its binary value if it has no string
+/// value.
+#[derive(Debug)]
+pub struct SecretsManagerKeySource {
+    pub profile: Option<String>,
+    /// The name or ARN of the secret.
+    pub secret_id: String,
+    /// The version of the secret to read. If neither this nor `version_stage` is set, the
+    /// `AWSCURRENT` version is read.
+    pub version_id: Option<String>,
+    /// The staging label of the version of the secret to read, such as `AWSPREVIOUS`.
+    pub version_stage: Option<String>,
+    /// The KMS key used to encrypt the secret when writing it. If it is not set, the secret keeps
+    /// its key, and new secrets use the `aws/secretsmanager` key.
+    pub kms_key_id: Option<String>,
+}
+
+/// Implements the KeySource trait.
+#[async_trait]
+impl KeySource for SecretsManagerKeySource {
+    async fn as_sign(
+        &self,
+    ) -> std::result::Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        let client = client::build_secrets_manager_client(self.profile.as_deref())?;
+        let response = client
+            .get_secret_value()
+            .secret_id(self.secret_id.to_owned())
+            .set_version_id(self.version_id.clone())
+            .set_version_stage(self.version_stage.clone())
+            .send()
+            .await
+            .context(error::SecretsManagerGetSecretValueSnafu {
+                profile: self.profile.clone(),
+                secret_id: &self.secret_id,
+            })?;
+        let data = response
+            .secret_string()
+            .map(str::as_bytes)
+            .or_else(|| response.secret_binary().map(|blob| blob.as_ref()))
+            .context(error::SecretsManagerMissingValueSnafu {
+                secret_id: &self.secret_id,
+            })?;
+        let sign = Box::new(parse_keypair(data).context(error::KeyPairParseSnafu)?);
+        Ok(sign)
+    }
+
+    /// Stores `value` as a new version of the secret, creating the secret if it doesn't exist.
+    /// The secret's description is set to `key_id_hex`.
+    async fn write(
+        &self,
+        value: &str,
+        key_id_hex: &str,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let client = client::build_secrets_manager_client(self.profile.as_deref())?;
+
+        let updated = client
+            .update_secret()
+            .secret_id(self.secret_id.to_owned())
+            .description(key_id_hex.to_owned())
+            .set_kms_key_id(self.kms_key_id.clone())
+            .secret_string(value.to_owned())
+            .send()
+            .await;
+        match updated {
+            Ok(_) => {}
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_resource_not_found_exception()) =>
+            {
+                client
+                    .create_secret()
+                    .name(self.secret_id.to_owned())
+                    .description(key_id_hex.to_owned())
+                    .set_kms_key_id(self.kms_key_id.clone())
+                    .secret_string(value.to_owned())
+                    .send()
+                    .await
+                    .context(error::SecretsManagerCreateSecretSnafu {
+                        profile: self.profile.clone(),
+                        secret_id: &self.secret_id,
+                    })?;
+            }
+            Err(err) => Err(err).context(error::SecretsManagerUpdateSecretSnafu {
+                profile: self.profile.clone(),
+                secret_id: &self.secret_id,
+            })?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides blocking versions of the methods that load a repository and read its targets, for
+//! callers that aren't async. Enabled with the `blocking` feature.
+//!
+//! The [`Transport`](crate::Transport) trait and the load pipeline are async from end to end, so
+//! these methods run the async methods to completion on a tokio runtime that is built on first use
+//! and then kept for the life of the process. Keeping one runtime lets a repository's transport,
+//! such as an [`HttpTransport`](crate::HttpTransport)'s connection pool, be reused between calls.
+//!
+//! Like tokio's `block_on`, each method panics if called from within a runtime; async callers
+//! should use the async methods directly. To control the runtime, use
+//! [`ToughContext::block_on`](crate::ToughContext::block_on) instead.
+
+use crate::error::Result;
+use crate::{FilesystemTransport, Prefix, Repository, RepositoryLoader, TargetName, ToughContext};
+use std::future::Future;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The context whose runtime the blocking methods share. Only its runtime is used; loaders and
+/// repositories bring their own transports.
+static CONTEXT: OnceLock<ToughContext> = OnceLock::new();
+
+/// Runs `future` to completion on the process-wide runtime, building it if needed.
+fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    CONTEXT
+        .get_or_init(|| ToughContext::with_transport(FilesystemTransport))
+        .block_on(future)
+}
+
+impl RepositoryLoader<'_> {
+    /// A blocking version of [`RepositoryLoader::load`].
+    pub fn load_blocking(self) -> Result<Repository> {
+        block_on(self.load())?
+    }
+}
+
+impl Repository {
+    /// A blocking version of [`Repository::read_target_bytes`].
+    pub fn read_target_bytes_blocking(
+        &self,
+        name: &TargetName,
+        max_size: u64,
+    ) -> Result<Option<Vec<u8>>> {
+        block_on(self.read_target_bytes(name, max_size))?
+    }
+
+    /// A blocking version of [`Repository::save_target`].
+    pub fn save_target_blocking<P>(
+        &self,
+        name: &TargetName,
+        outdir: P,
+        prepend: Prefix,
+    ) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        block_on(self.save_target(name, outdir, prepend))?
+    }
+}
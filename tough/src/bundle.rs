@@ -0,0 +1,159 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `MetadataBundle`, a single JSON document carrying a repository's current root,
+//! timestamp, snapshot and targets metadata (and optionally its delegated targets metadata), such
+//! as the director metadata bundles used in Uptane deployments.
+//!
+//! Each entry holds the metadata file exactly as it was signed and written, rather than as a
+//! parsed object, because snapshot and timestamp metadata may list the length and hashes of the
+//! files they refer to. A bundle is loaded with [`RepositoryLoader::metadata_bundle`], which serves
+//! the entries in place of files under the metadata base URL so that they are verified in the
+//! standard order.
+//!
+//! [`RepositoryLoader::metadata_bundle`]: crate::RepositoryLoader::metadata_bundle
+
+use crate::error::{self, Result};
+use crate::filename_encoding::{decode_non_ascii, role_filename, FilenameEncoding};
+use crate::schema::RoleType;
+use crate::transport::{Transport, TransportError, TransportErrorKind, TransportStream};
+use crate::Bytes;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use url::Url;
+
+/// The current metadata files of a repository, bundled into one document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataBundle {
+    /// The contents of the current root.json.
+    pub root: String,
+    /// The contents of timestamp.json.
+    pub timestamp: String,
+    /// The contents of snapshot.json.
+    pub snapshot: String,
+    /// The contents of targets.json.
+    pub targets: String,
+    /// The contents of each delegated targets role's metadata file, keyed by role name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub delegated_targets: BTreeMap<String, String>,
+}
+
+impl MetadataBundle {
+    /// Parses a bundle from its JSON form.
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).context(error::ParseBundleSnafu)
+    }
+
+    /// Serializes the bundle to its JSON form.
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut data = serde_json::to_vec_pretty(self).context(error::SerializeBundleSnafu)?;
+        data.push(b'\n');
+        Ok(data)
+    }
+}
+
+/// Only the version of a metadata file is needed to work out the names it may be fetched by.
+#[derive(Deserialize)]
+struct VersionOnly {
+    signed: VersionField,
+}
+
+#[derive(Deserialize)]
+struct VersionField {
+    version: u64,
+}
+
+fn version(role: RoleType, data: &str) -> Result<u64> {
+    let parsed: VersionOnly =
+        serde_json::from_str(data).context(error::ParseMetadataSnafu { role })?;
+    Ok(parsed.signed.version)
+}
+
+/// A `Transport` that serves the files of a `MetadataBundle` for URLs under the metadata base URL
+/// and passes every other URL, such as those of targets, to the wrapped transport.
+#[derive(Debug, Clone)]
+pub(crate) struct BundleTransport {
+    metadata_base_url: Url,
+    files: Arc<HashMap<String, Bytes>>,
+    inner: Box<dyn Transport + Send + Sync>,
+}
+
+impl BundleTransport {
+    /// `metadata_base_url` must end with a slash. Each entry is served under the names the client
+    /// requests it by, both with and without a consistent snapshot version prefix, with delegated
+    /// role names encoded by `filename_encoding`.
+    pub(crate) fn new(
+        bundle: MetadataBundle,
+        metadata_base_url: Url,
+        filename_encoding: &dyn FilenameEncoding,
+        inner: Box<dyn Transport + Send + Sync>,
+    ) -> Result<Self> {
+        let mut files = HashMap::new();
+        let root_version = version(RoleType::Root, &bundle.root)?;
+        files.insert(
+            format!("{root_version}.root.json"),
+            Bytes::from(bundle.root),
+        );
+        files.insert("timestamp.json".to_owned(), Bytes::from(bundle.timestamp));
+
+        let mut insert = |role: RoleType, name: &str, data: String| -> Result<()> {
+            let version = version(role, &data)?;
+            let data = Bytes::from(data);
+            files.insert(format!("{version}.{name}"), data.clone());
+            files.insert(name.to_owned(), data);
+            Ok(())
+        };
+        insert(RoleType::Snapshot, "snapshot.json", bundle.snapshot)?;
+        insert(RoleType::Targets, "targets.json", bundle.targets)?;
+        for (name, data) in bundle.delegated_targets {
+            let filename = role_filename(filename_encoding, &name, None)?;
+            insert(RoleType::DelegatedTargets, &filename, data)?;
+        }
+
+        Ok(Self {
+            metadata_base_url,
+            files: Arc::new(files),
+            inner,
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for BundleTransport {
+    async fn fetch(&self, url: Url) -> std::result::Result<TransportStream, TransportError> {
+        let Some(name) = url.as_str().strip_prefix(self.metadata_base_url.as_str()) else {
+            return self.inner.fetch(url).await;
+        };
+        // Non-ASCII characters in a filename are percent-encoded in the URL.
+        let data = self.files.get(name).or_else(|| {
+            decode_non_ascii(name).and_then(|decoded| self.files.get(decoded.as_str()))
+        });
+        match data {
+            Some(data) => {
+                Ok(futures::stream::once(futures::future::ready(Ok(data.clone()))).boxed())
+            }
+            None => Err(TransportError::new(TransportErrorKind::FileNotFound, url)),
+        }
+    }
+
+    /// Targets are fetched by the wrapped transport, so their downloads can be resumed if it
+    /// supports that. Metadata from the bundle can only be read whole.
+    async fn fetch_range(
+        &self,
+        url: Url,
+        offset: u64,
+    ) -> std::result::Result<TransportStream, TransportError> {
+        match url.as_str().strip_prefix(self.metadata_base_url.as_str()) {
+            None => self.inner.fetch_range(url, offset).await,
+            Some(_) if offset == 0 => self.fetch(url).await,
+            Some(_) => Err(TransportError::new(
+                TransportErrorKind::RangeNotSupported,
+                url,
+            )),
+        }
+    }
+}
@@ -1,18 +1,93 @@
 use crate::error::{self, Result};
-use crate::fetch::{fetch_max_size, fetch_sha256};
-use crate::schema::{RoleType, Target};
-use crate::transport::IntoVec;
-use crate::{encode_filename, Prefix, Repository, TargetName};
+use crate::fetch::{check_digests, fetch_digests_from, fetch_max_size};
+use crate::filename_encoding::role_filename;
+use crate::io::OnExceeded;
+use crate::schema::Target;
+use crate::transport::{IntoVec, TransportStream};
+use crate::{Prefix, Repository, TargetName};
+use aws_lc_rs::digest::Context;
 use bytes::Bytes;
 use futures::StreamExt;
 use futures_core::stream::BoxStream;
-use snafu::{futures::TryStreamExt, OptionExt, ResultExt};
-use std::path::Path;
-use tokio::io::AsyncWriteExt;
+use serde::Deserialize;
+use snafu::{ensure, futures::TryStreamExt, OptionExt, ResultExt};
+use std::collections::HashSet;
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use url::Url;
+use walkdir::WalkDir;
+
+/// Where [`Repository::cache_to`] writes a cached repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheLayout {
+    /// Metadata is written to `metadata_dir` and targets to `targets_dir`, as
+    /// [`Repository::cache`] writes them. Every version of root.json is only written if
+    /// `root_chain` is set.
+    Directories {
+        /// The directory where metadata files are saved.
+        metadata_dir: PathBuf,
+        /// The directory where targets files are saved.
+        targets_dir: PathBuf,
+        /// Whether to cache all versions of root.json.
+        root_chain: bool,
+    },
+
+    /// A repository that a plain web server can serve as it is. The metadata, including every
+    /// version of root.json, is written to `metadata/` in the given directory, and the targets to
+    /// `targets/`, named the way the loader fetches them. `DIR/metadata/` and `DIR/targets/` can
+    /// then be used as the metadata and targets URLs.
+    Servable(PathBuf),
+}
+
+impl CacheLayout {
+    /// The directory metadata files are written to.
+    pub fn metadata_dir(&self) -> PathBuf {
+        match self {
+            CacheLayout::Directories { metadata_dir, .. } => metadata_dir.clone(),
+            CacheLayout::Servable(dir) => dir.join("metadata"),
+        }
+    }
+
+    /// The directory targets files are written to.
+    pub fn targets_dir(&self) -> PathBuf {
+        match self {
+            CacheLayout::Directories { targets_dir, .. } => targets_dir.clone(),
+            CacheLayout::Servable(dir) => dir.join("targets"),
+        }
+    }
+
+    /// Whether all versions of root.json are written.
+    fn root_chain(&self) -> bool {
+        match self {
+            CacheLayout::Directories { root_chain, .. } => *root_chain,
+            CacheLayout::Servable(_) => true,
+        }
+    }
+}
+
+/// What [`Repository::cache_delta`] did to the cached targets.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheDelta {
+    /// Targets that weren't cached, or whose cached copy didn't match the repository, and were
+    /// downloaded.
+    pub downloaded: Vec<TargetName>,
+    /// Targets whose cached copy matched the repository and was kept.
+    pub unchanged: Vec<TargetName>,
+    /// Files that were removed from the targets directory because the repository doesn't list
+    /// them.
+    pub pruned: Vec<PathBuf>,
+}
 
 impl Repository {
     /// Cache an entire or partial repository to disk, including all required metadata.
-    /// The cached repo will be local, using filesystem paths.
+    /// The cached repo will be local, using filesystem paths, so a client using
+    /// [`FilesystemTransport`](crate::FilesystemTransport) can load it as a mirror.
+    ///
+    /// The metadata written is the metadata this repository verified when it was loaded, rather
+    /// than whatever the repository serves now, and each target is verified as it's fetched.
+    /// Delegated roles skipped under [`MissingRolePolicy::SkipWithWarning`](crate::MissingRolePolicy)
+    /// are left out.
     ///
     /// * `metadata_outdir` is the directory where cached metadata files will be saved.
     /// * `targets_outdir` is the directory where cached targets files will be saved.
@@ -31,35 +106,111 @@ impl Repository {
         P2: AsRef<Path>,
         S: AsRef<str>,
     {
+        let layout = CacheLayout::Directories {
+            metadata_dir: metadata_outdir.as_ref().to_owned(),
+            targets_dir: targets_outdir.as_ref().to_owned(),
+            root_chain: cache_root_chain,
+        };
+        self.cache_to(&layout, targets_subset).await
+    }
+
+    /// Cache an entire or partial repository to disk in the given [`CacheLayout`], as
+    /// [`Repository::cache`] does. [`CacheLayout::Servable`] writes a repository that can be
+    /// served by a plain web server right away.
+    ///
+    /// * `targets_subset` is the list of targets to include in the cached repo. If no subset is
+    ///   specified (`None`), then *all* targets are included in the cache.
+    pub async fn cache_to<S>(
+        &self,
+        layout: &CacheLayout,
+        targets_subset: Option<&[S]>,
+    ) -> Result<()>
+    where
+        S: AsRef<str>,
+    {
+        self.cache_layout(layout, targets_subset, None).await
+    }
+
+    /// Update a repository cached with [`Repository::cache_to`], such as a previous clone, in
+    /// the given [`CacheLayout`]. Targets whose cached copy already has the length and sha256
+    /// digest this repository lists are kept rather than downloaded again; other targets are
+    /// downloaded, and the metadata is rewritten.
+    ///
+    /// * `targets_subset` is the list of targets to include in the cached repo. If no subset is
+    ///   specified (`None`), then *all* targets are included in the cache.
+    /// * `prune` removes files from the targets directory that aren't a target this repository
+    ///   lists, by the name it would be cached under, such as targets removed from the repository
+    ///   or replaced with new versions. Directories are left in place.
+    pub async fn cache_delta<S>(
+        &self,
+        layout: &CacheLayout,
+        targets_subset: Option<&[S]>,
+        prune: bool,
+    ) -> Result<CacheDelta>
+    where
+        S: AsRef<str>,
+    {
+        let mut delta = CacheDelta::default();
+        self.cache_layout(layout, targets_subset, Some(&mut delta))
+            .await?;
+        if prune {
+            delta.pruned = self.prune_targets(&layout.targets_dir()).await?;
+        }
+        Ok(delta)
+    }
+
+    /// Caches the repository in `layout`. If `delta` is given, targets that are already cached
+    /// are kept, and each target is recorded in `delta`.
+    async fn cache_layout<S>(
+        &self,
+        layout: &CacheLayout,
+        targets_subset: Option<&[S]>,
+        mut delta: Option<&mut CacheDelta>,
+    ) -> Result<()>
+    where
+        S: AsRef<str>,
+    {
+        let metadata_outdir = layout.metadata_dir();
+        let targets_outdir = layout.targets_dir();
+
         // Create the output directories if the do not exist.
-        tokio::fs::create_dir_all(metadata_outdir.as_ref())
-            .await
-            .context(error::CacheDirectoryCreateSnafu {
-                path: metadata_outdir.as_ref(),
-            })?;
-        tokio::fs::create_dir_all(targets_outdir.as_ref())
-            .await
-            .context(error::CacheDirectoryCreateSnafu {
-                path: targets_outdir.as_ref(),
-            })?;
+        tokio::fs::create_dir_all(&metadata_outdir).await.context(
+            error::CacheDirectoryCreateSnafu {
+                path: &metadata_outdir,
+            },
+        )?;
+        tokio::fs::create_dir_all(&targets_outdir).await.context(
+            error::CacheDirectoryCreateSnafu {
+                path: &targets_outdir,
+            },
+        )?;
 
         // Fetch targets and save them to the outdir
-        if let Some(target_list) = targets_subset {
-            for raw_name in target_list {
-                let target_name = TargetName::new(raw_name.as_ref())?;
-                self.cache_target(&targets_outdir, &target_name).await?;
-            }
+        let target_names = if let Some(target_list) = targets_subset {
+            target_list
+                .iter()
+                .map(|raw_name| TargetName::new(raw_name.as_ref()))
+                .collect::<Result<Vec<_>>>()?
         } else {
-            let targets = &self.targets.signed.targets_map();
-            for target_name in targets.keys() {
-                self.cache_target(&targets_outdir, target_name).await?;
+            self.targets.signed.targets_map().into_keys().collect()
+        };
+        for target_name in target_names {
+            let Some(delta) = delta.as_deref_mut() else {
+                self.cache_target(&targets_outdir, &target_name).await?;
+                continue;
+            };
+            if self.is_cached(&targets_outdir, &target_name).await {
+                delta.unchanged.push(target_name);
+            } else {
+                self.cache_target(&targets_outdir, &target_name).await?;
+                delta.downloaded.push(target_name);
             }
         }
 
         // Cache all metadata
         self.cache_metadata_impl(&metadata_outdir).await?;
 
-        if cache_root_chain {
+        if layout.root_chain() {
             self.cache_root_chain(&metadata_outdir).await?;
         }
         Ok(())
@@ -67,6 +218,7 @@ impl Repository {
 
     /// Cache only a repository's metadata files (snapshot, targets, timestamp), including any
     /// delegated targets metadata.  The cached files will be saved to the local filesystem.
+    /// As with [`Repository::cache`], the files written are the ones verified at load time.
     ///
     /// * `metadata_outdir` is the directory where cached metadata files will be saved.
     /// * `cache_root_chain` specifies whether or not we will cache all versions of `root.json`.
@@ -94,35 +246,38 @@ impl Repository {
     where
         P: AsRef<Path>,
     {
-        self.cache_file_from_transport(
+        self.cache_trusted_file(
+            "snapshot.json",
+            self.snapshot.signed.version,
             self.snapshot_filename().as_str(),
-            self.max_snapshot_size()?
-                .unwrap_or(self.limits.max_snapshot_size),
-            "timestamp.json",
             &metadata_outdir,
         )
         .await?;
-        self.cache_file_from_transport(
+        self.cache_trusted_file(
+            "targets.json",
+            self.targets.signed.version,
             self.targets_filename().as_str(),
-            self.limits.max_targets_size,
-            "max_targets_size argument",
             &metadata_outdir,
         )
         .await?;
-        self.cache_file_from_transport(
+        self.cache_trusted_file(
+            "timestamp.json",
+            self.timestamp.signed.version,
             "timestamp.json",
-            self.limits.max_timestamp_size,
-            "max_timestamp_size argument",
             &metadata_outdir,
         )
         .await?;
 
         for name in self.targets.signed.role_names() {
-            if let Some(filename) = self.delegated_filename(name) {
-                self.cache_file_from_transport(
+            // Skipped roles have no trusted metadata to cache.
+            let Ok(role) = self.targets.signed.delegated_targets(name) else {
+                continue;
+            };
+            if let Some(filename) = self.delegated_filename(name)? {
+                self.cache_trusted_file(
+                    filename.as_str(),
+                    role.signed.version,
                     filename.as_str(),
-                    self.limits.max_targets_size,
-                    "max_targets_size argument",
                     &metadata_outdir,
                 )
                 .await?;
@@ -132,6 +287,40 @@ impl Repository {
         Ok(())
     }
 
+    /// Copies the metadata file that loading the repository verified and stored in the datastore
+    /// as `stored` to `outdir` as `filename`, after checking that the stored file is still the
+    /// `version` this repository trusts.
+    async fn cache_trusted_file<P: AsRef<Path>>(
+        &self,
+        stored: &str,
+        version: NonZeroU64,
+        filename: &str,
+        outdir: P,
+    ) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            signed: Version,
+        }
+        #[derive(Deserialize)]
+        struct Version {
+            version: NonZeroU64,
+        }
+
+        let data = self
+            .datastore
+            .bytes(stored)
+            .await?
+            .filter(|data| {
+                serde_json::from_slice::<Versioned>(data)
+                    .is_ok_and(|versioned| versioned.signed.version == version)
+            })
+            .context(error::CacheMetadataChangedSnafu { file: stored })?;
+        let outpath = outdir.as_ref().join(filename);
+        tokio::fs::write(&outpath, data)
+            .await
+            .context(error::CacheFileWriteSnafu { path: outpath })
+    }
+
     /// Cache all versions of root.json less than or equal to the current version.
     async fn cache_root_chain<P>(&self, outdir: P) -> Result<()>
     where
@@ -169,20 +358,16 @@ impl Repository {
     }
 
     /// Prepends the version number to the role.json filename if using consistent snapshot mode.
-    fn delegated_filename(&self, name: &str) -> Option<String> {
-        if self.root.signed.consistent_snapshot {
-            Some(format!(
-                "{}.{}.json",
-                self.snapshot
-                    .signed
-                    .meta
-                    .get(&format!("{name}.json"))?
-                    .version,
-                encode_filename(name)
-            ))
+    fn delegated_filename(&self, name: &str) -> Result<Option<String>> {
+        let version = if self.root.signed.consistent_snapshot {
+            match self.snapshot.signed.meta.get(&format!("{name}.json")) {
+                Some(meta) => Some(meta.version),
+                None => return Ok(None),
+            }
         } else {
-            Some(format!("{}.json", encode_filename(name)))
-        }
+            None
+        };
+        role_filename(self.filename_encoding.as_ref(), name, version).map(Some)
     }
 
     /// Copies a file using `Transport` to `outdir`.
@@ -237,46 +422,144 @@ impl Repository {
         .await
     }
 
-    /// Gets the max size of the snapshot.json file as specified by the timestamp file.
-    fn max_snapshot_size(&self) -> Result<Option<u64>> {
-        let snapshot_meta =
-            self.timestamp()
-                .signed
-                .meta
-                .get("snapshot.json")
-                .context(error::MetaMissingSnafu {
-                    file: "snapshot.json",
-                    role: RoleType::Timestamp,
-                })?;
-        Ok(snapshot_meta.length)
+    /// Whether the target `name` is already in `outdir`, under the name `cache_target` would give
+    /// it, with the length and sha256 digest this repository lists for it.
+    async fn is_cached(&self, outdir: &Path, name: &TargetName) -> bool {
+        let Ok(target) = self.targets.signed.find_target(name) else {
+            return false;
+        };
+        let path = outdir.join(self.target_filename(target, name));
+        let Ok(mut file) = tokio::fs::File::open(&path).await else {
+            return false;
+        };
+        if !file
+            .metadata()
+            .await
+            .is_ok_and(|metadata| metadata.is_file() && metadata.len() == target.length)
+        {
+            return false;
+        }
+        let Some((algorithm, digest)) = target.hashes.name_digest() else {
+            return false;
+        };
+        let mut context = Context::new(algorithm.algorithm());
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            match file.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(read) => context.update(&buf[..read]),
+                Err(_) => return false,
+            }
+        }
+        context.finish().as_ref() == digest.as_slice()
     }
 
-    /// Prepends the target digest to the name if using consistent snapshots. Returns both the
-    /// digest and the filename.
-    pub(crate) fn target_digest_and_filename(
-        &self,
-        target: &Target,
-        name: &TargetName,
-    ) -> (Vec<u8>, String) {
-        let sha256 = &target.hashes.sha256.clone().into_vec();
+    /// Removes each file under `targets_outdir` that isn't the cached copy of a target this
+    /// repository lists, and returns their paths.
+    async fn prune_targets(&self, targets_outdir: &Path) -> Result<Vec<PathBuf>> {
+        let listed: HashSet<PathBuf> = self
+            .targets
+            .signed
+            .targets_map()
+            .iter()
+            .map(|(name, target)| targets_outdir.join(self.target_filename(target, name)))
+            .collect();
+        let mut pruned = Vec::new();
+        for entry in WalkDir::new(targets_outdir) {
+            let entry = entry.context(error::WalkDirSnafu {
+                directory: targets_outdir,
+            })?;
+            if entry.file_type().is_dir() || listed.contains(entry.path()) {
+                continue;
+            }
+            tokio::fs::remove_file(entry.path())
+                .await
+                .context(error::CacheTargetRemoveSnafu { path: entry.path() })?;
+            pruned.push(entry.into_path());
+        }
+        Ok(pruned)
+    }
+
+    /// Prepends the target digest to the name if using consistent snapshots.
+    pub(crate) fn target_filename(&self, target: &Target, name: &TargetName) -> String {
         if self.consistent_snapshot {
-            (
-                sha256.clone(),
-                format!("{}.{}", hex::encode(sha256), name.resolved()),
-            )
+            let digest = target
+                .hashes
+                .name_digest()
+                .map(|(_, digest)| digest)
+                .unwrap_or_default();
+            format!("{}.{}", hex::encode(digest), name.resolved())
         } else {
-            (sha256.clone(), name.resolved().to_owned())
+            name.resolved().to_owned()
         }
     }
 
     /// Fetches the signed target using `Transport`. Aborts with error if the fetched target is
-    /// larger than its signed size.
+    /// larger than its signed size, or doesn't match the hashes that the
+    /// [`VerificationPolicy`](crate::VerificationPolicy) requires.
     pub(crate) async fn fetch_target(
+        &self,
+        name: &TargetName,
+        target: &Target,
+        filename: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes>>> {
+        let digests = self.verification_policy.digests(&target.hashes, filename)?;
+        let url = self
+            .targets_base_url
+            .join(filename)
+            .with_context(|_| error::JoinUrlSnafu {
+                path: filename,
+                url: self.targets_base_url.clone(),
+            })?;
+        let stream = self
+            .transport
+            .fetch(url.clone())
+            .await
+            .with_context(|_| error::TransportSnafu { url: url.clone() })?;
+        let on_exceeded = self.on_length_exceeded(name, &url, target.length, 0);
+        Ok(check_digests(
+            stream,
+            &url,
+            target.length,
+            "targets.json",
+            &digests,
+            Some(on_exceeded),
+        )
+        .context(error::TransportSnafu { url })
+        .boxed())
+    }
+
+    /// Tells the [`RepositoryObserver`](crate::RepositoryObserver) when more of target `name` is
+    /// served from `url` than its listed `length`. The bytes received are counted from `offset`.
+    fn on_length_exceeded(
+        &self,
+        name: &TargetName,
+        url: &Url,
+        length: u64,
+        offset: u64,
+    ) -> OnExceeded {
+        let observer = self.observer.clone();
+        let name = name.clone();
+        let url = url.clone();
+        Box::new(move |received| {
+            observer.on_target_length_exceeded(
+                &name,
+                &url,
+                length,
+                offset.saturating_add(received),
+            );
+        })
+    }
+
+    /// Checks `stream`, contents of the target that were received some other way than from the
+    /// `Transport`, as [`fetch_target`](Self::fetch_target) checks what it fetches.
+    pub(crate) fn check_target(
         &self,
         target: &Target,
-        digest: &[u8],
         filename: &str,
+        stream: TransportStream,
     ) -> Result<BoxStream<'static, Result<Bytes>>> {
+        let digests = self.verification_policy.digests(&target.hashes, filename)?;
         let url = self
             .targets_base_url
             .join(filename)
@@ -284,12 +567,70 @@ impl Repository {
                 path: filename,
                 url: self.targets_base_url.clone(),
             })?;
-        Ok(fetch_sha256(
+        Ok(
+            check_digests(stream, &url, target.length, "targets.json", &digests, None)
+                .context(error::TransportSnafu { url })
+                .boxed(),
+        )
+    }
+
+    /// Like [`fetch_target`](Self::fetch_target), but first reads the beginning of the target
+    /// back from `downloaded` and only fetches what comes after it.
+    pub(crate) async fn fetch_target_from<R>(
+        &self,
+        name: &TargetName,
+        target: &Target,
+        filename: &str,
+        mut downloaded: R,
+    ) -> Result<BoxStream<'static, Result<Bytes>>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let digests = self.verification_policy.digests(&target.hashes, filename)?;
+        let url = self
+            .targets_base_url
+            .join(filename)
+            .with_context(|_| error::JoinUrlSnafu {
+                path: filename,
+                url: self.targets_base_url.clone(),
+            })?;
+
+        let mut contexts: Vec<_> = digests
+            .into_iter()
+            .map(|(algorithm, digest)| (Context::new(algorithm.algorithm()), digest))
+            .collect();
+        let mut offset = 0_u64;
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let read = downloaded
+                .read(&mut buf)
+                .await
+                .with_context(|_| error::ResumeTargetReadSnafu { name: name.clone() })?;
+            if read == 0 {
+                break;
+            }
+            for (context, _) in &mut contexts {
+                context.update(&buf[..read]);
+            }
+            offset = offset.saturating_add(read as u64);
+            ensure!(
+                offset <= target.length,
+                error::MaxSizeExceededSnafu {
+                    max_size: target.length,
+                    specifier: "targets.json",
+                }
+            );
+        }
+
+        let on_exceeded = self.on_length_exceeded(name, &url, target.length, offset);
+        Ok(fetch_digests_from(
             self.transport.as_ref(),
             url.clone(),
+            offset,
             target.length,
             "targets.json",
-            digest,
+            contexts,
+            on_exceeded,
         )
         .await?
         .context(error::TransportSnafu { url })
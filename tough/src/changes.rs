@@ -0,0 +1,218 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Reports what changed in a repository since the previous load that used the same datastore.
+//!
+//! At the end of each successful load, the version of every role and a fingerprint of every
+//! target's metadata are recorded in the datastore. The next load compares against that record, so
+//! an updater can act only on the roles and targets that changed.
+
+use crate::datastore::Datastore;
+use crate::error::Result;
+use crate::schema::{RoleId, RoleType, Root, Signed, Snapshot, Target, Targets, Timestamp};
+use crate::TargetName;
+use aws_lc_rs::digest::{digest, SHA256};
+use log::warn;
+use olpc_cjson::CanonicalFormatter;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The datastore file recording the state of the previous load.
+const LOAD_STATE: &str = "last_load.json";
+
+/// What changed between the previous load and this one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepositoryChanges {
+    /// `false` if the datastore had no record of a previous load, in which case every role and
+    /// target is reported as added.
+    pub previous_load: bool,
+    /// The roles whose version changed, including delegated roles that were added or removed.
+    pub roles: Vec<RoleChange>,
+    /// Targets that are listed now but weren't before.
+    pub added_targets: Vec<TargetName>,
+    /// Targets that were listed before but aren't now.
+    pub removed_targets: Vec<TargetName>,
+    /// Targets whose length, hashes or custom metadata changed.
+    pub modified_targets: Vec<TargetName>,
+}
+
+impl RepositoryChanges {
+    /// Returns `true` if nothing changed since the previous load.
+    pub fn is_empty(&self) -> bool {
+        self.roles.is_empty()
+            && self.added_targets.is_empty()
+            && self.removed_targets.is_empty()
+            && self.modified_targets.is_empty()
+    }
+
+    /// Returns `true` if `role` changed version since the previous load.
+    pub fn role_changed(&self, role: &RoleId) -> bool {
+        self.roles.iter().any(|change| &change.role == role)
+    }
+}
+
+/// A role whose version differs from the previous load.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleChange {
+    /// The role that changed.
+    pub role: RoleId,
+    /// The version at the previous load, or `None` if the role is new.
+    pub previous_version: Option<u64>,
+    /// The version now, or `None` if the delegated role was removed.
+    pub current_version: Option<u64>,
+}
+
+/// The record of a load that is kept in the datastore.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct LoadState {
+    root: u64,
+    timestamp: u64,
+    snapshot: u64,
+    targets: u64,
+    delegated_roles: BTreeMap<String, u64>,
+    /// The sha256 of the canonical form of each target's metadata.
+    target_fingerprints: BTreeMap<TargetName, String>,
+}
+
+impl LoadState {
+    pub(crate) fn new(
+        root: &Signed<Root>,
+        timestamp: &Signed<Timestamp>,
+        snapshot: &Signed<Snapshot>,
+        targets: &Signed<Targets>,
+    ) -> Self {
+        let mut delegated_roles = BTreeMap::new();
+        delegated_versions(&targets.signed, &mut delegated_roles);
+        Self {
+            root: root.signed.version.get(),
+            timestamp: timestamp.signed.version.get(),
+            snapshot: snapshot.signed.version.get(),
+            targets: targets.signed.version.get(),
+            delegated_roles,
+            target_fingerprints: targets
+                .signed
+                .targets_iter()
+                .map(|(name, target)| (name.clone(), fingerprint(target)))
+                .collect(),
+        }
+    }
+
+    /// Reads the state of the previous load. A record that can't be parsed is treated as missing,
+    /// since it only affects what is reported as changed.
+    pub(crate) async fn read(datastore: &Datastore) -> Result<Option<Self>> {
+        Ok(datastore.bytes(LOAD_STATE).await?.and_then(|bytes| {
+            serde_json::from_slice(&bytes)
+                .map_err(|err| warn!("ignoring unreadable {LOAD_STATE} in datastore: {err}"))
+                .ok()
+        }))
+    }
+
+    /// The hex-encoded sha256 of the canonical JSON of the role versions, an object with the
+    /// members `root`, `timestamp`, `snapshot`, `targets`, and `delegated_roles`, which maps the
+    /// name of each loaded delegated role to its version.
+    pub(crate) fn release_fingerprint(&self) -> String {
+        #[derive(Serialize)]
+        struct RoleVersions<'a> {
+            root: u64,
+            timestamp: u64,
+            snapshot: u64,
+            targets: u64,
+            delegated_roles: &'a BTreeMap<String, u64>,
+        }
+
+        let mut data = Vec::new();
+        let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
+        // The versions are integers and strings, so serializing them can't fail.
+        let _ = RoleVersions {
+            root: self.root,
+            timestamp: self.timestamp,
+            snapshot: self.snapshot,
+            targets: self.targets,
+            delegated_roles: &self.delegated_roles,
+        }
+        .serialize(&mut ser);
+        hex::encode(digest(&SHA256, &data))
+    }
+
+    pub(crate) async fn write(&self, datastore: &Datastore) -> Result<()> {
+        datastore.create(LOAD_STATE, self).await
+    }
+
+    /// Compares this load with the `previous` one.
+    pub(crate) fn changes_since(&self, previous: Option<&LoadState>) -> RepositoryChanges {
+        let empty = LoadState::default();
+        let before = previous.unwrap_or(&empty);
+        let mut changes = RepositoryChanges {
+            previous_load: previous.is_some(),
+            ..RepositoryChanges::default()
+        };
+
+        for (role, old, new) in [
+            (RoleType::Root, before.root, self.root),
+            (RoleType::Timestamp, before.timestamp, self.timestamp),
+            (RoleType::Snapshot, before.snapshot, self.snapshot),
+            (RoleType::Targets, before.targets, self.targets),
+        ] {
+            if old != new {
+                changes.roles.push(RoleChange {
+                    role: RoleId::StandardRole(role),
+                    previous_version: Some(old).filter(|_| previous.is_some()),
+                    current_version: Some(new),
+                });
+            }
+        }
+        for (name, version) in &self.delegated_roles {
+            let old = before.delegated_roles.get(name).copied();
+            if old != Some(*version) {
+                changes.roles.push(RoleChange {
+                    role: RoleId::DelegatedRole(name.clone()),
+                    previous_version: old,
+                    current_version: Some(*version),
+                });
+            }
+        }
+        for (name, version) in &before.delegated_roles {
+            if !self.delegated_roles.contains_key(name) {
+                changes.roles.push(RoleChange {
+                    role: RoleId::DelegatedRole(name.clone()),
+                    previous_version: Some(*version),
+                    current_version: None,
+                });
+            }
+        }
+
+        for (name, fingerprint) in &self.target_fingerprints {
+            match before.target_fingerprints.get(name) {
+                None => changes.added_targets.push(name.clone()),
+                Some(old) if old != fingerprint => changes.modified_targets.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.removed_targets = before
+            .target_fingerprints
+            .keys()
+            .filter(|name| !self.target_fingerprints.contains_key(*name))
+            .cloned()
+            .collect();
+        changes
+    }
+}
+
+fn delegated_versions(targets: &Targets, versions: &mut BTreeMap<String, u64>) {
+    if let Some(delegations) = &targets.delegations {
+        for role in &delegations.roles {
+            if let Some(role_targets) = &role.targets {
+                versions.insert(role.name.clone(), role_targets.signed.version.get());
+                delegated_versions(&role_targets.signed, versions);
+            }
+        }
+    }
+}
+
+fn fingerprint(target: &Target) -> String {
+    let mut data = Vec::new();
+    let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
+    // A `Target` holds only JSON values, so serializing it can't fail.
+    let _ = target.serialize(&mut ser);
+    hex::encode(digest(&SHA256, &data))
+}
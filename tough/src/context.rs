@@ -0,0 +1,88 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `ToughContext`, which holds the objects that are expensive to build so that a process
+//! can build them once and share them between repository loads and edits.
+
+use crate::error::{self, Result};
+use crate::{DefaultTransport, Transport};
+use snafu::ResultExt;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use tokio::runtime::Runtime;
+
+/// Objects that are expensive to build, shared by the loads and edits they are passed to.
+///
+/// A context holds a [`Transport`], and so an [`HttpTransport`](crate::HttpTransport)'s connection
+/// pool, which [`RepositoryLoader::context`](crate::RepositoryLoader::context) and
+/// [`RepositoryEditor::context`](crate::editor::RepositoryEditor::context) use instead of building
+/// their own. For callers that aren't async, [`block_on`](Self::block_on) runs futures on a tokio
+/// runtime that the context builds on first use and then keeps.
+///
+/// Clones of a context share its transport and runtime.
+///
+/// ```
+/// # use tough::{FilesystemTransport, RepositoryLoader, ToughContext};
+/// # let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/tuf-reference-impl");
+/// # let root = std::fs::read(dir.join("metadata/root.json")).unwrap();
+/// # let metadata_base_url = url::Url::from_directory_path(dir.join("metadata")).unwrap();
+/// # let targets_base_url = url::Url::from_directory_path(dir.join("targets")).unwrap();
+/// let context = ToughContext::with_transport(FilesystemTransport);
+/// for _ in 0..2 {
+///     let loader = RepositoryLoader::new(&root, metadata_base_url.clone(), targets_base_url.clone())
+///         .context(&context);
+///     let repository = context.block_on(loader.load()).unwrap().unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ToughContext {
+    transport: Box<dyn Transport + Send + Sync>,
+    runtime: Arc<OnceLock<Runtime>>,
+}
+
+impl Default for ToughContext {
+    fn default() -> Self {
+        Self::with_transport(DefaultTransport::new())
+    }
+}
+
+impl ToughContext {
+    /// Creates a context that fetches with a [`DefaultTransport`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a context that fetches with `transport`.
+    pub fn with_transport<T: Transport + Send + Sync + 'static>(transport: T) -> Self {
+        Self {
+            transport: Box::new(transport),
+            runtime: Arc::default(),
+        }
+    }
+
+    /// The transport shared by the loads and edits this context is passed to.
+    pub fn transport(&self) -> &(dyn Transport + Send + Sync) {
+        self.transport.as_ref()
+    }
+
+    pub(crate) fn boxed_transport(&self) -> Box<dyn Transport + Send + Sync> {
+        self.transport.clone()
+    }
+
+    /// Runs `future` to completion on this context's runtime, building the runtime if this is the
+    /// first call. This is for callers that aren't async, and, like tokio's `block_on`, panics if
+    /// called from within a runtime.
+    pub fn block_on<F: Future>(&self, future: F) -> Result<F::Output> {
+        let runtime = if let Some(runtime) = self.runtime.get() {
+            runtime
+        } else {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .context(error::RuntimeCreateSnafu)?;
+            // If another thread raced us here, use the runtime it stored.
+            self.runtime.get_or_init(|| runtime)
+        };
+        Ok(runtime.block_on(future))
+    }
+}
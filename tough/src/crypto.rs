@@ -0,0 +1,239 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `CryptoBackend`, which abstracts over the library that verifies and makes signatures,
+//! and `crypto_mode`, which reports whether that library is running in FIPS mode, along with the
+//! `FipsKeyPolicy` that decides which key schemes that mode allows.
+
+use crate::error::Result;
+use crate::schema::key::Key;
+use crate::sign::Sign;
+use std::fmt::{self, Debug};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+mod aws_lc_backend;
+#[cfg(feature = "openssl")]
+mod openssl_backend;
+
+pub use aws_lc_backend::AwsLcBackend;
+#[cfg(feature = "openssl")]
+pub use openssl_backend::OpensslBackend;
+
+/// A cryptography library that can verify the signatures on TUF metadata and sign it.
+///
+/// tough uses the backend returned by [`crypto_backend`], which is chosen by cargo features. Each
+/// backend supports the same key schemes and key formats, so keys and signatures made with one
+/// can be used with another.
+pub trait CryptoBackend: Debug + Send + Sync {
+    /// A short name for the backend, e.g. `aws-lc-rs`.
+    fn name(&self) -> &'static str;
+
+    /// Whether the backend is running a FIPS validated module.
+    fn is_fips(&self) -> bool;
+
+    /// Returns whether `signature` is a valid signature of `msg` made with `key`.
+    fn verify(&self, key: &Key, msg: &[u8], signature: &[u8]) -> bool;
+
+    /// Parses a private key and returns an object that signs with it. The formats accepted are
+    /// those described for [`parse_keypair`](crate::sign::parse_keypair).
+    fn parse_keypair(&self, key: &[u8]) -> Result<Box<dyn Sign>>;
+}
+
+/// Returns the [`CryptoBackend`] tough verifies signatures and signs with.
+///
+/// This is [`AwsLcBackend`] unless tough is built with the `openssl` feature, in which case it is
+/// `OpensslBackend`. Digests are always computed with aws-lc-rs.
+pub fn crypto_backend() -> &'static dyn CryptoBackend {
+    #[cfg(feature = "openssl")]
+    {
+        &OpensslBackend
+    }
+    #[cfg(not(feature = "openssl"))]
+    {
+        &AwsLcBackend
+    }
+}
+
+/// The mode that tough's cryptography runs in, as reported by [`crypto_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CryptoMode {
+    /// The [`crypto_backend`] is not running in FIPS mode, and every key scheme tough knows is
+    /// allowed.
+    Default,
+    /// The [`crypto_backend`] is running a FIPS validated module. The key schemes allowed are
+    /// those of the [`FipsKeyPolicy`] set with [`set_fips_key_policy`].
+    Fips,
+}
+
+impl CryptoMode {
+    /// Whether signatures made with `key` can be verified, or made, in this mode.
+    pub fn allows_key(self, key: &Key) -> bool {
+        self.allows_key_with(fips_key_policy(), key)
+    }
+
+    /// Whether `key` is allowed in this mode under `policy`.
+    fn allows_key_with(self, policy: FipsKeyPolicy, key: &Key) -> bool {
+        match (self, policy) {
+            (CryptoMode::Default, _) | (CryptoMode::Fips, FipsKeyPolicy::AllowAll) => true,
+            (CryptoMode::Fips, FipsKeyPolicy::ApprovedOnly) => !matches!(key, Key::Ed25519 { .. }),
+        }
+    }
+}
+
+impl fmt::Display for CryptoMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CryptoMode::Default => "default",
+            CryptoMode::Fips => "FIPS",
+        })
+    }
+}
+
+/// Reports the active [`CryptoMode`].
+///
+/// This is [`CryptoMode::Fips`] only when the [`crypto_backend`] confirms that its FIPS module is
+/// in use, so a build that silently fell back to the default module isn't reported as FIPS. The
+/// backend is asked once, and its answer is reused for the life of the process.
+pub fn crypto_mode() -> CryptoMode {
+    static MODE: OnceLock<CryptoMode> = OnceLock::new();
+    *MODE.get_or_init(|| {
+        if crypto_backend().is_fips() {
+            CryptoMode::Fips
+        } else {
+            CryptoMode::Default
+        }
+    })
+}
+
+/// Which key schemes are allowed in [`CryptoMode::Fips`], as set with [`set_fips_key_policy`].
+/// Outside FIPS mode, every key scheme tough knows is allowed regardless of the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FipsKeyPolicy {
+    /// Every key scheme tough knows is allowed, so that repositories signed with Ed25519 keys
+    /// still load in FIPS builds.
+    #[default]
+    AllowAll,
+    /// Only FIPS approved key schemes are allowed: Ed25519 keys aren't trusted for signatures and
+    /// can't be used to sign.
+    ApprovedOnly,
+}
+
+static FIPS_APPROVED_KEYS_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Sets the [`FipsKeyPolicy`] for the rest of the process. Call this before loading or signing
+/// anything; it applies to every repository, editor, and key source in the process.
+pub fn set_fips_key_policy(policy: FipsKeyPolicy) {
+    FIPS_APPROVED_KEYS_ONLY.store(policy == FipsKeyPolicy::ApprovedOnly, Ordering::Relaxed);
+}
+
+/// Returns the [`FipsKeyPolicy`] set with [`set_fips_key_policy`], which is
+/// [`FipsKeyPolicy::AllowAll`] unless changed.
+pub fn fips_key_policy() -> FipsKeyPolicy {
+    if FIPS_APPROVED_KEYS_ONLY.load(Ordering::Relaxed) {
+        FipsKeyPolicy::ApprovedOnly
+    } else {
+        FipsKeyPolicy::AllowAll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::key::{Ed25519Key, Ed25519Scheme};
+    use std::collections::HashMap;
+
+    fn ed25519_key() -> Key {
+        Key::Ed25519 {
+            keyval: Ed25519Key {
+                public: vec![0; 32].into(),
+                _extra: HashMap::default(),
+            },
+            scheme: Ed25519Scheme::Ed25519,
+            _extra: HashMap::default(),
+        }
+    }
+
+    #[test]
+    fn fips_key_policy_forbids_ed25519() {
+        let key = ed25519_key();
+        for mode in [CryptoMode::Default, CryptoMode::Fips] {
+            assert!(mode.allows_key_with(FipsKeyPolicy::AllowAll, &key));
+        }
+        assert!(CryptoMode::Default.allows_key_with(FipsKeyPolicy::ApprovedOnly, &key));
+        assert!(!CryptoMode::Fips.allows_key_with(FipsKeyPolicy::ApprovedOnly, &key));
+    }
+
+    #[test]
+    #[cfg(not(feature = "fips"))]
+    fn default_mode_without_feature() {
+        assert_eq!(crypto_mode(), CryptoMode::Default);
+    }
+
+    /// Each private key format `parse_keypair` accepts, for each key scheme.
+    #[cfg(feature = "openssl")]
+    fn keypairs() -> Vec<Vec<u8>> {
+        use aws_lc_rs::rand::SystemRandom;
+        use aws_lc_rs::signature::{EcdsaKeyPair, Ed25519KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+        let rng = SystemRandom::new();
+        let ed25519 = Ed25519KeyPair::generate_pkcs8(&rng)
+            .unwrap()
+            .as_ref()
+            .to_vec();
+        let ecdsa = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+            .unwrap()
+            .as_ref()
+            .to_vec();
+        let data = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+        // An Ed25519 key generated by ring, whose PKCS#8 layout differs from aws-lc-rs's.
+        let ring_ed25519 = std::fs::read(data.join("targetskey")).unwrap();
+        let rsa = std::fs::read(data.join("snakeoil.pem")).unwrap();
+        let ecdsa_pem = pem::encode(&pem::Pem::new("PRIVATE KEY", ecdsa.clone())).into_bytes();
+        vec![ed25519, ring_ed25519, ecdsa, rsa, ecdsa_pem]
+    }
+
+    /// Signatures made by either backend verify with the other, and both backends describe a key
+    /// the same way.
+    #[tokio::test]
+    #[cfg(feature = "openssl")]
+    async fn backends_interoperate() {
+        use aws_lc_rs::rand::SystemRandom;
+
+        let backends: [&dyn CryptoBackend; 2] = [&AwsLcBackend, &OpensslBackend];
+        for keypair in keypairs() {
+            let tuf_keys: Vec<_> = backends
+                .iter()
+                .map(|backend| backend.parse_keypair(&keypair).unwrap().tuf_key())
+                .collect();
+            assert_eq!(tuf_keys[0], tuf_keys[1]);
+            let key = &tuf_keys[0];
+
+            for signer in backends {
+                let signature = signer
+                    .parse_keypair(&keypair)
+                    .unwrap()
+                    .sign(b"payload", &SystemRandom::new())
+                    .await
+                    .unwrap();
+                for verifier in backends {
+                    assert!(
+                        verifier.verify(key, b"payload", &signature),
+                        "{} didn't verify {}'s signature with {:?}",
+                        verifier.name(),
+                        signer.name(),
+                        key
+                    );
+                    assert!(!verifier.verify(key, b"other payload", &signature));
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn openssl_backend_refuses_unknown_keys() {
+        assert!(OpensslBackend.parse_keypair(b"not a key").is_err());
+        assert_eq!(crypto_backend().name(), "openssl");
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::crypto::CryptoBackend;
+use crate::error::{self, Result};
+use crate::schema::key::{EcdsaScheme, Ed25519Scheme, Key, RsaScheme};
+use crate::sign::{Sign, SignKeyPair};
+use aws_lc_rs::signature::{
+    EcdsaKeyPair, Ed25519KeyPair, RsaKeyPair, VerificationAlgorithm, ECDSA_P256_SHA256_ASN1_SIGNING,
+};
+use snafu::ResultExt;
+
+/// The [`CryptoBackend`] that uses aws-lc-rs, which is built with its FIPS validated module when
+/// tough is built with the `fips` feature.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AwsLcBackend;
+
+impl CryptoBackend for AwsLcBackend {
+    fn name(&self) -> &'static str {
+        "aws-lc-rs"
+    }
+
+    fn is_fips(&self) -> bool {
+        cfg!(feature = "fips") && aws_lc_rs::try_fips_mode().is_ok()
+    }
+
+    fn verify(&self, key: &Key, msg: &[u8], signature: &[u8]) -> bool {
+        let (alg, public_key): (&dyn VerificationAlgorithm, &[u8]) = match key {
+            Key::Ecdsa {
+                scheme: EcdsaScheme::EcdsaSha2Nistp256,
+                keyval,
+                ..
+            }
+            | Key::EcdsaOld {
+                scheme: EcdsaScheme::EcdsaSha2Nistp256,
+                keyval,
+                ..
+            } => (
+                &aws_lc_rs::signature::ECDSA_P256_SHA256_ASN1,
+                &keyval.public,
+            ),
+            Key::Ed25519 {
+                scheme: Ed25519Scheme::Ed25519,
+                keyval,
+                ..
+            } => (&aws_lc_rs::signature::ED25519, &keyval.public),
+            Key::Rsa {
+                scheme: RsaScheme::RsassaPssSha256,
+                keyval,
+                ..
+            } => (
+                &aws_lc_rs::signature::RSA_PSS_2048_8192_SHA256,
+                &keyval.public,
+            ),
+        };
+
+        alg.verify_sig(public_key, msg, signature).is_ok()
+    }
+
+    fn parse_keypair(&self, key: &[u8]) -> Result<Box<dyn Sign>> {
+        Ok(Box::new(parse_keypair(key)?))
+    }
+}
+
+fn parse_keypair(key: &[u8]) -> Result<SignKeyPair> {
+    if let Ok(ed25519_key_pair) = Ed25519KeyPair::from_pkcs8(key) {
+        Ok(SignKeyPair::ED25519(ed25519_key_pair))
+    } else if let Ok(ecdsa_key_pair) =
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, key)
+    {
+        Ok(SignKeyPair::ECDSA(ecdsa_key_pair))
+    } else if let Ok(pem) = pem::parse(key) {
+        match pem.tag() {
+            "PRIVATE KEY" => {
+                if let Ok(rsa_key_pair) = RsaKeyPair::from_pkcs8(pem.contents()) {
+                    Ok(SignKeyPair::RSA(rsa_key_pair))
+                } else if let Ok(ed25519_key_pair) = Ed25519KeyPair::from_pkcs8(pem.contents()) {
+                    Ok(SignKeyPair::ED25519(ed25519_key_pair))
+                } else if let Ok(ecdsa_key_pair) =
+                    EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pem.contents())
+                {
+                    Ok(SignKeyPair::ECDSA(ecdsa_key_pair))
+                } else {
+                    error::KeyUnrecognizedSnafu.fail()
+                }
+            }
+            "RSA PRIVATE KEY" => Ok(SignKeyPair::RSA(
+                RsaKeyPair::from_der(pem.contents()).context(error::KeyRejectedSnafu)?,
+            )),
+            _ => error::KeyUnrecognizedSnafu.fail(),
+        }
+    } else {
+        error::KeyUnrecognizedSnafu.fail()
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::crypto::CryptoBackend;
+use crate::error::{self, Result};
+use crate::schema::key::{
+    EcdsaKey, EcdsaScheme, Ed25519Key, Ed25519Scheme, Key, RsaKey, RsaScheme,
+};
+use crate::sign::Sign;
+use async_trait::async_trait;
+use aws_lc_rs::rand::SecureRandom;
+use openssl::bn::BigNumContext;
+use openssl::ec::{EcGroup, EcKey, EcPoint, PointConversionForm};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Private, Public};
+use openssl::rsa::{Padding, Rsa};
+use openssl::sign::{RsaPssSaltlen, Signer, Verifier};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+
+/// RSA keys are accepted in the same range of sizes as aws-lc-rs accepts them.
+const RSA_BITS: std::ops::RangeInclusive<u32> = 2048..=8192;
+
+/// The [`CryptoBackend`] that uses the system's OpenSSL, enabled with the `openssl` feature.
+///
+/// OpenSSL applies the restrictions of its FIPS provider itself when the provider is configured,
+/// but tough can't confirm that it is, so this backend never reports FIPS mode.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpensslBackend;
+
+impl CryptoBackend for OpensslBackend {
+    fn name(&self) -> &'static str {
+        "openssl"
+    }
+
+    fn is_fips(&self) -> bool {
+        false
+    }
+
+    fn verify(&self, key: &Key, msg: &[u8], signature: &[u8]) -> bool {
+        verify(key, msg, signature).unwrap_or(false)
+    }
+
+    fn parse_keypair(&self, key: &[u8]) -> Result<Box<dyn Sign>> {
+        let pkey = match pem::parse(key) {
+            Ok(pem) => match pem.tag() {
+                "PRIVATE KEY" => parse_pkcs8(pem.contents()),
+                "RSA PRIVATE KEY" => Rsa::private_key_from_der(pem.contents())
+                    .and_then(PKey::from_rsa)
+                    .ok(),
+                _ => None,
+            },
+            Err(_) => parse_pkcs8(key),
+        };
+        let keypair = pkey
+            .and_then(|pkey| OpensslKeyPair::new(pkey).ok().flatten())
+            .ok_or_else(|| error::KeyUnrecognizedSnafu.build())?;
+        Ok(Box::new(keypair))
+    }
+}
+
+/// The fields of a version 2 PKCS#8 document (RFC 5958) holding an Ed25519 key, from its version
+/// up to its 32 byte seed.
+const ED25519_PKCS8_V2_HEADER: &[u8] = &[
+    0x02, 0x01, 0x01, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+/// The length of a version 2 PKCS#8 Ed25519 document's contents, and what follows its seed up to
+/// its 32 byte public key. ring wraps the public key's BIT STRING in an explicit `[1]` tag, and
+/// aws-lc-rs tags it implicitly.
+const ED25519_PKCS8_V2_LAYOUTS: &[(u8, &[u8])] = &[
+    (0x53, &[0xa1, 0x23, 0x03, 0x21, 0x00]),
+    (0x51, &[0x81, 0x21, 0x00]),
+];
+
+/// Parses a PKCS#8 private key. OpenSSL doesn't accept the version 2 documents that ring and
+/// aws-lc-rs, and so `tuftool`, generate for Ed25519 keys, so those are read from their fixed
+/// layouts instead.
+fn parse_pkcs8(der: &[u8]) -> Option<PKey<Private>> {
+    PKey::private_key_from_pkcs8(der).ok().or_else(|| {
+        let (&[0x30, len], contents) = der.split_at_checked(2)? else {
+            return None;
+        };
+        let public_prefix = ED25519_PKCS8_V2_LAYOUTS
+            .iter()
+            .find_map(|&(layout_len, prefix)| (layout_len == len).then_some(prefix))?;
+        let rest = contents.strip_prefix(ED25519_PKCS8_V2_HEADER)?;
+        let (seed, rest) = rest.split_at_checked(32)?;
+        let public = rest.strip_prefix(public_prefix)?;
+        let pkey = PKey::private_key_from_raw_bytes(seed, Id::ED25519).ok()?;
+        (usize::from(len) == contents.len() && pkey.raw_public_key().ok()? == public)
+            .then_some(pkey)
+    })
+}
+
+fn verify(key: &Key, msg: &[u8], signature: &[u8]) -> std::result::Result<bool, ErrorStack> {
+    match key {
+        Key::Ecdsa {
+            scheme: EcdsaScheme::EcdsaSha2Nistp256,
+            keyval,
+            ..
+        }
+        | Key::EcdsaOld {
+            scheme: EcdsaScheme::EcdsaSha2Nistp256,
+            keyval,
+            ..
+        } => {
+            let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+            let mut ctx = BigNumContext::new()?;
+            let point = EcPoint::from_bytes(&group, &keyval.public, &mut ctx)?;
+            let pkey = PKey::from_ec_key(EcKey::from_public_key(&group, &point)?)?;
+            let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey)?;
+            verifier.verify_oneshot(signature, msg)
+        }
+        Key::Ed25519 {
+            scheme: Ed25519Scheme::Ed25519,
+            keyval,
+            ..
+        } => {
+            let pkey = PKey::public_key_from_raw_bytes(&keyval.public, Id::ED25519)?;
+            let mut verifier = Verifier::new_without_digest(&pkey)?;
+            verifier.verify_oneshot(signature, msg)
+        }
+        Key::Rsa {
+            scheme: RsaScheme::RsassaPssSha256,
+            keyval,
+            ..
+        } => {
+            let rsa = Rsa::public_key_from_der_pkcs1(&keyval.public)?;
+            if !RSA_BITS.contains(&rsa.n().num_bits().unsigned_abs()) {
+                return Ok(false);
+            }
+            let pkey: PKey<Public> = PKey::from_rsa(rsa)?;
+            let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey)?;
+            verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+            verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+            verifier.set_rsa_mgf1_md(MessageDigest::sha256())?;
+            verifier.verify_oneshot(signature, msg)
+        }
+    }
+}
+
+/// A private key held by OpenSSL, along with its public key as it appears in TUF metadata.
+struct OpensslKeyPair {
+    pkey: PKey<Private>,
+    key: Key,
+}
+
+impl Debug for OpensslKeyPair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpensslKeyPair")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OpensslKeyPair {
+    /// Returns `None` for keys of a type or size that tough doesn't sign with.
+    fn new(pkey: PKey<Private>) -> std::result::Result<Option<Self>, ErrorStack> {
+        let key = match pkey.id() {
+            Id::RSA => {
+                let rsa = pkey.rsa()?;
+                if !RSA_BITS.contains(&rsa.n().num_bits().unsigned_abs()) {
+                    return Ok(None);
+                }
+                Key::Rsa {
+                    keyval: RsaKey {
+                        public: rsa.public_key_to_der_pkcs1()?.into(),
+                        _extra: HashMap::new(),
+                    },
+                    scheme: RsaScheme::RsassaPssSha256,
+                    _extra: HashMap::new(),
+                }
+            }
+            Id::ED25519 => Key::Ed25519 {
+                keyval: Ed25519Key {
+                    public: pkey.raw_public_key()?.into(),
+                    _extra: HashMap::new(),
+                },
+                scheme: Ed25519Scheme::Ed25519,
+                _extra: HashMap::new(),
+            },
+            Id::EC => {
+                let ec = pkey.ec_key()?;
+                if ec.group().curve_name() != Some(Nid::X9_62_PRIME256V1) {
+                    return Ok(None);
+                }
+                let mut ctx = BigNumContext::new()?;
+                let public = ec.public_key().to_bytes(
+                    ec.group(),
+                    PointConversionForm::UNCOMPRESSED,
+                    &mut ctx,
+                )?;
+                Key::Ecdsa {
+                    keyval: EcdsaKey {
+                        public: public.into(),
+                        _extra: HashMap::new(),
+                    },
+                    scheme: EcdsaScheme::EcdsaSha2Nistp256,
+                    _extra: HashMap::new(),
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(Self { pkey, key }))
+    }
+
+    fn sign(&self, msg: &[u8]) -> std::result::Result<Vec<u8>, ErrorStack> {
+        match self.key {
+            Key::Rsa { .. } => {
+                let mut signer = Signer::new(MessageDigest::sha256(), &self.pkey)?;
+                signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+                signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+                signer.set_rsa_mgf1_md(MessageDigest::sha256())?;
+                signer.sign_oneshot_to_vec(msg)
+            }
+            Key::Ed25519 { .. } => Signer::new_without_digest(&self.pkey)?.sign_oneshot_to_vec(msg),
+            Key::Ecdsa { .. } | Key::EcdsaOld { .. } => {
+                Signer::new(MessageDigest::sha256(), &self.pkey)?.sign_oneshot_to_vec(msg)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Sign for OpensslKeyPair {
+    fn tuf_key(&self) -> Key {
+        self.key.clone()
+    }
+
+    /// OpenSSL draws on its own random number generator, so `rng` isn't used.
+    async fn sign(
+        &self,
+        msg: &[u8],
+        _rng: &(dyn SecureRandom + Sync),
+    ) -> std::result::Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(OpensslKeyPair::sign(self, msg)?)
+    }
+}
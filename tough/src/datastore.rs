@@ -1,43 +1,208 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+//! The datastore is where a client keeps the last metadata it trusted, which protects it against
+//! rollback attacks, along with the latest known system time.
+//!
+//! Applications normally only choose where the datastore lives with
+//! [`RepositoryLoader::datastore`](crate::RepositoryLoader::datastore), or provide their own
+//! [`DatastoreBackend`] with
+//! [`RepositoryLoader::datastore_backend`](crate::RepositoryLoader::datastore_backend) where
+//! there's no writable disk. The maintenance methods here let an operator inspect what is stored
+//! and, when a repository has legitimately lowered its versions (for example after an intentional
+//! re-key), reset that state.
+
 use crate::error::{self, Result};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use log::debug;
 use serde::Serialize;
-use snafu::{ensure, ResultExt};
+use snafu::{ensure, ResultExt, Snafu};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs::TryLockError;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tempfile::TempDir;
-use tokio::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+use tempfile::{NamedTempFile, TempDir};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, RwLock};
+
+/// The datastore file holding the latest known system time.
+const LATEST_KNOWN_TIME: &str = "latest_known_time.json";
+
+/// How long a load waits for another client to release the datastore by default.
+pub(crate) const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The file a [`FilesystemDatastore`] takes an advisory lock on while a client loads.
+const LOCK_FILE: &str = ".datastore.lock";
+
+/// The prefix of the temporary files a [`FilesystemDatastore`] writes documents to before
+/// renaming them into place.
+const TEMP_PREFIX: &str = ".datastore-write";
+
+/// How often a [`FilesystemDatastore`] retries its lock while another client holds it.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Held while a client has a [`DatastoreBackend`] locked, and released when dropped.
+pub type DatastoreLock = Box<dyn Send + Sync>;
+
+/// Storage for the documents a [`Datastore`] keeps, each identified by a file name such as
+/// `timestamp.json`.
+///
+/// [`FilesystemDatastore`] and [`MemoryDatastore`] are provided. [`Datastore`] serializes access,
+/// so implementations don't need to guard against a read racing a write.
+#[async_trait]
+pub trait DatastoreBackend: Debug + Send + Sync {
+    /// Returns the contents of the document `name`, or `None` if there is no such document.
+    async fn read(
+        &self,
+        name: &str,
+    ) -> std::result::Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+    /// Creates the document `name` with `data`, replacing any document of that name.
+    async fn create(
+        &self,
+        name: &str,
+        data: &[u8],
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+    /// Removes the document `name`. Removing a document that doesn't exist is not an error.
+    async fn remove(
+        &self,
+        name: &str,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+    /// Lists the names of the stored documents, in any order.
+    async fn list(
+        &self,
+    ) -> std::result::Result<Vec<String>, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+    /// Locks the stored documents against other clients sharing them, such as other processes
+    /// using the same directory, until the returned guard is dropped. If another client holds the
+    /// lock, waits for up to `timeout` for it to be released.
+    ///
+    /// A load holds the lock from reading the previously trusted metadata until it has written
+    /// the newly trusted metadata, so that concurrent loads don't interleave their writes. The
+    /// default takes no lock, which is fine for storage that only one client uses.
+    async fn lock(
+        &self,
+        _timeout: Duration,
+    ) -> std::result::Result<DatastoreLock, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        Ok(Box::new(()))
+    }
+}
 
 /// `Datastore` persists TUF metadata files.
 #[derive(Debug, Clone)]
-pub(crate) struct Datastore {
-    /// A lock around retrieving the datastore path.
-    path_lock: Arc<RwLock<DatastorePath>>,
+pub struct Datastore {
+    /// The backend, behind a lock so that writes aren't interleaved with reads.
+    backend: Arc<RwLock<Arc<dyn DatastoreBackend>>>,
     /// A lock to treat the `system_time` function as a critical section.
     time_lock: Arc<Mutex<()>>,
 }
 
 impl Datastore {
-    pub(crate) fn new(path: Option<PathBuf>) -> Result<Self> {
+    /// Uses `backend`, or a temporary directory if it's `None`.
+    pub(crate) fn new(backend: Option<Arc<dyn DatastoreBackend>>) -> Result<Self> {
+        let backend = match backend {
+            Some(backend) => backend,
+            None => Arc::new(FilesystemDatastore::temporary()?),
+        };
         Ok(Self {
-            path_lock: Arc::new(RwLock::new(match path {
-                None => DatastorePath::TempDir(TempDir::new().context(error::DatastoreInitSnafu)?),
-                Some(p) => DatastorePath::Path(p),
-            })),
+            backend: Arc::new(RwLock::new(backend)),
             time_lock: Arc::new(Mutex::new(())),
         })
     }
 
-    async fn read(&self) -> RwLockReadGuard<'_, DatastorePath> {
-        self.path_lock.read().await
+    /// Opens the datastore at `path`, a directory previously passed to
+    /// [`RepositoryLoader::datastore`](crate::RepositoryLoader::datastore).
+    pub fn open<P: Into<PathBuf>>(path: P) -> Self {
+        Self::with_backend(FilesystemDatastore::new(path))
+    }
+
+    /// Opens a datastore kept by `backend`, such as a clone of a [`MemoryDatastore`] previously
+    /// passed to [`RepositoryLoader::datastore_backend`](crate::RepositoryLoader::datastore_backend).
+    pub fn with_backend<B: DatastoreBackend + 'static>(backend: B) -> Self {
+        Self {
+            backend: Arc::new(RwLock::new(Arc::new(backend))),
+            time_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Lists the documents in the datastore, sorted by file name. For metadata files, the role
+    /// type and version are read from the file.
+    pub async fn inspect(&self) -> Result<Vec<DatastoreEntry>> {
+        let backend = self.backend.read().await;
+        let names = backend.list().await.context(error::DatastoreListSnafu)?;
+        let mut entries = Vec::new();
+        for name in names {
+            let Some(bytes) = backend
+                .read(&name)
+                .await
+                .context(error::DatastoreOpenSnafu { name: &name })?
+            else {
+                continue;
+            };
+            let signed = serde_json::from_slice::<serde_json::Value>(&bytes)
+                .ok()
+                .and_then(|value| value.get("signed").cloned());
+            entries.push(DatastoreEntry {
+                name,
+                role: signed
+                    .as_ref()
+                    .and_then(|signed| signed.get("_type"))
+                    .and_then(serde_json::Value::as_str)
+                    .map(str::to_owned),
+                version: signed
+                    .as_ref()
+                    .and_then(|signed| signed.get("version"))
+                    .and_then(serde_json::Value::as_u64),
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
     }
 
-    async fn write(&self) -> RwLockWriteGuard<'_, DatastorePath> {
-        self.path_lock.write().await
+    /// Removes the stored metadata for `role` (for example `"snapshot"`, or the name of a
+    /// delegated role), so the next load accepts whatever version the repository serves for it.
+    ///
+    /// A delegated role is named by the filename its metadata is stored under, without `.json`,
+    /// as listed by [`inspect`](Self::inspect). A `role` that could name a file outside the
+    /// datastore, such as one containing `/` or `..`, is refused.
+    pub async fn reset(&self, role: &str, _acknowledgement: ResetAcknowledgement) -> Result<()> {
+        ensure!(
+            !role.is_empty() && !role.contains("..") && !role.contains(['/', '\\', '\0']),
+            error::DatastoreUnsafeRoleSnafu { role }
+        );
+        self.remove(&format!("{role}.json")).await
+    }
+
+    /// Removes every document from the datastore, including the latest known time, returning the
+    /// client to the state of a fresh install that trusts only the root it is given.
+    pub async fn reset_all(&self, _acknowledgement: ResetAcknowledgement) -> Result<()> {
+        for entry in self.inspect().await? {
+            if Path::new(&entry.name)
+                .extension()
+                .is_some_and(|ext| ext == "json")
+            {
+                self.remove(&entry.name).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Locks the datastore against other clients sharing it, waiting for up to `timeout`. See
+    /// [`DatastoreBackend::lock`].
+    pub(crate) async fn lock(&self, timeout: Duration) -> Result<DatastoreLock> {
+        self.backend
+            .read()
+            .await
+            .lock(timeout)
+            .await
+            .context(error::DatastoreLockSnafu)
     }
 
     /// Get contents of a file in the datastore. This function is thread safe.
@@ -45,42 +210,43 @@ impl Datastore {
     /// TODO: [provide a thread safe interface](https://github.com/awslabs/tough/issues/602)
     ///
     pub(crate) async fn bytes(&self, file: &str) -> Result<Option<Vec<u8>>> {
-        let lock = &self.read().await;
-        let path = lock.path().join(file);
-        match tokio::fs::read(&path).await {
-            Ok(file) => Ok(Some(file)),
-            Err(err) => match err.kind() {
-                ErrorKind::NotFound => Ok(None),
-                _ => Err(err).context(error::DatastoreOpenSnafu { path: &path }),
-            },
-        }
+        self.backend
+            .read()
+            .await
+            .read(file)
+            .await
+            .context(error::DatastoreOpenSnafu { name: file })
     }
 
     /// Writes a JSON metadata file in the datastore. This function is thread safe.
     pub(crate) async fn create<T: Serialize>(&self, file: &str, value: &T) -> Result<()> {
-        let lock = &self.write().await;
-        let path = lock.path().join(file);
         let bytes = serde_json::to_vec(value).with_context(|_| error::DatastoreSerializeSnafu {
             what: format!("{file} in datastore"),
-            path: path.clone(),
+            name: file,
         })?;
-        tokio::fs::write(&path, bytes)
+        self.write_bytes(file, &bytes).await
+    }
+
+    /// Writes a metadata file in the datastore exactly as it was fetched, so that the hashes
+    /// listed for it still match. This function is thread safe.
+    pub(crate) async fn write_bytes(&self, file: &str, bytes: &[u8]) -> Result<()> {
+        self.backend
+            .write()
             .await
-            .context(error::DatastoreCreateSnafu { path: &path })
+            .create(file, bytes)
+            .await
+            .context(error::DatastoreCreateSnafu { name: file })
     }
 
     /// Deletes a file from the datastore. This function is thread safe.
     pub(crate) async fn remove(&self, file: &str) -> Result<()> {
-        let lock = self.write().await;
-        let path = lock.path().join(file);
-        debug!("removing '{}'", path.display());
-        match tokio::fs::remove_file(&path).await {
-            Ok(()) => Ok(()),
-            Err(err) => match err.kind() {
-                ErrorKind::NotFound => Ok(()),
-                _ => Err(err).context(error::DatastoreRemoveSnafu { path: &path }),
-            },
-        }
+        debug!("removing '{file}' from the datastore");
+        self.backend
+            .write()
+            .await
+            .remove(file)
+            .await
+            .context(error::DatastoreRemoveSnafu { name: file })
     }
 
     /// Ensures that system time has not stepped backward since it was last sampled. This function
@@ -89,7 +255,7 @@ impl Datastore {
         // Treat this function as a critical section. This lock is not used for anything else.
         let lock = self.time_lock.lock().await;
 
-        let file = "latest_known_time.json";
+        let file = LATEST_KNOWN_TIME;
         // Load the latest known system time, if it exists
         let poss_latest_known_time = self
             .bytes(file)
@@ -119,6 +285,31 @@ impl Datastore {
     }
 }
 
+/// A document stored in the datastore, as reported by [`Datastore::inspect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatastoreEntry {
+    /// The file name within the datastore.
+    pub name: String,
+    /// The `_type` of the signed metadata, if the document is TUF metadata.
+    pub role: Option<String>,
+    /// The version of the signed metadata, if the document is TUF metadata.
+    pub version: Option<u64>,
+}
+
+/// Resetting the datastore discards the record of the newest metadata the client has trusted, which
+/// is what protects it from rollback attacks. Constructing this type is an explicit statement that
+/// the caller knows the repository has legitimately moved backward.
+#[derive(Debug, Clone, Copy)]
+pub struct ResetAcknowledgement(());
+
+impl ResetAcknowledgement {
+    /// Acknowledges that resetting the datastore removes rollback protection until the client
+    /// loads the repository again.
+    pub fn i_understand_this_removes_rollback_protection() -> Self {
+        Self(())
+    }
+}
+
 /// Because `TempDir` is an RAII object, we need to hold on to it. This private enum allows us to
 /// hold either a `TempDir` or a `PathBuf` depending on whether or not the user wants to manage the
 /// directory.
@@ -139,3 +330,230 @@ impl DatastorePath {
         }
     }
 }
+
+/// A [`DatastoreBackend`] that keeps each document as a file in a directory. This is the backend
+/// used by [`RepositoryLoader::datastore`](crate::RepositoryLoader::datastore), and, in a
+/// temporary directory, when no datastore is set.
+///
+/// Documents are written to a temporary file and renamed into place, so a reader never sees a
+/// partly written document. [`lock`](DatastoreBackend::lock) takes an advisory lock on a
+/// `.datastore.lock` file in the directory, which is released if the process exits, so processes
+/// sharing the directory can load at the same time.
+#[derive(Debug)]
+pub struct FilesystemDatastore {
+    path: DatastorePath,
+}
+
+impl FilesystemDatastore {
+    /// Keeps documents in `path`, a directory that must already exist.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: DatastorePath::Path(path.into()),
+        }
+    }
+
+    /// Keeps documents in a new temporary directory, which is removed when the backend is dropped.
+    pub fn temporary() -> Result<Self> {
+        Ok(Self {
+            path: DatastorePath::TempDir(TempDir::new().context(error::DatastoreInitSnafu)?),
+        })
+    }
+
+    /// The directory the documents are kept in.
+    pub fn path(&self) -> &Path {
+        self.path.path()
+    }
+}
+
+/// An I/O error in a [`FilesystemDatastore`], with the path it occurred at.
+#[derive(Debug, Snafu)]
+#[snafu(display("{}: {}", path.display(), source))]
+struct FileError {
+    path: PathBuf,
+    source: std::io::Error,
+}
+
+/// Another client held a [`FilesystemDatastore`]'s lock for longer than the lock timeout.
+#[derive(Debug, Snafu)]
+#[snafu(display(
+    "Timed out after {:?} waiting for another client to release {}",
+    timeout,
+    path.display()
+))]
+struct LockTimeout {
+    path: PathBuf,
+    timeout: Duration,
+}
+
+#[async_trait]
+impl DatastoreBackend for FilesystemDatastore {
+    async fn read(
+        &self,
+        name: &str,
+    ) -> std::result::Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        let path = self.path().join(name);
+        match tokio::fs::read(&path).await {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Box::new(FileError { path, source: err })),
+        }
+    }
+
+    async fn create(
+        &self,
+        name: &str,
+        data: &[u8],
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let dir = self.path().to_owned();
+        let tmp = tokio::task::spawn_blocking(move || {
+            tempfile::Builder::new()
+                .prefix(TEMP_PREFIX)
+                .tempfile_in(&dir)
+                .context(FileSnafu { path: dir })
+        })
+        .await
+        // We do not cancel the task nor do we expect it to panic
+        .unwrap_or_else(|_| unreachable!())?;
+        let (f, tmp_path) = tmp.into_parts();
+        let mut f = tokio::fs::File::from_std(f);
+        f.write_all(data)
+            .await
+            .context(FileSnafu { path: &*tmp_path })?;
+        f.sync_all().await.context(FileSnafu { path: &*tmp_path })?;
+
+        // Rename the complete file into place, so that readers see the old or the new document.
+        let path = self.path().join(name);
+        NamedTempFile::from_parts(f.into_std().await, tmp_path)
+            .persist(&path)
+            .map_err(|err| err.error)
+            .context(FileSnafu { path })?;
+        Ok(())
+    }
+
+    async fn remove(
+        &self,
+        name: &str,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let path = self.path().join(name);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Box::new(FileError { path, source: err })),
+        }
+    }
+
+    async fn list(
+        &self,
+    ) -> std::result::Result<Vec<String>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let dir = self.path();
+        let mut read_dir = tokio::fs::read_dir(dir)
+            .await
+            .context(FileSnafu { path: dir })?;
+        let mut names = Vec::new();
+        while let Some(entry) = read_dir
+            .next_entry()
+            .await
+            .context(FileSnafu { path: dir })?
+        {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                if name == LOCK_FILE || name.starts_with(TEMP_PREFIX) {
+                    continue;
+                }
+                names.push(name.to_owned());
+            }
+        }
+        Ok(names)
+    }
+
+    async fn lock(
+        &self,
+        timeout: Duration,
+    ) -> std::result::Result<DatastoreLock, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        let path = self.path().join(LOCK_FILE);
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .await
+            .context(FileSnafu { path: &path })?
+            .into_std()
+            .await;
+        let start = Instant::now();
+        loop {
+            match file.try_lock() {
+                // The lock is released when the file is closed.
+                Ok(()) => return Ok(Box::new(file)),
+                Err(TryLockError::WouldBlock) => {
+                    if start.elapsed() >= timeout {
+                        return Err(Box::new(LockTimeout { path, timeout }));
+                    }
+                    tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+                }
+                Err(TryLockError::Error(err)) => {
+                    return Err(Box::new(FileError { path, source: err }))
+                }
+            }
+        }
+    }
+}
+
+/// A [`DatastoreBackend`] that keeps documents in memory, for environments without a writable
+/// disk.
+///
+/// Clones share the same documents. Keep a clone and pass it to each load made by the process so
+/// that rollback protection carries over from one load to the next.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDatastore {
+    documents: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+}
+
+impl MemoryDatastore {
+    /// Creates an empty in-memory datastore.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DatastoreBackend for MemoryDatastore {
+    async fn read(
+        &self,
+        name: &str,
+    ) -> std::result::Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        Ok(self.documents.lock().await.get(name).cloned())
+    }
+
+    async fn create(
+        &self,
+        name: &str,
+        data: &[u8],
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        self.documents
+            .lock()
+            .await
+            .insert(name.to_owned(), data.to_vec());
+        Ok(())
+    }
+
+    async fn remove(
+        &self,
+        name: &str,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        self.documents.lock().await.remove(name);
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+    ) -> std::result::Result<Vec<String>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(self.documents.lock().await.keys().cloned().collect())
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Bounds how long each step of `Repository::load` may take, so that a slow or stalling server
+//! can't hold a load open indefinitely across many fetches and retries.
+
+use crate::error::{self, Result};
+use crate::schema::RoleType;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// The deadlines set with `RepositoryLoader::role_timeout` and `RepositoryLoader::load_timeout`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadlines {
+    role_timeout: Option<Duration>,
+    load_deadline: Option<(Instant, Duration)>,
+}
+
+impl Deadlines {
+    /// Starts the load's overall clock, if it has one.
+    pub(crate) fn start(role_timeout: Option<Duration>, load_timeout: Option<Duration>) -> Self {
+        Self {
+            role_timeout,
+            load_deadline: load_timeout.map(|timeout| (Instant::now() + timeout, timeout)),
+        }
+    }
+
+    /// Runs the step that loads `role`, failing if it outlasts either deadline. The step's future
+    /// is dropped when a deadline passes, which cancels any fetch it has in flight.
+    pub(crate) async fn run<T, F>(&self, role: RoleType, step: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let role_deadline = self
+            .role_timeout
+            .map(|timeout| (Instant::now() + timeout, timeout, "per-role"));
+        let load_deadline = self
+            .load_deadline
+            .map(|(deadline, timeout)| (deadline, timeout, "per-load"));
+        let deadline = match (role_deadline, load_deadline) {
+            (Some(role), Some(load)) => Some(if role.0 <= load.0 { role } else { load }),
+            (role, load) => role.or(load),
+        };
+
+        match deadline {
+            None => step.await,
+            Some((deadline, timeout, scope)) => tokio::time::timeout_at(deadline, step)
+                .await
+                .unwrap_or_else(|_| {
+                    error::LoadTimeoutSnafu {
+                        role,
+                        scope,
+                        timeout,
+                    }
+                    .fail()
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn role_timeout() {
+        let deadlines = Deadlines::start(Some(Duration::from_millis(10)), None);
+        let err = deadlines
+            .run(RoleType::Snapshot, futures::future::pending::<Result<()>>())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                error::Error::LoadTimeout {
+                    scope: "per-role",
+                    ..
+                }
+            ),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Guards traversal of the delegation graph against cycles and unbounded size.
+//!
+//! Role names in snapshot.json are unique, so a role that (directly or indirectly) delegates to a
+//! role of the same name as one of its ancestors would make the client fetch the same metadata
+//! forever. `DelegationWalk` tracks the chain of role names from `targets` down to the role being
+//! visited and the number of roles visited so far, and fails with a clear error instead.
+
+use crate::error::{self, Result};
+use crate::schema::Targets;
+use snafu::ensure;
+
+/// The name of the top-level targets role, which is the root of every delegation walk.
+const TOP_LEVEL_TARGETS: &str = "targets";
+
+#[derive(Debug, Clone)]
+pub(crate) struct DelegationWalk {
+    ancestors: Vec<String>,
+    visited: u64,
+    max_roles: u64,
+}
+
+impl DelegationWalk {
+    /// Starts a walk at the top-level targets role that will visit at most `max_roles` delegated
+    /// roles.
+    pub(crate) fn new(max_roles: u64) -> Self {
+        Self {
+            ancestors: vec![TOP_LEVEL_TARGETS.to_owned()],
+            visited: 0,
+            max_roles,
+        }
+    }
+
+    /// Starts a walk at the delegated role `name` within `targets`, so that the roles above `name`
+    /// are treated as its ancestors. If `name` isn't found, the walk starts at the top level.
+    pub(crate) fn starting_at(targets: &Targets, name: &str, max_roles: u64) -> Self {
+        let mut walk = Self::new(max_roles);
+        if name != TOP_LEVEL_TARGETS {
+            if let Some(chain) = chain_to(targets, name) {
+                walk.ancestors.extend(chain);
+            }
+        }
+        walk
+    }
+
+    /// Records a visit to the role `name`, which is delegated by the current role. Fails if `name`
+    /// is already one of its own ancestors or if too many roles have been visited.
+    pub(crate) fn visit(&mut self, name: &str) -> Result<()> {
+        ensure!(
+            !self.ancestors.iter().any(|ancestor| ancestor == name),
+            error::DelegationCycleSnafu {
+                name,
+                chain: self.ancestors.clone(),
+            }
+        );
+        self.visited += 1;
+        ensure!(
+            self.visited <= self.max_roles,
+            error::MaxDelegatedRolesExceededSnafu {
+                max_roles: self.max_roles,
+            }
+        );
+        Ok(())
+    }
+
+    /// Makes `name` the current role, so that the roles it delegates are visited as its children.
+    pub(crate) fn descend(&mut self, name: &str) {
+        self.ancestors.push(name.to_owned());
+    }
+
+    /// Returns to the role that delegated the current role.
+    pub(crate) fn ascend(&mut self) {
+        self.ancestors.pop();
+    }
+
+    /// Visits every role delegated by `targets`, recursively.
+    pub(crate) fn check(&mut self, targets: &Targets) -> Result<()> {
+        if let Some(delegations) = &targets.delegations {
+            for role in &delegations.roles {
+                self.visit(&role.name)?;
+                if let Some(role_targets) = &role.targets {
+                    self.descend(&role.name);
+                    self.check(&role_targets.signed)?;
+                    self.ascend();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the names of the roles from just below `targets` down to and including `name`.
+fn chain_to(targets: &Targets, name: &str) -> Option<Vec<String>> {
+    for role in &targets.delegations.as_ref()?.roles {
+        if role.name == name {
+            return Some(vec![role.name.clone()]);
+        }
+        if let Some(role_targets) = &role.targets {
+            if let Some(mut chain) = chain_to(&role_targets.signed, name) {
+                chain.insert(0, role.name.clone());
+                return Some(chain);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{DelegatedRole, PathSet, Signed};
+    use chrono::Utc;
+    use std::num::NonZeroU64;
+
+    fn targets() -> Targets {
+        Targets::new("1.0.0".to_owned(), NonZeroU64::new(1).unwrap(), Utc::now())
+    }
+
+    fn delegate(parent: &mut Targets, name: &str, child: Option<Targets>) {
+        parent
+            .delegations
+            .as_mut()
+            .unwrap()
+            .roles
+            .push(DelegatedRole {
+                name: name.to_owned(),
+                keyids: Vec::new(),
+                threshold: NonZeroU64::new(1).unwrap(),
+                paths: PathSet::Paths(Vec::new()),
+                terminating: false,
+                targets: child.map(|signed| Signed {
+                    signed,
+                    signatures: Vec::new(),
+                }),
+            });
+    }
+
+    #[test]
+    fn acyclic_graph() {
+        let mut b = targets();
+        delegate(&mut b, "c", None);
+        let mut top = targets();
+        delegate(&mut top, "a", None);
+        delegate(&mut top, "b", Some(b));
+        DelegationWalk::new(3).check(&top).unwrap();
+    }
+
+    #[test]
+    fn cycle_back_to_top_level() {
+        let mut a = targets();
+        delegate(&mut a, "targets", None);
+        let mut top = targets();
+        delegate(&mut top, "a", Some(a));
+        let err = DelegationWalk::new(10).check(&top).unwrap_err();
+        assert!(
+            matches!(err, error::Error::DelegationCycle { .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn cycle_to_ancestor() {
+        let mut b = targets();
+        delegate(&mut b, "a", None);
+        let mut a = targets();
+        delegate(&mut a, "b", Some(b));
+        let mut top = targets();
+        delegate(&mut top, "a", Some(a));
+        let err = DelegationWalk::new(10).check(&top).unwrap_err();
+        assert!(
+            matches!(err, error::Error::DelegationCycle { .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn starting_at_nested_role() {
+        let mut b = targets();
+        delegate(&mut b, "c", None);
+        let mut a = targets();
+        delegate(&mut a, "b", Some(b));
+        let mut top = targets();
+        delegate(&mut top, "a", Some(a));
+
+        // Incoming metadata for "b" that delegates back to "a" must be rejected.
+        let mut incoming = targets();
+        delegate(&mut incoming, "a", None);
+        let err = DelegationWalk::starting_at(&top, "b", 10)
+            .check(&incoming)
+            .unwrap_err();
+        assert!(
+            matches!(err, error::Error::DelegationCycle { .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn too_many_roles() {
+        let mut top = targets();
+        for name in ["a", "b", "c"] {
+            delegate(&mut top, name, None);
+        }
+        let err = DelegationWalk::new(2).check(&top).unwrap_err();
+        assert!(
+            matches!(err, error::Error::MaxDelegatedRolesExceeded { .. }),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Just enough DER to build and take apart the small structures around TUF metadata: RFC 3161
+//! timestamp requests and responses, and the public keys and signatures that key services and
+//! PKCS#11 tokens return in other forms than TUF lists them.
+
+use std::convert::TryFrom;
+
+/// The tag of a BOOLEAN.
+pub const BOOLEAN: u8 = 0x01;
+/// The tag of an INTEGER.
+pub const INTEGER: u8 = 0x02;
+/// The tag of an OCTET STRING.
+pub const OCTET_STRING: u8 = 0x04;
+/// The tag of a SEQUENCE.
+pub const SEQUENCE: u8 = 0x30;
+
+/// Encodes a tag, length and `contents`.
+pub fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match u8::try_from(contents.len()) {
+        Ok(len) if len < 0x80 => out.push(len),
+        _ => {
+            let len = contents.len().to_be_bytes();
+            let start = len.iter().position(|b| *b != 0).unwrap_or(len.len());
+            // At most `size_of::<usize>()` length bytes follow.
+            #[allow(clippy::cast_possible_truncation)]
+            out.push(0x80 | (len.len() - start) as u8);
+            out.extend_from_slice(&len[start..]);
+        }
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+/// Encodes the big-endian unsigned integer `bytes` as an INTEGER, dropping leading zeros and
+/// adding one where a set high bit would make it negative.
+pub fn unsigned_integer(bytes: &[u8]) -> Vec<u8> {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let mut contents = Vec::with_capacity(bytes.len() - start + 1);
+    if bytes.get(start).is_none_or(|b| b & 0x80 != 0) {
+        contents.push(0);
+    }
+    contents.extend_from_slice(&bytes[start..]);
+    tlv(INTEGER, &contents)
+}
+
+/// Encodes a SEQUENCE of already encoded `items`.
+pub fn sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    tlv(SEQUENCE, &items.concat())
+}
+
+/// Reads one tag-length-value triple, returning the tag, the contents, and the rest of `input`.
+/// Returns `None` if `input` is too short for the length it gives.
+pub fn read_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&first, mut input) = input.split_first()?;
+    let len = if first < 0x80 {
+        usize::from(first)
+    } else {
+        let count = usize::from(first & 0x7f);
+        if count == 0 || count > std::mem::size_of::<usize>() || input.len() < count {
+            return None;
+        }
+        let (len_bytes, rest) = input.split_at(count);
+        input = rest;
+        len_bytes
+            .iter()
+            .fold(0_usize, |len, byte| (len << 8) | usize::from(*byte))
+    };
+    let contents = input.get(..len)?;
+    Some((tag, contents, &input[len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers_are_minimal_and_positive() {
+        assert_eq!(unsigned_integer(&[0x00, 0x00, 0x7f]), [0x02, 0x01, 0x7f]);
+        assert_eq!(unsigned_integer(&[0x80]), [0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(unsigned_integer(&[0x00, 0x00]), [0x02, 0x01, 0x00]);
+        assert_eq!(unsigned_integer(&[]), [0x02, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn long_lengths_round_trip() {
+        let encoded = sequence(&[vec![0xaa; 300]]);
+        assert_eq!(encoded[..4], [0x30, 0x82, 0x01, 0x2c]);
+        assert_eq!(encoded.len(), 304);
+        assert_eq!(
+            read_tlv(&encoded),
+            Some((SEQUENCE, &[0xaa; 300][..], &[][..]))
+        );
+    }
+
+    #[test]
+    fn read_leaves_the_rest() {
+        let input = [tlv(OCTET_STRING, &[1, 2]), tlv(BOOLEAN, &[0xff])].concat();
+        assert_eq!(
+            read_tlv(&input),
+            Some((OCTET_STRING, &[1, 2][..], &[0x01, 0x01, 0xff][..]))
+        );
+    }
+
+    #[test]
+    fn truncated_input() {
+        assert_eq!(read_tlv(&[0x04, 0x03, 1, 2]), None);
+        assert_eq!(read_tlv(&[0x04, 0x82, 0x01]), None);
+        assert_eq!(read_tlv(&[0x04, 0x80]), None);
+        assert_eq!(read_tlv(&[0x04]), None);
+    }
+}
@@ -4,6 +4,7 @@
 use crate::error::{self, Result};
 use crate::key_source::KeySource;
 use crate::schema::decoded::{Decoded, Hex};
+use crate::schema::key::Key;
 use crate::schema::{Delegations, KeyHolder, RoleId, RoleKeys, Root, Signed, Targets};
 use crate::sign::Sign;
 use snafu::{ensure, OptionExt, ResultExt};
@@ -52,6 +53,38 @@ impl KeyHolder {
         Err(error::Error::SigningKeysNotFound { role })
     }
 
+    /// Checks that `keys` include a threshold of the keys this `KeyHolder` lists for `role`, as
+    /// signing the role requires.
+    pub(crate) async fn check_signing_threshold(
+        &self,
+        role: RoleId,
+        keys: &[Box<dyn KeySource>],
+    ) -> Result<()> {
+        let available_keys = match self.get_keys(keys).await {
+            // Report how far short of the threshold the keys fall, rather than that none matched.
+            Err(error::Error::KeysNotFoundInRoot { .. }) => KeyList::new(),
+            result => result?,
+        };
+        let role_keys = self.role_keys(role.clone())?;
+        let available = role_keys
+            .keyids
+            .iter()
+            .filter(|keyid| available_keys.contains_key(*keyid))
+            .count() as u64;
+        ensure!(
+            available >= role_keys.threshold.get(),
+            error::SigningThresholdSnafu {
+                role: match role {
+                    RoleId::StandardRole(role) => role.to_string(),
+                    RoleId::DelegatedRole(name) => name,
+                },
+                threshold: role_keys.threshold.get(),
+                available,
+            }
+        );
+        Ok(())
+    }
+
     /// Verifies the role using `KeyHolder`'s keys
     pub(crate) fn verify_role(&self, targets: &Signed<Targets>, name: &str) -> Result<()> {
         match self {
@@ -116,3 +149,21 @@ pub(crate) async fn get_targets_keys(
     }
     Ok(delegations_keys)
 }
+
+/// Returns the public key of each of `keys`, by key ID.
+pub(crate) async fn public_keys(keys: &[Box<dyn KeySource>]) -> Result<HashMap<Decoded<Hex>, Key>> {
+    let mut key_pairs = HashMap::new();
+    for source in keys {
+        let key_pair = source
+            .public_key()
+            .await
+            .context(error::KeyPairFromKeySourceSnafu)?;
+        key_pairs.insert(
+            key_pair
+                .key_id()
+                .context(error::JsonSerializationSnafu {})?,
+            key_pair,
+        );
+    }
+    Ok(key_pairs)
+}
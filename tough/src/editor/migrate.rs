@@ -0,0 +1,118 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Moves the snapshot and timestamp roles of a repository to new keys, such as online keys held
+//! in a KMS, in a single step.
+
+use crate::editor::signed::SignedRepository;
+use crate::editor::RepositoryEditor;
+use crate::error::{self, Result};
+use crate::key_source::KeySource;
+use crate::schema::decoded::{Decoded, Hex};
+use crate::schema::RoleType;
+use snafu::{ensure, OptionExt, ResultExt};
+
+impl RepositoryEditor {
+    /// Replaces the keys of the snapshot and timestamp roles with the public keys of
+    /// `snapshot_keys` and `timestamp_keys`, and returns the repository signed with them.
+    ///
+    /// This bumps the version of root.json, lists the new keys for each role in place of the old
+    /// ones, drops old keys that no other role uses, and signs the new root with `root_keys`,
+    /// which must meet the threshold of both the current and the new root. Snapshot and timestamp
+    /// are then built and signed with the new keys, using the versions and expirations set on the
+    /// editor, and every role is checked against the new root. The loaded targets are kept as they
+    /// are; changes in the targets editor that weren't signed with `sign_targets_editor()` are
+    /// discarded.
+    ///
+    /// Nothing is written. If any step fails, such as a key source that can't be reached, the
+    /// error is returned and the repository on disk is left as it was, since it's only changed
+    /// once the returned `SignedRepository` is written.
+    pub async fn migrate_online_keys(
+        mut self,
+        root_keys: &[Box<dyn KeySource>],
+        snapshot_keys: Vec<Box<dyn KeySource>>,
+        timestamp_keys: Vec<Box<dyn KeySource>>,
+    ) -> Result<SignedRepository> {
+        let mut root_editor = self.root_editor();
+        root_editor.bump_version()?;
+
+        let mut replaced = Vec::new();
+        for (role, keys) in [
+            (RoleType::Snapshot, &snapshot_keys),
+            (RoleType::Timestamp, &timestamp_keys),
+        ] {
+            let old_ids: Vec<Decoded<Hex>> = root_editor
+                .root()
+                .roles
+                .get(&role)
+                .map(|role_keys| role_keys.keyids.clone())
+                .unwrap_or_default();
+            let mut new_ids = Vec::new();
+            for source in keys {
+                let key = source
+                    .public_key()
+                    .await
+                    .context(error::KeyPairFromKeySourceSnafu)?;
+                new_ids.push(root_editor.add_key(key, &[role])?);
+            }
+            for old_id in old_ids {
+                if !new_ids.contains(&old_id) {
+                    root_editor.remove_key(&old_id, Some(role));
+                    replaced.push(old_id);
+                }
+            }
+
+            let role_keys =
+                root_editor
+                    .root()
+                    .roles
+                    .get(&role)
+                    .context(error::NoRoleKeysinRootSnafu {
+                        role: role.to_string(),
+                    })?;
+            ensure!(
+                role_keys.keyids.len() as u64 >= role_keys.threshold.get(),
+                error::UnstableRootSnafu {
+                    role,
+                    actual: role_keys.keyids.len(),
+                    threshold: role_keys.threshold.get(),
+                }
+            );
+        }
+        for old_id in replaced {
+            let in_use = root_editor
+                .root()
+                .roles
+                .values()
+                .any(|role_keys| role_keys.keyids.contains(&old_id));
+            if !in_use {
+                root_editor.remove_key(&old_id, None);
+            }
+        }
+
+        let new_root = root_editor.sign(root_keys).await?;
+        self.root(new_root)?;
+
+        // Targets aren't re-signed; their keys haven't changed.
+        self.targets_editor = None;
+        ensure!(self.signed_targets.is_some(), error::NoTargetsSnafu);
+        let online_keys: Vec<Box<dyn KeySource>> =
+            snapshot_keys.into_iter().chain(timestamp_keys).collect();
+        let signed = self.sign(&online_keys).await?;
+
+        let root = &signed.root.signed.signed;
+        root.verify_role(&signed.targets.signed)
+            .context(error::VerifyRoleMetadataSnafu {
+                role: RoleType::Targets.to_string(),
+            })?;
+        root.verify_role(&signed.snapshot.signed)
+            .context(error::VerifyRoleMetadataSnafu {
+                role: RoleType::Snapshot.to_string(),
+            })?;
+        root.verify_role(&signed.timestamp.signed)
+            .context(error::VerifyRoleMetadataSnafu {
+                role: RoleType::Timestamp.to_string(),
+            })?;
+        Ok(signed)
+    }
+}
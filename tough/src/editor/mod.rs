@@ -5,38 +5,50 @@
 //! Provides a `RepositoryEditor` object for building and editing TUF repositories.
 
 mod keys;
+mod migrate;
+pub mod root;
 pub mod signed;
+mod sorted;
 pub mod targets;
 mod test;
 
-use crate::editor::signed::{SignedDelegatedTargets, SignedRepository, SignedRole};
+use crate::delegation_walk::DelegationWalk;
+use crate::editor::keys::public_keys;
+use crate::editor::root::RootEditor;
+use crate::editor::signed::{MetadataStyle, SignedDelegatedTargets, SignedRepository, SignedRole};
 use crate::editor::targets::TargetsEditor;
 use crate::error::{self, Result};
 use crate::fetch::fetch_max_size;
+use crate::filename_encoding::encode_role_name;
 use crate::key_source::KeySource;
+use crate::policy::HashAlgorithm;
 use crate::schema::decoded::{Decoded, Hex};
 use crate::schema::key::Key;
 use crate::schema::{
-    Hashes, KeyHolder, Metafile, PathSet, Role, RoleType, Root, Signed, Snapshot, Target, Targets,
-    Timestamp,
+    DelegatedTargets, Hashes, KeyHolder, Metafile, PathSet, Role, RoleId, RoleType, Root,
+    Signature, Signed, Snapshot, Target, TargetBuilder, Targets, Timestamp,
 };
 use crate::transport::{IntoVec, Transport};
-use crate::{encode_filename, Limits};
-use crate::{Repository, TargetName};
+use crate::{FilenameEncoding, Limits, PercentEncoding};
+use crate::{Repository, TargetName, ToughContext};
 use aws_lc_rs::digest::{SHA256, SHA256_OUTPUT_LEN};
 use aws_lc_rs::rand::SystemRandom;
 use chrono::{DateTime, Utc};
+use log::warn;
+use olpc_cjson::CanonicalFormatter;
+use serde::Serialize;
 use serde_json::Value;
-use snafu::{ensure, OptionExt, ResultExt};
+use snafu::{ensure, IntoError, OptionExt, ResultExt};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fmt::Display;
 use std::num::NonZeroU64;
 use std::path::Path;
+use std::sync::Arc;
 use url::Url;
 
-const SPEC_VERSION: &str = "1.0.0";
+pub(crate) const SPEC_VERSION: &str = "1.0.0";
 
 /// `RepositoryEditor` contains the various bits of data needed to construct
 /// or edit a TUF repository.
@@ -71,10 +83,14 @@ pub struct RepositoryEditor {
     snapshot_version: Option<NonZeroU64>,
     snapshot_expires: Option<DateTime<Utc>>,
     snapshot_extra: Option<HashMap<String, Value>>,
+    /// Extension fields for the files listed in snapshot.json, keyed by path
+    snapshot_meta_extra: HashMap<String, HashMap<String, Value>>,
 
     timestamp_version: Option<NonZeroU64>,
     timestamp_expires: Option<DateTime<Utc>>,
     timestamp_extra: Option<HashMap<String, Value>>,
+    /// Extension fields for snapshot.json as listed in timestamp.json
+    timestamp_meta_extra: HashMap<String, Value>,
 
     targets_editor: Option<TargetsEditor>,
 
@@ -83,6 +99,34 @@ pub struct RepositoryEditor {
 
     transport: Option<Box<dyn Transport>>,
     limits: Option<Limits>,
+
+    /// The versions of the repository this editor was loaded from, if any
+    loaded_versions: Option<LoadedVersions>,
+    allow_version_regression: bool,
+
+    /// Hashes listed for targets added by path and for metadata, besides `sha256`
+    hash_algorithms: Vec<HashAlgorithm>,
+
+    /// Turns delegated role names into the filenames their metadata is fetched from and written to
+    filename_encoding: Arc<dyn FilenameEncoding>,
+
+    /// Delegated roles that lost keys through `remove_key()`, which must still be signed by a
+    /// threshold of their remaining keys when the repository is signed
+    rekeyed_roles: HashSet<String>,
+
+    /// How the targets, snapshot and timestamp metadata are serialized by `sign()`
+    metadata_style: MetadataStyle,
+}
+
+/// The versions of the repository passed to `from_repo()`. Snapshot and timestamp are signed
+/// anew on every `sign()`, and clients reject them unless their versions advance. Targets and
+/// delegated roles only need a new version if their contents changed.
+#[derive(Debug, Clone)]
+struct LoadedVersions {
+    snapshot: u64,
+    timestamp: u64,
+    /// The version and a digest of the contents of `targets` and each delegated role, by name
+    targets: HashMap<String, (u64, Vec<u8>)>,
 }
 
 impl RepositoryEditor {
@@ -120,6 +164,7 @@ impl RepositoryEditor {
             buffer: root_buf,
             sha256: digest,
             length: root_buf_len,
+            style: MetadataStyle::Pretty,
         };
 
         let mut editor = TargetsEditor::new("targets");
@@ -131,12 +176,20 @@ impl RepositoryEditor {
             snapshot_version: None,
             snapshot_expires: None,
             snapshot_extra: None,
+            snapshot_meta_extra: HashMap::new(),
             timestamp_version: None,
             timestamp_expires: None,
             timestamp_extra: None,
+            timestamp_meta_extra: HashMap::new(),
             signed_targets: None,
             transport: None,
             limits: None,
+            loaded_versions: None,
+            allow_version_regression: false,
+            hash_algorithms: Vec::new(),
+            filename_encoding: Arc::new(PercentEncoding),
+            rekeyed_roles: HashSet::new(),
+            metadata_style: MetadataStyle::default(),
         })
     }
 
@@ -144,16 +197,41 @@ impl RepositoryEditor {
     /// `RepositoryEditor`. This `RepositoryEditor` will include all of the targets
     /// and bits of _extra metadata from the roles included. It will not, however,
     /// include the versions or expirations and the user is expected to set them.
+    ///
+    /// The loaded versions are recorded, and `sign()` fails if the new snapshot or timestamp
+    /// version isn't greater than the loaded one, or if the targets or a delegated role changed
+    /// without its version advancing; see `allow_version_regression()`.
     pub async fn from_repo<P>(root_path: P, repo: Repository) -> Result<RepositoryEditor>
     where
         P: AsRef<Path>,
     {
         let mut editor = RepositoryEditor::new(root_path).await?;
+        let mut loaded_targets = HashMap::new();
+        loaded_targets.insert(
+            "targets".to_string(),
+            (
+                repo.targets.signed.version.get(),
+                targets_digest(&repo.targets.signed)?,
+            ),
+        );
+        for role in repo.targets.signed.signed_delegated_targets() {
+            let targets = &role.signed.targets;
+            loaded_targets.insert(
+                role.signed.name,
+                (targets.version.get(), targets_digest(targets)?),
+            );
+        }
+        editor.loaded_versions = Some(LoadedVersions {
+            snapshot: repo.snapshot.signed.version.get(),
+            timestamp: repo.timestamp.signed.version.get(),
+            targets: loaded_targets,
+        });
         editor.targets(repo.targets)?;
         editor.snapshot(repo.snapshot.signed)?;
         editor.timestamp(repo.timestamp.signed)?;
         editor.transport = Some(repo.transport.clone());
         editor.limits = Some(repo.limits);
+        editor.filename_encoding = repo.filename_encoding;
         Ok(editor)
     }
 
@@ -168,9 +246,22 @@ impl RepositoryEditor {
         let root = KeyHolder::Root(self.signed_root.signed.signed.clone());
         // Sign the targets editor if able to with the provided keys
         self.sign_targets_editor(keys).await?;
-        let targets = self.signed_targets.clone().context(error::NoTargetsSnafu)?;
+        let targets = self.signed_targets.take().context(error::NoTargetsSnafu)?;
+        DelegationWalk::new(self.limits.unwrap_or_default().max_delegated_roles)
+            .check(&targets.signed)?;
+        for role in &self.rekeyed_roles {
+            // Roles removed since their keys changed don't need signing.
+            if let (Ok(parent), Ok(role_targets)) = (
+                targets.signed.parent_of(role),
+                targets.signed.delegated_targets(role),
+            ) {
+                parent
+                    .verify_role(role_targets, role)
+                    .context(error::ResignRequiredSnafu { role })?;
+            }
+        }
         let delegated_targets = targets.signed.signed_delegated_targets();
-        let signed_targets = SignedRole::from_signed(targets)?;
+        let signed_targets = SignedRole::from_signed_with_style(targets, self.metadata_style)?;
 
         let signed_delegated_targets = if delegated_targets.is_empty() {
             // If we don't have any delegated targets, there is no reason to create
@@ -181,19 +272,42 @@ impl RepositoryEditor {
             let mut roles = Vec::new();
             for role in delegated_targets {
                 // Create a `SignedRole<DelegatedTargets>` for each delegated targets
-                roles.push(SignedRole::from_signed(role)?);
+                roles.push(SignedRole::from_signed_with_style(
+                    role,
+                    self.metadata_style,
+                )?);
             }
             // SignedDelegatedTargets is a wrapper for a set of `SignedRole<DelegatedTargets>`
             Some(SignedDelegatedTargets {
                 roles,
                 consistent_snapshot: self.signed_root.signed.signed.consistent_snapshot,
+                filename_encoding: Arc::clone(&self.filename_encoding),
             })
         };
 
-        let signed_snapshot = self.build_snapshot(&signed_targets, &signed_delegated_targets)?;
-        let signed_snapshot = SignedRole::new(signed_snapshot, &root, keys, &rng).await?;
+        let mut roles = vec![("targets", &signed_targets.signed.signed)];
+        roles.extend(
+            signed_delegated_targets
+                .iter()
+                .flat_map(|delegated| &delegated.roles)
+                .map(|role| {
+                    (
+                        role.signed.signed.name.as_str(),
+                        &role.signed.signed.targets,
+                    )
+                }),
+        );
+        self.check_versions(&roles)?;
+
+        let signed_snapshot =
+            self.build_snapshot(&signed_targets, signed_delegated_targets.as_ref())?;
+        let signed_snapshot = SignedRole::new(signed_snapshot, &root, keys, &rng)
+            .await?
+            .restyle(self.metadata_style)?;
         let signed_timestamp = self.build_timestamp(&signed_snapshot)?;
-        let signed_timestamp = SignedRole::new(signed_timestamp, &root, keys, &rng).await?;
+        let signed_timestamp = SignedRole::new(signed_timestamp, &root, keys, &rng)
+            .await?
+            .restyle(self.metadata_style)?;
 
         // This validation can only be done from the top level targets.json role. This check verifies
         // that each target's delegate hierarchy is a match (i.e. its delegate ownership is valid).
@@ -212,6 +326,150 @@ impl RepositoryEditor {
         })
     }
 
+    /// Checks everything that `sign()` needs before signing anything, and returns every problem
+    /// found rather than only the first, so that tools can report them all at once. An empty list
+    /// means none of these checks would fail `sign()` with `keys`.
+    ///
+    /// This checks that the targets role being edited, snapshot.json and timestamp.json each have
+    /// a version and an expiration, that new versions advance past those loaded with
+    /// `from_repo()`, that `keys` include a threshold of the keys for each of those roles, that no
+    /// target is listed without a supported hash, and that delegated roles that lost keys through
+    /// `remove_key()` are still signed by a threshold of their keys.
+    pub async fn validate(&self, keys: &[Box<dyn KeySource>]) -> Vec<error::Error> {
+        let mut problems = Vec::new();
+        match &self.targets_editor {
+            Some(targets_editor) => problems.extend(targets_editor.problems(keys).await),
+            None if self.signed_targets.is_none() => problems.push(error::Error::NoTargets),
+            None => {}
+        }
+        for (field, missing) in [
+            ("snapshot version", self.snapshot_version.is_none()),
+            ("snapshot expiration", self.snapshot_expires.is_none()),
+            ("timestamp version", self.timestamp_version.is_none()),
+            ("timestamp expiration", self.timestamp_expires.is_none()),
+        ] {
+            if missing {
+                problems.push(error::MissingSnafu { field }.build());
+            }
+        }
+        // Compare the role being edited as it stands, and every other role as last signed.
+        let edited_targets = self
+            .targets_editor
+            .as_ref()
+            .and_then(|editor| editor.build_targets().ok());
+        let signed_delegated_targets = self
+            .signed_targets
+            .as_ref()
+            .map(|targets| targets.signed.signed_delegated_targets())
+            .unwrap_or_default();
+        let mut roles: Vec<(&str, &Targets)> = self
+            .signed_targets
+            .iter()
+            .map(|targets| ("targets", &targets.signed))
+            .chain(
+                signed_delegated_targets
+                    .iter()
+                    .map(|role| (role.signed.name.as_str(), &role.signed.targets)),
+            )
+            .collect();
+        if let Some(edited) = &edited_targets {
+            roles.retain(|(name, _)| *name != edited.name);
+            roles.push((edited.name.as_str(), &edited.targets));
+        }
+        problems.extend(self.version_regressions(&roles));
+
+        let root = KeyHolder::Root(self.signed_root.signed.signed.clone());
+        for role in [RoleType::Snapshot, RoleType::Timestamp] {
+            if let Err(err) = root
+                .check_signing_threshold(RoleId::StandardRole(role), keys)
+                .await
+            {
+                problems.push(err);
+            }
+        }
+
+        // The role being edited is checked against `keys` above. The roles it delegates are
+        // checked against its delegations as edited, which aren't in `signed_targets` yet.
+        let editing = self.targets_editor.as_ref().map(TargetsEditor::name);
+        let edited = self
+            .targets_editor
+            .as_ref()
+            .and_then(TargetsEditor::current_delegations);
+        let mut rekeyed_roles: Vec<_> = self
+            .rekeyed_roles
+            .iter()
+            .filter(|role| Some(role.as_str()) != editing)
+            .collect();
+        rekeyed_roles.sort();
+        for role in rekeyed_roles {
+            let edited_role = edited
+                .iter()
+                .flat_map(|delegations| &delegations.roles)
+                .find(|delegated_role| delegated_role.name == *role);
+            let (parent, role_targets) = match (edited_role, &self.signed_targets) {
+                (Some(delegated_role), _) => (edited.as_ref(), delegated_role.targets.as_ref()),
+                (None, Some(targets)) => (
+                    targets.signed.parent_of(role).ok(),
+                    targets.signed.delegated_targets(role).ok(),
+                ),
+                // Roles removed since their keys changed don't need signing.
+                (None, None) => (None, None),
+            };
+            if let (Some(parent), Some(role_targets)) = (parent, role_targets) {
+                if let Err(source) = parent.verify_role(role_targets, role) {
+                    problems
+                        .push(error::ResignRequiredSnafu { role: role.clone() }.into_error(source));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Returns a `RootEditor` for changing this repository's root.json. Pass the root it signs
+    /// to `root()`.
+    pub fn root_editor(&self) -> RootEditor {
+        RootEditor::new(self.signed_root.signed.clone())
+    }
+
+    /// Replace the repository's root.json, such as with one signed by `RootEditor`. Snapshot,
+    /// timestamp, and the top-level targets are signed with the new root's keys from here on;
+    /// targets that were already signed are not re-signed.
+    ///
+    /// A root with a newer version must be signed by a threshold of the current root's root
+    /// keys, as well as its own, or clients won't accept it.
+    pub fn root(&mut self, root: SignedRole<Root>) -> Result<&mut Self> {
+        let current = &self.signed_root.signed.signed;
+        let new = &root.signed;
+        ensure!(
+            new.signed.version >= current.version,
+            error::VersionRegressionSnafu {
+                role: RoleType::Root,
+                version: new.signed.version.get(),
+                loaded: current.version.get(),
+            }
+        );
+        new.signed
+            .verify_role(new)
+            .context(error::VerifyRoleMetadataSnafu {
+                role: RoleType::Root.to_string(),
+            })?;
+        if new.signed.version > current.version {
+            current
+                .verify_role(new)
+                .context(error::VerifyRoleMetadataSnafu {
+                    role: format!("root (cross-signed by version {})", current.version),
+                })?;
+        }
+
+        if let Some(editor) = &mut self.targets_editor {
+            if let Some(KeyHolder::Root(_)) = editor.key_holder {
+                editor.key_holder = Some(KeyHolder::Root(new.signed.clone()));
+            }
+        }
+        self.signed_root = root;
+        Ok(self)
+    }
+
     /// Add an existing `Targets` struct to the repository.
     pub fn targets(&mut self, targets: Signed<Targets>) -> Result<&mut Self> {
         ensure!(
@@ -232,8 +490,8 @@ impl RepositoryEditor {
         Ok(self)
     }
 
-    /// Add an existing `Snapshot` to the repository. Only the `_extra` data
-    /// is preserved
+    /// Add an existing `Snapshot` to the repository. Only the `_extra` data, and the `_extra` data
+    /// of each file it lists, is preserved
     pub fn snapshot(&mut self, snapshot: Snapshot) -> Result<&mut Self> {
         ensure!(
             snapshot.spec_version == SPEC_VERSION,
@@ -243,11 +501,18 @@ impl RepositoryEditor {
             }
         );
         self.snapshot_extra = Some(snapshot._extra);
+        self.snapshot_meta_extra = snapshot
+            .meta
+            .into_iter()
+            .filter(|(_, meta)| !meta._extra.is_empty())
+            .map(|(path, meta)| (path, meta._extra))
+            .collect();
         Ok(self)
     }
 
-    /// Add an existing `Timestamp` to the repository. Only the `_extra` data
-    /// is preserved
+    /// Add an existing `Timestamp` to the repository. Only the `_extra` data, and the `_extra`
+    /// data of its listing of snapshot.json, is preserved. The timestamp this editor signs only
+    /// ever describes snapshot.json, so a warning is logged if `timestamp` describes other files.
     pub fn timestamp(&mut self, timestamp: Timestamp) -> Result<&mut Self> {
         ensure!(
             timestamp.spec_version == SPEC_VERSION,
@@ -256,15 +521,73 @@ impl RepositoryEditor {
                 supported: SPEC_VERSION
             }
         );
+        let unexpected = timestamp.unexpected_meta();
+        if !unexpected.is_empty() {
+            warn!(
+                "Dropping files other than snapshot.json from timestamp metadata: {}",
+                unexpected.join(", ")
+            );
+        }
+        self.timestamp_meta_extra = timestamp
+            .meta
+            .get("snapshot.json")
+            .map(|meta| meta._extra.clone())
+            .unwrap_or_default();
         self.timestamp_extra = Some(timestamp._extra);
         Ok(self)
     }
 
+    /// Sets the extension field `name` of the listing of `role`'s metadata in snapshot.json, such
+    /// as a deployment channel. `role` is `targets` or the name of a delegated role. Fields set
+    /// here, or loaded with `snapshot()`, are written each time the snapshot is signed. See
+    /// [`Metafile::extension`](crate::schema::Metafile::extension) to read them back.
+    pub fn snapshot_meta_extension<T: Serialize>(
+        &mut self,
+        role: &str,
+        name: &str,
+        value: &T,
+    ) -> Result<&mut Self> {
+        let path = format!("{role}.json");
+        let value = Metafile::extension_value(name, value)
+            .context(error::MetafileExtensionSnafu { path: &path })?;
+        self.snapshot_meta_extra
+            .entry(path)
+            .or_default()
+            .insert(name.to_owned(), value);
+        Ok(self)
+    }
+
+    /// Sets the extension field `name` of the listing of snapshot.json in timestamp.json. Fields
+    /// set here, or loaded with `timestamp()`, are written each time the timestamp is signed.
+    pub fn timestamp_meta_extension<T: Serialize>(
+        &mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<&mut Self> {
+        let value =
+            Metafile::extension_value(name, value).context(error::MetafileExtensionSnafu {
+                path: "snapshot.json",
+            })?;
+        self.timestamp_meta_extra.insert(name.to_owned(), value);
+        Ok(self)
+    }
+
     /// Returns a mutable reference to the targets editor if it exists
     fn targets_editor_mut(&mut self) -> Result<&mut TargetsEditor> {
         self.targets_editor.as_mut().ok_or(error::Error::NoTargets)
     }
 
+    /// Adds signatures by keys being rotated out of the targets role currently being edited, to be
+    /// kept alongside the new signatures. See [`TargetsEditor::add_old_signatures`].
+    pub fn add_old_targets_signatures(
+        &mut self,
+        old_signatures: Vec<Signature>,
+    ) -> Result<&mut Self> {
+        self.targets_editor_mut()?
+            .add_old_signatures(old_signatures);
+        Ok(self)
+    }
+
     /// Add a `Target` to the repository
     pub fn add_target<T, E>(&mut self, name: T, target: Target) -> Result<&mut Self>
     where
@@ -292,8 +615,10 @@ impl RepositoryEditor {
     where
         P: AsRef<Path>,
     {
-        let (target_name, target) = RepositoryEditor::build_target(target_path).await?;
-        self.add_target(target_name, target)?;
+        let (target_name, target) =
+            Self::build_target_with_hashes(target_path, &self.hash_algorithms).await?;
+        self.targets_editor_mut()?
+            .add_target_keeping_custom(target_name, target)?;
         Ok(self)
     }
 
@@ -305,15 +630,59 @@ impl RepositoryEditor {
         P: AsRef<Path>,
     {
         for target in targets {
-            let (target_name, target) = RepositoryEditor::build_target(target).await?;
-            self.add_target(target_name, target)?;
+            let (target_name, target) =
+                Self::build_target_with_hashes(target, &self.hash_algorithms).await?;
+            self.targets_editor_mut()?
+                .add_target_keeping_custom(target_name, target)?;
         }
 
         Ok(self)
     }
 
+    /// Add a target to the repository using its path, with `custom` as its custom metadata.
+    ///
+    /// See the note on `add_target_path()` regarding performance.
+    pub async fn add_target_with_custom<P>(
+        &mut self,
+        target_path: P,
+        custom: HashMap<String, Value>,
+    ) -> Result<&mut Self>
+    where
+        P: AsRef<Path>,
+    {
+        let (target_name, target) =
+            Self::build_target_with_hashes(target_path, &self.hash_algorithms).await?;
+        self.add_target(
+            target_name,
+            TargetBuilder::from(target).custom_map(custom).build(),
+        )
+    }
+
+    /// Replaces the custom metadata of the target `name`, which must be listed by the targets
+    /// role being edited.
+    pub fn set_target_custom(
+        &mut self,
+        name: &TargetName,
+        custom: HashMap<String, Value>,
+    ) -> Result<&mut Self> {
+        self.targets_editor_mut()?.set_target_custom(name, custom)?;
+        Ok(self)
+    }
+
     /// Builds a target struct for the given path
     pub async fn build_target<P>(target_path: P) -> Result<(TargetName, Target)>
+    where
+        P: AsRef<Path>,
+    {
+        Self::build_target_with_hashes(target_path, &[]).await
+    }
+
+    /// Builds a target struct for the given path that lists its hash with each of `algorithms`,
+    /// as well as its `sha256` hash.
+    pub async fn build_target_with_hashes<P>(
+        target_path: P,
+        algorithms: &[HashAlgorithm],
+    ) -> Result<(TargetName, Target)>
     where
         P: AsRef<Path>,
     {
@@ -329,7 +698,7 @@ impl RepositoryEditor {
         )?;
 
         // Build a Target from the path given. If it is not a file, this will fail
-        let target = Target::from_path(target_path)
+        let target = Target::from_path_with_hashes(target_path, algorithms)
             .await
             .context(error::TargetFromPathSnafu { path: target_path })?;
 
@@ -362,26 +731,8 @@ impl RepositoryEditor {
         // Sign the new targets
         let new_targets = new_targets_editor.create_signed(key_source).await?;
         // Find the keyids for key_source
-        let mut keyids = Vec::new();
-        let mut key_pairs = HashMap::new();
-        for source in key_source {
-            let key_pair = source
-                .as_sign()
-                .await
-                .context(error::KeyPairFromKeySourceSnafu)?
-                .tuf_key();
-            keyids.push(
-                key_pair
-                    .key_id()
-                    .context(error::JsonSerializationSnafu {})?,
-            );
-            key_pairs.insert(
-                key_pair
-                    .key_id()
-                    .context(error::JsonSerializationSnafu {})?,
-                key_pair,
-            );
-        }
+        let key_pairs = public_keys(key_source).await?;
+        let keyids = key_pairs.keys().cloned().collect();
         // Add the new role to targets_editor
         self.targets_editor_mut()?.delegate_role(
             new_targets,
@@ -394,6 +745,24 @@ impl RepositoryEditor {
         Ok(self)
     }
 
+    #[allow(clippy::too_many_arguments)]
+    /// Delegates the targets of the `Targets` in `targets_editor` to `count` hashed bins, as
+    /// described by [`HashedBins`](crate::schema::HashedBins), each trusted with `key_source`. Add targets to a bin with
+    /// `change_delegated_targets(bins.bin_for(&name))`.
+    pub async fn delegate_hashed_bins(
+        &mut self,
+        count: NonZeroU64,
+        key_source: &[Box<dyn KeySource>],
+        threshold: NonZeroU64,
+        expiration: DateTime<Utc>,
+        version: NonZeroU64,
+    ) -> Result<&mut Self> {
+        self.targets_editor_mut()?
+            .delegate_hashed_bins(count, key_source, threshold, expiration, version)
+            .await?;
+        Ok(self)
+    }
+
     /// Set the `Snapshot` version
     pub fn snapshot_version(&mut self, snapshot_version: NonZeroU64) -> &mut Self {
         self.snapshot_version = Some(snapshot_version);
@@ -430,6 +799,49 @@ impl RepositoryEditor {
         self
     }
 
+    /// Allow `sign()` to produce snapshot or timestamp versions that are not greater than those of
+    /// the repository loaded with `from_repo()`, and targets or delegated roles that changed
+    /// without their versions advancing. A warning is logged instead of failing.
+    pub fn allow_version_regression(&mut self, allow: bool) -> &mut Self {
+        self.allow_version_regression = allow;
+        self
+    }
+
+    /// Set how `sign()` serializes targets, delegated targets, snapshot and timestamp metadata. The
+    /// default is [`MetadataStyle::Pretty`]; [`MetadataStyle::Compact`] makes large targets
+    /// metadata much smaller. root.json is written exactly as it was read.
+    pub fn metadata_style(&mut self, style: MetadataStyle) -> &mut Self {
+        self.metadata_style = style;
+        self
+    }
+
+    /// Fetch delegated roles' metadata in `update_delegated_targets()` and `add_role()` with the
+    /// [`ToughContext`]'s transport, rather than the transport of the repository passed to
+    /// `from_repo()`. An editor created with `new()` has no transport until this is called, and
+    /// uses the default [`Limits`].
+    pub fn context(&mut self, context: &ToughContext) -> &mut Self {
+        self.transport = Some(context.boxed_transport());
+        self.limits.get_or_insert_with(Limits::default);
+        self
+    }
+
+    /// List hashes with each of `algorithms`, as well as the `sha256` hash, for the targets added
+    /// with `add_target_path()` or `add_target_paths()` and for the metadata listed in snapshot
+    /// and timestamp metadata.
+    pub fn hash_algorithms(&mut self, algorithms: &[HashAlgorithm]) -> &mut Self {
+        self.hash_algorithms = algorithms.to_vec();
+        self
+    }
+
+    /// Set the [`FilenameEncoding`] that turns delegated role names into the filenames their
+    /// metadata is fetched from by `update_delegated_targets()` and `add_role()`, and written to
+    /// by the `SignedRepository` that `sign()` returns. An editor created with `from_repo()` uses
+    /// the encoding the repository was loaded with; otherwise the default is [`PercentEncoding`].
+    pub fn filename_encoding<E: FilenameEncoding + 'static>(&mut self, encoding: E) -> &mut Self {
+        self.filename_encoding = Arc::new(encoding);
+        self
+    }
+
     /// Takes the current Targets from `targets_editor` and inserts the role to its proper place in `signed_targets`
     /// Sets `targets_editor` to None
     /// Must be called before `change_delegated_targets()`
@@ -476,7 +888,7 @@ impl RepositoryEditor {
                 .context(error::DelegateMissingSnafu {
                     name: role.to_string(),
                 })?
-                .clone();
+                .without_role_targets();
             let targets = targets
                 .delegated_targets(role)
                 .context(error::DelegateMissingSnafu {
@@ -513,7 +925,7 @@ impl RepositoryEditor {
             .signed;
         let metadata_base_url = parse_url(metadata_url)?;
         // path to updated metadata
-        let encoded_name = encode_filename(name);
+        let encoded_name = encode_role_name(self.filename_encoding.as_ref(), name)?;
         let encoded_filename = format!("{encoded_name}.json");
         let role_url = metadata_base_url
             .join(&encoded_filename)
@@ -530,10 +942,9 @@ impl RepositoryEditor {
             "max targets limit",
         )
         .await?;
-        let data = stream
-            .into_vec()
-            .await
-            .context(error::TransportSnafu { url: role_url })?;
+        let data = stream.into_vec().await.context(error::TransportSnafu {
+            url: role_url.clone(),
+        })?;
         // Load incoming role metadata as Signed<Targets>
         let mut role: Signed<crate::schema::Targets> =
             serde_json::from_slice(&data).context(error::ParseMetadataSnafu {
@@ -565,7 +976,13 @@ impl RepositoryEditor {
         ensure!(
             role.signed.version >= current_targets.version,
             error::VersionMismatchSnafu {
-                role: RoleType::Targets,
+                role: if name == "targets" {
+                    RoleType::Targets
+                } else {
+                    RoleType::DelegatedTargets
+                },
+                name,
+                url: role_url,
                 fetched: role.signed.version,
                 expected: current_targets.version
             }
@@ -573,17 +990,28 @@ impl RepositoryEditor {
         // get a list of roles that we don't have metadata for yet
         // and copy current_targets delegated targets to role
         let new_roles = current_targets.update_targets(&mut role);
+        // refuse incoming metadata that delegates back to `name` or one of its delegators
+        let mut walk = DelegationWalk::starting_at(
+            &self
+                .signed_targets
+                .as_ref()
+                .context(error::NoTargetsSnafu)?
+                .signed,
+            name,
+            limits.max_delegated_roles,
+        );
+        walk.check(&role.signed)?;
         let delegations = role
             .signed
             .delegations
             .as_mut()
             .context(error::NoDelegationsSnafu)?;
         // the new targets will be the keyholder for any of its newly delegated roles, so create a keyholder
-        let key_holder = KeyHolder::Delegations(delegations.clone());
+        let key_holder = KeyHolder::Delegations(delegations.without_role_targets());
         // load the new roles
         for name in new_roles {
             // path to new metadata
-            let encoded_name = encode_filename(&name);
+            let encoded_name = encode_role_name(self.filename_encoding.as_ref(), &name)?;
             let encoded_filename = format!("{encoded_name}.json");
             let role_url = metadata_base_url
                 .join(&encoded_filename)
@@ -654,8 +1082,10 @@ impl RepositoryEditor {
             .as_ref()
             .context(error::MissingTransportSnafu)?
             .clone();
+        let filename_encoding = Arc::clone(&self.filename_encoding);
         self.targets_editor_mut()?.limits(limits);
         self.targets_editor_mut()?.transport(transport.clone());
+        self.targets_editor_mut()?.filename_encoding = filename_encoding;
         self.targets_editor_mut()?
             .add_role(name, metadata_url, paths, threshold, keys)
             .await?;
@@ -663,13 +1093,154 @@ impl RepositoryEditor {
         Ok(self)
     }
 
+    /// Adds a role to the targets currently in `targets_editor` from its signed metadata, such as
+    /// metadata that was received from the role's owner rather than fetched from a URL.
+    /// `delegate_signed_role()` uses `TargetsEditor::delegate_role()`.
+    pub fn delegate_signed_role(
+        &mut self,
+        targets: Signed<DelegatedTargets>,
+        paths: PathSet,
+        key_pairs: HashMap<Decoded<Hex>, Key>,
+        keyids: Vec<Decoded<Hex>>,
+        threshold: NonZeroU64,
+    ) -> Result<&mut Self> {
+        self.targets_editor_mut()?
+            .delegate_role(targets, paths, key_pairs, keyids, threshold)?;
+        Ok(self)
+    }
+
+    /// Adds `keys` to the delegations of the targets currently in `targets_editor`, and to the
+    /// delegated role `role` if it is provided. See `TargetsEditor::add_key()`.
+    pub fn add_key(
+        &mut self,
+        keys: HashMap<Decoded<Hex>, Key>,
+        role: Option<&str>,
+    ) -> Result<&mut Self> {
+        self.targets_editor_mut()?.add_key(keys, role)?;
+        Ok(self)
+    }
+
+    /// Removes `keyid` from the delegated role `role` of the targets currently in
+    /// `targets_editor`, or from its delegations entirely if no role is provided. See
+    /// `TargetsEditor::remove_key()`.
+    ///
+    /// Signatures by the removed key no longer count towards the threshold of the roles that
+    /// trusted it. `sign()` fails unless each of those roles is still signed by a threshold of its
+    /// remaining keys, so a role that falls short must be re-signed with
+    /// `change_delegated_targets()` and `sign_targets_editor()` first.
+    pub fn remove_key(&mut self, keyid: &Decoded<Hex>, role: Option<&str>) -> Result<&mut Self> {
+        let targets_editor = self.targets_editor_mut()?;
+        let rekeyed = match role {
+            Some(role) => vec![role.to_owned()],
+            None => targets_editor.roles_trusting(keyid),
+        };
+        targets_editor.remove_key(keyid, role)?;
+        self.rekeyed_roles.extend(rekeyed);
+        Ok(self)
+    }
+
+    /// Removes the delegated role `role` from the targets currently in `targets_editor`, along
+    /// with its targets and the roles it delegates. See `TargetsEditor::remove_role()`.
+    pub fn remove_role(&mut self, role: &str, recursive: bool) -> Result<&mut Self> {
+        self.targets_editor_mut()?.remove_role(role, recursive)?;
+        Ok(self)
+    }
+
     // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 
+    /// Fails if a new version doesn't advance past the version loaded with `from_repo()`, unless
+    /// `allow_version_regression()` was set.
+    fn check_versions(&self, roles: &[(&str, &Targets)]) -> Result<()> {
+        match self.version_regressions(roles).into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns an error for each new version that doesn't advance past the version loaded with
+    /// `from_repo()`, or logs a warning for each if `allow_version_regression()` was set. `roles`
+    /// are the targets and delegated roles to be signed, by name; these only need a new version
+    /// if their contents changed.
+    fn version_regressions(&self, roles: &[(&str, &Targets)]) -> Vec<error::Error> {
+        let mut regressions = Vec::new();
+        let Some(loaded) = &self.loaded_versions else {
+            return regressions;
+        };
+        for (role, version, loaded) in [
+            (RoleType::Snapshot, self.snapshot_version, loaded.snapshot),
+            (
+                RoleType::Timestamp,
+                self.timestamp_version,
+                loaded.timestamp,
+            ),
+        ] {
+            // A missing version is reported when the role is built
+            let Some(version) = version.map(NonZeroU64::get) else {
+                continue;
+            };
+            if version > loaded {
+                continue;
+            }
+            if self.allow_version_regression {
+                warn!(
+                    "New {role} version {version} is not greater than the loaded version {loaded}"
+                );
+            } else {
+                regressions.push(
+                    error::VersionRegressionSnafu {
+                        role,
+                        version,
+                        loaded,
+                    }
+                    .build(),
+                );
+            }
+        }
+        for (name, targets) in roles {
+            let Some((loaded, loaded_digest)) = loaded.targets.get(*name) else {
+                continue;
+            };
+            let version = targets.version.get();
+            if version > *loaded {
+                continue;
+            }
+            // A role that fails to serialize fails `sign()` anyway
+            if targets_digest(targets).map_or(true, |digest| digest == *loaded_digest) {
+                continue;
+            }
+            if self.allow_version_regression {
+                warn!(
+                    "Role '{name}' changed, but its new version {version} is not greater than the \
+                     loaded version {loaded}"
+                );
+            } else if *name == "targets" {
+                regressions.push(
+                    error::VersionRegressionSnafu {
+                        role: RoleType::Targets,
+                        version,
+                        loaded: *loaded,
+                    }
+                    .build(),
+                );
+            } else {
+                regressions.push(
+                    error::DelegatedVersionRegressionSnafu {
+                        name: *name,
+                        version,
+                        loaded: *loaded,
+                    }
+                    .build(),
+                );
+            }
+        }
+        regressions
+    }
+
     /// Build the `Snapshot` struct
     fn build_snapshot(
         &self,
         signed_targets: &SignedRole<Targets>,
-        signed_delegated_targets: &Option<SignedDelegatedTargets>,
+        signed_delegated_targets: Option<&SignedDelegatedTargets>,
     ) -> Result<Snapshot> {
         let version = self.snapshot_version.context(error::MissingSnafu {
             field: "snapshot version",
@@ -682,35 +1253,39 @@ impl RepositoryEditor {
         let mut snapshot = Snapshot::new(SPEC_VERSION.to_string(), version, expires);
 
         // Snapshot stores metadata about targets and root
-        let targets_meta = Self::snapshot_meta(signed_targets);
+        let targets_meta = Self::snapshot_meta(signed_targets, &self.hash_algorithms);
         snapshot
             .meta
             .insert("targets.json".to_owned(), targets_meta);
 
-        if let Some(signed_delegated_targets) = signed_delegated_targets.as_ref() {
+        if let Some(signed_delegated_targets) = signed_delegated_targets {
             for delegated_targets in &signed_delegated_targets.roles {
-                let meta = Self::snapshot_meta(delegated_targets);
+                let meta = Self::snapshot_meta(delegated_targets, &self.hash_algorithms);
                 snapshot.meta.insert(
                     format!("{}.json", delegated_targets.signed.signed.name),
                     meta,
                 );
             }
         }
+        for (path, meta) in &mut snapshot.meta {
+            if let Some(extra) = self.snapshot_meta_extra.get(path) {
+                meta._extra.clone_from(extra);
+            }
+        }
+        snapshot._extra = _extra;
 
         Ok(snapshot)
     }
 
     /// Build a `Metafiles` struct from a given `SignedRole<R>`. This metadata
-    /// includes the sha256 and length of the signed role.
-    fn snapshot_meta<R>(role: &SignedRole<R>) -> Metafile
+    /// includes the length of the signed role and its hash with each of `algorithms`, as well as
+    /// its sha256.
+    fn snapshot_meta<R>(role: &SignedRole<R>, algorithms: &[HashAlgorithm]) -> Metafile
     where
         R: Role,
     {
         Metafile {
-            hashes: Some(Hashes {
-                sha256: role.sha256.to_vec().into(),
-                _extra: HashMap::new(),
-            }),
+            hashes: Some(Hashes::compute(&role.buffer, algorithms)),
             length: Some(role.length),
             version: role.signed.signed.version(),
             _extra: HashMap::new(),
@@ -729,7 +1304,8 @@ impl RepositoryEditor {
         let mut timestamp = Timestamp::new(SPEC_VERSION.to_string(), version, expires);
 
         // Timestamp stores metadata about snapshot
-        let snapshot_meta = Self::timestamp_meta(signed_snapshot);
+        let mut snapshot_meta = Self::timestamp_meta(signed_snapshot, &self.hash_algorithms);
+        snapshot_meta._extra.clone_from(&self.timestamp_meta_extra);
         timestamp
             .meta
             .insert("snapshot.json".to_owned(), snapshot_meta);
@@ -739,16 +1315,14 @@ impl RepositoryEditor {
     }
 
     /// Build a `Metafiles` struct from a given `SignedRole<R>`. This metadata
-    /// includes the sha256 and length of the signed role.
-    fn timestamp_meta<R>(role: &SignedRole<R>) -> Metafile
+    /// includes the length of the signed role and its hash with each of `algorithms`, as well as
+    /// its sha256.
+    fn timestamp_meta<R>(role: &SignedRole<R>, algorithms: &[HashAlgorithm]) -> Metafile
     where
         R: Role,
     {
         Metafile {
-            hashes: Some(Hashes {
-                sha256: role.sha256.to_vec().into(),
-                _extra: HashMap::new(),
-            }),
+            hashes: Some(Hashes::compute(&role.buffer, algorithms)),
             length: Some(role.length),
             version: role.signed.signed.version(),
             _extra: HashMap::new(),
@@ -756,6 +1330,40 @@ impl RepositoryEditor {
     }
 }
 
+/// Moves `expires` earlier by a random amount of up to `max_jitter`, to the second, so that it
+/// never comes later than requested. Fails if that would move it before the earliest supported
+/// date.
+///
+/// Metadata that is re-signed on a fixed schedule otherwise expires at the same instant for every
+/// client; jittering a role's expiration each time it is signed (for example with
+/// [`RepositoryEditor::timestamp_expires`]) spreads out the refreshes that clients make ahead of
+/// it.
+pub fn jitter_expiration(
+    expires: DateTime<Utc>,
+    max_jitter: chrono::TimeDelta,
+) -> Result<DateTime<Utc>> {
+    let jitter = crate::jitter::random_duration(max_jitter)?;
+    expires
+        .checked_sub_signed(jitter)
+        .context(error::DatetimeOutOfRangeSnafu {
+            datetime: expires,
+            delta: jitter,
+        })
+}
+
+/// A digest of the canonical JSON of `targets`, used to tell whether a role changed. Delegated
+/// roles' own metadata isn't part of their parent's JSON.
+fn targets_digest(targets: &Targets) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
+    targets
+        .serialize(&mut ser)
+        .context(error::SerializeRoleSnafu {
+            role: RoleType::Targets.to_string(),
+        })?;
+    Ok(aws_lc_rs::digest::digest(&SHA256, &data).as_ref().to_vec())
+}
+
 fn parse_url(url: &str) -> Result<Url> {
     let mut url = Cow::from(url);
     if !url.ends_with('/') {
@@ -0,0 +1,238 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides a `RootEditor` object for changing root.json and signing the new version.
+
+use crate::editor::signed::{merge_signatures, SignedRole};
+use crate::error::{self, Result};
+use crate::key_source::KeySource;
+use crate::schema::decoded::{Decoded, Hex};
+use crate::schema::key::Key;
+use crate::schema::{KeyHolder, RoleKeys, RoleType, Root, Signature, Signed};
+use aws_lc_rs::rand::SystemRandom;
+use chrono::{DateTime, Utc};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+
+/// `RootEditor` makes changes to a root.json and signs the result, the way `tuftool root` does.
+///
+/// The editor starts from a signed root, which is kept as the *previous* root. When the edited
+/// root has a newer version, `sign()` cross-signs it: clients only accept a new root that is
+/// signed by a threshold of both the previous root's root keys and its own, so `sign()` signs with
+/// each of the given keys that either root lists for the root role.
+///
+/// Any change discards the signatures the root was loaded with. A root that is only being signed
+/// by more keys, without changes, keeps them, so signatures can be gathered from several parties
+/// with `allow_partial_signatures()`.
+#[derive(Debug, Clone)]
+pub struct RootEditor {
+    previous: Root,
+    root: Root,
+    signatures: Vec<Signature>,
+    allow_partial_signatures: bool,
+}
+
+impl RootEditor {
+    /// Creates a `RootEditor` for changing `root`, which is also the root the result is
+    /// cross-signed by if its version is bumped.
+    pub fn new(root: Signed<Root>) -> Self {
+        Self {
+            previous: root.signed.clone(),
+            root: root.signed,
+            signatures: root.signatures,
+            allow_partial_signatures: false,
+        }
+    }
+
+    /// Sets the root that clients currently trust, which the new root is cross-signed by. This is
+    /// needed when the root being edited has already had its version bumped.
+    pub fn cross_sign(&mut self, previous: Root) -> &mut Self {
+        self.previous = previous;
+        self
+    }
+
+    /// The root as currently edited.
+    pub fn root(&self) -> &Root {
+        &self.root
+    }
+
+    /// Set the version of root.json.
+    pub fn version(&mut self, version: NonZeroU64) -> &mut Self {
+        self.root.version = version;
+        self.changed()
+    }
+
+    /// Increment the version of root.json by one.
+    pub fn bump_version(&mut self) -> Result<&mut Self> {
+        self.root.version = self
+            .root
+            .version
+            .checked_add(1)
+            .context(error::RootVersionOverflowSnafu)?;
+        Ok(self.changed())
+    }
+
+    /// Set the expiration of root.json.
+    pub fn expires(&mut self, expires: DateTime<Utc>) -> &mut Self {
+        self.root.expires = expires;
+        self.changed()
+    }
+
+    /// Set whether the repository uses consistent snapshots.
+    pub fn consistent_snapshot(&mut self, consistent_snapshot: bool) -> &mut Self {
+        self.root.consistent_snapshot = consistent_snapshot;
+        self.changed()
+    }
+
+    /// Set the signature threshold of a top-level role, adding the role with no keys if root.json
+    /// doesn't list it.
+    pub fn threshold(&mut self, role: RoleType, threshold: NonZeroU64) -> &mut Self {
+        self.root
+            .roles
+            .entry(role)
+            .and_modify(|role_keys| role_keys.threshold = threshold)
+            .or_insert_with(|| RoleKeys {
+                keyids: Vec::new(),
+                threshold,
+                _extra: HashMap::new(),
+            });
+        self.changed()
+    }
+
+    /// Adds `key` to root.json if it isn't already listed, and authorizes it for each of `roles`.
+    /// Returns the key's ID.
+    ///
+    /// A key whose public key is already listed under another key ID is refused, since it would
+    /// let one signer count twice towards a threshold.
+    pub fn add_key(&mut self, key: Key, roles: &[RoleType]) -> Result<Decoded<Hex>> {
+        let key_id = if let Some((key_id, _)) = self.root.keys.iter().find(|(_, k)| **k == key) {
+            key_id.clone()
+        } else {
+            if let Some((existing, _)) = self
+                .root
+                .keys
+                .iter()
+                .find(|(_, k)| k.public_key() == key.public_key())
+            {
+                return error::RootKeyDuplicateSnafu {
+                    key_id: hex::encode(existing),
+                }
+                .fail();
+            }
+            let key_id = key.key_id().context(error::JsonSerializationSnafu)?;
+            self.root.keys.insert(key_id.clone(), key);
+            key_id
+        };
+
+        for role in roles {
+            let role_keys = self.root.roles.entry(*role).or_insert_with(|| RoleKeys {
+                keyids: Vec::new(),
+                threshold: NonZeroU64::MIN,
+                _extra: HashMap::new(),
+            });
+            if !role_keys.keyids.contains(&key_id) {
+                role_keys.keyids.push(key_id.clone());
+                role_keys.keyids.sort();
+            }
+        }
+        self.changed();
+        Ok(key_id)
+    }
+
+    /// Removes a key from `role`, or if `role` is `None`, from every role and from root.json.
+    ///
+    /// Roles that tough doesn't manage, in [`Root::unrecognized_roles`], are left unchanged, so a
+    /// key that one of them lists stays in root.json.
+    pub fn remove_key(&mut self, key_id: &Decoded<Hex>, role: Option<RoleType>) -> &mut Self {
+        if let Some(role) = role {
+            if let Some(role_keys) = self.root.roles.get_mut(&role) {
+                role_keys.keyids.retain(|k| k != key_id);
+            }
+        } else {
+            for role_keys in self.root.roles.values_mut() {
+                role_keys.keyids.retain(|k| k != key_id);
+            }
+            if !self.root.unrecognized_role_lists_key(key_id) {
+                self.root.keys.remove(key_id);
+            }
+        }
+        self.changed()
+    }
+
+    /// Allow `sign()` to return a root that isn't yet signed by enough keys, so that other
+    /// parties can add their signatures with another `RootEditor`.
+    pub fn allow_partial_signatures(&mut self, allow: bool) -> &mut Self {
+        self.allow_partial_signatures = allow;
+        self
+    }
+
+    /// Signs the root with each of `keys` that the new root, or the previous root when
+    /// cross-signing, lists for the root role.
+    ///
+    /// Unless partial signatures are allowed, this fails if the new root isn't signed by a
+    /// threshold of its own root keys and, when cross-signing, the previous root's.
+    pub async fn sign(&self, keys: &[Box<dyn KeySource>]) -> Result<SignedRole<Root>> {
+        let rng = SystemRandom::new();
+        let cross_signing = self.root.version > self.previous.version;
+        ensure!(
+            self.root.version >= self.previous.version,
+            error::VersionRegressionSnafu {
+                role: RoleType::Root,
+                version: self.root.version.get(),
+                loaded: self.previous.version.get(),
+            }
+        );
+
+        let mut signed = Signed {
+            signed: self.root.clone(),
+            signatures: Vec::new(),
+        };
+        let mut signers = vec![&self.root];
+        if cross_signing {
+            signers.push(&self.previous);
+        }
+        for signer in signers {
+            // Keys may belong to only one of the two roots, such as keys being rotated out.
+            match SignedRole::sign_with_keys(
+                self.root.clone(),
+                &KeyHolder::Root(signer.clone()),
+                keys,
+                &rng,
+            )
+            .await
+            {
+                Ok((role, _)) => merge_signatures(&mut signed, role.signatures),
+                Err(error::Error::KeysNotFoundInRoot { .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        ensure!(
+            !signed.signatures.is_empty(),
+            error::KeysNotFoundInRootSnafu
+        );
+        merge_signatures(&mut signed, self.signatures.clone());
+
+        if !self.allow_partial_signatures {
+            self.root
+                .verify_role(&signed)
+                .context(error::VerifyRoleMetadataSnafu {
+                    role: RoleType::Root.to_string(),
+                })?;
+            if cross_signing {
+                self.previous
+                    .verify_role(&signed)
+                    .context(error::VerifyRoleMetadataSnafu {
+                        role: format!("root (cross-signed by version {})", self.previous.version),
+                    })?;
+            }
+        }
+        SignedRole::from_signed(signed)
+    }
+
+    /// Discards the signatures the root was loaded with, which no longer cover its contents.
+    fn changed(&mut self) -> &mut Self {
+        self.signatures.clear();
+        self
+    }
+}
@@ -6,9 +6,11 @@
 //! Provides the `SignedDelegatedTargets` object which represents the output of `TargetsEditor` after
 //! signing, ready to be written to disk.
 
+use super::sorted::SortedFormatter;
 use crate::error::{self, Result};
 use crate::io::{is_file, DigestAdapter};
 use crate::key_source::KeySource;
+use crate::schema::decoded::{Decoded, Hex};
 use crate::schema::{
     DelegatedTargets, KeyHolder, Role, RoleType, Root, Signature, Signed, Snapshot, Target,
     Targets, Timestamp,
@@ -16,25 +18,53 @@ use crate::schema::{
 use async_trait::async_trait;
 use aws_lc_rs::digest::{digest, SHA256, SHA256_OUTPUT_LEN};
 use aws_lc_rs::rand::SecureRandom;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use futures::TryStreamExt;
 use olpc_cjson::CanonicalFormatter;
 use serde::{Deserialize, Serialize};
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter};
 use serde_plain::derive_fromstr_from_deserialize;
 use snafu::{ensure, OptionExt, ResultExt};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::future::{ready, Future};
-use tokio::fs::{canonicalize, copy, create_dir_all, remove_file, symlink_metadata};
+use std::io::Write;
+use tokio::fs::{canonicalize, copy, create_dir_all, hard_link, remove_file, symlink_metadata};
 
 #[cfg(not(target_os = "windows"))]
 use tokio::fs::symlink;
 #[cfg(target_os = "windows")]
 use tokio::fs::symlink_file as symlink;
 
-use crate::{FilesystemTransport, TargetName, Transport};
+use crate::filename_encoding::role_filename;
+use crate::tsa::{self, TimestampAuthority};
+use crate::{
+    FilenameEncoding, FilesystemTransport, HashAlgorithm, MetadataBundle, TargetName, Transport,
+};
 use std::borrow::Cow;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use url::Url;
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
+
+/// Role buffers are produced by `serde_json`, so they are always valid UTF-8.
+fn buffer_string(buffer: &[u8]) -> String {
+    String::from_utf8_lossy(buffer).into_owned()
+}
+
+/// How the editor serializes the metadata it signs. Either way, object keys are sorted so that the
+/// same role always produces the same file, and the length and hashes listed for a role in other
+/// roles' metadata are those of exactly what is written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetadataStyle {
+    /// Indented JSON with a trailing newline, which is easy to read and diff.
+    #[default]
+    Pretty,
+    /// JSON without any whitespace, which keeps large targets metadata small.
+    Compact,
+}
+derive_fromstr_from_deserialize!(MetadataStyle);
 
 /// A signed role, including its serialized form (`buffer`) which is meant to
 /// be written to file. The `sha256` and `length` are calculated from this
@@ -49,6 +79,7 @@ pub struct SignedRole<T> {
     pub(crate) buffer: Vec<u8>,
     pub(crate) sha256: [u8; SHA256_OUTPUT_LEN],
     pub(crate) length: u64,
+    pub(crate) style: MetadataStyle,
 }
 
 impl<T> SignedRole<T>
@@ -62,6 +93,25 @@ where
         keys: &[Box<dyn KeySource>],
         rng: &(dyn SecureRandom + Sync),
     ) -> Result<Self> {
+        let (role, threshold) = Self::sign_with_keys(role, key_holder, keys, rng).await?;
+
+        // since for root the check depends on cross-sign
+        if T::TYPE != RoleType::Root && threshold > role.signatures.len() as u64 {
+            return Err(error::Error::SigningKeysNotFound {
+                role: T::TYPE.to_string(),
+            });
+        }
+        SignedRole::from_signed(role)
+    }
+
+    /// Signs `role` with each of `keys` that `key_holder` lists for it, returning the signed role
+    /// and its threshold without checking that the threshold is met.
+    pub(crate) async fn sign_with_keys(
+        role: T,
+        key_holder: &KeyHolder,
+        keys: &[Box<dyn KeySource>],
+        rng: &(dyn SecureRandom + Sync),
+    ) -> Result<(Signed<T>, u64)> {
         let root_keys = key_holder.get_keys(keys).await?;
 
         let role_keys = key_holder.role_keys(role.role_id())?;
@@ -93,31 +143,38 @@ where
                 .context(error::SignMessageSnafu)?;
 
             // Add the signatures to the `Signed` struct for this role
-            role.signatures.push(Signature {
-                keyid: signing_key_id.clone(),
-                sig: sig.into(),
-            });
+            role.signatures
+                .push(Signature::new(signing_key_id.clone(), sig.into()));
         }
 
-        // since for root the check depends on cross-sign
-        if T::TYPE != RoleType::Root && role_keys.threshold.get() > role.signatures.len() as u64 {
-            return Err(error::Error::SigningKeysNotFound {
-                role: T::TYPE.to_string(),
-            });
-        }
-        SignedRole::from_signed(role)
+        Ok((role, role_keys.threshold.get()))
     }
 
     /// Creates a `SignedRole<Role>` from a `Signed<Role>`.
     /// This is used to create signed roles for any signed metadata
     pub(crate) fn from_signed(role: Signed<T>) -> Result<SignedRole<T>> {
-        // Serialize the role, and calculate its length and
-        // sha256.
-        let mut buffer =
-            serde_json::to_vec_pretty(&role).context(error::SerializeSignedRoleSnafu {
-                role: T::TYPE.to_string(),
-            })?;
-        buffer.push(b'\n');
+        Self::from_signed_with_style(role, MetadataStyle::Pretty)
+    }
+
+    /// Creates a `SignedRole<Role>` from a `Signed<Role>`, serialized in `style`.
+    pub(crate) fn from_signed_with_style(
+        mut role: Signed<T>,
+        style: MetadataStyle,
+    ) -> Result<SignedRole<T>> {
+        // Signatures are not covered by the signature itself, so their order is arbitrary. Sort
+        // them by key ID so the same set of signatures always produces the same file.
+        role.signatures.sort_by(|a, b| a.keyid.cmp(&b.keyid));
+
+        // Serialize the role, and calculate its length and sha256. Object keys are sorted
+        // because the schema uses `HashMap`s, whose iteration order differs between runs.
+        let buffer = match style {
+            MetadataStyle::Pretty => {
+                let mut buffer = serialize_sorted(&role, PrettyFormatter::new())?;
+                buffer.push(b'\n');
+                buffer
+            }
+            MetadataStyle::Compact => serialize_sorted(&role, CompactFormatter)?,
+        };
         let length = buffer.len() as u64;
 
         let mut sha256 = [0; SHA256_OUTPUT_LEN];
@@ -130,11 +187,21 @@ where
             buffer,
             sha256,
             length,
+            style,
         };
 
         Ok(signed_role)
     }
 
+    /// Serializes the role again in `style`, unless it already is.
+    pub(crate) fn restyle(self, style: MetadataStyle) -> Result<Self> {
+        if self.style == style {
+            Ok(self)
+        } else {
+            Self::from_signed_with_style(self.signed, style)
+        }
+    }
+
     /// Provides access to the internal signed metadata object.
     pub fn signed(&self) -> &Signed<T> {
         &self.signed
@@ -159,6 +226,75 @@ where
     /// Write the current role's buffer to the given directory with the
     /// appropriate file name.
     pub async fn write<P>(&self, outdir: P, consistent_snapshot: bool) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.write_as(
+            outdir.as_ref(),
+            &self.signed.signed.filename(consistent_snapshot),
+        )
+        .await
+    }
+
+    /// Write the current role's buffer to `filename` in the given directory.
+    async fn write_as(&self, outdir: &Path, filename: &str) -> Result<()> {
+        tokio::fs::create_dir_all(outdir)
+            .await
+            .context(error::DirCreateSnafu { path: outdir })?;
+
+        let path = outdir.join(filename);
+        tokio::fs::write(&path, &self.buffer)
+            .await
+            .context(error::FileWriteSnafu { path })
+    }
+
+    /// Writes a gzip-compressed copy of the role's buffer next to the file written as `filename`,
+    /// named after it with `.gz` appended, and adds it to `manifest`.
+    async fn write_gzip(
+        &self,
+        outdir: &Path,
+        filename: String,
+        manifest: &mut GzipManifest,
+    ) -> Result<()> {
+        let gz_filename = format!("{filename}.gz");
+        let path = outdir.join(&gz_filename);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder
+            .write_all(&self.buffer)
+            .context(error::CompressMetadataSnafu { path: &path })?;
+        let compressed = encoder
+            .finish()
+            .context(error::CompressMetadataSnafu { path: &path })?;
+        tokio::fs::write(&path, &compressed)
+            .await
+            .context(error::FileWriteSnafu { path })?;
+        manifest.files.insert(
+            filename,
+            GzipCompanion {
+                filename: gz_filename,
+                length: compressed.len() as u64,
+                sha256: digest(&SHA256, &compressed).as_ref().to_vec().into(),
+                metadata_sha256: self.sha256.to_vec().into(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Obtains an RFC 3161 timestamp token over this role's buffer from `tsa`. The token can be
+    /// stored with `write_timestamp_token`; `tsa::verify_token` checks that it covers the buffer,
+    /// but not the TSA's signature over it.
+    pub async fn timestamp_token(&self, tsa: &dyn TimestampAuthority) -> Result<Vec<u8>> {
+        tsa::countersign(tsa, &self.buffer).await
+    }
+
+    /// Write a timestamp token for this role next to its metadata file, named after the metadata
+    /// file with a `.tsr` extension.
+    pub async fn write_timestamp_token<P>(
+        &self,
+        outdir: P,
+        consistent_snapshot: bool,
+        token: &[u8],
+    ) -> Result<()>
     where
         P: AsRef<Path>,
     {
@@ -167,31 +303,53 @@ where
             .await
             .context(error::DirCreateSnafu { path: outdir })?;
 
-        let filename = self.signed.signed.filename(consistent_snapshot);
+        let filename = tsa::token_filename(&self.signed.signed.filename(consistent_snapshot));
 
         let path = outdir.join(filename);
-        tokio::fs::write(&path, &self.buffer)
+        tokio::fs::write(&path, token)
             .await
             .context(error::FileWriteSnafu { path })
     }
 
-    /// Append the old signatures for root role
+    /// Append signatures that were made over this role's metadata elsewhere, such as the old
+    /// root's signatures on a cross-signed root, or signatures by keys being rotated out of a
+    /// targets role. A signature is skipped if the role already has one by the same key.
+    ///
+    /// The signatures are not verified here; for targets roles, see
+    /// [`TargetsEditor::add_old_signatures`](crate::editor::targets::TargetsEditor::add_old_signatures).
     pub fn add_old_signatures(mut self, old_signatures: Vec<Signature>) -> Result<Self> {
-        for old_signature in old_signatures {
-            //add only if the signature of the key does not exist
-            if !self
-                .signed
-                .signatures
-                .iter()
-                .any(|new_sig| new_sig.keyid == old_signature.keyid)
-            {
-                self.signed.signatures.push(Signature {
-                    keyid: old_signature.keyid,
-                    sig: old_signature.sig,
-                });
-            }
+        merge_signatures(&mut self.signed, old_signatures);
+        SignedRole::from_signed_with_style(self.signed, self.style)
+    }
+}
+
+/// Serializes `role` with `formatter`, writing object keys in sorted order.
+fn serialize_sorted<T, F>(role: &Signed<T>, formatter: F) -> Result<Vec<u8>>
+where
+    T: Role + Serialize,
+    F: Formatter,
+{
+    let mut buffer = Vec::new();
+    let mut ser =
+        serde_json::Serializer::with_formatter(&mut buffer, SortedFormatter::new(formatter));
+    role.serialize(&mut ser)
+        .context(error::SerializeSignedRoleSnafu {
+            role: T::TYPE.to_string(),
+        })?;
+    Ok(buffer)
+}
+
+/// Adds each of `old_signatures` to `role` unless a signature by the same key is already present.
+pub(crate) fn merge_signatures<T>(role: &mut Signed<T>, old_signatures: Vec<Signature>) {
+    for old_signature in old_signatures {
+        //add only if the signature of the key does not exist
+        if !role
+            .signatures
+            .iter()
+            .any(|new_sig| new_sig.keyid == old_signature.keyid)
+        {
+            role.signatures.push(old_signature);
         }
-        SignedRole::from_signed(self.signed)
     }
 }
 
@@ -261,6 +419,124 @@ impl SignedRepository {
         Ok(())
     }
 
+    /// The contents of the metadata files that `write` writes, keyed by their filenames, for
+    /// publishers that store or serve the metadata somewhere other than a local directory.
+    pub fn metadata_files(&self) -> Result<BTreeMap<String, &[u8]>> {
+        let consistent_snapshot = self.root.signed.signed.consistent_snapshot;
+        let mut files = BTreeMap::new();
+        files.insert(
+            self.root.signed.signed.filename(consistent_snapshot),
+            self.root.buffer.as_slice(),
+        );
+        files.insert(
+            self.targets.signed.signed.filename(consistent_snapshot),
+            self.targets.buffer.as_slice(),
+        );
+        files.insert(
+            self.snapshot.signed.signed.filename(consistent_snapshot),
+            self.snapshot.buffer.as_slice(),
+        );
+        files.insert(
+            self.timestamp.signed.signed.filename(consistent_snapshot),
+            self.timestamp.buffer.as_slice(),
+        );
+        if let Some(delegated_targets) = &self.delegated_targets {
+            for role in &delegated_targets.roles {
+                files.insert(
+                    delegated_targets.filename(role, consistent_snapshot)?,
+                    role.buffer.as_slice(),
+                );
+            }
+        }
+        Ok(files)
+    }
+
+    /// Writes the metadata to the given directory like `write`, and also writes a gzip-compressed
+    /// `.json.gz` companion of each file for mirrors that serve compressed metadata. The
+    /// companions' lengths and hashes are returned and written to [`GZIP_MANIFEST_FILENAME`], so
+    /// that a mirror can check what it serves without decompressing it.
+    pub async fn write_with_gzip<P>(&self, outdir: P) -> Result<GzipManifest>
+    where
+        P: AsRef<Path>,
+    {
+        let outdir = outdir.as_ref();
+        self.write(outdir).await?;
+
+        let consistent_snapshot = self.root.signed.signed.consistent_snapshot;
+        let mut manifest = GzipManifest::default();
+        self.root
+            .write_gzip(
+                outdir,
+                self.root.signed.signed.filename(consistent_snapshot),
+                &mut manifest,
+            )
+            .await?;
+        self.targets
+            .write_gzip(
+                outdir,
+                self.targets.signed.signed.filename(consistent_snapshot),
+                &mut manifest,
+            )
+            .await?;
+        self.snapshot
+            .write_gzip(
+                outdir,
+                self.snapshot.signed.signed.filename(consistent_snapshot),
+                &mut manifest,
+            )
+            .await?;
+        self.timestamp
+            .write_gzip(
+                outdir,
+                self.timestamp.signed.signed.filename(consistent_snapshot),
+                &mut manifest,
+            )
+            .await?;
+        if let Some(delegated_targets) = &self.delegated_targets {
+            for role in &delegated_targets.roles {
+                let filename = delegated_targets.filename(role, consistent_snapshot)?;
+                role.write_gzip(outdir, filename, &mut manifest).await?;
+            }
+        }
+
+        let path = outdir.join(GZIP_MANIFEST_FILENAME);
+        let mut data =
+            serde_json::to_vec_pretty(&manifest).context(error::SerializeGzipManifestSnafu)?;
+        data.push(b'\n');
+        tokio::fs::write(&path, data)
+            .await
+            .context(error::FileWriteSnafu { path })?;
+        Ok(manifest)
+    }
+
+    /// Collects the signed metadata into a single [`MetadataBundle`], which can be loaded with
+    /// [`RepositoryLoader::metadata_bundle`](crate::RepositoryLoader::metadata_bundle).
+    pub fn bundle(&self) -> MetadataBundle {
+        MetadataBundle {
+            root: buffer_string(&self.root.buffer),
+            timestamp: buffer_string(&self.timestamp.buffer),
+            snapshot: buffer_string(&self.snapshot.buffer),
+            targets: buffer_string(&self.targets.buffer),
+            delegated_targets: self
+                .delegated_targets
+                .iter()
+                .flat_map(|delegated| &delegated.roles)
+                .map(|role| (role.signed.signed.name.clone(), buffer_string(&role.buffer)))
+                .collect(),
+        }
+    }
+
+    /// Writes the signed metadata to `path` as a single [`MetadataBundle`].
+    pub async fn write_bundle<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        tokio::fs::write(path, self.bundle().to_vec()?)
+            .await
+            .context(error::FileWriteSnafu { path })
+    }
+
     /// Crawls a given directory and symlinks any targets found to the given
     /// "out" directory. If consistent snapshots are used, the target files
     /// are prefixed with their `sha256`.
@@ -315,6 +591,23 @@ impl SignedRepository {
         .await
     }
 
+    /// Like [`copy_targets`](Self::copy_targets), but when several targets have identical
+    /// contents, only the first is copied and the rest are hard-linked to it. This saves disk
+    /// space in repositories where many target names point to the same bytes.
+    pub async fn copy_targets_deduplicated<P1, P2>(
+        &self,
+        indir: P1,
+        outdir: P2,
+        replace_behavior: PathExists,
+    ) -> Result<()>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        self.walk_targets_deduplicated(indir.as_ref(), outdir.as_ref(), replace_behavior)
+            .await
+    }
+
     /// Symlinks a single target to the desired directory. If `target_filename` is given, it
     /// becomes the filename suffix, otherwise the original filename is used. (A unique filename
     /// prefix is used if consistent snapshots are enabled.)  Fails if the target already exists in
@@ -430,11 +723,38 @@ impl TargetsWalker for SignedRepository {
     }
 }
 
+/// The name of the manifest that [`SignedRepository::write_with_gzip`] writes next to the metadata.
+pub const GZIP_MANIFEST_FILENAME: &str = "gzip-manifest.json";
+
+/// The gzip-compressed companions written by [`SignedRepository::write_with_gzip`].
+///
+/// The manifest isn't signed; clients still verify the metadata they decompress as usual. It lets
+/// mirror tooling check that a compressed file matches the metadata it was made from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GzipManifest {
+    /// The companion of each metadata file, keyed by the metadata file's name.
+    pub files: BTreeMap<String, GzipCompanion>,
+}
+
+/// A gzip-compressed copy of a metadata file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GzipCompanion {
+    /// The name of the compressed file.
+    pub filename: String,
+    /// The length in bytes of the compressed file.
+    pub length: u64,
+    /// The sha256 digest of the compressed file.
+    pub sha256: Decoded<Hex>,
+    /// The sha256 digest of the metadata file it decompresses to.
+    pub metadata_sha256: Decoded<Hex>,
+}
+
 /// A set of signed targets role metadata.
 #[derive(Debug)]
 pub struct SignedDelegatedTargets {
     pub(crate) roles: Vec<SignedRole<DelegatedTargets>>,
     pub(crate) consistent_snapshot: bool,
+    pub(crate) filename_encoding: Arc<dyn FilenameEncoding>,
 }
 
 impl SignedDelegatedTargets {
@@ -445,11 +765,26 @@ impl SignedDelegatedTargets {
         P: AsRef<Path>,
     {
         for targets in &self.roles {
-            targets.write(&outdir, consistent_snapshot).await?;
+            let filename = self.filename(targets, consistent_snapshot)?;
+            targets.write_as(outdir.as_ref(), &filename).await?;
         }
         Ok(())
     }
 
+    /// The filename `role` is written to, encoded with the editor's `FilenameEncoding`.
+    fn filename(
+        &self,
+        role: &SignedRole<DelegatedTargets>,
+        consistent_snapshot: bool,
+    ) -> Result<String> {
+        let targets = &role.signed.signed;
+        role_filename(
+            self.filename_encoding.as_ref(),
+            &targets.name,
+            consistent_snapshot.then_some(targets.targets.version),
+        )
+    }
+
     /// Returns all `SignedRole<DelegatedTargets>>` contained by this `SignedDelegatedTargets`
     pub fn roles(self) -> Vec<SignedRole<DelegatedTargets>> {
         self.roles
@@ -509,6 +844,23 @@ impl SignedDelegatedTargets {
         .await
     }
 
+    /// Like [`copy_targets`](Self::copy_targets), but when several targets have identical
+    /// contents, only the first is copied and the rest are hard-linked to it. This saves disk
+    /// space in repositories where many target names point to the same bytes.
+    pub async fn copy_targets_deduplicated<P1, P2>(
+        &self,
+        indir: P1,
+        outdir: P2,
+        replace_behavior: PathExists,
+    ) -> Result<()>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        self.walk_targets_deduplicated(indir.as_ref(), outdir.as_ref(), replace_behavior)
+            .await
+    }
+
     /// Symlinks a single target to the desired directory. If `target_filename` is given, it
     /// becomes the filename suffix, otherwise the original filename is used. (A unique filename
     /// prefix is used if consistent snapshots are enabled.)  Fails if the target already exists in
@@ -685,17 +1037,7 @@ trait TargetsWalker {
 
         // Walk the absolute path of the indir. Using the absolute path here
         // means that `entry.path()` call will return its absolute path.
-        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
-        let root = abs_indir.clone();
-        tokio::task::spawn_blocking(move || {
-            let walker = WalkDir::new(&root).follow_links(true);
-            for entry in walker {
-                if tx.blocking_send(entry).is_err() {
-                    // Receiver error'ed out
-                    break;
-                };
-            }
-        });
+        let mut rx = walk_dir(abs_indir.clone());
 
         while let Some(entry) = rx.recv().await {
             let entry = entry.context(error::WalkDirSnafu {
@@ -705,13 +1047,13 @@ trait TargetsWalker {
             // If the entry is not a file, move on
             if !entry.file_type().is_file() {
                 continue;
-            };
+            }
 
             // Call the requested function to manipulate the path we found
             if let Err(e) = f(self, entry.path(), outdir, replace_behavior, None).await {
                 match e {
                     // If we found a path that isn't a known target in the repo, skip it.
-                    error::Error::PathIsNotTarget { .. } => continue,
+                    error::Error::PathIsNotTarget { .. } => {}
                     _ => return Err(e),
                 }
             }
@@ -720,6 +1062,90 @@ trait TargetsWalker {
         Ok(())
     }
 
+    /// Copies every target found in `indir` to `outdir` like `copy_target` does, except that a
+    /// target whose digest matches one already written is hard-linked to that file instead.
+    async fn walk_targets_deduplicated(
+        &self,
+        indir: &Path,
+        outdir: &Path,
+        replace_behavior: PathExists,
+    ) -> Result<()> {
+        create_dir_all(outdir)
+            .await
+            .context(error::DirCreateSnafu { path: outdir })?;
+        let abs_indir = canonicalize(indir)
+            .await
+            .context(error::AbsolutePathSnafu { path: indir })?;
+        let mut rx = walk_dir(abs_indir.clone());
+
+        // The first file written (or found, when skipping) for each digest.
+        let mut written: HashMap<(HashAlgorithm, Vec<u8>), PathBuf> = HashMap::new();
+        while let Some(entry) = rx.recv().await {
+            let entry = entry.context(error::WalkDirSnafu {
+                directory: &abs_indir,
+            })?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let input = entry.path();
+            let path = match self.target_path(input, outdir, None).await {
+                // If we found a path that isn't a known target in the repo, skip it.
+                Err(error::Error::PathIsNotTarget { .. }) => continue,
+                result => result?,
+            };
+            // `target_path` verified that the file matches the target of the same name.
+            let digest = self
+                .targets()
+                .get(&TargetName::new(
+                    input
+                        .file_name()
+                        .context(error::NoFileNameSnafu { path: input })?
+                        .to_str()
+                        .context(error::PathUtf8Snafu { path: input })?,
+                )?)
+                .context(error::PathIsNotTargetSnafu { path: input })?
+                .hashes
+                .name_digest()
+                .context(error::PathIsNotTargetSnafu { path: input })?;
+
+            let dest = match path {
+                TargetPath::New { path } => path,
+                TargetPath::File { path } => match replace_behavior {
+                    PathExists::Skip => {
+                        written.entry(digest).or_insert(path);
+                        continue;
+                    }
+                    PathExists::Fail => error::PathExistsFailSnafu { path }.fail()?,
+                    PathExists::Replace => {
+                        remove_file(&path)
+                            .await
+                            .context(error::RemoveTargetSnafu { path: &path })?;
+                        path
+                    }
+                },
+                TargetPath::Symlink { path } => error::TargetFileTypeMismatchSnafu {
+                    expected: "regular file",
+                    found: "symlink",
+                    path,
+                }
+                .fail()?,
+            };
+
+            if let Some(original) = written.get(&digest) {
+                hard_link(original, &dest)
+                    .await
+                    .context(error::HardLinkCreateSnafu { path: dest })?;
+            } else {
+                copy(input, &dest)
+                    .await
+                    .context(error::FileWriteSnafu { path: &dest })?;
+                written.insert(digest, dest);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Determines the output path of a target based on consistent snapshot rules. Returns Err if
     /// the target already exists in the repo with a different hash, or if the target is not known
     /// to the repo.  (We're dealing with a signed repo, so it's too late to add targets.)
@@ -747,33 +1173,44 @@ trait TargetsWalker {
             )?)
         };
 
-        // create a Target object using the input path.
-        let target_from_path = Target::from_path(input)
-            .await
-            .context(error::TargetFromPathSnafu { path: input })?;
-
         // Use the file name to see if a target exists in the repo
         // with that name. If so...
         let repo_targets = &self.targets();
         let repo_target = repo_targets
             .get(&target_name)
             .context(error::PathIsNotTargetSnafu { path: input })?;
+        let (algorithm, expected) = repo_target
+            .hashes
+            .name_digest()
+            .context(error::PathIsNotTargetSnafu { path: input })?;
+
+        // create a Target object using the input path, hashed with the algorithm the target is
+        // named by.
+        let target_from_path = Target::from_path_with_hashes(input, &[algorithm])
+            .await
+            .context(error::TargetFromPathSnafu { path: input })?;
+        let calculated = target_from_path
+            .hashes
+            .digest(algorithm)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
         // compare the hashes of the target from the repo and the target we just created.  They
         // should match, or we alert the caller; if target replacement is intended, it should
         // happen earlier, in RepositoryEditor.
         ensure!(
-            target_from_path.hashes.sha256 == repo_target.hashes.sha256,
+            calculated == expected,
             error::HashMismatchSnafu {
                 context: "target",
-                calculated: hex::encode(target_from_path.hashes.sha256),
-                expected: hex::encode(&repo_target.hashes.sha256),
+                calculated: hex::encode(calculated),
+                expected: hex::encode(&expected),
             }
         );
 
         let dest = if self.consistent_snapshot() {
             outdir.join(format!(
                 "{}.{}",
-                hex::encode(&target_from_path.hashes.sha256),
+                hex::encode(&expected),
                 target_name.resolved()
             ))
         } else {
@@ -798,7 +1235,7 @@ trait TargetsWalker {
                 .fetch(url.clone())
                 .await
                 .with_context(|_| error::TransportSnafu { url: url.clone() })?;
-            let stream = DigestAdapter::sha256(stream, &repo_target.hashes.sha256, url.clone());
+            let stream = DigestAdapter::new(stream, algorithm, &expected, url.clone());
 
             // The act of reading with the DigestAdapter verifies the checksum, assuming the read
             // succeeds.
@@ -820,3 +1257,18 @@ trait TargetsWalker {
         }
     }
 }
+
+/// Walks `root`, following links, and sends each entry found on the returned channel.
+fn walk_dir(root: PathBuf) -> tokio::sync::mpsc::Receiver<walkdir::Result<DirEntry>> {
+    let (tx, rx) = tokio::sync::mpsc::channel(10);
+    tokio::task::spawn_blocking(move || {
+        let walker = WalkDir::new(&root).follow_links(true);
+        for entry in walker {
+            if tx.blocking_send(entry).is_err() {
+                // Receiver error'ed out
+                break;
+            }
+        }
+    });
+    rx
+}
@@ -0,0 +1,269 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `SortedFormatter`, which wraps a [`Formatter`] to write object keys in sorted order.
+//!
+//! The schema uses `HashMap`s, whose iteration order differs between runs, so roles are written
+//! with their keys sorted to make the same role always produce the same file. Sorting while
+//! serializing avoids converting the whole role to a `serde_json::Value` first, which is slow for
+//! large targets roles.
+
+use serde_json::ser::{CharEscape, Formatter};
+use std::io::{Error, Result, Write};
+
+/// A [`Formatter`] that buffers each object's entries and passes them to the wrapped formatter
+/// sorted by key, the way a `BTreeMap` would order them. If a key is repeated, the last value
+/// written for it wins.
+#[derive(Debug)]
+pub(super) struct SortedFormatter<F> {
+    inner: F,
+    object_stack: Vec<Object>,
+}
+
+/// An object in progress. Keys and values are appended to `buf`, and each entry records where its
+/// key and value are.
+#[derive(Debug, Default)]
+struct Object {
+    buf: Vec<u8>,
+    entries: Vec<Entry>,
+    key_start: usize,
+    value_start: usize,
+}
+
+/// An object entry: the key is `buf[key_start..value_start]` and the value is
+/// `buf[value_start..end]`. `key` is the decoded key, which is what entries are sorted by.
+#[derive(Debug)]
+struct Entry {
+    key: String,
+    key_start: usize,
+    value_start: usize,
+    end: usize,
+}
+
+/// Either the writer received by a `Formatter` method, or the buffer of the object in progress.
+enum ContextWriter<'a, W: ?Sized> {
+    Writer(&'a mut W),
+    Object(&'a mut Vec<u8>),
+}
+
+impl<W: Write + ?Sized> Write for ContextWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match self {
+            ContextWriter::Writer(writer) => writer.write(buf),
+            ContextWriter::Object(object) => object.write(buf),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        match self {
+            ContextWriter::Writer(writer) => writer.write_all(buf),
+            ContextWriter::Object(object) => object.write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            ContextWriter::Writer(writer) => writer.flush(),
+            ContextWriter::Object(_) => Ok(()),
+        }
+    }
+}
+
+impl<F: Formatter> SortedFormatter<F> {
+    pub(super) fn new(inner: F) -> Self {
+        Self {
+            inner,
+            object_stack: Vec::new(),
+        }
+    }
+
+    /// Returns the formatter to pass calls to, and the writer it should use: the buffer of the
+    /// object in progress if there is one, otherwise `writer`.
+    fn split<'a, W: Write + ?Sized>(
+        &'a mut self,
+        writer: &'a mut W,
+    ) -> (&'a mut F, ContextWriter<'a, W>) {
+        let writer = match self.object_stack.last_mut() {
+            Some(object) => ContextWriter::Object(&mut object.buf),
+            None => ContextWriter::Writer(writer),
+        };
+        (&mut self.inner, writer)
+    }
+
+    fn obj_mut(&mut self) -> Result<&mut Object> {
+        self.object_stack.last_mut().ok_or_else(|| {
+            Error::other("serde_json called an object method without calling begin_object first")
+        })
+    }
+}
+
+/// Passes a `Formatter` method through to the wrapped formatter, using the appropriate writer.
+macro_rules! wrapper {
+    ($f:ident) => {
+        fn $f<W: Write + ?Sized>(&mut self, writer: &mut W) -> Result<()> {
+            let (inner, mut writer) = self.split(writer);
+            inner.$f(&mut writer)
+        }
+    };
+
+    ($f:ident, $t:ty) => {
+        fn $f<W: Write + ?Sized>(&mut self, writer: &mut W, arg: $t) -> Result<()> {
+            let (inner, mut writer) = self.split(writer);
+            inner.$f(&mut writer, arg)
+        }
+    };
+}
+
+impl<F: Formatter> Formatter for SortedFormatter<F> {
+    wrapper!(write_null);
+    wrapper!(write_bool, bool);
+    wrapper!(write_i8, i8);
+    wrapper!(write_i16, i16);
+    wrapper!(write_i32, i32);
+    wrapper!(write_i64, i64);
+    wrapper!(write_i128, i128);
+    wrapper!(write_u8, u8);
+    wrapper!(write_u16, u16);
+    wrapper!(write_u32, u32);
+    wrapper!(write_u64, u64);
+    wrapper!(write_u128, u128);
+    wrapper!(write_f32, f32);
+    wrapper!(write_f64, f64);
+    wrapper!(write_number_str, &str);
+    wrapper!(begin_string);
+    wrapper!(end_string);
+    wrapper!(write_string_fragment, &str);
+    wrapper!(write_char_escape, CharEscape);
+    wrapper!(begin_array);
+    wrapper!(end_array);
+    wrapper!(begin_array_value, bool);
+    wrapper!(end_array_value);
+    wrapper!(write_raw_fragment, &str);
+
+    fn begin_object<W: Write + ?Sized>(&mut self, writer: &mut W) -> Result<()> {
+        let (inner, mut writer) = self.split(writer);
+        inner.begin_object(&mut writer)?;
+        self.object_stack.push(Object::default());
+        Ok(())
+    }
+
+    fn end_object<W: Write + ?Sized>(&mut self, writer: &mut W) -> Result<()> {
+        let mut object = self.object_stack.pop().ok_or_else(|| {
+            Error::other("serde_json called end_object without calling begin_object first")
+        })?;
+        let mut entries = std::mem::take(&mut object.entries);
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        entries.dedup_by(|next, prev| {
+            let duplicate = next.key == prev.key;
+            if duplicate {
+                std::mem::swap(next, prev);
+            }
+            duplicate
+        });
+
+        let (inner, mut writer) = self.split(writer);
+        let mut first = true;
+        for entry in entries {
+            inner.begin_object_key(&mut writer, first)?;
+            writer.write_all(&object.buf[entry.key_start..entry.value_start])?;
+            inner.end_object_key(&mut writer)?;
+
+            inner.begin_object_value(&mut writer)?;
+            writer.write_all(&object.buf[entry.value_start..entry.end])?;
+            inner.end_object_value(&mut writer)?;
+
+            first = false;
+        }
+        inner.end_object(&mut writer)
+    }
+
+    fn begin_object_key<W: Write + ?Sized>(&mut self, _writer: &mut W, _first: bool) -> Result<()> {
+        let object = self.obj_mut()?;
+        object.key_start = object.buf.len();
+        Ok(())
+    }
+
+    fn end_object_key<W: Write + ?Sized>(&mut self, _writer: &mut W) -> Result<()> {
+        let object = self.obj_mut()?;
+        object.value_start = object.buf.len();
+        Ok(())
+    }
+
+    fn begin_object_value<W: Write + ?Sized>(&mut self, _writer: &mut W) -> Result<()> {
+        Ok(())
+    }
+
+    fn end_object_value<W: Write + ?Sized>(&mut self, _writer: &mut W) -> Result<()> {
+        let object = self.obj_mut()?;
+        let key = serde_json::from_slice(&object.buf[object.key_start..object.value_start])?;
+        object.entries.push(Entry {
+            key,
+            key_start: object.key_start,
+            value_start: object.value_start,
+            end: object.buf.len(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SortedFormatter;
+    use serde::Serialize;
+    use serde_json::ser::PrettyFormatter;
+    use std::collections::HashMap;
+
+    fn to_pretty<T: Serialize>(value: &T) -> String {
+        let mut buf = Vec::new();
+        let mut ser = serde_json::Serializer::with_formatter(
+            &mut buf,
+            SortedFormatter::new(PrettyFormatter::new()),
+        );
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn sorts_nested_objects() {
+        #[derive(Serialize)]
+        struct Role {
+            version: u64,
+            expires: &'static str,
+            targets: HashMap<&'static str, Vec<HashMap<&'static str, u64>>>,
+            empty: HashMap<&'static str, u64>,
+        }
+
+        let role = Role {
+            version: 1,
+            expires: "2030-01-01T00:00:00Z",
+            targets: [
+                ("b \"quoted\"", vec![[("z", 1), ("y", 2)].into()]),
+                ("a", Vec::new()),
+            ]
+            .into(),
+            empty: HashMap::new(),
+        };
+        let expected = r#"{
+  "empty": {},
+  "expires": "2030-01-01T00:00:00Z",
+  "targets": {
+    "a": [],
+    "b \"quoted\"": [
+      {
+        "y": 2,
+        "z": 1
+      }
+    ]
+  },
+  "version": 1
+}"#;
+        assert_eq!(to_pretty(&role), expected);
+    }
+
+    /// Keys are sorted as strings, not as their encoded form, so `"a"` comes before `"a!"`.
+    #[test]
+    fn sorts_decoded_keys() {
+        let map: HashMap<&str, u64> = [("a!", 1), ("a", 2)].into();
+        assert_eq!(to_pretty(&map), "{\n  \"a\": 2,\n  \"a!\": 1\n}");
+    }
+}
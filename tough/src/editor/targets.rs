@@ -4,29 +4,33 @@
 
 //! Provides a `TargetsEditor` object for building and editing targets roles.
 
-use crate::editor::signed::{SignedDelegatedTargets, SignedRole};
+use crate::editor::keys::public_keys;
+use crate::editor::signed::{merge_signatures, MetadataStyle, SignedDelegatedTargets, SignedRole};
 use crate::error::{self, Result};
 use crate::fetch::fetch_max_size;
+use crate::filename_encoding::encode_role_name;
 use crate::key_source::KeySource;
+use crate::policy::HashAlgorithm;
 use crate::schema::decoded::{Decoded, Hex};
 use crate::schema::key::Key;
 use crate::schema::{
-    DelegatedRole, DelegatedTargets, Delegations, KeyHolder, PathSet, RoleType, Signed, Target,
-    Targets,
+    DelegatedRole, DelegatedTargets, Delegations, HashedBins, KeyHolder, PathSet, RoleId, RoleType,
+    Signature, Signed, Target, TargetBuilder, Targets,
 };
 use crate::transport::{IntoVec, Transport};
-use crate::{encode_filename, Limits};
+use crate::{FilenameEncoding, Limits, PercentEncoding};
 use crate::{Repository, TargetName};
 use aws_lc_rs::rand::SystemRandom;
 use chrono::{DateTime, Utc};
 use serde_json::Value;
-use snafu::{OptionExt, ResultExt};
+use snafu::{ensure, OptionExt, ResultExt};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::Display;
 use std::num::NonZeroU64;
 use std::path::Path;
+use std::sync::Arc;
 use url::Url;
 
 const SPEC_VERSION: &str = "1.0.0";
@@ -82,6 +86,18 @@ pub struct TargetsEditor {
     limits: Option<Limits>,
 
     transport: Option<Box<dyn Transport>>,
+
+    /// Signatures over the role's new metadata by keys that are being rotated out
+    old_signatures: Vec<Signature>,
+
+    /// Hashes listed for targets added by path, besides `sha256`
+    hash_algorithms: Vec<HashAlgorithm>,
+
+    /// Turns delegated role names into the filenames their metadata is fetched from and written to
+    pub(crate) filename_encoding: Arc<dyn FilenameEncoding>,
+
+    /// How the metadata is serialized by `sign()`
+    metadata_style: MetadataStyle,
 }
 
 impl TargetsEditor {
@@ -99,6 +115,10 @@ impl TargetsEditor {
             _extra: None,
             limits: None,
             transport: None,
+            old_signatures: Vec::new(),
+            hash_algorithms: Vec::new(),
+            filename_encoding: Arc::new(PercentEncoding),
+            metadata_style: MetadataStyle::default(),
         }
     }
 
@@ -117,29 +137,24 @@ impl TargetsEditor {
             _extra: Some(targets._extra),
             limits: None,
             transport: None,
+            old_signatures: Vec::new(),
+            hash_algorithms: Vec::new(),
+            filename_encoding: Arc::new(PercentEncoding),
+            metadata_style: MetadataStyle::default(),
         }
     }
 
     /// Creates a `TargetsEditor` with the provided targets from an already loaded repo
     /// `version` and `expires` are thrown out to encourage updating the version and expiration
     /// If a `Repository` has been loaded, use `from_repo()` to preserve the `Transport` and `Limits`.
-    pub fn from_repo(repo: Repository, name: &str) -> Result<Self> {
+    pub fn from_repo(mut repo: Repository, name: &str) -> Result<Self> {
+        // `repo` is consumed, so the role's metadata is moved out of it rather than cloned
         let (targets, key_holder) = if name == "targets" {
             (
-                repo.targets.signed.clone(),
+                repo.targets.signed,
                 KeyHolder::Root(repo.root.signed.clone()),
             )
         } else {
-            let targets = repo
-                .delegated_role(name)
-                .context(error::DelegateNotFoundSnafu {
-                    name: name.to_string(),
-                })?
-                .targets
-                .as_ref()
-                .context(error::NoTargetsSnafu)?
-                .signed
-                .clone();
             let key_holder = KeyHolder::Delegations(
                 repo.targets
                     .signed
@@ -147,8 +162,20 @@ impl TargetsEditor {
                     .context(error::DelegateMissingSnafu {
                         name: name.to_string(),
                     })?
-                    .clone(),
+                    .without_role_targets(),
             );
+            let targets = repo
+                .targets
+                .signed
+                .delegated_role_mut(name)
+                .ok()
+                .context(error::DelegateNotFoundSnafu {
+                    name: name.to_string(),
+                })?
+                .targets
+                .take()
+                .context(error::NoTargetsSnafu)?
+                .signed;
             (targets, key_holder)
         };
         Ok(TargetsEditor {
@@ -163,6 +190,10 @@ impl TargetsEditor {
             _extra: Some(targets._extra),
             limits: Some(repo.limits),
             transport: Some(repo.transport),
+            old_signatures: Vec::new(),
+            hash_algorithms: Vec::new(),
+            filename_encoding: repo.filename_encoding,
+            metadata_style: MetadataStyle::default(),
         })
     }
 
@@ -176,6 +207,24 @@ impl TargetsEditor {
         self.transport = Some(transport);
     }
 
+    /// Set the [`FilenameEncoding`] for delegated role filenames, used when loading a role and
+    /// by the `SignedDelegatedTargets` that `sign()` returns. The default is [`PercentEncoding`].
+    pub fn filename_encoding<E: FilenameEncoding + 'static>(&mut self, encoding: E) -> &mut Self {
+        self.filename_encoding = Arc::new(encoding);
+        self
+    }
+
+    /// Adds signatures made over this role's new metadata by keys outside its current key set,
+    /// such as the keys being replaced during a key rotation. They are kept alongside the
+    /// signatures made by `sign()` or `create_signed()` for a transition window.
+    ///
+    /// Old signatures don't count toward the role's threshold, which must still be met by valid
+    /// signatures from the role's current keys when signing.
+    pub fn add_old_signatures(&mut self, old_signatures: Vec<Signature>) -> &mut Self {
+        self.old_signatures.extend(old_signatures);
+        self
+    }
+
     /// Add a `Target` to the `Targets` role
     pub fn add_target<T, E>(&mut self, name: T, target: Target) -> Result<&mut Self>
     where
@@ -188,12 +237,31 @@ impl TargetsEditor {
             }
             .build()
         })?;
+        ensure!(
+            !target.hashes.algorithms().is_empty(),
+            error::UnhashedTargetRefusedSnafu {
+                name: target_name.raw(),
+            }
+        );
         self.new_targets
             .get_or_insert_with(HashMap::new)
             .insert(target_name, target);
         Ok(self)
     }
 
+    /// List hashes with each of `algorithms`, as well as the `sha256` hash, for the targets added
+    /// with `add_target_path()` or `add_target_paths()`.
+    pub fn hash_algorithms(&mut self, algorithms: &[HashAlgorithm]) -> &mut Self {
+        self.hash_algorithms = algorithms.to_vec();
+        self
+    }
+
+    /// Set how `sign()` serializes the metadata. The default is [`MetadataStyle::Pretty`].
+    pub fn metadata_style(&mut self, style: MetadataStyle) -> &mut Self {
+        self.metadata_style = style;
+        self
+    }
+
     /// Add a target to the repository using its path
     ///
     /// Note: This function builds a `Target` synchronously;
@@ -216,14 +284,74 @@ impl TargetsEditor {
         )?;
 
         // Build a Target from the path given. If it is not a file, this will fail
-        let target = Target::from_path(target_path)
+        let target = Target::from_path_with_hashes(target_path, &self.hash_algorithms)
+            .await
+            .context(error::TargetFromPathSnafu { path: target_path })?;
+
+        self.add_target_keeping_custom(target_name, target)
+    }
+
+    /// Adds a target built from a file. A file has no custom metadata of its own, so the target
+    /// keeps whatever custom metadata the role already lists for the target it replaces.
+    pub(crate) fn add_target_keeping_custom(
+        &mut self,
+        name: TargetName,
+        mut target: Target,
+    ) -> Result<&mut Self> {
+        if let Some(listed) = self.listed_target_mut(&name) {
+            target.custom.clone_from(&listed.custom);
+        }
+        self.add_target(name, target)
+    }
+
+    /// Add a target to the targets using its path, with `custom` as its custom metadata.
+    ///
+    /// See the note on `add_target_path()` regarding performance.
+    pub async fn add_target_with_custom<P>(
+        &mut self,
+        target_path: P,
+        custom: HashMap<String, Value>,
+    ) -> Result<&mut Self>
+    where
+        P: AsRef<Path>,
+    {
+        let target_path = target_path.as_ref();
+        let target_name = TargetName::new(
+            target_path
+                .file_name()
+                .context(error::NoFileNameSnafu { path: target_path })?
+                .to_str()
+                .context(error::PathUtf8Snafu { path: target_path })?,
+        )?;
+        let target = Target::from_path_with_hashes(target_path, &self.hash_algorithms)
             .await
             .context(error::TargetFromPathSnafu { path: target_path })?;
+        self.add_target(
+            target_name,
+            TargetBuilder::from(target).custom_map(custom).build(),
+        )
+    }
 
-        self.add_target(target_name, target)?;
+    /// Replaces the custom metadata of the listed target `name` with `custom`.
+    pub fn set_target_custom(
+        &mut self,
+        name: &TargetName,
+        custom: HashMap<String, Value>,
+    ) -> Result<&mut Self> {
+        self.listed_target_mut(name)
+            .context(error::TargetNotListedSnafu { name: name.clone() })?
+            .custom = custom;
         Ok(self)
     }
 
+    /// The target `name` as the role will list it, whether it was added or already listed.
+    fn listed_target_mut(&mut self, name: &TargetName) -> Option<&mut Target> {
+        if let Some(target) = self.new_targets.as_mut().and_then(|t| t.get_mut(name)) {
+            return Some(target);
+        }
+        self.existing_targets.as_mut()?.get_mut(name)
+    }
+
     /// Add a list of target paths to the targets
     ///
     /// See the note on `add_target_path()` regarding performance.
@@ -290,7 +418,7 @@ impl TargetsEditor {
             {
                 // Key isn't present yet, so we need to add it
                 delegations.keys.insert(keyid.clone(), key);
-            };
+            }
             keyids.push(keyid.clone());
         }
 
@@ -299,11 +427,13 @@ impl TargetsEditor {
             for delegated_role in &mut delegations.roles {
                 if delegated_role.name == role {
                     delegated_role.keyids.extend(keyids.clone());
+                    delegated_role.keyids.sort();
                 }
             }
             for delegated_role in &mut *self.new_roles.get_or_insert(Vec::new()) {
                 if delegated_role.name == role {
                     delegated_role.keyids.extend(keyids.clone());
+                    delegated_role.keyids.sort();
                 }
             }
         }
@@ -318,7 +448,11 @@ impl TargetsEditor {
             .context(error::NoDelegationsSnafu)?;
         // If a role was provided remove keyid from the delegated role
         if let Some(role) = role {
-            for delegated_role in &mut delegations.roles {
+            for delegated_role in delegations
+                .roles
+                .iter_mut()
+                .chain(self.new_roles.iter_mut().flatten())
+            {
                 if delegated_role.name == role {
                     delegated_role.keyids.retain(|key| keyid != key);
                 }
@@ -329,6 +463,31 @@ impl TargetsEditor {
         Ok(self)
     }
 
+    /// Returns the name of the role being edited
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the delegations of the role being edited, including roles added with
+    /// `delegate_role()`
+    pub(crate) fn current_delegations(&self) -> Option<Delegations> {
+        let mut delegations = self.delegations.clone()?;
+        delegations
+            .roles
+            .extend(self.new_roles.iter().flatten().cloned());
+        Some(delegations)
+    }
+
+    /// Returns the names of the roles delegated by this role that trust `keyid`
+    pub(crate) fn roles_trusting(&self, keyid: &Decoded<Hex>) -> Vec<String> {
+        let delegations = self.delegations.iter().flat_map(|d| &d.roles);
+        delegations
+            .chain(self.new_roles.iter().flatten())
+            .filter(|role| role.keyids.contains(keyid))
+            .map(|role| role.name.clone())
+            .collect()
+    }
+
     /// Adds a `DelegatedRole` to `new_roles`
     /// To use `delegate_role()` a new `Targets` should be created using `TargetsEditor::new()`
     /// followed by `create_signed()` to provide a `Signed<DelegatedTargets>` for the new role.
@@ -337,10 +496,11 @@ impl TargetsEditor {
         targets: Signed<DelegatedTargets>,
         paths: PathSet,
         key_pairs: HashMap<Decoded<Hex>, Key>,
-        keyids: Vec<Decoded<Hex>>,
+        mut keyids: Vec<Decoded<Hex>>,
         threshold: NonZeroU64,
     ) -> Result<&mut Self> {
         self.add_key(key_pairs, None)?;
+        keyids.sort();
         self.new_roles
             .get_or_insert(Vec::new())
             .push(DelegatedRole {
@@ -357,6 +517,28 @@ impl TargetsEditor {
         Ok(self)
     }
 
+    /// Delegates to `count` hashed bins, as described by [`HashedBins`], each trusted with
+    /// `keys`. Each bin is created empty, with `version` and `expiration`, and signed with `keys`.
+    pub async fn delegate_hashed_bins(
+        &mut self,
+        count: NonZeroU64,
+        keys: &[Box<dyn KeySource>],
+        threshold: NonZeroU64,
+        expiration: DateTime<Utc>,
+        version: NonZeroU64,
+    ) -> Result<&mut Self> {
+        let bins = HashedBins::new(count).context(error::InvalidHashedBinsSnafu)?;
+        let key_pairs = public_keys(keys).await?;
+        let keyids: Vec<_> = key_pairs.keys().cloned().collect();
+        for (name, paths) in bins.bins() {
+            let mut bin_editor = TargetsEditor::new(&name);
+            bin_editor.version(version).expires(expiration);
+            let bin = bin_editor.create_signed(keys).await?;
+            self.delegate_role(bin, paths, key_pairs.clone(), keyids.clone(), threshold)?;
+        }
+        Ok(self)
+    }
+
     /// Removes a role from delegations
     /// If `recursive` is `false`, `role` is only removed if it is directly delegated by this role
     /// If `true` removes whichever role eventually delegates 'role'
@@ -369,13 +551,16 @@ impl TargetsEditor {
         delegations
             .roles
             .retain(|delegated_role| delegated_role.name != role);
+        if let Some(new_roles) = self.new_roles.as_mut() {
+            new_roles.retain(|delegated_role| delegated_role.name != role);
+        }
         if recursive {
             // Keep all roles that do not delegate `role` down the chain of delegations
             delegations.roles.retain(|delegated_role| {
                 delegated_role
                     .targets
                     .as_ref()
-                    .map_or(true, |targets| targets.signed.delegated_role(role).is_err())
+                    .is_none_or(|targets| targets.signed.delegated_role(role).is_err())
             });
         }
         Ok(self)
@@ -400,7 +585,7 @@ impl TargetsEditor {
 
         let metadata_base_url = parse_url(metadata_url)?;
         // path to updated metadata
-        let encoded_name = encode_filename(name);
+        let encoded_name = encode_role_name(self.filename_encoding.as_ref(), name)?;
         let encoded_filename = format!("{encoded_name}.json");
         let role_url = metadata_base_url
             .join(&encoded_filename)
@@ -472,6 +657,12 @@ impl TargetsEditor {
         if let Some(ref new_targets) = self.new_targets {
             targets.extend(new_targets.clone());
         }
+        if let Some((name, _)) = targets
+            .iter()
+            .find(|(_, target)| target.hashes.algorithms().is_empty())
+        {
+            return error::UnhashedTargetRefusedSnafu { name: name.raw() }.fail();
+        }
 
         let mut delegations = self.delegations.clone();
         if let Some(delegations) = delegations.as_mut() {
@@ -503,10 +694,9 @@ impl TargetsEditor {
         let mut key_pairs = HashMap::new();
         for source in keys {
             let key_pair = source
-                .as_sign()
+                .public_key()
                 .await
-                .context(error::KeyPairFromKeySourceSnafu)?
-                .tuf_key();
+                .context(error::KeyPairFromKeySourceSnafu)?;
             key_pairs.insert(
                 key_pair
                     .key_id()
@@ -545,14 +735,16 @@ impl TargetsEditor {
         keys: &[Box<dyn KeySource>],
     ) -> Result<Signed<DelegatedTargets>> {
         let rng = SystemRandom::new();
+        let created;
         let key_holder = if let Some(key_holder) = self.key_holder.as_ref() {
-            key_holder.clone()
+            key_holder
         } else {
-            self.create_key_holder(keys).await?
+            created = self.create_key_holder(keys).await?;
+            &created
         };
         // create a signed role for the targets being edited
         let targets = self.build_targets()?;
-        let targets = SignedRole::new(targets, &key_holder, keys, &rng).await?;
+        let targets = self.sign_role(targets, key_holder, keys, &rng).await?;
         Ok(targets.signed)
     }
 
@@ -562,24 +754,30 @@ impl TargetsEditor {
     pub async fn sign(&self, keys: &[Box<dyn KeySource>]) -> Result<SignedDelegatedTargets> {
         let rng = SystemRandom::new();
         let mut roles = Vec::new();
+        let created;
         let key_holder = if let Some(key_holder) = self.key_holder.as_ref() {
-            key_holder.clone()
+            key_holder
         } else {
-            self.create_key_holder(keys).await?
+            created = self.create_key_holder(keys).await?;
+            &created
         };
 
         // create a signed role for the targets we are editing
         let signed_targets = self.build_targets()?;
-        let signed_targets = SignedRole::new(signed_targets, &key_holder, keys, &rng).await?;
+        let signed_targets = self
+            .sign_role(signed_targets, key_holder, keys, &rng)
+            .await?
+            .restyle(self.metadata_style)?;
         roles.push(signed_targets);
         // create signed roles for any role metadata we added to this targets
         if let Some(new_roles) = &self.new_roles {
             for role in new_roles {
-                roles.push(SignedRole::from_signed(
+                roles.push(SignedRole::from_signed_with_style(
                     role.clone()
                         .targets
                         .context(error::NoTargetsSnafu)?
                         .delegated_targets(&role.name),
+                    self.metadata_style,
                 )?);
             }
         }
@@ -587,8 +785,73 @@ impl TargetsEditor {
         Ok(SignedDelegatedTargets {
             roles,
             consistent_snapshot: false,
+            filename_encoding: Arc::clone(&self.filename_encoding),
         })
     }
+
+    /// Returns every problem that would stop `create_signed()` from signing the role with
+    /// `keys`, rather than only the first. Roles created without a key holder have their keys
+    /// checked when they are delegated instead.
+    pub(crate) async fn problems(&self, keys: &[Box<dyn KeySource>]) -> Vec<error::Error> {
+        let mut problems = Vec::new();
+        if self.version.is_none() {
+            problems.push(
+                error::MissingSnafu {
+                    field: "targets version",
+                }
+                .build(),
+            );
+        }
+        if self.expires.is_none() {
+            problems.push(
+                error::MissingSnafu {
+                    field: "targets expiration",
+                }
+                .build(),
+            );
+        }
+        let targets = self
+            .existing_targets
+            .iter()
+            .chain(self.new_targets.iter())
+            .flatten();
+        for (name, target) in targets {
+            if target.hashes.algorithms().is_empty() {
+                problems.push(error::UnhashedTargetRefusedSnafu { name: name.raw() }.build());
+            }
+        }
+        if let Some(key_holder) = &self.key_holder {
+            let role = if self.name == "targets" {
+                RoleId::StandardRole(RoleType::Targets)
+            } else {
+                RoleId::DelegatedRole(self.name.clone())
+            };
+            if let Err(err) = key_holder.check_signing_threshold(role, keys).await {
+                problems.push(err);
+            }
+        }
+        problems
+    }
+
+    /// Signs the role being edited and appends any old signatures. With old signatures present,
+    /// the threshold is checked by verifying the signatures against the role's current keys, so
+    /// that only those keys count toward it.
+    async fn sign_role(
+        &self,
+        targets: DelegatedTargets,
+        key_holder: &KeyHolder,
+        keys: &[Box<dyn KeySource>],
+        rng: &SystemRandom,
+    ) -> Result<SignedRole<DelegatedTargets>> {
+        if self.old_signatures.is_empty() {
+            return SignedRole::new(targets, key_holder, keys, rng).await;
+        }
+        let (mut signed, _) = SignedRole::sign_with_keys(targets, key_holder, keys, rng).await?;
+        merge_signatures(&mut signed, self.old_signatures.clone());
+        let (name, role) = signed.clone().targets();
+        key_holder.verify_role(&role, &name)?;
+        SignedRole::from_signed(signed)
+    }
 }
 
 fn parse_url(url: &str) -> Result<Url> {
@@ -3,9 +3,11 @@
 
 #[cfg(test)]
 mod tests {
+    use crate::editor::jitter_expiration;
+    use crate::editor::signed::SignedRole;
     use crate::editor::RepositoryEditor;
     use crate::key_source::LocalKeySource;
-    use crate::schema::{Signed, Snapshot, Target, Targets, Timestamp};
+    use crate::schema::{Root, Signature, Signed, Snapshot, Target, Targets, Timestamp};
     use crate::TargetName;
     use chrono::{TimeDelta, Utc};
     use std::num::NonZeroU64;
@@ -51,6 +53,20 @@ mod tests {
     }
 
     // Make sure we can't create a repo without any data
+    #[tokio::test]
+    async fn unhashed_target_refused() {
+        let unhashed: Target = serde_json::from_str(r#"{"length": 5}"#).unwrap();
+        let mut editor = RepositoryEditor::new(tuf_root_path()).await.unwrap();
+        let err = editor
+            .add_target(TargetName::new("unhashed.txt").unwrap(), unhashed)
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::error::Error::UnhashedTargetRefused { .. }),
+            "{}",
+            err
+        );
+    }
+
     #[tokio::test]
     async fn empty_repository() {
         let root_key = key_path();
@@ -61,6 +77,16 @@ mod tests {
         assert!(editor.sign(&[Box::new(key_source)]).await.is_err());
     }
 
+    // Make sure jitter only ever moves an expiration earlier, by no more than the maximum
+    #[test]
+    fn expiration_jitter_is_bounded() {
+        let expires = Utc::now() + days(7);
+        for _ in 0..100 {
+            let jittered = jitter_expiration(expires, days(1)).unwrap();
+            assert!(jittered <= expires && jittered >= expires - days(1));
+        }
+    }
+
     // Make sure we can add targets from different sources
     #[allow(clippy::similar_names)]
     #[tokio::test]
@@ -172,4 +198,33 @@ mod tests {
         assert!(editor.snapshot_expires.is_none());
         assert!(editor.timestamp_expires.is_none());
     }
+
+    // Make sure serializing a role doesn't depend on map iteration or signature order
+    #[test]
+    fn deterministic_signed_role() {
+        let root: Signed<Root> =
+            serde_json::from_slice(&std::fs::read(tuf_root_path()).unwrap()).unwrap();
+        let mut first = root.clone();
+        for keyid in root.signed.keys.keys() {
+            first.signatures.push(Signature::new(
+                keyid.clone(),
+                root.signatures[0].sig.clone(),
+            ));
+        }
+
+        // Rebuilding the maps gives them a new hasher, and so a new iteration order.
+        let mut second = first.clone();
+        second.signed.keys = first.signed.keys.clone().into_iter().collect();
+        second.signed.roles = first.signed.roles.clone().into_iter().collect();
+        second.signatures.reverse();
+
+        let first = SignedRole::from_signed(first).unwrap();
+        let second = SignedRole::from_signed(second).unwrap();
+        assert_eq!(first.buffer(), second.buffer());
+        assert!(first
+            .signed()
+            .signatures
+            .windows(2)
+            .all(|pair| pair[0].keyid <= pair[1].keyid));
+    }
 }
@@ -0,0 +1,133 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `EmbeddedRootCheck`, which compares the trusted root that a product ships with against
+//! a repository's current root, so that products can be alerted before the root they embed falls
+//! too far behind.
+//!
+//! A client establishes trust by walking from its embedded root to the current root one version
+//! at a time, so an old embedded root still works, but each load walks further, and it stops
+//! working altogether once the walk exceeds [`Limits::max_root_updates`] or the repository stops
+//! serving the older roots. Clients that never update their root, such as those loaded with
+//! [`RootUpdate::Disabled`], stop working when the embedded root expires.
+//!
+//! [`Limits::max_root_updates`]: crate::Limits::max_root_updates
+//! [`RootUpdate::Disabled`]: crate::RootUpdate::Disabled
+
+use crate::error::{self, Result};
+use crate::schema::{Root, Signed};
+use crate::Repository;
+use chrono::{DateTime, TimeDelta, Utc};
+use snafu::{ensure, ResultExt};
+
+/// How far behind a repository's current root an embedded trusted root may be before
+/// [`EmbeddedRootCheck::check`] reports it as stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddedRootCheck {
+    /// The most root versions the embedded root may be behind the current root.
+    pub max_version_distance: u64,
+    /// The least time that must remain before the embedded root expires.
+    pub min_remaining_validity: TimeDelta,
+}
+
+/// By default, an embedded root is stale once it is more than 5 versions behind, or expires
+/// within 90 days.
+impl Default for EmbeddedRootCheck {
+    fn default() -> Self {
+        Self {
+            max_version_distance: 5,
+            min_remaining_validity: TimeDelta::days(90),
+        }
+    }
+}
+
+/// Why [`EmbeddedRootCheck::check`] found an embedded root to be stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Staleness {
+    /// The embedded root is more versions behind the current root than allowed.
+    VersionDistance {
+        /// How many versions behind the embedded root is.
+        distance: u64,
+        /// The most versions behind it may be.
+        max: u64,
+    },
+    /// The embedded root expires sooner than allowed, or has already expired.
+    Expiring {
+        /// When the embedded root expires.
+        expires: DateTime<Utc>,
+    },
+}
+
+/// How an embedded trusted root compares with a repository's current root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EmbeddedRootReport {
+    /// The version of the embedded root.
+    pub embedded_version: u64,
+    /// The version of the repository's current root.
+    pub current_version: u64,
+    /// How many versions behind the current root the embedded root is.
+    pub version_distance: u64,
+    /// When the embedded root expires.
+    pub expires: DateTime<Utc>,
+    /// Each way in which the embedded root is stale; empty if it isn't.
+    pub staleness: Vec<Staleness>,
+}
+
+impl EmbeddedRootReport {
+    /// Whether the embedded root should be replaced in the products that ship it.
+    pub fn is_stale(&self) -> bool {
+        !self.staleness.is_empty()
+    }
+}
+
+impl EmbeddedRootCheck {
+    /// Compares `embedded`, the contents of a root.json that a product ships as its trusted root,
+    /// with the current root of `repository`, as of `now`.
+    ///
+    /// `embedded` must be signed by a threshold of its own root keys, and must not be newer than
+    /// the current root. Whether it establishes trust in the current root isn't checked here;
+    /// load `repository` with `embedded` as its trusted root for that.
+    pub fn check(
+        &self,
+        embedded: &[u8],
+        repository: &Repository,
+        now: DateTime<Utc>,
+    ) -> Result<EmbeddedRootReport> {
+        let embedded: Signed<Root> =
+            serde_json::from_slice(embedded).context(error::ParseTrustedMetadataSnafu)?;
+        embedded
+            .signed
+            .verify_role(&embedded)
+            .context(error::VerifyTrustedMetadataSnafu)?;
+        let embedded_version = embedded.signed.version.get();
+        let current_version = repository.root().signed.version.get();
+        ensure!(
+            embedded_version <= current_version,
+            error::EmbeddedRootNewerSnafu {
+                embedded_version,
+                current_version,
+            }
+        );
+
+        let version_distance = current_version - embedded_version;
+        let expires = embedded.signed.expires;
+        let mut staleness = Vec::new();
+        if version_distance > self.max_version_distance {
+            staleness.push(Staleness::VersionDistance {
+                distance: version_distance,
+                max: self.max_version_distance,
+            });
+        }
+        if expires - now < self.min_remaining_validity {
+            staleness.push(Staleness::Expiring { expires });
+        }
+        Ok(EmbeddedRootReport {
+            embedded_version,
+            current_version,
+            version_distance,
+            expires,
+            staleness,
+        })
+    }
+}
@@ -10,6 +10,7 @@ use crate::{schema, TargetName, TransportError};
 use chrono::{DateTime, Utc};
 use snafu::{Backtrace, Snafu};
 use std::io;
+use std::num::NonZeroU64;
 use std::path::PathBuf;
 use url::Url;
 
@@ -29,6 +30,13 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// The loader requires consistent snapshots but the trusted root doesn't enable them.
+    #[snafu(display(
+        "Root version {} sets consistent_snapshot to false, but the loader requires consistent snapshots",
+        version
+    ))]
+    ConsistentSnapshotRequired { version: u64, backtrace: Backtrace },
+
     #[snafu(display(
         "Failed to create temp directory for the repository datastore: {}",
         source
@@ -39,38 +47,75 @@ pub enum Error {
     },
 
     /// The library failed to create a file in the datastore.
-    #[snafu(display("Failed to create file at datastore path {}: {}", path.display(), source))]
+    #[snafu(display("Failed to create '{}' in the datastore: {}", name, source))]
     DatastoreCreate {
-        path: PathBuf,
-        source: std::io::Error,
+        name: String,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        backtrace: Backtrace,
+    },
+
+    /// The library failed to list the files in the datastore.
+    #[snafu(display("Failed to list the files in the datastore: {}", source))]
+    DatastoreList {
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        backtrace: Backtrace,
+    },
+
+    /// The library failed to lock the datastore against other clients sharing it.
+    #[snafu(display("Failed to lock the datastore: {}", source))]
+    DatastoreLock {
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
         backtrace: Backtrace,
     },
 
     /// The library failed to open a file in the datastore.
-    #[snafu(display("Failed to open file from datastore path {}: {}", path.display(), source))]
+    #[snafu(display("Failed to open '{}' from the datastore: {}", name, source))]
     DatastoreOpen {
-        path: PathBuf,
-        source: std::io::Error,
+        name: String,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
         backtrace: Backtrace,
     },
 
     /// The library failed to remove a file in the datastore.
-    #[snafu(display("Failed to remove file at datastore path {}: {}", path.display(), source))]
+    #[snafu(display("Failed to remove '{}' from the datastore: {}", name, source))]
     DatastoreRemove {
-        path: PathBuf,
-        source: std::io::Error,
+        name: String,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
         backtrace: Backtrace,
     },
 
+    /// A role given to reset the datastore could name a file outside it.
+    #[snafu(display(
+        "Role '{}' can't be reset: it could name a file outside the datastore",
+        role
+    ))]
+    DatastoreUnsafeRole { role: String, backtrace: Backtrace },
+
     /// The library failed to serialize an object to JSON to the datastore.
-    #[snafu(display("Failed to serialize {} to JSON at datastore path {}: {}", what, path.display(), source))]
+    #[snafu(display(
+        "Failed to serialize {} to JSON as '{}' in the datastore: {}",
+        what,
+        name,
+        source
+    ))]
     DatastoreSerialize {
         what: String,
-        path: PathBuf,
+        name: String,
         source: serde_json::Error,
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Delegation cycle: role '{}' is delegated by its own descendant ({})",
+        name,
+        chain.join(" -> ")
+    ))]
+    DelegationCycle {
+        name: String,
+        chain: Vec<String>,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to create directory '{}': {}", path.display(), source))]
     DirCreate {
         path: PathBuf,
@@ -79,9 +124,19 @@ pub enum Error {
     },
 
     /// A metadata file has expired.
-    #[snafu(display("{} metadata is expired", role))]
+    #[snafu(display(
+        "{} metadata version {} from '{}' expired at {}",
+        name,
+        version,
+        url,
+        expires
+    ))]
     ExpiredMetadata {
         role: RoleType,
+        name: String,
+        url: Url,
+        version: u64,
+        expires: DateTime<Utc>,
         backtrace: Backtrace,
     },
 
@@ -123,6 +178,13 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to compress metadata for '{}': {}", path.display(), source))]
+    CompressMetadata {
+        path: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
     /// A downloaded target's checksum does not match the checksum listed in the repository
     /// metadata.
     #[snafu(display(
@@ -138,6 +200,31 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// The [`VerificationPolicy`](crate::VerificationPolicy) requires a hash that isn't listed
+    /// for a file.
+    #[snafu(display("No {} hash is listed for {}", algorithm, context))]
+    HashMissing {
+        context: String,
+        algorithm: String,
+        backtrace: Backtrace,
+    },
+
+    /// A hash listed for a file isn't a hex string.
+    #[snafu(display("The {} hash listed for {} is not valid hex", algorithm, context))]
+    InvalidListedHash {
+        context: String,
+        algorithm: String,
+        backtrace: Backtrace,
+    },
+
+    /// The [`VerificationPolicy`](crate::VerificationPolicy) requires the length of a metadata
+    /// file to be listed, but it isn't.
+    #[snafu(display("No length is listed for {}", context))]
+    MetadataLengthMissing {
+        context: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Source path for target must be file or symlink - '{}'", path.display()))]
     InvalidFileType { path: PathBuf, backtrace: Backtrace },
 
@@ -188,6 +275,17 @@ pub enum Error {
     #[snafu(display("Unrecognized private key format"))]
     KeyUnrecognized { backtrace: Backtrace },
 
+    #[snafu(display("Private key scheme is not allowed in {} crypto mode", mode))]
+    KeyDisallowed {
+        mode: crate::CryptoMode,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "OpenSSH private key is encrypted; remove its passphrase with `ssh-keygen -p` to use it"
+    ))]
+    KeyEncrypted { backtrace: Backtrace },
+
     #[snafu(display("Failed to create symlink at '{}': {}", path.display(), source))]
     LinkCreate {
         path: PathBuf,
@@ -195,6 +293,60 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to create hard link at '{}': {}", path.display(), source))]
+    HardLinkCreate {
+        path: PathBuf,
+        source: io::Error,
+        backtrace: Backtrace,
+    },
+
+    /// A `MultiRepositoryLoader` was not given a loader for a repository that its map names.
+    #[snafu(display("No loader was given for repository '{}' in the map", name))]
+    MapMissingLoader { name: String, backtrace: Backtrace },
+
+    /// A terminating mapping's repositories listed a target, but too few of them agreed on it.
+    #[snafu(display(
+        "Only {} repositories agree on target '{}', {} are required",
+        agreeing,
+        name,
+        threshold
+    ))]
+    MapNoConsensus {
+        name: String,
+        threshold: NonZeroU64,
+        agreeing: usize,
+        backtrace: Backtrace,
+    },
+
+    /// A repository named in a map failed to load.
+    #[snafu(display("Failed to load repository '{}': {}", name, source))]
+    MapRepositoryLoad {
+        name: String,
+        source: Box<Error>,
+        backtrace: Backtrace,
+    },
+
+    /// A mapping's threshold can't be met by its repositories.
+    #[snafu(display(
+        "Mapping threshold {} is greater than its {} repositories",
+        threshold,
+        repositories
+    ))]
+    MapThreshold {
+        threshold: NonZeroU64,
+        repositories: usize,
+        backtrace: Backtrace,
+    },
+
+    /// A mapping names the same repository more than once, which would let it count more than
+    /// once toward the mapping's threshold.
+    #[snafu(display("Mapping names repository '{}' more than once", name))]
+    MapDuplicateRepository { name: String, backtrace: Backtrace },
+
+    /// A mapping names a repository that the map doesn't list.
+    #[snafu(display("Mapping names repository '{}', which the map doesn't list", name))]
+    MapUnknownRepository { name: String, backtrace: Backtrace },
+
     /// A file's maximum size exceeded a limit set by the consumer of this library or the metadata.
     #[snafu(display("Maximum size {} (specified by {}) exceeded", max_size, specifier))]
     MaxSizeExceeded {
@@ -203,6 +355,13 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// The maximum delegated roles setting was exceeded.
+    #[snafu(display("Maximum number of delegated roles {} exceeded", max_roles))]
+    MaxDelegatedRolesExceeded {
+        max_roles: u64,
+        backtrace: Backtrace,
+    },
+
     /// The maximum root updates setting was exceeded.
     #[snafu(display("Maximum root updates {} exceeded", max_root_updates))]
     MaxUpdatesExceeded {
@@ -218,6 +377,20 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// Loading a role outlasted a deadline set on the `RepositoryLoader`.
+    #[snafu(display(
+        "Loading {} metadata exceeded the {} deadline of {:?}",
+        role,
+        scope,
+        timeout
+    ))]
+    LoadTimeout {
+        role: RoleType,
+        scope: &'static str,
+        timeout: std::time::Duration,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Missing '{}' when building repo from RepositoryEditor", field))]
     Missing { field: String, backtrace: Backtrace },
 
@@ -242,20 +415,75 @@ pub enum Error {
     #[snafu(display("Key for role '{}' doesn't exist in root.json", role))]
     NoRoleKeysinRoot { role: String },
 
+    /// None of the candidate trusted roots given to `RepositoryLoader` established trust in the
+    /// repository's current root.
+    #[snafu(display(
+        "None of the {} candidate trusted roots established trust; the last one failed: {}",
+        candidates,
+        source
+    ))]
+    NoTrustedRoot {
+        candidates: usize,
+        source: Box<Error>,
+        backtrace: Backtrace,
+    },
+
+    /// No candidate trusted roots were given to establish trust.
+    #[snafu(display("No candidate trusted roots were given"))]
+    NoTrustedRootCandidates { backtrace: Backtrace },
+
+    /// An offline load was requested without a datastore to load the metadata from.
+    #[snafu(display("Offline loads need a datastore written by an earlier online load"))]
+    OfflineDatastoreRequired { backtrace: Backtrace },
+
     /// A downloaded metadata file has an older version than a previously downloaded metadata file.
     #[snafu(display(
-        "Found version {} of {} metadata when we had previously fetched version {}",
+        "Found version {} of {} metadata in '{}' when we had previously fetched version {}",
         new_version,
-        role,
+        name,
+        url,
         current_version
     ))]
     OlderMetadata {
         role: RoleType,
+        name: String,
+        url: Url,
         current_version: u64,
         new_version: u64,
         backtrace: Backtrace,
     },
 
+    /// timestamp.json describes files other than snapshot.json, and the
+    /// [`TimestampMetaPolicy`](crate::TimestampMetaPolicy) is to fail.
+    #[snafu(display(
+        "Timestamp metadata at '{}' must only describe snapshot.json, but also describes: {}",
+        url,
+        files
+    ))]
+    TimestampMetaUnexpected {
+        files: String,
+        url: Url,
+        backtrace: Backtrace,
+    },
+
+    /// A target is listed without a hash in any supported algorithm, so it can't be verified,
+    /// and the [`UnhashedTargetPolicy`](crate::UnhashedTargetPolicy) is to fail.
+    #[snafu(display(
+        "Target '{}' listed by role '{}' has no supported hash and can't be verified",
+        name,
+        role
+    ))]
+    UnhashedTargetListed {
+        name: String,
+        role: String,
+        backtrace: Backtrace,
+    },
+
+    /// The editor was given a target without a hash in any supported algorithm, which clients
+    /// can't verify.
+    #[snafu(display("Target '{}' has no supported hash; clients can't verify it", name))]
+    UnhashedTargetRefused { name: String, backtrace: Backtrace },
+
     /// The library failed to parse a metadata file, either because it was not valid JSON or it did
     /// not conform to the expected schema.
     ///
@@ -272,6 +500,14 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// The library failed to parse a metadata bundle, because it was not valid JSON or did not
+    /// have the expected entries.
+    #[snafu(display("Failed to parse metadata bundle: {}", source))]
+    ParseBundle {
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
     /// The library failed to parse the trusted root metadata file, either because it was not valid
     /// JSON or it did not conform to the expected schema. The *trusted* root metadata file is the
     /// file is either the `root` argument passed to `Repository::load`, or the most recently
@@ -316,6 +552,19 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// The part of a target that was already downloaded, given to
+    /// [`Repository::resume_target`](crate::Repository::resume_target), couldn't be read.
+    #[snafu(display(
+        "Failed to read the downloaded part of target '{}': {}",
+        name.raw(),
+        source
+    ))]
+    ResumeTargetRead {
+        name: TargetName,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Unable to get info about the outdir '{}': {}", path.display(), source))]
     SaveTargetDirInfo {
         path: PathBuf,
@@ -344,12 +593,32 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Unable to read the file mode listed for target '{}': {}", name.raw(), source))]
+    SaveTargetMode {
+        name: TargetName,
+        source: crate::schema::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("The target '{}' is not listed by the role being edited", name.raw()))]
+    TargetNotListed {
+        name: TargetName,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("The target '{}' was not found", name.raw()))]
     SaveTargetNotFound {
         name: TargetName,
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to set the permissions of '{}': {}", path.display(), source))]
+    SaveTargetSetMode {
+        path: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display(
         "The target '{}' had an unsafe name. Not writing to '{}' because it is not in the outdir '{}'",
         name.raw(),
@@ -362,6 +631,36 @@ pub enum Error {
         filepath: PathBuf,
     },
 
+    /// `RootEditor::add_key` was given a key whose public key root.json already lists under
+    /// another key ID.
+    #[snafu(display("Public key is already listed in root.json with key ID {}", key_id))]
+    RootKeyDuplicate {
+        key_id: String,
+        backtrace: Backtrace,
+    },
+
+    /// The [`RootUpdatePolicy`](crate::RootUpdatePolicy) rejected a new version of root.json.
+    #[snafu(display(
+        "Update to root.json version {} was rejected by policy: {}",
+        version,
+        source
+    ))]
+    RootUpdateRejected {
+        version: NonZeroU64,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Root version cannot be incremented past the maximum"))]
+    RootVersionOverflow { backtrace: Backtrace },
+
+    /// The [`ToughContext`](crate::ToughContext) couldn't build its tokio runtime.
+    #[snafu(display("Failed to create a tokio runtime: {}", source))]
+    RuntimeCreate {
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to serialize role '{}' for signing: {}", role, source))]
     SerializeRole {
         role: String,
@@ -376,6 +675,29 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to generate a key pair"))]
+    KeyGenerate {
+        source: aws_lc_rs::error::Unspecified,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Moving {} earlier by {} is outside the supported range of dates",
+        datetime,
+        delta
+    ))]
+    DatetimeOutOfRange {
+        datetime: DateTime<Utc>,
+        delta: chrono::TimeDelta,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to generate random data"))]
+    Random {
+        source: aws_lc_rs::error::Unspecified,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to sign message"))]
     Sign {
         source: aws_lc_rs::error::Unspecified,
@@ -388,9 +710,55 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to talk to SSH agent at '{}': {}", path.display(), source))]
+    SshAgentIo {
+        path: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "SSH agent holds {} Ed25519 keys matching {}; expected one",
+        count,
+        key
+    ))]
+    SshAgentKeyAmbiguous {
+        key: String,
+        count: usize,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("SSH agent holds no Ed25519 key matching {}", key))]
+    SshAgentKeyNotFound { key: String, backtrace: Backtrace },
+
+    #[snafu(display("Unexpected reply from SSH agent: {}", message))]
+    SshAgentProtocol {
+        message: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("No SSH agent socket given and SSH_AUTH_SOCK is not set"))]
+    SshAgentSocketMissing { backtrace: Backtrace },
+
+    #[snafu(display("Keys cannot be written to an SSH agent; add them with ssh-add instead"))]
+    SshAgentWrite { backtrace: Backtrace },
+
     #[snafu(display("Unable to find signing keys for role '{}'", role))]
     SigningKeysNotFound { role: String },
 
+    /// Fewer of the given keys are listed for a role than its threshold requires.
+    #[snafu(display(
+        "Role '{}' needs {} signatures, but only {} of the given keys can sign it",
+        role,
+        threshold,
+        available
+    ))]
+    SigningThreshold {
+        role: String,
+        threshold: u64,
+        available: u64,
+    },
+
     #[snafu(display(
         "Tried to use role metadata with spec version '{}', version '{}' is supported",
         given,
@@ -470,9 +838,18 @@ pub enum Error {
     UnsafeTargetNameSlash { name: String },
 
     /// A metadata file could not be verified.
-    #[snafu(display("Failed to verify {} metadata: {}", role, source))]
+    #[snafu(display(
+        "Failed to verify {} metadata version {} from '{}': {}",
+        name,
+        version,
+        url,
+        source
+    ))]
     VerifyMetadata {
         role: RoleType,
+        name: String,
+        url: Url,
+        version: u64,
         source: crate::schema::Error,
         backtrace: Backtrace,
     },
@@ -484,6 +861,17 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A root embedded as a trusted root is newer than the repository's current root.
+    #[snafu(display(
+        "Embedded root version {} is newer than the repository's current root version {}",
+        embedded_version,
+        current_version
+    ))]
+    EmbeddedRootNewer {
+        embedded_version: u64,
+        current_version: u64,
+    },
+
     /// The trusted root metadata file could not be verified.
     #[snafu(display("Failed to verify trusted root metadata: {}", source))]
     VerifyTrustedMetadata {
@@ -493,18 +881,51 @@ pub enum Error {
 
     /// A fetched metadata file did not have the version we expected it to have.
     #[snafu(display(
-        "{} metadata version mismatch: fetched {}, expected {}",
-        role,
+        "{} metadata version mismatch in '{}': fetched {}, expected {}",
+        name,
+        url,
         fetched,
         expected
     ))]
     VersionMismatch {
         role: RoleType,
+        name: String,
+        url: Url,
         fetched: u64,
         expected: u64,
         backtrace: Backtrace,
     },
 
+    /// The editor was asked to sign a role with a version no greater than the published one it
+    /// was loaded from, which clients would reject.
+    #[snafu(display(
+        "New {} version {} is not greater than the loaded version {}",
+        role,
+        version,
+        loaded
+    ))]
+    VersionRegression {
+        role: RoleType,
+        version: u64,
+        loaded: u64,
+        backtrace: Backtrace,
+    },
+
+    /// The editor was asked to sign a delegated role that changed since it was loaded, with a
+    /// version no greater than the published one, which clients would reject.
+    #[snafu(display(
+        "Delegated role '{}' changed, but its new version {} is not greater than the loaded version {}",
+        name,
+        version,
+        loaded
+    ))]
+    DelegatedVersionRegression {
+        name: String,
+        version: u64,
+        loaded: u64,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Error reading data from '{}': {}", url, source))]
     CacheFileRead {
         url: Url,
@@ -519,6 +940,13 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "The datastore no longer holds the version of '{}' that was loaded, so it can't be cached; \
+        a later load may have replaced it",
+        file
+    ))]
+    CacheMetadataChanged { file: String, backtrace: Backtrace },
+
     #[snafu(display("Error creating the directory '{}': {}", path.display(), source))]
     CacheDirectoryCreate {
         path: PathBuf,
@@ -533,6 +961,13 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Error removing unlisted target file '{}': {}", path.display(), source))]
+    CacheTargetRemove {
+        path: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("The target '{}' was not found", target_name.raw()))]
     CacheTargetMissing {
         target_name: TargetName,
@@ -568,6 +1003,17 @@ pub enum Error {
     #[snafu(display("Targets doesn't contain delegations field"))]
     NoDelegations,
 
+    /// A delegated role lost signing keys and is no longer signed by a threshold of its keys.
+    #[snafu(display(
+        "Delegated role '{}' must be re-signed after its keys changed: {}",
+        role,
+        source
+    ))]
+    ResignRequired {
+        role: String,
+        source: crate::schema::Error,
+    },
+
     #[snafu(display("Delegated roles are not consistent for {}", name))]
     DelegatedRolesNotConsistent { name: String },
 
@@ -575,9 +1021,26 @@ pub enum Error {
     #[snafu(display("Invalid file permissions"))]
     InvalidPath { source: crate::schema::Error },
 
+    /// The requested hashed bins couldn't be created.
+    #[snafu(display("Invalid hashed bins: {}", source))]
+    InvalidHashedBins { source: crate::schema::Error },
+
     #[snafu(display("Role missing from snapshot meta: {}", name))]
     RoleNotInMeta { name: String },
 
+    /// A filename encoding turned a delegated role name into a filename that could refer to a
+    /// file outside the metadata directory.
+    #[snafu(display(
+        "Filename encoding turned role '{}' into unsafe filename '{}'",
+        name,
+        filename
+    ))]
+    UnsafeRoleFilename {
+        name: String,
+        filename: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("The key for {} was not included", role))]
     KeyNotFound {
         role: String,
@@ -597,6 +1060,52 @@ pub enum Error {
     #[snafu(display("Invalid threshold number"))]
     InvalidThreshold { backtrace: Backtrace },
 
+    #[snafu(display("Failed to serialize gzip manifest: {}", source))]
+    SerializeGzipManifest {
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to serialize metadata bundle: {}", source))]
+    SerializeBundle {
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
+    /// A target is listed in only one of an Uptane director and image repository pair.
+    #[snafu(display("Target '{}' is not listed in the {} repository", name, repository))]
+    UptaneTargetMissing {
+        name: String,
+        repository: String,
+        backtrace: Backtrace,
+    },
+
+    /// An Uptane director and image repository disagree about a target.
+    #[snafu(display(
+        "Target '{}' has a different {} in the director and image repositories",
+        name,
+        field
+    ))]
+    UptaneTargetMismatch {
+        name: String,
+        field: String,
+        backtrace: Backtrace,
+    },
+
+    /// An Uptane director and image repository list a target without a hash algorithm in common,
+    /// so they can't be shown to agree on it.
+    #[snafu(display(
+        "Target '{}' has no hash algorithm in common in the director and image repositories",
+        name
+    ))]
+    UptaneNoSharedHash { name: String, backtrace: Backtrace },
+
+    #[snafu(display("Failed to set extension field for '{}': {}", path, source))]
+    MetafileExtension {
+        path: String,
+        source: crate::schema::Error,
+    },
+
     /// The library failed to serialize an object to JSON.
     #[snafu(display("Failed to serialize to JSON: {}", source))]
     JsonSerialization {
@@ -645,4 +1154,22 @@ pub enum Error {
 
     #[snafu(display("The targets editor was not cleared"))]
     TargetsEditorSome,
+
+    #[snafu(display("Time-stamping authority request failed: {}", source))]
+    TimestampAuthority {
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Timestamp token does not cover the metadata digest"))]
+    TimestampImprintMismatch { backtrace: Backtrace },
+
+    #[snafu(display("Timestamp token does not echo the request's nonce"))]
+    TimestampNonceMismatch { backtrace: Backtrace },
+
+    #[snafu(display("Timestamp response is not valid DER"))]
+    TimestampMalformed { backtrace: Backtrace },
+
+    #[snafu(display("Time-stamping authority rejected the request with status {}", status))]
+    TimestampRejected { status: u64, backtrace: Backtrace },
 }
@@ -2,8 +2,11 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::error::{self, Result};
-use crate::io::{max_size_adapter, DigestAdapter};
+use crate::io::{DigestAdapter, MaxSizeAdapter, OnExceeded};
+use crate::policy::HashAlgorithm;
 use crate::transport::{Transport, TransportStream};
+use aws_lc_rs::digest::Context;
+use futures::StreamExt;
 use snafu::ResultExt;
 use url::Url;
 
@@ -18,17 +21,72 @@ pub(crate) async fn fetch_max_size(
         .await
         .with_context(|_| error::TransportSnafu { url: url.clone() })?;
 
-    let stream = max_size_adapter(stream, url, max_size, specifier);
+    let stream = MaxSizeAdapter::new(stream, url, max_size, specifier).boxed();
     Ok(stream)
 }
 
-pub(crate) async fn fetch_sha256(
+/// Fetches `url`, failing if it is larger than `size` or doesn't match every one of `digests`.
+pub(crate) async fn fetch_digests(
     transport: &dyn Transport,
     url: Url,
     size: u64,
     specifier: &'static str,
-    sha256: &[u8],
+    digests: &[(HashAlgorithm, Vec<u8>)],
 ) -> Result<TransportStream> {
-    let stream = fetch_max_size(transport, url.clone(), size, specifier).await?;
-    Ok(DigestAdapter::sha256(stream, sha256, url))
+    let stream = transport
+        .fetch(url.clone())
+        .await
+        .with_context(|_| error::TransportSnafu { url: url.clone() })?;
+    Ok(check_digests(stream, &url, size, specifier, digests, None))
+}
+
+/// Wraps `stream`, the contents of `url`, so that it fails if it is larger than `size` or doesn't
+/// match every one of `digests`. If it is larger, `on_exceeded` is called with the number of bytes
+/// received.
+pub(crate) fn check_digests(
+    stream: TransportStream,
+    url: &Url,
+    size: u64,
+    specifier: &'static str,
+    digests: &[(HashAlgorithm, Vec<u8>)],
+    on_exceeded: Option<OnExceeded>,
+) -> TransportStream {
+    let mut stream = MaxSizeAdapter::new(stream, url.clone(), size, specifier)
+        .on_exceeded(on_exceeded)
+        .boxed();
+    for (algorithm, digest) in digests {
+        stream = DigestAdapter::new(stream, *algorithm, digest, url.clone()).boxed();
+    }
+    stream
+}
+
+/// Fetches `url` from byte `offset` on, for a download that already received the bytes before it
+/// and updated `digests` with them. Fails if the whole file is larger than `size` or doesn't match
+/// every one of `digests`. If it is larger, `on_exceeded` is called with the number of bytes
+/// received after `offset`.
+pub(crate) async fn fetch_digests_from(
+    transport: &dyn Transport,
+    url: Url,
+    offset: u64,
+    size: u64,
+    specifier: &'static str,
+    digests: Vec<(Context, Vec<u8>)>,
+    on_exceeded: OnExceeded,
+) -> Result<TransportStream> {
+    let mut stream = if offset == size {
+        // Everything was downloaded already, and only needs checking.
+        futures::stream::empty().boxed()
+    } else {
+        transport
+            .fetch_range(url.clone(), offset)
+            .await
+            .with_context(|_| error::TransportSnafu { url: url.clone() })?
+    };
+    stream = MaxSizeAdapter::new(stream, url.clone(), size - offset, specifier)
+        .on_exceeded(Some(on_exceeded))
+        .boxed();
+    for (context, digest) in digests {
+        stream = DigestAdapter::resume(stream, context, &digest, url.clone()).boxed();
+    }
+    Ok(stream)
 }
@@ -0,0 +1,172 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `FilenameEncoding`, which turns the names of delegated roles into the filenames their
+//! metadata is stored under.
+
+use crate::encode_filename;
+use crate::error::{self, Result};
+use percent_encoding::utf8_percent_encode;
+use snafu::ensure;
+use std::fmt::Debug;
+use std::num::NonZeroU64;
+
+/// Turns the name of a delegated role into the name of its metadata file, without the `.json`
+/// extension or any version prefix.
+///
+/// The same encoding has to be used everywhere a repository's metadata is written and read, so
+/// set it on the [`RepositoryEditor`](crate::editor::RepositoryEditor) that writes a repository
+/// and on each [`RepositoryLoader`](crate::RepositoryLoader) that reads it. Whatever the encoding,
+/// a filename that could reach outside the metadata directory, such as one containing `/`, is
+/// refused.
+pub trait FilenameEncoding: Debug + Send + Sync {
+    /// Encodes the role name `name`.
+    fn encode(&self, name: &str) -> String;
+}
+
+/// Percent-encodes every character except ASCII letters, digits, and `_.-~`, like Python's
+/// `urllib.parse.quote(name, safe="")`. This matches the Python TUF implementation and is the
+/// default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PercentEncoding;
+
+impl FilenameEncoding for PercentEncoding {
+    fn encode(&self, name: &str) -> String {
+        encode_filename(name)
+    }
+}
+
+/// Percent-encodes ASCII characters the way [`PercentEncoding`] does, but keeps every other
+/// character as it is, for storage that handles Unicode filenames and mirrors that expect them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnicodePreservingEncoding;
+
+impl FilenameEncoding for UnicodePreservingEncoding {
+    fn encode(&self, name: &str) -> String {
+        let mut encoded = String::with_capacity(name.len());
+        let mut buf = [0; 4];
+        for c in name.chars() {
+            if c.is_ascii() {
+                encoded.extend(utf8_percent_encode(
+                    c.encode_utf8(&mut buf),
+                    &crate::CHARACTERS_TO_ESCAPE,
+                ));
+            } else {
+                encoded.push(c);
+            }
+        }
+        encoded
+    }
+}
+
+/// Encodes the delegated role name `name`, refusing encodings that could name a file outside the
+/// metadata directory.
+pub(crate) fn encode_role_name(encoding: &dyn FilenameEncoding, name: &str) -> Result<String> {
+    let encoded = encoding.encode(name);
+    ensure!(
+        !encoded.is_empty()
+            && encoded != "."
+            && encoded != ".."
+            && !encoded.contains(['/', '\\', '\0']),
+        error::UnsafeRoleFilenameSnafu {
+            name,
+            filename: encoded,
+        }
+    );
+    Ok(encoded)
+}
+
+/// The filename of the metadata of the delegated role `name`, prefixed with `version` when
+/// consistent snapshots are used.
+pub(crate) fn role_filename(
+    encoding: &dyn FilenameEncoding,
+    name: &str,
+    version: Option<NonZeroU64>,
+) -> Result<String> {
+    let encoded = encode_role_name(encoding, name)?;
+    Ok(match version {
+        Some(version) => format!("{version}.{encoded}.json"),
+        None => format!("{encoded}.json"),
+    })
+}
+
+/// Decodes the percent-encoded sequences in a `file://` URL path that stand for non-ASCII
+/// characters, which `Url::join` adds when a filename contains them, and leaves everything else
+/// as it is. No ASCII character, and so no path separator, can come out of this.
+pub(crate) fn decode_non_ascii(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut changed = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = path
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .filter(|byte| !byte.is_ascii())
+            {
+                decoded.push(byte);
+                changed = true;
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    if !changed {
+        return None;
+    }
+    // Sequences that don't form valid UTF-8 can't be a filename we wrote, so leave them encoded.
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_encoding_escapes_unicode() {
+        assert_eq!(PercentEncoding.encode("café/x"), "caf%C3%A9%2Fx");
+    }
+
+    #[test]
+    fn unicode_preserving_encoding() {
+        assert_eq!(UnicodePreservingEncoding.encode("café/x y"), "café%2Fx%20y");
+        assert_eq!(
+            UnicodePreservingEncoding.encode("日本-1_a.b~"),
+            "日本-1_a.b~"
+        );
+    }
+
+    #[derive(Debug)]
+    struct Literal;
+
+    impl FilenameEncoding for Literal {
+        fn encode(&self, name: &str) -> String {
+            name.to_owned()
+        }
+    }
+
+    #[test]
+    fn unsafe_filenames_are_refused() {
+        for name in ["", ".", "..", "a/b", "a\\b"] {
+            assert!(role_filename(&Literal, name, None).is_err(), "{}", name);
+        }
+        assert_eq!(
+            role_filename(&Literal, "a..b", NonZeroU64::new(3)).unwrap(),
+            "3.a..b.json"
+        );
+    }
+
+    #[test]
+    fn decode_only_non_ascii() {
+        assert_eq!(
+            decode_non_ascii("/repo/caf%C3%A9%2Fx.json").unwrap(),
+            "/repo/café%2Fx.json"
+        );
+        assert!(decode_non_ascii("/repo/a%2E%2E.json").is_none());
+        // Sequences that aren't valid UTF-8 aren't decoded.
+        assert!(decode_non_ascii("/repo/%FF.json").is_none());
+    }
+}
@@ -9,15 +9,21 @@ use futures_core::stream::BoxStream;
 use futures_core::Stream;
 use log::trace;
 use reqwest::header::{self, HeaderValue, ACCEPT_RANGES};
-use reqwest::{Client, ClientBuilder, Request, Response};
+use reqwest::{Client, ClientBuilder, Request, Response, StatusCode};
 use reqwest::{Error, Method};
 use rustls::crypto::{aws_lc_rs, CryptoProvider};
 use snafu::ResultExt;
 use snafu::Snafu;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU64;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
 use std::task::Poll;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use url::Url;
 
 /// A builder for [`HttpTransport`] which allows settings customization.
@@ -29,12 +35,15 @@ use url::Url;
 /// let http_transport = HttpTransportBuilder::new()
 /// .tries(3)
 /// .backoff_factor(1.5)
+/// .pool_max_idle_per_host(16)
+/// .http2(true)
+/// .resolve("tuf.example.com", [10, 0, 0, 7].into())
 /// .build();
 /// ```
 ///
-/// See [`HttpTransport`] for proxy support and other behavior details.
-///
-#[derive(Clone, Copy, Debug)]
+/// See [`HttpTransport`] for proxy support and other behavior details. Clones of a builder share
+/// the host overrides added with [`resolve`](Self::resolve).
+#[derive(Clone, Debug)]
 pub struct HttpTransportBuilder {
     timeout: Duration,
     connect_timeout: Duration,
@@ -42,6 +51,13 @@ pub struct HttpTransportBuilder {
     initial_backoff: Duration,
     max_backoff: Duration,
     backoff_factor: f32,
+    pool_max_idle_per_host: usize,
+    pool_idle_timeout: Option<Duration>,
+    http2: bool,
+    tcp_keepalive: Option<Duration>,
+    compression: bool,
+    resolve: Arc<[(String, IpAddr)]>,
+    max_bytes_per_second: Option<NonZeroU64>,
 }
 
 impl Default for HttpTransportBuilder {
@@ -62,6 +78,14 @@ impl Default for HttpTransportBuilder {
             initial_backoff: std::time::Duration::from_millis(100),
             max_backoff: std::time::Duration::from_secs(1),
             backoff_factor: 1.5,
+            // These match reqwest's defaults.
+            pool_max_idle_per_host: usize::MAX,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            http2: false,
+            tcp_keepalive: None,
+            compression: false,
+            resolve: Arc::new([]),
+            max_bytes_per_second: None,
         }
     }
 }
@@ -116,9 +140,123 @@ impl HttpTransportBuilder {
         self
     }
 
+    /// Set the maximum number of idle connections kept open per host. Reusing connections avoids
+    /// a new TCP and TLS handshake for each of the many small requests a repository load makes.
+    #[must_use]
+    pub fn pool_max_idle_per_host(mut self, value: usize) -> Self {
+        self.pool_max_idle_per_host = value;
+        self
+    }
+
+    /// Set how long an idle connection is kept open for reuse, or `None` to keep it indefinitely.
+    #[must_use]
+    pub fn pool_idle_timeout(mut self, value: Option<Duration>) -> Self {
+        self.pool_idle_timeout = value;
+        self
+    }
+
+    /// Allow HTTP/2 to be negotiated with servers that support it. When disabled (the default),
+    /// only HTTP/1.1 is used.
+    #[must_use]
+    pub fn http2(mut self, value: bool) -> Self {
+        self.http2 = value;
+        self
+    }
+
+    /// Set the interval for TCP keepalive probes on open connections, or `None` to disable them.
+    #[must_use]
+    pub fn tcp_keepalive(mut self, value: Option<Duration>) -> Self {
+        self.tcp_keepalive = value;
+        self
+    }
+
+    /// Ask servers to compress metadata (files ending in `.json`) using whichever of gzip and zstd
+    /// this crate was built with, via its `gzip` and `zstd` features, and decompress responses as
+    /// they stream in. Without either feature this setting has no effect.
+    ///
+    /// Size limits, whether from [`Limits`](crate::Limits) or signed metadata, and hashes are
+    /// checked against the decompressed bytes, which are the bytes that were signed. A response
+    /// that decompresses to more than the limit is rejected like any other oversized response.
+    ///
+    /// Byte offsets into a compressed response don't correspond to offsets into the decompressed
+    /// metadata, so a compressed metadata fetch can't be resumed. It is retried only if it fails
+    /// before any bytes arrive; once bytes have been passed on, an interrupted fetch fails rather
+    /// than starting over. Targets are always fetched uncompressed so that their downloads can
+    /// resume.
+    #[must_use]
+    pub fn compression(mut self, value: bool) -> Self {
+        self.compression = value;
+        self
+    }
+
+    /// Connect to `ip` for requests to `host`, rather than resolving `host` with the system's DNS.
+    /// Call this more than once for the same host to give several addresses to try.
+    ///
+    /// This lets split-horizon deployments and tests reach a repository at an alternate endpoint
+    /// without changing its URLs. TLS certificates are still verified against `host`, and the
+    /// port still comes from the URL.
+    #[must_use]
+    pub fn resolve(mut self, host: impl Into<String>, ip: IpAddr) -> Self {
+        self.resolve = self
+            .resolve
+            .iter()
+            .cloned()
+            .chain([(host.into(), ip)])
+            .collect();
+        self
+    }
+
+    /// Limit the rate at which response bodies are read, or `None` for no limit. The limit is
+    /// shared by every fetch made with the built transport and its clones, so concurrent fetches
+    /// together stay under it.
+    #[must_use]
+    pub fn max_bytes_per_second(mut self, value: Option<NonZeroU64>) -> Self {
+        self.max_bytes_per_second = value;
+        self
+    }
+
     /// Construct an [`HttpTransport`] transport from this builder's settings.
     pub fn build(self) -> HttpTransport {
-        HttpTransport { settings: self }
+        HttpTransport {
+            limiter: self
+                .max_bytes_per_second
+                .map(|rate| Arc::new(RateLimiter::new(rate))),
+            settings: self,
+            client: Arc::default(),
+        }
+    }
+
+    /// Builds a `reqwest` client from these settings.
+    fn client(&self) -> Result<Client, HttpError> {
+        let mut builder = ClientBuilder::new()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive);
+        if !self.http2 {
+            builder = builder.http1_only();
+        }
+        let mut overrides: BTreeMap<&str, Vec<SocketAddr>> = BTreeMap::new();
+        for (host, ip) in self.resolve.iter() {
+            // reqwest ignores the port of an override and uses the URL's
+            overrides
+                .entry(host.as_str())
+                .or_default()
+                .push(SocketAddr::new(*ip, 0));
+        }
+        for (host, addrs) in overrides {
+            builder = builder.resolve_to_addrs(host, &addrs);
+        }
+        #[cfg(feature = "gzip")]
+        {
+            builder = builder.gzip(self.compression);
+        }
+        #[cfg(feature = "zstd")]
+        {
+            builder = builder.zstd(self.compression);
+        }
+        builder.build().context(HttpClientSnafu)
     }
 }
 
@@ -130,14 +268,30 @@ impl HttpTransportBuilder {
 /// - 404: Not Found.
 /// - 410: Gone.
 ///
+/// Clones of an `HttpTransport` share one connection pool, which is created on first use.
+///
 /// # Proxy Support
 ///
 /// To use the `HttpTransport` with a proxy, specify the `HTTPS_PROXY` environment variable.
 /// The transport will also respect the `NO_PROXY` environment variable.
 ///
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct HttpTransport {
     settings: HttpTransportBuilder,
+    client: Arc<OnceLock<Client>>,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl HttpTransport {
+    /// Returns the shared client, building it if this is the first request.
+    fn client(&self) -> Result<Client, HttpError> {
+        if let Some(client) = self.client.get() {
+            return Ok(client.clone());
+        }
+        let client = self.settings.client()?;
+        // If another request raced us here, use the client it stored so that the pool is shared.
+        Ok(self.client.get_or_init(|| client).clone())
+    }
 }
 
 /// Implement the `tough` `Transport` trait for `HttpRetryTransport`
@@ -146,8 +300,91 @@ impl Transport for HttpTransport {
     /// Send a GET request to the URL. The returned `TransportStream` will retry as necessary per
     /// the `ClientSettings`.
     async fn fetch(&self, url: Url) -> Result<TransportStream, TransportError> {
-        let r = RetryState::new(self.settings.initial_backoff);
-        Ok(fetch_with_retries(r, &self.settings, &url).boxed())
+        self.fetch_range(url, 0).await
+    }
+
+    /// Send a GET request for the URL's bytes from `offset` on, with a `Range` header unless
+    /// `offset` is zero. The stream fails with [`TransportErrorKind::RangeNotSupported`] if the
+    /// server doesn't reply with that range.
+    async fn fetch_range(&self, url: Url, offset: u64) -> Result<TransportStream, TransportError> {
+        let client = self.client().map_err(|e| {
+            TransportError::new_with_cause(TransportErrorKind::Other, url.clone(), e)
+        })?;
+        let mut r = RetryState::new(self.settings.initial_backoff);
+        r.next_byte = offset;
+        let stream = fetch_with_retries(r, &self.settings, client, &url).boxed();
+        Ok(match &self.limiter {
+            Some(limiter) => Throttled {
+                stream,
+                limiter: Arc::clone(limiter),
+                delay: None,
+            }
+            .boxed(),
+            None => stream,
+        })
+    }
+}
+
+/// Spaces out chunks of response bodies so that, together, they arrive no faster than a limit.
+#[derive(Debug)]
+struct RateLimiter {
+    bytes_per_second: NonZeroU64,
+    /// When the bytes already read will have been paid for.
+    next_free: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_second: NonZeroU64) -> Self {
+        Self {
+            bytes_per_second,
+            next_free: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Accounts for a chunk of `len` bytes and returns when it may be passed on.
+    fn reserve(&self, len: usize) -> Instant {
+        let mut next_free = self
+            .next_free
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let start = (*next_free).max(Instant::now());
+        let nanos = len as u128 * 1_000_000_000 / u128::from(self.bytes_per_second.get());
+        *next_free = start + Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX));
+        start
+    }
+}
+
+/// A response body read under a [`RateLimiter`]. While a chunk is held back, the body isn't
+/// polled, which lets the connection's flow control slow the sender too.
+struct Throttled {
+    stream: TransportStream,
+    limiter: Arc<RateLimiter>,
+    delay: Option<(Pin<Box<tokio::time::Sleep>>, bytes::Bytes)>,
+}
+
+impl Stream for Throttled {
+    type Item = Result<bytes::Bytes, TransportError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some((sleep, _)) = &mut self.delay {
+            futures::ready!(sleep.as_mut().poll(cx));
+            let (_, chunk) = self.delay.take().expect("checked above");
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+        match futures::ready!(self.stream.as_mut().poll_next(cx)) {
+            Some(Ok(chunk)) => {
+                let ready_at = self.limiter.reserve(chunk.len());
+                if ready_at <= Instant::now() {
+                    return Poll::Ready(Some(Ok(chunk)));
+                }
+                self.delay = Some((Box::pin(tokio::time::sleep_until(ready_at.into())), chunk));
+                self.poll_next(cx)
+            }
+            other => Poll::Ready(other),
+        }
     }
 }
 
@@ -174,6 +411,7 @@ impl std::fmt::Debug for RequestState {
 struct RetryStream {
     retry_state: RetryState,
     settings: HttpTransportBuilder,
+    client: Client,
     url: Url,
     request: RequestState,
     done: bool,
@@ -229,7 +467,10 @@ impl RetryStream {
             }
             // New chunk received, keep track of position for potential recovery.
             Poll::Ready(Some(Ok(data))) => {
-                self.retry_state.next_byte += data.len();
+                self.retry_state.next_byte = self
+                    .retry_state
+                    .next_byte
+                    .saturating_add(u64::try_from(data.len()).unwrap_or(u64::MAX));
                 Poll::Ready(Some(Ok(data)))
             }
             // Error while streaming the response body. Try to recover.
@@ -265,10 +506,30 @@ impl RetryStream {
                 match http_result {
                     HttpResult::Ok(response) => {
                         trace!("{:?} - returning from successful fetch", self.retry_state);
-                        if let Some(ranges) = response.headers().get(ACCEPT_RANGES) {
-                            if let Ok(val) = ranges.to_str() {
-                                if val.contains("bytes") {
-                                    self.has_range_support = true;
+                        // A server that ignores the range sends the file from its beginning.
+                        if self.retry_state.next_byte > 0
+                            && response.status() != StatusCode::PARTIAL_CONTENT
+                        {
+                            self.done = true;
+                            return Some(Poll::Ready(Some(Err(TransportError::new_with_cause(
+                                TransportErrorKind::RangeNotSupported,
+                                self.url.clone(),
+                                format!(
+                                    "requested bytes from {}, got HTTP status {}",
+                                    self.retry_state.next_byte,
+                                    response.status()
+                                ),
+                            )))));
+                        }
+                        // Resuming by byte offset isn't possible in a compressed response.
+                        if response.status() == StatusCode::PARTIAL_CONTENT {
+                            self.has_range_support = true;
+                        } else if !self.compressed() {
+                            if let Some(ranges) = response.headers().get(ACCEPT_RANGES) {
+                                if let Ok(val) = ranges.to_str() {
+                                    if val.contains("bytes") {
+                                        self.has_range_support = true;
+                                    }
                                 }
                             }
                         }
@@ -314,7 +575,16 @@ impl RetryStream {
         }
         .into()
     }
-    /// Check all criteria for a retry and account for it.
+    /// Whether this fetch negotiates a compressed response.
+    fn compressed(&self) -> bool {
+        self.settings.compression
+            && std::path::Path::new(self.url.path())
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    }
+
+    /// Check all criteria for a retry and account for it. Without range support, only a fetch
+    /// that hasn't passed on any bytes yet can be retried, since a retry would start over.
     fn may_retry(&mut self) -> bool {
         let tries_left = self
             .settings
@@ -331,16 +601,23 @@ impl RetryStream {
         &mut self,
         cx: &mut std::task::Context<'_>,
     ) -> Result<Poll<Option<Result<bytes::Bytes, TransportError>>>, HttpError> {
-        let client = ClientBuilder::new()
-            .timeout(self.settings.timeout)
-            .connect_timeout(self.settings.connect_timeout)
-            .build()
-            .context(HttpClientSnafu)?;
+        let client = self.client.clone();
 
         // build the request
-        let request = build_request(&client, self.retry_state.next_byte, &self.url)?;
-
-        let backoff = self.retry_state.wait;
+        // A range is of the file as stored, so it must not be compressed in transit either.
+        let request = build_request(
+            &client,
+            self.retry_state.next_byte,
+            &self.url,
+            self.settings.compression && (!self.compressed() || self.retry_state.next_byte > 0),
+        )?;
+
+        // Only pause before a retry, not before the first try
+        let backoff = if self.retry_state.current_try == 0 {
+            Duration::ZERO
+        } else {
+            self.retry_state.wait
+        };
 
         let delayed_request = async move {
             tokio::time::sleep(backoff).await;
@@ -364,7 +641,7 @@ struct RetryState {
     /// The amount that the we should sleep before the next retry.
     wait: Duration,
     /// The next byte that we should read. e.g. the last read byte + 1.
-    next_byte: usize,
+    next_byte: u64,
 }
 
 impl RetryState {
@@ -397,12 +674,18 @@ impl RetryState {
 }
 
 /// Sends a `GET` request to the `url`. Retries the request as necessary per the `ClientSettings`.
-fn fetch_with_retries(r: RetryState, cs: &HttpTransportBuilder, url: &Url) -> RetryStream {
-    trace!("beginning fetch for '{}'", url);
+fn fetch_with_retries(
+    r: RetryState,
+    cs: &HttpTransportBuilder,
+    client: Client,
+    url: &Url,
+) -> RetryStream {
+    trace!("beginning fetch for '{url}'");
 
     RetryStream {
         retry_state: r,
-        settings: *cs,
+        settings: cs.clone(),
+        client,
         url: url.clone(),
         request: RequestState::None,
         done: false,
@@ -449,16 +732,16 @@ impl From<reqwest::Error> for ErrorClass {
     fn from(err: reqwest::Error) -> Self {
         if err.is_timeout() {
             // a connection timeout occurred
-            trace!("timeout error during fetch: {}", err);
+            trace!("timeout error during fetch: {err}");
             ErrorClass::Retryable(err)
         } else if err.is_request() {
             // an error occurred while sending the request
-            trace!("error sending request during fetch: {}", err);
+            trace!("error sending request during fetch: {err}");
             ErrorClass::Retryable(err)
         } else {
             // the error is not from an HTTP status code or a timeout, retries will not succeed.
             // these appear to be internal, reqwest errors and are expected to be unlikely.
-            trace!("internal reqwest error during fetch: {}", err);
+            trace!("internal reqwest error during fetch: {err}");
             ErrorClass::Fatal(err)
         }
     }
@@ -477,19 +760,19 @@ fn parse_response_code(response: reqwest::Response) -> HttpResult {
             None => {
                 // this shouldn't happen, we received this err from the err_for_status function,
                 // so the error should have a status. we cannot consider this a retryable error.
-                trace!("error is fatal (no status): {}", err);
+                trace!("error is fatal (no status): {err}");
                 HttpResult::Err(ErrorClass::Fatal(err))
             }
             Some(status) if status.is_server_error() => {
-                trace!("error is retryable: {}", err);
+                trace!("error is retryable: {err}");
                 HttpResult::Err(ErrorClass::Retryable(err))
             }
             Some(status) if matches!(status.as_u16(), 403 | 404 | 410) => {
-                trace!("error is file not found: {}", err);
+                trace!("error is file not found: {err}");
                 HttpResult::Err(ErrorClass::FileNotFound(err))
             }
             Some(_) => {
-                trace!("error is fatal (status): {}", err);
+                trace!("error is fatal (status): {err}");
                 HttpResult::Err(ErrorClass::Fatal(err))
             }
         },
@@ -497,12 +780,23 @@ fn parse_response_code(response: reqwest::Response) -> HttpResult {
 }
 
 /// Builds a GET request. If `next_byte` is greater than zero, adds a byte range header to the request.
-fn build_request(client: &Client, next_byte: usize, url: &Url) -> Result<Request, HttpError> {
+/// If `identity` is set, asks for an uncompressed response, overriding the `Accept-Encoding` header
+/// that the client would otherwise add.
+fn build_request(
+    client: &Client,
+    next_byte: u64,
+    url: &Url,
+    identity: bool,
+) -> Result<Request, HttpError> {
+    let mut request = client.request(Method::GET, url.as_str());
+    if identity {
+        request = request.header(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static("identity"),
+        );
+    }
     if next_byte == 0 {
-        let request = client
-            .request(Method::GET, url.as_str())
-            .build()
-            .context(RequestBuildSnafu)?;
+        let request = request.build().context(RequestBuildSnafu)?;
         Ok(request)
     } else {
         let header_value_string = format!("bytes={next_byte}-");
@@ -510,8 +804,7 @@ fn build_request(client: &Client, next_byte: usize, url: &Url) -> Result<Request
             HeaderValue::from_str(header_value_string.as_str()).context(InvalidHeaderSnafu {
                 header_value: &header_value_string,
             })?;
-        let request = client
-            .request(Method::GET, url.as_str())
+        let request = request
             .header(header::RANGE, header_value)
             .build()
             .context(RequestBuildSnafu)?;
@@ -1,30 +1,61 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::{error, transport::TransportStream, TransportError};
-use aws_lc_rs::digest::{Context, SHA256};
+use crate::{error, transport::TransportStream, HashAlgorithm, TransportError};
+use aws_lc_rs::digest::Context;
 use futures::StreamExt;
 use futures_core::Stream;
 use std::{convert::TryInto, path::Path, task::Poll};
 use tokio::fs;
 use url::Url;
 
-pub(crate) struct DigestAdapter {
+/// A stream adapter that checks the digest of everything read through it, as tough does for the
+/// metadata and targets it fetches. Custom transports and caching layers can wrap other streams
+/// with it to enforce the hashes listed in TUF metadata.
+///
+/// The wrapped stream yields the underlying chunks unchanged, then, if the digest doesn't match,
+/// a [`TransportError`] in place of the end of the stream. The `url` is only used in that error.
+/// The stream ends after the first error, whether it is a mismatch or comes from the wrapped
+/// stream. As with [`Repository::read_target`](crate::Repository::read_target), data from the stream
+/// must not be used if it returns an error.
+pub struct DigestAdapter {
     url: Url,
     stream: TransportStream,
     hash: Vec<u8>,
     digest: Context,
+    failed: bool,
+}
+
+impl std::fmt::Debug for DigestAdapter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DigestAdapter")
+            .field("url", &self.url)
+            .field("hash", &hex::encode(&self.hash))
+            .finish_non_exhaustive()
+    }
 }
 
 impl DigestAdapter {
-    pub(crate) fn sha256(stream: TransportStream, hash: &[u8], url: Url) -> TransportStream {
+    /// Wraps `stream` so that it fails at the end unless its SHA-256 digest is `hash`.
+    pub fn sha256(stream: TransportStream, hash: &[u8], url: Url) -> Self {
+        Self::new(stream, HashAlgorithm::Sha256, hash, url)
+    }
+
+    /// Wraps `stream` so that it fails at the end unless its `algorithm` digest is `hash`.
+    pub fn new(stream: TransportStream, algorithm: HashAlgorithm, hash: &[u8], url: Url) -> Self {
+        Self::resume(stream, Context::new(algorithm.algorithm()), hash, url)
+    }
+
+    /// Wraps `stream`, the rest of data that `digest` has already been updated with, so that it
+    /// fails at the end unless the digest of all of the data is `hash`.
+    pub(crate) fn resume(stream: TransportStream, digest: Context, hash: &[u8], url: Url) -> Self {
         Self {
             url,
             stream,
             hash: hash.to_owned(),
-            digest: Context::new(&SHA256),
+            digest,
+            failed: false,
         }
-        .boxed()
     }
 }
 
@@ -35,11 +66,15 @@ impl Stream for DigestAdapter {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
+        if self.failed {
+            return Poll::Ready(None);
+        }
         let poll = self.stream.as_mut().poll_next(cx);
         match &poll {
             Poll::Ready(Some(Ok(bytes))) => {
                 self.digest.update(bytes);
             }
+            Poll::Ready(Some(Err(_))) => self.failed = true,
             Poll::Ready(None) => {
                 let result = &self.digest.clone().finish();
                 if result.as_ref() != self.hash.as_slice() {
@@ -49,6 +84,7 @@ impl Stream for DigestAdapter {
                         expected: hex::encode(&self.hash),
                     }
                     .build();
+                    self.failed = true;
                     return Poll::Ready(Some(Err(TransportError::new_with_cause(
                         crate::TransportErrorKind::Other,
                         self.url.clone(),
@@ -56,72 +92,133 @@ impl Stream for DigestAdapter {
                     ))));
                 }
             }
-            Poll::Ready(Some(Err(_))) | Poll::Pending => (),
-        };
+            Poll::Pending => (),
+        }
 
         poll
     }
 }
 
-/// Create a new stream from `stream`. The new stream returns an error for the item that exceeds the
-/// total byte count of `max_size`.
-/// * `stream` - The original stream.
-/// * `max_size` - Size limit in bytes.
-/// * `specifier` - Error message to use.
-pub(crate) fn max_size_adapter(
-    stream: TransportStream,
+/// A stream adapter that limits how much can be read through it, as tough does for everything it
+/// fetches, to protect against endless data attacks.
+///
+/// Like [`DigestAdapter`], it's available for custom transports and caching layers to enforce the
+/// lengths listed in TUF metadata or their own limits.
+///
+/// The stream is abandoned as soon as the limit is exceeded: the chunk that exceeds it is replaced
+/// by an error, the wrapped stream is dropped without reading any more of it, and the stream ends.
+pub struct MaxSizeAdapter {
     url: Url,
+    stream: TransportStream,
     max_size: u64,
     specifier: &'static str,
-) -> TransportStream {
-    let mut size: u64 = 0;
-    let stream = stream.map(move |chunk| {
-        if let Ok(bytes) = &chunk {
-            size = size.saturating_add(bytes.len().try_into().unwrap_or(u64::MAX));
+    size: u64,
+    exceeded: bool,
+    on_exceeded: Option<OnExceeded>,
+}
+
+/// Called by a [`MaxSizeAdapter`] with the number of bytes received when its limit is exceeded.
+pub(crate) type OnExceeded = Box<dyn FnOnce(u64) + Send>;
+
+impl std::fmt::Debug for MaxSizeAdapter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaxSizeAdapter")
+            .field("url", &self.url)
+            .field("max_size", &self.max_size)
+            .field("specifier", &self.specifier)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MaxSizeAdapter {
+    /// Create a new stream from `stream`. The new stream returns an error for the item that
+    /// exceeds the total byte count of `max_size`.
+    /// * `stream` - The original stream.
+    /// * `url` - The URL to report in the error.
+    /// * `max_size` - Size limit in bytes.
+    /// * `specifier` - Where the limit came from, such as `"snapshot.json"`, for the error
+    ///   message.
+    pub fn new(stream: TransportStream, url: Url, max_size: u64, specifier: &'static str) -> Self {
+        Self {
+            url,
+            stream,
+            max_size,
+            specifier,
+            size: 0,
+            exceeded: false,
+            on_exceeded: None,
         }
-        if size > max_size {
-            let size_err = error::MaxSizeExceededSnafu {
-                max_size,
-                specifier,
+    }
+
+    /// Calls `on_exceeded` with the number of bytes received if the limit is exceeded.
+    pub(crate) fn on_exceeded(mut self, on_exceeded: Option<OnExceeded>) -> Self {
+        self.on_exceeded = on_exceeded;
+        self
+    }
+}
+
+impl Stream for MaxSizeAdapter {
+    type Item = <TransportStream as Stream>::Item;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if self.exceeded {
+            return Poll::Ready(None);
+        }
+        let poll = self.stream.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(Ok(bytes))) = &poll {
+            self.size = self
+                .size
+                .saturating_add(bytes.len().try_into().unwrap_or(u64::MAX));
+            if self.size > self.max_size {
+                // Stop reading, so that a transport can close its connection rather than receive
+                // the rest of whatever is being served.
+                self.exceeded = true;
+                self.stream = futures::stream::empty().boxed();
+                if let Some(on_exceeded) = self.on_exceeded.take() {
+                    on_exceeded(self.size);
+                }
+                let size_err = error::MaxSizeExceededSnafu {
+                    max_size: self.max_size,
+                    specifier: self.specifier,
+                }
+                .build();
+                return Poll::Ready(Some(Err(TransportError::new_with_cause(
+                    crate::TransportErrorKind::Other,
+                    self.url.clone(),
+                    size_err,
+                ))));
             }
-            .build();
-            return Err(TransportError::new_with_cause(
-                crate::TransportErrorKind::Other,
-                url.clone(),
-                size_err,
-            ));
         }
-        chunk
-    });
-
-    stream.boxed()
+        poll
+    }
 }
 
 /// Async analogue of `std::path::Path::is_file`
 pub async fn is_file(path: impl AsRef<Path>) -> bool {
-    fs::metadata(path)
-        .await
-        .map(|m| m.is_file())
-        .unwrap_or(false)
+    fs::metadata(path).await.is_ok_and(|m| m.is_file())
 }
 
 /// Async analogue of `std::path::Path::is_dir`
 pub async fn is_dir(path: impl AsRef<Path>) -> bool {
-    fs::metadata(path)
-        .await
-        .map(|m| m.is_dir())
-        .unwrap_or(false)
+    fs::metadata(path).await.is_ok_and(|m| m.is_dir())
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        io::{max_size_adapter, DigestAdapter},
+        io::{DigestAdapter, MaxSizeAdapter},
         transport::IntoVec,
+        HashAlgorithm,
     };
     use bytes::Bytes;
     use futures::{stream, StreamExt};
     use hex_literal::hex;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
     use url::Url;
 
     #[tokio::test]
@@ -129,15 +226,36 @@ mod tests {
         let url = Url::parse("file:///").unwrap();
 
         let stream = stream::iter("hello".as_bytes().chunks(2).map(Bytes::from).map(Ok)).boxed();
-        let stream = max_size_adapter(stream, url.clone(), 5, "test");
+        let stream = MaxSizeAdapter::new(stream, url.clone(), 5, "test").boxed();
         let buf = stream.into_vec().await.expect("consuming entire stream");
         assert_eq!(buf, b"hello");
 
         let stream = stream::iter("hello".as_bytes().chunks(2).map(Bytes::from).map(Ok)).boxed();
-        let stream = max_size_adapter(stream, url, 4, "test");
+        let stream = MaxSizeAdapter::new(stream, url, 4, "test").boxed();
         assert!(stream.into_vec().await.is_err());
     }
 
+    #[tokio::test]
+    async fn test_max_size_adapter_abandons_stream() {
+        let url = Url::parse("file:///").unwrap();
+        let received = Arc::new(AtomicU64::new(0));
+        let on_exceeded = {
+            let received = Arc::clone(&received);
+            Box::new(move |size| received.store(size, Ordering::SeqCst))
+        };
+
+        // An endless stream is never read past the chunk that exceeds the limit.
+        let stream = stream::repeat(Bytes::from_static(b"abc")).map(Ok).boxed();
+        let mut stream = MaxSizeAdapter::new(stream, url, 7, "test")
+            .on_exceeded(Some(on_exceeded))
+            .boxed();
+        assert_eq!(stream.next().await.unwrap().unwrap(), "abc");
+        assert_eq!(stream.next().await.unwrap().unwrap(), "abc");
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+        assert_eq!(received.load(Ordering::SeqCst), 9);
+    }
+
     #[tokio::test]
     async fn test_digest_adapter() {
         let stream = stream::iter("hello".as_bytes().chunks(2).map(Bytes::from).map(Ok)).boxed();
@@ -145,7 +263,8 @@ mod tests {
             stream,
             &hex!("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"),
             Url::parse("file:///").unwrap(),
-        );
+        )
+        .boxed();
         let buf = stream.into_vec().await.expect("consuming entire stream");
         assert_eq!(buf, b"hello");
 
@@ -154,7 +273,22 @@ mod tests {
             stream,
             &hex!("0ebdc3317b75839f643387d783535adc360ca01f33c75f7c1e7373adcd675c0b"),
             Url::parse("file:///").unwrap(),
-        );
+        )
+        .boxed();
         assert!(stream.into_vec().await.is_err());
+
+        let stream = stream::iter("hello".as_bytes().chunks(2).map(Bytes::from).map(Ok)).boxed();
+        let stream = DigestAdapter::new(
+            stream,
+            HashAlgorithm::Sha512,
+            &hex!(
+                "9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca7"
+                "2323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043"
+            ),
+            Url::parse("file:///").unwrap(),
+        )
+        .boxed();
+        let buf = stream.into_vec().await.expect("consuming entire stream");
+        assert_eq!(buf, b"hello");
     }
 }
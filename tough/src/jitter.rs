@@ -0,0 +1,55 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Random offsets for expirations and refresh times, so that clients which loaded the same
+//! metadata don't all act at the same instant.
+
+use crate::error::{self, Result};
+use aws_lc_rs::rand::{SecureRandom, SystemRandom};
+use chrono::TimeDelta;
+use snafu::ResultExt;
+use std::convert::TryFrom;
+
+/// Returns a random duration from zero up to and including `max`, in whole seconds. A `max` of
+/// less than a second gives zero.
+pub(crate) fn random_duration(max: TimeDelta) -> Result<TimeDelta> {
+    let Ok(max_secs) = u64::try_from(max.num_seconds()) else {
+        return Ok(TimeDelta::zero());
+    };
+    if max_secs == 0 {
+        return Ok(TimeDelta::zero());
+    }
+    let mut bytes = [0u8; 8];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .context(error::RandomSnafu)?;
+    // The bias from the modulo is negligible for spans that fit in a `TimeDelta`
+    let secs = u64::from_le_bytes(bytes) % (max_secs + 1);
+    Ok(TimeDelta::seconds(i64::try_from(secs).unwrap_or(i64::MAX)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_duration_is_bounded() {
+        let max = TimeDelta::seconds(10);
+        for _ in 0..100 {
+            let duration = random_duration(max).unwrap();
+            assert!(duration >= TimeDelta::zero() && duration <= max);
+        }
+    }
+
+    #[test]
+    fn random_duration_of_nothing_is_zero() {
+        assert_eq!(
+            random_duration(TimeDelta::zero()).unwrap(),
+            TimeDelta::zero()
+        );
+        assert_eq!(
+            random_duration(TimeDelta::seconds(-5)).unwrap(),
+            TimeDelta::zero()
+        );
+    }
+}
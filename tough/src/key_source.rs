@@ -4,6 +4,7 @@
 //! Provides an abstraction over the source of a signing key. This allows signing keys to be
 //! obtained, for example, from local files or from cloud provider key stores.
 use crate::error;
+use crate::schema::key::Key;
 use crate::sign::{parse_keypair, Sign};
 use async_trait::async_trait;
 use snafu::ResultExt;
@@ -11,6 +12,11 @@ use std::fmt::Debug;
 use std::path::PathBuf;
 use std::result::Result;
 
+#[cfg(unix)]
+mod ssh_agent;
+#[cfg(unix)]
+pub use ssh_agent::SshAgentKeySource;
+
 /// This trait should be implemented for each source of signing keys. Examples
 /// of sources include: files, AWS SSM, etc.
 #[async_trait]
@@ -20,6 +26,15 @@ pub trait KeySource: Debug + Send + Sync {
         &self,
     ) -> Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
+    /// Returns the public key of the signing key, in the form it takes in TUF metadata.
+    ///
+    /// The default implementation constructs a signer with `as_sign` and asks it for its key.
+    /// Sources whose signer is expensive to construct, or that can read the public key without
+    /// access to the private key, should override this.
+    async fn public_key(&self) -> Result<Key, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(self.as_sign().await?.tuf_key())
+    }
+
     /// Writes a key back to the `KeySource`
     async fn write(
         &self,
@@ -0,0 +1,423 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides a `KeySource` for Ed25519 keys held by an SSH agent, so that metadata can be signed
+//! without the private key ever being written to the signing host.
+use crate::error::{self, Result};
+use crate::key_source::KeySource;
+use crate::schema::key::{Ed25519Key, Ed25519Scheme, Key};
+use crate::sign::Sign;
+use crate::ssh_wire::{parse_ed25519_key, Reader, SSH_ED25519};
+use async_trait::async_trait;
+use aws_lc_rs::rand::SecureRandom;
+use snafu::{ensure, OptionExt, ResultExt};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+/// Agent replies are small; anything larger than this is not a well-behaved agent.
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+/// Signs with an Ed25519 key held by an SSH agent, such as `ssh-agent` or a hardware token's agent.
+///
+/// The agent is reached over the Unix socket at `socket`, or at `SSH_AUTH_SOCK` if `socket` is
+/// `None`. Keys of other types held by the agent are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct SshAgentKeySource {
+    /// The path to the agent's socket. Defaults to the value of `SSH_AUTH_SOCK`.
+    pub socket: Option<PathBuf>,
+    /// Selects one of the agent's Ed25519 keys by its comment or by its hex-encoded TUF key ID.
+    /// If `None`, the agent must hold exactly one Ed25519 key.
+    pub key: Option<String>,
+}
+
+impl SshAgentKeySource {
+    fn socket(&self) -> Result<PathBuf> {
+        match &self.socket {
+            Some(socket) => Ok(socket.clone()),
+            None => std::env::var_os("SSH_AUTH_SOCK")
+                .map(PathBuf::from)
+                .context(error::SshAgentSocketMissingSnafu),
+        }
+    }
+
+    /// Asks the agent for its Ed25519 keys and picks the one this source refers to.
+    async fn identity(&self) -> Result<SshAgentSigner> {
+        let socket = self.socket()?;
+        let mut agent = Agent::connect(&socket).await?;
+        let identities = agent.identities().await?;
+
+        let matching: Vec<_> = identities
+            .into_iter()
+            .filter(|identity| match &self.key {
+                Some(key) => identity.comment == *key || identity.key_id_hex() == *key,
+                None => true,
+            })
+            .collect();
+        let description = self.key.as_deref().unwrap_or("any Ed25519 key");
+        ensure!(
+            !matching.is_empty(),
+            error::SshAgentKeyNotFoundSnafu { key: description }
+        );
+        ensure!(
+            matching.len() == 1,
+            error::SshAgentKeyAmbiguousSnafu {
+                key: description,
+                count: matching.len(),
+            }
+        );
+        let identity = matching
+            .into_iter()
+            .next()
+            .expect("checked one match above");
+        Ok(SshAgentSigner { socket, identity })
+    }
+}
+
+#[async_trait]
+impl KeySource for SshAgentKeySource {
+    async fn as_sign(
+        &self,
+    ) -> std::result::Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        Ok(Box::new(self.identity().await?))
+    }
+
+    async fn public_key(
+        &self,
+    ) -> std::result::Result<Key, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Ok(self.identity().await?.tuf_key())
+    }
+
+    async fn write(
+        &self,
+        _value: &str,
+        _key_id_hex: &str,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        error::SshAgentWriteSnafu.fail()?
+    }
+}
+
+/// An Ed25519 key held by an agent, along with the comment the agent gave it.
+#[derive(Debug, Clone)]
+struct Identity {
+    /// The key in SSH wire format, which identifies it to the agent.
+    blob: Vec<u8>,
+    public: Vec<u8>,
+    comment: String,
+}
+
+impl Identity {
+    fn tuf_key(&self) -> Key {
+        Key::Ed25519 {
+            keyval: Ed25519Key {
+                public: self.public.clone().into(),
+                _extra: HashMap::new(),
+            },
+            scheme: Ed25519Scheme::Ed25519,
+            _extra: HashMap::new(),
+        }
+    }
+
+    fn key_id_hex(&self) -> String {
+        self.tuf_key().key_id().map(hex::encode).unwrap_or_default()
+    }
+}
+
+/// Signs by sending each message to the agent. A new connection is made for each signature, so
+/// the signer stays usable if the agent is restarted with the same key.
+#[derive(Debug)]
+struct SshAgentSigner {
+    socket: PathBuf,
+    identity: Identity,
+}
+
+#[async_trait]
+impl Sign for SshAgentSigner {
+    fn tuf_key(&self) -> Key {
+        self.identity.tuf_key()
+    }
+
+    async fn sign(
+        &self,
+        msg: &[u8],
+        _rng: &(dyn SecureRandom + Sync),
+    ) -> std::result::Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let mut agent = Agent::connect(&self.socket).await?;
+        Ok(agent.sign(&self.identity.blob, msg).await?)
+    }
+}
+
+/// A connection speaking the SSH agent protocol (draft-miller-ssh-agent).
+struct Agent {
+    socket: PathBuf,
+    stream: UnixStream,
+}
+
+impl Agent {
+    async fn connect(socket: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket)
+            .await
+            .context(error::SshAgentIoSnafu { path: socket })?;
+        Ok(Self {
+            socket: socket.to_owned(),
+            stream,
+        })
+    }
+
+    /// Sends a request and returns the type and contents of the agent's reply.
+    async fn request(&mut self, kind: u8, contents: &[u8]) -> Result<(u8, Vec<u8>)> {
+        let mut message = Vec::with_capacity(contents.len() + 5);
+        put_u32(&mut message, contents.len() + 1);
+        message.push(kind);
+        message.extend_from_slice(contents);
+        self.stream
+            .write_all(&message)
+            .await
+            .context(error::SshAgentIoSnafu { path: &self.socket })?;
+
+        let len = self
+            .stream
+            .read_u32()
+            .await
+            .context(error::SshAgentIoSnafu { path: &self.socket })? as usize;
+        ensure!(
+            (1..=MAX_MESSAGE_LEN).contains(&len),
+            error::SshAgentProtocolSnafu {
+                message: format!("reply length {len} is out of range"),
+            }
+        );
+        let mut reply = vec![0; len];
+        self.stream
+            .read_exact(&mut reply)
+            .await
+            .context(error::SshAgentIoSnafu { path: &self.socket })?;
+        let contents = reply.split_off(1);
+        Ok((reply[0], contents))
+    }
+
+    /// Lists the agent's Ed25519 keys.
+    async fn identities(&mut self) -> Result<Vec<Identity>> {
+        let (kind, reply) = self.request(SSH_AGENTC_REQUEST_IDENTITIES, &[]).await?;
+        expect_reply(kind, SSH_AGENT_IDENTITIES_ANSWER)?;
+        let mut reader = Reader::new(&reply);
+        let count = reader.u32().context(truncated())?;
+        let mut identities = Vec::new();
+        for _ in 0..count {
+            let blob = reader.string().context(truncated())?;
+            let comment =
+                String::from_utf8_lossy(reader.string().context(truncated())?).into_owned();
+            if let Some(public) = parse_ed25519_key(blob) {
+                identities.push(Identity {
+                    blob: blob.to_vec(),
+                    public: public.to_vec(),
+                    comment,
+                });
+            }
+        }
+        Ok(identities)
+    }
+
+    /// Asks the agent to sign `data` with the key `blob`, and returns the raw Ed25519 signature.
+    async fn sign(&mut self, blob: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let mut request = Vec::with_capacity(blob.len() + data.len() + 12);
+        put_string(&mut request, blob);
+        put_string(&mut request, data);
+        put_u32(&mut request, 0);
+        let (kind, reply) = self.request(SSH_AGENTC_SIGN_REQUEST, &request).await?;
+        expect_reply(kind, SSH_AGENT_SIGN_RESPONSE)?;
+
+        let mut signature = Reader::new(Reader::new(&reply).string().context(truncated())?);
+        let format = signature.string().context(truncated())?;
+        ensure!(
+            format == SSH_ED25519,
+            error::SshAgentProtocolSnafu {
+                message: format!(
+                    "expected an ssh-ed25519 signature, got '{}'",
+                    String::from_utf8_lossy(format)
+                ),
+            }
+        );
+        let signature = signature.string().context(truncated())?;
+        ensure!(
+            signature.len() == 64,
+            error::SshAgentProtocolSnafu {
+                message: format!(
+                    "expected a 64-byte Ed25519 signature, got {} bytes",
+                    signature.len()
+                ),
+            }
+        );
+        Ok(signature.to_vec())
+    }
+}
+
+fn expect_reply(kind: u8, expected: u8) -> Result<()> {
+    ensure!(
+        kind != SSH_AGENT_FAILURE,
+        error::SshAgentProtocolSnafu {
+            message: "the agent refused the request",
+        }
+    );
+    ensure!(
+        kind == expected,
+        error::SshAgentProtocolSnafu {
+            message: format!("expected reply type {expected}, got {kind}"),
+        }
+    );
+    Ok(())
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: usize) {
+    // Every length we send is bounded well below `u32::MAX` by the size of TUF metadata.
+    buf.extend_from_slice(&u32::try_from(value).unwrap_or(u32::MAX).to_be_bytes());
+}
+
+fn put_string(buf: &mut Vec<u8>, value: &[u8]) {
+    put_u32(buf, value.len());
+    buf.extend_from_slice(value);
+}
+
+/// Fails a reply that ends before a field it should contain.
+fn truncated() -> error::SshAgentProtocolSnafu<&'static str> {
+    error::SshAgentProtocolSnafu {
+        message: "reply is truncated",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_lc_rs::rand::SystemRandom;
+    use aws_lc_rs::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+    use tokio::net::UnixListener;
+
+    fn key_blob(public: &[u8]) -> Vec<u8> {
+        let mut blob = Vec::new();
+        put_string(&mut blob, SSH_ED25519);
+        put_string(&mut blob, public);
+        blob
+    }
+
+    async fn read_message(stream: &mut UnixStream) -> Option<(u8, Vec<u8>)> {
+        let len = stream.read_u32().await.ok()? as usize;
+        let mut message = vec![0; len];
+        stream.read_exact(&mut message).await.ok()?;
+        let contents = message.split_off(1);
+        Some((message[0], contents))
+    }
+
+    async fn write_message(stream: &mut UnixStream, kind: u8, contents: &[u8]) {
+        let mut message = Vec::new();
+        put_u32(&mut message, contents.len() + 1);
+        message.push(kind);
+        message.extend_from_slice(contents);
+        stream.write_all(&message).await.unwrap();
+    }
+
+    /// Runs a minimal agent holding `keys`, each with its comment, until the test ends.
+    fn fake_agent(socket: &Path, keys: Vec<(Ed25519KeyPair, &'static str)>) {
+        let listener = UnixListener::bind(socket).unwrap();
+        let keys = std::sync::Arc::new(keys);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let keys = keys.clone();
+                tokio::spawn(async move {
+                    while let Some((kind, contents)) = read_message(&mut stream).await {
+                        let mut reply = Vec::new();
+                        match kind {
+                            SSH_AGENTC_REQUEST_IDENTITIES => {
+                                put_u32(&mut reply, keys.len());
+                                for (key, comment) in keys.iter() {
+                                    put_string(&mut reply, &key_blob(key.public_key().as_ref()));
+                                    put_string(&mut reply, comment.as_bytes());
+                                }
+                                write_message(&mut stream, SSH_AGENT_IDENTITIES_ANSWER, &reply)
+                                    .await;
+                            }
+                            SSH_AGENTC_SIGN_REQUEST => {
+                                let mut reader = Reader::new(&contents);
+                                let blob = reader.string().unwrap();
+                                let data = reader.string().unwrap();
+                                let key = keys
+                                    .iter()
+                                    .find(|(key, _)| key_blob(key.public_key().as_ref()) == blob);
+                                if let Some((key, _)) = key {
+                                    let mut signature = Vec::new();
+                                    put_string(&mut signature, SSH_ED25519);
+                                    put_string(&mut signature, key.sign(data).as_ref());
+                                    put_string(&mut reply, &signature);
+                                    write_message(&mut stream, SSH_AGENT_SIGN_RESPONSE, &reply)
+                                        .await;
+                                } else {
+                                    write_message(&mut stream, SSH_AGENT_FAILURE, &[]).await;
+                                }
+                            }
+                            _ => write_message(&mut stream, SSH_AGENT_FAILURE, &[]).await,
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    fn generate() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn signs_with_agent_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("agent.sock");
+        fake_agent(
+            &socket,
+            vec![(generate(), "release"), (generate(), "other")],
+        );
+
+        let source = SshAgentKeySource {
+            socket: Some(socket.clone()),
+            key: Some("release".to_owned()),
+        };
+        let signer = source.as_sign().await.unwrap();
+        let key = signer.tuf_key();
+        let signature = signer.sign(b"payload", &SystemRandom::new()).await.unwrap();
+        let public = UnparsedPublicKey::new(&ED25519, key.public_key());
+        assert!(public.verify(b"payload", &signature).is_ok());
+        assert!(public.verify(b"other payload", &signature).is_err());
+
+        // The key can also be picked by its TUF key ID.
+        let by_id = SshAgentKeySource {
+            socket: Some(socket),
+            key: Some(hex::encode(key.key_id().unwrap())),
+        };
+        assert_eq!(by_id.public_key().await.unwrap(), key);
+    }
+
+    #[tokio::test]
+    async fn key_must_be_unambiguous() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("agent.sock");
+        fake_agent(&socket, vec![(generate(), "a"), (generate(), "b")]);
+
+        let any = SshAgentKeySource {
+            socket: Some(socket.clone()),
+            key: None,
+        };
+        assert!(any.as_sign().await.is_err());
+        let missing = SshAgentKeySource {
+            socket: Some(socket),
+            key: Some("c".to_owned()),
+        };
+        assert!(missing.as_sign().await.is_err());
+    }
+}
@@ -6,11 +6,32 @@
 //! This client adheres to [TUF version 1.0.0][spec], with the following exceptions:
 //!
 //! * Delegated roles (and TAP 3) are not yet supported.
-//! * TAP 4 (multiple repository consensus) is not yet supported.
 //!
 //! [TUF repositories]: https://theupdateframework.github.io/
 //! [spec]: https://github.com/theupdateframework/specification/blob/9f148556ca15da2ec5c022c8b3e6f99a028e5fe5/tuf-spec.md
 //!
+//! # Multiple repositories
+//!
+//! [TAP 4] multiple repository consensus is supported by [`MultiRepositoryLoader`], which loads
+//! the repositories named in a `map.json` ([`MapFile`]) into a [`MultiRepository`]. It only
+//! returns a target when the threshold of repositories mapped to its path agree on its length and
+//! hashes.
+//!
+//! [TAP 4]: https://github.com/theupdateframework/taps/blob/master/tap4.md
+//!
+//! # FIPS
+//!
+//! With the `fips` feature, aws-lc-rs uses its FIPS validated module. [`crypto_mode`] reports the
+//! active mode. Every key scheme is still allowed unless [`set_fips_key_policy`] is called with
+//! [`FipsKeyPolicy::ApprovedOnly`], after which signatures from Ed25519 keys don't count towards a
+//! role's threshold and Ed25519 keys can't be used to sign.
+//!
+//! # Crypto backends
+//!
+//! Signatures are verified and made by a [`CryptoBackend`], which [`crypto_backend`] returns. By
+//! default this is [`AwsLcBackend`]. With the `openssl` feature it is `OpensslBackend`, which uses
+//! the system's OpenSSL. Digests are computed with aws-lc-rs either way.
+//!
 //! # Testing
 //!
 //! Unit tests are run in the usual manner: `cargo test`.
@@ -30,32 +51,85 @@
     clippy::result_large_err
 )]
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod bundle;
 mod cache;
+mod changes;
+mod context;
+mod crypto;
 mod datastore;
+mod deadline;
+mod delegation_walk;
+pub mod der;
 pub mod editor;
+mod embedded_root;
 pub mod error;
 mod fetch;
+mod filename_encoding;
 #[cfg(feature = "http")]
 pub mod http;
 mod io;
+mod jitter;
 pub mod key_source;
+mod metadata_sizes;
+mod multi_repository;
+mod observer;
+mod offline;
+mod policy;
 pub mod schema;
 pub mod sign;
+mod ssh_wire;
+mod target_cache;
 mod target_name;
+mod target_resolution;
+#[cfg(feature = "test-helpers")]
+pub mod test_helpers;
 mod transport;
+pub mod tsa;
+pub mod uptane;
 mod urlpath;
 
-use crate::datastore::Datastore;
+use crate::bundle::BundleTransport;
+pub use crate::bundle::MetadataBundle;
+pub use crate::cache::{CacheDelta, CacheLayout};
+use crate::changes::LoadState;
+pub use crate::changes::{RepositoryChanges, RoleChange};
+pub use crate::context::ToughContext;
+#[cfg(feature = "openssl")]
+pub use crate::crypto::OpensslBackend;
+pub use crate::crypto::{
+    crypto_backend, crypto_mode, fips_key_policy, set_fips_key_policy, AwsLcBackend, CryptoBackend,
+    CryptoMode, FipsKeyPolicy,
+};
+pub use crate::datastore::{
+    Datastore, DatastoreBackend, DatastoreEntry, DatastoreLock, FilesystemDatastore,
+    MemoryDatastore, ResetAcknowledgement,
+};
+use crate::deadline::Deadlines;
+use crate::delegation_walk::DelegationWalk;
+pub use crate::embedded_root::{EmbeddedRootCheck, EmbeddedRootReport, Staleness};
 use crate::error::Result;
-use crate::fetch::{fetch_max_size, fetch_sha256};
+use crate::fetch::{fetch_digests, fetch_max_size};
+pub use crate::filename_encoding::{FilenameEncoding, PercentEncoding, UnicodePreservingEncoding};
 /// An HTTP transport that includes retries.
 #[cfg(feature = "http")]
 pub use crate::http::{HttpTransport, HttpTransportBuilder};
 use crate::io::is_dir;
+pub use crate::io::{DigestAdapter, MaxSizeAdapter};
+pub use crate::metadata_sizes::MetadataSizes;
+pub use crate::multi_repository::{MapFile, Mapping, MultiRepository, MultiRepositoryLoader};
+pub use crate::observer::RepositoryObserver;
+use crate::offline::OfflineTransport;
+pub use crate::policy::{
+    HashAlgorithm, HashAlgorithms, RootUpdateFn, RootUpdatePolicy, VerificationPolicy,
+};
 use crate::schema::{
-    DelegatedRole, Delegations, Role, RoleType, Root, Signed, Snapshot, Timestamp,
+    DelegatedRole, Delegations, Metafile, Role, RoleType, Root, Signed, Snapshot, Timestamp,
 };
+pub use crate::target_cache::TargetCache;
 pub use crate::target_name::TargetName;
+pub use crate::target_resolution::{ResolutionOutcome, ResolutionStep, TargetResolution};
 pub use crate::transport::IntoVec;
 pub use crate::transport::{
     DefaultTransport, FilesystemTransport, Transport, TransportError, TransportErrorKind,
@@ -65,16 +139,22 @@ use async_recursion::async_recursion;
 pub use async_trait::async_trait;
 pub use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use futures::StreamExt;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 use futures_core::Stream;
 use log::warn;
 use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use snafu::{ensure, OptionExt, ResultExt};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::num::{NonZeroU64, NonZeroUsize};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tempfile::NamedTempFile;
 use tokio::fs::{canonicalize, create_dir_all};
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
 use url::Url;
 
 /// Represents whether a Repository should fail to load when metadata is expired (`Safe`) or whether
@@ -112,6 +192,65 @@ impl From<ExpirationEnforcement> for bool {
     }
 }
 
+/// What a Repository should do when snapshot.json lists a delegated targets role whose metadata
+/// file can't be found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingRolePolicy {
+    /// Fail to load the repository.
+    #[default]
+    Fail,
+
+    /// Log a warning and load the repository without the role. Targets the role (or any role it
+    /// delegates to) would have listed can't be found, but every other target can still be read.
+    /// [`Repository::skipped_roles`] lists the roles that were skipped.
+    SkipWithWarning,
+}
+
+/// What a Repository should do with a target listed without a hash in any algorithm in
+/// [`HashAlgorithm`], such as one listed with only its length. tough can't verify such a target,
+/// so it can't safely read it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnhashedTargetPolicy {
+    /// Fail to load the repository.
+    #[default]
+    Fail,
+
+    /// Log a warning and load the repository without the target, as if it weren't listed.
+    SkipWithWarning,
+}
+
+/// What a Repository should do when timestamp.json describes files other than snapshot.json,
+/// which the TUF specification forbids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampMetaPolicy {
+    /// Fail to load the repository.
+    #[default]
+    Fail,
+
+    /// Log a warning and ignore the other files. Only the listing of snapshot.json is ever used.
+    Warn,
+}
+
+/// Whether a Repository should update its trusted root by fetching newer versions of root.json
+/// from the repository, as step 1 of the TUF client workflow describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RootUpdate {
+    /// Fetch `N+1.root.json` until the most recent root is reached, trusting each one that is
+    /// signed by a threshold of keys from the root before it.
+    #[default]
+    Enabled,
+
+    /// Never fetch newer versions of root.json; the trusted root, and any roots passed to
+    /// [`RepositoryLoader::trusted_root_chain`], are the only roots used. The trusted root is
+    /// still verified with its own keys, and its expiration is still enforced.
+    ///
+    /// This is for deployments that distribute every root out-of-band. It gives up the
+    /// repository's ability to rotate or revoke keys: if a key in the trusted root is compromised,
+    /// clients keep trusting it until a new root reaches them out-of-band, and metadata signed with
+    /// newly rotated keys fails to load until then.
+    Disabled,
+}
+
 /// A builder for settings with which to load a [`Repository`]. Required settings are provided in
 /// the [`RepositoryLoader::new`] function. Optional parameters can be added after calling new.
 /// Finally, call [`RepositoryLoader::load`] to load the [`Repository`].
@@ -170,12 +309,30 @@ impl From<ExpirationEnforcement> for bool {
 #[derive(Debug, Clone)]
 pub struct RepositoryLoader<'a> {
     root: &'a [u8],
+    additional_roots: Vec<&'a [u8]>,
+    trusted_root_chain: Vec<Vec<u8>>,
     metadata_base_url: Url,
     targets_base_url: Url,
     transport: Option<Box<dyn Transport + Send + Sync>>,
     limits: Option<Limits>,
-    datastore: Option<PathBuf>,
+    datastore: Option<Arc<dyn DatastoreBackend>>,
+    datastore_lock_timeout: Option<Duration>,
     expiration_enforcement: Option<ExpirationEnforcement>,
+    verification_policy: Option<VerificationPolicy>,
+    bundle: Option<MetadataBundle>,
+    role_timeout: Option<Duration>,
+    load_timeout: Option<Duration>,
+    require_consistent_snapshot: bool,
+    target_cache: Option<TargetCache>,
+    offline: bool,
+    missing_role_policy: Option<MissingRolePolicy>,
+    unhashed_target_policy: Option<UnhashedTargetPolicy>,
+    timestamp_meta_policy: Option<TimestampMetaPolicy>,
+    root_update_policy: Option<Arc<dyn RootUpdatePolicy>>,
+    root_update: Option<RootUpdate>,
+    apply_target_modes: bool,
+    observer: Option<Arc<dyn RepositoryObserver>>,
+    filename_encoding: Option<Arc<dyn FilenameEncoding>>,
 }
 
 impl<'a> RepositoryLoader<'a> {
@@ -191,12 +348,30 @@ impl<'a> RepositoryLoader<'a> {
     pub fn new(root: &'a impl AsRef<[u8]>, metadata_base_url: Url, targets_base_url: Url) -> Self {
         Self {
             root: root.as_ref(),
+            additional_roots: Vec::new(),
+            trusted_root_chain: Vec::new(),
             metadata_base_url,
             targets_base_url,
             transport: None,
             limits: None,
             datastore: None,
+            datastore_lock_timeout: None,
             expiration_enforcement: None,
+            verification_policy: None,
+            bundle: None,
+            role_timeout: None,
+            load_timeout: None,
+            require_consistent_snapshot: false,
+            target_cache: None,
+            offline: false,
+            missing_role_policy: None,
+            unhashed_target_policy: None,
+            timestamp_meta_policy: None,
+            root_update_policy: None,
+            root_update: None,
+            apply_target_modes: false,
+            observer: None,
+            filename_encoding: None,
         }
     }
 
@@ -205,6 +380,36 @@ impl<'a> RepositoryLoader<'a> {
         Repository::load(self).await
     }
 
+    /// Add another candidate trusted root metadata file.
+    ///
+    /// Devices from different vendors may ship different trusted roots for the same repository
+    /// lineage. When candidates are added, each is tried in order, starting with the root passed
+    /// to [`RepositoryLoader::new`], and the load succeeds with the first one that establishes
+    /// trust up to the repository's current root. [`Repository::trusted_root_index`] reports which
+    /// one was used.
+    #[must_use]
+    pub fn additional_root(mut self, root: &'a impl AsRef<[u8]>) -> Self {
+        self.additional_roots.push(root.as_ref());
+        self
+    }
+
+    /// Supply a chain of later root metadata files (such as `1.root.json` through `N.root.json`)
+    /// that the client has pinned, so trust can be fast-forwarded locally before going to the
+    /// network.
+    ///
+    /// After the trusted root is loaded, each root in the chain that is newer than it is checked
+    /// the same way a downloaded root would be: it must be the next version, and it must be signed
+    /// by a threshold of keys from both the previous root and itself. The first root that isn't
+    /// valid ends the chain with a warning, so the load continues from the newest valid root.
+    #[must_use]
+    pub fn trusted_root_chain<R: AsRef<[u8]>>(mut self, chain: Vec<R>) -> Self {
+        self.trusted_root_chain = chain
+            .into_iter()
+            .map(|root| root.as_ref().to_vec())
+            .collect();
+        self
+    }
+
     /// Set the transport. If no transport has been set, [`DefaultTransport`] will be used.
     #[must_use]
     pub fn transport<T: Transport + Send + Sync + 'static>(mut self, transport: T) -> Self {
@@ -212,6 +417,14 @@ impl<'a> RepositoryLoader<'a> {
         self
     }
 
+    /// Fetch with the [`ToughContext`]'s transport, sharing it, and any connections it holds open,
+    /// with the context's other users. This replaces any transport set before.
+    #[must_use]
+    pub fn context(mut self, context: &ToughContext) -> Self {
+        self.transport = Some(context.boxed_transport());
+        self
+    }
+
     /// Set a the repository [`Limits`].
     #[must_use]
     pub fn limits(mut self, limits: Limits) -> Self {
@@ -228,7 +441,28 @@ impl<'a> RepositoryLoader<'a> {
     /// directory will be created and cleaned up for for you.
     #[must_use]
     pub fn datastore<P: Into<PathBuf>>(mut self, datastore: P) -> Self {
-        self.datastore = Some(datastore.into());
+        self.datastore = Some(Arc::new(FilesystemDatastore::new(datastore)));
+        self
+    }
+
+    /// Keep the datastore's documents in `backend` rather than in a directory, such as a
+    /// [`MemoryDatastore`] where there's no writable disk. This replaces any
+    /// [`datastore`](Self::datastore) directory that was set.
+    #[must_use]
+    pub fn datastore_backend<B: DatastoreBackend + 'static>(mut self, backend: B) -> Self {
+        self.datastore = Some(Arc::new(backend));
+        self
+    }
+
+    /// Set how long [`RepositoryLoader::load`] waits for another client sharing the datastore,
+    /// such as another process using the same directory, to finish its own load. The default is
+    /// 30 seconds. If the wait times out, the load fails with
+    /// [`Error::DatastoreLock`](crate::error::Error::DatastoreLock).
+    ///
+    /// See [`DatastoreBackend::lock`].
+    #[must_use]
+    pub fn datastore_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.datastore_lock_timeout = Some(timeout);
         self
     }
 
@@ -242,6 +476,163 @@ impl<'a> RepositoryLoader<'a> {
         self.expiration_enforcement = Some(exp);
         self
     }
+
+    /// Set the [`VerificationPolicy`], which can require stronger hashes and listed lengths for
+    /// targets and metadata than the TUF specification does.
+    #[must_use]
+    pub fn verification_policy(mut self, policy: VerificationPolicy) -> Self {
+        self.verification_policy = Some(policy);
+        self
+    }
+
+    /// Set how long loading any one role may take, including every fetch and retry made for it.
+    /// Loading the top-level targets role includes loading its delegated roles.
+    ///
+    /// Transports may time out individual requests, but a server that responds slowly enough can
+    /// still stretch a load out across many requests. When the deadline passes, the fetch in
+    /// flight is cancelled and [`RepositoryLoader::load`] fails with
+    /// [`Error::LoadTimeout`](crate::error::Error::LoadTimeout).
+    #[must_use]
+    pub fn role_timeout(mut self, timeout: Duration) -> Self {
+        self.role_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how long the whole of [`RepositoryLoader::load`] may take, from the root metadata to
+    /// the last delegated targets role. This bounds the worst-case time of an update check; see
+    /// also [`RepositoryLoader::role_timeout`].
+    ///
+    /// The deadline is enforced with tokio's timer, so the load must run within a runtime that has
+    /// time enabled. Dropping the future returned by `load` cancels the load at any point.
+    #[must_use]
+    pub fn load_timeout(mut self, timeout: Duration) -> Self {
+        self.load_timeout = Some(timeout);
+        self
+    }
+
+    /// Refuse to load a repository whose root metadata doesn't enable consistent snapshots.
+    ///
+    /// Without consistent snapshots, metadata and targets are fetched by names that are reused
+    /// across versions, so a CDN can serve a cached file that is out of step with the rest of the
+    /// repository. When set, [`RepositoryLoader::load`] fails with
+    /// [`Error::ConsistentSnapshotRequired`](crate::error::Error::ConsistentSnapshotRequired) if
+    /// the most recent root sets `consistent_snapshot` to `false`.
+    #[must_use]
+    pub fn require_consistent_snapshot(mut self, require: bool) -> Self {
+        self.require_consistent_snapshot = require;
+        self
+    }
+
+    /// Load metadata from a [`MetadataBundle`] rather than fetching it from `metadata_base_url`.
+    ///
+    /// The bundled files are verified exactly as fetched files would be, starting from the trusted
+    /// root. Because only the bundle's root is available, the trusted root must be either that
+    /// root or its immediate predecessor. `metadata_base_url` is still used to name the files in
+    /// errors, and targets are fetched from `targets_base_url` with the configured transport.
+    #[must_use]
+    pub fn metadata_bundle(mut self, bundle: MetadataBundle) -> Self {
+        self.bundle = Some(bundle);
+        self
+    }
+
+    /// Keep the verified contents of targets read with [`Repository::read_target`] in `cache`, and
+    /// serve later reads of the same target from it rather than fetching the target again.
+    ///
+    /// Pass a clone of the same [`TargetCache`] to each load of a repository to keep its contents
+    /// across loads.
+    #[must_use]
+    pub fn target_cache(mut self, cache: TargetCache) -> Self {
+        self.target_cache = Some(cache);
+        self
+    }
+
+    /// Load the metadata that an earlier load stored in the [`datastore`](Self::datastore)
+    /// instead of fetching it, so that nothing is fetched over the network.
+    ///
+    /// The stored files are verified exactly as fetched files would be, starting from the trusted
+    /// root, so expired metadata is still refused unless
+    /// [`expiration_enforcement`](Self::expiration_enforcement) says otherwise. The load fails if a
+    /// file it needs isn't in the datastore; a datastore must be set, and must have been used by
+    /// a successful online load of the same repository. Targets can only be read from a
+    /// [`target_cache`](Self::target_cache); every other read fails.
+    #[must_use]
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Set the [`MissingRolePolicy`], which decides whether a delegated targets role whose
+    /// metadata file is missing from the repository fails the load. The default is to fail.
+    #[must_use]
+    pub fn missing_role_policy(mut self, policy: MissingRolePolicy) -> Self {
+        self.missing_role_policy = Some(policy);
+        self
+    }
+
+    /// Set the [`UnhashedTargetPolicy`], which decides whether a target listed without a hash in
+    /// any algorithm in [`HashAlgorithm`] fails the load. The default is to fail.
+    #[must_use]
+    pub fn unhashed_target_policy(mut self, policy: UnhashedTargetPolicy) -> Self {
+        self.unhashed_target_policy = Some(policy);
+        self
+    }
+
+    /// Set the [`TimestampMetaPolicy`], which decides whether a timestamp.json that describes
+    /// files other than snapshot.json fails the load. The default is to fail.
+    #[must_use]
+    pub fn timestamp_meta_policy(mut self, policy: TimestampMetaPolicy) -> Self {
+        self.timestamp_meta_policy = Some(policy);
+        self
+    }
+
+    /// Set a [`RootUpdatePolicy`] that each new version of root.json must pass, in addition to the
+    /// checks the TUF specification requires, before it's trusted. A root it rejects fails the
+    /// load with [`error::Error::RootUpdateRejected`], and isn't stored in the datastore.
+    #[must_use]
+    pub fn root_update_policy<P: RootUpdatePolicy + 'static>(mut self, policy: P) -> Self {
+        self.root_update_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Set whether newer versions of root.json are fetched to update the trusted root. The
+    /// default, [`RootUpdate::Enabled`], follows the TUF specification; see [`RootUpdate::Disabled`]
+    /// for what is given up by pinning the trusted root instead.
+    #[must_use]
+    pub fn root_update(mut self, root_update: RootUpdate) -> Self {
+        self.root_update = Some(root_update);
+        self
+    }
+
+    /// Give targets saved by [`Repository::save_target`] and [`Repository::download_targets`] the
+    /// file permissions listed in their `custom.mode`, as described in
+    /// [`Target::mode`](schema::Target::mode). By default, and on platforms other than Unix,
+    /// saved targets are only readable and writable by their owner.
+    ///
+    /// Only the read, write and execute bits are applied; setuid, setgid and sticky bits are
+    /// ignored, as is the listed owner and group.
+    #[must_use]
+    pub fn apply_target_modes(mut self, apply: bool) -> Self {
+        self.apply_target_modes = apply;
+        self
+    }
+
+    /// Set a [`RepositoryObserver`] that is told when roots are updated, metadata is fetched,
+    /// roles are close to expiring, and targets are read, by this load and by the [`Repository`]
+    /// it returns.
+    #[must_use]
+    pub fn observer<O: RepositoryObserver + 'static>(mut self, observer: O) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Set the [`FilenameEncoding`] that turns delegated role names into the filenames their
+    /// metadata is fetched from and cached under. This must match the encoding the repository was
+    /// written with; the default is [`PercentEncoding`].
+    #[must_use]
+    pub fn filename_encoding<E: FilenameEncoding + 'static>(mut self, encoding: E) -> Self {
+        self.filename_encoding = Some(Arc::new(encoding));
+        self
+    }
 }
 
 /// Limits used when fetching repository metadata.
@@ -257,13 +648,22 @@ impl<'a> RepositoryLoader<'a> {
 /// endless data attack (defined by TUF as an attacker responding to clients with extremely
 /// large files that interfere with the client's system).
 ///
+/// [`Repository::recommended_limits`] suggests values based on a loaded repository, and a warning
+/// is logged when a fetched file is close to the limit it was fetched under.
+///
 /// The [`Default`] implementation sets the following values:
 /// * `max_root_size`: 1 MiB
 /// * `max_targets_size`: 10 MiB
 /// * `max_timestamp_size`: 1 MiB
 /// * `max_snapshot_size`: 1 MiB
 /// * `max_root_updates`: 1024
+/// * `max_delegated_roles`: 16384
+///
+/// More limits may be added, so `Limits` can't be built from a struct literal outside this crate.
+/// Start from [`Limits::default`] and change the limits that matter with the setters, such as
+/// [`Limits::max_root_size`].
 #[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
 pub struct Limits {
     /// The maximum allowable size in bytes for downloaded root.json files.
     pub max_root_size: u64,
@@ -281,6 +681,10 @@ pub struct Limits {
 
     /// The maximum number of updates to root.json to download.
     pub max_root_updates: u64,
+
+    /// The maximum number of delegated targets roles to load. This bounds the work a repository
+    /// can cause a client to do by defining an enormous delegation graph.
+    pub max_delegated_roles: u64,
 }
 
 impl Default for Limits {
@@ -291,10 +695,55 @@ impl Default for Limits {
             max_timestamp_size: 1024 * 1024,    // 1 MiB
             max_snapshot_size: 1024 * 1024,     // 1 MiB
             max_root_updates: 1024,
+            max_delegated_roles: 16384,
         }
     }
 }
 
+impl Limits {
+    /// Set `max_root_size`.
+    #[must_use]
+    pub fn max_root_size(mut self, max_root_size: u64) -> Self {
+        self.max_root_size = max_root_size;
+        self
+    }
+
+    /// Set `max_targets_size`.
+    #[must_use]
+    pub fn max_targets_size(mut self, max_targets_size: u64) -> Self {
+        self.max_targets_size = max_targets_size;
+        self
+    }
+
+    /// Set `max_timestamp_size`.
+    #[must_use]
+    pub fn max_timestamp_size(mut self, max_timestamp_size: u64) -> Self {
+        self.max_timestamp_size = max_timestamp_size;
+        self
+    }
+
+    /// Set `max_snapshot_size`.
+    #[must_use]
+    pub fn max_snapshot_size(mut self, max_snapshot_size: u64) -> Self {
+        self.max_snapshot_size = max_snapshot_size;
+        self
+    }
+
+    /// Set `max_root_updates`.
+    #[must_use]
+    pub fn max_root_updates(mut self, max_root_updates: u64) -> Self {
+        self.max_root_updates = max_root_updates;
+        self
+    }
+
+    /// Set `max_delegated_roles`.
+    #[must_use]
+    pub fn max_delegated_roles(mut self, max_delegated_roles: u64) -> Self {
+        self.max_delegated_roles = max_delegated_roles;
+        self
+    }
+}
+
 /// Use this enum to specify whether or not we should include a prefix in the target name when
 /// saving a target.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -313,100 +762,262 @@ pub struct Repository {
     transport: Box<dyn Transport + Send + Sync>,
     consistent_snapshot: bool,
     datastore: Datastore,
-    earliest_expiration: DateTime<Utc>,
-    earliest_expiration_role: RoleType,
+    earliest_expiration: RoleExpiration,
+    /// The earliest expiration of the root, timestamp, snapshot and targets metadata.
+    top_level_expiration: RoleExpiration,
+    /// The expiration of each loaded delegated role, keyed by role name.
+    delegated_expirations: HashMap<String, RoleExpiration>,
     root: Signed<Root>,
+    trusted_root_index: usize,
     snapshot: Signed<Snapshot>,
     timestamp: Signed<Timestamp>,
     targets: Signed<crate::schema::Targets>,
     limits: Limits,
+    metadata_sizes: MetadataSizes,
+    changes: RepositoryChanges,
+    fingerprint: String,
+    verification_policy: VerificationPolicy,
     metadata_base_url: Url,
     targets_base_url: Url,
     expiration_enforcement: ExpirationEnforcement,
+    target_cache: Option<TargetCache>,
+    apply_target_modes: bool,
+    observer: Arc<dyn RepositoryObserver>,
+    filename_encoding: Arc<dyn FilenameEncoding>,
+}
+
+/// When one of the loaded roles expires, and where it was fetched from.
+#[derive(Debug, Clone)]
+struct RoleExpiration {
+    expires: DateTime<Utc>,
+    role: RoleType,
+    /// The role type for top-level roles, or the delegated role's name
+    name: String,
+    version: NonZeroU64,
+    url: Url,
+}
+
+impl RoleExpiration {
+    fn new<T: Role>(role: &T, metadata_base_url: &Url, consistent_snapshot: bool) -> Result<Self> {
+        Ok(Self {
+            expires: role.expires(),
+            role: T::TYPE,
+            name: T::TYPE.to_string(),
+            version: role.version(),
+            url: role_url(metadata_base_url, role, consistent_snapshot)?,
+        })
+    }
 }
 
 impl Repository {
     /// Load and verify TUF repository metadata using a [`RepositoryLoader`] for the settings.
+    #[allow(clippy::too_many_lines)]
     async fn load(loader: RepositoryLoader<'_>) -> Result<Self> {
+        let deadlines = Deadlines::start(loader.role_timeout, loader.load_timeout);
+        ensure!(
+            loader.datastore.is_some() || !loader.offline,
+            error::OfflineDatastoreRequiredSnafu
+        );
         let datastore = Datastore::new(loader.datastore)?;
-        let transport = loader
-            .transport
-            .unwrap_or_else(|| Box::new(DefaultTransport::new()));
+        // Hold the datastore lock until the newly trusted metadata is written, so that clients
+        // sharing the datastore don't interleave their reads and writes of it.
+        let _lock = datastore
+            .lock(
+                loader
+                    .datastore_lock_timeout
+                    .unwrap_or(datastore::DEFAULT_LOCK_TIMEOUT),
+            )
+            .await?;
         let limits = loader.limits.unwrap_or_default();
         let expiration_enforcement = loader.expiration_enforcement.unwrap_or_default();
+        let verification_policy = loader.verification_policy.unwrap_or_default();
+        let missing_role_policy = loader.missing_role_policy.unwrap_or_default();
+        let observer = loader
+            .observer
+            .unwrap_or_else(|| Arc::new(observer::NoopObserver));
+        let filename_encoding = loader
+            .filename_encoding
+            .unwrap_or_else(|| Arc::new(PercentEncoding));
         let metadata_base_url = parse_url(loader.metadata_base_url)?;
         let targets_base_url = parse_url(loader.targets_base_url)?;
+        let transport: Box<dyn Transport + Send + Sync> = if loader.offline {
+            Box::new(OfflineTransport::new(
+                datastore.clone(),
+                metadata_base_url.clone(),
+            ))
+        } else {
+            loader
+                .transport
+                .unwrap_or_else(|| Box::new(DefaultTransport::new()))
+        };
+        let transport: Box<dyn Transport + Send + Sync> = match loader.bundle {
+            Some(bundle) => Box::new(BundleTransport::new(
+                bundle,
+                metadata_base_url.clone(),
+                filename_encoding.as_ref(),
+                transport,
+            )?),
+            None => transport,
+        };
+        let mut metadata_sizes = MetadataSizes::default();
+        let previous_state = LoadState::read(&datastore).await?;
 
         // 0. Load the trusted root metadata file + 1. Update the root metadata file
-        let root = load_root(
-            transport.as_ref(),
-            loader.root,
-            &datastore,
-            limits.max_root_size,
-            limits.max_root_updates,
-            &metadata_base_url,
-            expiration_enforcement,
-        )
-        .await?;
+        let candidates: Vec<&[u8]> = std::iter::once(loader.root)
+            .chain(loader.additional_roots)
+            .collect();
+        let (root, trusted_root_index) = deadlines
+            .run(
+                RoleType::Root,
+                load_root_from_candidates(
+                    transport.as_ref(),
+                    &candidates,
+                    &loader.trusted_root_chain,
+                    &datastore,
+                    &limits,
+                    &metadata_base_url,
+                    expiration_enforcement,
+                    loader.offline,
+                    loader.root_update.unwrap_or_default(),
+                    loader.root_update_policy.as_deref(),
+                    observer.as_ref(),
+                    &mut metadata_sizes,
+                ),
+            )
+            .await?;
+        ensure!(
+            root.signed.consistent_snapshot || !loader.require_consistent_snapshot,
+            error::ConsistentSnapshotRequiredSnafu {
+                version: root.signed.version.get(),
+            }
+        );
 
         // 2. Download the timestamp metadata file
-        let timestamp = load_timestamp(
-            transport.as_ref(),
-            &root,
-            &datastore,
-            limits.max_timestamp_size,
-            &metadata_base_url,
-            expiration_enforcement,
-        )
-        .await?;
+        let timestamp = deadlines
+            .run(
+                RoleType::Timestamp,
+                load_timestamp(
+                    transport.as_ref(),
+                    &root,
+                    &datastore,
+                    limits.max_timestamp_size,
+                    &metadata_base_url,
+                    expiration_enforcement,
+                    loader.timestamp_meta_policy.unwrap_or_default(),
+                    observer.as_ref(),
+                    &mut metadata_sizes,
+                ),
+            )
+            .await?;
 
         // 3. Download the snapshot metadata file
-        let snapshot = load_snapshot(
-            transport.as_ref(),
-            &root,
-            &timestamp,
-            limits.max_snapshot_size,
-            &datastore,
-            &metadata_base_url,
-            expiration_enforcement,
-        )
-        .await?;
+        let snapshot = deadlines
+            .run(
+                RoleType::Snapshot,
+                load_snapshot(
+                    transport.as_ref(),
+                    &root,
+                    &timestamp,
+                    limits.max_snapshot_size,
+                    &datastore,
+                    &metadata_base_url,
+                    expiration_enforcement,
+                    verification_policy,
+                    observer.as_ref(),
+                    &mut metadata_sizes,
+                ),
+            )
+            .await?;
 
         // 4. Download the targets metadata file
-        let targets = load_targets(
-            transport.as_ref(),
-            &root,
-            &snapshot,
-            &datastore,
-            limits.max_targets_size,
-            &metadata_base_url,
-            expiration_enforcement,
-        )
-        .await?;
-
-        let expires_iter = [
-            (root.signed.expires, RoleType::Root),
-            (timestamp.signed.expires, RoleType::Timestamp),
-            (snapshot.signed.expires, RoleType::Snapshot),
-            (targets.signed.expires, RoleType::Targets),
+        let mut delegated_expirations = Vec::new();
+        let mut targets = deadlines
+            .run(
+                RoleType::Targets,
+                load_targets(
+                    transport.as_ref(),
+                    &root,
+                    &snapshot,
+                    &datastore,
+                    &limits,
+                    &metadata_base_url,
+                    expiration_enforcement,
+                    verification_policy,
+                    missing_role_policy,
+                    observer.as_ref(),
+                    filename_encoding.as_ref(),
+                    &mut metadata_sizes,
+                    &mut delegated_expirations,
+                ),
+            )
+            .await?;
+        remove_unhashed_targets(
+            &mut targets.signed,
+            "targets",
+            loader.unhashed_target_policy.unwrap_or_default(),
+        )?;
+
+        let consistent_snapshot = root.signed.consistent_snapshot;
+        let expirations = [
+            RoleExpiration::new(&root.signed, &metadata_base_url, consistent_snapshot)?,
+            RoleExpiration::new(&timestamp.signed, &metadata_base_url, consistent_snapshot)?,
+            RoleExpiration::new(&snapshot.signed, &metadata_base_url, consistent_snapshot)?,
+            RoleExpiration::new(&targets.signed, &metadata_base_url, consistent_snapshot)?,
         ];
-        let (earliest_expiration, earliest_expiration_role) =
-            expires_iter.iter().min_by_key(|tup| tup.0).unwrap();
+        let top_level_expiration = expirations
+            .iter()
+            .min_by_key(|expiration| expiration.expires)
+            .unwrap()
+            .clone();
+        let earliest_expiration = delegated_expirations
+            .iter()
+            .chain([&top_level_expiration])
+            .min_by_key(|expiration| expiration.expires)
+            .unwrap()
+            .clone();
+        let warn_before = datastore.system_time().await? + observer.expiration_warning_window();
+        for expiration in expirations.iter().chain(&delegated_expirations) {
+            if expiration.expires <= warn_before {
+                observer.on_expiration_warning(
+                    &expiration.name,
+                    expiration.version,
+                    expiration.expires,
+                );
+            }
+        }
+
+        let state = LoadState::new(&root, &timestamp, &snapshot, &targets);
+        let changes = state.changes_since(previous_state.as_ref());
+        let fingerprint = state.release_fingerprint();
+        state.write(&datastore).await?;
 
         Ok(Self {
             transport,
             consistent_snapshot: root.signed.consistent_snapshot,
             datastore,
-            earliest_expiration: *earliest_expiration,
-            earliest_expiration_role: *earliest_expiration_role,
+            earliest_expiration,
+            top_level_expiration,
+            delegated_expirations: delegated_expirations
+                .into_iter()
+                .map(|expiration| (expiration.name.clone(), expiration))
+                .collect(),
             root,
+            trusted_root_index,
             snapshot,
             timestamp,
             targets,
             limits,
+            metadata_sizes,
+            changes,
+            fingerprint,
+            verification_policy,
             metadata_base_url,
             targets_base_url,
             expiration_enforcement,
+            target_cache: loader.target_cache,
+            apply_target_modes: loader.apply_target_modes,
+            observer,
+            filename_encoding,
         })
     }
 
@@ -420,6 +1031,32 @@ impl Repository {
         &self.root
     }
 
+    /// Returns which trusted root established trust in the current root: `0` for the root passed
+    /// to [`RepositoryLoader::new`], or `n` for the `n`th root added with
+    /// [`RepositoryLoader::additional_root`].
+    pub fn trusted_root_index(&self) -> usize {
+        self.trusted_root_index
+    }
+
+    /// Returns which roles and targets changed since the previous load that used the same
+    /// [`RepositoryLoader::datastore`]. Without a persistent datastore, every load is reported
+    /// as the first.
+    pub fn changes(&self) -> &RepositoryChanges {
+        &self.changes
+    }
+
+    /// Returns a stable identifier of the repository state that was loaded: the versions of the
+    /// root, timestamp, snapshot and targets metadata, and of each loaded delegated role. Clients
+    /// that loaded the same versions have the same fingerprint, so a fleet can report in
+    /// telemetry exactly which release each client is on.
+    ///
+    /// The fingerprint is the hex-encoded sha256 of the canonical JSON of an object with the
+    /// members `root`, `timestamp`, `snapshot` and `targets`, each the role's version, and
+    /// `delegated_roles`, which maps the name of each loaded delegated role to its version.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
     /// Returns a reference to the signed snapshot
     pub fn snapshot(&self) -> &Signed<Snapshot> {
         &self.snapshot
@@ -430,15 +1067,148 @@ impl Repository {
         &self.timestamp
     }
 
+    /// Returns the version, length and hashes that the snapshot metadata lists for each role,
+    /// keyed and sorted by role name, such as `targets` or the name of a delegated role.
+    pub fn snapshot_meta(&self) -> BTreeMap<&str, &Metafile> {
+        meta_by_role(&self.snapshot.signed.meta)
+    }
+
+    /// Returns the version, length and hashes that the timestamp metadata lists for the snapshot
+    /// metadata, keyed by role name. The only role is `snapshot`.
+    pub fn timestamp_meta(&self) -> BTreeMap<&str, &Metafile> {
+        meta_by_role(&self.timestamp.signed.meta)
+    }
+
+    /// Returns the earliest expiration of the loaded root, timestamp, snapshot, targets and
+    /// delegated targets metadata, after which the repository can't be used until it is loaded
+    /// again.
+    pub fn earliest_expiration(&self) -> DateTime<Utc> {
+        self.earliest_expiration.expires
+    }
+
+    /// Suggests when to load the repository again: a random time within `window` before
+    /// [`earliest_expiration`](Self::earliest_expiration), not before now, and always before the
+    /// expiration itself. Clients that loaded the same metadata at the same time then spread
+    /// their refreshes over the window rather than all checking the repository as the metadata
+    /// is about to expire. If the metadata has expired, or `window` isn't positive, this is now.
+    pub fn suggested_refresh(&self, window: chrono::TimeDelta) -> Result<DateTime<Utc>> {
+        let now = Utc::now();
+        let earliest_expiration = self.earliest_expiration.expires;
+        let start = earliest_expiration
+            .checked_sub_signed(window)
+            .context(error::DatetimeOutOfRangeSnafu {
+                datetime: earliest_expiration,
+                delta: window,
+            })?
+            .max(now);
+        if start >= earliest_expiration {
+            return Ok(now);
+        }
+        // Whole seconds short of the span, so the suggestion comes before the expiration
+        let span = earliest_expiration - start - chrono::TimeDelta::nanoseconds(1);
+        Ok(start + jitter::random_duration(span)?)
+    }
+
+    /// Returns the sizes of the metadata files fetched while loading this repository.
+    pub fn metadata_sizes(&self) -> &MetadataSizes {
+        &self.metadata_sizes
+    }
+
+    /// Returns `Limits` sized for this repository's metadata, with room to grow. Integrators can
+    /// use this against a representative repository to choose custom `Limits` for their clients.
+    pub fn recommended_limits(&self) -> Limits {
+        self.metadata_sizes.recommended_limits(&self.limits)
+    }
+
     ///return a vec of all targets including all target files delegated by targets
     pub fn all_targets(&self) -> impl Iterator<Item = (&TargetName, &schema::Target)> + '_ {
         self.targets.signed.targets_iter()
     }
 
+    /// Returns the targets whose `custom` metadata holds `value` at the [JSON Pointer]
+    /// `pointer` (see [`Target::custom_value`](schema::Target::custom_value)), searching the
+    /// whole delegation tree and sorted by name. A target listed by more than one role is only
+    /// returned for the role that [`read_target`](Self::read_target) would use.
+    ///
+    /// [JSON Pointer]: https://www.rfc-editor.org/rfc/rfc6901
+    pub fn targets_with_custom(
+        &self,
+        pointer: &str,
+        value: &serde_json::Value,
+    ) -> Vec<(&TargetName, &schema::Target)> {
+        self.resolved_targets(
+            self.all_targets()
+                .filter(|(_, target)| target.custom_value(pointer) == Some(value)),
+        )
+    }
+
+    /// Returns the targets listed by the targets role `role`, sorted by name. `"targets"` names
+    /// the top-level targets role.
+    ///
+    /// A target is only returned if [`read_target`](Self::read_target) would use this role's
+    /// listing of it: targets outside the paths delegated to `role`, or also listed by a role that
+    /// takes precedence, are left out.
+    pub fn role_targets(&self, role: &str) -> Result<Vec<(&TargetName, &schema::Target)>> {
+        let targets = self.role_metadata(role)?;
+        Ok(self.resolved_targets(targets.targets.iter()))
+    }
+
+    /// Returns the targets listed by the targets role `role` and by the roles it delegates to,
+    /// however deeply, sorted by name. As with [`role_targets`](Self::role_targets), a target is
+    /// only returned if [`read_target`](Self::read_target) would use one of these listings of it.
+    pub fn role_targets_recursive(
+        &self,
+        role: &str,
+    ) -> Result<Vec<(&TargetName, &schema::Target)>> {
+        let targets = self.role_metadata(role)?;
+        Ok(self.resolved_targets(targets.targets_iter()))
+    }
+
+    /// Looks up `name` in the delegation tree the way [`read_target`](Self::read_target) does,
+    /// and reports each role that was considered along with why the lookup failed, if it did:
+    /// whether no delegation covers the name, a role that covers it has no metadata loaded, or it
+    /// is only listed by roles that aren't reachable for it.
+    ///
+    /// Nothing is fetched and expiration isn't checked.
+    pub fn resolve_target(&self, name: &TargetName) -> TargetResolution {
+        TargetResolution::resolve(&self.targets.signed, name)
+    }
+
+    /// Returns the metadata of the targets role `role`, where `"targets"` is the top-level role.
+    fn role_metadata(&self, role: &str) -> Result<&schema::Targets> {
+        if role == "targets" {
+            return Ok(&self.targets.signed);
+        }
+        self.targets
+            .signed
+            .delegated_targets(role)
+            .map(|targets| &targets.signed)
+            .context(error::TargetsNotFoundSnafu { name: role })
+    }
+
+    /// Keeps the listings in `targets` that the delegation tree resolves their names to, sorted by
+    /// name.
+    fn resolved_targets<'a>(
+        &'a self,
+        targets: impl Iterator<Item = (&'a TargetName, &'a schema::Target)>,
+    ) -> Vec<(&'a TargetName, &'a schema::Target)> {
+        let mut targets: Vec<_> = targets
+            .filter(|(name, target)| {
+                self.targets
+                    .signed
+                    .find_target(name)
+                    .is_ok_and(|found| std::ptr::eq(found, *target))
+            })
+            .collect();
+        targets.sort_by_key(|(name, _)| *name);
+        targets
+    }
+
     /// Fetches a target from the repository.
     ///
     /// If the repository metadata is expired or there is an issue making the request, `Err` is
-    /// returned.
+    /// returned. Of the delegated roles, only those on the way to the role that lists the target
+    /// have to be unexpired.
     ///
     /// If the requested target is not listed in the repository metadata, `Ok(None)` is returned.
     ///
@@ -446,20 +1216,15 @@ impl Repository {
     /// checksum is validated. If the maximum size is reached or there is a checksum mismatch, the
     /// stream returns a [`error::Error`]. **Consumers of this library must not use data from the
     /// stream if it returns an error.**
+    ///
+    /// With a [`RepositoryLoader::target_cache`], a target that was read before is returned from
+    /// the cache as a single chunk, without contacting the repository.
     pub async fn read_target(
         &self,
         name: &TargetName,
     ) -> Result<Option<impl Stream<Item = error::Result<Bytes>> + IntoVec<error::Error> + Send>>
     {
-        // Check for repository metadata expiration.
-        if self.expiration_enforcement == ExpirationEnforcement::Safe {
-            ensure!(
-                self.datastore.system_time().await? < self.earliest_expiration,
-                error::ExpiredMetadataSnafu {
-                    role: self.earliest_expiration_role
-                }
-            );
-        }
+        self.check_target_expiration(name).await?;
 
         // 5. Verify the desired target against its targets metadata.
         //
@@ -479,13 +1244,194 @@ impl Repository {
         //   found earlier in step 4. In either case, the client MUST write the file to
         //   non-volatile storage as FILENAME.EXT.
         Ok(if let Ok(target) = self.targets.signed.find_target(name) {
-            let (sha256, file) = self.target_digest_and_filename(target, name);
-            Some(self.fetch_target(target, &sha256, file.as_str()).await?)
+            self.observer.on_target_fetch_start(name);
+            let digest = target
+                .hashes
+                .name_digest()
+                .map(|(_, digest)| digest)
+                .unwrap_or_default();
+            if let Some(data) = self
+                .target_cache
+                .as_ref()
+                .and_then(|c| c.get(name, &digest))
+            {
+                let stream = futures::stream::once(async { Ok(data) }).boxed();
+                return Ok(Some(self.observe_target(name, stream)));
+            }
+            let file = self.target_filename(target, name);
+            let stream = self
+                .fetch_target(name, target, file.as_str())
+                .await
+                .inspect_err(|err| self.observer.on_target_fetch_finish(name, Err(err)))?;
+            let stream = match &self.target_cache {
+                Some(cache) => cache.read_through(name.clone(), &digest, stream),
+                None => stream,
+            };
+            Some(self.observe_target(name, stream))
         } else {
             None
         })
     }
 
+    /// Fetches a target the way [`read_target`](Self::read_target) does and returns its
+    /// contents once every length and hash check has passed.
+    ///
+    /// `max_size` limits how much of the target is held in memory: a target whose listed length
+    /// is larger is refused without being fetched, and the read stops with an error if more than
+    /// `max_size` bytes arrive. If the requested target is not listed in the repository metadata,
+    /// `Ok(None)` is returned.
+    pub async fn read_target_bytes(
+        &self,
+        name: &TargetName,
+        max_size: u64,
+    ) -> Result<Option<Vec<u8>>> {
+        let Ok(target) = self.targets.signed.find_target(name) else {
+            return Ok(None);
+        };
+        ensure!(
+            target.length <= max_size,
+            error::MaxSizeExceededSnafu {
+                max_size,
+                specifier: "read_target_bytes",
+            }
+        );
+        let capacity = usize::try_from(target.length).unwrap_or_default();
+        let Some(stream) = self.read_target(name).await? else {
+            return Ok(None);
+        };
+        stream
+            .try_fold(Vec::with_capacity(capacity), |mut data, chunk| {
+                let result = if (data.len() + chunk.len()) as u64 > max_size {
+                    error::MaxSizeExceededSnafu {
+                        max_size,
+                        specifier: "read_target_bytes",
+                    }
+                    .fail()
+                } else {
+                    data.extend_from_slice(&chunk);
+                    Ok(data)
+                };
+                std::future::ready(result)
+            })
+            .await
+            .map(Some)
+    }
+
+    /// Resumes an interrupted [`read_target`](Self::read_target). `downloaded` reads back the
+    /// beginning of the target that was already received, and the returned stream provides the
+    /// rest of it, which is fetched with [`Transport::fetch_range`].
+    ///
+    /// The bytes read from `downloaded` count towards the target's length and hashes, so the
+    /// stream returns an error unless they and the rest of the target together match the targets
+    /// metadata. As with `read_target`, **data from the stream must not be used if it returns an
+    /// error**, and none of the target should be used until the stream has ended. If the
+    /// [`Transport`] can't fetch part of a file, the error's source is a [`TransportError`] of
+    /// kind [`TransportErrorKind::RangeNotSupported`], and the target has to be read from the
+    /// beginning instead.
+    ///
+    /// The [`RepositoryLoader::target_cache`] isn't used.
+    pub async fn resume_target<R>(
+        &self,
+        name: &TargetName,
+        downloaded: R,
+    ) -> Result<Option<impl Stream<Item = error::Result<Bytes>> + IntoVec<error::Error> + Send>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.check_target_expiration(name).await?;
+        Ok(match self.targets.signed.find_target(name) {
+            Ok(target) => {
+                self.observer.on_target_fetch_start(name);
+                let file = self.target_filename(target, name);
+                let stream = self
+                    .fetch_target_from(name, target, file.as_str(), downloaded)
+                    .await
+                    .inspect_err(|err| self.observer.on_target_fetch_finish(name, Err(err)))?;
+                Some(self.observe_target(name, stream))
+            }
+            Err(_) => None,
+        })
+    }
+
+    /// Tells the [`RepositoryObserver`] when reading the contents of `name` in `stream` finishes.
+    fn observe_target(
+        &self,
+        name: &TargetName,
+        stream: BoxStream<'static, error::Result<Bytes>>,
+    ) -> BoxStream<'static, error::Result<Bytes>> {
+        observer::observe_target(self.observer.clone(), name.clone(), stream)
+    }
+
+    /// Fails if `name` may not be read because the top-level metadata, or the metadata of a
+    /// delegated role on the way to the role that lists `name`, has expired. Delegated roles that
+    /// don't lead to `name` don't matter.
+    async fn check_target_expiration(&self, name: &TargetName) -> Result<()> {
+        if self.expiration_enforcement == ExpirationEnforcement::Safe {
+            let now = self.datastore.system_time().await?;
+            let delegation_path = self
+                .targets
+                .signed
+                .target_delegation_path(name)
+                .unwrap_or_default();
+            let expirations = delegation_path
+                .iter()
+                .filter_map(|role| self.delegated_expirations.get(*role));
+            for expiration in std::iter::once(&self.top_level_expiration).chain(expirations) {
+                ensure!(
+                    now < expiration.expires,
+                    error::ExpiredMetadataSnafu {
+                        role: expiration.role,
+                        name: &expiration.name,
+                        url: expiration.url.clone(),
+                        version: expiration.version,
+                        expires: expiration.expires,
+                    }
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Verifies contents of a target that were received without tough, such as from a CDN edge,
+    /// against the length and hashes listed for the target. Nothing is fetched.
+    ///
+    /// If the repository metadata is expired, `Err` is returned, as it is by
+    /// [`read_target`](Self::read_target). If the target is not listed in the repository metadata,
+    /// `Ok(None)` is returned.
+    ///
+    /// Otherwise, a stream of the contents read from `reader` is returned, which fails just as the
+    /// stream from `read_target` does if they are longer than the listed length or don't match the
+    /// listed hashes, and also if reading from `reader` fails. **Consumers of this library must not
+    /// use data from the stream if it returns an error.** Errors name the URL that the target would
+    /// have been fetched from.
+    pub async fn verify_target_stream<R>(
+        &self,
+        name: &TargetName,
+        reader: R,
+    ) -> Result<Option<impl Stream<Item = error::Result<Bytes>> + IntoVec<error::Error> + Send>>
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        self.check_target_expiration(name).await?;
+        let Ok(target) = self.targets.signed.find_target(name) else {
+            return Ok(None);
+        };
+        let file = self.target_filename(target, name);
+        let url = self
+            .targets_base_url
+            .join(&file)
+            .with_context(|_| error::JoinUrlSnafu {
+                path: &file,
+                url: self.targets_base_url.clone(),
+            })?;
+        let stream = ReaderStream::new(reader)
+            .map_err(move |err| {
+                TransportError::new_with_cause(TransportErrorKind::Other, url.clone(), err)
+            })
+            .boxed();
+        Ok(Some(self.check_target(target, &file, stream)?))
+    }
+
     /// Fetches a target from the repository and saves it to `outdir`. Attempts to do this as safely
     /// as possible by using `path_clean` to eliminate `../` path traversals from the the target's
     /// name. Ensures that the resulting filepath is in `outdir` or a child of `outdir`.
@@ -502,6 +1448,8 @@ impl Repository {
     /// - intermediate directories will be created in `outdir` with `create_dir_all`
     /// - Will error if the result of path resolution results in a filepath outside of `outdir` or
     ///   outside of a delegated target's correct path of delegation.
+    /// - The file is given the permissions listed for the target if
+    ///   [`RepositoryLoader::apply_target_modes`] was set.
     ///
     pub async fn save_target<P>(&self, name: &TargetName, outdir: P, prepend: Prefix) -> Result<()>
     where
@@ -535,8 +1483,12 @@ impl Repository {
                         target_name: name.clone(),
                     }
                 })?;
-                let sha256 = target.hashes.sha256.clone().into_vec();
-                format!("{}.{}", hex::encode(sha256), name.resolved())
+                let digest = target
+                    .hashes
+                    .name_digest()
+                    .map(|(_, digest)| digest)
+                    .unwrap_or_default();
+                format!("{}.{}", hex::encode(digest), name.resolved())
             }
             Prefix::None => name.resolved().to_owned(),
         };
@@ -562,6 +1514,14 @@ impl Repository {
             }
         );
 
+        // A target that isn't listed fails below, when it is read.
+        let mode = match self.targets.signed.find_target(name) {
+            Ok(target) if self.apply_target_modes => target
+                .mode()
+                .context(error::SaveTargetModeSnafu { name: name.clone() })?,
+            _ => None,
+        };
+
         // Fetch and write the target using NamedTempFile for an atomic file creation.
         let mut stream = self
             .read_target(name)
@@ -592,6 +1552,12 @@ impl Repository {
                 .context(error::FileWriteSnafu { path: &tmp_path })?;
         }
 
+        if let Some(mode) = mode {
+            set_permissions(&f, mode)
+                .await
+                .context(error::SaveTargetSetModeSnafu { path: &tmp_path })?;
+        }
+
         // Reconstruct `NamedTempFile` in order to persist it at the target location.
         let f = NamedTempFile::from_parts(f.into_std().await, tmp_path);
         f.persist(&resolved_filepath)
@@ -602,10 +1568,63 @@ impl Repository {
         Ok(())
     }
 
+    /// Fetches targets from the repository and saves them to `outdir`, as [`save_target`] does
+    /// with [`Prefix::None`], fetching up to `concurrency` targets at a time.
+    ///
+    /// A target that fails doesn't stop the others. Each target's result is returned in the order
+    /// of `names`, and `progress` is called with each target's result as soon as it completes.
+    ///
+    /// [`save_target`]: Self::save_target
+    pub async fn download_targets<P, F>(
+        &self,
+        names: &[TargetName],
+        outdir: P,
+        concurrency: NonZeroUsize,
+        mut progress: F,
+    ) -> Vec<(TargetName, Result<()>)>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&TargetName, &Result<()>),
+    {
+        let outdir = outdir.as_ref();
+        let mut downloads = futures::stream::iter(names.iter().enumerate())
+            .map(|(index, name)| async move {
+                (index, self.save_target(name, outdir, Prefix::None).await)
+            })
+            .buffer_unordered(concurrency.get());
+
+        let mut results: Vec<Option<Result<()>>> = names.iter().map(|_| None).collect();
+        while let Some((index, result)) = downloads.next().await {
+            progress(&names[index], &result);
+            results[index] = Some(result);
+        }
+        names
+            .iter()
+            .cloned()
+            .zip(results.into_iter().flatten())
+            .collect()
+    }
+
     /// Return the named `DelegatedRole` if found.
     pub fn delegated_role(&self, name: &str) -> Option<&DelegatedRole> {
         self.targets.signed.delegated_role(name).ok()
     }
+
+    /// The names of the delegated targets roles that were skipped because their metadata files
+    /// were missing, under [`MissingRolePolicy::SkipWithWarning`].
+    pub fn skipped_roles(&self) -> Vec<&str> {
+        fn skipped<'a>(targets: &'a crate::schema::Targets, names: &mut Vec<&'a str>) {
+            for role in targets.delegations.iter().flat_map(|d| &d.roles) {
+                match &role.targets {
+                    Some(role_targets) => skipped(&role_targets.signed, names),
+                    None => names.push(&role.name),
+                }
+            }
+        }
+        let mut names = Vec::new();
+        skipped(&self.targets.signed, &mut names);
+        names
+    }
 }
 
 /// The set of characters that will be escaped when converting a delegated role name into a
@@ -619,7 +1638,7 @@ impl Repository {
 /// > `_.-~` are never quoted.
 ///
 /// [urllib.parse.quote]: https://docs.python.org/3/library/urllib.parse.html#url-quoting
-const CHARACTERS_TO_ESCAPE: AsciiSet = NON_ALPHANUMERIC
+pub(crate) const CHARACTERS_TO_ESCAPE: AsciiSet = NON_ALPHANUMERIC
     .remove(b'_')
     .remove(b'.')
     .remove(b'-')
@@ -631,16 +1650,86 @@ pub(crate) fn encode_filename<S: AsRef<str>>(name: S) -> String {
     utf8_percent_encode(name.as_ref(), &CHARACTERS_TO_ESCAPE).to_string()
 }
 
+/// Gives a saved target's file the read, write and execute bits of its listed mode.
+#[cfg(unix)]
+async fn set_permissions(file: &tokio::fs::File, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(std::fs::Permissions::from_mode(mode & 0o777))
+        .await
+}
+
+/// File modes only exist on Unix.
+#[cfg(not(unix))]
+async fn set_permissions(_file: &tokio::fs::File, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Checks `targets`, listed by `role`, and the roles it delegates to for targets without a hash in
+/// any algorithm in [`HashAlgorithm`], failing or removing them according to `policy`.
+fn remove_unhashed_targets(
+    targets: &mut crate::schema::Targets,
+    role: &str,
+    policy: UnhashedTargetPolicy,
+) -> Result<()> {
+    let mut unhashed: Vec<_> = targets
+        .targets
+        .iter()
+        .filter(|(_, target)| target.hashes.algorithms().is_empty())
+        .map(|(name, _)| name.clone())
+        .collect();
+    unhashed.sort();
+    for name in unhashed {
+        ensure!(
+            policy == UnhashedTargetPolicy::SkipWithWarning,
+            error::UnhashedTargetListedSnafu {
+                name: name.raw(),
+                role,
+            }
+        );
+        warn!(
+            "Skipping target '{}' listed by role '{}': no supported hash is listed for it",
+            name.raw(),
+            role
+        );
+        targets.targets.remove(&name);
+    }
+    if let Some(delegations) = &mut targets.delegations {
+        for delegated_role in &mut delegations.roles {
+            if let Some(delegated) = &mut delegated_role.targets {
+                remove_unhashed_targets(&mut delegated.signed, &delegated_role.name, policy)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 /// TUF v1.0.16, 5.2.9, 5.3.3, 5.4.5, 5.5.4, The expiration timestamp in the `[metadata]` file MUST
 /// be higher than the fixed update start time.
-async fn check_expired<T: Role>(datastore: &Datastore, role: &T) -> Result<()> {
+async fn check_expired<T: Role>(datastore: &Datastore, role: &T, url: &Url) -> Result<()> {
     ensure!(
         datastore.system_time().await? <= role.expires(),
-        error::ExpiredMetadataSnafu { role: T::TYPE }
+        error::ExpiredMetadataSnafu {
+            role: T::TYPE,
+            name: T::TYPE.to_string(),
+            url: url.clone(),
+            version: role.version(),
+            expires: role.expires(),
+        }
     );
     Ok(())
 }
 
+/// The URL that `role` is fetched from.
+fn role_url<T: Role>(metadata_base_url: &Url, role: &T, consistent_snapshot: bool) -> Result<Url> {
+    let path = role.filename(consistent_snapshot);
+    metadata_base_url
+        .join(&path)
+        .with_context(|_| error::JoinUrlSnafu {
+            path,
+            url: metadata_base_url.clone(),
+        })
+}
+
 /// Checks to see if the `Url` has a trailing slash and adds one if not. Without a trailing slash,
 /// the last component of a `Url` is considered to be a file. `metadata_url` and `targets_url`
 /// must refer to a base (i.e. directory), so we need them to end with a slash.
@@ -654,30 +1743,134 @@ fn parse_url(url: Url) -> Result<Url> {
     }
 }
 
+/// Keys a `meta` map by role name, dropping the `.json` extension of each metadata path.
+fn meta_by_role(meta: &HashMap<String, Metafile>) -> BTreeMap<&str, &Metafile> {
+    meta.iter()
+        .map(|(path, metafile)| (path.strip_suffix(".json").unwrap_or(path), metafile))
+        .collect()
+}
+
+/// The changes to the datastore that loading the root from a candidate trusted root makes, held
+/// back until the candidate establishes trust so that a failing candidate changes nothing.
+#[derive(Debug, Default)]
+struct RootDatastoreChanges {
+    /// Each root that trust was established through, kept for offline loads, by filename.
+    roots: Vec<(String, Vec<u8>)>,
+    /// Whether the timestamp or snapshot keys were rotated, so that the stored timestamp and
+    /// snapshot metadata are deleted (step 1.9).
+    keys_rotated: bool,
+}
+
+impl RootDatastoreChanges {
+    /// Makes the changes to `datastore`.
+    async fn commit(self, datastore: &Datastore) -> Result<()> {
+        for (path, data) in &self.roots {
+            datastore.write_bytes(path, data).await?;
+        }
+        if self.keys_rotated {
+            let r1 = datastore.remove("timestamp.json").await;
+            let r2 = datastore.remove("snapshot.json").await;
+            r1.and(r2)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs steps 0 and 1 with each candidate trusted root in turn, returning the current root from
+/// the first candidate that establishes trust in it, along with that candidate's index. Only that
+/// candidate's changes to the datastore and metadata sizes are kept.
+#[allow(clippy::too_many_arguments)]
+async fn load_root_from_candidates(
+    transport: &dyn Transport,
+    candidates: &[&[u8]],
+    chain: &[Vec<u8>],
+    datastore: &Datastore,
+    limits: &Limits,
+    metadata_base_url: &Url,
+    expiration_enforcement: ExpirationEnforcement,
+    offline: bool,
+    root_update: RootUpdate,
+    policy: Option<&dyn RootUpdatePolicy>,
+    observer: &dyn RepositoryObserver,
+    sizes: &mut MetadataSizes,
+) -> Result<(Signed<Root>, usize)> {
+    let mut last_error = None;
+    for (index, candidate) in candidates.iter().enumerate() {
+        let mut candidate_sizes = *sizes;
+        let mut changes = RootDatastoreChanges::default();
+        match load_root(
+            transport,
+            candidate,
+            chain,
+            datastore,
+            limits.max_root_size,
+            limits.max_root_updates,
+            metadata_base_url,
+            expiration_enforcement,
+            offline,
+            root_update,
+            policy,
+            observer,
+            &mut candidate_sizes,
+            &mut changes,
+        )
+        .await
+        {
+            Ok(root) => {
+                changes.commit(datastore).await?;
+                *sizes = candidate_sizes;
+                return Ok((root, index));
+            }
+            // With a single trusted root, report its failure as-is.
+            Err(err) if candidates.len() == 1 => return Err(err),
+            Err(err) => {
+                warn!("Trusted root candidate {index} did not establish trust: {err}");
+                last_error = Some(err);
+            }
+        }
+    }
+    let last_error = last_error.context(error::NoTrustedRootCandidatesSnafu)?;
+    Err(Box::new(last_error)).context(error::NoTrustedRootSnafu {
+        candidates: candidates.len(),
+    })
+}
+
 /// Steps 0 and 1 of the client application, which load the current root metadata file based on a
-/// trusted root metadata file.
+/// trusted root metadata file. The changes these steps make to the datastore are added to
+/// `changes` rather than made.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 async fn load_root<R: AsRef<[u8]>>(
     transport: &dyn Transport,
     root: R,
+    chain: &[Vec<u8>],
     datastore: &Datastore,
     max_root_size: u64,
     max_root_updates: u64,
     metadata_base_url: &Url,
     expiration_enforcement: ExpirationEnforcement,
+    offline: bool,
+    root_update: RootUpdate,
+    policy: Option<&dyn RootUpdatePolicy>,
+    observer: &dyn RepositoryObserver,
+    sizes: &mut MetadataSizes,
+    changes: &mut RootDatastoreChanges,
 ) -> Result<Signed<Root>> {
     // 0. Load the trusted root metadata file. We assume that a good, trusted copy of this file was
     //    shipped with the package manager or software updater using an out-of-band process. Note
     //    that the expiration of the trusted root metadata file does not matter, because we will
     //    attempt to update it in the next step.
+    sizes.record(
+        RoleType::Root,
+        "trusted root.json",
+        root.as_ref().len(),
+        None,
+    );
     let mut root: Signed<Root> =
         serde_json::from_slice(root.as_ref()).context(error::ParseTrustedMetadataSnafu)?;
     root.signed
         .verify_role(&root)
         .context(error::VerifyTrustedMetadataSnafu)?;
 
-    // Used in step 1.2
-    let original_root_version = root.signed.version.get();
-
     // Used in step 1.9
     let original_timestamp_keys = root
         .signed
@@ -690,12 +1883,23 @@ async fn load_root<R: AsRef<[u8]>>(
         .cloned()
         .collect::<Vec<_>>();
 
+    // Off-spec: before going to the network, fast-forward through the roots the client supplied.
+    fast_forward_root(&mut root, chain, policy, observer, sizes, changes);
+
+    // Used in step 1.2
+    let original_root_version = root.signed.version.get();
+
     // 1. Update the root metadata file. Since it may now be signed using entirely different keys,
     //    the client must somehow be able to establish a trusted line of continuity to the latest
     //    set of keys. To do so, the client MUST download intermediate root metadata files, until
     //    the latest available one is reached. Therefore, it MUST temporarily turn on consistent
     //    snapshots in order to download versioned root metadata files as described next.
     loop {
+        // Off-spec: the client may pin its trusted root, in which case go straight to step 1.8.
+        if root_update == RootUpdate::Disabled {
+            break;
+        }
+
         // 1.1. Let N denote the version number of the trusted root metadata file.
         //
         // 1.2. Try downloading version N+1 of the root metadata file, up to some X number of bytes
@@ -730,8 +1934,9 @@ async fn load_root<R: AsRef<[u8]>>(
                 let data = match stream.into_vec().await {
                     Ok(d) => d,
                     Err(e) if e.kind() == TransportErrorKind::FileNotFound => break,
-                    err @ Err(_) => err.context(error::TransportSnafu { url })?,
+                    err @ Err(_) => err.context(error::TransportSnafu { url: url.clone() })?,
                 };
+                sizes.record(RoleType::Root, &path, data.len(), Some(max_root_size));
                 let new_root: Signed<Root> =
                     serde_json::from_slice(&data).context(error::ParseMetadataSnafu {
                         role: RoleType::Root,
@@ -743,17 +1948,19 @@ async fn load_root<R: AsRef<[u8]>>(
                 //   file being validated (version N+1). If version N+1 is not signed as required,
                 //   discard it, abort the update cycle, and report the signature failure. On the
                 //   next update cycle, begin at step 0 and version N of the root metadata file.
+                let verify_context = || error::VerifyMetadataSnafu {
+                    role: RoleType::Root,
+                    name: "root",
+                    url: url.clone(),
+                    version: new_root.signed.version,
+                };
                 root.signed
                     .verify_role(&new_root)
-                    .context(error::VerifyMetadataSnafu {
-                        role: RoleType::Root,
-                    })?;
+                    .with_context(|_| verify_context())?;
                 new_root
                     .signed
                     .verify_role(&new_root)
-                    .context(error::VerifyMetadataSnafu {
-                        role: RoleType::Root,
-                    })?;
+                    .with_context(|_| verify_context())?;
 
                 // 1.4. Check for a rollback attack. The version number of the trusted root
                 //   metadata file (version N) must be less than or equal to the version number of
@@ -767,6 +1974,8 @@ async fn load_root<R: AsRef<[u8]>>(
                     root.signed.version <= new_root.signed.version,
                     error::OlderMetadataSnafu {
                         role: RoleType::Root,
+                        name: "root",
+                        url,
                         current_version: root.signed.version,
                         new_version: new_root.signed.version
                     }
@@ -782,16 +1991,28 @@ async fn load_root<R: AsRef<[u8]>>(
                     break;
                 }
 
+                // Off-spec: the client's own policy for root updates.
+                if let Some(policy) = policy {
+                    policy
+                        .check(&root, &new_root)
+                        .context(error::RootUpdateRejectedSnafu {
+                            version: new_root.signed.version,
+                        })?;
+                }
+
                 // 1.5. Note that the expiration of the new (intermediate) root metadata file does
                 //   not matter yet, because we will check for it in step 1.8.
                 //
                 // 1.6. Set the trusted root metadata file to the new root metadata file.
                 //
                 // (This is where version N+1 becomes version N.)
+                observer.on_root_update(&root, &new_root);
+                observer.on_metadata_fetched("root", new_root.signed.version, &data);
                 root = new_root;
+                // Keep each root that trust was established through, for offline loads.
+                changes.roots.push((path, data));
 
                 // 1.7. Repeat steps 1.1 to 1.7.
-                continue;
             }
         }
     }
@@ -801,7 +2022,16 @@ async fn load_root<R: AsRef<[u8]>>(
     // file has expired, abort the update cycle, report the potential freeze attack. On the next
     // update cycle, begin at step 5.1 and version N of the root metadata file.
     if expiration_enforcement == ExpirationEnforcement::Safe {
-        check_expired(datastore, &root.signed).await?;
+        check_expired(
+            datastore,
+            &root.signed,
+            &role_url(
+                metadata_base_url,
+                &root.signed,
+                root.signed.consistent_snapshot,
+            )?,
+        )
+        .await?;
     }
 
     // 1.9. If the timestamp and / or snapshot keys have been rotated, then delete the trusted
@@ -810,17 +2040,16 @@ async fn load_root<R: AsRef<[u8]>>(
     //   happens when attackers arbitrarily increase the version numbers of: (1) the timestamp
     //   metadata, (2) the snapshot metadata, and / or (3) the targets, or a delegated targets,
     //   metadata file in the snapshot metadata.
-    if original_timestamp_keys
-        .iter()
-        .ne(root.signed.keys(RoleType::Timestamp))
-        || original_snapshot_keys
+    //
+    //   Offline loads only have the stored files, which were trusted under the current keys when
+    //   they were stored, so they are kept.
+    changes.keys_rotated = !offline
+        && (original_timestamp_keys
             .iter()
-            .ne(root.signed.keys(RoleType::Snapshot))
-    {
-        let r1 = datastore.remove("timestamp.json").await;
-        let r2 = datastore.remove("snapshot.json").await;
-        r1.and(r2)?;
-    }
+            .ne(root.signed.keys(RoleType::Timestamp))
+            || original_snapshot_keys
+                .iter()
+                .ne(root.signed.keys(RoleType::Snapshot)));
 
     // 1.10. Set whether consistent snapshots are used as per the trusted root metadata file (see
     //   Section 4.3).
@@ -831,7 +2060,71 @@ async fn load_root<R: AsRef<[u8]>>(
     Ok(root)
 }
 
+/// Updates `root` through the client-supplied `chain` of root metadata files, checking each the
+/// way step 1 checks a downloaded root, and stopping at the first that doesn't validly follow on
+/// from the current root or that `policy` rejects. The roots it updates through are added to
+/// `changes`.
+fn fast_forward_root(
+    root: &mut Signed<Root>,
+    chain: &[Vec<u8>],
+    policy: Option<&dyn RootUpdatePolicy>,
+    observer: &dyn RepositoryObserver,
+    sizes: &mut MetadataSizes,
+    changes: &mut RootDatastoreChanges,
+) {
+    let mut parsed = Vec::with_capacity(chain.len());
+    for (index, data) in chain.iter().enumerate() {
+        match serde_json::from_slice::<Signed<Root>>(data) {
+            Ok(new_root) => parsed.push((new_root, data)),
+            Err(err) => warn!("Root {index} of the trusted root chain is not valid: {err}"),
+        }
+    }
+    parsed.sort_by_key(|(new_root, _)| new_root.signed.version);
+
+    for (new_root, data) in parsed {
+        let version = new_root.signed.version.get();
+        if version <= root.signed.version.get() {
+            continue;
+        }
+        if version != root.signed.version.get() + 1 {
+            warn!(
+                "The trusted root chain skips from version {} to {}; continuing from version {}",
+                root.signed.version, version, root.signed.version
+            );
+            break;
+        }
+        let verified = root
+            .signed
+            .verify_role(&new_root)
+            .and_then(|()| new_root.signed.verify_role(&new_root));
+        if let Err(err) = verified {
+            warn!(
+                "Version {} of the trusted root chain is not validly signed; continuing from \
+                 version {}: {}",
+                version, root.signed.version, err
+            );
+            break;
+        }
+        if let Some(Err(err)) = policy.map(|policy| policy.check(root, &new_root)) {
+            warn!(
+                "Version {} of the trusted root chain was rejected by policy; continuing from \
+                 version {}: {}",
+                version, root.signed.version, err
+            );
+            break;
+        }
+
+        let path = format!("{version}.root.json");
+        sizes.record(RoleType::Root, &path, data.len(), None);
+        // Keep each root that trust was established through, for offline loads.
+        changes.roots.push((path, data.clone()));
+        observer.on_root_update(root, &new_root);
+        *root = new_root;
+    }
+}
+
 /// Step 2 of the client application, which loads the timestamp metadata file.
+#[allow(clippy::too_many_arguments)]
 async fn load_timestamp(
     transport: &dyn Transport,
     root: &Signed<Root>,
@@ -839,6 +2132,9 @@ async fn load_timestamp(
     max_timestamp_size: u64,
     metadata_base_url: &Url,
     expiration_enforcement: ExpirationEnforcement,
+    meta_policy: TimestampMetaPolicy,
+    observer: &dyn RepositoryObserver,
+    sizes: &mut MetadataSizes,
 ) -> Result<Signed<Timestamp>> {
     // 2. Download the timestamp metadata file, up to Y number of bytes (because the size is
     //    unknown.) The value for Y is set by the authors of the application using TUF. For
@@ -861,7 +2157,13 @@ async fn load_timestamp(
     let data = stream
         .into_vec()
         .await
-        .context(error::TransportSnafu { url })?;
+        .context(error::TransportSnafu { url: url.clone() })?;
+    sizes.record(
+        RoleType::Timestamp,
+        path,
+        data.len(),
+        Some(max_timestamp_size),
+    );
     let timestamp: Signed<Timestamp> =
         serde_json::from_slice(&data).context(error::ParseMetadataSnafu {
             role: RoleType::Timestamp,
@@ -874,8 +2176,25 @@ async fn load_timestamp(
         .verify_role(&timestamp)
         .context(error::VerifyMetadataSnafu {
             role: RoleType::Timestamp,
+            name: "timestamp",
+            url: url.clone(),
+            version: timestamp.signed.version,
         })?;
 
+    // The timestamp metadata file MUST only describe the snapshot metadata file.
+    let unexpected = timestamp.signed.unexpected_meta();
+    if !unexpected.is_empty() {
+        let files = unexpected.join(", ");
+        ensure!(
+            meta_policy == TimestampMetaPolicy::Warn,
+            error::TimestampMetaUnexpectedSnafu {
+                files,
+                url: url.clone(),
+            }
+        );
+        warn!("Ignoring files other than snapshot.json described by '{url}': {files}");
+    }
+
     // 2.2. Check for a rollback attack. The version number of the trusted timestamp metadata file,
     //   if any, must be less than or equal to the version number of the new timestamp metadata
     //   file. If the new timestamp metadata file is older than the trusted timestamp metadata
@@ -890,6 +2209,8 @@ async fn load_timestamp(
                 old_timestamp.signed.version <= timestamp.signed.version,
                 error::OlderMetadataSnafu {
                     role: RoleType::Timestamp,
+                    name: "timestamp",
+                    url: url.clone(),
                     current_version: old_timestamp.signed.version,
                     new_version: timestamp.signed.version
                 }
@@ -902,17 +2223,18 @@ async fn load_timestamp(
     // metadata file becomes the trusted timestamp metadata file. If the new timestamp metadata file
     // has expired, discard it, abort the update cycle, and report the potential freeze attack.
     if expiration_enforcement == ExpirationEnforcement::Safe {
-        check_expired(datastore, &timestamp.signed).await?;
+        check_expired(datastore, &timestamp.signed, &url).await?;
     }
 
     // Now that everything seems okay, write the timestamp file to the datastore.
-    datastore.create("timestamp.json", &timestamp).await?;
+    datastore.write_bytes("timestamp.json", &data).await?;
+    observer.on_metadata_fetched("timestamp", timestamp.signed.version, &data);
 
     Ok(timestamp)
 }
 
 /// Step 3 of the client application, which loads the snapshot metadata file.
-#[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 async fn load_snapshot(
     transport: &dyn Transport,
     root: &Signed<Root>,
@@ -921,6 +2243,9 @@ async fn load_snapshot(
     datastore: &Datastore,
     metadata_base_url: &Url,
     expiration_enforcement: ExpirationEnforcement,
+    policy: VerificationPolicy,
+    observer: &dyn RepositoryObserver,
+    sizes: &mut MetadataSizes,
 ) -> Result<Signed<Snapshot>> {
     // 3. Download snapshot metadata file, up to the number of bytes specified in the timestamp
     //    metadata file. If consistent snapshots are not used (see Section 7), then the filename
@@ -949,13 +2274,14 @@ async fn load_snapshot(
             path: path.clone(),
             url: metadata_base_url.clone(),
         })?;
+    policy.check_metadata_listing(snapshot_meta.length, snapshot_meta.hashes.as_ref(), &path)?;
     let stream = if let Some(hashes) = &snapshot_meta.hashes {
-        fetch_sha256(
+        fetch_digests(
             transport,
             url.clone(),
             snapshot_meta.length.unwrap_or(max_snapshot_size),
             "timestamp.json",
-            &hashes.sha256,
+            &policy.digests(hashes, &path)?,
         )
         .await?
     } else {
@@ -971,7 +2297,15 @@ async fn load_snapshot(
     let data = stream
         .into_vec()
         .await
-        .context(error::TransportSnafu { url })?;
+        .context(error::TransportSnafu { url: url.clone() })?;
+    sizes.record(
+        RoleType::Snapshot,
+        &path,
+        data.len(),
+        snapshot_meta
+            .length
+            .map_or(Some(max_snapshot_size), |_| None),
+    );
     let snapshot: Signed<Snapshot> =
         serde_json::from_slice(&data).context(error::ParseMetadataSnafu {
             role: RoleType::Snapshot,
@@ -982,11 +2316,13 @@ async fn load_snapshot(
     //   hashes and version do not match, discard the new snapshot metadata, abort the update
     //   cycle, and report the failure.
     //
-    // (We already checked the hash in `fetch_sha256` above.)
+    // (We already checked the hash in `fetch_digests` above.)
     ensure!(
         snapshot.signed.version == snapshot_meta.version,
         error::VersionMismatchSnafu {
             role: RoleType::Snapshot,
+            name: "snapshot",
+            url: url.clone(),
             fetched: snapshot.signed.version,
             expected: snapshot_meta.version
         }
@@ -1000,6 +2336,9 @@ async fn load_snapshot(
         .verify_role(&snapshot)
         .context(error::VerifyMetadataSnafu {
             role: RoleType::Snapshot,
+            name: "snapshot",
+            url: url.clone(),
+            version: snapshot.signed.version,
         })?;
 
     // 3.3. Check for a rollback attack.
@@ -1020,6 +2359,8 @@ async fn load_snapshot(
                 old_snapshot.signed.version <= snapshot.signed.version,
                 error::OlderMetadataSnafu {
                     role: RoleType::Snapshot,
+                    name: "snapshot",
+                    url: url.clone(),
                     current_version: old_snapshot.signed.version,
                     new_version: snapshot.signed.version
                 }
@@ -1046,6 +2387,8 @@ async fn load_snapshot(
                     old_targets_meta.version <= targets_meta.version,
                     error::OlderMetadataSnafu {
                         role: RoleType::Targets,
+                        name: "targets",
+                        url: url.clone(),
                         current_version: old_targets_meta.version,
                         new_version: targets_meta.version,
                     }
@@ -1059,25 +2402,34 @@ async fn load_snapshot(
     // metadata file becomes the trusted snapshot metadata file. If the new snapshot metadata file
     // is expired, discard it, abort the update cycle, and report the potential freeze attack.
     if expiration_enforcement == ExpirationEnforcement::Safe {
-        check_expired(datastore, &snapshot.signed).await?;
+        check_expired(datastore, &snapshot.signed, &url).await?;
     }
 
     // Now that everything seems okay, write the snapshot file to the datastore.
-    datastore.create("snapshot.json", &snapshot).await?;
+    datastore.write_bytes("snapshot.json", &data).await?;
+    observer.on_metadata_fetched("snapshot", snapshot.signed.version, &data);
 
     Ok(snapshot)
 }
 
 /// Step 4 of the client application, which loads the targets metadata file.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 async fn load_targets(
     transport: &dyn Transport,
     root: &Signed<Root>,
     snapshot: &Signed<Snapshot>,
     datastore: &Datastore,
-    max_targets_size: u64,
+    limits: &Limits,
     metadata_base_url: &Url,
     expiration_enforcement: ExpirationEnforcement,
+    policy: VerificationPolicy,
+    missing_role_policy: MissingRolePolicy,
+    observer: &dyn RepositoryObserver,
+    filename_encoding: &dyn FilenameEncoding,
+    sizes: &mut MetadataSizes,
+    delegated_expirations: &mut Vec<RoleExpiration>,
 ) -> Result<Signed<crate::schema::Targets>> {
+    let max_targets_size = limits.max_targets_size;
     // 4. Download the top-level targets metadata file, up to either the number of bytes specified
     //    in the snapshot metadata file, or some Z number of bytes. The value for Z is set by the
     //    authors of the application using TUF. For example, Z may be tens of kilobytes. If
@@ -1104,29 +2456,35 @@ async fn load_targets(
     let targets_url = metadata_base_url
         .join(&path)
         .with_context(|_| error::JoinUrlSnafu {
-            path,
+            path: path.clone(),
             url: metadata_base_url.clone(),
         })?;
     let (max_targets_size, specifier) = match targets_meta.length {
         Some(length) => (length, "snapshot.json"),
         None => (max_targets_size, "max_targets_size parameter"),
     };
+    policy.check_metadata_listing(targets_meta.length, targets_meta.hashes.as_ref(), &path)?;
     let stream = if let Some(hashes) = &targets_meta.hashes {
-        fetch_sha256(
+        fetch_digests(
             transport,
             targets_url.clone(),
             max_targets_size,
             specifier,
-            &hashes.sha256,
+            &policy.digests(hashes, &path)?,
         )
         .await?
     } else {
         fetch_max_size(transport, targets_url.clone(), max_targets_size, specifier).await?
     };
-    let data = stream
-        .into_vec()
-        .await
-        .context(error::TransportSnafu { url: targets_url })?;
+    let data = stream.into_vec().await.context(error::TransportSnafu {
+        url: targets_url.clone(),
+    })?;
+    sizes.record(
+        RoleType::Targets,
+        &path,
+        data.len(),
+        targets_meta.length.map_or(Some(max_targets_size), |_| None),
+    );
     let mut targets: Signed<crate::schema::Targets> =
         serde_json::from_slice(&data).context(error::ParseMetadataSnafu {
             role: RoleType::Targets,
@@ -1137,11 +2495,13 @@ async fn load_targets(
     //   prevent a mix-and-match attack by man-in-the-middle attackers. If the new targets metadata
     //   file does not match, discard it, abort the update cycle, and report the failure.
     //
-    // (We already checked the hash in `fetch_sha256` above.)
+    // (We already checked the hash in `fetch_digests` above.)
     ensure!(
         targets.signed.version == targets_meta.version,
         error::VersionMismatchSnafu {
             role: RoleType::Targets,
+            name: "targets",
+            url: targets_url.clone(),
             fetched: targets.signed.version,
             expected: targets_meta.version
         }
@@ -1155,6 +2515,9 @@ async fn load_targets(
         .verify_role(&targets)
         .context(error::VerifyMetadataSnafu {
             role: RoleType::Targets,
+            name: "targets",
+            url: targets_url.clone(),
+            version: targets.signed.version,
         })?;
 
     // 4.3. Check for a rollback attack. The version number of the trusted targets metadata file,
@@ -1171,6 +2534,8 @@ async fn load_targets(
                 old_targets.signed.version <= targets.signed.version,
                 error::OlderMetadataSnafu {
                     role: RoleType::Targets,
+                    name: "targets",
+                    url: targets_url.clone(),
                     current_version: old_targets.signed.version,
                     new_version: targets.signed.version
                 }
@@ -1183,11 +2548,12 @@ async fn load_targets(
     // metadata file becomes the trusted targets metadata file. If the new targets metadata file is
     // expired, discard it, abort the update cycle, and report the potential freeze attack.
     if expiration_enforcement == ExpirationEnforcement::Safe {
-        check_expired(datastore, &targets.signed).await?;
+        check_expired(datastore, &targets.signed, &targets_url).await?;
     }
 
     // Now that everything seems okay, write the targets file to the datastore.
-    datastore.create("targets.json", &targets).await?;
+    datastore.write_bytes("targets.json", &data).await?;
+    observer.on_metadata_fetched("targets", targets.signed.version, &data);
 
     // 4.5. Perform a preorder depth-first search for metadata about the desired target, beginning
     //   with the top-level targets role.
@@ -1198,8 +2564,15 @@ async fn load_targets(
             root.signed.consistent_snapshot,
             metadata_base_url,
             max_targets_size,
+            policy,
+            missing_role_policy,
             delegations,
             datastore,
+            &mut DelegationWalk::new(limits.max_delegated_roles),
+            observer,
+            filename_encoding,
+            sizes,
+            delegated_expirations,
         )
         .await?;
     }
@@ -1210,7 +2583,9 @@ async fn load_targets(
     Ok(targets)
 }
 
-// Follow the paths of delegations starting with the top level targets.json delegation
+// Follow the paths of delegations starting with the top level targets.json delegation. `walk`
+// rejects delegation cycles and oversized graphs before any metadata for them is fetched.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 #[async_recursion]
 async fn load_delegations(
     transport: &dyn Transport,
@@ -1218,12 +2593,20 @@ async fn load_delegations(
     consistent_snapshot: bool,
     metadata_base_url: &Url,
     max_targets_size: u64,
+    policy: VerificationPolicy,
+    missing_role_policy: MissingRolePolicy,
     delegation: &mut Delegations,
     datastore: &Datastore,
+    walk: &mut DelegationWalk,
+    observer: &dyn RepositoryObserver,
+    filename_encoding: &dyn FilenameEncoding,
+    sizes: &mut MetadataSizes,
+    expirations: &mut Vec<RoleExpiration>,
 ) -> Result<()> {
     let mut delegated_roles: HashMap<String, Option<Signed<crate::schema::Targets>>> =
         HashMap::new();
     for delegated_role in &delegation.roles {
+        walk.visit(&delegated_role.name)?;
         // find the role file metadata
         let role_meta = snapshot
             .signed
@@ -1233,29 +2616,63 @@ async fn load_delegations(
                 name: delegated_role.name.clone(),
             })?;
 
-        let path = if consistent_snapshot {
-            format!(
-                "{}.{}.json",
-                &role_meta.version,
-                encode_filename(&delegated_role.name)
-            )
-        } else {
-            format!("{}.json", encode_filename(&delegated_role.name))
-        };
+        let path = filename_encoding::role_filename(
+            filename_encoding,
+            &delegated_role.name,
+            consistent_snapshot.then_some(role_meta.version),
+        )?;
         let role_url = metadata_base_url
             .join(&path)
             .with_context(|_| error::JoinUrlSnafu {
                 path: path.clone(),
                 url: metadata_base_url.clone(),
             })?;
-        let specifier = "max_targets_size parameter";
-        // load the role json file
-        let stream =
-            fetch_max_size(transport, role_url.clone(), max_targets_size, specifier).await?;
-        let data = stream
-            .into_vec()
-            .await
-            .context(error::TransportSnafu { url: role_url })?;
+        // load the role json file, checking it against the length and hashes that snapshot.json
+        // lists for it, if any
+        let (max_role_size, specifier) = match role_meta.length {
+            Some(length) => (length, "snapshot.json"),
+            None => (max_targets_size, "max_targets_size parameter"),
+        };
+        policy.check_metadata_listing(role_meta.length, role_meta.hashes.as_ref(), &path)?;
+        let fetched = async {
+            let stream = if let Some(hashes) = &role_meta.hashes {
+                fetch_digests(
+                    transport,
+                    role_url.clone(),
+                    max_role_size,
+                    specifier,
+                    &policy.digests(hashes, &path)?,
+                )
+                .await?
+            } else {
+                fetch_max_size(transport, role_url.clone(), max_role_size, specifier).await?
+            };
+            stream.into_vec().await.context(error::TransportSnafu {
+                url: role_url.clone(),
+            })
+        }
+        .await;
+        let data = match fetched {
+            Ok(data) => data,
+            Err(error::Error::Transport { source, .. })
+                if source.kind() == TransportErrorKind::FileNotFound
+                    && missing_role_policy == MissingRolePolicy::SkipWithWarning =>
+            {
+                warn!(
+                    "Skipping delegated role '{}': {} was not found",
+                    delegated_role.name, role_url
+                );
+                delegated_roles.insert(delegated_role.name.clone(), None);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        sizes.record(
+            RoleType::DelegatedTargets,
+            &path,
+            data.len(),
+            role_meta.length.map_or(Some(max_role_size), |_| None),
+        );
         // since each role is a targets, we load them as such
         let role: Signed<crate::schema::Targets> =
             serde_json::from_slice(&data).context(error::ParseMetadataSnafu {
@@ -1265,18 +2682,33 @@ async fn load_delegations(
         delegation
             .verify_role(&role, &delegated_role.name)
             .context(error::VerifyMetadataSnafu {
-                role: RoleType::Targets,
+                role: RoleType::DelegatedTargets,
+                name: &delegated_role.name,
+                url: role_url.clone(),
+                version: role.signed.version,
             })?;
         ensure!(
             role.signed.version == role_meta.version,
             error::VersionMismatchSnafu {
-                role: RoleType::Targets,
+                role: RoleType::DelegatedTargets,
+                name: &delegated_role.name,
+                url: role_url.clone(),
                 fetched: role.signed.version,
                 expected: role_meta.version
             }
         );
 
-        datastore.create(&path, &role).await?;
+        datastore.write_bytes(&path, &data).await?;
+        observer.on_metadata_fetched(&delegated_role.name, role.signed.version, &data);
+        // Delegated roles aren't needed until a target is looked up, so their expiration is
+        // enforced by `read_target` rather than here.
+        expirations.push(RoleExpiration {
+            expires: role.signed.expires,
+            role: RoleType::DelegatedTargets,
+            name: delegated_role.name.clone(),
+            version: role.signed.version,
+            url: role_url,
+        });
         delegated_roles.insert(delegated_role.name.clone(), Some(role));
     }
     // load all roles delegated by this role
@@ -1289,16 +2721,25 @@ async fn load_delegations(
                 })?;
         if let Some(targets) = &mut delegated_role.targets {
             if let Some(delegations) = &mut targets.signed.delegations {
+                walk.descend(&delegated_role.name);
                 load_delegations(
                     transport,
                     snapshot,
                     consistent_snapshot,
                     metadata_base_url,
                     max_targets_size,
+                    policy,
+                    missing_role_policy,
                     delegations,
                     datastore,
+                    walk,
+                    observer,
+                    filename_encoding,
+                    sizes,
+                    expirations,
                 )
                 .await?;
+                walk.ascend();
             }
         }
     }
@@ -1339,6 +2780,51 @@ mod tests {
         assert_eq!(default, ExpirationEnforcement::Safe);
     }
 
+    #[test]
+    fn unhashed_targets_fail_or_are_skipped() {
+        let listed = |json: &str| -> schema::Target { serde_json::from_str(json).unwrap() };
+        let mut targets = schema::Targets::new("1.0.0".to_owned(), NonZeroU64::MIN, Utc::now());
+        targets.targets.insert(
+            TargetName::new("hashed.txt").unwrap(),
+            listed(r#"{"length":2,"hashes":{"sha256":"abcd"}}"#),
+        );
+        targets.targets.insert(
+            TargetName::new("sha512-only.txt").unwrap(),
+            listed(r#"{"length":2,"hashes":{"sha512":"abcd"}}"#),
+        );
+        targets.targets.insert(
+            TargetName::new("unhashed.txt").unwrap(),
+            listed(r#"{"length":2}"#),
+        );
+
+        let err =
+            remove_unhashed_targets(&mut targets.clone(), "targets", UnhashedTargetPolicy::Fail)
+                .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                error::Error::UnhashedTargetListed { name, role, .. }
+                    if name == "unhashed.txt" && role == "targets"
+            ),
+            "{}",
+            err
+        );
+
+        remove_unhashed_targets(
+            &mut targets,
+            "targets",
+            UnhashedTargetPolicy::SkipWithWarning,
+        )
+        .unwrap();
+        let mut kept = targets
+            .targets
+            .keys()
+            .map(TargetName::raw)
+            .collect::<Vec<_>>();
+        kept.sort_unstable();
+        assert_eq!(kept, ["hashed.txt", "sha512-only.txt"]);
+    }
+
     #[test]
     fn encode_filename_1() {
         let input = "../a";
@@ -0,0 +1,136 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Records how large the metadata fetched during a load actually was, so that integrators can set
+//! [`Limits`] from a real repository instead of guessing, and so that a repository that is growing
+//! towards a configured limit is noticed before loads start failing.
+
+use crate::schema::RoleType;
+use crate::Limits;
+use log::warn;
+
+/// A fetched file larger than this percentage of the limit it was fetched under is logged.
+const WARN_PERCENT: u64 = 80;
+
+/// Recommended limits are the observed sizes multiplied by this factor, to leave room for growth.
+const SAFETY_FACTOR: u64 = 2;
+
+/// The smallest size limit that [`MetadataSizes::recommended_limits`] will suggest.
+const MIN_RECOMMENDED_SIZE: u64 = 64 * 1024;
+
+/// The smallest `max_delegated_roles` that [`MetadataSizes::recommended_limits`] will suggest, so
+/// that a repository without delegations can still add a few.
+const MIN_RECOMMENDED_DELEGATED_ROLES: u64 = 16;
+
+/// The largest size, in bytes, of each kind of metadata file fetched while loading a repository.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetadataSizes {
+    /// The largest root.json, including the trusted root that the load started from.
+    pub root: u64,
+    /// The size of timestamp.json.
+    pub timestamp: u64,
+    /// The size of snapshot.json.
+    pub snapshot: u64,
+    /// The largest of targets.json and every delegated targets role.
+    pub targets: u64,
+    /// The number of delegated targets roles that were loaded.
+    pub delegated_roles: u64,
+}
+
+impl MetadataSizes {
+    /// Records that `file`, a `role` metadata file of `size` bytes, was fetched. `limit` is the
+    /// size limit taken from [`Limits`] for this fetch, or `None` if the limit came from signed
+    /// metadata, in which case the file is expected to be exactly that size.
+    pub(crate) fn record(&mut self, role: RoleType, file: &str, size: usize, limit: Option<u64>) {
+        let size = size as u64;
+        let largest = match role {
+            RoleType::Root => &mut self.root,
+            RoleType::Timestamp => &mut self.timestamp,
+            RoleType::Snapshot => &mut self.snapshot,
+            RoleType::Targets | RoleType::DelegatedTargets => &mut self.targets,
+        };
+        *largest = (*largest).max(size);
+        if role == RoleType::DelegatedTargets {
+            self.delegated_roles += 1;
+        }
+
+        if let Some(limit) = limit {
+            if size.saturating_mul(100) > limit.saturating_mul(WARN_PERCENT) {
+                warn!(
+                    "{} is {} bytes, more than {}% of the {} limit of {} bytes; consider raising \
+                    it (see `Repository::recommended_limits`)",
+                    file,
+                    size,
+                    WARN_PERCENT,
+                    limit_name(role),
+                    limit
+                );
+            }
+        }
+    }
+
+    /// Returns `Limits` that fit these sizes with room to grow. Limits that aren't about metadata
+    /// size or delegation count are copied from `current`.
+    pub fn recommended_limits(&self, current: &Limits) -> Limits {
+        let size = |observed: u64| {
+            observed
+                .saturating_mul(SAFETY_FACTOR)
+                .max(MIN_RECOMMENDED_SIZE)
+        };
+        Limits {
+            max_root_size: size(self.root),
+            max_targets_size: size(self.targets),
+            max_timestamp_size: size(self.timestamp),
+            max_snapshot_size: size(self.snapshot),
+            max_delegated_roles: self
+                .delegated_roles
+                .saturating_mul(SAFETY_FACTOR)
+                .max(MIN_RECOMMENDED_DELEGATED_ROLES),
+            ..*current
+        }
+    }
+}
+
+/// Returns the name of the `Limits` field that bounds fetches of `role` metadata.
+fn limit_name(role: RoleType) -> &'static str {
+    match role {
+        RoleType::Root => "max_root_size",
+        RoleType::Timestamp => "max_timestamp_size",
+        RoleType::Snapshot => "max_snapshot_size",
+        RoleType::Targets | RoleType::DelegatedTargets => "max_targets_size",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_keeps_largest() {
+        let mut sizes = MetadataSizes::default();
+        sizes.record(RoleType::Targets, "targets.json", 100, None);
+        sizes.record(RoleType::DelegatedTargets, "a.json", 300, None);
+        sizes.record(RoleType::DelegatedTargets, "b.json", 200, None);
+        assert_eq!(sizes.targets, 300);
+        assert_eq!(sizes.delegated_roles, 2);
+    }
+
+    #[test]
+    fn recommended_limits_scale_observed_sizes() {
+        let sizes = MetadataSizes {
+            root: 1024,
+            timestamp: 1024,
+            snapshot: 100 * 1024,
+            targets: 4 * 1024 * 1024,
+            delegated_roles: 20,
+        };
+        let current = Limits::default();
+        let limits = sizes.recommended_limits(&current);
+        assert_eq!(limits.max_root_size, MIN_RECOMMENDED_SIZE);
+        assert_eq!(limits.max_timestamp_size, MIN_RECOMMENDED_SIZE);
+        assert_eq!(limits.max_snapshot_size, 200 * 1024);
+        assert_eq!(limits.max_targets_size, 8 * 1024 * 1024);
+        assert_eq!(limits.max_delegated_roles, 40);
+        assert_eq!(limits.max_root_updates, current.max_root_updates);
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `MultiRepository`, which looks up targets across several repositories as described by
+//! [TAP 4], only returning a target when enough of the repositories agree on it.
+//!
+//! [TAP 4]: https://github.com/theupdateframework/taps/blob/master/tap4.md
+
+use crate::error::{self, Result};
+use crate::schema::{PathPattern, Target};
+use crate::{IntoVec, Repository, RepositoryLoader, TargetName};
+use bytes::Bytes;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+
+/// The contents of a TAP 4 `map.json` file, which assigns target paths to the repositories that
+/// must agree on them.
+///
+/// ```json
+/// {
+///   "repositories": {
+///     "Django": ["https://djangoproject.com/"],
+///     "PyPI": ["https://pypi.python.org/"]
+///   },
+///   "mapping": [
+///     {
+///       "paths": ["*django*"],
+///       "repositories": ["Django", "PyPI"],
+///       "terminating": true,
+///       "threshold": 2
+///     },
+///     {
+///       "paths": ["*"],
+///       "repositories": ["PyPI"]
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct MapFile {
+    /// The mirrors of each repository, by repository name. Each repository is loaded with the
+    /// [`RepositoryLoader`] given for its name, so these are informational.
+    pub repositories: HashMap<String, Vec<String>>,
+
+    /// The mappings to try for a target, in order.
+    pub mapping: Vec<Mapping>,
+}
+
+/// One entry of [`MapFile::mapping`].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Mapping {
+    /// The target paths that this mapping applies to, as glob patterns.
+    pub paths: Vec<PathPattern>,
+
+    /// The names of the repositories consulted for these targets.
+    pub repositories: Vec<String>,
+
+    /// Whether a target matching `paths` that isn't agreed on stops the search, rather than moving
+    /// on to the next mapping.
+    #[serde(default)]
+    pub terminating: bool,
+
+    /// How many of `repositories` must list a target with the same length and hashes.
+    #[serde(default = "Mapping::default_threshold")]
+    pub threshold: NonZeroU64,
+}
+
+impl Mapping {
+    fn default_threshold() -> NonZeroU64 {
+        NonZeroU64::MIN
+    }
+
+    fn matches(&self, name: &TargetName) -> bool {
+        self.paths
+            .iter()
+            .any(|pattern| pattern.matches_target_name(name))
+    }
+}
+
+/// Loads each repository named in a [`MapFile`] into a [`MultiRepository`].
+#[derive(Debug, Clone)]
+pub struct MultiRepositoryLoader<'a> {
+    map: MapFile,
+    loaders: HashMap<String, RepositoryLoader<'a>>,
+}
+
+impl<'a> MultiRepositoryLoader<'a> {
+    /// Create a new `MultiRepositoryLoader` for `map`. A loader must be given with
+    /// [`repository`](Self::repository) for each repository that `map` names.
+    pub fn new(map: MapFile) -> Self {
+        Self {
+            map,
+            loaders: HashMap::new(),
+        }
+    }
+
+    /// Set the loader for the repository `name`, which holds its trusted root and where to fetch
+    /// it from.
+    #[must_use]
+    pub fn repository<S: Into<String>>(mut self, name: S, loader: RepositoryLoader<'a>) -> Self {
+        self.loaders.insert(name.into(), loader);
+        self
+    }
+
+    /// Check the map, then load and verify every repository it names.
+    pub async fn load(mut self) -> Result<MultiRepository> {
+        for mapping in &self.map.mapping {
+            for (i, name) in mapping.repositories.iter().enumerate() {
+                ensure!(
+                    self.map.repositories.contains_key(name),
+                    error::MapUnknownRepositorySnafu { name }
+                );
+                ensure!(
+                    !mapping.repositories[..i].contains(name),
+                    error::MapDuplicateRepositorySnafu { name }
+                );
+            }
+            ensure!(
+                mapping.threshold.get() <= mapping.repositories.len() as u64,
+                error::MapThresholdSnafu {
+                    threshold: mapping.threshold,
+                    repositories: mapping.repositories.len(),
+                }
+            );
+        }
+
+        let mut names: Vec<&String> = self.map.repositories.keys().collect();
+        names.sort();
+        let mut loads = Vec::with_capacity(names.len());
+        for name in names {
+            let loader = self
+                .loaders
+                .remove(name)
+                .context(error::MapMissingLoaderSnafu { name })?;
+            loads.push(async move {
+                let repository = loader
+                    .load()
+                    .await
+                    .map_err(Box::new)
+                    .context(error::MapRepositoryLoadSnafu { name })?;
+                Ok::<_, error::Error>((name.clone(), repository))
+            });
+        }
+        let repositories = futures::future::try_join_all(loads)
+            .await?
+            .into_iter()
+            .collect();
+        Ok(MultiRepository {
+            map: self.map,
+            repositories,
+        })
+    }
+}
+
+/// Several repositories, loaded by a [`MultiRepositoryLoader`], that targets are looked up in
+/// according to a [`MapFile`].
+///
+/// A target is looked up with the first mapping whose paths match its name. If at least
+/// `threshold` of the mapping's repositories list the target with the same length and hashes,
+/// that target is used. Otherwise a terminating mapping ends the search, and a non-terminating
+/// one moves on to the next matching mapping.
+#[derive(Debug, Clone)]
+pub struct MultiRepository {
+    map: MapFile,
+    repositories: HashMap<String, Repository>,
+}
+
+impl MultiRepository {
+    /// The map that targets are looked up with.
+    pub fn map(&self) -> &MapFile {
+        &self.map
+    }
+
+    /// The repository loaded for `name`, if the map names it.
+    pub fn repository(&self, name: &str) -> Option<&Repository> {
+        self.repositories.get(name)
+    }
+
+    /// Returns the target metadata that the repositories agree on for `name`.
+    ///
+    /// `Ok(None)` is returned if no mapping settles on the target. A terminating mapping whose
+    /// repositories list the target, but not as many alike as its threshold, returns
+    /// [`error::Error::MapNoConsensus`].
+    pub fn target(&self, name: &TargetName) -> Result<Option<&Target>> {
+        Ok(self.find(name)?.map(|(_, target)| target))
+    }
+
+    /// Fetches a target agreed on by the repositories, as in [`target`](Self::target), from one
+    /// of the repositories that agreed on it. The stream is verified as in
+    /// [`Repository::read_target`].
+    pub async fn read_target(
+        &self,
+        name: &TargetName,
+    ) -> Result<Option<impl Stream<Item = error::Result<Bytes>> + IntoVec<error::Error> + Send>>
+    {
+        match self.find(name)? {
+            Some((repository, _)) => repository.read_target(name).await,
+            None => Ok(None),
+        }
+    }
+
+    fn find(&self, name: &TargetName) -> Result<Option<(&Repository, &Target)>> {
+        for mapping in self.map.mapping.iter().filter(|m| m.matches(name)) {
+            // Repositories that list the target, grouped by the length and hashes they list.
+            let mut groups: Vec<(&Target, Vec<&Repository>)> = Vec::new();
+            for repository in mapping
+                .repositories
+                .iter()
+                .filter_map(|repository| self.repositories.get(repository))
+            {
+                let Ok(target) = repository.targets().signed.find_target(name) else {
+                    continue;
+                };
+                match groups.iter_mut().find(|(listed, _)| {
+                    listed.length == target.length && listed.hashes.agrees_with(&target.hashes)
+                }) {
+                    Some((_, agreeing)) => agreeing.push(repository),
+                    None => groups.push((target, vec![repository])),
+                }
+            }
+            if let Some((target, agreeing)) = groups
+                .iter()
+                .find(|(_, agreeing)| agreeing.len() as u64 >= mapping.threshold.get())
+            {
+                return Ok(Some((agreeing[0], target)));
+            }
+            if mapping.terminating {
+                ensure!(
+                    groups.is_empty(),
+                    error::MapNoConsensusSnafu {
+                        name: name.raw(),
+                        threshold: mapping.threshold,
+                        agreeing: groups
+                            .iter()
+                            .map(|(_, agreeing)| agreeing.len())
+                            .max()
+                            .unwrap_or_default(),
+                    }
+                );
+                return Ok(None);
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `RepositoryObserver`, which is told about what happens while a repository is loaded
+//! and while its targets are read.
+
+use crate::error::{Error, Result};
+use crate::schema::{Root, Signed};
+use crate::TargetName;
+use bytes::Bytes;
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use std::fmt::Debug;
+use std::num::NonZeroU64;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use url::Url;
+
+/// Callbacks for the lifecycle of a repository, set with
+/// [`RepositoryLoader::observer`](crate::RepositoryLoader::observer), so that integrators can
+/// emit metrics or traces without wrapping the [`Transport`](crate::Transport).
+///
+/// Each method does nothing by default. Methods are called synchronously from the load or read
+/// they describe, so they should return quickly.
+pub trait RepositoryObserver: Debug + Send + Sync {
+    /// Called when a new version of root.json is trusted in place of `old`, whether it was fetched
+    /// or came from the [`trusted_root_chain`](crate::RepositoryLoader::trusted_root_chain). Any
+    /// [`RootUpdatePolicy`](crate::RootUpdatePolicy) has already accepted it.
+    fn on_root_update(&self, _old: &Signed<Root>, _new: &Signed<Root>) {}
+
+    /// Called when a metadata file has been fetched and has passed every check, with the name of
+    /// its role (such as `timestamp`, or the name of a delegated role), its version, and the bytes
+    /// that were fetched.
+    fn on_metadata_fetched(&self, _role: &str, _version: NonZeroU64, _bytes: &[u8]) {}
+
+    /// Called when a listed target starts being read by
+    /// [`Repository::read_target`](crate::Repository::read_target) or
+    /// [`Repository::resume_target`](crate::Repository::resume_target), including reads served
+    /// from a [`TargetCache`](crate::TargetCache).
+    fn on_target_fetch_start(&self, _name: &TargetName) {}
+
+    /// Called once a read that [`on_target_fetch_start`](Self::on_target_fetch_start) was called
+    /// for finishes: with the number of bytes read if every length and hash check passed, or with
+    /// the error that ended it. Nothing is called if the stream is dropped before it ends.
+    fn on_target_fetch_finish(
+        &self,
+        _name: &TargetName,
+        _result: std::result::Result<u64, &Error>,
+    ) {
+    }
+
+    /// Called when more of a target is served from `url` than the `length` listed for it. The read
+    /// is abandoned as soon as the listed length is passed, so `received` is the number of bytes
+    /// received by then, which is at least `length + 1` but may be much less than what was being
+    /// served. [`on_target_fetch_finish`](Self::on_target_fetch_finish) is then called with the
+    /// error.
+    fn on_target_length_exceeded(
+        &self,
+        _name: &TargetName,
+        _url: &Url,
+        _length: u64,
+        _received: u64,
+    ) {
+    }
+
+    /// Called at the end of a load for each loaded role that expires within
+    /// [`expiration_warning_window`](Self::expiration_warning_window) of the current time, or has
+    /// already expired, which only loads under
+    /// [`ExpirationEnforcement::Unsafe`](crate::ExpirationEnforcement::Unsafe).
+    fn on_expiration_warning(&self, _role: &str, _version: NonZeroU64, _expires: DateTime<Utc>) {}
+
+    /// How soon before a role expires [`on_expiration_warning`](Self::on_expiration_warning) is
+    /// called for it. The default is zero, so it's only called for roles that have expired.
+    fn expiration_warning_window(&self) -> TimeDelta {
+        TimeDelta::zero()
+    }
+}
+
+/// The observer used when none is set.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NoopObserver;
+
+impl RepositoryObserver for NoopObserver {}
+
+/// Wraps the contents of a target so that `observer` is told when reading them finishes.
+pub(crate) fn observe_target(
+    observer: Arc<dyn RepositoryObserver>,
+    name: TargetName,
+    stream: BoxStream<'static, Result<Bytes>>,
+) -> BoxStream<'static, Result<Bytes>> {
+    ObservedTarget {
+        stream,
+        observer: Some(observer),
+        name,
+        received: 0,
+    }
+    .boxed()
+}
+
+struct ObservedTarget {
+    stream: BoxStream<'static, Result<Bytes>>,
+    /// `None` once the observer has been told that the read finished.
+    observer: Option<Arc<dyn RepositoryObserver>>,
+    name: TargetName,
+    received: u64,
+}
+
+impl Stream for ObservedTarget {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let item = ready!(this.stream.poll_next_unpin(cx));
+        match &item {
+            Some(Ok(chunk)) => this.received += chunk.len() as u64,
+            Some(Err(err)) => {
+                if let Some(observer) = this.observer.take() {
+                    observer.on_target_fetch_finish(&this.name, Err(err));
+                }
+            }
+            None => {
+                if let Some(observer) = this.observer.take() {
+                    observer.on_target_fetch_finish(&this.name, Ok(this.received));
+                }
+            }
+        }
+        Poll::Ready(item)
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `OfflineTransport`, which serves metadata from the datastore for loads made with
+//! [`RepositoryLoader::offline`](crate::RepositoryLoader::offline).
+//!
+//! Each load writes the metadata files it trusts to the datastore exactly as they were fetched:
+//! every root it updates through as `N.root.json`, and the current timestamp, snapshot, targets
+//! and delegated targets metadata. An offline load verifies those files again, in the standard
+//! order, without fetching anything.
+
+use crate::datastore::Datastore;
+use crate::filename_encoding::decode_non_ascii;
+use crate::transport::{Transport, TransportError, TransportErrorKind, TransportStream};
+use async_trait::async_trait;
+use futures::StreamExt;
+use url::Url;
+
+/// A `Transport` that serves files under the metadata base URL from the datastore, and refuses
+/// every other URL, such as those of targets.
+#[derive(Debug, Clone)]
+pub(crate) struct OfflineTransport {
+    datastore: Datastore,
+    metadata_base_url: Url,
+}
+
+impl OfflineTransport {
+    /// `metadata_base_url` must end with a slash.
+    pub(crate) fn new(datastore: Datastore, metadata_base_url: Url) -> Self {
+        Self {
+            datastore,
+            metadata_base_url,
+        }
+    }
+
+    /// The datastore keeps snapshot and targets metadata under their unversioned names, so a
+    /// consistent snapshot name such as `3.snapshot.json` is served from `snapshot.json`. The
+    /// loader still checks that the version matches the one requested.
+    fn unversioned(name: &str) -> Option<&str> {
+        let (version, rest) = name.split_once('.')?;
+        (version.parse::<u64>().is_ok() && (rest == "snapshot.json" || rest == "targets.json"))
+            .then_some(rest)
+    }
+}
+
+#[async_trait]
+impl Transport for OfflineTransport {
+    async fn fetch(&self, url: Url) -> Result<TransportStream, TransportError> {
+        let Some(name) = url
+            .as_str()
+            .strip_prefix(self.metadata_base_url.as_str())
+            .filter(|name| !name.is_empty() && !name.contains('/'))
+        else {
+            return Err(TransportError::new_with_cause(
+                TransportErrorKind::Other,
+                url,
+                "the repository was loaded offline, so only metadata in the datastore is available",
+            ));
+        };
+        let mut data = self.datastore.bytes(name).await;
+        // Non-ASCII characters in a filename are percent-encoded in the URL.
+        if let (Ok(None), Some(decoded)) = (&data, decode_non_ascii(name)) {
+            data = self.datastore.bytes(&decoded).await;
+        }
+        if let (Ok(None), Some(unversioned)) = (&data, Self::unversioned(name)) {
+            data = self.datastore.bytes(unversioned).await;
+        }
+        match data {
+            Ok(Some(data)) => {
+                Ok(futures::stream::once(futures::future::ready(Ok(data.into()))).boxed())
+            }
+            Ok(None) => Err(TransportError::new_with_cause(
+                TransportErrorKind::FileNotFound,
+                url,
+                "not in the datastore",
+            )),
+            Err(err) => Err(TransportError::new_with_cause(
+                TransportErrorKind::Other,
+                url,
+                err,
+            )),
+        }
+    }
+}
@@ -0,0 +1,434 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `VerificationPolicy`, which lets a client demand more of the hashes and lengths listed
+//! for the files it fetches than the TUF specification requires, and `RootUpdatePolicy`, which
+//! lets it demand more of each new version of root.json.
+
+use crate::error::{self, Result};
+use crate::schema::{Hashes, Root, Signed};
+use aws_lc_rs::digest::{Algorithm, SHA256, SHA512};
+use snafu::ensure;
+use std::fmt::{self, Debug, Formatter};
+
+/// A hash algorithm that can be listed for a target or metadata file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum HashAlgorithm {
+    /// SHA-256, which tough always checks when a hash is listed.
+    #[default]
+    Sha256,
+    /// SHA-512.
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// Every algorithm, weakest first.
+    pub const ALL: [HashAlgorithm; 2] = [HashAlgorithm::Sha256, HashAlgorithm::Sha512];
+
+    /// The key for this algorithm in a `hashes` object.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    pub(crate) fn algorithm(self) -> &'static Algorithm {
+        match self {
+            HashAlgorithm::Sha256 => &SHA256,
+            HashAlgorithm::Sha512 => &SHA512,
+        }
+    }
+}
+
+/// A set of [`HashAlgorithm`]s, such as the algorithms a [`VerificationPolicy`] allows.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HashAlgorithms(u8);
+
+impl HashAlgorithms {
+    /// Every algorithm in [`HashAlgorithm`].
+    pub const ALL: HashAlgorithms =
+        HashAlgorithms::only(HashAlgorithm::Sha256).with(HashAlgorithm::Sha512);
+
+    /// The set containing only `algorithm`.
+    pub const fn only(algorithm: HashAlgorithm) -> Self {
+        Self(1 << algorithm as u8)
+    }
+
+    /// This set, with `algorithm` added.
+    #[must_use]
+    pub const fn with(self, algorithm: HashAlgorithm) -> Self {
+        Self(self.0 | Self::only(algorithm).0)
+    }
+
+    /// Whether `algorithm` is in this set.
+    pub const fn contains(self, algorithm: HashAlgorithm) -> bool {
+        self.0 & Self::only(algorithm).0 != 0
+    }
+}
+
+impl Default for HashAlgorithms {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl Debug for HashAlgorithms {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(
+                HashAlgorithm::ALL
+                    .iter()
+                    .filter(|algorithm| self.contains(**algorithm)),
+            )
+            .finish()
+    }
+}
+
+/// Requirements on the hashes and lengths listed for fetched files, set with
+/// [`RepositoryLoader::verification_policy`](crate::RepositoryLoader::verification_policy).
+///
+/// The default policy is what the TUF specification requires: files are checked against their
+/// listed length and every listed hash whose algorithm is in [`HashAlgorithm`], strongest first.
+/// Targets always list a length and at least one hash, while snapshot, targets and delegated targets
+/// metadata are checked against whatever length and hashes the timestamp and snapshot metadata
+/// list for them.
+///
+/// The policy applies alike to the snapshot metadata listed in the timestamp metadata, to the
+/// targets and delegated targets metadata listed in the snapshot metadata, and to targets.
+///
+/// More requirements may be added, so `VerificationPolicy` can't be built from a struct literal
+/// outside this crate. Start from [`VerificationPolicy::default`] and change the requirements that
+/// matter with the setters, such as [`VerificationPolicy::minimum_hash`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VerificationPolicy {
+    /// Fail if a `sha512` hash listed for a file can't be checked because it isn't a hex string,
+    /// rather than checking only the other hashes.
+    pub require_sha512_if_listed: bool,
+    /// The weakest hash a listing may offer. With [`HashAlgorithm::Sha512`], a file is only
+    /// fetched if a `sha512` hash is listed for it, and that hash is checked.
+    pub minimum_hash: HashAlgorithm,
+    /// Fail to load if the timestamp or snapshot metadata doesn't list hashes for the snapshot,
+    /// targets or delegated targets metadata, rather than relying on the signature alone.
+    pub require_metadata_hashes: bool,
+    /// Fail to load if the timestamp or snapshot metadata doesn't list the length of the snapshot,
+    /// targets or delegated targets metadata, rather than falling back to the size in
+    /// [`Limits`](crate::Limits).
+    pub require_metadata_length: bool,
+    /// The algorithms whose listed hashes are checked. Hashes listed with other algorithms are
+    /// ignored, and a file is only fetched if a hash is listed for one of these algorithms that
+    /// is at least [`minimum_hash`](Self::minimum_hash).
+    pub allowed_hashes: HashAlgorithms,
+}
+
+impl VerificationPolicy {
+    /// Set `require_sha512_if_listed`.
+    #[must_use]
+    pub fn require_sha512_if_listed(mut self, require_sha512_if_listed: bool) -> Self {
+        self.require_sha512_if_listed = require_sha512_if_listed;
+        self
+    }
+
+    /// Set `minimum_hash`.
+    #[must_use]
+    pub fn minimum_hash(mut self, minimum_hash: HashAlgorithm) -> Self {
+        self.minimum_hash = minimum_hash;
+        self
+    }
+
+    /// Set `require_metadata_hashes`.
+    #[must_use]
+    pub fn require_metadata_hashes(mut self, require_metadata_hashes: bool) -> Self {
+        self.require_metadata_hashes = require_metadata_hashes;
+        self
+    }
+
+    /// Set `require_metadata_length`.
+    #[must_use]
+    pub fn require_metadata_length(mut self, require_metadata_length: bool) -> Self {
+        self.require_metadata_length = require_metadata_length;
+        self
+    }
+
+    /// Set `allowed_hashes`.
+    #[must_use]
+    pub fn allowed_hashes(mut self, allowed_hashes: HashAlgorithms) -> Self {
+        self.allowed_hashes = allowed_hashes;
+        self
+    }
+
+    /// Returns each digest that the file `context`, listed with `hashes`, must match, strongest
+    /// first.
+    pub(crate) fn digests(
+        self,
+        hashes: &Hashes,
+        context: &str,
+    ) -> Result<Vec<(HashAlgorithm, Vec<u8>)>> {
+        let mut digests = Vec::new();
+        for algorithm in hashes.algorithms() {
+            if !self.allowed_hashes.contains(algorithm) {
+                continue;
+            }
+            let required = match algorithm {
+                HashAlgorithm::Sha256 => true,
+                HashAlgorithm::Sha512 => {
+                    self.require_sha512_if_listed || self.minimum_hash >= HashAlgorithm::Sha512
+                }
+            };
+            match hashes.digest(algorithm) {
+                Ok(Some(digest)) => digests.push((algorithm, digest)),
+                Ok(None) => {}
+                Err(_) => ensure!(
+                    !required,
+                    error::InvalidListedHashSnafu {
+                        context,
+                        algorithm: algorithm.name(),
+                    }
+                ),
+            }
+        }
+        ensure!(
+            digests
+                .iter()
+                .any(|(algorithm, _)| *algorithm >= self.minimum_hash),
+            error::HashMissingSnafu {
+                context,
+                algorithm: self.required_hash().name(),
+            }
+        );
+        Ok(digests)
+    }
+
+    /// The weakest algorithm whose hash satisfies the policy.
+    fn required_hash(self) -> HashAlgorithm {
+        HashAlgorithm::ALL
+            .iter()
+            .copied()
+            .find(|algorithm| {
+                *algorithm >= self.minimum_hash && self.allowed_hashes.contains(*algorithm)
+            })
+            .unwrap_or(self.minimum_hash)
+    }
+
+    /// Checks that a metadata file listed with `length` and `hashes` meets the policy.
+    pub(crate) fn check_metadata_listing(
+        self,
+        length: Option<u64>,
+        hashes: Option<&Hashes>,
+        context: &str,
+    ) -> Result<()> {
+        ensure!(
+            length.is_some() || !self.require_metadata_length,
+            error::MetadataLengthMissingSnafu { context }
+        );
+        ensure!(
+            hashes.is_some() || !self.require_metadata_hashes,
+            error::HashMissingSnafu {
+                context,
+                algorithm: HashAlgorithm::Sha256.name(),
+            }
+        );
+        let required = self.required_hash();
+        ensure!(
+            hashes.is_some() || required == HashAlgorithm::Sha256,
+            error::HashMissingSnafu {
+                context,
+                algorithm: required.name(),
+            }
+        );
+        Ok(())
+    }
+}
+
+/// Extra requirements on root.json updates, set with
+/// [`RepositoryLoader::root_update_policy`](crate::RepositoryLoader::root_update_policy).
+///
+/// The loader calls `check` for each new version of root.json once it has verified that the new
+/// root is signed by a threshold of both roots' root keys and is the next version, and before it
+/// trusts the new root. This includes roots from
+/// [`RepositoryLoader::trusted_root_chain`](crate::RepositoryLoader::trusted_root_chain).
+///
+/// A closure can be used as a policy by wrapping it in [`RootUpdateFn`].
+pub trait RootUpdatePolicy: Debug + Send + Sync {
+    /// Returns an error if `new` may not replace `old`, the currently trusted root. The error
+    /// fails the load with [`Error::RootUpdateRejected`](crate::error::Error::RootUpdateRejected).
+    fn check(
+        &self,
+        old: &Signed<Root>,
+        new: &Signed<Root>,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
+}
+
+/// A [`RootUpdatePolicy`] that calls a closure with the old and new roots.
+///
+/// ```
+/// # use tough::RootUpdateFn;
+/// # use tough::schema::{RoleType, Root, Signed};
+/// type CheckResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+///
+/// let threshold = |root: &Signed<Root>| {
+///     root.signed.roles.get(&RoleType::Root).map(|keys| keys.threshold)
+/// };
+/// let policy = RootUpdateFn(move |old: &Signed<Root>, new: &Signed<Root>| -> CheckResult {
+///     if threshold(new) < threshold(old) {
+///         return Err("the root threshold may not decrease".into());
+///     }
+///     Ok(())
+/// });
+/// ```
+#[derive(Clone, Copy)]
+pub struct RootUpdateFn<F>(pub F);
+
+impl<F> Debug for RootUpdateFn<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RootUpdateFn").finish_non_exhaustive()
+    }
+}
+
+impl<F> RootUpdatePolicy for RootUpdateFn<F>
+where
+    F: Fn(
+            &Signed<Root>,
+            &Signed<Root>,
+        ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>
+        + Send
+        + Sync,
+{
+    fn check(
+        &self,
+        old: &Signed<Root>,
+        new: &Signed<Root>,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        (self.0)(old, new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    fn hashes(sha512: Option<&str>) -> Hashes {
+        listed(Some(vec![1, 2]), sha512)
+    }
+
+    fn listed(sha256: Option<Vec<u8>>, sha512: Option<&str>) -> Hashes {
+        let mut extra = HashMap::new();
+        if let Some(sha512) = sha512 {
+            extra.insert("sha512".to_owned(), Value::String(sha512.to_owned()));
+        }
+        Hashes {
+            sha256: sha256.unwrap_or_default().into(),
+            _extra: extra,
+        }
+    }
+
+    #[test]
+    fn default_policy_checks_listed_hashes() {
+        let digests = VerificationPolicy::default()
+            .digests(&hashes(Some("0304")), "file")
+            .unwrap();
+        assert_eq!(
+            digests,
+            vec![
+                (HashAlgorithm::Sha512, vec![3, 4]),
+                (HashAlgorithm::Sha256, vec![1, 2])
+            ]
+        );
+        let digests = VerificationPolicy::default()
+            .digests(&hashes(None), "file")
+            .unwrap();
+        assert_eq!(digests, vec![(HashAlgorithm::Sha256, vec![1, 2])]);
+    }
+
+    #[test]
+    fn sha512_checked_if_listed() {
+        let policy = VerificationPolicy::default().require_sha512_if_listed(true);
+        assert_eq!(
+            policy.digests(&hashes(Some("0304")), "file").unwrap()[0],
+            (HashAlgorithm::Sha512, vec![3, 4])
+        );
+        assert_eq!(policy.digests(&hashes(None), "file").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn undecodable_sha512_skipped_unless_required() {
+        let mut listed = hashes(None);
+        listed
+            ._extra
+            .insert("sha512".to_owned(), serde_json::json!({"value": "0304"}));
+        assert_eq!(
+            VerificationPolicy::default()
+                .digests(&listed, "file")
+                .unwrap()
+                .len(),
+            1
+        );
+        let policy = VerificationPolicy::default().require_sha512_if_listed(true);
+        let err = policy.digests(&listed, "file").unwrap_err();
+        assert!(
+            matches!(err, error::Error::InvalidListedHash { .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn minimum_sha512_requires_listing() {
+        let policy = VerificationPolicy::default().minimum_hash(HashAlgorithm::Sha512);
+        let err = policy.digests(&hashes(None), "file").unwrap_err();
+        assert!(matches!(err, error::Error::HashMissing { .. }), "{}", err);
+        assert!(policy
+            .check_metadata_listing(Some(1), None, "file")
+            .is_err());
+    }
+
+    #[test]
+    fn sha512_only_listing_is_checked() {
+        let digests = VerificationPolicy::default()
+            .digests(&listed(None, Some("0304")), "file")
+            .unwrap();
+        assert_eq!(digests, vec![(HashAlgorithm::Sha512, vec![3, 4])]);
+    }
+
+    #[test]
+    fn disallowed_hashes_ignored() {
+        let policy = VerificationPolicy::default()
+            .allowed_hashes(HashAlgorithms::only(HashAlgorithm::Sha256));
+        assert_eq!(
+            policy.digests(&hashes(Some("0304")), "file").unwrap(),
+            vec![(HashAlgorithm::Sha256, vec![1, 2])]
+        );
+        let err = policy
+            .digests(&listed(None, Some("0304")), "file")
+            .unwrap_err();
+        assert!(
+            matches!(err, error::Error::HashMissing { ref algorithm, .. } if algorithm == "sha256"),
+            "{}",
+            err
+        );
+
+        let policy = VerificationPolicy::default()
+            .allowed_hashes(HashAlgorithms::only(HashAlgorithm::Sha512));
+        let err = policy.digests(&hashes(None), "file").unwrap_err();
+        assert!(
+            matches!(err, error::Error::HashMissing { ref algorithm, .. } if algorithm == "sha512"),
+            "{}",
+            err
+        );
+        assert!(policy
+            .check_metadata_listing(Some(1), None, "file")
+            .is_err());
+    }
+
+    #[test]
+    fn hash_algorithm_sets() {
+        let sha256 = HashAlgorithms::only(HashAlgorithm::Sha256);
+        assert!(sha256.contains(HashAlgorithm::Sha256));
+        assert!(!sha256.contains(HashAlgorithm::Sha512));
+        assert_eq!(sha256.with(HashAlgorithm::Sha512), HashAlgorithms::ALL);
+        assert_eq!(format!("{:?}", HashAlgorithms::ALL), "{Sha256, Sha512}");
+    }
+}
@@ -1,10 +1,17 @@
 use crate::schema::decoded::{Decoded, Hex};
 use crate::schema::error;
 use crate::schema::key::Key;
+#[cfg(feature = "strict-schema")]
+use crate::schema::Hashes;
+use crate::schema::{RoleKeys, RoleType, Root, Signature};
+use chrono::{DateTime, Utc};
 use serde::{de::Error as _, Deserialize, Deserializer};
+use serde_json::Value;
 use snafu::ensure;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt;
+use std::num::NonZeroU64;
 
 /// Validates the key ID for each key during deserialization and fails if any don't match.
 pub(super) fn deserialize_keys<'de, D>(
@@ -16,6 +23,7 @@ where
     // An inner function that does actual key ID validation:
     // * fails if a key ID doesn't match its contents
     // * fails if there is a duplicate key ID
+    // * fails if another key ID has the same public key
     // If this passes we insert the entry.
     fn validate_and_insert_entry(
         keyid: Decoded<Hex>,
@@ -31,6 +39,16 @@ where
                 calculated: hex::encode(&calculated),
             }
         );
+        if let Some((other, _)) = map
+            .iter()
+            .find(|(other, existing)| **other != keyid && existing.public_key() == key.public_key())
+        {
+            // Report the pair in a stable order, since map iteration order isn't
+            let mut pair = [keyid_hex, hex::encode(other)];
+            pair.sort();
+            let [keyid, other] = pair;
+            return error::DuplicatePublicKeySnafu { keyid, other }.fail();
+        }
         ensure!(
             map.insert(keyid, key).is_none(),
             error::DuplicateKeyIdSnafu { keyid: keyid_hex }
@@ -63,6 +81,22 @@ where
     deserializer.deserialize_map(Visitor)
 }
 
+/// Fails if a role lists the same key ID more than once.
+pub(super) fn deserialize_keyids<'de, D>(deserializer: D) -> Result<Vec<Decoded<Hex>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let keyids = Vec::<Decoded<Hex>>::deserialize(deserializer)?;
+    for (i, keyid) in keyids.iter().enumerate() {
+        if keyids[..i].contains(keyid) {
+            return Err(D::Error::custom(error::Error::DuplicateKeyId {
+                keyid: hex::encode(keyid),
+            }));
+        }
+    }
+    Ok(keyids)
+}
+
 /// Deserializes the `_extra` field on roles, skipping the `_type` tag.
 pub(super) fn extra_skip_type<'de, D>(
     deserializer: D,
@@ -75,6 +109,138 @@ where
     Ok(map)
 }
 
+/// The roles tough manages, and the roles it doesn't as they were found.
+type SplitRoles = (HashMap<RoleType, RoleKeys>, HashMap<String, Value>);
+
+/// The fields of a `Root` as found in metadata.
+#[derive(Deserialize)]
+#[serde(tag = "_type")]
+#[serde(rename = "root")]
+pub(super) struct RootFields {
+    spec_version: String,
+    consistent_snapshot: bool,
+    version: NonZeroU64,
+    expires: DateTime<Utc>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    keys: HashMap<Decoded<Hex>, Key>,
+    #[serde(deserialize_with = "deserialize_roles")]
+    roles: SplitRoles,
+    #[serde(flatten)]
+    #[serde(deserialize_with = "extra_skip_type")]
+    _extra: HashMap<String, Value>,
+}
+
+impl From<RootFields> for Root {
+    fn from(fields: RootFields) -> Self {
+        let (roles, unrecognized_roles) = fields.roles;
+        Root {
+            spec_version: fields.spec_version,
+            consistent_snapshot: fields.consistent_snapshot,
+            version: fields.version,
+            expires: fields.expires,
+            keys: fields.keys,
+            roles,
+            unrecognized_roles,
+            _extra: fields._extra,
+        }
+    }
+}
+
+/// Deserializes the `roles` of a root, splitting the roles tough manages from the roles it
+/// doesn't, which are kept as they were found.
+fn deserialize_roles<'de, D>(deserializer: D) -> Result<SplitRoles, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct Visitor;
+
+    impl<'de> serde::de::Visitor<'de> for Visitor {
+        type Value = SplitRoles;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter.write_str("a map")
+        }
+
+        fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+        where
+            M: serde::de::MapAccess<'de>,
+        {
+            let mut roles = HashMap::new();
+            let mut unrecognized_roles = HashMap::new();
+            while let Some(name) = access.next_key::<String>()? {
+                if let Ok(role) = name.parse::<RoleType>() {
+                    roles.insert(role, access.next_value()?);
+                } else {
+                    unrecognized_roles.insert(name, access.next_value()?);
+                }
+            }
+            Ok((roles, unrecognized_roles))
+        }
+    }
+
+    deserializer.deserialize_map(Visitor)
+}
+
+/// The fields of a `Signature` as found in metadata, before `sig` is decoded.
+#[derive(Deserialize)]
+pub(super) struct SignatureFields {
+    keyid: Decoded<Hex>,
+    sig: String,
+    #[serde(flatten)]
+    _extra: HashMap<String, Value>,
+}
+
+impl TryFrom<SignatureFields> for Signature {
+    type Error = error::Error;
+
+    fn try_from(fields: SignatureFields) -> Result<Self, Self::Error> {
+        let SignatureFields { keyid, sig, _extra } = fields;
+        #[cfg(feature = "strict-schema")]
+        if let Some(field) = _extra.keys().next() {
+            return error::UnknownFieldSnafu {
+                object: "signature",
+                field,
+            }
+            .fail();
+        }
+        let sig = if cfg!(feature = "strict-schema") {
+            sig.parse()?
+        } else {
+            sig.parse().unwrap_or_else(|_| Decoded::undecoded(sig))
+        };
+        Ok(Signature { keyid, sig, _extra })
+    }
+}
+
+/// The fields of a `Hashes` as found in metadata, before the hashes for other algorithms are
+/// checked to be hex strings.
+#[cfg(feature = "strict-schema")]
+#[derive(Deserialize)]
+pub(super) struct HashesFields {
+    #[serde(default = "super::unlisted_digest")]
+    sha256: Decoded<Hex>,
+    #[serde(flatten)]
+    _extra: HashMap<String, Value>,
+}
+
+#[cfg(feature = "strict-schema")]
+impl TryFrom<HashesFields> for Hashes {
+    type Error = error::Error;
+
+    fn try_from(fields: HashesFields) -> Result<Self, Self::Error> {
+        for (algorithm, hash) in &fields._extra {
+            ensure!(
+                hash.as_str().is_some_and(|hash| hex::decode(hash).is_ok()),
+                error::HashEncodingSnafu { algorithm }
+            );
+        }
+        Ok(Hashes {
+            sha256: fields.sha256,
+            _extra: fields._extra,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::schema::{Root, Signed};
@@ -87,6 +253,57 @@ mod tests {
         .is_err());
     }
 
+    #[test]
+    fn duplicate_public_key() {
+        let mut root: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/data/simple-rsa/root.json")).unwrap();
+        let keys = root["signed"]["keys"].as_object_mut().unwrap();
+        // The same key with an extra field has a different key ID
+        let mut key = keys.values().next().unwrap().clone();
+        key["x-note"] = "copy".into();
+        let keyid = serde_json::from_value::<crate::schema::key::Key>(key.clone())
+            .unwrap()
+            .key_id()
+            .unwrap();
+        keys.insert(hex::encode(keyid), key);
+        let err = serde_json::from_value::<Signed<Root>>(root).unwrap_err();
+        assert!(err.to_string().contains("same public key"), "{}", err);
+    }
+
+    /// A role tough doesn't manage is kept as it was found, and serialized back into `roles`.
+    #[test]
+    fn unrecognized_role_round_trip() {
+        let mut root: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/data/simple-rsa/root.json")).unwrap();
+        let keyid = root["signed"]["roles"]["root"]["keyids"][0].clone();
+        let mirrors = serde_json::json!({
+            "keyids": [keyid],
+            "threshold": 1,
+            "mirror_hint": {"region": "us-west-2"}
+        });
+        root["signed"]["roles"]["mirrors"] = mirrors.clone();
+
+        let parsed = serde_json::from_value::<Signed<Root>>(root.clone()).unwrap();
+        assert_eq!(parsed.signed.roles.len(), 4);
+        assert_eq!(parsed.signed.unrecognized_roles["mirrors"], mirrors);
+        assert!(parsed
+            .signed
+            .unrecognized_role_lists_key(&serde_json::from_value(keyid).unwrap()));
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), root);
+    }
+
+    #[test]
+    fn duplicate_role_keyid() {
+        let mut root: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/data/simple-rsa/root.json")).unwrap();
+        let keyids = root["signed"]["roles"]["root"]["keyids"]
+            .as_array_mut()
+            .unwrap();
+        keyids.push(keyids[0].clone());
+        let err = serde_json::from_value::<Signed<Root>>(root).unwrap_err();
+        assert!(err.to_string().contains("Duplicate key ID"), "{}", err);
+    }
+
     /// Ensure that we can deserialize a root.json file that has hex-encoded ECDSA keys. This uses
     /// sigstore's root.json file taken from here:
     /// `<https://sigstore-tuf-root.storage.googleapis.com/2.root.json>`
@@ -118,4 +335,38 @@ mod tests {
         ))
         .is_ok());
     }
+
+    /// Ensure that a signature with a field or encoding from a later version of the specification
+    /// is preserved rather than failing the whole file.
+    #[test]
+    #[cfg(not(feature = "strict-schema"))]
+    fn future_signature_fields_round_trip() {
+        let json = serde_json::json!({
+            "keyid": "abcd",
+            "sig": "bm90IGhleA==",
+            "encoding": "base64"
+        });
+        let sig: crate::schema::Signature = serde_json::from_value(json.clone()).unwrap();
+        assert!(sig.sig.is_empty());
+        assert_eq!(serde_json::to_value(&sig).unwrap(), json);
+    }
+
+    #[test]
+    #[cfg(feature = "strict-schema")]
+    fn strict_signature_fields() {
+        use crate::schema::{Hashes, Signature};
+
+        assert!(serde_json::from_value::<Signature>(
+            serde_json::json!({"keyid": "abcd", "sig": "abcd", "encoding": "hex"})
+        )
+        .is_err());
+        assert!(serde_json::from_value::<Signature>(
+            serde_json::json!({"keyid": "abcd", "sig": "zz"})
+        )
+        .is_err());
+        assert!(serde_json::from_value::<Hashes>(
+            serde_json::json!({"sha256": "abcd", "sha512": {"value": "abcd"}})
+        )
+        .is_err());
+    }
 }
@@ -34,6 +34,18 @@ impl<T> Decoded<T> {
     }
 }
 
+impl<T> Decoded<T> {
+    /// Keeps a string that couldn't be decoded, so that it is serialized unchanged. It decodes to
+    /// no bytes.
+    pub(crate) fn undecoded(original: String) -> Self {
+        Self {
+            bytes: Vec::new(),
+            original,
+            spooky: PhantomData,
+        }
+    }
+}
+
 impl<T: Encode> From<Vec<u8>> for Decoded<T> {
     fn from(b: Vec<u8>) -> Self {
         let original = T::encode(&b);
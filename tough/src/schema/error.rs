@@ -21,10 +21,23 @@ pub enum Error {
     #[snafu(display("Duplicate key ID: {}", keyid))]
     DuplicateKeyId { keyid: String },
 
+    /// The same public key was present under two key IDs, so one signer could count twice
+    /// towards a threshold.
+    #[snafu(display("Key IDs {} and {} have the same public key", keyid, other))]
+    DuplicatePublicKey { keyid: String, other: String },
+
     /// A duplicate role was present in the delegations metadata.
     #[snafu(display("Duplicate role name: {}", name))]
     DuplicateRoleName { name: String },
 
+    /// A field the schema doesn't know was found with the `strict-schema` feature enabled.
+    #[snafu(display("Unknown field '{}' in {}", field, object))]
+    UnknownField {
+        object: &'static str,
+        field: String,
+        backtrace: Backtrace,
+    },
+
     /// Unable to open a file
     #[snafu(display("Failed to open '{}': {}", path.display(), source))]
     FileOpen {
@@ -57,6 +70,13 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// A hash for an algorithm other than SHA-256 isn't a hex string.
+    #[snafu(display("The {} hash is not a hex string", algorithm))]
+    HashEncoding {
+        algorithm: String,
+        backtrace: Backtrace,
+    },
+
     /// Failed to decode a hexadecimal-encoded string.
     #[snafu(display("Invalid hex string: {}", source))]
     HexDecode {
@@ -72,6 +92,18 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    /// An extension field's value didn't deserialize into the type it was requested as.
+    #[snafu(display("Failed to parse extension field '{}': {}", name, source))]
+    ExtensionParse {
+        name: String,
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
+    /// An extension field was given the name of one of the fields TUF defines.
+    #[snafu(display("'{}' is a TUF field and can't be used as an extension field", name))]
+    ReservedField { name: String, backtrace: Backtrace },
+
     /// A required role is missing from the root metadata file.
     #[snafu(display("Role {} missing from root metadata", role))]
     MissingRole {
@@ -108,6 +140,26 @@ pub enum Error {
     #[snafu(display("TUF targets must be files, given: '{}'", path.display()))]
     TargetNotAFile { path: PathBuf, backtrace: Backtrace },
 
+    /// A value in a target's `custom` metadata doesn't follow tough's convention for that key.
+    #[snafu(display("Invalid '{}' in target's custom metadata: expected {}", key, expected))]
+    InvalidTargetCustom {
+        key: &'static str,
+        expected: &'static str,
+        backtrace: Backtrace,
+    },
+
+    /// A path hash prefix wasn't a hex string.
+    #[snafu(display("Path hash prefix '{}' is not a hex string", prefix))]
+    InvalidPathHashPrefix { prefix: String },
+
+    /// The number of hashed bins wasn't a power of two, or was too large.
+    #[snafu(display(
+        "The number of hashed bins must be a power of two no greater than {}, given {}",
+        max,
+        count
+    ))]
+    HashedBinCount { count: u64, max: u64 },
+
     /// Target doesn't have proper permissions from parent delegations
     #[snafu(display("Invalid file permissions from parent delegation: {}", child))]
     UnmatchedPath { child: String },
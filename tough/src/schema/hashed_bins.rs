@@ -0,0 +1,161 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `HashedBins`, which splits the targets of a role between delegated roles by the hash
+//! of their names.
+
+use crate::schema::error::{self, Result};
+use crate::schema::{target_name_digest, PathHashPrefix, PathSet};
+use crate::TargetName;
+use snafu::ensure;
+use std::num::NonZeroU64;
+
+/// The hashed bin delegations the TUF specification suggests for roles with many targets.
+///
+/// Each target belongs to exactly one of `count` bins, chosen by the first hex digits of the
+/// sha256 digest of its name. Each bin is a delegated role whose `path_hash_prefixes` list a
+/// contiguous range of those digits, and is named after the range, such as `00-07`, or after
+/// its only prefix when each bin has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashedBins {
+    count: u64,
+    /// The number of hex digits in each prefix.
+    prefix_len: usize,
+    /// The number of prefixes in each bin.
+    bin_size: u64,
+}
+
+impl HashedBins {
+    /// The most bins that can be created, which keeps every prefix to four hex digits.
+    pub const MAX_COUNT: u64 = 1 << 16;
+
+    /// Describes `count` bins, which must be a power of two no greater than
+    /// [`MAX_COUNT`](Self::MAX_COUNT).
+    pub fn new(count: NonZeroU64) -> Result<Self> {
+        let count = count.get();
+        ensure!(
+            count.is_power_of_two() && count <= Self::MAX_COUNT,
+            error::HashedBinCountSnafu {
+                count,
+                max: Self::MAX_COUNT,
+            }
+        );
+        let prefix_len = format!("{:x}", count - 1).len();
+        let prefixes = 1_u64 << (4 * prefix_len);
+        Ok(Self {
+            count,
+            prefix_len,
+            bin_size: prefixes / count,
+        })
+    }
+
+    /// The number of bins.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The name and paths of each bin, in order of their prefixes.
+    pub fn bins(&self) -> impl Iterator<Item = (String, PathSet)> + '_ {
+        (0..self.count).map(move |bin| {
+            let first = bin * self.bin_size;
+            let prefixes = (first..first + self.bin_size)
+                .map(|prefix| PathHashPrefix(self.prefix(prefix)))
+                .collect();
+            (self.bin_name(bin), PathSet::PathHashPrefixes(prefixes))
+        })
+    }
+
+    /// The name of the bin that `target_name` belongs to.
+    pub fn bin_for(&self, target_name: &TargetName) -> String {
+        let digest = target_name_digest(target_name);
+        // The digest is hex, so its first digits always parse.
+        let prefix = u64::from_str_radix(&digest[..self.prefix_len], 16).unwrap_or_default();
+        self.bin_name(prefix / self.bin_size)
+    }
+
+    fn bin_name(&self, bin: u64) -> String {
+        let first = bin * self.bin_size;
+        let last = first + self.bin_size - 1;
+        if first == last {
+            self.prefix(first)
+        } else {
+            format!("{}-{}", self.prefix(first), self.prefix(last))
+        }
+    }
+
+    fn prefix(&self, prefix: u64) -> String {
+        format!("{:0width$x}", prefix, width = self.prefix_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bins(count: u64) -> HashedBins {
+        HashedBins::new(NonZeroU64::new(count).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn bin_names_and_prefixes() {
+        let names: Vec<_> = bins(4).bins().map(|(name, _)| name).collect();
+        assert_eq!(names, ["0-3", "4-7", "8-b", "c-f"]);
+
+        let all: Vec<_> = bins(16).bins().map(|(name, _)| name).collect();
+        assert_eq!(all.len(), 16);
+        assert_eq!(all[10], "a");
+
+        let (name, paths) = bins(32).bins().nth(1).unwrap();
+        assert_eq!(name, "08-0f");
+        let PathSet::PathHashPrefixes(prefixes) = paths else {
+            panic!("expected path hash prefixes");
+        };
+        let prefixes: Vec<_> = prefixes.iter().map(PathHashPrefix::value).collect();
+        assert_eq!(prefixes, ["08", "09", "0a", "0b", "0c", "0d", "0e", "0f"]);
+    }
+
+    #[test]
+    fn single_bin() {
+        let bins: Vec<_> = bins(1).bins().collect();
+        assert_eq!(bins.len(), 1);
+        assert_eq!(bins[0].0, "0-f");
+    }
+
+    #[test]
+    fn invalid_counts() {
+        for count in [3, 48, HashedBins::MAX_COUNT * 2] {
+            let err = HashedBins::new(NonZeroU64::new(count).unwrap()).unwrap_err();
+            assert!(
+                matches!(err, error::Error::HashedBinCount { .. }),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn each_target_matches_only_its_bin() {
+        let bins = bins(64);
+        for name in ["file1.txt", "a/b/c.tar.gz", "UPPER", "x"] {
+            let name = TargetName::new(name).unwrap();
+            let matching: Vec<_> = bins
+                .bins()
+                .filter(|(_, paths)| paths.matches_target_name(&name))
+                .map(|(bin, _)| bin)
+                .collect();
+            assert_eq!(matching, [bins.bin_for(&name)]);
+        }
+    }
+
+    #[test]
+    fn prefixes_are_hex() {
+        assert!(PathHashPrefix::new("0aF9").is_ok());
+        assert!(PathHashPrefix::new("0g").is_err());
+
+        // Prefixes match the digest without regard to case.
+        let name = TargetName::new("file1.txt").unwrap();
+        let digest = target_name_digest(&name);
+        let upper = PathHashPrefix::new(digest[..4].to_ascii_uppercase()).unwrap();
+        assert!(upper.matches_target_name(&name));
+    }
+}
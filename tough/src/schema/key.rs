@@ -5,7 +5,6 @@
 use crate::schema::decoded::{Decoded, EcdsaFlex, Hex, RsaPem};
 use crate::schema::error::{self, Result};
 use aws_lc_rs::digest::{digest, SHA256};
-use aws_lc_rs::signature::VerificationAlgorithm;
 use olpc_cjson::CanonicalFormatter;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -150,42 +149,25 @@ impl Key {
         Ok(digest(&SHA256, &buf).as_ref().to_vec().into())
     }
 
+    /// Returns the decoded public key, which is the same for two keys that differ only in their
+    /// other fields and so have different key IDs.
+    pub fn public_key(&self) -> &[u8] {
+        match self {
+            Key::Rsa { keyval, .. } => &keyval.public,
+            Key::Ed25519 { keyval, .. } => &keyval.public,
+            Key::Ecdsa { keyval, .. } | Key::EcdsaOld { keyval, .. } => &keyval.public,
+        }
+    }
+
     /// Verify a signature of an object made with this key.
+    ///
+    /// The signature is checked by the active [`CryptoBackend`](crate::CryptoBackend). Keys that the
+    /// active [`CryptoMode`](crate::CryptoMode) doesn't allow never verify.
     pub(super) fn verify(&self, msg: &[u8], signature: &[u8]) -> bool {
-        let (alg, public_key): (&dyn VerificationAlgorithm, untrusted::Input<'_>) = match self {
-            Key::Ecdsa {
-                scheme: EcdsaScheme::EcdsaSha2Nistp256,
-                keyval,
-                ..
-            }
-            | Key::EcdsaOld {
-                scheme: EcdsaScheme::EcdsaSha2Nistp256,
-                keyval,
-                ..
-            } => (
-                &aws_lc_rs::signature::ECDSA_P256_SHA256_ASN1,
-                untrusted::Input::from(&keyval.public),
-            ),
-            Key::Ed25519 {
-                scheme: Ed25519Scheme::Ed25519,
-                keyval,
-                ..
-            } => (
-                &aws_lc_rs::signature::ED25519,
-                untrusted::Input::from(&keyval.public),
-            ),
-            Key::Rsa {
-                scheme: RsaScheme::RsassaPssSha256,
-                keyval,
-                ..
-            } => (
-                &aws_lc_rs::signature::RSA_PSS_2048_8192_SHA256,
-                untrusted::Input::from(&keyval.public),
-            ),
-        };
-
-        alg.verify_sig(public_key.as_slice_less_safe(), msg, signature)
-            .is_ok()
+        if !crate::crypto_mode().allows_key(self) {
+            return false;
+        }
+        crate::crypto_backend().verify(self, msg, signature)
     }
 }
 
@@ -215,6 +197,16 @@ impl FromStr for Key {
             } else {
                 Err(KeyParseError(()))
             }
+        } else if let Some(public) = crate::sign::parse_ssh_public_key(s) {
+            // An `ssh-ed25519` line from an OpenSSH `.pub` file.
+            Ok(Key::Ed25519 {
+                keyval: Ed25519Key {
+                    public: public.into(),
+                    _extra: HashMap::new(),
+                },
+                scheme: Ed25519Scheme::Ed25519,
+                _extra: HashMap::new(),
+            })
         } else if let Ok(public) = serde_plain::from_str::<Decoded<EcdsaFlex>>(s) {
             Ok(Key::Ecdsa {
                 keyval: EcdsaKey {
@@ -5,15 +5,19 @@
 mod de;
 pub mod decoded;
 mod error;
+mod hashed_bins;
 mod iter;
 pub mod key;
 mod spki;
 mod verify;
 
+use crate::policy::HashAlgorithm;
 use crate::schema::decoded::{Decoded, Hex};
 pub use crate::schema::error::{Error, Result};
+pub use crate::schema::hashed_bins::HashedBins;
 use crate::schema::iter::KeysIter;
 use crate::schema::key::Key;
+pub use crate::schema::verify::SignatureReport;
 use crate::sign::Sign;
 pub use crate::transport::{FilesystemTransport, Transport};
 use crate::{encode_filename, TargetName};
@@ -22,12 +26,14 @@ use chrono::{DateTime, Utc};
 use globset::{Glob, GlobMatcher};
 use hex::ToHex;
 use olpc_cjson::CanonicalFormatter;
-use serde::de::Error as SerdeDeError;
+use serde::de::{DeserializeOwned, Error as SerdeDeError};
+use serde::ser::SerializeMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 use serde_plain::{derive_display_from_serialize, derive_fromstr_from_deserialize};
-use snafu::ResultExt;
+use snafu::{ensure, OptionExt, ResultExt};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::num::NonZeroU64;
 use std::ops::{Deref, DerefMut};
 use std::path::Path;
@@ -59,7 +65,7 @@ derive_display_from_serialize!(RoleType);
 derive_fromstr_from_deserialize!(RoleType);
 
 /// A role identifier
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RoleId {
     /// Top level roles are identified by a `RoleType`
     StandardRole(RoleType),
@@ -108,12 +114,40 @@ pub struct Signed<T> {
 }
 
 /// A signature and the key ID that made it.
+///
+/// Build one with [`Signature::new`]. Code that builds it from a struct literal has to list
+/// `_extra`, which was added after the other fields, and would break again if more were added.
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(try_from = "de::SignatureFields")]
 pub struct Signature {
     /// The key ID (listed in root.json) that made this signature.
     pub keyid: Decoded<Hex>,
     /// A hex-encoded signature of the canonical JSON form of a role.
+    ///
+    /// A `sig` that isn't hex, such as one in an encoding from a later version of the
+    /// specification, is kept in its original form with no decoded bytes. It is written back out
+    /// unchanged and never verifies. With the `strict-schema` feature, it fails to parse instead.
     pub sig: Decoded<Hex>,
+    /// Extra arguments found during deserialization.
+    ///
+    /// These are kept so that re-written metadata doesn't lose fields added by later versions of
+    /// the specification. With the `strict-schema` feature, a signature with extra fields fails
+    /// to parse.
+    ///
+    /// If you're instantiating this struct, you should make this `HashMap::empty()`.
+    #[serde(flatten)]
+    pub _extra: HashMap<String, Value>,
+}
+
+impl Signature {
+    /// Creates the signature `sig` made by the key `keyid`, without extra fields.
+    pub fn new(keyid: Decoded<Hex>, sig: Decoded<Hex>) -> Self {
+        Self {
+            keyid,
+            sig,
+            _extra: HashMap::new(),
+        }
+    }
 }
 
 /// A `KeyHolder` is metadata that is responsible for verifying the signatures of a role.
@@ -132,9 +166,8 @@ pub enum KeyHolder {
 /// authorized for all top-level roles, including the root role itself. Revocation and replacement
 /// of top-level role keys, including for the root role, is done by changing the keys listed for the
 /// roles in this file.
-#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
-#[serde(tag = "_type")]
-#[serde(rename = "root")]
+#[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
+#[serde(from = "de::RootFields")]
 pub struct Root {
     /// A string that contains the version number of the TUF specification. Its format follows the
     /// Semantic Versioning 2.0.0 (semver) specification.
@@ -162,16 +195,79 @@ pub struct Root {
     /// for each role.
     pub roles: HashMap<RoleType, RoleKeys>,
 
+    /// Roles listed in `roles` that tough doesn't manage, such as `mirrors`, as they were found.
+    /// They are serialized back into `roles` unchanged, so that editing root.json doesn't drop
+    /// them and signatures over them still verify.
+    ///
+    /// If you're instantiating this struct, you should make this `HashMap::empty()`.
+    pub unrecognized_roles: HashMap<String, Value>,
+
     /// Extra arguments found during deserialization.
     ///
     /// We must store these to correctly verify signatures for this object.
     ///
     /// If you're instantiating this struct, you should make this `HashMap::empty()`.
-    #[serde(flatten)]
-    #[serde(deserialize_with = "de::extra_skip_type")]
     pub _extra: HashMap<String, Value>,
 }
 
+/// The fields of a `Root` as they're serialized, with `roles` and `unrecognized_roles` merged.
+#[derive(Serialize)]
+#[serde(tag = "_type")]
+#[serde(rename = "root")]
+struct RootRef<'a> {
+    spec_version: &'a str,
+    consistent_snapshot: bool,
+    version: NonZeroU64,
+    expires: &'a DateTime<Utc>,
+    keys: &'a HashMap<Decoded<Hex>, Key>,
+    roles: RolesRef<'a>,
+    #[serde(flatten)]
+    _extra: &'a HashMap<String, Value>,
+}
+
+struct RolesRef<'a> {
+    roles: &'a HashMap<RoleType, RoleKeys>,
+    unrecognized_roles: &'a HashMap<String, Value>,
+}
+
+impl Serialize for RolesRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map =
+            serializer.serialize_map(Some(self.roles.len() + self.unrecognized_roles.len()))?;
+        for (role, role_keys) in self.roles {
+            map.serialize_entry(role, role_keys)?;
+        }
+        for (role, value) in self.unrecognized_roles {
+            map.serialize_entry(role, value)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Root {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        RootRef {
+            spec_version: &self.spec_version,
+            consistent_snapshot: self.consistent_snapshot,
+            version: self.version,
+            expires: &self.expires,
+            keys: &self.keys,
+            roles: RolesRef {
+                roles: &self.roles,
+                unrecognized_roles: &self.unrecognized_roles,
+            },
+            _extra: &self._extra,
+        }
+        .serialize(serializer)
+    }
+}
+
 /// Represents the key IDs used for a role and the threshold of signatures required to validate it.
 /// TUF 4.3: A ROLE is one of "root", "snapshot", "targets", "timestamp", or "mirrors". A role for
 /// each of "root", "snapshot", "timestamp", and "targets" MUST be specified in the key list.
@@ -182,6 +278,7 @@ pub struct Root {
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 pub struct RoleKeys {
     /// The key IDs used for the role.
+    #[serde(deserialize_with = "de::deserialize_keyids")]
     pub keyids: Vec<Decoded<Hex>>,
 
     /// The threshold of signatures required to validate the role.
@@ -208,6 +305,23 @@ impl Root {
         }
     }
 
+    /// Whether a role in `unrecognized_roles` lists `key_id` among its `keyids`.
+    pub fn unrecognized_role_lists_key(&self, key_id: &Decoded<Hex>) -> bool {
+        self.unrecognized_roles.values().any(|role| {
+            role.get("keyids")
+                .and_then(Value::as_array)
+                .is_some_and(|keyids| {
+                    keyids.iter().any(|keyid| {
+                        keyid
+                            .as_str()
+                            .and_then(|keyid| keyid.parse::<Decoded<Hex>>().ok())
+                            .as_ref()
+                            == Some(key_id)
+                    })
+                })
+        })
+    }
+
     /// Given an object/key that impls Sign, return the corresponding
     /// key ID from Root
     pub fn key_id(&self, key_pair: &dyn Sign) -> Option<Decoded<Hex>> {
@@ -320,10 +434,70 @@ pub struct Metafile {
     pub _extra: HashMap<String, Value>,
 }
 
+impl Metafile {
+    /// The fields of a `Metafile` that TUF defines, which can't be used as extension fields.
+    const RESERVED_FIELDS: [&'static str; 3] = ["length", "hashes", "version"];
+
+    /// Returns the extension field `name` from `_extra`, deserialized as `T`, or `None` if it
+    /// isn't set. Publishers use extension fields for things like deployment channels; they are
+    /// covered by the signature of the snapshot or timestamp metadata that lists the file.
+    pub fn extension<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>> {
+        self._extra
+            .get(name)
+            .map(|value| T::deserialize(value).context(error::ExtensionParseSnafu { name }))
+            .transpose()
+    }
+
+    /// Sets the extension field `name` in `_extra` to `value`, returning the previous value.
+    pub fn set_extension<T: Serialize>(&mut self, name: &str, value: &T) -> Result<Option<Value>> {
+        let value = Self::extension_value(name, value)?;
+        Ok(self._extra.insert(name.to_owned(), value))
+    }
+
+    /// Removes the extension field `name` from `_extra`, returning its value.
+    pub fn remove_extension(&mut self, name: &str) -> Option<Value> {
+        self._extra.remove(name)
+    }
+
+    /// Returns the `custom` extension field, deserialized as `T`.
+    pub fn custom<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        self.extension("custom")
+    }
+
+    /// Sets the `custom` extension field, returning the previous value.
+    pub fn set_custom<T: Serialize>(&mut self, value: &T) -> Result<Option<Value>> {
+        self.set_extension("custom", value)
+    }
+
+    /// Serializes `value` for the extension field `name`, which must not be a TUF field.
+    pub(crate) fn extension_value<T: Serialize>(name: &str, value: &T) -> Result<Value> {
+        ensure!(
+            !Self::RESERVED_FIELDS.contains(&name),
+            error::ReservedFieldSnafu { name }
+        );
+        serde_json::to_value(value).context(error::JsonSerializationSnafu {
+            what: format!("extension field '{name}'"),
+        })
+    }
+}
+
 /// Represents the hash dictionary in a `snapshot.json` file.
+///
+/// Hashes for algorithms other than SHA-256 are kept in `_extra` as they were found, whether they
+/// are hex strings or envelopes of some other shape, so they survive a round trip. With the
+/// `strict-schema` feature, a hash that isn't a hex string fails to parse. [`Hashes::digest`] and
+/// [`Hashes::set_digest`] read and write the hashes of the algorithms in [`HashAlgorithm`].
+///
+/// Some generators list targets without a `sha256` hash, or without `hashes` at all. These parse,
+/// with an empty `sha256`, so that the metadata's signatures can be checked. A target listed
+/// without a hash in any algorithm in [`HashAlgorithm`] can't be verified; see
+/// [`UnhashedTargetPolicy`](crate::UnhashedTargetPolicy) for how such targets are treated. A
+/// target listed with an empty `hashes` object is serialized without one, as if none were listed.
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[cfg_attr(feature = "strict-schema", serde(try_from = "de::HashesFields"))]
 pub struct Hashes {
-    /// The SHA 256 digest of a metadata file.
+    /// The SHA 256 digest of a metadata file, or empty if none is listed.
+    #[serde(default = "unlisted_digest", skip_serializing_if = "is_empty_digest")]
     pub sha256: Decoded<Hex>,
 
     /// Extra arguments found during deserialization.
@@ -335,6 +509,145 @@ pub struct Hashes {
     pub _extra: HashMap<String, Value>,
 }
 
+pub(super) fn unlisted_digest() -> Decoded<Hex> {
+    Vec::new().into()
+}
+
+fn is_empty_digest(digest: &Decoded<Hex>) -> bool {
+    digest.is_empty()
+}
+
+impl Hashes {
+    /// The `hashes` of a target that lists none.
+    fn unlisted() -> Self {
+        Self {
+            sha256: Vec::new().into(),
+            _extra: HashMap::new(),
+        }
+    }
+
+    /// Whether no hash is listed at all, in which case a target is serialized without `hashes`.
+    fn is_unlisted(&self) -> bool {
+        self.sha256.is_empty() && self._extra.is_empty()
+    }
+
+    /// Computes the `sha256` hash of `data`, and its hash with each of `algorithms`.
+    pub fn compute(data: &[u8], algorithms: &[HashAlgorithm]) -> Self {
+        let mut contexts = HashContexts::new(algorithms);
+        contexts.update(data);
+        contexts.finish()
+    }
+
+    /// Returns the listed digest for `algorithm`, or `None` if it isn't listed. A listed hash that
+    /// isn't a hex string is an error.
+    pub fn digest(&self, algorithm: HashAlgorithm) -> Result<Option<Vec<u8>>> {
+        if algorithm == HashAlgorithm::Sha256 {
+            return Ok((!self.sha256.is_empty()).then(|| self.sha256.to_vec()));
+        }
+        self._extra
+            .get(algorithm.name())
+            .map(|value| {
+                value
+                    .as_str()
+                    .and_then(|hash| hex::decode(hash).ok())
+                    .context(error::HashEncodingSnafu {
+                        algorithm: algorithm.name(),
+                    })
+            })
+            .transpose()
+    }
+
+    /// Lists `digest` as the hash for `algorithm`, replacing any listed before.
+    pub fn set_digest(&mut self, algorithm: HashAlgorithm, digest: &[u8]) {
+        if algorithm == HashAlgorithm::Sha256 {
+            self.sha256 = digest.to_vec().into();
+        } else {
+            self._extra.insert(
+                algorithm.name().to_owned(),
+                Value::String(hex::encode(digest)),
+            );
+        }
+    }
+
+    /// Whether `self` and `other` list at least one algorithm in [`HashAlgorithm`] in common, and
+    /// the same digest for every algorithm they both list. Digests are compared decoded, so the
+    /// case of a hex string doesn't matter; a shared hash that isn't a hex string never agrees.
+    pub fn agrees_with(&self, other: &Hashes) -> bool {
+        let theirs = other.algorithms();
+        let mut shared = false;
+        for algorithm in self.algorithms() {
+            if !theirs.contains(&algorithm) {
+                continue;
+            }
+            match (self.digest(algorithm), other.digest(algorithm)) {
+                (Ok(Some(ours)), Ok(Some(theirs))) if ours == theirs => shared = true,
+                _ => return false,
+            }
+        }
+        shared
+    }
+
+    /// The digest that a target listed with these hashes is named by in a repository that uses
+    /// consistent snapshots, and cached by: its `sha256` hash if one is listed, otherwise its
+    /// strongest hash in [`HashAlgorithm`]. `None` if no such hash is listed.
+    pub(crate) fn name_digest(&self) -> Option<(HashAlgorithm, Vec<u8>)> {
+        if !self.sha256.is_empty() {
+            return Some((HashAlgorithm::Sha256, self.sha256.to_vec()));
+        }
+        self.algorithms().into_iter().find_map(|algorithm| {
+            self.digest(algorithm)
+                .ok()
+                .flatten()
+                .map(|digest| (algorithm, digest))
+        })
+    }
+
+    /// The algorithms in [`HashAlgorithm`] that a hash is listed for, strongest first.
+    pub fn algorithms(&self) -> Vec<HashAlgorithm> {
+        HashAlgorithm::ALL
+            .iter()
+            .rev()
+            .copied()
+            .filter(|algorithm| match algorithm {
+                HashAlgorithm::Sha256 => !self.sha256.is_empty(),
+                HashAlgorithm::Sha512 => self._extra.contains_key(algorithm.name()),
+            })
+            .collect()
+    }
+}
+
+/// Digests being computed for [`Hashes`] over data read in chunks. SHA-256 is always included.
+struct HashContexts(Vec<(HashAlgorithm, Context)>);
+
+impl HashContexts {
+    fn new(algorithms: &[HashAlgorithm]) -> Self {
+        let mut contexts = vec![(HashAlgorithm::Sha256, Context::new(&SHA256))];
+        for algorithm in algorithms {
+            if contexts.iter().all(|(listed, _)| listed != algorithm) {
+                contexts.push((*algorithm, Context::new(algorithm.algorithm())));
+            }
+        }
+        Self(contexts)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for (_, context) in &mut self.0 {
+            context.update(data);
+        }
+    }
+
+    fn finish(self) -> Hashes {
+        let mut hashes = Hashes {
+            sha256: Vec::new().into(),
+            _extra: HashMap::new(),
+        };
+        for (algorithm, context) in self.0 {
+            hashes.set_digest(algorithm, context.finish().as_ref());
+        }
+        hashes
+    }
+}
+
 impl Snapshot {
     /// Create a new `Snapshot` object.
     pub fn new(spec_version: String, version: NonZeroU64, expires: DateTime<Utc>) -> Self {
@@ -416,6 +729,15 @@ pub struct Targets {
     pub _extra: HashMap<String, Value>,
 }
 
+/// The keys of a target's `custom` metadata that tough's file mode and ownership convention uses.
+/// See [`Target::mode`].
+const TARGET_MODE: &str = "mode";
+const TARGET_UID: &str = "uid";
+const TARGET_GID: &str = "gid";
+
+/// The bits of a file mode that [`Target::mode`] may list.
+const MODE_BITS: u32 = 0o7777;
+
 /// TUF 4.5: TARGETS is an object whose format is the following:
 /// ```text
 /// { TARGETPATH : {
@@ -433,6 +755,10 @@ pub struct Target {
     /// HASHES is a dictionary that specifies one or more hashes, including the cryptographic hash
     /// function. For example: `{ "sha256": HASH, ... }`. HASH is the hexdigest of the cryptographic
     /// function computed on the target file.
+    #[serde(
+        default = "Hashes::unlisted",
+        skip_serializing_if = "Hashes::is_unlisted"
+    )]
     pub hashes: Hashes,
 
     /// If defined, the elements and values of "custom" will be made available to the client
@@ -454,8 +780,32 @@ pub struct Target {
 }
 
 impl Target {
+    /// Starts building a target that is `length` bytes long and has `hashes`, so that `custom`
+    /// metadata can be attached without constructing a `Target` by hand. To start from a target
+    /// built some other way, such as by [`from_path`](Self::from_path), use
+    /// `TargetBuilder::from(target)`.
+    pub fn builder(length: u64, hashes: Hashes) -> TargetBuilder {
+        TargetBuilder {
+            target: Target {
+                length,
+                hashes,
+                custom: HashMap::new(),
+                _extra: HashMap::new(),
+            },
+        }
+    }
+
     /// Given a path, returns a Target struct
     pub async fn from_path<P>(path: P) -> Result<Target>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_path_with_hashes(path, &[]).await
+    }
+
+    /// Like [`from_path`](Self::from_path), but lists the target's hash with each of `algorithms`
+    /// as well as its `sha256` hash.
+    pub async fn from_path_with_hashes<P>(path: P, algorithms: &[HashAlgorithm]) -> Result<Target>
     where
         P: AsRef<Path>,
     {
@@ -469,7 +819,7 @@ impl Target {
         let mut file = File::open(path)
             .await
             .context(error::FileOpenSnafu { path })?;
-        let mut digest = Context::new(&SHA256);
+        let mut digest = HashContexts::new(algorithms);
         let mut buf = [0; 8 * 1024];
         let mut length = 0;
         loop {
@@ -488,14 +838,141 @@ impl Target {
 
         Ok(Target {
             length,
-            hashes: Hashes {
-                sha256: Decoded::from(digest.finish().as_ref().to_vec()),
-                _extra: HashMap::new(),
-            },
+            hashes: digest.finish(),
             custom: HashMap::new(),
             _extra: HashMap::new(),
         })
     }
+
+    /// Looks up a value in the target's `custom` metadata by [JSON Pointer], such as `/arch` or
+    /// `/release/channel`. The pointer's first token names a key of `custom`. Returns `None` if
+    /// nothing is at that location, or if the pointer doesn't start with `/`.
+    ///
+    /// [JSON Pointer]: https://www.rfc-editor.org/rfc/rfc6901
+    pub fn custom_value(&self, pointer: &str) -> Option<&Value> {
+        let tokens = pointer.strip_prefix('/')?;
+        let (key, rest) = match tokens.find('/') {
+            Some(i) => tokens.split_at(i),
+            None => (tokens, ""),
+        };
+        let key = key.replace("~1", "/").replace("~0", "~");
+        self.custom.get(&key)?.pointer(rest)
+    }
+
+    /// Returns the Unix file mode listed for the target, if any.
+    ///
+    /// tough follows this convention for the permissions and ownership of targets, which other TUF
+    /// implementations may ignore:
+    ///
+    /// * `custom.mode` is the file mode as a string of octal digits, such as `"0755"`. Only
+    ///   permission, setuid, setgid and sticky bits (`0o7777`) may be set.
+    /// * `custom.uid` and `custom.gid` are the numeric IDs of the file's owner and group.
+    ///
+    /// Returns an error if `custom.mode` is present but doesn't follow the convention.
+    pub fn mode(&self) -> Result<Option<u32>> {
+        let Some(value) = self.custom.get(TARGET_MODE) else {
+            return Ok(None);
+        };
+        value
+            .as_str()
+            .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+            .filter(|mode| mode & !MODE_BITS == 0)
+            .map(Some)
+            .context(error::InvalidTargetCustomSnafu {
+                key: TARGET_MODE,
+                expected: "a string of octal digits no greater than 7777",
+            })
+    }
+
+    /// Lists `mode` as the target's Unix file mode, following the convention described in
+    /// [`mode`](Self::mode).
+    pub fn set_mode(&mut self, mode: u32) -> Result<()> {
+        ensure!(
+            mode & !MODE_BITS == 0,
+            error::InvalidTargetCustomSnafu {
+                key: TARGET_MODE,
+                expected: "a file mode no greater than 0o7777",
+            }
+        );
+        self.custom
+            .insert(TARGET_MODE.to_owned(), Value::String(format!("{mode:04o}")));
+        Ok(())
+    }
+
+    /// Returns the numeric ID of the target's owner, if one is listed. See [`mode`](Self::mode).
+    pub fn uid(&self) -> Result<Option<u32>> {
+        self.custom_id(TARGET_UID)
+    }
+
+    /// Returns the numeric ID of the target's group, if one is listed. See [`mode`](Self::mode).
+    pub fn gid(&self) -> Result<Option<u32>> {
+        self.custom_id(TARGET_GID)
+    }
+
+    /// Lists the numeric IDs of the target's owner and group, following the convention described
+    /// in [`mode`](Self::mode).
+    pub fn set_owner(&mut self, uid: u32, gid: u32) {
+        self.custom.insert(TARGET_UID.to_owned(), uid.into());
+        self.custom.insert(TARGET_GID.to_owned(), gid.into());
+    }
+
+    fn custom_id(&self, key: &'static str) -> Result<Option<u32>> {
+        let Some(value) = self.custom.get(key) else {
+            return Ok(None);
+        };
+        value
+            .as_u64()
+            .and_then(|id| u32::try_from(id).ok())
+            .map(Some)
+            .context(error::InvalidTargetCustomSnafu {
+                key,
+                expected: "an integer from 0 to 4294967295",
+            })
+    }
+}
+
+/// Builds a [`Target`], started with [`Target::builder`] or from an existing target, whose
+/// `custom` metadata is kept.
+///
+/// ```
+/// # use tough::schema::{Hashes, Target};
+/// # fn example(hashes: Hashes) -> Target {
+/// Target::builder(1024, hashes)
+///     .custom("version", "1.2.3")
+///     .custom("requires", serde_json::json!(["libfoo >= 2"]))
+///     .build()
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TargetBuilder {
+    target: Target,
+}
+
+impl TargetBuilder {
+    /// Sets `key` in the target's `custom` metadata to `value`.
+    #[must_use]
+    pub fn custom(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.target.custom.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets each key of `custom` in the target's `custom` metadata.
+    #[must_use]
+    pub fn custom_map(mut self, custom: HashMap<String, Value>) -> Self {
+        self.target.custom.extend(custom);
+        self
+    }
+
+    /// Returns the target.
+    pub fn build(self) -> Target {
+        self.target
+    }
+}
+
+impl From<Target> for TargetBuilder {
+    fn from(target: Target) -> Self {
+        Self { target }
+    }
 }
 
 impl Targets {
@@ -540,6 +1017,27 @@ impl Targets {
         .fail()
     }
 
+    /// Returns the names of the delegated roles that [`find_target`](Self::find_target) passes
+    /// through to reach `target_name`, from the first delegation to the role that lists the
+    /// target. The list is empty if this role lists it, and `None` if no loaded role does.
+    pub(crate) fn target_delegation_path(&self, target_name: &TargetName) -> Option<Vec<&str>> {
+        if self.targets.contains_key(target_name) {
+            return Some(Vec::new());
+        }
+        for role in &self.delegations.as_ref()?.roles {
+            if !role.paths.matches_target_name(target_name) {
+                continue;
+            }
+            if let Some(targets) = &role.targets {
+                if let Some(mut path) = targets.signed.target_delegation_path(target_name) {
+                    path.insert(0, role.name.as_str());
+                    return Some(path);
+                }
+            }
+        }
+        None
+    }
+
     /// Returns a hashmap of all targets and all delegated targets recursively
     pub fn targets_map(&self) -> HashMap<TargetName, &Target> {
         self.targets_iter()
@@ -609,14 +1107,12 @@ impl Targets {
         {
             if role.name == name {
                 return Ok(role);
-            } else if let Ok(role) = role
-                .targets
-                .as_ref()
-                .ok_or(error::Error::NoTargets)?
-                .signed
-                .delegated_role(name)
-            {
-                return Ok(role);
+            }
+            // Roles whose metadata wasn't loaded can't delegate to anything we know about.
+            if let Some(targets) = &role.targets {
+                if let Ok(role) = targets.signed.delegated_role(name) {
+                    return Ok(role);
+                }
             }
         }
         Err(error::Error::RoleNotFound {
@@ -634,14 +1130,11 @@ impl Targets {
         {
             if role.name == name {
                 return Ok(role);
-            } else if let Ok(role) = role
-                .targets
-                .as_mut()
-                .ok_or(error::Error::NoTargets)?
-                .signed
-                .delegated_role_mut(name)
-            {
-                return Ok(role);
+            }
+            if let Some(targets) = &mut role.targets {
+                if let Ok(role) = targets.signed.delegated_role_mut(name) {
+                    return Ok(role);
+                }
             }
         }
         Err(error::Error::RoleNotFound {
@@ -684,12 +1177,21 @@ impl Targets {
     }
 
     /// Returns a vec of all targets roles delegated by this role
+    ///
+    /// Each returned role lists its own delegations, but not their loaded metadata, since that is
+    /// returned separately; copying it into every ancestor would grow with the depth of the tree.
     pub fn signed_delegated_targets(&self) -> Vec<Signed<DelegatedTargets>> {
         let mut delegated_targets = Vec::new();
         if let Some(delegations) = &self.delegations {
             for role in &delegations.roles {
                 if let Some(targets) = &role.targets {
-                    delegated_targets.push(targets.clone().delegated_targets(&role.name));
+                    delegated_targets.push(
+                        Signed {
+                            signed: targets.signed.without_delegated_targets(),
+                            signatures: targets.signatures.clone(),
+                        }
+                        .delegated_targets(&role.name),
+                    );
                     delegated_targets.extend(targets.signed.signed_delegated_targets());
                 }
             }
@@ -697,6 +1199,22 @@ impl Targets {
         delegated_targets
     }
 
+    /// Copies this role without the loaded metadata of the roles it delegates to. The copy
+    /// serializes identically, so it has the same signatures.
+    pub(crate) fn without_delegated_targets(&self) -> Targets {
+        Targets {
+            spec_version: self.spec_version.clone(),
+            version: self.version,
+            expires: self.expires,
+            targets: self.targets.clone(),
+            delegations: self
+                .delegations
+                .as_ref()
+                .map(Delegations::without_role_targets),
+            _extra: self._extra.clone(),
+        }
+    }
+
     /// Link all current targets to `new_targets` metadata, returns a list of new `Targets` not included in the original `Targets`' delegated roles
     /// This is used to insert a set of updated `Targets` metadata without reloading the rest of the chain.
     pub fn update_targets(&self, new_targets: &mut Signed<Targets>) -> Vec<String> {
@@ -864,6 +1382,7 @@ pub struct DelegatedRole {
     pub name: String,
 
     /// The key IDs used by this role.
+    #[serde(deserialize_with = "de::deserialize_keyids")]
     pub keyids: Vec<Decoded<Hex>>,
 
     /// The threshold of signatures required to validate the role.
@@ -923,7 +1442,7 @@ pub enum PathSet {
 /// * a `PATHPATTERN` of `"targets/*.tgz"` would match file paths `"targets/foo.tgz"` and
 ///   `"targets/bar.tgz"`, but not `"targets/foo.txt"`.
 /// * a `PATHPATTERN` of `"foo-version-?.tgz"` matches `"foo-version-2.tgz"` and
-///     `"foo-version-a.tgz"`, but not `"foo-version-alpha.tgz"`.
+///   `"foo-version-a.tgz"`, but not `"foo-version-alpha.tgz"`.
 /// * a `PATHPATTERN` of `"*.tgz"` would match `"foo.tgz"` and `"bar.tgz"`,
 ///   but not `"targets/foo.tgz"`
 /// * a `PATHPATTERN` of `"foo.tgz"` would match only `"foo.tgz"`
@@ -950,7 +1469,7 @@ impl PathPattern {
         &self.value
     }
 
-    fn matches_target_name(&self, target_name: &TargetName) -> bool {
+    pub(crate) fn matches_target_name(&self, target_name: &TargetName) -> bool {
         self.glob.is_match(target_name.resolved())
     }
 }
@@ -989,31 +1508,43 @@ impl<'de> Deserialize<'de> for PathPattern {
 }
 
 /// The first characters found in the string representation of a sha256 digest. This can be used for
-/// randomly sharding a repository. See [`PathSet::PathHashDigest`] for the description of how this
+/// randomly sharding a repository. See [`PathSet::PathHashPrefixes`] for the description of how this
 /// is used.
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct PathHashPrefix(String);
 
 impl PathHashPrefix {
-    /// Create a new, valid `PathPattern`.
+    /// Create a new `PathHashPrefix`, which must be a hex string.
     pub fn new<S: Into<String>>(value: S) -> Result<Self> {
-        // In case we choose to reject some of these in the future, we return a result. For now this
-        // will always succeed.
-        Ok(PathHashPrefix(value.into()))
+        let value = value.into();
+        ensure!(
+            value.chars().all(|c| c.is_ascii_hexdigit()),
+            error::InvalidPathHashPrefixSnafu { prefix: value }
+        );
+        Ok(PathHashPrefix(value))
     }
 
-    /// Get the inner value of this `PathPattern` as a string.
+    /// Get the inner value of this `PathHashPrefix` as a string.
     pub fn value(&self) -> &str {
         &self.0
     }
 
+    /// Whether the hex digest of `target_name` starts with this prefix. Hex digits are compared
+    /// without regard to case.
     fn matches_target_name(&self, target_name: &TargetName) -> bool {
-        let target_name_digest =
-            digest(&SHA256, target_name.resolved().as_bytes()).encode_hex::<String>();
-        target_name_digest.starts_with(self.value())
+        let target_name_digest = target_name_digest(target_name);
+        target_name_digest
+            .get(..self.0.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(&self.0))
     }
 }
 
+/// The lowercase hex digest of the sha256 hash of `target_name`, which path hash prefixes are
+/// matched against.
+pub(crate) fn target_name_digest(target_name: &TargetName) -> String {
+    digest(&SHA256, target_name.resolved().as_bytes()).encode_hex::<String>()
+}
+
 impl FromStr for PathHashPrefix {
     type Err = Error;
 
@@ -1076,6 +1607,27 @@ impl Delegations {
         }
         None
     }
+
+    /// Copies the keys and delegated roles without the loaded metadata of the delegated roles,
+    /// which is all that is needed to verify them and costs far less than a full clone for large
+    /// repositories.
+    pub(crate) fn without_role_targets(&self) -> Delegations {
+        Delegations {
+            keys: self.keys.clone(),
+            roles: self
+                .roles
+                .iter()
+                .map(|role| DelegatedRole {
+                    name: role.name.clone(),
+                    keyids: role.keyids.clone(),
+                    threshold: role.threshold,
+                    paths: role.paths.clone(),
+                    terminating: role.terminating,
+                    targets: None,
+                })
+                .collect(),
+        }
+    }
 }
 
 impl DelegatedRole {
@@ -1087,6 +1639,11 @@ impl DelegatedRole {
             _extra: HashMap::new(),
         }
     }
+
+    /// Determines if the role's paths or path hash prefixes match `target`
+    pub fn target_is_delegated(&self, target: &TargetName) -> bool {
+        self.paths.matches_target_name(target)
+    }
 }
 
 // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
@@ -1135,6 +1692,19 @@ impl Timestamp {
             _extra: HashMap::new(),
         }
     }
+
+    /// Returns the files other than snapshot.json that `meta` describes, sorted. The TUF
+    /// specification requires that timestamp metadata only describes snapshot.json.
+    pub fn unexpected_meta(&self) -> Vec<&str> {
+        let mut files: Vec<_> = self
+            .meta
+            .keys()
+            .map(String::as_str)
+            .filter(|file| *file != "snapshot.json")
+            .collect();
+        files.sort_unstable();
+        files
+    }
 }
 
 impl Role for Timestamp {
@@ -1247,4 +1817,95 @@ fn targets_iter_and_map_test() {
     assert!(map.contains_key(&TargetName::new("a.txt").unwrap()));
     assert!(map.contains_key(&TargetName::new("b.txt").unwrap()));
     assert!(map.contains_key(&TargetName::new("c.txt").unwrap()));
+
+    // Assert that each delegated role is returned once, without its descendants' metadata but
+    // serializing as before
+    let delegated = a.signed_delegated_targets();
+    assert_eq!(delegated.len(), 2);
+    let b = &delegated[0].signed;
+    assert_eq!(b.name, "b-role");
+    assert!(b.targets.delegations.as_ref().unwrap().roles[0]
+        .targets
+        .is_none());
+    assert_eq!(
+        serde_json::to_value(&b.targets).unwrap(),
+        serde_json::to_value(&a.delegated_targets("b-role").unwrap().signed).unwrap()
+    );
+}
+
+#[test]
+fn unhashed_targets_round_trip() {
+    for listed in [r#"{"length":5}"#, r#"{"length":5,"hashes":{"md5":"abcd"}}"#] {
+        let target: Target = serde_json::from_str(listed).unwrap();
+        assert!(target.hashes.sha256.is_empty());
+        assert!(target.hashes.algorithms().is_empty());
+        assert_eq!(target.hashes.digest(HashAlgorithm::Sha256).unwrap(), None);
+        assert_eq!(serde_json::to_string(&target).unwrap(), listed);
+    }
+
+    // A target listed with only a sha512 hash is hashed.
+    let target: Target =
+        serde_json::from_str(r#"{"length":5,"hashes":{"sha512":"abcd"}}"#).unwrap();
+    assert_eq!(target.hashes.algorithms(), [HashAlgorithm::Sha512]);
+    assert_eq!(
+        target.hashes.name_digest(),
+        Some((HashAlgorithm::Sha512, vec![0xab, 0xcd]))
+    );
+}
+
+#[test]
+fn target_mode_convention() {
+    let mut target = Target {
+        length: 0,
+        hashes: Hashes::unlisted(),
+        custom: HashMap::new(),
+        _extra: HashMap::new(),
+    };
+    assert_eq!(target.mode().unwrap(), None);
+    assert_eq!(target.uid().unwrap(), None);
+
+    target.set_mode(0o755).unwrap();
+    target.set_owner(0, 100);
+    assert_eq!(target.custom["mode"], "0755");
+    assert_eq!(target.mode().unwrap(), Some(0o755));
+    assert_eq!(target.uid().unwrap(), Some(0));
+    assert_eq!(target.gid().unwrap(), Some(100));
+    assert!(target.set_mode(0o10000).is_err());
+
+    for invalid in [
+        serde_json::json!(493),
+        serde_json::json!("0789"),
+        serde_json::json!("17777"),
+    ] {
+        target.custom.insert("mode".to_owned(), invalid);
+        assert!(target.mode().is_err());
+    }
+    target
+        .custom
+        .insert("uid".to_owned(), serde_json::json!(-1));
+    assert!(target.uid().is_err());
+}
+
+#[test]
+fn hashes_agree_on_shared_algorithms() {
+    let sha256 = Hashes::compute(b"data", &[]);
+    let both = Hashes::compute(b"data", &[HashAlgorithm::Sha512]);
+    let sha512 = {
+        let mut hashes = both.clone();
+        hashes.sha256 = Vec::new().into();
+        hashes
+    };
+    // Listings that share sha256 agree, whatever else either of them lists.
+    assert!(sha256.agrees_with(&both));
+    assert!(both.agrees_with(&sha512));
+    // Hex case doesn't matter.
+    let mut upper = both.clone();
+    let digest = upper._extra["sha512"].as_str().unwrap().to_uppercase();
+    upper
+        ._extra
+        .insert("sha512".to_owned(), Value::String(digest));
+    assert!(sha512.agrees_with(&upper));
+    // Listings without an algorithm in common, or with a differing digest, don't agree.
+    assert!(!sha256.agrees_with(&sha512));
+    assert!(!both.agrees_with(&Hashes::compute(b"other", &[HashAlgorithm::Sha512])));
 }
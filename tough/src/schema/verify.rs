@@ -1,18 +1,62 @@
+use super::decoded::{Decoded, Hex};
 use super::error::{self, Result};
 use super::{Delegations, Role, RoleType, Root, Signed, Targets};
 use olpc_cjson::CanonicalFormatter;
 use serde::Serialize;
 use snafu::{ensure, OptionExt, ResultExt};
 use std::collections::HashSet;
+use std::num::NonZeroU64;
+
+/// Which of a role's keys made valid signatures over a piece of metadata, as reported by
+/// [`Root::signature_report`] and [`Delegations::signature_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureReport {
+    /// The number of valid signatures the role requires.
+    pub threshold: NonZeroU64,
+    /// The role's key IDs that made a valid signature, in the order the role lists them.
+    pub signed: Vec<Decoded<Hex>>,
+    /// The role's key IDs that haven't made a valid signature, in the order the role lists them.
+    pub missing: Vec<Decoded<Hex>>,
+}
+
+impl SignatureReport {
+    /// Whether the threshold of valid signatures is met.
+    pub fn is_satisfied(&self) -> bool {
+        self.signed.len() as u64 >= self.threshold.get()
+    }
+
+    /// How many more valid signatures are needed to meet the threshold.
+    pub fn needed(&self) -> u64 {
+        self.threshold
+            .get()
+            .saturating_sub(self.signed.len() as u64)
+    }
+}
 
 impl Root {
     /// Checks that the given metadata role is valid based on a threshold of key signatures.
     pub fn verify_role<T: Role + Serialize>(&self, role: &Signed<T>) -> Result<()> {
+        let report = self.signature_report(role)?;
+        ensure!(
+            report.is_satisfied(),
+            error::SignatureThresholdSnafu {
+                role: T::TYPE,
+                threshold: report.threshold,
+                valid: report.signed.len() as u64,
+            }
+        );
+        Ok(())
+    }
+
+    /// Reports which of the keys this root lists for the metadata's role have validly signed it.
+    pub fn signature_report<T: Role + Serialize>(
+        &self,
+        role: &Signed<T>,
+    ) -> Result<SignatureReport> {
         let role_keys = self
             .roles
             .get(&T::TYPE)
             .context(error::MissingRoleSnafu { role: T::TYPE })?;
-        let mut valid = 0;
 
         let mut data = Vec::new();
         let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
@@ -22,30 +66,19 @@ impl Root {
                 what: format!("{} role", T::TYPE),
             })?;
 
-        let mut valid_keyids = HashSet::new();
-
-        for signature in &role.signatures {
-            if role_keys.keyids.contains(&signature.keyid) {
-                if let Some(key) = self.keys.get(&signature.keyid) {
-                    if key.verify(&data, &signature.sig) {
-                        // Ignore duplicate keyids.
-                        if valid_keyids.insert(&signature.keyid) {
-                            valid += 1;
-                        }
-                    }
-                }
-            }
-        }
-
-        ensure!(
-            valid >= u64::from(role_keys.threshold),
-            error::SignatureThresholdSnafu {
-                role: T::TYPE,
-                threshold: role_keys.threshold,
-                valid,
-            }
-        );
-        Ok(())
+        // Duplicate signatures by one key only count once.
+        let (signed, missing) = role_keys.keyids.iter().cloned().partition(|keyid| {
+            self.keys.get(keyid).is_some_and(|key| {
+                role.signatures
+                    .iter()
+                    .any(|signature| &signature.keyid == keyid && key.verify(&data, &signature.sig))
+            })
+        });
+        Ok(SignatureReport {
+            threshold: role_keys.threshold,
+            signed,
+            missing,
+        })
     }
 }
 
@@ -69,11 +102,17 @@ impl Delegations {
             .context(error::JsonSerializationSnafu {
                 what: format!("{name} role"),
             })?;
+
+        let mut valid_keyids = HashSet::new();
+
         for signature in &role.signatures {
             if role_keys.keyids.contains(&signature.keyid) {
                 if let Some(key) = self.keys.get(&signature.keyid) {
                     if key.verify(&data, &signature.sig) {
-                        valid += 1;
+                        // Ignore duplicate keyids.
+                        if valid_keyids.insert(&signature.keyid) {
+                            valid += 1;
+                        }
                     }
                 }
             }
@@ -89,11 +128,44 @@ impl Delegations {
         );
         Ok(())
     }
+
+    /// Reports which of the keys these delegations list for the delegated role `name` have
+    /// validly signed `role`.
+    pub fn signature_report(&self, role: &Signed<Targets>, name: &str) -> Result<SignatureReport> {
+        let role_keys =
+            self.roles
+                .iter()
+                .find(|role| role.name == name)
+                .ok_or(error::Error::RoleNotFound {
+                    name: name.to_string(),
+                })?;
+
+        let mut data = Vec::new();
+        let mut ser = serde_json::Serializer::with_formatter(&mut data, CanonicalFormatter::new());
+        role.signed
+            .serialize(&mut ser)
+            .context(error::JsonSerializationSnafu {
+                what: format!("{name} role"),
+            })?;
+
+        let (signed, missing) = role_keys.keyids.iter().cloned().partition(|keyid| {
+            self.keys.get(keyid).is_some_and(|key| {
+                role.signatures
+                    .iter()
+                    .any(|signature| &signature.keyid == keyid && key.verify(&data, &signature.sig))
+            })
+        });
+        Ok(SignatureReport {
+            threshold: role_keys.threshold,
+            signed,
+            missing,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Root, Signed};
+    use super::{RoleType, Root, Signed};
 
     #[test]
     fn simple_rsa() {
@@ -102,6 +174,28 @@ mod tests {
         root.signed.verify_role(&root).unwrap();
     }
 
+    #[test]
+    fn signature_report_lists_missing_keys() {
+        let root: Signed<Root> =
+            serde_json::from_str(include_str!("../../tests/data/simple-rsa/root.json")).unwrap();
+        let report = root.signed.signature_report(&root).unwrap();
+        assert!(report.is_satisfied());
+        assert_eq!(report.needed(), 0);
+        assert!(report.missing.is_empty());
+
+        let unsigned = Signed {
+            signed: root.signed.clone(),
+            signatures: Vec::new(),
+        };
+        let report = root.signed.signature_report(&unsigned).unwrap();
+        assert!(!report.is_satisfied());
+        assert_eq!(report.needed(), report.threshold.get());
+        assert_eq!(
+            report.missing.len(),
+            root.signed.roles[&RoleType::Root].keyids.len()
+        );
+    }
+
     #[test]
     fn no_root_json_signatures_is_err() {
         let root: Signed<Root> = serde_json::from_str(include_str!(
@@ -127,7 +221,7 @@ mod tests {
     #[test]
     // FIXME: this is not actually testing for expired metadata!
     // These tests should be transformed into full repositories and go through Repository::load
-    #[ignore]
+    #[ignore = "does not test for expired metadata"]
     fn expired_root_json_signature_is_err() {
         let root: Signed<Root> = serde_json::from_str(include_str!(
             "../../tests/data/expired-root-json-signature/root.json"
@@ -171,4 +265,38 @@ mod tests {
             .verify_role(&root)
             .expect_err("expired root signature should not verify");
     }
+
+    #[tokio::test]
+    async fn delegated_duplicate_sigs_is_err() {
+        use crate::editor::targets::TargetsEditor;
+        use crate::key_source::{KeySource, LocalKeySource};
+        use crate::schema::{DelegatedRole, Delegations, PathSet};
+        use std::num::NonZeroU64;
+
+        let key: Box<dyn KeySource> = Box::new(LocalKeySource {
+            path: "tests/data/targetskey".into(),
+        });
+        let tuf_key = key.as_sign().await.unwrap().tuf_key();
+        let keyid = tuf_key.key_id().unwrap();
+        let mut editor = TargetsEditor::new("role");
+        editor
+            .version(NonZeroU64::MIN)
+            .expires(chrono::Utc::now() + chrono::Duration::days(1));
+        let (_, mut role) = editor.create_signed(&[key]).await.unwrap().targets();
+        role.signatures.push(role.signatures[0].clone());
+
+        let mut delegations = Delegations::new();
+        delegations.keys.insert(keyid.clone(), tuf_key);
+        delegations.roles.push(DelegatedRole {
+            name: "role".to_owned(),
+            keyids: vec![keyid],
+            threshold: NonZeroU64::new(2).unwrap(),
+            paths: PathSet::Paths(Vec::new()),
+            terminating: false,
+            targets: None,
+        });
+        delegations
+            .verify_role(&role, "role")
+            .expect_err("a duplicated signature should count once");
+    }
 }
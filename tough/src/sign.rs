@@ -3,6 +3,9 @@
 
 //! Provides the `Sign` trait which abstracts over the method of signing with different key types.
 
+mod openssh;
+
+use crate::crypto::{crypto_backend, crypto_mode};
 use crate::error::{self, Result};
 use crate::schema::key::Key;
 use crate::sign::SignKeyPair::ECDSA;
@@ -11,10 +14,12 @@ use crate::sign::SignKeyPair::RSA;
 use async_trait::async_trait;
 use aws_lc_rs::rand::SecureRandom;
 use aws_lc_rs::signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair};
-use snafu::ResultExt;
+use snafu::{ensure, ResultExt};
 use std::collections::HashMap;
 use std::error::Error;
 
+pub(crate) use openssh::parse_public_key as parse_ssh_public_key;
+
 /// This trait must be implemented for each type of key with which you will
 /// sign things.
 #[async_trait]
@@ -32,7 +37,7 @@ pub trait Sign: Sync + Send {
 
 /// Implements `Sign` for a reference to any type that implements `Sign`.
 #[async_trait]
-impl<'a, T: Sign> Sign for &'a T {
+impl<T: Sign> Sign for &T {
     fn tuf_key(&self) -> Key {
         (*self).tuf_key()
     }
@@ -46,6 +51,22 @@ impl<'a, T: Sign> Sign for &'a T {
     }
 }
 
+/// Implements `Sign` for a box holding any type that implements `Sign`.
+#[async_trait]
+impl<T: Sign + ?Sized> Sign for Box<T> {
+    fn tuf_key(&self) -> Key {
+        (**self).tuf_key()
+    }
+
+    async fn sign(
+        &self,
+        msg: &[u8],
+        rng: &(dyn SecureRandom + Sync),
+    ) -> std::result::Result<Vec<u8>, Box<dyn Error + Send + Sync + 'static>> {
+        (**self).sign(msg, rng).await
+    }
+}
+
 /// Implements the Sign trait for ED25519
 #[async_trait]
 impl Sign for Ed25519KeyPair {
@@ -168,29 +189,21 @@ impl Sign for SignKeyPair {
 
 /// Parses a supplied keypair and if it is recognized, returns an object that
 /// implements the Sign trait
-/// Accepted Keys: ED25519 pkcs8, Ecdsa pkcs8, RSA
+/// Accepted Keys: ED25519 pkcs8, Ecdsa pkcs8 (either DER or PEM), RSA, unencrypted OpenSSH
+/// ED25519 (`ssh-keygen -t ed25519`), and ED25519 seeds written as hex, optionally followed by
+/// their public key.
+///
+/// The key is parsed by the active [`CryptoBackend`](crate::CryptoBackend), which also makes its
+/// signatures. Keys that the active [`CryptoMode`](crate::CryptoMode) doesn't allow are refused.
 pub fn parse_keypair(key: &[u8]) -> Result<impl Sign> {
-    if let Ok(ed25519_key_pair) = Ed25519KeyPair::from_pkcs8(key) {
-        Ok(SignKeyPair::ED25519(ed25519_key_pair))
-    } else if let Ok(ecdsa_key_pair) =
-        EcdsaKeyPair::from_pkcs8(&aws_lc_rs::signature::ECDSA_P256_SHA256_ASN1_SIGNING, key)
-    {
-        Ok(SignKeyPair::ECDSA(ecdsa_key_pair))
-    } else if let Ok(pem) = pem::parse(key) {
-        match pem.tag() {
-            "PRIVATE KEY" => {
-                if let Ok(rsa_key_pair) = RsaKeyPair::from_pkcs8(pem.contents()) {
-                    Ok(SignKeyPair::RSA(rsa_key_pair))
-                } else {
-                    error::KeyUnrecognizedSnafu.fail()
-                }
-            }
-            "RSA PRIVATE KEY" => Ok(SignKeyPair::RSA(
-                RsaKeyPair::from_der(pem.contents()).context(error::KeyRejectedSnafu)?,
-            )),
-            _ => error::KeyUnrecognizedSnafu.fail(),
-        }
-    } else {
-        error::KeyUnrecognizedSnafu.fail()
-    }
+    let keypair = match openssh::to_pkcs8(key)? {
+        Some(pkcs8) => crypto_backend().parse_keypair(&pkcs8)?,
+        None => crypto_backend().parse_keypair(key)?,
+    };
+    let mode = crypto_mode();
+    ensure!(
+        mode.allows_key(&keypair.tuf_key()),
+        error::KeyDisallowedSnafu { mode }
+    );
+    Ok(keypair)
 }
@@ -0,0 +1,115 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Reads Ed25519 keys in the formats that OpenSSH writes them, and Ed25519 seeds written as hex,
+//! so that existing keys can be used to sign TUF metadata.
+use crate::error::{self, Result};
+use crate::ssh_wire::{parse_ed25519_key, Reader, ED25519_PUBLIC_KEY_LEN, SSH_ED25519};
+use aws_lc_rs::signature::Ed25519KeyPair;
+use base64::Engine;
+use snafu::{ensure, OptionExt, ResultExt};
+
+const OPENSSH_PRIVATE_KEY_TAG: &str = "OPENSSH PRIVATE KEY";
+const OPENSSH_MAGIC: &[u8] = b"openssh-key-v1\0";
+const ED25519_SEED_LEN: usize = 32;
+
+/// The fields of a version 1 PKCS#8 document (RFC 5208) holding an Ed25519 key, up to its 32 byte
+/// seed. Every crypto backend reads this layout.
+const ED25519_PKCS8_V1_PREFIX: &[u8] = &[
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+/// Returns `key` as a PKCS#8 document if it is an unencrypted OpenSSH Ed25519 private key, or an
+/// Ed25519 seed written as hex, optionally followed by its public key. Returns `None` if `key` is
+/// in neither format.
+pub(super) fn to_pkcs8(key: &[u8]) -> Result<Option<Vec<u8>>> {
+    match pem::parse(key) {
+        Ok(pem) if pem.tag() == OPENSSH_PRIVATE_KEY_TAG => {
+            parse_private_key(pem.contents()).map(Some)
+        }
+        Ok(_) => Ok(None),
+        Err(_) => parse_hex_seed(key),
+    }
+}
+
+/// Returns the public key from an `ssh-ed25519` line, as found in a `.pub` file or
+/// `authorized_keys`.
+pub(crate) fn parse_public_key(line: &str) -> Option<Vec<u8>> {
+    let mut fields = line.split_whitespace();
+    if fields.next()?.as_bytes() != SSH_ED25519 {
+        return None;
+    }
+    let blob = base64::engine::general_purpose::STANDARD
+        .decode(fields.next()?)
+        .ok()?;
+    parse_ed25519_key(&blob).map(<[u8]>::to_vec)
+}
+
+/// Reads the `openssh-key-v1` format that `ssh-keygen` writes private keys in.
+fn parse_private_key(contents: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = Reader::new(
+        contents
+            .strip_prefix(OPENSSH_MAGIC)
+            .context(error::KeyUnrecognizedSnafu)?,
+    );
+    let cipher = reader.string().context(error::KeyUnrecognizedSnafu)?;
+    let kdf = reader.string().context(error::KeyUnrecognizedSnafu)?;
+    ensure!(
+        cipher == b"none" && kdf == b"none",
+        error::KeyEncryptedSnafu
+    );
+    let _kdf_options = reader.string().context(error::KeyUnrecognizedSnafu)?;
+    // `ssh-keygen` only ever writes one key to a file.
+    ensure!(reader.u32() == Some(1), error::KeyUnrecognizedSnafu);
+    let listed_public = reader
+        .string()
+        .and_then(parse_ed25519_key)
+        .context(error::KeyUnrecognizedSnafu)?;
+
+    let mut private = Reader::new(reader.string().context(error::KeyUnrecognizedSnafu)?);
+    let check = (private.u32(), private.u32());
+    ensure!(
+        check.0.is_some() && check.0 == check.1,
+        error::KeyUnrecognizedSnafu
+    );
+    ensure!(
+        private.string() == Some(SSH_ED25519),
+        error::KeyUnrecognizedSnafu
+    );
+    let public = private.string().context(error::KeyUnrecognizedSnafu)?;
+    // OpenSSH stores the seed followed by the public key.
+    let secret = private.string().context(error::KeyUnrecognizedSnafu)?;
+    ensure!(
+        public == listed_public && secret.len() == ED25519_SEED_LEN + ED25519_PUBLIC_KEY_LEN,
+        error::KeyUnrecognizedSnafu
+    );
+    let (seed, secret_public) = secret.split_at(ED25519_SEED_LEN);
+    ensure!(secret_public == public, error::KeyUnrecognizedSnafu);
+    Ed25519KeyPair::from_seed_and_public_key(seed, public).context(error::KeyRejectedSnafu)?;
+    Ok(pkcs8(seed))
+}
+
+/// Reads a hex-encoded Ed25519 seed, or a seed followed by its public key as written by libsodium
+/// and Go's `crypto/ed25519`.
+fn parse_hex_seed(key: &[u8]) -> Result<Option<Vec<u8>>> {
+    let Some(decoded) = std::str::from_utf8(key)
+        .ok()
+        .and_then(|key| hex::decode(key.trim()).ok())
+    else {
+        return Ok(None);
+    };
+    match decoded.len() {
+        ED25519_SEED_LEN => Ok(Some(pkcs8(&decoded))),
+        len if len == ED25519_SEED_LEN + ED25519_PUBLIC_KEY_LEN => {
+            let (seed, public) = decoded.split_at(ED25519_SEED_LEN);
+            Ed25519KeyPair::from_seed_and_public_key(seed, public)
+                .context(error::KeyRejectedSnafu)?;
+            Ok(Some(pkcs8(seed)))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn pkcs8(seed: &[u8]) -> Vec<u8> {
+    [ED25519_PKCS8_V1_PREFIX, seed].concat()
+}
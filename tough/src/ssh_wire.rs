@@ -0,0 +1,87 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Reads the SSH wire format (RFC 4251, section 5), which both OpenSSH key files and the ssh-agent
+//! protocol are written in.
+
+use std::convert::{TryFrom, TryInto};
+
+/// The key type name of an Ed25519 key.
+pub(crate) const SSH_ED25519: &[u8] = b"ssh-ed25519";
+/// The length of an Ed25519 public key.
+pub(crate) const ED25519_PUBLIC_KEY_LEN: usize = 32;
+
+/// Reads the `uint32` and `string` types of the SSH wire format.
+pub(crate) struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self(data)
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let (value, rest) = self.0.split_at_checked(len)?;
+        self.0 = rest;
+        Some(value)
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    pub(crate) fn string(&mut self) -> Option<&'a [u8]> {
+        let len = usize::try_from(self.u32()?).ok()?;
+        self.take(len)
+    }
+
+    /// Whether everything has been read.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Returns the public key from an SSH wire-format key blob, if it is an Ed25519 key.
+pub(crate) fn parse_ed25519_key(blob: &[u8]) -> Option<&[u8]> {
+    let mut reader = Reader::new(blob);
+    if reader.string()? != SSH_ED25519 {
+        return None;
+    }
+    let public = reader.string()?;
+    (public.len() == ED25519_PUBLIC_KEY_LEN && reader.is_empty()).then_some(public)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blob(fields: &[&[u8]]) -> Vec<u8> {
+        let mut blob = Vec::new();
+        for field in fields {
+            blob.extend_from_slice(&u32::try_from(field.len()).unwrap().to_be_bytes());
+            blob.extend_from_slice(field);
+        }
+        blob
+    }
+
+    #[test]
+    fn reads_strings() {
+        let data = blob(&[b"one", b""]);
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.string(), Some(&b"one"[..]));
+        assert_eq!(reader.string(), Some(&b""[..]));
+        assert!(reader.is_empty());
+        assert_eq!(reader.u32(), None);
+        assert_eq!(Reader::new(&data[..5]).string(), None);
+    }
+
+    #[test]
+    fn ed25519_blobs() {
+        assert!(parse_ed25519_key(&blob(&[b"ssh-rsa", &[1; 32]])).is_none());
+        assert_eq!(
+            parse_ed25519_key(&blob(&[SSH_ED25519, &[1; 32]])),
+            Some(&[1; 32][..])
+        );
+        assert!(parse_ed25519_key(&blob(&[SSH_ED25519, &[1; 31]])).is_none());
+        assert!(parse_ed25519_key(&blob(&[SSH_ED25519, &[1; 32], b"extra"])).is_none());
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `TargetCache`, an in-memory cache of verified target contents that
+//! [`Repository::read_target`](crate::Repository::read_target) consults before fetching a target.
+
+use crate::error::Result;
+use crate::TargetName;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{ready, Context, Poll};
+
+/// A cache of target contents, set with
+/// [`RepositoryLoader::target_cache`](crate::RepositoryLoader::target_cache).
+///
+/// Targets are keyed by name and hash, so a target that changes in a later version of the
+/// repository is fetched again. A target is only added once it has been read to the end and has
+/// passed every length and hash check. When adding a target would take the cache over its size,
+/// the least recently read targets are evicted; a target larger than the whole cache is never
+/// added.
+///
+/// Clones share the same cache, so one cache can serve repeated loads of a repository.
+#[derive(Clone)]
+pub struct TargetCache {
+    max_size: u64,
+    inner: Arc<Mutex<CacheState>>,
+}
+
+type CacheKey = (TargetName, Vec<u8>);
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, Entry>,
+    size: u64,
+    /// Incremented on every read, to order entries for eviction.
+    clock: u64,
+}
+
+struct Entry {
+    data: Bytes,
+    last_used: u64,
+}
+
+impl TargetCache {
+    /// Creates an empty cache that holds at most `max_size` bytes of target contents.
+    pub fn new(max_size: u64) -> Self {
+        Self {
+            max_size,
+            inner: Arc::default(),
+        }
+    }
+
+    /// The most bytes of target contents that the cache holds.
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// The bytes of target contents held in the cache.
+    pub fn size(&self) -> u64 {
+        self.lock().size
+    }
+
+    /// The number of targets held in the cache.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns `true` if the cache holds no targets.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Evicts every target.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.size = 0;
+    }
+
+    /// Returns the contents of the target `name` with the hash `sha256`, if cached.
+    pub(crate) fn get(&self, name: &TargetName, sha256: &[u8]) -> Option<Bytes> {
+        let mut inner = self.lock();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let entry = inner.entries.get_mut(&(name.clone(), sha256.to_vec()))?;
+        entry.last_used = clock;
+        Some(entry.data.clone())
+    }
+
+    /// Wraps the fetched contents of a target so that they are added to the cache if the stream
+    /// ends without an error.
+    pub(crate) fn read_through(
+        &self,
+        name: TargetName,
+        sha256: &[u8],
+        stream: BoxStream<'static, Result<Bytes>>,
+    ) -> BoxStream<'static, Result<Bytes>> {
+        ReadThrough {
+            stream,
+            cache: self.clone(),
+            key: Some((name, sha256.to_vec())),
+            buffer: Vec::new(),
+        }
+        .boxed()
+    }
+
+    fn insert(&self, key: CacheKey, data: Bytes) {
+        let length = data.len() as u64;
+        if length > self.max_size {
+            return;
+        }
+        let mut inner = self.lock();
+        if let Some(old) = inner.entries.remove(&key) {
+            inner.size -= old.data.len() as u64;
+        }
+        while inner.size + length > self.max_size {
+            let Some(oldest) = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(evicted) = inner.entries.remove(&oldest) {
+                inner.size -= evicted.data.len() as u64;
+            }
+        }
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.size += length;
+        inner.entries.insert(key, Entry { data, last_used });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        // The entries are consistent between statements, so a panic elsewhere can't corrupt them
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for TargetCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TargetCache")
+            .field("max_size", &self.max_size)
+            .field("size", &self.size())
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A target's contents being fetched, which are copied into the cache once they've been verified.
+struct ReadThrough {
+    stream: BoxStream<'static, Result<Bytes>>,
+    cache: TargetCache,
+    /// `None` once the contents are known not to be cacheable.
+    key: Option<CacheKey>,
+    buffer: Vec<u8>,
+}
+
+impl Stream for ReadThrough {
+    type Item = Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let item = ready!(this.stream.poll_next_unpin(cx));
+        match &item {
+            Some(Ok(chunk)) if this.key.is_some() => {
+                if (this.buffer.len() + chunk.len()) as u64 > this.cache.max_size {
+                    this.key = None;
+                    this.buffer = Vec::new();
+                } else {
+                    this.buffer.extend_from_slice(chunk);
+                }
+            }
+            Some(Ok(_)) => {}
+            Some(Err(_)) => {
+                this.key = None;
+                this.buffer = Vec::new();
+            }
+            None => {
+                if let Some(key) = this.key.take() {
+                    this.cache
+                        .insert(key, Bytes::from(std::mem::take(&mut this.buffer)));
+                }
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &str) -> TargetName {
+        TargetName::new(name).unwrap()
+    }
+
+    #[test]
+    fn evicts_least_recently_read() {
+        let cache = TargetCache::new(10);
+        cache.insert((name("a"), vec![1]), Bytes::from_static(b"aaaa"));
+        cache.insert((name("b"), vec![2]), Bytes::from_static(b"bbbb"));
+        assert!(cache.get(&name("a"), &[1]).is_some());
+        cache.insert((name("c"), vec![3]), Bytes::from_static(b"cccc"));
+        assert!(cache.get(&name("b"), &[2]).is_none());
+        assert!(cache.get(&name("a"), &[1]).is_some());
+        assert_eq!(cache.size(), 8);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn keyed_by_hash() {
+        let cache = TargetCache::new(10);
+        cache.insert((name("a"), vec![1]), Bytes::from_static(b"aaaa"));
+        assert!(cache.get(&name("a"), &[2]).is_none());
+        cache.insert((name("a"), vec![1]), Bytes::from_static(b"aa"));
+        assert_eq!(cache.size(), 2);
+    }
+
+    #[test]
+    fn oversized_target_not_cached() {
+        let cache = TargetCache::new(3);
+        cache.insert((name("a"), vec![1]), Bytes::from_static(b"aaaa"));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `TargetResolution`, which explains how a target name was looked up in the delegation
+//! tree, and why the lookup failed if it did.
+
+use crate::schema::{Target, Targets};
+use crate::TargetName;
+
+/// How [`Repository::resolve_target`](crate::Repository::resolve_target) looked for a target,
+/// and what it found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetResolution {
+    /// Each role that was considered, in the order the lookup considered them.
+    pub steps: Vec<ResolutionStep>,
+    /// The result of the lookup.
+    pub outcome: ResolutionOutcome,
+}
+
+/// A role that was considered while resolving a target name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolutionStep {
+    /// The role's metadata was searched for the target.
+    Searched {
+        /// The name of the role.
+        role: String,
+        /// Whether the role lists the target.
+        listed: bool,
+    },
+    /// The delegated role was skipped, along with the roles it delegates to, because its paths
+    /// don't match the target name.
+    PathsNotMatched {
+        /// The name of the role.
+        role: String,
+    },
+    /// The delegated role's paths match the target name, but its metadata isn't loaded, so it
+    /// couldn't be searched.
+    MetadataMissing {
+        /// The name of the role.
+        role: String,
+    },
+}
+
+/// The result of resolving a target name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolutionOutcome {
+    /// The target is listed by `role`, and this is the listing
+    /// [`Repository::read_target`](crate::Repository::read_target) uses.
+    Found {
+        /// The name of the role.
+        role: String,
+        /// The role's listing of the target.
+        target: Box<Target>,
+    },
+    /// The top-level targets role doesn't list the target, and no delegated role's paths cover
+    /// its name.
+    NotDelegated,
+    /// Each role in `roles` covers the name and was searched, but none of them list the target.
+    NotListed {
+        /// The names of the roles that were searched.
+        roles: Vec<String>,
+    },
+    /// The target wasn't found, and `roles`, whose paths cover the name, couldn't be searched
+    /// because their metadata isn't loaded.
+    RoleMetadataMissing {
+        /// The names of the roles that couldn't be searched.
+        roles: Vec<String>,
+    },
+    /// The target is listed by each role in `roles`, but none of them are reachable for its name:
+    /// the paths delegated to the role, or to one of the roles above it, don't cover the name.
+    Unreachable {
+        /// The names of the roles that list the target.
+        roles: Vec<String>,
+    },
+}
+
+impl TargetResolution {
+    /// Resolves `name` the way `Targets::find_target` does, starting from the top-level targets
+    /// role `targets`.
+    pub(crate) fn resolve(targets: &Targets, name: &TargetName) -> Self {
+        let mut steps = Vec::new();
+        if let Some((role, target)) = search(targets, "targets", name, &mut steps) {
+            return Self {
+                steps,
+                outcome: ResolutionOutcome::Found {
+                    role,
+                    target: Box::new(target.clone()),
+                },
+            };
+        }
+
+        let missing = roles_where(&steps, |step| match step {
+            ResolutionStep::MetadataMissing { role } => Some(role),
+            _ => None,
+        });
+        let outcome = if missing.is_empty() {
+            let mut listing = Vec::new();
+            listing_roles(targets, "targets", name, &mut listing);
+            if listing.is_empty() {
+                let searched = roles_where(&steps, |step| match step {
+                    ResolutionStep::Searched { role, .. } => Some(role),
+                    _ => None,
+                });
+                if searched.len() == 1 {
+                    ResolutionOutcome::NotDelegated
+                } else {
+                    ResolutionOutcome::NotListed { roles: searched }
+                }
+            } else {
+                ResolutionOutcome::Unreachable { roles: listing }
+            }
+        } else {
+            ResolutionOutcome::RoleMetadataMissing { roles: missing }
+        };
+        Self { steps, outcome }
+    }
+
+    /// The target, if it was found.
+    pub fn target(&self) -> Option<&Target> {
+        match &self.outcome {
+            ResolutionOutcome::Found { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+}
+
+/// Searches `targets`, the metadata of `role`, and then the roles it delegates `name` to, in
+/// order, recording each role it considers in `steps`.
+fn search<'a>(
+    targets: &'a Targets,
+    role: &str,
+    name: &TargetName,
+    steps: &mut Vec<ResolutionStep>,
+) -> Option<(String, &'a Target)> {
+    let found = targets.targets.get(name);
+    steps.push(ResolutionStep::Searched {
+        role: role.to_owned(),
+        listed: found.is_some(),
+    });
+    if let Some(target) = found {
+        return Some((role.to_owned(), target));
+    }
+    for delegated in targets.delegations.iter().flat_map(|d| &d.roles) {
+        if !delegated.target_is_delegated(name) {
+            steps.push(ResolutionStep::PathsNotMatched {
+                role: delegated.name.clone(),
+            });
+            continue;
+        }
+        let Some(delegated_targets) = &delegated.targets else {
+            steps.push(ResolutionStep::MetadataMissing {
+                role: delegated.name.clone(),
+            });
+            continue;
+        };
+        if let Some(found) = search(&delegated_targets.signed, &delegated.name, name, steps) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Adds each role in the tree under `targets` that lists `name`, whatever its paths, to
+/// `listing`.
+fn listing_roles(targets: &Targets, role: &str, name: &TargetName, listing: &mut Vec<String>) {
+    if targets.targets.contains_key(name) {
+        listing.push(role.to_owned());
+    }
+    for delegated in targets.delegations.iter().flat_map(|d| &d.roles) {
+        if let Some(delegated_targets) = &delegated.targets {
+            listing_roles(&delegated_targets.signed, &delegated.name, name, listing);
+        }
+    }
+}
+
+fn roles_where<'a>(
+    steps: &'a [ResolutionStep],
+    role: impl Fn(&'a ResolutionStep) -> Option<&'a String>,
+) -> Vec<String> {
+    steps.iter().filter_map(role).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{DelegatedRole, PathPattern, PathSet, Signed};
+    use chrono::Utc;
+    use std::num::NonZeroU64;
+
+    fn targets(listed: &[&str]) -> Targets {
+        let mut targets = Targets::new("1.0.0".to_owned(), NonZeroU64::new(1).unwrap(), Utc::now());
+        for name in listed {
+            let target = serde_json::from_value(serde_json::json!({
+                "length": 1,
+                "hashes": { "sha256": "00" },
+            }))
+            .unwrap();
+            targets.targets.insert(name_of(name), target);
+        }
+        targets
+    }
+
+    fn delegate(parent: &mut Targets, name: &str, pattern: &str, child: Option<Targets>) {
+        parent
+            .delegations
+            .as_mut()
+            .unwrap()
+            .roles
+            .push(DelegatedRole {
+                name: name.to_owned(),
+                keyids: Vec::new(),
+                threshold: NonZeroU64::new(1).unwrap(),
+                paths: PathSet::Paths(vec![PathPattern::new(pattern).unwrap()]),
+                terminating: false,
+                targets: child.map(|signed| Signed {
+                    signed,
+                    signatures: Vec::new(),
+                }),
+            });
+    }
+
+    fn name_of(name: &str) -> TargetName {
+        TargetName::new(name).unwrap()
+    }
+
+    fn searched(role: &str, listed: bool) -> ResolutionStep {
+        ResolutionStep::Searched {
+            role: role.to_owned(),
+            listed,
+        }
+    }
+
+    fn skipped(role: &str) -> ResolutionStep {
+        ResolutionStep::PathsNotMatched {
+            role: role.to_owned(),
+        }
+    }
+
+    fn roles(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| (*name).to_owned()).collect()
+    }
+
+    /// `targets` delegates `a/*` to `a`, which also lists `b/stray.txt`, and `b/*` to `b`, whose
+    /// metadata isn't loaded.
+    fn tree() -> Targets {
+        let mut top = targets(&["top.txt"]);
+        delegate(
+            &mut top,
+            "a",
+            "a/*",
+            Some(targets(&["a/file.txt", "b/stray.txt", "c/stray.txt"])),
+        );
+        delegate(&mut top, "b", "b/*", None);
+        top
+    }
+
+    #[test]
+    fn found_in_delegated_role() {
+        let resolution = TargetResolution::resolve(&tree(), &name_of("a/file.txt"));
+        assert_eq!(
+            resolution.steps,
+            [searched("targets", false), searched("a", true)]
+        );
+        assert!(matches!(
+            resolution.outcome,
+            ResolutionOutcome::Found { ref role, .. } if role == "a"
+        ));
+        assert!(resolution.target().is_some());
+    }
+
+    #[test]
+    fn not_delegated() {
+        let resolution = TargetResolution::resolve(&tree(), &name_of("d/file.txt"));
+        assert_eq!(
+            resolution.steps,
+            [searched("targets", false), skipped("a"), skipped("b")]
+        );
+        assert_eq!(resolution.outcome, ResolutionOutcome::NotDelegated);
+        assert!(resolution.target().is_none());
+    }
+
+    #[test]
+    fn not_listed() {
+        let resolution = TargetResolution::resolve(&tree(), &name_of("a/missing.txt"));
+        assert_eq!(
+            resolution.outcome,
+            ResolutionOutcome::NotListed {
+                roles: roles(&["targets", "a"])
+            }
+        );
+    }
+
+    #[test]
+    fn role_metadata_missing() {
+        let resolution = TargetResolution::resolve(&tree(), &name_of("b/stray.txt"));
+        assert_eq!(
+            resolution.steps,
+            [
+                searched("targets", false),
+                skipped("a"),
+                ResolutionStep::MetadataMissing {
+                    role: "b".to_owned()
+                },
+            ]
+        );
+        assert_eq!(
+            resolution.outcome,
+            ResolutionOutcome::RoleMetadataMissing {
+                roles: roles(&["b"])
+            }
+        );
+    }
+
+    #[test]
+    fn unreachable() {
+        let resolution = TargetResolution::resolve(&tree(), &name_of("c/stray.txt"));
+        assert_eq!(
+            resolution.outcome,
+            ResolutionOutcome::Unreachable {
+                roles: roles(&["a"])
+            }
+        );
+    }
+}
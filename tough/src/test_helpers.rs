@@ -0,0 +1,141 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides helpers for creating throwaway repositories in tests, enabled with the `test-helpers`
+//! feature. Nothing here is fit for signing a real repository.
+
+use crate::editor::signed::SignedRole;
+use crate::error::{self, Result};
+use crate::key_source::KeySource;
+use crate::schema::key::Key;
+use crate::schema::{KeyHolder, RoleKeys, RoleType, Root};
+use crate::sign::{parse_keypair, Sign};
+use async_trait::async_trait;
+use aws_lc_rs::rand::SystemRandom;
+use aws_lc_rs::signature::Ed25519KeyPair;
+use chrono::{DateTime, Utc};
+use snafu::ResultExt;
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+
+/// A version 1 root.json in which a single, newly generated Ed25519 key signs for every role with a
+/// threshold of 1, along with that key.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use chrono::{Duration, Utc};
+/// use tough::editor::RepositoryEditor;
+/// use tough::test_helpers::DegenerateRoot;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let root = DegenerateRoot::new(false, Utc::now() + Duration::days(7))
+///     .await
+///     .unwrap();
+/// let root_path = dir.path().join("root.json");
+/// std::fs::write(&root_path, root.root().buffer()).unwrap();
+/// let editor = RepositoryEditor::new(&root_path).await.unwrap();
+/// // Sign the repository with `root.key_source()`.
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DegenerateRoot {
+    root: SignedRole<Root>,
+    key: String,
+    public_key: Key,
+}
+
+impl DegenerateRoot {
+    /// Generates a key and a root that lists it for every role and expires at `expires`.
+    pub async fn new(consistent_snapshot: bool, expires: DateTime<Utc>) -> Result<Self> {
+        let document = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .context(error::KeyGenerateSnafu)?;
+        let key = pem::encode(&pem::Pem::new("PRIVATE KEY", document.as_ref()));
+        let public_key = parse_keypair(key.as_bytes())?.tuf_key();
+        let key_id = public_key.key_id().context(error::JsonSerializationSnafu)?;
+
+        let role_keys = RoleKeys {
+            keyids: vec![key_id.clone()],
+            threshold: NonZeroU64::MIN,
+            _extra: HashMap::new(),
+        };
+        let root = Root {
+            spec_version: crate::editor::SPEC_VERSION.to_owned(),
+            consistent_snapshot,
+            version: NonZeroU64::MIN,
+            expires,
+            keys: HashMap::from([(key_id, public_key.clone())]),
+            roles: [
+                RoleType::Root,
+                RoleType::Snapshot,
+                RoleType::Targets,
+                RoleType::Timestamp,
+            ]
+            .iter()
+            .map(|role| (*role, role_keys.clone()))
+            .collect(),
+            unrecognized_roles: HashMap::new(),
+            _extra: HashMap::new(),
+        };
+        let keys: [Box<dyn KeySource>; 1] = [Box::new(PemKeySource { pem: key.clone() })];
+        let root = SignedRole::new(
+            root.clone(),
+            &KeyHolder::Root(root),
+            &keys,
+            &SystemRandom::new(),
+        )
+        .await?;
+        Ok(Self {
+            root,
+            key,
+            public_key,
+        })
+    }
+
+    /// The signed root.json.
+    pub fn root(&self) -> &SignedRole<Root> {
+        &self.root
+    }
+
+    /// The private key, as a PKCS#8 PEM document.
+    pub fn key_pem(&self) -> &str {
+        &self.key
+    }
+
+    /// The public key, as it's listed in root.json.
+    pub fn public_key(&self) -> &Key {
+        &self.public_key
+    }
+
+    /// A [`KeySource`] for the private key, which can sign any role of a repository with this
+    /// root. It can't be written to.
+    pub fn key_source(&self) -> Box<dyn KeySource> {
+        Box::new(PemKeySource {
+            pem: self.key.clone(),
+        })
+    }
+}
+
+/// A key held in memory as a PEM document.
+#[derive(Debug)]
+struct PemKeySource {
+    pem: String,
+}
+
+#[async_trait]
+impl KeySource for PemKeySource {
+    async fn as_sign(
+        &self,
+    ) -> std::result::Result<Box<dyn Sign>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        Ok(Box::new(parse_keypair(self.pem.as_bytes())?))
+    }
+
+    async fn write(
+        &self,
+        _value: &str,
+        _key_id_hex: &str,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        Err("a degenerate root's key can't be replaced".into())
+    }
+}
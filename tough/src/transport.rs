@@ -1,3 +1,4 @@
+use crate::filename_encoding::decode_non_ascii;
 use crate::SafeUrlPath;
 #[cfg(feature = "http")]
 use crate::{HttpTransport, HttpTransportBuilder};
@@ -8,9 +9,10 @@ use futures::{StreamExt, TryStreamExt};
 use futures_core::Stream;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
-use std::io::{self, ErrorKind};
+use std::io::{self, ErrorKind, SeekFrom};
 use std::path::Path;
 use std::pin::Pin;
+use tokio::io::AsyncSeekExt;
 use tokio_util::io::ReaderStream;
 use url::Url;
 
@@ -45,6 +47,22 @@ impl<S: Stream<Item = Result<Bytes, E>> + Send, E: Send> IntoVec<E> for S {
 pub trait Transport: Debug + DynClone + Send + Sync {
     /// Opens a `Read` object for the file specified by `url`.
     async fn fetch(&self, url: Url) -> Result<TransportStream, TransportError>;
+
+    /// Opens a `Read` object for the file specified by `url`, starting at byte `offset`, so that
+    /// an interrupted download can be resumed without fetching its beginning again.
+    ///
+    /// The default implementation can only start at the beginning of the file, and returns a
+    /// [`TransportErrorKind::RangeNotSupported`] error for any other `offset`.
+    async fn fetch_range(&self, url: Url, offset: u64) -> Result<TransportStream, TransportError> {
+        if offset == 0 {
+            self.fetch(url).await
+        } else {
+            Err(TransportError::new(
+                TransportErrorKind::RangeNotSupported,
+                url,
+            ))
+        }
+    }
 }
 
 // Implements `Clone` for `Transport` trait objects (i.e. on `Box::<dyn Clone>`). To facilitate
@@ -75,6 +93,9 @@ pub enum TransportErrorKind {
     /// transports it might be less obvious, but the intent of `FileNotFound` is to indicate that
     /// the file probably doesn't exist.
     FileNotFound,
+    /// The [`Transport`] can't fetch part of a file, as [`Transport::fetch_range`] was asked to,
+    /// e.g. because the server ignored the requested range.
+    RangeNotSupported,
     /// The transport failed for any other reason, e.g. IO error, HTTP broken pipe, etc.
     Other,
 }
@@ -87,6 +108,7 @@ impl Display for TransportErrorKind {
             match self {
                 TransportErrorKind::UnsupportedUrlScheme => "unsupported URL scheme",
                 TransportErrorKind::FileNotFound => "file not found",
+                TransportErrorKind::RangeNotSupported => "range not supported",
                 TransportErrorKind::Other => "other",
             }
         )
@@ -157,7 +179,7 @@ impl Display for TransportError {
 
 impl Error for TransportError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        self.source.as_ref().map(|e| e.as_ref() as &(dyn Error))
+        self.source.as_ref().map(|e| e.as_ref() as &dyn Error)
     }
 }
 
@@ -169,10 +191,14 @@ pub struct FilesystemTransport;
 
 impl FilesystemTransport {
     async fn open(
-        file_path: impl AsRef<Path>,
+        file_path: &Path,
+        offset: u64,
     ) -> Result<impl Stream<Item = Result<Bytes, io::Error>> + Send, io::Error> {
         // Open the file
-        let f = tokio::fs::File::open(file_path).await?;
+        let mut f = tokio::fs::File::open(file_path).await?;
+        if offset > 0 {
+            f.seek(SeekFrom::Start(offset)).await?;
+        }
 
         // And convert to stream
         let reader = tokio::io::BufReader::new(f);
@@ -185,6 +211,10 @@ impl FilesystemTransport {
 #[async_trait]
 impl Transport for FilesystemTransport {
     async fn fetch(&self, url: Url) -> Result<TransportStream, TransportError> {
+        self.fetch_range(url, 0).await
+    }
+
+    async fn fetch_range(&self, url: Url, offset: u64) -> Result<TransportStream, TransportError> {
         // If the scheme isn't "file://", reject
         if url.scheme() != "file" {
             return Err(TransportError::new(
@@ -196,7 +226,14 @@ impl Transport for FilesystemTransport {
         let file_path = url.safe_url_filepath();
 
         // Open the file
-        let stream = Self::open(file_path).await;
+        let mut stream = Self::open(&file_path, offset).await;
+        // Non-ASCII characters in a filename, such as those kept by `UnicodePreservingEncoding`,
+        // are percent-encoded in the URL.
+        if matches!(&stream, Err(e) if e.kind() == ErrorKind::NotFound) {
+            if let Some(decoded) = file_path.to_str().and_then(decode_non_ascii) {
+                stream = Self::open(Path::new(&decoded), offset).await;
+            }
+        }
 
         // And map to `TransportError`
         let map_io_err = move |e: io::Error| -> TransportError {
@@ -217,7 +254,10 @@ impl Transport for FilesystemTransport {
 
 /// A Transport that provides support for both local files and, if the `http` feature is enabled,
 /// HTTP-transported files.
-#[derive(Debug, Clone, Copy)]
+///
+/// With the `http` feature, clones share the connection pool of the `HttpTransport` they hold.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "http"), derive(Copy))]
 pub struct DefaultTransport {
     file: FilesystemTransport,
     #[cfg(feature = "http")]
@@ -257,7 +297,18 @@ impl Transport for DefaultTransport {
     async fn fetch(&self, url: Url) -> Result<TransportStream, TransportError> {
         match url.scheme() {
             "file" => self.file.fetch(url).await,
-            "http" | "https" => self.handle_http(url).await,
+            "http" | "https" => self.handle_http(url, 0).await,
+            _ => Err(TransportError::new(
+                TransportErrorKind::UnsupportedUrlScheme,
+                url,
+            )),
+        }
+    }
+
+    async fn fetch_range(&self, url: Url, offset: u64) -> Result<TransportStream, TransportError> {
+        match url.scheme() {
+            "file" => self.file.fetch_range(url, offset).await,
+            "http" | "https" => self.handle_http(url, offset).await,
             _ => Err(TransportError::new(
                 TransportErrorKind::UnsupportedUrlScheme,
                 url,
@@ -269,7 +320,7 @@ impl Transport for DefaultTransport {
 impl DefaultTransport {
     #[cfg(not(feature = "http"))]
     #[allow(clippy::trivially_copy_pass_by_ref, clippy::unused_self)]
-    async fn handle_http(&self, url: Url) -> Result<TransportStream, TransportError> {
+    async fn handle_http(&self, url: Url, _offset: u64) -> Result<TransportStream, TransportError> {
         Err(TransportError::new_with_cause(
             TransportErrorKind::UnsupportedUrlScheme,
             url,
@@ -278,7 +329,7 @@ impl DefaultTransport {
     }
 
     #[cfg(feature = "http")]
-    async fn handle_http(&self, url: Url) -> Result<TransportStream, TransportError> {
-        self.http.fetch(url).await
+    async fn handle_http(&self, url: Url, offset: u64) -> Result<TransportStream, TransportError> {
+        self.http.fetch_range(url, offset).await
     }
 }
@@ -0,0 +1,269 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Optional RFC 3161 countersignatures for signed metadata.
+//!
+//! A time-stamping authority (TSA) signs the sha256 digest of a metadata file along with the time
+//! it saw the request, which lets an auditor later prove when a given metadata version existed.
+//! Tokens are stored as companion files next to the metadata (`<filename>.tsr`, the raw DER
+//! `TimeStampResp`) so that TUF clients, which do not know about them, are unaffected.
+//!
+//! **Tokens are not authenticated.** The checks in this module only confirm that a response is
+//! shaped like a granted `TimeStampResp` that mentions the expected digest (and, for
+//! [`countersign`], the request's nonce). They do not parse the CMS `SignedData`, check the TSA's
+//! signature, or validate its certificate chain, so anyone can produce a token that passes them.
+//! Treat a stored token as evidence only after verifying it with a tool such as
+//! `openssl ts -verify` and the TSA's certificate.
+
+use crate::der::{read_tlv, tlv, BOOLEAN, INTEGER, OCTET_STRING, SEQUENCE};
+use crate::error::{self, Result};
+use async_trait::async_trait;
+use aws_lc_rs::digest::{digest, SHA256};
+use aws_lc_rs::rand::{SecureRandom, SystemRandom};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::fmt::Debug;
+
+/// The extension used for companion files containing a timestamp response.
+pub const TOKEN_EXTENSION: &str = "tsr";
+
+/// DER encoding of the sha256 `AlgorithmIdentifier` (OID 2.16.840.1.101.3.4.2.1, NULL params).
+const SHA256_ALGORITHM_IDENTIFIER: &[u8] = &[
+    0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00,
+];
+
+/// A service that countersigns a DER-encoded RFC 3161 `TimeStampReq`, returning the DER-encoded
+/// `TimeStampResp`.
+#[async_trait]
+pub trait TimestampAuthority: Debug + Send + Sync {
+    /// Submits `request` to the authority and returns its raw response.
+    async fn timestamp(
+        &self,
+        request: &[u8],
+    ) -> std::result::Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>>;
+}
+
+/// A `TimestampAuthority` reached over HTTP, as described in section 3.4 of RFC 3161. Every query
+/// made through an authority, or its clones, shares one HTTP client and its connection pool.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct HttpTimestampAuthority {
+    url: url::Url,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "http")]
+impl HttpTimestampAuthority {
+    /// Creates an authority that timestamp queries are sent to with an HTTP POST to `url`.
+    pub fn new(url: url::Url) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// The URL that timestamp queries are sent to.
+    pub fn url(&self) -> &url::Url {
+        &self.url
+    }
+}
+
+#[cfg(feature = "http")]
+#[async_trait]
+impl TimestampAuthority for HttpTimestampAuthority {
+    async fn timestamp(
+        &self,
+        request: &[u8],
+    ) -> std::result::Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header("Content-Type", "application/timestamp-query")
+            .body(request.to_vec())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// Builds a DER-encoded `TimeStampReq` over a sha256 `digest`, asking the TSA to include its
+/// certificate in the response.
+pub fn timestamp_request(digest: &[u8]) -> Vec<u8> {
+    build_request(digest, None)
+}
+
+/// Builds a `TimeStampReq` over `digest`, with `nonce` as its DER-encoded nonce if given.
+fn build_request(digest: &[u8], nonce: Option<&[u8]>) -> Vec<u8> {
+    let mut message_imprint = SHA256_ALGORITHM_IDENTIFIER.to_vec();
+    message_imprint.extend(tlv(OCTET_STRING, digest));
+
+    let mut request = tlv(INTEGER, &[1]);
+    request.extend(tlv(SEQUENCE, &message_imprint));
+    if let Some(nonce) = nonce {
+        request.extend(nonce);
+    }
+    request.extend(tlv(BOOLEAN, &[0xff]));
+    tlv(SEQUENCE, &request)
+}
+
+/// Obtains a timestamp response over `metadata` (the exact bytes written to disk) from `tsa` and
+/// checks it with [`verify_token`] before returning it. The request carries a random nonce, and
+/// the token must echo it, so that a response to an earlier request isn't accepted.
+///
+/// As with `verify_token`, the TSA's signature over the token is not checked.
+pub async fn countersign(tsa: &dyn TimestampAuthority, metadata: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; 8];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .context(error::RandomSnafu)?;
+    // A positive INTEGER, so that its DER encoding is exactly these bytes
+    nonce[0] = (nonce[0] & 0x7f) | 0x01;
+    let nonce = tlv(INTEGER, &nonce);
+
+    let response = tsa
+        .timestamp(&build_request(
+            digest(&SHA256, metadata).as_ref(),
+            Some(&nonce),
+        ))
+        .await
+        .context(error::TimestampAuthoritySnafu)?;
+    let token = checked_token(metadata, &response)?;
+    ensure!(contains(token, &nonce), error::TimestampNonceMismatchSnafu);
+    Ok(response)
+}
+
+/// Checks that `response` is a granted `TimeStampResp` whose token mentions the sha256 digest of
+/// `metadata`.
+///
+/// This is a consistency check, not authentication: the token's CMS signature and the TSA's
+/// certificate are not verified, so a forged token passes. See the [module docs](self).
+pub fn verify_token(metadata: &[u8], response: &[u8]) -> Result<()> {
+    checked_token(metadata, response).map(|_| ())
+}
+
+/// Does the checks of [`verify_token`], returning the token.
+fn checked_token<'a>(metadata: &[u8], response: &'a [u8]) -> Result<&'a [u8]> {
+    let (tag, resp, _) = read_tlv(response).context(error::TimestampMalformedSnafu)?;
+    ensure!(tag == SEQUENCE, error::TimestampMalformedSnafu);
+    let (tag, status_info, token) = read_tlv(resp).context(error::TimestampMalformedSnafu)?;
+    ensure!(tag == SEQUENCE, error::TimestampMalformedSnafu);
+    let (tag, status, _) = read_tlv(status_info).context(error::TimestampMalformedSnafu)?;
+    ensure!(tag == INTEGER, error::TimestampMalformedSnafu);
+
+    // PKIStatus 0 is "granted" and 1 is "grantedWithMods"; anything else carries no token.
+    let status = status
+        .iter()
+        .fold(0_u64, |acc, byte| (acc << 8) | u64::from(*byte));
+    ensure!(status <= 1, error::TimestampRejectedSnafu { status });
+
+    // The token's TSTInfo repeats the message imprint from the request. Scanning for the hashed
+    // message avoids a full CMS parser while still tying the token to this exact metadata.
+    let hashed_message = tlv(OCTET_STRING, digest(&SHA256, metadata).as_ref());
+    ensure!(
+        contains(token, &hashed_message),
+        error::TimestampImprintMismatchSnafu
+    );
+    Ok(token)
+}
+
+/// Whether `needle` appears in `haystack`.
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// Returns the companion file name used to store the timestamp token for `metadata_filename`.
+pub fn token_filename(metadata_filename: &str) -> String {
+    format!("{metadata_filename}.{TOKEN_EXTENSION}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal granted response whose "token" is just the message imprint.
+    fn granted_response(metadata: &[u8], status: u8) -> Vec<u8> {
+        let status_info = tlv(SEQUENCE, &tlv(INTEGER, &[status]));
+        let token = tlv(
+            SEQUENCE,
+            &tlv(OCTET_STRING, digest(&SHA256, metadata).as_ref()),
+        );
+        tlv(SEQUENCE, &[status_info, token].concat())
+    }
+
+    #[test]
+    fn request_encoding() {
+        let request = timestamp_request(&[0xab; 32]);
+        let (tag, body, rest) = read_tlv(&request).unwrap();
+        assert_eq!(tag, SEQUENCE);
+        assert!(rest.is_empty());
+        assert_eq!(body.len(), 3 + 2 + 15 + 34 + 3);
+    }
+
+    #[test]
+    fn verify_granted() {
+        verify_token(b"metadata", &granted_response(b"metadata", 0)).unwrap();
+    }
+
+    #[test]
+    fn verify_rejected() {
+        assert!(verify_token(b"metadata", &granted_response(b"metadata", 2)).is_err());
+    }
+
+    #[test]
+    fn verify_wrong_digest() {
+        assert!(verify_token(b"metadata", &granted_response(b"other", 0)).is_err());
+    }
+
+    /// A fake authority that grants every request with a "token" holding the whole request, so
+    /// it covers the request's digest and echoes its nonce; or, if `replay` is set, a token that
+    /// only covers the digest, as if it answered an earlier request.
+    #[derive(Debug)]
+    struct FakeAuthority {
+        replay: bool,
+    }
+
+    #[async_trait]
+    impl TimestampAuthority for FakeAuthority {
+        async fn timestamp(
+            &self,
+            request: &[u8],
+        ) -> std::result::Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>>
+        {
+            let (_, body, _) = read_tlv(request).unwrap();
+            let (_, _, body) = read_tlv(body).unwrap();
+            let (_, imprint, _) = read_tlv(body).unwrap();
+            let status_info = tlv(SEQUENCE, &tlv(INTEGER, &[0]));
+            let token = if self.replay {
+                tlv(SEQUENCE, &imprint[SHA256_ALGORITHM_IDENTIFIER.len()..])
+            } else {
+                tlv(SEQUENCE, request)
+            };
+            Ok(tlv(SEQUENCE, &[status_info, token].concat()))
+        }
+    }
+
+    #[tokio::test]
+    async fn countersign_checks_nonce() {
+        let response = countersign(&FakeAuthority { replay: false }, b"metadata")
+            .await
+            .unwrap();
+        verify_token(b"metadata", &response).unwrap();
+
+        let err = countersign(&FakeAuthority { replay: true }, b"metadata")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, error::Error::TimestampNonceMismatch { .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn verify_truncated() {
+        let response = granted_response(b"metadata", 0);
+        assert!(verify_token(b"metadata", &response[..response.len() - 1]).is_err());
+    }
+}
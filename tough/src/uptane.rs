@@ -0,0 +1,184 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Provides `UptaneRepositories`, which pairs an [Uptane] director repository with an image
+//! repository.
+//!
+//! In Uptane, the director repository decides which targets a device should install and the image
+//! repository holds the signed images themselves. A device must only install a target if both
+//! repositories agree on it, so [`UptaneRepositories::verify_target_consistency`] checks that the
+//! target is listed in both with the same length and hashes before fetching it from the image
+//! repository.
+//!
+//! [Uptane]: https://uptane.github.io/
+
+use crate::error::{self, Result};
+use crate::schema::Target;
+use crate::transport::IntoVec;
+use crate::{Repository, RepositoryLoader, TargetName};
+use bytes::Bytes;
+use futures_core::Stream;
+use snafu::{ensure, OptionExt};
+
+/// A director repository and an image repository that are verified together.
+#[derive(Debug)]
+pub struct UptaneRepositories {
+    director: Repository,
+    image: Repository,
+}
+
+impl UptaneRepositories {
+    /// Pairs two repositories that have already been loaded.
+    pub fn new(director: Repository, image: Repository) -> Self {
+        Self { director, image }
+    }
+
+    /// Loads and verifies the director and image repositories.
+    pub async fn load(director: RepositoryLoader<'_>, image: RepositoryLoader<'_>) -> Result<Self> {
+        Ok(Self::new(director.load().await?, image.load().await?))
+    }
+
+    /// The director repository.
+    pub fn director(&self) -> &Repository {
+        &self.director
+    }
+
+    /// The image repository.
+    pub fn image(&self) -> &Repository {
+        &self.image
+    }
+
+    /// Checks that the target `name` is listed in both repositories with the same length and
+    /// hashes, then fetches it from the image repository.
+    ///
+    /// The returned stream is verified against the image repository's metadata in the same way
+    /// as [`Repository::read_target`], so **consumers must not use data from the stream if it
+    /// returns an error.**
+    pub async fn verify_target_consistency(
+        &self,
+        name: &TargetName,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + IntoVec<error::Error> + Send> {
+        let director_target = find(&self.director, name, "director")?;
+        let image_target = find(&self.image, name, "image")?;
+        check_consistent(name, director_target, image_target)?;
+        self.image
+            .read_target(name)
+            .await?
+            .context(error::UptaneTargetMissingSnafu {
+                name: name.raw(),
+                repository: "image",
+            })
+    }
+}
+
+fn find<'a>(repository: &'a Repository, name: &TargetName, which: &str) -> Result<&'a Target> {
+    repository
+        .targets
+        .signed
+        .find_target(name)
+        .ok()
+        .context(error::UptaneTargetMissingSnafu {
+            name: name.raw(),
+            repository: which,
+        })
+}
+
+/// Fails unless the two listings agree on the length, list a hash with at least one algorithm in
+/// common, and agree on the digest for every algorithm they share.
+fn check_consistent(name: &TargetName, director: &Target, image: &Target) -> Result<()> {
+    ensure!(
+        director.length == image.length,
+        error::UptaneTargetMismatchSnafu {
+            name: name.raw(),
+            field: "length",
+        }
+    );
+    if director.hashes.agrees_with(&image.hashes) {
+        return Ok(());
+    }
+    let image_algorithms = image.hashes.algorithms();
+    let mismatched = director.hashes.algorithms().into_iter().find(|algorithm| {
+        image_algorithms.contains(algorithm)
+            && director.hashes.digest(*algorithm).ok() != image.hashes.digest(*algorithm).ok()
+    });
+    match mismatched {
+        Some(algorithm) => error::UptaneTargetMismatchSnafu {
+            name: name.raw(),
+            field: algorithm.name(),
+        }
+        .fail(),
+        None => error::UptaneNoSharedHashSnafu { name: name.raw() }.fail(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::decoded::Decoded;
+    use crate::schema::Hashes;
+    use std::collections::HashMap;
+
+    fn target(length: u64, sha256: &[u8]) -> Target {
+        Target {
+            length,
+            hashes: Hashes {
+                sha256: Decoded::from(sha256.to_vec()),
+                _extra: HashMap::new(),
+            },
+            custom: HashMap::new(),
+            _extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn mismatched_hash() {
+        let name = TargetName::new("image.bin").unwrap();
+        check_consistent(&name, &target(4, b"abcd"), &target(4, b"abcd")).unwrap();
+        let err = check_consistent(&name, &target(4, b"abcd"), &target(4, b"dcba")).unwrap_err();
+        assert!(
+            matches!(err, error::Error::UptaneTargetMismatch { ref field, .. } if field == "sha256"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn hashes_compared_decoded() {
+        let name = TargetName::new("image.bin").unwrap();
+        let mut upper = target(4, b"");
+        upper
+            .hashes
+            ._extra
+            .insert("sha512".to_owned(), "ABCD".into());
+        let mut lower = target(4, b"");
+        lower
+            .hashes
+            ._extra
+            .insert("sha512".to_owned(), "abcd".into());
+        check_consistent(&name, &upper, &lower).unwrap();
+
+        let err = check_consistent(&name, &target(4, b""), &target(4, b"")).unwrap_err();
+        assert!(
+            matches!(err, error::Error::UptaneNoSharedHash { .. }),
+            "{}",
+            err
+        );
+        let err = check_consistent(&name, &upper, &target(4, b"abcd")).unwrap_err();
+        assert!(
+            matches!(err, error::Error::UptaneNoSharedHash { .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn mismatched_length() {
+        let name = TargetName::new("image.bin").unwrap();
+        let err = check_consistent(&name, &target(4, b"abcd"), &target(5, b"abcd")).unwrap_err();
+        assert!(
+            matches!(err, error::Error::UptaneTargetMismatch { ref field, .. } if field == "length"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+/// Instead of guarding every individual thing with `#[cfg(feature = "blocking")]`, use a module.
+#[cfg(feature = "blocking")]
+mod blocking {
+    use crate::test_utils::{dir_url, test_data};
+    use tempfile::TempDir;
+    use tough::{Prefix, RepositoryLoader, TargetName};
+
+    /// The blocking methods load a repository and read its targets without an async caller, and
+    /// can be called again after the first has built the runtime.
+    #[test]
+    fn load_and_read_blocking() {
+        let base = test_data().join("tuf-reference-impl");
+        let root = std::fs::read(base.join("metadata").join("1.root.json")).unwrap();
+        let expected = std::fs::read(base.join("targets").join("file1.txt")).unwrap();
+        let name = TargetName::new("file1.txt").unwrap();
+
+        for _ in 0..2 {
+            let repo = RepositoryLoader::new(
+                &root,
+                dir_url(base.join("metadata")),
+                dir_url(base.join("targets")),
+            )
+            .load_blocking()
+            .unwrap();
+            let data = repo
+                .read_target_bytes_blocking(&name, expected.len() as u64)
+                .unwrap()
+                .unwrap();
+            assert_eq!(data, expected);
+
+            let outdir = TempDir::new().unwrap();
+            repo.save_target_blocking(&name, outdir.path(), Prefix::None)
+                .unwrap();
+            assert_eq!(
+                std::fs::read(outdir.path().join("file1.txt")).unwrap(),
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use tempfile::TempDir;
+use test_utils::{
+    dir_url, read_to_end, simple_rsa_editor, simple_rsa_root, snakeoil_keys, test_data,
+};
+use tough::{MetadataBundle, RepositoryLoader, TargetName};
+
+async fn read_metadata(name: &str) -> String {
+    tokio::fs::read_to_string(
+        test_data()
+            .join("tuf-reference-impl")
+            .join("metadata")
+            .join(name),
+    )
+    .await
+    .unwrap()
+}
+
+async fn reference_impl_bundle() -> MetadataBundle {
+    MetadataBundle {
+        root: read_metadata("1.root.json").await,
+        timestamp: read_metadata("timestamp.json").await,
+        snapshot: read_metadata("snapshot.json").await,
+        targets: read_metadata("targets.json").await,
+        delegated_targets: vec![
+            ("role1".to_owned(), read_metadata("role1.json").await),
+            ("role2".to_owned(), read_metadata("role2.json").await),
+        ]
+        .into_iter()
+        .collect(),
+    }
+}
+
+/// Test that a repository loads from a bundle when no metadata files are available.
+#[tokio::test]
+async fn load_from_bundle() {
+    let base = test_data().join("tuf-reference-impl");
+    let root = tokio::fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    let empty = TempDir::new().unwrap();
+
+    let repo = RepositoryLoader::new(&root, dir_url(empty.path()), dir_url(base.join("targets")))
+        .metadata_bundle(reference_impl_bundle().await)
+        .load()
+        .await
+        .unwrap();
+
+    assert!(repo.delegated_role("role1").is_some());
+    let file1 = TargetName::new("file1.txt").unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await,
+        &b"This is an example target file."[..]
+    );
+}
+
+/// Test that bundled metadata is verified like fetched metadata.
+#[tokio::test]
+async fn tampered_bundle_is_rejected() {
+    let base = test_data().join("tuf-reference-impl");
+    let root = tokio::fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    let mut bundle = reference_impl_bundle().await;
+    bundle.targets = bundle.targets.replace("file1.txt", "file9.txt");
+
+    assert!(RepositoryLoader::new(
+        &root,
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets"))
+    )
+    .metadata_bundle(bundle)
+    .load()
+    .await
+    .is_err());
+}
+
+/// Test that a bundle emitted by the editor survives serialization and loads.
+#[tokio::test]
+async fn editor_bundle_round_trip() {
+    let targets_dir = test_data().join("tuf-reference-impl").join("targets");
+    let mut editor = simple_rsa_editor(1).await;
+    editor
+        .add_target_paths(vec![targets_dir.join("file3.txt")])
+        .await
+        .unwrap();
+    let signed = editor.sign(&snakeoil_keys()).await.unwrap();
+
+    let outdir = TempDir::new().unwrap();
+    let bundle_path = outdir.path().join("bundle.json");
+    signed.write_bundle(&bundle_path).await.unwrap();
+    let bundle = MetadataBundle::from_slice(&tokio::fs::read(&bundle_path).await.unwrap()).unwrap();
+    assert_eq!(bundle, signed.bundle());
+
+    let root = tokio::fs::read(simple_rsa_root()).await.unwrap();
+    let repo = RepositoryLoader::new(&root, dir_url(outdir.path()), dir_url(&targets_dir))
+        .metadata_bundle(bundle)
+        .load()
+        .await
+        .unwrap();
+    let file3 = TargetName::new("file3.txt").unwrap();
+    assert!(repo.all_targets().any(|(name, _)| name == &file3));
+}
@@ -0,0 +1,119 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::path::Path;
+use tempfile::TempDir;
+use test_utils::{dir_url, sign_and_write, simple_rsa_editor, simple_rsa_root, test_data};
+use tough::schema::{RoleId, RoleType};
+use tough::{Repository, RepositoryLoader, TargetName};
+
+/// Writes a repository at `version` listing `targets` from the reference implementation.
+async fn write_repo(outdir: &Path, version: u64, targets: &[&str]) {
+    let targets_dir = test_data().join("tuf-reference-impl").join("targets");
+    let mut editor = simple_rsa_editor(version).await;
+    editor
+        .add_target_paths(targets.iter().map(|name| targets_dir.join(name)).collect())
+        .await
+        .unwrap();
+    sign_and_write(editor, outdir).await;
+}
+
+async fn load(metadata: &Path, datastore: &Path) -> Repository {
+    let root = tokio::fs::read(simple_rsa_root()).await.unwrap();
+    RepositoryLoader::new(&root, dir_url(metadata), dir_url(metadata))
+        .datastore(datastore)
+        .load()
+        .await
+        .unwrap()
+}
+
+fn names(names: &[&str]) -> Vec<TargetName> {
+    names
+        .iter()
+        .map(|name| TargetName::new(*name).unwrap())
+        .collect()
+}
+
+/// Test that changes are reported relative to the previous load with the same datastore.
+#[tokio::test]
+async fn changes_between_loads() {
+    let metadata = TempDir::new().unwrap();
+    let datastore = TempDir::new().unwrap();
+    write_repo(metadata.path(), 1, &["file1.txt", "file2.txt"]).await;
+
+    let repo = load(metadata.path(), datastore.path()).await;
+    let changes = repo.changes();
+    assert!(!changes.previous_load);
+    assert_eq!(changes.roles.len(), 4);
+    let mut added = changes.added_targets.clone();
+    added.sort();
+    assert_eq!(added, names(&["file1.txt", "file2.txt"]));
+
+    // Nothing changed.
+    let repo = load(metadata.path(), datastore.path()).await;
+    assert!(repo.changes().previous_load);
+    assert!(repo.changes().is_empty(), "{:?}", repo.changes());
+
+    // file1.txt is dropped and file3.txt is added.
+    write_repo(metadata.path(), 2, &["file2.txt", "file3.txt"]).await;
+    let repo = load(metadata.path(), datastore.path()).await;
+    let changes = repo.changes();
+    assert_eq!(changes.added_targets, names(&["file3.txt"]));
+    assert_eq!(changes.removed_targets, names(&["file1.txt"]));
+    assert!(changes.modified_targets.is_empty());
+    assert!(changes.role_changed(&RoleId::StandardRole(RoleType::Targets)));
+    assert!(!changes.role_changed(&RoleId::StandardRole(RoleType::Root)));
+    let targets_change = changes
+        .roles
+        .iter()
+        .find(|change| change.role == RoleId::StandardRole(RoleType::Targets))
+        .unwrap();
+    assert_eq!(targets_change.previous_version, Some(1));
+    assert_eq!(targets_change.current_version, Some(2));
+}
+
+/// Test that the release fingerprint hashes the version of every loaded role, including delegated
+/// roles, and changes when any version does.
+#[tokio::test]
+async fn release_fingerprint() {
+    let base = test_data().join("tuf-reference-impl");
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    let role1 = repo.targets().signed.delegated_targets("role1").unwrap();
+    let role2 = repo.targets().signed.delegated_targets("role2").unwrap();
+    let versions = format!(
+        r#"{{"delegated_roles":{{"role1":{},"role2":{}}},"root":{},"snapshot":{},"targets":{},"timestamp":{}}}"#,
+        role1.signed.version,
+        role2.signed.version,
+        repo.root().signed.version,
+        repo.snapshot().signed.version,
+        repo.targets().signed.version,
+        repo.timestamp().signed.version,
+    );
+    assert_eq!(
+        repo.fingerprint(),
+        hex::encode(aws_lc_rs::digest::digest(
+            &aws_lc_rs::digest::SHA256,
+            versions.as_bytes()
+        ))
+    );
+
+    let metadata = TempDir::new().unwrap();
+    write_repo(metadata.path(), 1, &["file1.txt"]).await;
+    let first = load(metadata.path(), TempDir::new().unwrap().path()).await;
+    let again = load(metadata.path(), TempDir::new().unwrap().path()).await;
+    assert_eq!(first.fingerprint(), again.fingerprint());
+    write_repo(metadata.path(), 2, &["file1.txt"]).await;
+    let second = load(metadata.path(), TempDir::new().unwrap().path()).await;
+    assert_ne!(first.fingerprint(), second.fingerprint());
+}
@@ -0,0 +1,61 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use test_utils::{dir_url, test_data};
+use tough::{FilesystemTransport, RepositoryLoader, ToughContext, Transport, TransportError};
+use url::Url;
+
+/// A transport that serves files from disk and counts its fetches.
+#[derive(Debug, Clone, Default)]
+struct CountingTransport {
+    fetches: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl Transport for CountingTransport {
+    async fn fetch(
+        &self,
+        url: Url,
+    ) -> Result<
+        std::pin::Pin<
+            Box<dyn futures_core::Stream<Item = Result<bytes::Bytes, TransportError>> + Send>,
+        >,
+        TransportError,
+    > {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        FilesystemTransport.fetch(url).await
+    }
+}
+
+/// Loads made from outside a runtime share the context's runtime and transport.
+#[test]
+fn context_shared_between_blocking_loads() {
+    let base = test_data().join("tuf-reference-impl");
+    let root = std::fs::read(base.join("metadata").join("root.json")).unwrap();
+    let transport = CountingTransport::default();
+    let context = ToughContext::with_transport(transport.clone());
+
+    let load = |context: &ToughContext| {
+        let loader = RepositoryLoader::new(
+            &root,
+            dir_url(base.join("metadata")),
+            dir_url(base.join("targets")),
+        )
+        .context(context);
+        context.block_on(loader.load()).unwrap().unwrap()
+    };
+    let repo = load(&context);
+    let fetches = transport.fetches.load(Ordering::SeqCst);
+    assert!(fetches > 0);
+
+    let repo_again = load(&context.clone());
+    assert_eq!(transport.fetches.load(Ordering::SeqCst), fetches * 2);
+    assert_eq!(
+        repo.snapshot().signed.version,
+        repo_again.snapshot().signed.version
+    );
+}
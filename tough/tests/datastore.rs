@@ -0,0 +1,153 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::time::Duration;
+use tempfile::TempDir;
+use test_utils::{dir_url, test_data};
+use tough::error::Error;
+use tough::{
+    Datastore, DatastoreBackend, FilesystemDatastore, MemoryDatastore, RepositoryLoader,
+    ResetAcknowledgement,
+};
+
+mod test_utils;
+
+async fn load_into(datastore: &TempDir) {
+    let base = test_data().join("tuf-reference-impl");
+    RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .datastore(datastore.path())
+    .load()
+    .await
+    .unwrap();
+}
+
+/// Test that `inspect` reports the metadata a load stores, with versions.
+#[tokio::test]
+async fn test_datastore_inspect() {
+    let dir = TempDir::new().unwrap();
+    load_into(&dir).await;
+
+    let entries = Datastore::open(dir.path()).inspect().await.unwrap();
+    let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    assert!(names.contains(&"latest_known_time.json"));
+    let timestamp = entries
+        .iter()
+        .find(|entry| entry.name == "timestamp.json")
+        .unwrap();
+    assert_eq!(timestamp.role.as_deref(), Some("timestamp"));
+    assert!(timestamp.version.is_some());
+    let time = entries
+        .iter()
+        .find(|entry| entry.name == "latest_known_time.json")
+        .unwrap();
+    assert_eq!(time.version, None);
+}
+
+/// Test that `reset` removes one role and `reset_all` removes everything.
+#[tokio::test]
+async fn test_datastore_reset() {
+    let dir = TempDir::new().unwrap();
+    load_into(&dir).await;
+    let datastore = Datastore::open(dir.path());
+    let ack = ResetAcknowledgement::i_understand_this_removes_rollback_protection();
+
+    datastore.reset("snapshot", ack).await.unwrap();
+    let entries = datastore.inspect().await.unwrap();
+    assert!(entries.iter().all(|entry| entry.name != "snapshot.json"));
+    assert!(entries.iter().any(|entry| entry.name == "timestamp.json"));
+
+    // A role that could name a file outside the datastore is refused.
+    for role in [
+        "../timestamp",
+        "metadata/targets",
+        "..\\targets",
+        "targets\0",
+    ] {
+        assert!(datastore.reset(role, ack).await.is_err(), "{}", role);
+    }
+    assert!(datastore
+        .inspect()
+        .await
+        .unwrap()
+        .iter()
+        .any(|entry| entry.name == "targets.json"));
+
+    datastore.reset_all(ack).await.unwrap();
+    assert!(datastore.inspect().await.unwrap().is_empty());
+
+    // The repository can still be loaded afterward.
+    load_into(&dir).await;
+}
+
+/// Test that a `MemoryDatastore` keeps what a load stores, so that a later load can use it.
+#[tokio::test]
+async fn test_memory_datastore() {
+    let base = test_data().join("tuf-reference-impl");
+    let root = tokio::fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    let memory = MemoryDatastore::new();
+    let loader = RepositoryLoader::new(
+        &root,
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .datastore_backend(memory.clone());
+    loader.clone().load().await.unwrap();
+
+    let entries = Datastore::with_backend(memory.clone())
+        .inspect()
+        .await
+        .unwrap();
+    let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    assert!(names.contains(&"timestamp.json"));
+    assert!(names.contains(&"role1.json"));
+
+    // Everything an offline load needs was kept in memory.
+    loader.offline(true).load().await.unwrap();
+}
+
+/// Test that loads sharing a datastore directory take turns, and that a load waiting on another
+/// client's lock gives up after the lock timeout.
+#[tokio::test]
+async fn test_datastore_lock() {
+    let dir = TempDir::new().unwrap();
+    let base = test_data().join("tuf-reference-impl");
+    let root = tokio::fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    let loader = RepositoryLoader::new(
+        &root,
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .datastore(dir.path());
+
+    let lock = FilesystemDatastore::new(dir.path())
+        .lock(Duration::from_secs(1))
+        .await
+        .unwrap();
+    let err = loader
+        .clone()
+        .datastore_lock_timeout(Duration::from_millis(100))
+        .load()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::DatastoreLock { .. }), "{}", err);
+    drop(lock);
+
+    let loads = (0..4).map(|_| loader.clone().load());
+    for result in futures::future::join_all(loads).await {
+        result.unwrap();
+    }
+
+    // Neither the lock file nor any temporary file is reported as a document.
+    let entries = Datastore::open(dir.path()).inspect().await.unwrap();
+    assert!(entries.iter().all(|entry| entry.name.ends_with(".json")));
+}
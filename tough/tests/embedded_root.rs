@@ -0,0 +1,92 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use test_utils::{dir_url, test_data};
+use tough::error::Error;
+use tough::{EmbeddedRootCheck, Repository, RepositoryLoader, Staleness};
+
+async fn rotated_root_repo(root: &[u8]) -> Repository {
+    let base = test_data().join("rotated-root");
+    RepositoryLoader::new(&root, dir_url(&base), dir_url(base.join("targets")))
+        .load()
+        .await
+        .unwrap()
+}
+
+fn date(s: &str) -> DateTime<Utc> {
+    s.parse().unwrap()
+}
+
+/// An embedded root is stale when it is too many versions behind, or expires too soon.
+#[tokio::test]
+async fn embedded_root_staleness() {
+    let base = test_data().join("rotated-root");
+    let root1 = tokio::fs::read(base.join("1.root.json")).await.unwrap();
+    let root2 = tokio::fs::read(base.join("2.root.json")).await.unwrap();
+    let repo = rotated_root_repo(&root1).await;
+
+    // Version 2 is current, and expires at 2029-07-27.
+    let check = EmbeddedRootCheck::default();
+    let report = check
+        .check(&root2, &repo, date("2029-01-01T00:00:00Z"))
+        .unwrap();
+    assert_eq!(report.embedded_version, 2);
+    assert_eq!(report.current_version, 2);
+    assert_eq!(report.version_distance, 0);
+    assert!(!report.is_stale());
+
+    let report = check
+        .check(&root2, &repo, date("2029-07-01T00:00:00Z"))
+        .unwrap();
+    assert_eq!(
+        report.staleness,
+        [Staleness::Expiring {
+            expires: date("2029-07-27T00:00:00Z")
+        }]
+    );
+
+    // Version 1 is one version behind, and has long expired.
+    let check = EmbeddedRootCheck {
+        max_version_distance: 0,
+        min_remaining_validity: TimeDelta::zero(),
+    };
+    let report = check
+        .check(&root1, &repo, date("2029-01-01T00:00:00Z"))
+        .unwrap();
+    assert_eq!(report.version_distance, 1);
+    assert_eq!(
+        report.staleness,
+        [
+            Staleness::VersionDistance {
+                distance: 1,
+                max: 0
+            },
+            Staleness::Expiring {
+                expires: report.expires
+            }
+        ]
+    );
+}
+
+/// An embedded root must be signed by its own keys.
+#[tokio::test]
+async fn embedded_root_verified() {
+    let base = test_data().join("rotated-root");
+    let root2 = tokio::fs::read(base.join("2.root.json")).await.unwrap();
+    let repo = rotated_root_repo(&root2).await;
+
+    let mut tampered: serde_json::Value = serde_json::from_slice(&root2).unwrap();
+    tampered["signed"]["expires"] = "2999-01-01T00:00:00Z".into();
+    let tampered = serde_json::to_vec(&tampered).unwrap();
+    let err = EmbeddedRootCheck::default()
+        .check(&tampered, &repo, Utc::now())
+        .unwrap_err();
+    assert!(
+        matches!(err, Error::VerifyTrustedMetadata { .. }),
+        "{}",
+        err
+    );
+}
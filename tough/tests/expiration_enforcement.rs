@@ -1,10 +1,16 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use test_utils::{dir_url, test_data};
+use chrono::{TimeDelta, Utc};
+use std::num::NonZeroU64;
+use tempfile::TempDir;
+use test_utils::{days, dir_url, test_data};
+use tough::editor::signed::PathExists;
+use tough::editor::RepositoryEditor;
 use tough::error::Error::ExpiredMetadata;
-use tough::schema::RoleType;
-use tough::{ExpirationEnforcement, RepositoryLoader};
+use tough::key_source::{KeySource, LocalKeySource};
+use tough::schema::{PathPattern, PathSet, RoleType, Target};
+use tough::{ExpirationEnforcement, RepositoryLoader, TargetName};
 
 mod test_utils;
 
@@ -25,8 +31,16 @@ async fn test_expiration_enforcement_safe() {
     .await;
     if let Err(err) = result {
         match err {
-            ExpiredMetadata { role, backtrace: _ } => {
+            ExpiredMetadata {
+                role,
+                ref name,
+                ref url,
+                ..
+            } => {
                 assert_eq!(role, RoleType::Timestamp);
+                assert_eq!(name, "timestamp");
+                assert!(url.path().ends_with("/timestamp.json"), "{}", url);
+                assert!(err.to_string().contains("timestamp.json"), "{}", err);
             }
             _ => {
                 panic!(
@@ -56,3 +70,137 @@ async fn test_expiration_enforcement_unsafe() {
     .await;
     assert!(result.is_ok())
 }
+
+/// Test that the suggested refresh falls within the window before the repository's earliest
+/// expiration, and that a window reaching before the earliest supported date is an error.
+#[tokio::test]
+async fn test_suggested_refresh() {
+    let base = test_data().join("tuf-reference-impl");
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+
+    let expires = repo.earliest_expiration();
+    assert_eq!(expires, repo.timestamp().signed.expires);
+    let window = TimeDelta::try_days(1).unwrap();
+    for _ in 0..100 {
+        let refresh = repo.suggested_refresh(window).unwrap();
+        assert!(refresh < expires && refresh >= expires - window);
+    }
+    assert!(repo.suggested_refresh(TimeDelta::max_value()).is_err());
+}
+
+/// Test that an expired delegated role counts towards the earliest expiration, and that
+/// `read_target` refuses to read the targets it lists while it's expired, naming the role, but
+/// still reads targets that other roles list.
+#[tokio::test]
+async fn test_expired_delegated_role() {
+    let root_path = test_data().join("simple-rsa").join("root.json");
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource {
+        path: test_data().join("snakeoil.pem"),
+    })];
+    let one = NonZeroU64::MIN;
+    let later = Utc::now() + days(7);
+    let expired = Utc::now() - days(1);
+    let file1_path = test_data()
+        .join("tuf-reference-impl")
+        .join("targets")
+        .join("file1.txt");
+    let file1 = Target::from_path(&file1_path).await.unwrap();
+    let delegated_target = TargetName::new("delegated/file1.txt").unwrap();
+
+    let mut editor = RepositoryEditor::new(&root_path).await.unwrap();
+    editor
+        .targets_version(one)
+        .unwrap()
+        .targets_expires(later)
+        .unwrap()
+        .snapshot_version(one)
+        .snapshot_expires(later)
+        .timestamp_version(one)
+        .timestamp_expires(later)
+        .add_target_paths(vec![file1_path])
+        .await
+        .unwrap()
+        .delegate_role(
+            "delegated",
+            &keys,
+            PathSet::Paths(vec![PathPattern::new("delegated/*").unwrap()]),
+            one,
+            expired,
+            one,
+        )
+        .await
+        .unwrap()
+        .sign_targets_editor(&keys)
+        .await
+        .unwrap()
+        .change_delegated_targets("delegated")
+        .unwrap()
+        .add_target(delegated_target.clone(), file1.clone())
+        .unwrap()
+        .targets_version(one)
+        .unwrap()
+        .targets_expires(expired)
+        .unwrap()
+        .sign_targets_editor(&keys)
+        .await
+        .unwrap();
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_dir = repo_dir.path().join("metadata");
+    let targets_dir = repo_dir.path().join("targets");
+    let signed = editor.sign(&keys).await.unwrap();
+    signed.write(&metadata_dir).await.unwrap();
+    signed
+        .copy_targets(
+            test_data().join("tuf-reference-impl").join("targets"),
+            &targets_dir,
+            PathExists::Fail,
+        )
+        .await
+        .unwrap();
+
+    let load = |expiration_enforcement| {
+        let metadata_dir = metadata_dir.clone();
+        let targets_dir = targets_dir.clone();
+        let root_path = root_path.clone();
+        async move {
+            RepositoryLoader::new(
+                &tokio::fs::read(root_path).await.unwrap(),
+                dir_url(metadata_dir),
+                dir_url(targets_dir),
+            )
+            .expiration_enforcement(expiration_enforcement)
+            .load()
+            .await
+            .unwrap()
+        }
+    };
+    let target = TargetName::new("file1.txt").unwrap();
+
+    let repo = load(ExpirationEnforcement::Safe).await;
+    assert_eq!(repo.earliest_expiration().timestamp(), expired.timestamp());
+    assert!(repo.read_target(&target).await.unwrap().is_some());
+    match repo.read_target(&delegated_target).await {
+        Err(ExpiredMetadata { role, ref name, .. }) => {
+            assert_eq!(role, RoleType::DelegatedTargets);
+            assert_eq!(name, "delegated");
+        }
+        Err(err) => panic!("expected ExpiredMetadata, got: {}", err),
+        Ok(_) => panic!("read_target was expected to return an error"),
+    }
+
+    let repo = load(ExpirationEnforcement::Unsafe).await;
+    assert!(repo.read_target(&target).await.unwrap().is_some());
+    assert!(!matches!(
+        repo.read_target(&delegated_target).await,
+        Err(ExpiredMetadata { .. })
+    ));
+}
@@ -0,0 +1,134 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use chrono::Utc;
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use test_utils::{days, dir_url, simple_rsa_editor, simple_rsa_root, snakeoil_keys, test_data};
+use tough::schema::{PathPattern, PathSet, Target};
+use tough::{RepositoryLoader, TargetName, UnicodePreservingEncoding};
+
+fn targets_dir() -> PathBuf {
+    test_data().join("tuf-reference-impl").join("targets")
+}
+
+/// Writes a repository to `metadata_dir` that delegates `file1.txt` to a role named `café`, with
+/// role filenames encoded by `UnicodePreservingEncoding`.
+async fn write_repo(metadata_dir: &Path) {
+    let keys = snakeoil_keys();
+    let one = NonZeroU64::new(1).unwrap();
+    let expires = Utc::now() + days(7);
+    let name = TargetName::new("file1.txt").unwrap();
+    let target = Target::from_path(targets_dir().join("file1.txt"))
+        .await
+        .unwrap();
+
+    let mut editor = simple_rsa_editor(1).await;
+    editor
+        .filename_encoding(UnicodePreservingEncoding)
+        .delegate_role(
+            "café",
+            &keys,
+            PathSet::Paths(vec![PathPattern::new("*.txt").unwrap()]),
+            one,
+            expires,
+            one,
+        )
+        .await
+        .unwrap()
+        .sign_targets_editor(&keys)
+        .await
+        .unwrap()
+        .change_delegated_targets("café")
+        .unwrap()
+        .add_target(name, target)
+        .unwrap()
+        .targets_version(one)
+        .unwrap()
+        .targets_expires(expires)
+        .unwrap()
+        .sign_targets_editor(&keys)
+        .await
+        .unwrap();
+    editor
+        .sign(&keys)
+        .await
+        .unwrap()
+        .write_with_gzip(metadata_dir)
+        .await
+        .unwrap();
+}
+
+/// A repository written with a non-default encoding is loaded, and cached, by a client that uses
+/// the same encoding, and its delegated role is written under the name that encoding gives it.
+#[tokio::test]
+async fn unicode_role_filename_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let metadata_dir = dir.path().join("metadata");
+    write_repo(&metadata_dir).await;
+    // The root uses consistent snapshots.
+    assert!(metadata_dir.join("1.café.json").is_file());
+    assert!(metadata_dir.join("1.café.json.gz").is_file());
+
+    let repo = RepositoryLoader::new(
+        &std::fs::read(simple_rsa_root()).unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(targets_dir()),
+    )
+    .filename_encoding(UnicodePreservingEncoding)
+    .load()
+    .await
+    .unwrap();
+    // Targets are stored under their consistent snapshot names, which the test data lacks, so
+    // check the listing rather than reading the target.
+    let name = TargetName::new("file1.txt").unwrap();
+    assert!(repo.resolve_target(&name).target().is_some());
+
+    let cache_dir = dir.path().join("cache");
+    repo.cache(
+        cache_dir.join("metadata"),
+        cache_dir.join("targets"),
+        Some::<&[&str]>(&[]),
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(cache_dir.join("metadata").join("1.café.json").is_file());
+}
+
+/// `FilesystemTransport` also finds the Unicode filename when a client using the default encoding
+/// asks for its percent-encoded form, and that client caches the role under its own encoding.
+#[tokio::test]
+async fn default_encoding_reads_unicode_filename() {
+    let dir = TempDir::new().unwrap();
+    let metadata_dir = dir.path().join("metadata");
+    write_repo(&metadata_dir).await;
+
+    let repo = RepositoryLoader::new(
+        &std::fs::read(simple_rsa_root()).unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(targets_dir()),
+    )
+    .load()
+    .await
+    .unwrap();
+    let name = TargetName::new("file1.txt").unwrap();
+    assert!(repo.resolve_target(&name).target().is_some());
+
+    let cache_dir = dir.path().join("cache");
+    repo.cache(
+        cache_dir.join("metadata"),
+        cache_dir.join("targets"),
+        Some::<&[&str]>(&[]),
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(cache_dir
+        .join("metadata")
+        .join("1.caf%C3%A9.json")
+        .is_file());
+}
@@ -0,0 +1,75 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use chrono::Utc;
+use std::num::NonZeroU64;
+use tempfile::TempDir;
+use test_utils::{
+    days, dir_url, sign_and_write, simple_rsa_editor, simple_rsa_root, snakeoil_keys, test_data,
+};
+use tough::schema::{HashedBins, Target};
+use tough::{RepositoryLoader, ResolutionOutcome, TargetName};
+
+/// Targets added to the hashed bins the editor delegates to are found in those bins by clients.
+#[tokio::test]
+async fn delegate_hashed_bins() {
+    let dir = TempDir::new().unwrap();
+    let keys = snakeoil_keys();
+    let one = NonZeroU64::new(1).unwrap();
+    let count = NonZeroU64::new(4).unwrap();
+    let expires = Utc::now() + days(7);
+    let bins = HashedBins::new(count).unwrap();
+    let targets_dir = test_data().join("tuf-reference-impl").join("targets");
+
+    let mut editor = simple_rsa_editor(1).await;
+    editor
+        .delegate_hashed_bins(count, &keys, one, expires, one)
+        .await
+        .unwrap()
+        .sign_targets_editor(&keys)
+        .await
+        .unwrap();
+    for file in ["file1.txt", "file2.txt", "file3.txt"] {
+        let name = TargetName::new(file).unwrap();
+        let target = Target::from_path(targets_dir.join(file)).await.unwrap();
+        editor
+            .change_delegated_targets(&bins.bin_for(&name))
+            .unwrap()
+            .add_target(name, target)
+            .unwrap()
+            .targets_version(one)
+            .unwrap()
+            .targets_expires(expires)
+            .unwrap()
+            .sign_targets_editor(&keys)
+            .await
+            .unwrap();
+    }
+    let metadata_dir = dir.path().join("metadata");
+    sign_and_write(editor, &metadata_dir).await;
+
+    let repo = RepositoryLoader::new(
+        &std::fs::read(simple_rsa_root()).unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(&targets_dir),
+    )
+    .load()
+    .await
+    .unwrap();
+    let delegations = repo.targets().signed.delegations.as_ref().unwrap();
+    assert_eq!(delegations.roles.len(), 4);
+    for file in ["file1.txt", "file2.txt", "file3.txt"] {
+        let name = TargetName::new(file).unwrap();
+        match repo.resolve_target(&name).outcome {
+            ResolutionOutcome::Found { role, .. } => assert_eq!(role, bins.bin_for(&name)),
+            outcome => panic!("{} not found: {:?}", file, outcome),
+        }
+        let listed = repo.targets().signed.find_target(&name).unwrap();
+        assert_eq!(
+            listed.length,
+            std::fs::metadata(targets_dir.join(file)).unwrap().len()
+        );
+    }
+}
@@ -4,9 +4,15 @@ mod test_utils;
 #[cfg(feature = "http")]
 mod http_happy {
     use crate::test_utils::{read_to_end, test_data};
+    use futures::StreamExt;
     use httptest::{matchers::*, responders::*, Expectation, Server};
+    use std::num::NonZeroU64;
     use std::str::FromStr;
-    use tough::{DefaultTransport, HttpTransport, RepositoryLoader, TargetName, Transport};
+    use std::time::{Duration, Instant};
+    use tough::{
+        DefaultTransport, HttpTransport, HttpTransportBuilder, IntoVec, RepositoryLoader,
+        TargetName, Transport, TransportErrorKind,
+    };
     use url::Url;
 
     /// Set an expectation in a test HTTP server which serves a file from `tuf-reference-impl`.
@@ -38,6 +44,142 @@ mod http_happy {
         run_http_test(HttpTransport::default()).await;
     }
 
+    /// Test that `tough` works with connection pooling and HTTP/2 settings customized.
+    #[tokio::test]
+    async fn test_http_transport_pool_settings() {
+        run_http_test(
+            HttpTransportBuilder::new()
+                .pool_max_idle_per_host(1)
+                .pool_idle_timeout(Some(Duration::from_secs(5)))
+                .tcp_keepalive(Some(Duration::from_secs(30)))
+                .http2(true)
+                .build(),
+        )
+        .await;
+    }
+
+    /// Test that a host name with a static resolution override reaches the server without DNS.
+    #[tokio::test]
+    async fn test_http_transport_resolve_override() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/metadata/timestamp.json"))
+                .times(1)
+                .respond_with(status_code(200).body("{}")),
+        );
+        let url = Url::parse(&format!(
+            "http://tuf.repo.invalid:{}/metadata/timestamp.json",
+            server.addr().port()
+        ))
+        .unwrap();
+        let transport = HttpTransportBuilder::new()
+            .tries(1)
+            .resolve("tuf.repo.invalid", server.addr().ip())
+            .build();
+        let body = read_to_end(transport.fetch(url).await.unwrap()).await;
+        assert_eq!(body, &b"{}"[..]);
+    }
+
+    /// Test that a rate limit is shared across fetches, so the second body is held back until the
+    /// first has been paid for.
+    #[tokio::test]
+    async fn test_http_transport_rate_limit() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/target"))
+                .times(2)
+                .respond_with(status_code(200).body(vec![0u8; 1000])),
+        );
+        let url = Url::parse(&server.url_str("/target")).unwrap();
+        let transport = HttpTransportBuilder::new()
+            .max_bytes_per_second(NonZeroU64::new(2000))
+            .build();
+
+        let start = Instant::now();
+        for _ in 0..2 {
+            let body = read_to_end(transport.fetch(url.clone()).await.unwrap()).await;
+            assert_eq!(body.len(), 1000);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(450));
+    }
+
+    /// Test that a range request asks for the rest of the file, and fails if the server sends the
+    /// whole file instead.
+    #[tokio::test]
+    async fn test_http_transport_fetch_range() {
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/ranged"),
+                request::headers(contains(("range", "bytes=4-"))),
+            ])
+            .times(1)
+            .respond_with(status_code(206).body("56789")),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/unranged"))
+                .times(1)
+                .respond_with(status_code(200).body("0123456789")),
+        );
+        let transport = HttpTransportBuilder::new().tries(1).build();
+
+        let url = Url::parse(&server.url_str("/ranged")).unwrap();
+        let body = read_to_end(transport.fetch_range(url, 4).await.unwrap()).await;
+        assert_eq!(body, &b"56789"[..]);
+
+        let url = Url::parse(&server.url_str("/unranged")).unwrap();
+        let err = transport
+            .fetch_range(url, 4)
+            .await
+            .unwrap()
+            .into_vec()
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), TransportErrorKind::RangeNotSupported);
+    }
+
+    /// Test that a target served with a body larger than its listed length fails as soon as the
+    /// length is passed, rather than after the whole body has been received.
+    #[tokio::test]
+    async fn test_http_oversized_target() {
+        let server = Server::run();
+        let repo_dir = test_data().join("tuf-reference-impl");
+        let mut body = tokio::fs::read(repo_dir.join("targets").join("file1.txt"))
+            .await
+            .unwrap();
+        let length = body.len();
+        body.resize(length + 16 * 1024 * 1024, b'!');
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/targets/file1.txt"))
+                .times(1)
+                .respond_with(status_code(200).body(body)),
+        );
+        let repo = RepositoryLoader::new(
+            &tokio::fs::read(repo_dir.join("metadata").join("1.root.json"))
+                .await
+                .unwrap(),
+            Url::from_directory_path(repo_dir.join("metadata")).unwrap(),
+            Url::from_str(server.url_str("/targets/").as_str()).unwrap(),
+        )
+        .transport(DefaultTransport::default())
+        .load()
+        .await
+        .unwrap();
+
+        let file1 = TargetName::new("file1.txt").unwrap();
+        let mut stream = repo.read_target(&file1).await.unwrap().unwrap();
+        let mut received = 0;
+        let err = loop {
+            match stream.next().await.unwrap() {
+                Ok(chunk) => received += chunk.len(),
+                Err(err) => break err,
+            }
+        };
+        assert!(received <= length, "{}", received);
+        assert!(err.to_string().contains("Maximum size"), "{}", err);
+        assert!(stream.next().await.is_none());
+    }
+
     /// Test that `DefaultTransport` works over HTTP when the `http` feature is enabled.
     #[tokio::test]
     async fn test_http_default_transport() {
@@ -93,15 +235,98 @@ mod http_happy {
     }
 }
 
+/// Tests for metadata compression, which needs the `gzip` feature.
+#[cfg(feature = "gzip")]
+mod http_compression {
+    use crate::test_utils::{read_to_end, test_data};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use httptest::{matchers::*, responders::*, Expectation, Server};
+    use std::io::Write;
+    use std::str::FromStr;
+    use tough::{HttpTransportBuilder, RepositoryLoader, TargetName};
+    use url::Url;
+
+    /// Serve a metadata file gzip-compressed, but only to clients that ask for gzip.
+    async fn create_compressed_get(relative_path: &str) -> httptest::Expectation {
+        let path = test_data().join("tuf-reference-impl").join(relative_path);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(&tokio::fs::read(path).await.unwrap())
+            .unwrap();
+        Expectation::matching(all_of![
+            request::method_path("GET", format!("/{}", relative_path)),
+            request::headers(contains(("accept-encoding", matches("gzip")))),
+        ])
+        .times(1)
+        .respond_with(
+            status_code(200)
+                .append_header("content-encoding", "gzip")
+                .body(encoder.finish().unwrap()),
+        )
+    }
+
+    /// Test that compressed metadata is decompressed and verified, while targets are requested
+    /// uncompressed.
+    #[tokio::test]
+    async fn test_http_transport_compressed_metadata() {
+        let server = Server::run();
+        let repo_dir = test_data().join("tuf-reference-impl");
+        for file in ["timestamp", "snapshot", "targets", "role1", "role2"] {
+            server.expect(create_compressed_get(&format!("metadata/{}.json", file)).await);
+        }
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/metadata/2.root.json"))
+                .respond_with(status_code(404)),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/targets/file1.txt"),
+                request::headers(contains(("accept-encoding", "identity"))),
+            ])
+            .respond_with(
+                status_code(200).body(
+                    tokio::fs::read(repo_dir.join("targets").join("file1.txt"))
+                        .await
+                        .unwrap(),
+                ),
+            ),
+        );
+
+        let repo = RepositoryLoader::new(
+            &tokio::fs::read(repo_dir.join("metadata").join("1.root.json"))
+                .await
+                .unwrap(),
+            Url::from_str(server.url_str("/metadata").as_str()).unwrap(),
+            Url::from_str(server.url_str("/targets").as_str()).unwrap(),
+        )
+        .transport(HttpTransportBuilder::new().compression(true).build())
+        .load()
+        .await
+        .unwrap();
+
+        let file1 = TargetName::new("file1.txt").unwrap();
+        assert_eq!(
+            read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await,
+            &b"This is an example target file."[..]
+        );
+    }
+}
+
 #[cfg(feature = "http")]
 #[cfg(feature = "integ")]
 mod http_integ {
     use crate::test_utils::test_data;
     use failure_server::IntegServers;
-    use std::path::PathBuf;
-    use tough::{HttpTransportBuilder, RepositoryLoader};
+    use std::path::{Path, PathBuf};
+    use tempfile::TempDir;
+    use tokio::sync::Mutex;
+    use tough::{HttpTransportBuilder, IntoVec, RepositoryLoader, TargetName};
     use url::Url;
 
+    /// The servers listen on fixed ports, so only one test can run them at a time.
+    static SERVERS: Mutex<()> = Mutex::const_new(());
+
     pub fn tuf_reference_impl() -> PathBuf {
         test_data().join("tuf-reference-impl")
     }
@@ -123,6 +348,7 @@ mod http_integ {
     /// `fetch` loop.
     #[tokio::test]
     async fn test_retries() {
+        let _servers = SERVERS.lock().await;
         // create a faulty http representation of tuf-reference-impl
         let tuf_reference_path = tuf_reference_impl();
         let mut integ_servers = IntegServers::new(tuf_reference_path).unwrap();
@@ -160,4 +386,66 @@ mod http_integ {
             .teardown()
             .expect("failed to stop HTTP servers");
     }
+
+    fn copy_dir(from: &Path, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                copy_dir(&entry.path(), &to.join(entry.file_name()));
+            } else {
+                std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+            }
+        }
+    }
+
+    /// Test that a target served through faulty HTTP connections with a body larger than its
+    /// listed length is rejected.
+    #[tokio::test]
+    async fn test_oversized_target() {
+        let _servers = SERVERS.lock().await;
+        let repo_dir = TempDir::new().unwrap();
+        copy_dir(&tuf_reference_impl(), repo_dir.path());
+        let target = repo_dir.path().join("targets").join("file1.txt");
+        let mut body = std::fs::read(&target).unwrap();
+        body.resize(body.len() + 1024 * 1024, b'!');
+        std::fs::write(&target, body).unwrap();
+
+        let mut integ_servers = IntegServers::new(repo_dir.path()).unwrap();
+        integ_servers
+            .run()
+            .await
+            .expect("Failed to run integration test HTTP servers");
+
+        let transport = HttpTransportBuilder::new()
+            .tries(200)
+            .initial_backoff(std::time::Duration::from_nanos(100))
+            .max_backoff(std::time::Duration::from_millis(1))
+            .build();
+        let repo = RepositoryLoader::new(
+            &tokio::fs::read(tuf_reference_impl_root_json())
+                .await
+                .unwrap(),
+            Url::parse("http://localhost:10102/metadata").unwrap(),
+            Url::parse("http://localhost:10102/targets").unwrap(),
+        )
+        .transport(transport)
+        .load()
+        .await
+        .unwrap();
+        let file1 = TargetName::new("file1.txt").unwrap();
+        let err = repo
+            .read_target(&file1)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_vec()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Maximum size"), "{}", err);
+
+        integ_servers
+            .teardown()
+            .expect("failed to stop HTTP servers");
+    }
 }
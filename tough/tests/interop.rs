@@ -1,9 +1,14 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use serde_json::json;
+use std::num::NonZeroUsize;
 use tempfile::TempDir;
 use test_utils::{dir_url, read_to_end, test_data};
-use tough::{FilesystemTransport, Limits, Repository, RepositoryLoader, TargetName};
+use tough::schema::Target;
+use tough::{
+    FilesystemTransport, Limits, MissingRolePolicy, Repository, RepositoryLoader, TargetName,
+};
 
 mod test_utils;
 
@@ -61,6 +66,35 @@ async fn assert_tuf_reference_impl(repo: &Repository) {
         .target_is_delegated(&file3));
 }
 
+/// Test that the snapshot and timestamp meta are listed by role name.
+#[tokio::test]
+async fn test_snapshot_and_timestamp_meta() {
+    let base = test_data().join("tuf-reference-impl");
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+
+    let snapshot_meta = repo.snapshot_meta();
+    assert_eq!(
+        snapshot_meta.keys().copied().collect::<Vec<_>>(),
+        ["role1", "role2", "root", "targets"]
+    );
+    assert_eq!(snapshot_meta["targets"].version.get(), 1);
+    let timestamp_meta = repo.timestamp_meta();
+    assert_eq!(
+        timestamp_meta.keys().copied().collect::<Vec<_>>(),
+        ["snapshot"]
+    );
+    assert_eq!(timestamp_meta["snapshot"].length, Some(556));
+}
+
 /// Test that `tough` can process repositories generated by [`tuf`], the reference Python
 /// implementation using the `load` function with non-default [`Options`].
 #[tokio::test]
@@ -76,13 +110,15 @@ async fn test_tuf_reference_impl_default_transport() {
         dir_url(base.join("targets")),
     )
     .transport(FilesystemTransport)
-    .limits(Limits {
-        max_root_size: 1000,
-        max_targets_size: 2000,
-        max_timestamp_size: 3000,
-        max_snapshot_size: 4000,
-        max_root_updates: 1,
-    })
+    .limits(
+        Limits::default()
+            .max_root_size(1000)
+            .max_targets_size(2000)
+            .max_timestamp_size(3000)
+            .max_snapshot_size(4000)
+            .max_root_updates(1)
+            .max_delegated_roles(5),
+    )
     .datastore(datastore.path())
     .load()
     .await
@@ -121,3 +157,202 @@ async fn test_dubious_role_name() {
     let expected_filename = "..%2F..%2Fpath%2Flike%2Fdubious.json";
     assert!(datastore.path().join(expected_filename).is_file())
 }
+
+/// Test that a delegated role is checked against the hashes that snapshot.json lists for it, so a
+/// tampered file is rejected before its signatures are considered.
+#[tokio::test]
+async fn test_delegated_role_hash_mismatch() {
+    let base = test_data().join("dubious-role-names");
+    let metadata = TempDir::new().unwrap();
+    let mut entries = tokio::fs::read_dir(base.join("metadata")).await.unwrap();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        tokio::fs::copy(entry.path(), metadata.path().join(entry.file_name()))
+            .await
+            .unwrap();
+    }
+    // Change the role's expiration without changing its length
+    let role_path = metadata.path().join("%F0%9F%8D%BA%2F30.json");
+    let role = tokio::fs::read_to_string(&role_path).await.unwrap();
+    let tampered = role.replace("3021-01-27T00:56:42", "3021-01-27T00:56:43");
+    assert_ne!(role, tampered);
+    tokio::fs::write(&role_path, tampered).await.unwrap();
+
+    let err = RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(metadata.path()),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("Hash mismatch"), "{}", err);
+}
+
+/// Test that a loader requiring consistent snapshots refuses a repository that doesn't use them
+/// and accepts one that does.
+#[tokio::test]
+async fn test_require_consistent_snapshot() {
+    for (name, consistent) in [
+        ("tuf-reference-impl", false),
+        ("consistent-snapshots", true),
+    ] {
+        let base = test_data().join(name);
+        let result = RepositoryLoader::new(
+            &tokio::fs::read(base.join("metadata").join("1.root.json"))
+                .await
+                .unwrap(),
+            dir_url(base.join("metadata")),
+            dir_url(base.join("targets")),
+        )
+        .require_consistent_snapshot(true)
+        .load()
+        .await;
+        match result {
+            Ok(_) => assert!(consistent, "{} loaded", name),
+            Err(err) => assert!(
+                !consistent
+                    && matches!(err, tough::error::Error::ConsistentSnapshotRequired { .. }),
+                "{}: {}",
+                name,
+                err
+            ),
+        }
+    }
+}
+
+/// Test that a delegated role whose metadata file is missing fails the load by default, and is
+/// skipped under `MissingRolePolicy::SkipWithWarning` without affecting the other targets.
+#[tokio::test]
+async fn test_missing_delegated_role() {
+    let base = test_data().join("tuf-reference-impl");
+    let metadata = TempDir::new().unwrap();
+    let mut entries = tokio::fs::read_dir(base.join("metadata")).await.unwrap();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        if entry.file_name() != "role1.json" {
+            tokio::fs::copy(entry.path(), metadata.path().join(entry.file_name()))
+                .await
+                .unwrap();
+        }
+    }
+    let root = tokio::fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    let loader = RepositoryLoader::new(
+        &root,
+        dir_url(metadata.path()),
+        dir_url(base.join("targets")),
+    );
+
+    let err = loader.clone().load().await.unwrap_err();
+    assert!(err.to_string().contains("role1.json"), "{}", err);
+
+    let repo = loader
+        .missing_role_policy(MissingRolePolicy::SkipWithWarning)
+        .load()
+        .await
+        .unwrap();
+    assert_eq!(repo.skipped_roles(), ["role1"]);
+    assert!(repo.delegated_role("role1").unwrap().targets.is_none());
+    let file1 = TargetName::new("file1.txt").unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await,
+        &b"This is an example target file."[..]
+    );
+    let file3 = TargetName::new("file3.txt").unwrap();
+    assert!(repo.read_target(&file3).await.unwrap().is_none());
+}
+
+/// Test that targets can be selected by their custom metadata with JSON Pointers.
+#[tokio::test]
+async fn test_targets_with_custom() {
+    let base = test_data().join("tuf-reference-impl");
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+
+    let found = repo.targets_with_custom("/file_permissions", &json!("0644"));
+    let names: Vec<&str> = found.iter().map(|(name, _)| name.raw()).collect();
+    assert_eq!(names, ["file1.txt"]);
+    assert!(repo
+        .targets_with_custom("/file_permissions", &json!("0755"))
+        .is_empty());
+
+    let target: Target = serde_json::from_value(json!({
+        "length": 1,
+        "hashes": {"sha256": "00"},
+        "custom": {"release/info": {"channels": ["stable", "beta"]}}
+    }))
+    .unwrap();
+    assert_eq!(
+        target.custom_value("/release~1info/channels/1"),
+        Some(&json!("beta"))
+    );
+    assert_eq!(target.custom_value("/release~1info/arch"), None);
+    assert_eq!(target.custom_value("release~1info"), None);
+}
+
+/// Test that `download_targets` saves each target, reports progress as they complete, and reports
+/// a failing target without stopping the rest.
+#[tokio::test]
+async fn test_download_targets() {
+    let base = test_data().join("tuf-reference-impl");
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+
+    let names: Vec<TargetName> = ["file1.txt", "missing.txt", "file2.txt", "file3.txt"]
+        .iter()
+        .map(|name| TargetName::new(*name).unwrap())
+        .collect();
+    let outdir = TempDir::new().unwrap();
+    let mut completed = Vec::new();
+    let results = repo
+        .download_targets(
+            &names,
+            outdir.path(),
+            NonZeroUsize::new(2).unwrap(),
+            |name, _| completed.push(name.clone()),
+        )
+        .await;
+
+    completed.sort();
+    let mut expected = names.clone();
+    expected.sort();
+    assert_eq!(completed, expected);
+
+    assert_eq!(
+        results.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        names.iter().collect::<Vec<_>>()
+    );
+    for (name, result) in &results {
+        if name.raw() == "missing.txt" {
+            assert!(result.is_err());
+        } else {
+            result.as_ref().unwrap();
+            assert_eq!(
+                tokio::fs::read(outdir.path().join(name.raw()))
+                    .await
+                    .unwrap(),
+                tokio::fs::read(base.join("targets").join(name.raw()))
+                    .await
+                    .unwrap()
+            );
+        }
+    }
+}
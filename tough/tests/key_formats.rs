@@ -0,0 +1,66 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use aws_lc_rs::rand::SystemRandom;
+use aws_lc_rs::signature::{UnparsedPublicKey, ED25519};
+use std::str::FromStr;
+use test_utils::test_data;
+use tough::error::Error;
+use tough::schema::key::Key;
+use tough::sign::{parse_keypair, Sign};
+
+/// The public key of the key pair in `ssh-ed25519`, as listed in `ssh-ed25519.pub`.
+fn ssh_public_key() -> Key {
+    let line = std::fs::read_to_string(test_data().join("ssh-ed25519.pub")).unwrap();
+    Key::from_str(&line).unwrap()
+}
+
+/// An Ed25519 key written by `ssh-keygen` signs with the key listed in its `.pub` file.
+#[tokio::test]
+async fn openssh_ed25519_key() {
+    let keypair = parse_keypair(&std::fs::read(test_data().join("ssh-ed25519")).unwrap()).unwrap();
+    assert_eq!(keypair.tuf_key(), ssh_public_key());
+
+    let signature = keypair
+        .sign(b"payload", &SystemRandom::new())
+        .await
+        .unwrap();
+    UnparsedPublicKey::new(&ED25519, keypair.tuf_key().public_key())
+        .verify(b"payload", &signature)
+        .unwrap();
+}
+
+/// A passphrase-protected OpenSSH key is refused with an error saying so.
+#[test]
+fn openssh_encrypted_key() {
+    let key = std::fs::read(test_data().join("ssh-ed25519-encrypted")).unwrap();
+    let err = parse_keypair(&key).err().unwrap();
+    assert!(matches!(err, Error::KeyEncrypted { .. }), "{}", err);
+}
+
+/// The same key as a hex seed, alone or followed by its public key.
+#[test]
+fn hex_ed25519_seed() {
+    let seed = std::fs::read_to_string(test_data().join("ed25519-seed.hex")).unwrap();
+    let keypair = parse_keypair(seed.as_bytes()).unwrap();
+    assert_eq!(keypair.tuf_key(), ssh_public_key());
+
+    let public = hex::encode(ssh_public_key().public_key());
+    let with_public = format!("{}{}", seed.trim(), public);
+    let keypair = parse_keypair(with_public.as_bytes()).unwrap();
+    assert_eq!(keypair.tuf_key(), ssh_public_key());
+
+    // A public key that doesn't belong to the seed is rejected.
+    let mismatched = format!("{}{}", seed.trim(), "00".repeat(32));
+    assert!(parse_keypair(mismatched.as_bytes()).is_err());
+}
+
+/// Only `ssh-ed25519` lines are read as public keys.
+#[test]
+fn ssh_public_key_lines() {
+    assert!(Key::from_str("ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQC7 user@host").is_err());
+    assert!(Key::from_str("ssh-ed25519 not-base64").is_err());
+    assert!(matches!(ssh_public_key(), Key::Ed25519 { .. }));
+}
@@ -0,0 +1,108 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use chrono::Utc;
+use std::num::NonZeroU64;
+use std::path::Path;
+use tempfile::TempDir;
+use test_utils::{days, dir_url, sign_and_write, simple_rsa_editor, simple_rsa_root, test_data};
+use tough::editor::RepositoryEditor;
+use tough::error::Error;
+use tough::key_source::{KeySource, LocalKeySource};
+use tough::schema::RoleType;
+use tough::{Repository, RepositoryLoader};
+
+fn key(name: &str) -> Box<dyn KeySource> {
+    Box::new(LocalKeySource {
+        path: test_data().join(name),
+    })
+}
+
+/// Writes a repository signed with snakeoil.pem, which simple-rsa's root lists for every role,
+/// to `metadata_dir`, and loads it.
+async fn create_repo(metadata_dir: &Path) -> Repository {
+    sign_and_write(simple_rsa_editor(1).await, metadata_dir).await;
+    load(metadata_dir).await
+}
+
+async fn load(metadata_dir: &Path) -> Repository {
+    RepositoryLoader::new(
+        &std::fs::read(simple_rsa_root()).unwrap(),
+        dir_url(metadata_dir),
+        dir_url(metadata_dir.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap()
+}
+
+async fn editor(repo: Repository) -> RepositoryEditor {
+    let two = NonZeroU64::new(2).unwrap();
+    let expires = Utc::now() + days(1);
+    let mut editor = RepositoryEditor::from_repo(simple_rsa_root(), repo)
+        .await
+        .unwrap();
+    editor
+        .snapshot_version(two)
+        .snapshot_expires(expires)
+        .timestamp_version(two)
+        .timestamp_expires(expires);
+    editor
+}
+
+/// Snapshot and timestamp are moved to the new key in a cross-signed root that clients accept.
+#[tokio::test]
+async fn migrate_online_keys() {
+    let dir = TempDir::new().unwrap();
+    let repo = create_repo(dir.path()).await;
+    let old_key_id = repo.root().signed.roles[&RoleType::Snapshot].keyids[0].clone();
+    let new_key_id = key("snakeoil_2.pem")
+        .public_key()
+        .await
+        .unwrap()
+        .key_id()
+        .unwrap();
+
+    let signed = editor(repo)
+        .await
+        .migrate_online_keys(
+            &[key("snakeoil.pem")],
+            vec![key("snakeoil_2.pem")],
+            vec![key("snakeoil_2.pem")],
+        )
+        .await
+        .unwrap();
+    signed.write(dir.path()).await.unwrap();
+
+    let repo = load(dir.path()).await;
+    let root = &repo.root().signed;
+    assert_eq!(root.version.get(), 2);
+    for role in [RoleType::Snapshot, RoleType::Timestamp] {
+        assert_eq!(root.roles[&role].keyids, std::slice::from_ref(&new_key_id));
+    }
+    // The old key is still used by the root and targets roles.
+    for role in [RoleType::Root, RoleType::Targets] {
+        assert_eq!(root.roles[&role].keyids, std::slice::from_ref(&old_key_id));
+    }
+    assert_eq!(repo.snapshot().signed.version.get(), 2);
+    assert_eq!(repo.timestamp().signed.version.get(), 2);
+}
+
+/// Without root keys that meet the current root's threshold, nothing is signed.
+#[tokio::test]
+async fn migrate_online_keys_without_root_keys() {
+    let dir = TempDir::new().unwrap();
+    let repo = create_repo(dir.path()).await;
+    let err = editor(repo)
+        .await
+        .migrate_online_keys(
+            &[key("snakeoil_2.pem")],
+            vec![key("snakeoil_2.pem")],
+            vec![key("snakeoil_2.pem")],
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::KeysNotFoundInRoot { .. }), "{}", err);
+}
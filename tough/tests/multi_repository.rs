@@ -0,0 +1,154 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::path::Path;
+use tempfile::TempDir;
+use test_utils::{dir_url, read_to_end, sign_and_write, simple_rsa_editor, simple_rsa_root};
+use tough::editor::signed::PathExists;
+use tough::error::Error;
+use tough::{MapFile, MultiRepository, MultiRepositoryLoader, RepositoryLoader, TargetName};
+
+/// Writes a repository to `outdir`, with metadata in `metadata` and targets in `targets`, that
+/// lists each of `targets` with the given contents.
+async fn write_repo(outdir: &Path, targets: &[(&str, &str)]) {
+    let input = outdir.join("input");
+    tokio::fs::create_dir_all(&input).await.unwrap();
+    for (name, contents) in targets {
+        tokio::fs::write(input.join(name), contents).await.unwrap();
+    }
+    let mut editor = simple_rsa_editor(1).await;
+    editor
+        .add_target_paths(targets.iter().map(|(name, _)| input.join(name)).collect())
+        .await
+        .unwrap();
+    sign_and_write(editor, &outdir.join("metadata"))
+        .await
+        .copy_targets(&input, outdir.join("targets"), PathExists::Fail)
+        .await
+        .unwrap();
+}
+
+async fn load(map: &str, repos: &[(&str, &Path)]) -> tough::error::Result<MultiRepository> {
+    let map: MapFile = serde_json::from_str(map).unwrap();
+    let root = tokio::fs::read(simple_rsa_root()).await.unwrap();
+    let mut loader = MultiRepositoryLoader::new(map);
+    for (name, dir) in repos {
+        loader = loader.repository(
+            *name,
+            RepositoryLoader::new(
+                &root,
+                dir_url(dir.join("metadata")),
+                dir_url(dir.join("targets")),
+            ),
+        );
+    }
+    loader.load().await
+}
+
+const MAP: &str = r#"{
+    "repositories": {
+        "a": ["https://a.example.com/"],
+        "b": ["https://b.example.com/"],
+        "c": ["https://c.example.com/"]
+    },
+    "mapping": [
+        {
+            "paths": ["strict-*"],
+            "repositories": ["a", "b", "c"],
+            "terminating": true,
+            "threshold": 2
+        },
+        {
+            "paths": ["*"],
+            "repositories": ["a", "b"],
+            "threshold": 2
+        },
+        {
+            "paths": ["*"],
+            "repositories": ["c"]
+        }
+    ]
+}"#;
+
+/// Test that targets are only returned when the mapping's threshold of repositories agree on them,
+/// and that terminating and non-terminating mappings are followed.
+#[tokio::test]
+async fn threshold_of_repositories_agree() {
+    let a = TempDir::new().unwrap();
+    let b = TempDir::new().unwrap();
+    let c = TempDir::new().unwrap();
+    write_repo(a.path(), &[("agreed.txt", "same"), ("split.txt", "a")]).await;
+    write_repo(b.path(), &[("agreed.txt", "same"), ("split.txt", "b")]).await;
+    write_repo(c.path(), &[("split.txt", "c")]).await;
+    let repos = load(MAP, &[("a", a.path()), ("b", b.path()), ("c", c.path())])
+        .await
+        .unwrap();
+
+    let agreed = TargetName::new("agreed.txt").unwrap();
+    assert_eq!(
+        read_to_end(repos.read_target(&agreed).await.unwrap().unwrap()).await,
+        b"same"
+    );
+
+    // a and b disagree, so the search moves on to c alone.
+    let split = TargetName::new("split.txt").unwrap();
+    assert_eq!(
+        read_to_end(repos.read_target(&split).await.unwrap().unwrap()).await,
+        b"c"
+    );
+
+    assert!(repos
+        .target(&TargetName::new("missing.txt").unwrap())
+        .unwrap()
+        .is_none());
+}
+
+/// Test that a terminating mapping without consensus stops the search with an error.
+#[tokio::test]
+async fn terminating_mapping_without_consensus() {
+    let a = TempDir::new().unwrap();
+    let b = TempDir::new().unwrap();
+    let c = TempDir::new().unwrap();
+    write_repo(a.path(), &[("strict-x.txt", "a")]).await;
+    write_repo(b.path(), &[("strict-x.txt", "b")]).await;
+    write_repo(c.path(), &[]).await;
+    let repos = load(MAP, &[("a", a.path()), ("b", b.path()), ("c", c.path())])
+        .await
+        .unwrap();
+
+    let err = repos
+        .target(&TargetName::new("strict-x.txt").unwrap())
+        .unwrap_err();
+    assert!(
+        matches!(err, Error::MapNoConsensus { agreeing: 1, .. }),
+        "{}",
+        err
+    );
+}
+
+/// Test that maps naming unknown repositories or unreachable thresholds, or without a loader for
+/// every repository, are refused.
+#[tokio::test]
+async fn invalid_maps() {
+    let a = TempDir::new().unwrap();
+    write_repo(a.path(), &[]).await;
+
+    let unknown =
+        r#"{"repositories": {"a": []}, "mapping": [{"paths": ["*"], "repositories": ["b"]}]}"#;
+    let result = load(unknown, &[("a", a.path())]).await;
+    assert!(matches!(result, Err(Error::MapUnknownRepository { .. })));
+
+    let threshold = r#"{"repositories": {"a": []}, "mapping": [{"paths": ["*"], "repositories": ["a"], "threshold": 2}]}"#;
+    let result = load(threshold, &[("a", a.path())]).await;
+    assert!(matches!(result, Err(Error::MapThreshold { .. })));
+
+    // One repository named twice must not satisfy a threshold of two.
+    let duplicate = r#"{"repositories": {"a": []}, "mapping": [{"paths": ["*"], "repositories": ["a", "a"], "threshold": 2}]}"#;
+    let result = load(duplicate, &[("a", a.path())]).await;
+    assert!(matches!(result, Err(Error::MapDuplicateRepository { .. })));
+
+    let result = load(MAP, &[("a", a.path())]).await;
+    assert!(matches!(result, Err(Error::MapMissingLoader { .. })));
+}
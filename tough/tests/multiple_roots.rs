@@ -0,0 +1,137 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use tempfile::TempDir;
+use test_utils::{dir_url, test_data};
+use tough::error::Error;
+use tough::RepositoryLoader;
+
+/// Test that a load succeeds with the first candidate root that establishes trust and reports it,
+/// keeping nothing from the candidates that failed.
+#[tokio::test]
+async fn second_candidate_root_establishes_trust() {
+    let base = test_data().join("rotated-root");
+    let unrelated_root = tokio::fs::read(
+        test_data()
+            .join("tuf-reference-impl")
+            .join("metadata")
+            .join("1.root.json"),
+    )
+    .await
+    .unwrap();
+    let root = tokio::fs::read(base.join("1.root.json")).await.unwrap();
+
+    let repo = RepositoryLoader::new(
+        &unrelated_root,
+        dir_url(&base),
+        dir_url(base.join("targets")),
+    )
+    .additional_root(&root)
+    .load()
+    .await
+    .unwrap();
+
+    assert_eq!(repo.trusted_root_index(), 1);
+    assert_eq!(u64::from(repo.root().signed.version), 2);
+    // Only the roots of the candidate that established trust count towards the sizes, not the
+    // larger unrelated root tried first.
+    let latest_root = tokio::fs::metadata(base.join("2.root.json")).await.unwrap();
+    assert!(unrelated_root.len() as u64 > latest_root.len());
+    assert_eq!(repo.metadata_sizes().root, latest_root.len());
+}
+
+/// Test that a load fails when no candidate root establishes trust.
+#[tokio::test]
+async fn no_candidate_root_establishes_trust() {
+    let base = test_data().join("rotated-root");
+    let unrelated_root = tokio::fs::read(
+        test_data()
+            .join("tuf-reference-impl")
+            .join("metadata")
+            .join("1.root.json"),
+    )
+    .await
+    .unwrap();
+    let other_root = tokio::fs::read(test_data().join("simple-rsa").join("root.json"))
+        .await
+        .unwrap();
+
+    let err = RepositoryLoader::new(
+        &unrelated_root,
+        dir_url(&base),
+        dir_url(base.join("targets")),
+    )
+    .additional_root(&other_root)
+    .load()
+    .await
+    .unwrap_err();
+
+    assert!(
+        matches!(err, Error::NoTrustedRoot { candidates: 2, .. }),
+        "{}",
+        err
+    );
+}
+
+/// Copies the rotated-root repository, without its `2.root.json`, to a temporary directory.
+async fn rotated_root_without_latest_root() -> TempDir {
+    let base = test_data().join("rotated-root");
+    let metadata = TempDir::new().unwrap();
+    let mut entries = tokio::fs::read_dir(&base).await.unwrap();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        if entry.file_name() != "2.root.json" && entry.file_type().await.unwrap().is_file() {
+            tokio::fs::copy(entry.path(), metadata.path().join(entry.file_name()))
+                .await
+                .unwrap();
+        }
+    }
+    metadata
+}
+
+/// Test that a supplied root chain fast-forwards trust without downloading the roots, skipping
+/// roots that aren't newer than the trusted root.
+#[tokio::test]
+async fn trusted_root_chain_fast_forwards() {
+    let base = test_data().join("rotated-root");
+    let metadata = rotated_root_without_latest_root().await;
+    let root = tokio::fs::read(base.join("1.root.json")).await.unwrap();
+    let root_2 = tokio::fs::read(base.join("2.root.json")).await.unwrap();
+    let loader = RepositoryLoader::new(
+        &root,
+        dir_url(metadata.path()),
+        dir_url(base.join("targets")),
+    );
+
+    // Without the chain, the client can't establish trust in the rotated keys.
+    loader.clone().load().await.unwrap_err();
+
+    let repo = loader
+        .trusted_root_chain(vec![root.clone(), root_2])
+        .load()
+        .await
+        .unwrap();
+    assert_eq!(u64::from(repo.root().signed.version), 2);
+}
+
+/// Test that the chain ends at the first root that isn't validly signed.
+#[tokio::test]
+async fn trusted_root_chain_stops_at_invalid_root() {
+    let base = test_data().join("rotated-root");
+    let metadata = rotated_root_without_latest_root().await;
+    let root = tokio::fs::read(base.join("1.root.json")).await.unwrap();
+    let mut forged: serde_json::Value =
+        serde_json::from_slice(&tokio::fs::read(base.join("2.root.json")).await.unwrap()).unwrap();
+    forged["signed"]["expires"] = "2999-01-01T00:00:00Z".into();
+
+    RepositoryLoader::new(
+        &root,
+        dir_url(metadata.path()),
+        dir_url(base.join("targets")),
+    )
+    .trusted_root_chain(vec![serde_json::to_vec(&forged).unwrap()])
+    .load()
+    .await
+    .unwrap_err();
+}
@@ -0,0 +1,248 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use bytes::Bytes;
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::StreamExt;
+use std::num::NonZeroU64;
+use std::sync::{Arc, Mutex};
+use test_utils::{days, dir_url, read_to_end, test_data};
+use tough::error::Error;
+use tough::schema::{Root, Signed};
+use tough::{
+    FilesystemTransport, IntoVec, RepositoryLoader, RepositoryObserver, TargetName, Transport,
+    TransportError,
+};
+use url::Url;
+
+/// An observer that records each call it receives as a line of text.
+#[derive(Debug, Clone, Default)]
+struct Recorder {
+    events: Arc<Mutex<Vec<String>>>,
+    window: TimeDelta,
+}
+
+impl Recorder {
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+
+    fn push(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+impl RepositoryObserver for Recorder {
+    fn on_root_update(&self, old: &Signed<Root>, new: &Signed<Root>) {
+        self.push(format!(
+            "root update {} -> {}",
+            old.signed.version, new.signed.version
+        ));
+    }
+
+    fn on_metadata_fetched(&self, role: &str, version: NonZeroU64, bytes: &[u8]) {
+        assert!(!bytes.is_empty());
+        self.push(format!("fetched {role} v{version}"));
+    }
+
+    fn on_target_fetch_start(&self, name: &TargetName) {
+        self.push(format!("start {}", name.raw()));
+    }
+
+    fn on_target_fetch_finish(&self, name: &TargetName, result: Result<u64, &Error>) {
+        match result {
+            Ok(length) => self.push(format!("finish {} {length}", name.raw())),
+            Err(_) => self.push(format!("fail {}", name.raw())),
+        }
+    }
+
+    fn on_target_length_exceeded(&self, name: &TargetName, url: &Url, length: u64, received: u64) {
+        let file = url.path_segments().unwrap().next_back().unwrap();
+        self.push(format!(
+            "exceeded {} {file} {length} {received}",
+            name.raw()
+        ));
+    }
+
+    fn on_expiration_warning(&self, role: &str, version: NonZeroU64, _expires: DateTime<Utc>) {
+        self.push(format!("expiring {role} v{version}"));
+    }
+
+    fn expiration_warning_window(&self) -> TimeDelta {
+        self.window
+    }
+}
+
+/// The observer is told about each metadata file that is fetched, each role that expires within
+/// its window, and each target that is read.
+#[tokio::test]
+async fn observer_load_and_read() {
+    let base = test_data().join("tuf-reference-impl");
+    let recorder = Recorder::default();
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .observer(recorder.clone())
+    .load()
+    .await
+    .unwrap();
+    assert_eq!(
+        recorder.take(),
+        [
+            "fetched timestamp v1",
+            "fetched snapshot v1",
+            "fetched targets v1",
+            "fetched role1 v1",
+            "fetched role2 v1",
+        ]
+    );
+
+    let name = TargetName::new("file1.txt").unwrap();
+    let data = read_to_end(repo.read_target(&name).await.unwrap().unwrap()).await;
+    assert_eq!(
+        recorder.take(),
+        [
+            "start file1.txt".to_owned(),
+            format!("finish file1.txt {}", data.len()),
+        ]
+    );
+
+    // Nothing is reported for targets that aren't listed.
+    let missing = TargetName::new("missing.txt").unwrap();
+    assert!(repo.read_target(&missing).await.unwrap().is_none());
+    assert!(recorder.take().is_empty());
+}
+
+/// Roles that expire within the observer's window are reported at the end of the load.
+#[tokio::test]
+async fn observer_expiration_warning() {
+    let base = test_data().join("tuf-reference-impl");
+    let recorder = Recorder {
+        window: days(365 * 100),
+        ..Recorder::default()
+    };
+    RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .observer(recorder.clone())
+    .load()
+    .await
+    .unwrap();
+    let expiring: Vec<_> = recorder
+        .take()
+        .into_iter()
+        .filter(|event| event.starts_with("expiring"))
+        .collect();
+    assert_eq!(
+        expiring,
+        [
+            "expiring root v1",
+            "expiring timestamp v1",
+            "expiring snapshot v1",
+            "expiring targets v1",
+            "expiring role1 v1",
+            "expiring role2 v1",
+        ]
+    );
+}
+
+/// Each root that replaces the trusted root is reported along with the fetched file.
+#[tokio::test]
+async fn observer_root_update() {
+    let base = test_data().join("rotated-root");
+    let recorder = Recorder::default();
+    RepositoryLoader::new(
+        &tokio::fs::read(base.join("1.root.json")).await.unwrap(),
+        dir_url(&base),
+        dir_url(base.join("targets")),
+    )
+    .observer(recorder.clone())
+    .load()
+    .await
+    .unwrap();
+    let events = recorder.take();
+    assert_eq!(events[..2], ["root update 1 -> 2", "fetched root v2"]);
+}
+
+/// A transport that serves files from disk, but follows each target with endless padding.
+#[derive(Debug, Clone, Copy)]
+struct EndlessTargetTransport;
+
+#[async_trait::async_trait]
+impl Transport for EndlessTargetTransport {
+    async fn fetch(
+        &self,
+        url: Url,
+    ) -> Result<
+        std::pin::Pin<
+            Box<dyn futures_core::Stream<Item = Result<bytes::Bytes, TransportError>> + Send>,
+        >,
+        TransportError,
+    > {
+        let is_target = url.path().contains("/targets/");
+        let stream = FilesystemTransport.fetch(url).await?;
+        if is_target {
+            let padding = futures::stream::repeat(Bytes::from_static(b"padding")).map(Ok);
+            Ok(stream.chain(padding).boxed())
+        } else {
+            Ok(stream)
+        }
+    }
+}
+
+/// A target that is served past its listed length is abandoned as soon as the length is passed,
+/// and the observer is told how much was received from where.
+#[tokio::test]
+async fn observer_target_length_exceeded() {
+    let base = test_data().join("tuf-reference-impl");
+    let recorder = Recorder::default();
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .transport(EndlessTargetTransport)
+    .observer(recorder.clone())
+    .load()
+    .await
+    .unwrap();
+    recorder.take();
+
+    let name = TargetName::new("file1.txt").unwrap();
+    let length = tokio::fs::metadata(base.join("targets").join("file1.txt"))
+        .await
+        .unwrap()
+        .len();
+    let err = repo
+        .read_target(&name)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_vec()
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Maximum size"), "{}", err);
+    assert_eq!(
+        recorder.take(),
+        [
+            "start file1.txt".to_owned(),
+            format!(
+                "exceeded file1.txt file1.txt {length} {}",
+                length + "padding".len() as u64
+            ),
+            "fail file1.txt".to_owned(),
+        ]
+    );
+}
@@ -0,0 +1,155 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::path::Path;
+use tempfile::TempDir;
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::error::Error;
+use tough::{Repository, RepositoryLoader, TargetCache, TargetName};
+
+async fn load(
+    root: &Path,
+    metadata: &Path,
+    targets: &Path,
+    datastore: &Path,
+    offline: bool,
+    cache: Option<TargetCache>,
+) -> tough::error::Result<Repository> {
+    let root = tokio::fs::read(root).await.unwrap();
+    let mut loader = RepositoryLoader::new(&root, dir_url(metadata), dir_url(targets))
+        .datastore(datastore)
+        .offline(offline);
+    if let Some(cache) = cache {
+        loader = loader.target_cache(cache);
+    }
+    loader.load().await
+}
+
+/// Test that repositories loaded once can be loaded again from the datastore alone, including
+/// ones with consistent snapshots, percent encoded delegated role names and a rotated root.
+#[tokio::test]
+async fn offline_load_from_datastore() {
+    for (name, metadata) in [
+        ("tuf-reference-impl", "metadata"),
+        ("consistent-snapshots", "metadata"),
+        ("dubious-role-names", "metadata"),
+        ("rotated-root", ""),
+    ] {
+        let base = test_data().join(name);
+        let metadata = base.join(metadata);
+        let root = metadata.join("1.root.json");
+        let datastore = TempDir::new().unwrap();
+        let online = load(
+            &root,
+            &metadata,
+            &base.join("targets"),
+            datastore.path(),
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let missing = TempDir::new().unwrap();
+        let offline = load(
+            &root,
+            missing.path(),
+            missing.path(),
+            datastore.path(),
+            true,
+            None,
+        )
+        .await
+        .unwrap_or_else(|err| panic!("{}: {}", name, err));
+        assert_eq!(offline.root().signed, online.root().signed, "{}", name);
+        assert_eq!(
+            offline.snapshot().signed,
+            online.snapshot().signed,
+            "{}",
+            name
+        );
+        assert_eq!(
+            offline.targets().signed,
+            online.targets().signed,
+            "{}",
+            name
+        );
+    }
+}
+
+/// Test that an offline load fails when the datastore doesn't hold the metadata, or when there is
+/// no datastore.
+#[tokio::test]
+async fn offline_load_requires_stored_metadata() {
+    let base = test_data().join("tuf-reference-impl");
+    let root = base.join("metadata").join("1.root.json");
+    let datastore = TempDir::new().unwrap();
+    let result = load(
+        &root,
+        &base.join("metadata"),
+        &base.join("targets"),
+        datastore.path(),
+        true,
+        None,
+    )
+    .await;
+    assert!(
+        matches!(result, Err(Error::Transport { .. })),
+        "{:?}",
+        result.err()
+    );
+
+    let result = RepositoryLoader::new(
+        &tokio::fs::read(&root).await.unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .offline(true)
+    .load()
+    .await;
+    assert!(matches!(
+        result,
+        Err(Error::OfflineDatastoreRequired { .. })
+    ));
+}
+
+/// Test that offline loads only serve targets from the target cache.
+#[tokio::test]
+async fn offline_targets_from_cache() {
+    let base = test_data().join("tuf-reference-impl");
+    let root = base.join("metadata").join("1.root.json");
+    let datastore = TempDir::new().unwrap();
+    let cache = TargetCache::new(1024);
+    let file1 = TargetName::new("file1.txt").unwrap();
+    let file2 = TargetName::new("file2.txt").unwrap();
+
+    let online = load(
+        &root,
+        &base.join("metadata"),
+        &base.join("targets"),
+        datastore.path(),
+        false,
+        Some(cache.clone()),
+    )
+    .await
+    .unwrap();
+    read_to_end(online.read_target(&file1).await.unwrap().unwrap()).await;
+
+    let offline = load(
+        &root,
+        &base.join("metadata"),
+        &base.join("targets"),
+        datastore.path(),
+        true,
+        Some(cache),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        read_to_end(offline.read_target(&file1).await.unwrap().unwrap()).await,
+        &b"This is an example target file."[..]
+    );
+    assert!(offline.read_target(&file2).await.is_err());
+}
@@ -0,0 +1,63 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use test_utils::{dir_url, test_data};
+use tough::error::Error;
+use tough::{Repository, RepositoryLoader, TargetName};
+
+async fn load_reference_repo() -> Repository {
+    let base = test_data().join("tuf-reference-impl");
+    RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap()
+}
+
+/// A target that fits within the limit is returned whole.
+#[tokio::test]
+async fn read_target_bytes() {
+    let repo = load_reference_repo().await;
+    let name = TargetName::new("file1.txt").unwrap();
+    let expected = std::fs::read(
+        test_data()
+            .join("tuf-reference-impl")
+            .join("targets")
+            .join("file1.txt"),
+    )
+    .unwrap();
+    let data = repo
+        .read_target_bytes(&name, expected.len() as u64)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(data, expected);
+
+    let missing = TargetName::new("missing.txt").unwrap();
+    assert!(repo
+        .read_target_bytes(&missing, 1024)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+/// A target whose listed length exceeds the limit is refused.
+#[tokio::test]
+async fn read_target_bytes_too_large() {
+    let repo = load_reference_repo().await;
+    let name = TargetName::new("file1.txt").unwrap();
+    let length = repo.targets().signed.find_target(&name).unwrap().length;
+    let err = repo.read_target_bytes(&name, length - 1).await.unwrap_err();
+    assert!(
+        matches!(err, Error::MaxSizeExceeded { max_size, .. } if max_size == length - 1),
+        "{}",
+        err
+    );
+}
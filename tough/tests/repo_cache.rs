@@ -4,7 +4,7 @@
 use std::path::PathBuf;
 use tempfile::TempDir;
 use test_utils::{dir_url, read_to_end, test_data, DATA_1, DATA_2};
-use tough::{Repository, RepositoryLoader, TargetName};
+use tough::{CacheLayout, Repository, RepositoryLoader, TargetName};
 use url::Url;
 
 mod test_utils;
@@ -290,3 +290,123 @@ async fn test_repo_cache_consistent_snapshots() {
         .join("5aa1d2b3bea034a0f9d0b27a1bc72919b3145a2b092b72ac0415a05e07e2bdd1.data1.txt");
     assert!(expected_filepath.is_file())
 }
+
+/// Test that the cached metadata is what was verified when the repository was loaded, even if the
+/// repository has changed since then.
+#[tokio::test]
+async fn test_repo_cache_trusted_metadata() {
+    let base = test_data().join("tuf-reference-impl");
+    let source = TempDir::new().unwrap();
+    let mut entries = tokio::fs::read_dir(base.join("metadata")).await.unwrap();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        tokio::fs::copy(entry.path(), source.path().join(entry.file_name()))
+            .await
+            .unwrap();
+    }
+    let repo_paths = RepoPaths::new();
+    let repo = RepositoryLoader::new(
+        &repo_paths.root().await,
+        dir_url(source.path()),
+        repo_paths.targets_base_url.clone(),
+    )
+    .load()
+    .await
+    .unwrap();
+
+    for name in [
+        "timestamp.json",
+        "snapshot.json",
+        "targets.json",
+        "role1.json",
+    ] {
+        tokio::fs::write(source.path().join(name), "{}")
+            .await
+            .unwrap();
+    }
+
+    let destination = TempDir::new().unwrap();
+    let metadata_destination = destination.as_ref().join("metadata");
+    let targets_destination = destination.as_ref().join("targets");
+    repo.cache(
+        &metadata_destination,
+        &targets_destination,
+        Some(&["file1.txt"]),
+        true,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        tokio::fs::read(metadata_destination.join("snapshot.json"))
+            .await
+            .unwrap(),
+        tokio::fs::read(base.join("metadata").join("snapshot.json"))
+            .await
+            .unwrap()
+    );
+
+    let copied_repo = RepositoryLoader::new(
+        &repo_paths.root().await,
+        dir_url(&metadata_destination),
+        dir_url(&targets_destination),
+    )
+    .load()
+    .await
+    .unwrap();
+    let file1 = TargetName::new("file1.txt").unwrap();
+    assert_eq!(
+        read_to_end(copied_repo.read_target(&file1).await.unwrap().unwrap()).await,
+        &b"This is an example target file."[..]
+    );
+    assert!(copied_repo
+        .delegated_role("role1")
+        .unwrap()
+        .targets
+        .is_some());
+}
+
+/// Test that a repository cached with the servable layout can be loaded from its `metadata/` and
+/// `targets/` directories, starting from its first root.
+#[tokio::test]
+async fn test_repo_cache_servable() {
+    let repo_name = "consistent-snapshots";
+    let metadata_dir = test_data().join(repo_name).join("metadata");
+    let targets_dir = test_data().join(repo_name).join("targets");
+    let root = tokio::fs::read(metadata_dir.join("1.root.json"))
+        .await
+        .unwrap();
+    let repo = RepositoryLoader::new(&root, dir_url(metadata_dir), dir_url(targets_dir))
+        .load()
+        .await
+        .unwrap();
+
+    let destination = TempDir::new().unwrap();
+    let layout = CacheLayout::Servable(destination.path().join("repo"));
+    repo.cache_to(&layout, Some(&["data1.txt"])).await.unwrap();
+    assert_eq!(
+        layout.metadata_dir(),
+        destination.path().join("repo/metadata")
+    );
+    assert_eq!(
+        layout.targets_dir(),
+        destination.path().join("repo/targets")
+    );
+    assert!(layout.metadata_dir().join("1.root.json").is_file());
+
+    let copied_repo = RepositoryLoader::new(
+        &root,
+        dir_url(layout.metadata_dir()),
+        dir_url(layout.targets_dir()),
+    )
+    .load()
+    .await
+    .unwrap();
+    let data1 = read_to_end(
+        copied_repo
+            .read_target(&TargetName::new("data1.txt").unwrap())
+            .await
+            .unwrap()
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(String::from_utf8(data1).unwrap(), DATA_1);
+}
@@ -2,22 +2,28 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::test_utils::{days, dir_url, read_to_end, test_data};
+use aws_lc_rs::digest::{digest, SHA256, SHA512};
 use chrono::Utc;
+use flate2::read::GzDecoder;
 use std::collections::HashMap;
+use std::io::Read;
 use std::num::NonZeroU64;
 use std::path::PathBuf;
 use tempfile::TempDir;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
-use tough::editor::signed::PathExists;
-use tough::editor::{targets::TargetsEditor, RepositoryEditor};
+use tough::editor::signed::{GzipManifest, MetadataStyle, PathExists, GZIP_MANIFEST_FILENAME};
+use tough::editor::{root::RootEditor, targets::TargetsEditor, RepositoryEditor};
 use tough::key_source::KeySource;
 use tough::key_source::LocalKeySource;
 use tough::schema::decoded::Decoded;
 use tough::schema::decoded::Hex;
 use tough::schema::key::Key;
-use tough::schema::{PathPattern, PathSet};
-use tough::{Repository, RepositoryLoader, TargetName};
+use tough::schema::{
+    DelegatedRole, Delegations, KeyHolder, PathPattern, PathSet, RoleType, Root, Signature, Signed,
+    Target, Targets,
+};
+use tough::{HashAlgorithm, IntoVec, Prefix, Repository, RepositoryLoader, TargetName};
 use url::Url;
 
 mod test_utils;
@@ -103,6 +109,469 @@ async fn test_repo_editor() -> RepositoryEditor {
     editor
 }
 
+// Test that each gzip companion decompresses to the metadata file it's listed for, with the
+// length and hashes recorded in the manifest
+#[tokio::test]
+async fn write_with_gzip() {
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource { path: key_path() })];
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.as_ref().join("metadata");
+    let signed = test_repo_editor().await.sign(&keys).await.unwrap();
+    let manifest = signed.write_with_gzip(&metadata_destination).await.unwrap();
+
+    assert_eq!(
+        manifest
+            .files
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>(),
+        [
+            "1.root.json",
+            "5432.snapshot.json",
+            "789.targets.json",
+            "timestamp.json"
+        ]
+    );
+    for (filename, companion) in &manifest.files {
+        let plain = std::fs::read(metadata_destination.join(filename)).unwrap();
+        let compressed = std::fs::read(metadata_destination.join(&companion.filename)).unwrap();
+        assert_eq!(companion.filename, format!("{}.gz", filename));
+        assert_eq!(companion.length, compressed.len() as u64);
+        assert_eq!(
+            companion.sha256,
+            digest(&SHA256, &compressed).as_ref().to_vec()
+        );
+        assert_eq!(
+            companion.metadata_sha256,
+            digest(&SHA256, &plain).as_ref().to_vec()
+        );
+        let mut decompressed = Vec::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, plain);
+    }
+
+    let written: GzipManifest = serde_json::from_slice(
+        &std::fs::read(metadata_destination.join(GZIP_MANIFEST_FILENAME)).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(written, manifest);
+}
+
+// Test that compact metadata has no whitespace, is smaller than pretty metadata, and loads with
+// the lengths and hashes listed for it
+#[tokio::test]
+async fn compact_metadata() {
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource { path: key_path() })];
+    let pretty = test_repo_editor().await.sign(&keys).await.unwrap();
+    let mut editor = test_repo_editor().await;
+    editor.metadata_style(MetadataStyle::Compact);
+    let compact = editor.sign(&keys).await.unwrap();
+
+    let pretty_files = pretty.metadata_files().unwrap();
+    let compact_files = compact.metadata_files().unwrap();
+    for (filename, data) in &compact_files {
+        if filename.ends_with("root.json") {
+            assert_eq!(data, &pretty_files[filename]);
+            continue;
+        }
+        assert!(!data.contains(&b'\n'), "{}", filename);
+        assert!(data.len() < pretty_files[filename].len(), "{}", filename);
+    }
+
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.path().join("metadata");
+    let targets_destination = repo_dir.path().join("targets");
+    compact.write(&metadata_destination).await.unwrap();
+    compact
+        .link_targets(targets_path(), &targets_destination, PathExists::Skip)
+        .await
+        .unwrap();
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_path()).await.unwrap(),
+        dir_url(&metadata_destination),
+        dir_url(&targets_destination),
+    )
+    .load()
+    .await
+    .unwrap();
+    let file3 = TargetName::new("file3.txt").unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target(&file3).await.unwrap().unwrap()).await,
+        tokio::fs::read(targets_path().join("file3.txt"))
+            .await
+            .unwrap()
+    );
+}
+
+// Test that extension fields on the snapshot and timestamp listings survive signing, loading, and
+// re-signing a loaded repo
+#[tokio::test]
+async fn meta_extensions_round_trip() {
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource { path: key_path() })];
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.as_ref().join("metadata");
+    let load = || async {
+        RepositoryLoader::new(
+            &tokio::fs::read(root_path()).await.unwrap(),
+            dir_url(&metadata_destination),
+            dir_url(targets_path()),
+        )
+        .load()
+        .await
+        .unwrap()
+    };
+    let assert_extensions = |repo: &Repository| {
+        let targets_meta = repo.snapshot_meta()["targets"];
+        assert_eq!(
+            targets_meta.custom::<Vec<String>>().unwrap().unwrap(),
+            ["stable", "beta"]
+        );
+        assert_eq!(targets_meta.extension::<u64>("rollout").unwrap(), Some(50));
+        assert_eq!(targets_meta.extension::<u64>("missing").unwrap(), None);
+        assert!(targets_meta.extension::<u64>("custom").is_err());
+        assert_eq!(
+            repo.timestamp_meta()["snapshot"]
+                .custom::<String>()
+                .unwrap()
+                .unwrap(),
+            "stable"
+        );
+    };
+
+    let mut editor = test_repo_editor().await;
+    editor
+        .snapshot_meta_extension("targets", "custom", &["stable", "beta"])
+        .unwrap()
+        .snapshot_meta_extension("targets", "rollout", &50)
+        .unwrap()
+        .timestamp_meta_extension("custom", &"stable")
+        .unwrap();
+    assert!(editor
+        .snapshot_meta_extension("targets", "version", &2)
+        .is_err());
+    editor
+        .sign(&keys)
+        .await
+        .unwrap()
+        .write(&metadata_destination)
+        .await
+        .unwrap();
+    assert_extensions(&load().await);
+
+    let mut editor = RepositoryEditor::from_repo(root_path(), load().await)
+        .await
+        .unwrap();
+    editor
+        .targets_version(NonZeroU64::new(790).unwrap())
+        .unwrap()
+        .targets_expires(Utc::now().checked_add_signed(days(13)).unwrap())
+        .unwrap()
+        .snapshot_version(NonZeroU64::new(5433).unwrap())
+        .snapshot_expires(Utc::now().checked_add_signed(days(21)).unwrap())
+        .timestamp_version(NonZeroU64::new(1235).unwrap())
+        .timestamp_expires(Utc::now().checked_add_signed(days(3)).unwrap());
+    editor
+        .sign(&keys)
+        .await
+        .unwrap()
+        .write(&metadata_destination)
+        .await
+        .unwrap();
+    let repo = load().await;
+    assert_eq!(repo.snapshot().signed.version.get(), 5433);
+    assert_extensions(&repo);
+}
+
+// Test that the editor lists extra hashes for targets and metadata, and that the client checks
+// every hash listed
+#[tokio::test]
+async fn sha512_hashes() {
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource { path: key_path() })];
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.as_ref().join("metadata");
+    let targets_destination = repo_dir.as_ref().join("targets");
+    let file1 = TargetName::new("file1.txt").unwrap();
+    let file2 = TargetName::new("file2.txt").unwrap();
+
+    let mut tampered =
+        Target::from_path_with_hashes(targets_path().join("file2.txt"), &[HashAlgorithm::Sha512])
+            .await
+            .unwrap();
+    tampered.hashes.set_digest(HashAlgorithm::Sha512, &[0; 64]);
+    let mut editor = test_repo_editor().await;
+    editor
+        .hash_algorithms(&[HashAlgorithm::Sha512])
+        .add_target_path(targets_path().join("file1.txt"))
+        .await
+        .unwrap()
+        .add_target(file2.clone(), tampered)
+        .unwrap();
+    let signed = editor.sign(&keys).await.unwrap();
+    signed.write(&metadata_destination).await.unwrap();
+    signed
+        .copy_targets(targets_path(), &targets_destination, PathExists::Skip)
+        .await
+        .unwrap();
+
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_path()).await.unwrap(),
+        dir_url(&metadata_destination),
+        dir_url(&targets_destination),
+    )
+    .load()
+    .await
+    .unwrap();
+    let target = &repo.targets().signed.targets[&file1];
+    assert_eq!(
+        target.hashes.algorithms(),
+        [HashAlgorithm::Sha512, HashAlgorithm::Sha256]
+    );
+    let contents = std::fs::read(targets_path().join("file1.txt")).unwrap();
+    assert_eq!(
+        target
+            .hashes
+            .digest(HashAlgorithm::Sha512)
+            .unwrap()
+            .unwrap(),
+        digest(&SHA512, &contents).as_ref()
+    );
+    let targets_meta = repo.snapshot_meta()["targets"];
+    let written = std::fs::read(metadata_destination.join("789.targets.json")).unwrap();
+    assert_eq!(
+        targets_meta
+            .hashes
+            .as_ref()
+            .unwrap()
+            .digest(HashAlgorithm::Sha512)
+            .unwrap()
+            .unwrap(),
+        digest(&SHA512, &written).as_ref()
+    );
+
+    assert_eq!(
+        read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await,
+        contents
+    );
+    let stream = repo.read_target(&file2).await.unwrap().unwrap();
+    let err = stream.into_vec().await.unwrap_err();
+    assert!(err.to_string().contains("Hash mismatch"), "{}", err);
+}
+
+// Test that re-signing a loaded repo fails unless the snapshot and timestamp versions advance,
+// and the targets version advances if the role changed
+#[tokio::test]
+async fn version_regression_from_repo() {
+    let keys: Vec<Box<dyn KeySource>> = vec![Box::new(LocalKeySource { path: key_path() })];
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.as_ref().join("metadata");
+    let signed = test_repo_editor().await.sign(&keys).await.unwrap();
+    signed.write(&metadata_destination).await.unwrap();
+
+    let load = || async {
+        RepositoryLoader::new(
+            &tokio::fs::read(root_path()).await.unwrap(),
+            dir_url(&metadata_destination),
+            dir_url(targets_path()),
+        )
+        .load()
+        .await
+        .unwrap()
+    };
+    let edit = |repo: Repository| async {
+        let mut editor = RepositoryEditor::from_repo(root_path(), repo)
+            .await
+            .unwrap();
+        editor
+            .targets_version(NonZeroU64::new(790).unwrap())
+            .unwrap()
+            .targets_expires(Utc::now().checked_add_signed(days(13)).unwrap())
+            .unwrap()
+            .snapshot_version(NonZeroU64::new(5433).unwrap())
+            .snapshot_expires(Utc::now().checked_add_signed(days(21)).unwrap())
+            .timestamp_version(NonZeroU64::new(1234).unwrap())
+            .timestamp_expires(Utc::now().checked_add_signed(days(3)).unwrap());
+        editor
+    };
+
+    // The timestamp version is unchanged from the loaded repo
+    let err = edit(load().await).await.sign(&keys).await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            tough::error::Error::VersionRegression {
+                version: 1234,
+                loaded: 1234,
+                ..
+            }
+        ),
+        "{}",
+        err
+    );
+
+    let mut editor = edit(load().await).await;
+    editor.allow_version_regression(true);
+    assert!(editor.sign(&keys).await.is_ok());
+
+    // The targets role changed, with a new expiration, but kept its version
+    let mut editor = edit(load().await).await;
+    editor
+        .targets_version(NonZeroU64::new(789).unwrap())
+        .unwrap()
+        .timestamp_version(NonZeroU64::new(1235).unwrap());
+    let problems = editor.validate(&keys).await;
+    assert!(
+        matches!(
+            problems.as_slice(),
+            [tough::error::Error::VersionRegression {
+                role: RoleType::Targets,
+                version: 789,
+                loaded: 789,
+                ..
+            }]
+        ),
+        "{:?}",
+        problems
+    );
+    let err = editor.sign(&keys).await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            tough::error::Error::VersionRegression {
+                role: RoleType::Targets,
+                ..
+            }
+        ),
+        "{}",
+        err
+    );
+
+    // An unchanged targets role may keep its version
+    let repo = load().await;
+    let targets_expires = repo.targets().signed.expires;
+    let mut editor = edit(repo).await;
+    editor
+        .targets_version(NonZeroU64::new(789).unwrap())
+        .unwrap()
+        .targets_expires(targets_expires)
+        .unwrap()
+        .timestamp_version(NonZeroU64::new(1235).unwrap());
+    let problems = editor.validate(&keys).await;
+    assert!(problems.is_empty(), "{:?}", problems);
+    assert!(editor.sign(&keys).await.is_ok());
+}
+
+// Test that a root rotated to a new key with `RootEditor` must be cross-signed by the old key, and
+// that a repository signed with the new key loads from the old root.json
+#[tokio::test]
+async fn rotate_root_key() {
+    let old_key: Box<dyn KeySource> = Box::new(LocalKeySource { path: key_path() });
+    let new_key: Box<dyn KeySource> = Box::new(LocalKeySource {
+        path: test_data().join("snakeoil_2.pem"),
+    });
+    let old_key_id = old_key.public_key().await.unwrap().key_id().unwrap();
+
+    let mut editor = test_repo_editor().await;
+    let mut root_editor = editor.root_editor();
+    root_editor
+        .add_key(
+            new_key.public_key().await.unwrap(),
+            &[
+                RoleType::Root,
+                RoleType::Snapshot,
+                RoleType::Targets,
+                RoleType::Timestamp,
+            ],
+        )
+        .unwrap();
+    root_editor
+        .remove_key(&old_key_id, None)
+        .bump_version()
+        .unwrap()
+        .expires(Utc::now().checked_add_signed(days(365)).unwrap());
+    assert_eq!(root_editor.root().version.get(), 2);
+
+    // Clients trust the new root only if the old root's key signs it too
+    let err = root_editor
+        .sign(std::slice::from_ref(&new_key))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, tough::error::Error::VerifyRoleMetadata { .. }),
+        "{}",
+        err
+    );
+    let keys = vec![old_key, new_key];
+    let signed_root = root_editor.sign(&keys).await.unwrap();
+    assert_eq!(signed_root.signed().signatures.len(), 2);
+
+    editor.root(signed_root).unwrap();
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_destination = repo_dir.as_ref().join("metadata");
+    let signed = editor.sign(&keys[1..]).await.unwrap();
+    signed.write(&metadata_destination).await.unwrap();
+
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_path()).await.unwrap(),
+        dir_url(&metadata_destination),
+        dir_url(targets_path()),
+    )
+    .load()
+    .await
+    .unwrap();
+    assert_eq!(repo.root().signed.version.get(), 2);
+    assert!(!repo.root().signed.keys.contains_key(&old_key_id));
+}
+
+// Test that a role tough doesn't manage survives editing and signing root.json unchanged, along
+// with the key it lists
+#[tokio::test]
+async fn unrecognized_root_role_preserved() {
+    let old_key: Box<dyn KeySource> = Box::new(LocalKeySource { path: key_path() });
+    let new_key: Box<dyn KeySource> = Box::new(LocalKeySource {
+        path: test_data().join("snakeoil_2.pem"),
+    });
+    let mut root: Signed<Root> =
+        serde_json::from_slice(&tokio::fs::read(root_path()).await.unwrap()).unwrap();
+    let old_key_id = root.signed.roles[&RoleType::Root].keyids[0].clone();
+    let mirrors = serde_json::json!({
+        "keyids": [old_key_id],
+        "threshold": 1,
+        "mirror_hint": {"region": "us-west-2"}
+    });
+    root.signed
+        .unrecognized_roles
+        .insert("mirrors".to_owned(), mirrors.clone());
+    let mut root_editor = RootEditor::new(root);
+    root_editor
+        .add_key(
+            new_key.public_key().await.unwrap(),
+            &[
+                RoleType::Root,
+                RoleType::Snapshot,
+                RoleType::Targets,
+                RoleType::Timestamp,
+            ],
+        )
+        .unwrap();
+    root_editor
+        .remove_key(&old_key_id, None)
+        .bump_version()
+        .unwrap();
+    let signed_root = root_editor.sign(&[old_key, new_key]).await.unwrap();
+
+    let reloaded: Signed<Root> = serde_json::from_slice(signed_root.buffer()).unwrap();
+    assert_eq!(reloaded.signed.unrecognized_roles["mirrors"], mirrors);
+    // The key is no longer authorized for the roles tough manages, but is still listed for mirrors
+    assert!(reloaded.signed.roles[&RoleType::Root]
+        .keyids
+        .iter()
+        .all(|keyid| keyid != &old_key_id));
+    assert!(reloaded.signed.keys.contains_key(&old_key_id));
+    reloaded.signed.verify_role(&reloaded).unwrap();
+}
+
 async fn key_hash_map(keys: &[Box<dyn KeySource>]) -> HashMap<Decoded<Hex>, Key> {
     let mut key_pairs = HashMap::new();
     for source in keys {
@@ -112,6 +581,57 @@ async fn key_hash_map(keys: &[Box<dyn KeySource>]) -> HashMap<Decoded<Hex>, Key>
     key_pairs
 }
 
+/// A `TargetsEditor` for delegated role "A", whose only key is the one in `keys`.
+async fn rotation_editor(
+    keys: &[Box<dyn KeySource>],
+    expires: chrono::DateTime<Utc>,
+) -> TargetsEditor {
+    let role_keys = key_hash_map(keys).await;
+    let mut delegations = Delegations::new();
+    delegations.roles.push(DelegatedRole {
+        name: "A".to_string(),
+        keyids: role_keys.keys().cloned().collect(),
+        threshold: NonZeroU64::new(1).unwrap(),
+        paths: PathSet::Paths(vec![PathPattern::new("*.txt").unwrap()]),
+        terminating: false,
+        targets: None,
+    });
+    delegations.keys = role_keys;
+    let targets = Targets::new("1.0.0".to_string(), NonZeroU64::new(1).unwrap(), expires);
+    let mut editor = TargetsEditor::from_targets("A", targets, KeyHolder::Delegations(delegations));
+    editor.version(NonZeroU64::new(2).unwrap()).expires(expires);
+    editor
+}
+
+// Test that a delegated role carries the signatures of a rotated-out key, and that only its
+// current keys count toward its threshold
+#[tokio::test]
+async fn targets_old_signatures() {
+    let old_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource {
+        path: targets_key_path(),
+    })];
+    let new_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource {
+        path: targets_key_path1(),
+    })];
+    let expires = Utc::now().checked_add_signed(days(21)).unwrap();
+    let old_signatures: Vec<Signature> = rotation_editor(old_key, expires)
+        .await
+        .create_signed(old_key)
+        .await
+        .unwrap()
+        .signatures;
+
+    let mut editor = rotation_editor(new_key, expires).await;
+    editor.add_old_signatures(old_signatures.clone());
+    assert!(editor.create_signed(old_key).await.is_err());
+
+    let signed = editor.create_signed(new_key).await.unwrap();
+    assert_eq!(signed.signatures.len(), 2);
+    assert!(signed.signatures.contains(&old_signatures[0]));
+    let signed = editor.sign(new_key).await.unwrap();
+    assert_eq!(signed.roles()[0].signed().signatures.len(), 2);
+}
+
 // Test a RepositoryEditor can be created from an existing Repo
 #[tokio::test]
 async fn repository_editor_from_repository() {
@@ -152,13 +672,13 @@ async fn create_sign_write_reload_repo() {
         .await
         .unwrap();
 
-    let targets_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+    let targets_key: &[std::boxed::Box<dyn tough::key_source::KeySource + 'static>] =
         &[Box::new(LocalKeySource { path: key_path() })];
-    let role1_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+    let role1_key: &[std::boxed::Box<dyn tough::key_source::KeySource + 'static>] =
         &[Box::new(LocalKeySource {
             path: targets_key_path(),
         })];
-    let role2_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+    let role2_key: &[std::boxed::Box<dyn tough::key_source::KeySource + 'static>] =
         &[Box::new(LocalKeySource {
             path: targets_key_path1(),
         })];
@@ -256,13 +776,13 @@ async fn create_sign_write_reload_repo() {
 async fn create_role_flow() {
     let editor = test_repo_editor().await;
 
-    let targets_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+    let targets_key: &[std::boxed::Box<dyn tough::key_source::KeySource + 'static>] =
         &[Box::new(LocalKeySource { path: key_path() })];
-    let role1_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+    let role1_key: &[std::boxed::Box<dyn tough::key_source::KeySource + 'static>] =
         &[Box::new(LocalKeySource {
             path: targets_key_path(),
         })];
-    let role2_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+    let role2_key: &[std::boxed::Box<dyn tough::key_source::KeySource + 'static>] =
         &[Box::new(LocalKeySource {
             path: targets_key_path1(),
         })];
@@ -322,11 +842,11 @@ async fn create_role_flow() {
     let root_key = key_path();
     let key_source = LocalKeySource { path: root_key };
     let timestamp_expiration = Utc::now().checked_add_signed(days(3)).unwrap();
-    let timestamp_version = NonZeroU64::new(1234).unwrap();
+    let timestamp_version = NonZeroU64::new(1235).unwrap();
     let snapshot_expiration = Utc::now().checked_add_signed(days(21)).unwrap();
-    let snapshot_version = NonZeroU64::new(5432).unwrap();
+    let snapshot_version = NonZeroU64::new(5433).unwrap();
     let targets_expiration = Utc::now().checked_add_signed(days(13)).unwrap();
-    let targets_version = NonZeroU64::new(789).unwrap();
+    let targets_version = NonZeroU64::new(790).unwrap();
     editor
         .targets_expires(targets_expiration)
         .unwrap()
@@ -401,7 +921,7 @@ async fn create_role_flow() {
         )
         .await
         .unwrap()
-        .version(NonZeroU64::new(1).unwrap())
+        .version(NonZeroU64::new(2).unwrap())
         .expires(Utc::now().checked_add_signed(days(21)).unwrap());
 
     // sign A and write A and B metadata to output directory
@@ -440,9 +960,9 @@ async fn create_role_flow() {
         .await
         .unwrap();
     editor
-        .snapshot_version(NonZeroU64::new(1).unwrap())
+        .snapshot_version(NonZeroU64::new(5434).unwrap())
         .snapshot_expires(Utc::now().checked_add_signed(days(21)).unwrap())
-        .timestamp_version(NonZeroU64::new(1).unwrap())
+        .timestamp_version(NonZeroU64::new(1236).unwrap())
         .timestamp_expires(Utc::now().checked_add_signed(days(21)).unwrap());
 
     let signed_refreshed_repo = editor.sign(&[Box::new(key_source)]).await.unwrap();
@@ -480,13 +1000,13 @@ async fn update_targets_flow() {
     // The beginning of this creates a repo with Target -> A ('*.txt') -> B ('file?.txt')
     let editor = test_repo_editor().await;
 
-    let targets_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+    let targets_key: &[std::boxed::Box<dyn tough::key_source::KeySource + 'static>] =
         &[Box::new(LocalKeySource { path: key_path() })];
-    let role1_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+    let role1_key: &[std::boxed::Box<dyn tough::key_source::KeySource + 'static>] =
         &[Box::new(LocalKeySource {
             path: targets_key_path(),
         })];
-    let role2_key: &[std::boxed::Box<(dyn tough::key_source::KeySource + 'static)>] =
+    let role2_key: &[std::boxed::Box<dyn tough::key_source::KeySource + 'static>] =
         &[Box::new(LocalKeySource {
             path: targets_key_path1(),
         })];
@@ -546,11 +1066,11 @@ async fn update_targets_flow() {
     let root_key = key_path();
     let key_source = LocalKeySource { path: root_key };
     let timestamp_expiration = Utc::now().checked_add_signed(days(3)).unwrap();
-    let timestamp_version = NonZeroU64::new(1234).unwrap();
+    let timestamp_version = NonZeroU64::new(1235).unwrap();
     let snapshot_expiration = Utc::now().checked_add_signed(days(21)).unwrap();
-    let snapshot_version = NonZeroU64::new(5432).unwrap();
+    let snapshot_version = NonZeroU64::new(5433).unwrap();
     let targets_expiration = Utc::now().checked_add_signed(days(13)).unwrap();
-    let targets_version = NonZeroU64::new(789).unwrap();
+    let targets_version = NonZeroU64::new(790).unwrap();
     editor
         .targets_expires(targets_expiration)
         .unwrap()
@@ -625,7 +1145,7 @@ async fn update_targets_flow() {
         )
         .await
         .unwrap()
-        .version(NonZeroU64::new(1).unwrap())
+        .version(NonZeroU64::new(2).unwrap())
         .expires(Utc::now().checked_add_signed(days(21)).unwrap());
 
     // sign A and write A and B metadata to output directory
@@ -664,9 +1184,9 @@ async fn update_targets_flow() {
         .await
         .unwrap();
     editor
-        .snapshot_version(NonZeroU64::new(1).unwrap())
+        .snapshot_version(NonZeroU64::new(5434).unwrap())
         .snapshot_expires(Utc::now().checked_add_signed(days(21)).unwrap())
-        .timestamp_version(NonZeroU64::new(1).unwrap())
+        .timestamp_version(NonZeroU64::new(1236).unwrap())
         .timestamp_expires(Utc::now().checked_add_signed(days(21)).unwrap());
 
     let signed_refreshed_repo = editor.sign(&[Box::new(key_source)]).await.unwrap();
@@ -748,9 +1268,9 @@ async fn update_targets_flow() {
         .update_delegated_targets("A", metadata_base_url_out.as_str())
         .await
         .unwrap()
-        .snapshot_version(snapshot_version)
+        .snapshot_version(NonZeroU64::new(5435).unwrap())
         .snapshot_expires(snapshot_expiration)
-        .timestamp_version(timestamp_version)
+        .timestamp_version(NonZeroU64::new(1237).unwrap())
         .timestamp_expires(timestamp_expiration);
     let signed_repo = editor.sign(targets_key).await.unwrap();
 
@@ -801,7 +1321,7 @@ async fn update_targets_flow() {
         .add_target_paths(targets)
         .await
         .unwrap()
-        .version(targets_version)
+        .version(NonZeroU64::new(791).unwrap())
         .expires(targets_expiration);
 
     // Sign A metadata
@@ -847,9 +1367,9 @@ async fn update_targets_flow() {
         .update_delegated_targets("A", metadata_base_url_out.as_str())
         .await
         .unwrap()
-        .snapshot_version(snapshot_version)
+        .snapshot_version(NonZeroU64::new(5436).unwrap())
         .snapshot_expires(snapshot_expiration)
-        .timestamp_version(timestamp_version)
+        .timestamp_version(NonZeroU64::new(1238).unwrap())
         .timestamp_expires(timestamp_expiration);
     let signed_repo = editor.sign(targets_key).await.unwrap();
 
@@ -886,3 +1406,329 @@ async fn update_targets_flow() {
         &b"Updated file1.txt"[..]
     );
 }
+
+#[cfg(unix)]
+#[tokio::test]
+/// Targets with identical contents are copied once and hard-linked thereafter
+async fn copy_targets_deduplicated() {
+    use std::os::unix::fs::MetadataExt;
+
+    let indir = TempDir::new().unwrap();
+    for (name, contents) in [("a.txt", "same"), ("b.txt", "same"), ("c.txt", "other")] {
+        tokio::fs::write(indir.path().join(name), contents)
+            .await
+            .unwrap();
+    }
+    let mut editor = test_repo_editor().await;
+    editor
+        .add_target_paths(
+            ["a.txt", "b.txt", "c.txt"]
+                .iter()
+                .map(|name| indir.path().join(name))
+                .collect(),
+        )
+        .await
+        .unwrap();
+    let key_source: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource { path: key_path() })];
+    let signed_repo = editor.sign(key_source).await.unwrap();
+
+    let outdir = TempDir::new().unwrap();
+    signed_repo
+        .copy_targets_deduplicated(indir.path(), outdir.path(), PathExists::Skip)
+        .await
+        .unwrap();
+
+    // simple-rsa's root.json uses consistent snapshots, so each file is prefixed with its sha256.
+    let mut inodes = HashMap::new();
+    let mut entries = tokio::fs::read_dir(outdir.path()).await.unwrap();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        let name = entry.file_name().into_string().unwrap();
+        let suffix = name.split_once('.').unwrap().1.to_owned();
+        inodes.insert(suffix, entry.metadata().await.unwrap().ino());
+    }
+    assert_eq!(inodes.len(), 3);
+    assert_eq!(inodes["a.txt"], inodes["b.txt"]);
+    assert_ne!(inodes["a.txt"], inodes["c.txt"]);
+}
+
+/// Custom target metadata set through the editor is signed and loaded, and survives re-adding the
+/// target from its file
+#[tokio::test]
+async fn custom_target_metadata() {
+    let file1 = TargetName::new("file1.txt").unwrap();
+    let file2 = TargetName::new("file2.txt").unwrap();
+    let file3 = TargetName::new("file3.txt").unwrap();
+    let file1_target = Target::from_path(targets_path().join("file1.txt"))
+        .await
+        .unwrap();
+    let built = Target::builder(file1_target.length, file1_target.hashes.clone())
+        .custom("version", "1.2.3")
+        .custom("requires", serde_json::json!(["file3.txt"]))
+        .build();
+    assert_eq!(built.length, file1_target.length);
+    assert_eq!(built.custom["version"], "1.2.3");
+    assert_eq!(built.custom["requires"], serde_json::json!(["file3.txt"]));
+
+    let mut editor = test_repo_editor().await;
+    editor.add_target(file1.clone(), built).unwrap();
+    editor
+        .add_target_with_custom(
+            targets_path().join("file2.txt"),
+            HashMap::from([("version".to_owned(), "2.0.0".into())]),
+        )
+        .await
+        .unwrap();
+    editor
+        .set_target_custom(
+            &file3,
+            HashMap::from([("version".to_owned(), "3.0.0".into())]),
+        )
+        .unwrap();
+    // Adding a target again from its file keeps the custom metadata already listed for it.
+    editor
+        .add_target_path(targets_path().join("file3.txt"))
+        .await
+        .unwrap();
+    assert!(editor
+        .set_target_custom(&TargetName::new("missing.txt").unwrap(), HashMap::new())
+        .is_err());
+
+    let key_source: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource { path: key_path() })];
+    let signed_repo = editor.sign(key_source).await.unwrap();
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_dir = repo_dir.path().join("metadata");
+    let targets_dir = repo_dir.path().join("targets");
+    signed_repo.write(&metadata_dir).await.unwrap();
+    signed_repo
+        .link_targets(targets_path(), &targets_dir, PathExists::Skip)
+        .await
+        .unwrap();
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_path()).await.unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(&targets_dir),
+    )
+    .load()
+    .await
+    .unwrap();
+
+    let targets = &repo.targets().signed.targets;
+    assert_eq!(targets[&file1].custom["version"], "1.2.3");
+    assert_eq!(targets[&file2].custom["version"], "2.0.0");
+    assert_eq!(targets[&file3].custom["version"], "3.0.0");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+/// Targets are saved with the mode listed in their custom metadata only when the loader is asked
+/// to apply it
+async fn save_target_applies_listed_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut target = Target::from_path(targets_path().join("file1.txt"))
+        .await
+        .unwrap();
+    target.set_mode(0o4755).unwrap();
+    assert_eq!(target.mode().unwrap(), Some(0o4755));
+    let mut editor = test_repo_editor().await;
+    editor.add_target("file1.txt", target).unwrap();
+    let key_source: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource { path: key_path() })];
+    let signed_repo = editor.sign(key_source).await.unwrap();
+
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_dir = repo_dir.path().join("metadata");
+    let targets_dir = repo_dir.path().join("targets");
+    signed_repo.write(&metadata_dir).await.unwrap();
+    signed_repo
+        .copy_targets(targets_path(), &targets_dir, PathExists::Skip)
+        .await
+        .unwrap();
+
+    let file1 = TargetName::new("file1.txt").unwrap();
+    let file3 = TargetName::new("file3.txt").unwrap();
+    let root = tokio::fs::read(root_path()).await.unwrap();
+    for (apply, expected) in [(false, 0o600), (true, 0o755)] {
+        let repo = RepositoryLoader::new(&root, dir_url(&metadata_dir), dir_url(&targets_dir))
+            .apply_target_modes(apply)
+            .load()
+            .await
+            .unwrap();
+        let outdir = TempDir::new().unwrap();
+        for name in [&file1, &file3] {
+            repo.save_target(name, outdir.path(), Prefix::None)
+                .await
+                .unwrap();
+        }
+        let mode = |name: &str| {
+            std::fs::metadata(outdir.path().join(name))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777
+        };
+        // The setuid bit is never applied.
+        assert_eq!(mode("file1.txt"), expected);
+        // A target without a listed mode keeps the default.
+        assert_eq!(mode("file3.txt"), 0o600);
+    }
+}
+
+/// A repository editor that delegates "A" from targets to `role1_key`, and also trusts
+/// `role2_key` for "A", with the targets role still in `targets_editor`.
+async fn rekey_editor() -> RepositoryEditor {
+    let role1_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource {
+        path: targets_key_path(),
+    })];
+    let role2_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource {
+        path: targets_key_path1(),
+    })];
+    let mut editor = test_repo_editor().await;
+    editor
+        .delegate_role(
+            "A",
+            role1_key,
+            PathSet::Paths(vec![PathPattern::new("*.txt").unwrap()]),
+            NonZeroU64::new(1).unwrap(),
+            Utc::now().checked_add_signed(days(21)).unwrap(),
+            NonZeroU64::new(1).unwrap(),
+        )
+        .await
+        .unwrap()
+        .add_key(key_hash_map(role2_key).await, Some("A"))
+        .unwrap();
+    editor
+}
+
+#[tokio::test]
+/// Keys and roles can be removed through `RepositoryEditor`, and a role that is no longer signed
+/// by a threshold of its keys must be re-signed before the repository can be signed
+async fn remove_key_and_role() {
+    let targets_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource { path: key_path() })];
+    let role2_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource {
+        path: targets_key_path1(),
+    })];
+    let role1_keyid = key_hash_map(&[Box::new(LocalKeySource {
+        path: targets_key_path(),
+    })])
+    .await
+    .into_keys()
+    .next()
+    .unwrap();
+    let role2_keyid = key_hash_map(role2_key).await.into_keys().next().unwrap();
+
+    // "A" is only signed by the key being removed.
+    let mut editor = rekey_editor().await;
+    editor.remove_key(&role1_keyid, Some("A")).unwrap();
+    let problems = editor.validate(targets_key).await;
+    assert!(
+        matches!(&problems[..], [tough::error::Error::ResignRequired { role, .. }] if role == "A"),
+        "{:?}",
+        problems
+    );
+    let Err(err) = editor.sign(targets_key).await else {
+        panic!("signed a role that lost its signing key");
+    };
+    assert!(
+        matches!(&err, tough::error::Error::ResignRequired { role, .. } if role == "A"),
+        "{}",
+        err
+    );
+
+    // Re-signing "A" with its remaining key satisfies its threshold.
+    let mut editor = rekey_editor().await;
+    editor
+        .remove_key(&role1_keyid, Some("A"))
+        .unwrap()
+        .sign_targets_editor(targets_key)
+        .await
+        .unwrap()
+        .change_delegated_targets("A")
+        .unwrap()
+        .targets_version(NonZeroU64::new(2).unwrap())
+        .unwrap()
+        .targets_expires(Utc::now().checked_add_signed(days(21)).unwrap())
+        .unwrap()
+        .sign_targets_editor(role2_key)
+        .await
+        .unwrap();
+    let repodir = TempDir::new().unwrap();
+    let metadata_dir = repodir.path().join("metadata");
+    editor
+        .sign(targets_key)
+        .await
+        .unwrap()
+        .write(&metadata_dir)
+        .await
+        .unwrap();
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_path()).await.unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(targets_path()),
+    )
+    .load()
+    .await
+    .unwrap();
+    assert_eq!(repo.delegated_role("A").unwrap().keyids, [role2_keyid]);
+
+    // A removed role doesn't need re-signing.
+    let mut editor = rekey_editor().await;
+    editor
+        .remove_key(&role1_keyid, Some("A"))
+        .unwrap()
+        .remove_role("A", false)
+        .unwrap();
+    let repodir = TempDir::new().unwrap();
+    let metadata_dir = repodir.path().join("metadata");
+    editor
+        .sign(targets_key)
+        .await
+        .unwrap()
+        .write(&metadata_dir)
+        .await
+        .unwrap();
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_path()).await.unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(targets_path()),
+    )
+    .load()
+    .await
+    .unwrap();
+    assert!(repo.delegated_role("A").is_none());
+}
+
+#[tokio::test]
+/// `validate()` reports every problem that would fail `sign()`, and none for an editor that signs
+async fn validate_reports_every_problem() {
+    let targets_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource { path: key_path() })];
+    let wrong_key: &[Box<dyn KeySource>] = &[Box::new(LocalKeySource {
+        path: targets_key_path(),
+    })];
+
+    let editor = RepositoryEditor::new(root_path()).await.unwrap();
+    let problems: Vec<String> = editor
+        .validate(wrong_key)
+        .await
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            "Missing 'targets version' when building repo from RepositoryEditor",
+            "Missing 'targets expiration' when building repo from RepositoryEditor",
+            "Role 'targets' needs 1 signatures, but only 0 of the given keys can sign it",
+            "Missing 'snapshot version' when building repo from RepositoryEditor",
+            "Missing 'snapshot expiration' when building repo from RepositoryEditor",
+            "Missing 'timestamp version' when building repo from RepositoryEditor",
+            "Missing 'timestamp expiration' when building repo from RepositoryEditor",
+            "Role 'snapshot' needs 1 signatures, but only 0 of the given keys can sign it",
+            "Role 'timestamp' needs 1 signatures, but only 0 of the given keys can sign it",
+        ]
+    );
+
+    let editor = test_repo_editor().await;
+    let problems = editor.validate(targets_key).await;
+    assert!(problems.is_empty(), "{:?}", problems);
+    editor.sign(targets_key).await.unwrap();
+}
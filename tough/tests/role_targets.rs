@@ -0,0 +1,130 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use chrono::Utc;
+use std::num::NonZeroU64;
+use tempfile::TempDir;
+use test_utils::{
+    days, dir_url, sign_and_write, simple_rsa_editor, simple_rsa_root, snakeoil_keys, test_data,
+};
+use tough::error::Error;
+use tough::schema::{PathPattern, PathSet, Target};
+use tough::{Repository, RepositoryLoader, ResolutionOutcome, TargetName};
+
+/// Builds a repository in which the top-level targets role delegates `team/*` to `team`, which
+/// delegates `team/sub/*` to `team-sub`.
+async fn nested_repo(dir: &TempDir) -> Repository {
+    let keys = snakeoil_keys();
+    let one = NonZeroU64::new(1).unwrap();
+    let expires = Utc::now() + days(7);
+    let target = Target::from_path(test_data().join("targets").join("file4.txt"))
+        .await
+        .unwrap();
+    let name = |name: &str| TargetName::new(name).unwrap();
+    let paths = |pattern: &str| PathSet::Paths(vec![PathPattern::new(pattern).unwrap()]);
+
+    let mut editor = simple_rsa_editor(1).await;
+    editor
+        .add_target(name("top.txt"), target.clone())
+        .unwrap()
+        .delegate_role("team", &keys, paths("team/*"), one, expires, one)
+        .await
+        .unwrap()
+        .sign_targets_editor(&keys)
+        .await
+        .unwrap()
+        .change_delegated_targets("team")
+        .unwrap()
+        .add_target(name("team/a.txt"), target.clone())
+        .unwrap()
+        .delegate_role("team-sub", &keys, paths("team/sub/*"), one, expires, one)
+        .await
+        .unwrap()
+        .targets_version(one)
+        .unwrap()
+        .targets_expires(expires)
+        .unwrap()
+        .sign_targets_editor(&keys)
+        .await
+        .unwrap()
+        .change_delegated_targets("team-sub")
+        .unwrap()
+        .add_target(name("team/sub/b.txt"), target)
+        .unwrap()
+        .targets_version(one)
+        .unwrap()
+        .targets_expires(expires)
+        .unwrap()
+        .sign_targets_editor(&keys)
+        .await
+        .unwrap();
+    let metadata_dir = dir.path().join("metadata");
+    sign_and_write(editor, &metadata_dir).await;
+
+    RepositoryLoader::new(
+        &std::fs::read(simple_rsa_root()).unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(dir.path().join("targets")),
+    )
+    .load()
+    .await
+    .unwrap()
+}
+
+fn names(targets: &[(&TargetName, &Target)]) -> Vec<String> {
+    targets
+        .iter()
+        .map(|(name, _)| name.raw().to_owned())
+        .collect()
+}
+
+/// A role's own targets, or those of the roles it delegates to as well, can be listed.
+#[tokio::test]
+async fn role_targets_nested() {
+    let dir = TempDir::new().unwrap();
+    let repo = nested_repo(&dir).await;
+
+    assert_eq!(names(&repo.role_targets("targets").unwrap()), ["top.txt"]);
+    assert_eq!(names(&repo.role_targets("team").unwrap()), ["team/a.txt"]);
+    assert_eq!(
+        names(&repo.role_targets("team-sub").unwrap()),
+        ["team/sub/b.txt"]
+    );
+    assert_eq!(
+        names(&repo.role_targets_recursive("team").unwrap()),
+        ["team/a.txt", "team/sub/b.txt"]
+    );
+    assert_eq!(
+        names(&repo.role_targets_recursive("targets").unwrap()),
+        ["team/a.txt", "team/sub/b.txt", "top.txt"]
+    );
+
+    let err = repo.role_targets_recursive("no-such-role").unwrap_err();
+    assert!(matches!(err, Error::TargetsNotFound { .. }), "{}", err);
+}
+
+/// Resolving a target reports the role that lists it, or why none does.
+#[tokio::test]
+async fn resolve_target_nested() {
+    let dir = TempDir::new().unwrap();
+    let repo = nested_repo(&dir).await;
+    let resolve = |name: &str| repo.resolve_target(&TargetName::new(name).unwrap()).outcome;
+
+    assert!(matches!(
+        resolve("team/sub/b.txt"),
+        ResolutionOutcome::Found { role, .. } if role == "team-sub"
+    ));
+    assert_eq!(resolve("other/c.txt"), ResolutionOutcome::NotDelegated);
+    assert_eq!(
+        resolve("team/sub/missing.txt"),
+        ResolutionOutcome::NotListed {
+            roles: vec![
+                "targets".to_owned(),
+                "team".to_owned(),
+                "team-sub".to_owned()
+            ]
+        }
+    );
+}
@@ -0,0 +1,77 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use test_utils::{dir_url, test_data};
+use tough::error::Error;
+use tough::schema::RoleType;
+use tough::{RepositoryLoader, RootUpdate};
+
+/// With root updates disabled, the trusted root isn't replaced by the newer root the repository
+/// serves, so the load is held to the trusted root, which here has expired.
+#[tokio::test]
+async fn disabled_keeps_trusted_root() {
+    let base = test_data().join("rotated-root");
+    let root = tokio::fs::read(base.join("1.root.json")).await.unwrap();
+
+    let repo = RepositoryLoader::new(&root, dir_url(&base), dir_url(base.join("targets")))
+        .root_update(RootUpdate::Enabled)
+        .load()
+        .await
+        .unwrap();
+    assert_eq!(u64::from(repo.root().signed.version), 2);
+
+    let err = RepositoryLoader::new(&root, dir_url(&base), dir_url(base.join("targets")))
+        .root_update(RootUpdate::Disabled)
+        .load()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            Error::ExpiredMetadata {
+                role: RoleType::Root,
+                version: 1,
+                ..
+            }
+        ),
+        "{}",
+        err
+    );
+}
+
+/// With root updates disabled, a trusted root that is already current loads as usual.
+#[tokio::test]
+async fn disabled_loads_current_root() {
+    let base = test_data().join("rotated-root");
+    let root = tokio::fs::read(base.join("2.root.json")).await.unwrap();
+
+    let repo = RepositoryLoader::new(&root, dir_url(&base), dir_url(base.join("targets")))
+        .root_update(RootUpdate::Disabled)
+        .load()
+        .await
+        .unwrap();
+    assert_eq!(u64::from(repo.root().signed.version), 2);
+}
+
+/// With root updates disabled, the trusted root must still be signed by its own keys.
+#[tokio::test]
+async fn disabled_verifies_trusted_root() {
+    let base = test_data().join("rotated-root");
+    let mut root: serde_json::Value =
+        serde_json::from_slice(&tokio::fs::read(base.join("2.root.json")).await.unwrap()).unwrap();
+    root["signed"]["expires"] = "2999-01-01T00:00:00Z".into();
+    let root = serde_json::to_vec(&root).unwrap();
+
+    let err = RepositoryLoader::new(&root, dir_url(&base), dir_url(base.join("targets")))
+        .root_update(RootUpdate::Disabled)
+        .load()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, Error::VerifyTrustedMetadata { .. }),
+        "{}",
+        err
+    );
+}
@@ -4,7 +4,9 @@
 mod test_utils;
 
 use test_utils::{dir_url, test_data};
-use tough::RepositoryLoader;
+use tough::error::Error;
+use tough::schema::{Root, Signed};
+use tough::{RepositoryLoader, RootUpdateFn};
 
 #[tokio::test]
 async fn rotated_root() {
@@ -21,3 +23,42 @@ async fn rotated_root() {
 
     assert_eq!(u64::from(repo.root().signed.version), 2);
 }
+
+/// A root update that the client's policy rejects fails the load, and the policy sees the root
+/// being replaced and its replacement.
+#[tokio::test]
+async fn rotated_root_rejected_by_policy() {
+    let base = test_data().join("rotated-root");
+    let root = tokio::fs::read(base.join("1.root.json")).await.unwrap();
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = std::sync::Arc::clone(&seen);
+    let repo = RepositoryLoader::new(&root, dir_url(&base), dir_url(base.join("targets")))
+        .root_update_policy(RootUpdateFn(
+            move |old: &Signed<Root>, new: &Signed<Root>| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((old.signed.version.get(), new.signed.version.get()));
+                Ok(())
+            },
+        ))
+        .load()
+        .await
+        .unwrap();
+    assert_eq!(u64::from(repo.root().signed.version), 2);
+    assert_eq!(*seen.lock().unwrap(), [(1, 2)]);
+
+    let err = RepositoryLoader::new(&root, dir_url(&base), dir_url(base.join("targets")))
+        .root_update_policy(RootUpdateFn(|_: &_, _: &_| {
+            Err("root updates are frozen".into())
+        }))
+        .load()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, Error::RootUpdateRejected { version, .. } if version.get() == 2),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,91 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::{
+    DefaultTransport, Repository, RepositoryLoader, TargetCache, TargetName, Transport,
+    TransportError,
+};
+use url::Url;
+
+/// A transport that serves files from disk and counts the targets it fetches.
+#[derive(Debug, Clone, Default)]
+struct CountingTransport {
+    target_fetches: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl Transport for CountingTransport {
+    async fn fetch(
+        &self,
+        url: Url,
+    ) -> Result<
+        std::pin::Pin<
+            Box<dyn futures_core::Stream<Item = Result<bytes::Bytes, TransportError>> + Send>,
+        >,
+        TransportError,
+    > {
+        if url.path().contains("/targets/") {
+            self.target_fetches.fetch_add(1, Ordering::SeqCst);
+        }
+        DefaultTransport::new().fetch(url).await
+    }
+}
+
+async fn load(transport: CountingTransport, cache: TargetCache) -> Repository {
+    let base = test_data().join("tuf-reference-impl");
+    RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .transport(transport)
+    .target_cache(cache)
+    .load()
+    .await
+    .unwrap()
+}
+
+/// Test that a target read to the end is served from the cache afterwards, including by a later
+/// load that shares the cache.
+#[tokio::test]
+async fn repeated_reads_hit_cache() {
+    let transport = CountingTransport::default();
+    let cache = TargetCache::new(1024);
+    let file1 = TargetName::new("file1.txt").unwrap();
+
+    let repo = load(transport.clone(), cache.clone()).await;
+    for _ in 0..3 {
+        assert_eq!(
+            read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await,
+            &b"This is an example target file."[..]
+        );
+    }
+    assert_eq!(transport.target_fetches.load(Ordering::SeqCst), 1);
+    assert_eq!(cache.len(), 1);
+
+    let repo = load(transport.clone(), cache.clone()).await;
+    read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await;
+    assert_eq!(transport.target_fetches.load(Ordering::SeqCst), 1);
+}
+
+/// Test that a target larger than the cache is fetched every time.
+#[tokio::test]
+async fn oversized_target_not_cached() {
+    let transport = CountingTransport::default();
+    let cache = TargetCache::new(8);
+    let file1 = TargetName::new("file1.txt").unwrap();
+
+    let repo = load(transport.clone(), cache.clone()).await;
+    for _ in 0..2 {
+        read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await;
+    }
+    assert_eq!(transport.target_fetches.load(Ordering::SeqCst), 2);
+    assert!(cache.is_empty());
+}
@@ -51,6 +51,7 @@ async fn create_root(root_path: &Path, consistent_snapshot: bool) -> Vec<Box<dyn
                 RoleType::Timestamp => empty_keys,
                 // RoleType::DelegatedTargets => empty_keys.clone(),
             },
+            unrecognized_roles: HashMap::new(),
             _extra: HashMap::new(),
         },
         signatures: Vec::new(),
@@ -5,15 +5,19 @@
 // cause compiler warnings for unused code, so we suppress them.
 #![allow(unused)]
 
-use chrono::TimeDelta;
+use chrono::{TimeDelta, Utc};
 use futures::TryStreamExt;
 use futures_core::Stream;
 use std::io::Read;
+use std::num::NonZeroU64;
 use std::path::{Path, PathBuf};
+use tough::editor::signed::SignedRepository;
+use tough::editor::RepositoryEditor;
+use tough::key_source::{KeySource, LocalKeySource};
 use tough::IntoVec;
 use url::Url;
 
-/// Utilities for tests. Not every test module uses every function, so we suppress unused warnings.
+// Utilities for tests. Not every test module uses every function, so we suppress unused warnings.
 
 pub const DATA_1: &str = "123\n456\n789\n0\n";
 pub const DATA_2: &str = "abc\ndef\nhij\nk\n";
@@ -43,3 +47,39 @@ where
 pub fn days(value: i64) -> TimeDelta {
     TimeDelta::try_days(value).unwrap()
 }
+
+/// Returns the path to the simple-rsa root, which lists snakeoil.pem for every role
+pub fn simple_rsa_root() -> PathBuf {
+    test_data().join("simple-rsa").join("root.json")
+}
+
+/// Returns snakeoil.pem as the only signing key
+pub fn snakeoil_keys() -> Vec<Box<dyn KeySource>> {
+    vec![Box::new(LocalKeySource {
+        path: test_data().join("snakeoil.pem"),
+    })]
+}
+
+/// Returns an editor for the simple-rsa root with every top-level role at `version`, and with
+/// targets, snapshot and timestamp expiring in 13, 21 and 3 days
+pub async fn simple_rsa_editor(version: u64) -> RepositoryEditor {
+    let version = NonZeroU64::new(version).unwrap();
+    let mut editor = RepositoryEditor::new(simple_rsa_root()).await.unwrap();
+    editor
+        .targets_expires(Utc::now() + days(13))
+        .unwrap()
+        .targets_version(version)
+        .unwrap()
+        .snapshot_expires(Utc::now() + days(21))
+        .snapshot_version(version)
+        .timestamp_expires(Utc::now() + days(3))
+        .timestamp_version(version);
+    editor
+}
+
+/// Signs `editor` with snakeoil.pem and writes its metadata to `metadata_dir`
+pub async fn sign_and_write(editor: RepositoryEditor, metadata_dir: &Path) -> SignedRepository {
+    let signed = editor.sign(&snakeoil_keys()).await.unwrap();
+    signed.write(metadata_dir).await.unwrap();
+    signed
+}
@@ -0,0 +1,65 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use aws_lc_rs::rand::SystemRandom;
+use tempfile::TempDir;
+use test_utils::{dir_url, sign_and_write, simple_rsa_editor, simple_rsa_root, snakeoil_keys};
+use tough::editor::signed::SignedRole;
+use tough::error::Error;
+use tough::schema::{KeyHolder, Root, Signed, Timestamp};
+use tough::{RepositoryLoader, TimestampMetaPolicy};
+
+/// A timestamp.json that also describes targets.json is refused unless the loader tolerates it.
+#[tokio::test]
+async fn timestamp_describing_other_files() {
+    let root_path = simple_rsa_root();
+    let keys = snakeoil_keys();
+    let repo_dir = TempDir::new().unwrap();
+    let metadata_dir = repo_dir.path().join("metadata");
+    sign_and_write(simple_rsa_editor(1).await, &metadata_dir).await;
+
+    // Re-sign the timestamp with a listing of targets.json alongside snapshot.json.
+    let root: Signed<Root> = serde_json::from_slice(&std::fs::read(&root_path).unwrap()).unwrap();
+    let mut timestamp: Signed<Timestamp> =
+        serde_json::from_slice(&std::fs::read(metadata_dir.join("timestamp.json")).unwrap())
+            .unwrap();
+    let snapshot_meta = timestamp.signed.meta["snapshot.json"].clone();
+    timestamp
+        .signed
+        .meta
+        .insert("targets.json".to_owned(), snapshot_meta);
+    assert_eq!(timestamp.signed.unexpected_meta(), vec!["targets.json"]);
+    SignedRole::new(
+        timestamp.signed,
+        &KeyHolder::Root(root.signed),
+        &keys,
+        &SystemRandom::new(),
+    )
+    .await
+    .unwrap()
+    .write(&metadata_dir, false)
+    .await
+    .unwrap();
+
+    let root_bytes = std::fs::read(&root_path).unwrap();
+    let loader = || {
+        RepositoryLoader::new(
+            &root_bytes,
+            dir_url(&metadata_dir),
+            dir_url(repo_dir.path().join("targets")),
+        )
+    };
+    let err = loader().load().await.unwrap_err();
+    assert!(
+        matches!(err, Error::TimestampMetaUnexpected { ref files, .. } if files == "targets.json"),
+        "{}",
+        err
+    );
+    loader()
+        .timestamp_meta_policy(TimestampMetaPolicy::Warn)
+        .load()
+        .await
+        .unwrap();
+}
@@ -2,7 +2,7 @@ use std::str::FromStr;
 use tempfile::TempDir;
 use test_utils::read_to_end;
 use tokio::fs;
-use tough::{DefaultTransport, Transport, TransportErrorKind};
+use tough::{DefaultTransport, IntoVec, Transport, TransportErrorKind};
 use url::Url;
 
 mod test_utils;
@@ -47,3 +47,136 @@ async fn default_transport_file() {
     let contents = String::from_utf8_lossy(&temp_vec);
     assert_eq!(contents, "123123987");
 }
+
+#[tokio::test]
+async fn default_transport_file_range() {
+    let dir = TempDir::new().unwrap();
+    let filepath = dir.path().join("file.txt");
+    fs::write(&filepath, "123123987").await.unwrap();
+    let transport = DefaultTransport::new();
+    let url = Url::from_file_path(filepath).unwrap();
+    let read = transport.fetch_range(url.clone(), 6).await.unwrap();
+    assert_eq!(read_to_end(read).await, &b"987"[..]);
+    let read = transport.fetch_range(url, 9).await.unwrap();
+    assert!(read_to_end(read).await.is_empty());
+}
+
+/// A target download picks up where the bytes already received end, and is only accepted if they
+/// and the rest of the target together match its hash.
+#[tokio::test]
+async fn resume_target() {
+    let base = test_utils::test_data().join("tuf-reference-impl");
+    let root = fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    let repo = tough::RepositoryLoader::new(
+        &root,
+        test_utils::dir_url(base.join("metadata")),
+        test_utils::dir_url(base.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    let name = tough::TargetName::new("file1.txt").unwrap();
+    let contents = b"This is an example target file.";
+
+    for offset in [0, 8, contents.len()] {
+        let rest = repo
+            .resume_target(&name, &contents[..offset])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(read_to_end(rest).await, &contents[offset..]);
+    }
+
+    let rest = repo
+        .resume_target(&name, &b"That is a"[..])
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(rest.into_vec().await.is_err());
+
+    let too_long = [&contents[..], b"!"].concat();
+    assert!(repo
+        .resume_target(&name, too_long.as_slice())
+        .await
+        .is_err());
+}
+
+/// A transport that serves files from disk, but never finishes fetching timestamp.json.
+#[derive(Debug, Clone, Copy)]
+struct StallingTimestampTransport;
+
+#[async_trait::async_trait]
+impl Transport for StallingTimestampTransport {
+    async fn fetch(
+        &self,
+        url: Url,
+    ) -> Result<
+        std::pin::Pin<
+            Box<
+                dyn futures_core::Stream<Item = Result<bytes::Bytes, tough::TransportError>> + Send,
+            >,
+        >,
+        tough::TransportError,
+    > {
+        if url.path().ends_with("/timestamp.json") {
+            futures::future::pending::<()>().await;
+        }
+        DefaultTransport::new().fetch(url).await
+    }
+}
+
+/// A role that never finishes loading is cut off by either deadline.
+#[tokio::test]
+async fn load_deadlines() {
+    let base = test_utils::test_data().join("tuf-reference-impl");
+    let root = fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    let loader = || {
+        tough::RepositoryLoader::new(
+            &root,
+            test_utils::dir_url(base.join("metadata")),
+            test_utils::dir_url(base.join("targets")),
+        )
+        .transport(StallingTimestampTransport)
+    };
+
+    // Long enough that loading root never hits it, even on a busy machine
+    let err = loader()
+        .role_timeout(std::time::Duration::from_secs(2))
+        .load()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            tough::error::Error::LoadTimeout {
+                role: tough::schema::RoleType::Timestamp,
+                scope: "per-role",
+                ..
+            }
+        ),
+        "{}",
+        err
+    );
+
+    let err = loader()
+        .role_timeout(std::time::Duration::from_secs(60))
+        .load_timeout(std::time::Duration::from_millis(100))
+        .load()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            tough::error::Error::LoadTimeout {
+                scope: "per-load",
+                ..
+            }
+        ),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,51 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::error::Error;
+use tough::uptane::UptaneRepositories;
+use tough::{RepositoryLoader, TargetName};
+
+async fn reference_impl_pair() -> UptaneRepositories {
+    let base = test_data().join("tuf-reference-impl");
+    let root = tokio::fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    let loader = || {
+        RepositoryLoader::new(
+            &root,
+            dir_url(base.join("metadata")),
+            dir_url(base.join("targets")),
+        )
+    };
+    UptaneRepositories::load(loader(), loader()).await.unwrap()
+}
+
+/// Test that a target both repositories agree on is fetched from the image repository.
+#[tokio::test]
+async fn consistent_target_is_read() {
+    let repos = reference_impl_pair().await;
+    let file1 = TargetName::new("file1.txt").unwrap();
+    assert_eq!(
+        read_to_end(repos.verify_target_consistency(&file1).await.unwrap()).await,
+        &b"This is an example target file."[..]
+    );
+}
+
+/// Test that a target the director doesn't list is refused.
+#[tokio::test]
+async fn target_missing_from_director() {
+    let repos = reference_impl_pair().await;
+    let missing = TargetName::new("missing.txt").unwrap();
+    let err = match repos.verify_target_consistency(&missing).await {
+        Ok(_) => panic!("expected missing.txt to be refused"),
+        Err(err) => err,
+    };
+    assert!(
+        matches!(err, Error::UptaneTargetMissing { ref repository, .. } if repository == "director"),
+        "{}",
+        err
+    );
+}
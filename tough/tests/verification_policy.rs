@@ -0,0 +1,103 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use test_utils::{dir_url, read_to_end, test_data};
+use tough::error::{Error, Result};
+use tough::{
+    HashAlgorithm, HashAlgorithms, Repository, RepositoryLoader, TargetName, VerificationPolicy,
+};
+
+async fn load_reference_impl(policy: VerificationPolicy) -> Result<Repository> {
+    let base = test_data().join("tuf-reference-impl");
+    let root = tokio::fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    RepositoryLoader::new(
+        &root,
+        dir_url(base.join("metadata")),
+        dir_url(base.join("targets")),
+    )
+    .verification_policy(policy)
+    .load()
+    .await
+}
+
+/// Test that listed sha512 hashes are checked when the policy asks for them.
+#[tokio::test]
+async fn sha512_checked_if_listed() {
+    let repo = load_reference_impl(VerificationPolicy::default().require_sha512_if_listed(true))
+        .await
+        .unwrap();
+    let file1 = TargetName::new("file1.txt").unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await,
+        &b"This is an example target file."[..]
+    );
+}
+
+/// Test that a minimum hash of sha512 rejects metadata listed only with sha256.
+#[tokio::test]
+async fn minimum_sha512_rejects_sha256_listing() {
+    let err =
+        load_reference_impl(VerificationPolicy::default().minimum_hash(HashAlgorithm::Sha512))
+            .await
+            .unwrap_err();
+    assert!(
+        matches!(err, Error::HashMissing { ref context, .. } if context == "snapshot.json"),
+        "{}",
+        err
+    );
+}
+
+/// Test that metadata listed without a length or hashes is rejected when the policy requires them.
+#[tokio::test]
+async fn metadata_listing_requirements() {
+    let err = load_reference_impl(VerificationPolicy::default().require_metadata_length(true))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, Error::MetadataLengthMissing { ref context, .. } if context == "targets.json"),
+        "{}",
+        err
+    );
+
+    let err = load_reference_impl(VerificationPolicy::default().require_metadata_hashes(true))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, Error::HashMissing { ref context, .. } if context == "targets.json"),
+        "{}",
+        err
+    );
+}
+
+/// Test that only hashes of the allowed algorithms are checked, and that a listing without one
+/// is rejected.
+#[tokio::test]
+async fn allowed_hashes() {
+    let err = load_reference_impl(
+        VerificationPolicy::default().allowed_hashes(HashAlgorithms::only(HashAlgorithm::Sha512)),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(err, Error::HashMissing { ref context, ref algorithm, .. } if context == "snapshot.json" && algorithm == "sha512"),
+        "{}",
+        err
+    );
+
+    let repo = load_reference_impl(
+        VerificationPolicy::default()
+            .allowed_hashes(HashAlgorithms::only(HashAlgorithm::Sha256))
+            .require_sha512_if_listed(true),
+    )
+    .await
+    .unwrap();
+    let file1 = TargetName::new("file1.txt").unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await,
+        &b"This is an example target file."[..]
+    );
+}
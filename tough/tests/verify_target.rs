@@ -0,0 +1,74 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::io::Cursor;
+use test_utils::{dir_url, test_data};
+use tough::{IntoVec, Repository, RepositoryLoader, TargetName};
+
+/// Loads the reference implementation's repository with a targets URL that has nothing in it, so
+/// that any attempt to fetch a target fails.
+async fn load() -> (Repository, tempfile::TempDir) {
+    let base = test_data().join("tuf-reference-impl");
+    let empty = tempfile::tempdir().unwrap();
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(base.join("metadata").join("1.root.json"))
+            .await
+            .unwrap(),
+        dir_url(base.join("metadata")),
+        dir_url(empty.path()),
+    )
+    .load()
+    .await
+    .unwrap();
+    (repo, empty)
+}
+
+/// Contents received out-of-band are checked against the listed length and hashes.
+#[tokio::test]
+async fn verify_target_stream() {
+    let (repo, _empty) = load().await;
+    let name = TargetName::new("file1.txt").unwrap();
+    let contents = std::fs::read(test_data().join("tuf-reference-impl/targets/file1.txt")).unwrap();
+
+    let verified = repo
+        .verify_target_stream(&name, Cursor::new(contents.clone()))
+        .await
+        .unwrap()
+        .unwrap()
+        .into_vec()
+        .await
+        .unwrap();
+    assert_eq!(verified, contents);
+
+    let mut tampered = contents.clone();
+    tampered[0] ^= 1;
+    let err = repo
+        .verify_target_stream(&name, Cursor::new(tampered))
+        .await
+        .unwrap()
+        .unwrap()
+        .into_vec()
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("file1.txt"), "{}", err);
+
+    let mut longer = contents.clone();
+    longer.push(b'\n');
+    assert!(repo
+        .verify_target_stream(&name, Cursor::new(longer))
+        .await
+        .unwrap()
+        .unwrap()
+        .into_vec()
+        .await
+        .is_err());
+
+    let unlisted = TargetName::new("file4.txt").unwrap();
+    assert!(repo
+        .verify_target_stream(&unlisted, Cursor::new(contents))
+        .await
+        .unwrap()
+        .is_none());
+}
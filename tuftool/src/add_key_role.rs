@@ -69,10 +69,9 @@ impl AddKeyArgs {
         for source in &self.new_keys {
             let key_source = parse_key_source(source)?;
             let key_pair = key_source
-                .as_sign()
+                .public_key()
                 .await
-                .context(error::KeyPairFromKeySourceSnafu)?
-                .tuf_key();
+                .context(error::KeyPairFromKeySourceSnafu)?;
             key_pairs.insert(
                 key_pair
                     .key_id()
@@ -3,6 +3,7 @@
 
 use crate::common::load_metadata_repo;
 use crate::datetime::parse_datetime;
+use crate::delegation_package::DelegationPackage;
 use crate::error::{self, Result};
 use crate::source::parse_key_source;
 use chrono::{DateTime, Utc};
@@ -17,8 +18,13 @@ use url::Url;
 #[derive(Debug, Parser)]
 pub(crate) struct AddRoleArgs {
     /// The role being delegated
-    #[arg(short, long = "delegated-role")]
-    delegatee: String,
+    #[arg(
+        short,
+        long = "delegated-role",
+        required_unless_present = "package",
+        conflicts_with = "package"
+    )]
+    delegatee: Option<String>,
 
     /// Expiration of new role file; can be in full RFC 3339 format, or something like 'in
     /// 7 days'
@@ -26,8 +32,13 @@ pub(crate) struct AddRoleArgs {
     expires: DateTime<Utc>,
 
     /// Incoming metadata
-    #[arg(short, long = "incoming-metadata")]
-    indir: Url,
+    #[arg(
+        short,
+        long = "incoming-metadata",
+        required_unless_present = "package",
+        conflicts_with = "package"
+    )]
+    indir: Option<Url>,
 
     /// Key files to sign with
     #[arg(short, long = "key", required = true)]
@@ -41,8 +52,13 @@ pub(crate) struct AddRoleArgs {
     #[arg(short, long)]
     outdir: PathBuf,
 
+    /// A delegation package written by `create-role --package`, as a path or URL. The role's
+    /// name, metadata, keys, paths and threshold are all taken from the package.
+    #[arg(long)]
+    package: Option<String>,
+
     /// The delegated paths
-    #[arg(short, long, conflicts_with = "path_hash_prefixes")]
+    #[arg(short, long, conflicts_with_all = ["path_hash_prefixes", "package"])]
     paths: Option<Vec<PathPattern>>,
 
     /// Path to root.json file for the repository
@@ -62,8 +78,13 @@ pub(crate) struct AddRoleArgs {
     snapshot_version: Option<NonZeroU64>,
 
     /// threshold of signatures to sign delegatee
-    #[arg(short, long)]
-    threshold: NonZeroU64,
+    #[arg(
+        short,
+        long,
+        required_unless_present = "package",
+        conflicts_with = "package"
+    )]
+    threshold: Option<NonZeroU64>,
 
     /// Expiration of timestamp.json file; can be in full RFC 3339 format, or something like 'in
     /// 7 days'
@@ -78,12 +99,16 @@ pub(crate) struct AddRoleArgs {
     version: NonZeroU64,
 
     /// The delegated paths hash prefixes
-    #[arg(short = 'x', long)]
+    #[arg(short = 'x', long, conflicts_with = "package")]
     path_hash_prefixes: Option<Vec<PathHashPrefix>>,
 }
 
 impl AddRoleArgs {
     pub(crate) async fn run(&self, role: &str) -> Result<()> {
+        let package = match &self.package {
+            Some(source) => Some(DelegationPackage::load(source).await?),
+            None => None,
+        };
         // load the repo
         let repository = load_metadata_repo(&self.root, self.metadata_base_url.clone()).await?;
         // if sign_all use Repository Editor to sign the entire repo if not use targets editor
@@ -94,6 +119,7 @@ impl AddRoleArgs {
                 RepositoryEditor::from_repo(&self.root, repository)
                     .await
                     .context(error::EditorFromRepoSnafu { path: &self.root })?,
+                package.as_ref(),
             )
             .await
         } else {
@@ -102,39 +128,45 @@ impl AddRoleArgs {
                 role,
                 TargetsEditor::from_repo(repository, role)
                     .context(error::EditorFromRepoSnafu { path: &self.root })?,
+                package.as_ref(),
             )
             .await
         }
     }
 
-    #[allow(clippy::option_if_let_else)]
     /// Adds a role to metadata using targets Editor
-    async fn add_role(&self, role: &str, mut editor: TargetsEditor) -> Result<()> {
-        let paths = if let Some(paths) = &self.paths {
-            PathSet::Paths(paths.clone())
-        } else if let Some(path_hash_prefixes) = &self.path_hash_prefixes {
-            PathSet::PathHashPrefixes(path_hash_prefixes.clone())
-        } else {
-            // Should warn that no paths are being delegated
-            PathSet::Paths(Vec::new())
-        };
-
+    async fn add_role(
+        &self,
+        role: &str,
+        mut editor: TargetsEditor,
+        package: Option<&DelegationPackage>,
+    ) -> Result<()> {
         let mut keys = Vec::new();
         for source in &self.keys {
             let key_source = parse_key_source(source)?;
             keys.push(key_source);
         }
 
+        let delegatee = if let Some(package) = package {
+            editor
+                .delegate_role(
+                    package.delegated_targets(),
+                    package.paths.clone(),
+                    package.keys.clone(),
+                    package.keys.keys().cloned().collect(),
+                    package.threshold,
+                )
+                .context(error::DelegationStructureSnafu)?;
+            package.name.clone()
+        } else {
+            let (delegatee, indir, threshold) = self.incoming()?;
+            editor
+                .add_role(&delegatee, indir.as_str(), self.paths(), threshold, None)
+                .await
+                .context(error::LoadMetadataSnafu)?;
+            delegatee
+        };
         let updated_role = editor
-            .add_role(
-                &self.delegatee,
-                self.indir.as_str(),
-                paths,
-                self.threshold,
-                None,
-            )
-            .await
-            .context(error::LoadMetadataSnafu)?
             .version(self.version)
             .expires(self.expires)
             .sign(&keys)
@@ -145,15 +177,19 @@ impl AddRoleArgs {
             .write(metadata_destination_out, false)
             .await
             .context(error::WriteRolesSnafu {
-                roles: [self.delegatee.clone(), role.to_string()].to_vec(),
+                roles: [delegatee, role.to_string()].to_vec(),
             })?;
 
         Ok(())
     }
 
-    #[allow(clippy::option_if_let_else)]
     /// Adds a role to metadata using repo Editor
-    async fn with_repo_editor(&self, role: &str, mut editor: RepositoryEditor) -> Result<()> {
+    async fn with_repo_editor(
+        &self,
+        role: &str,
+        mut editor: RepositoryEditor,
+        package: Option<&DelegationPackage>,
+    ) -> Result<()> {
         let mut keys = Vec::new();
         for source in &self.keys {
             let key_source = parse_key_source(source)?;
@@ -174,14 +210,6 @@ impl AddRoleArgs {
         let timestamp_expires = self.timestamp_expires.context(error::MissingSnafu {
             what: "timestamp expires".to_string(),
         })?;
-        let paths = if let Some(paths) = &self.paths {
-            PathSet::Paths(paths.clone())
-        } else if let Some(path_hash_prefixes) = &self.path_hash_prefixes {
-            PathSet::PathHashPrefixes(path_hash_prefixes.clone())
-        } else {
-            // Should warn that no paths are being delegated
-            PathSet::Paths(Vec::new())
-        };
         // Sign the top level targets (it's currently the one in targets_editor)
         editor
             .targets_version(self.version)
@@ -200,16 +228,26 @@ impl AddRoleArgs {
                 role: role.to_string(),
             })?;
         // Add the new role to the signing role
+        let delegatee = if let Some(package) = package {
+            editor
+                .delegate_signed_role(
+                    package.delegated_targets(),
+                    package.paths.clone(),
+                    package.keys.clone(),
+                    package.keys.keys().cloned().collect(),
+                    package.threshold,
+                )
+                .context(error::DelegationStructureSnafu)?;
+            package.name.clone()
+        } else {
+            let (delegatee, indir, threshold) = self.incoming()?;
+            editor
+                .add_role(&delegatee, indir.as_str(), self.paths(), threshold, None)
+                .await
+                .context(error::LoadMetadataSnafu)?;
+            delegatee
+        };
         editor
-            .add_role(
-                &self.delegatee,
-                self.indir.as_str(),
-                paths,
-                self.threshold,
-                None,
-            )
-            .await
-            .context(error::LoadMetadataSnafu)?
             .targets_version(self.version)
             .context(error::DelegationStructureSnafu)?
             .targets_expires(self.expires)
@@ -225,9 +263,36 @@ impl AddRoleArgs {
             .write(metadata_destination_out)
             .await
             .context(error::WriteRolesSnafu {
-                roles: [self.delegatee.clone(), role.to_string()].to_vec(),
+                roles: [delegatee, role.to_string()].to_vec(),
             })?;
 
         Ok(())
     }
+
+    /// The delegated role, incoming metadata URL and threshold, which must be given on the
+    /// command line when no package is used
+    fn incoming(&self) -> Result<(String, &Url, NonZeroU64)> {
+        let delegatee = self.delegatee.clone().context(error::MissingSnafu {
+            what: "delegated role".to_string(),
+        })?;
+        let indir = self.indir.as_ref().context(error::MissingSnafu {
+            what: "incoming metadata".to_string(),
+        })?;
+        let threshold = self.threshold.context(error::MissingSnafu {
+            what: "threshold".to_string(),
+        })?;
+        Ok((delegatee, indir, threshold))
+    }
+
+    #[allow(clippy::option_if_let_else)]
+    fn paths(&self) -> PathSet {
+        if let Some(paths) = &self.paths {
+            PathSet::Paths(paths.clone())
+        } else if let Some(path_hash_prefixes) = &self.path_hash_prefixes {
+            PathSet::PathHashPrefixes(path_hash_prefixes.clone())
+        } else {
+            // Should warn that no paths are being delegated
+            PathSet::Paths(Vec::new())
+        }
+    }
 }
@@ -0,0 +1,116 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::UNUSED_URL;
+use crate::datetime::parse_duration;
+use crate::error::{self, Result};
+use aws_lc_rs::digest::{digest, SHA256};
+use chrono::{TimeDelta, Utc};
+use clap::Parser;
+use snafu::ResultExt;
+use std::path::PathBuf;
+use tough::{EmbeddedRootCheck, RepositoryLoader, Staleness};
+use url::Url;
+
+/// Copy the chain of root.json files from a trusted root to a repository's current root, and
+/// print the root a product should embed as its trusted root
+#[derive(Debug, Parser)]
+pub(crate) struct BundleTrustArgs {
+    /// Path to the root.json file that products currently embed
+    #[arg(short, long)]
+    root: PathBuf,
+
+    /// TUF repository metadata base URL
+    #[arg(short, long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// Directory to copy the root.json chain to, as `N.root.json` files
+    #[arg(short, long)]
+    out: PathBuf,
+
+    /// Warn if the embedded root is more than this many versions behind the current root
+    #[arg(long, value_name = "N", default_value = "5")]
+    max_version_distance: u64,
+
+    /// Warn if the embedded root expires within this long, such as '90d'
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "90d")]
+    min_validity: TimeDelta,
+}
+
+impl BundleTrustArgs {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let embedded = tokio::fs::read(&self.root)
+            .await
+            .context(error::OpenRootSnafu { path: &self.root })?;
+
+        // Each root the load establishes trust through is written to the datastore.
+        let datastore = tempfile::tempdir().context(error::DirCreateSnafu {
+            path: std::env::temp_dir(),
+        })?;
+        let repository = RepositoryLoader::new(
+            &embedded,
+            self.metadata_base_url.clone(),
+            Url::parse(UNUSED_URL).with_context(|_| error::UrlParseSnafu {
+                url: UNUSED_URL.to_owned(),
+            })?,
+        )
+        .datastore(datastore.path())
+        .load()
+        .await
+        .context(error::RepoLoadSnafu)?;
+
+        let report = EmbeddedRootCheck {
+            max_version_distance: self.max_version_distance,
+            min_remaining_validity: self.min_validity,
+        }
+        .check(&embedded, &repository, Utc::now())
+        .context(error::MetadataSnafu)?;
+
+        tokio::fs::create_dir_all(&self.out)
+            .await
+            .context(error::DirCreateSnafu { path: &self.out })?;
+        let mut current = embedded;
+        for version in report.embedded_version..=report.current_version {
+            let name = format!("{version}.root.json");
+            if version > report.embedded_version {
+                let path = datastore.path().join(&name);
+                current = tokio::fs::read(&path)
+                    .await
+                    .context(error::FileOpenSnafu { path })?;
+            }
+            let path = self.out.join(&name);
+            tokio::fs::write(&path, &current)
+                .await
+                .context(error::FileWriteSnafu { path })?;
+        }
+
+        println!(
+            "Copied root.json versions {} through {} to {}",
+            report.embedded_version,
+            report.current_version,
+            self.out.display()
+        );
+        println!("Embed this root as the trusted root:");
+        println!(
+            "  path: {}",
+            self.out
+                .join(format!("{}.root.json", report.current_version))
+                .display()
+        );
+        println!("  length: {} bytes", current.len());
+        println!("  sha256: {}", hex::encode(digest(&SHA256, &current)));
+
+        for staleness in &report.staleness {
+            match staleness {
+                Staleness::VersionDistance { distance, max } => eprintln!(
+                    "Warning: the embedded root is {distance} versions behind the current root \
+                     (more than {max})"
+                ),
+                Staleness::Expiring { expires } => {
+                    eprintln!("Warning: the embedded root expires at {expires}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
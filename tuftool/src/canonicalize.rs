@@ -0,0 +1,67 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::error::{self, Result};
+use aws_lc_rs::digest::{digest, SHA256};
+use clap::Parser;
+use olpc_cjson::CanonicalFormatter;
+use serde::Serialize;
+use serde_json::Value;
+use snafu::{OptionExt, ResultExt};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Print the canonical JSON form of a metadata file, and its sha256 digest, for debugging
+/// signature mismatches and for external signing workflows
+///
+/// The output follows OLPC canonical JSON, which leaves control characters in strings (such as the
+/// newlines in PEM-encoded keys) unescaped, so it may not parse as strict JSON.
+#[derive(Debug, Parser)]
+pub(crate) struct CanonicalizeArgs {
+    /// Path to the metadata file
+    input: PathBuf,
+
+    /// Write the canonical JSON to this file instead of stdout
+    #[arg(short, long)]
+    outfile: Option<PathBuf>,
+
+    /// Only canonicalize the `signed` object, which is what role signatures cover
+    #[arg(long)]
+    signed: bool,
+}
+
+impl CanonicalizeArgs {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let path = &self.input;
+        let mut value: Value = crate::load_file(path).await?;
+        if self.signed {
+            value = value
+                .get_mut("signed")
+                .map(Value::take)
+                .context(error::MissingSnafu {
+                    what: format!("'signed' object in {}", path.display()),
+                })?;
+        }
+
+        let mut canonical = Vec::new();
+        let mut ser =
+            serde_json::Serializer::with_formatter(&mut canonical, CanonicalFormatter::new());
+        value
+            .serialize(&mut ser)
+            .context(error::FileWriteJsonSnafu { path })?;
+
+        match &self.outfile {
+            Some(outfile) => tokio::fs::write(outfile, &canonical)
+                .await
+                .context(error::FileWriteSnafu { path: outfile })?,
+            None => std::io::stdout()
+                .write_all(&canonical)
+                .context(error::FileWriteSnafu {
+                    path: PathBuf::from("<stdout>"),
+                })?,
+        }
+        // Keep the digest off stdout so the canonical bytes can be piped as-is.
+        eprintln!("sha256: {}", hex::encode(digest(&SHA256, &canonical)));
+        Ok(())
+    }
+}
@@ -1,26 +1,30 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::common::UNUSED_URL;
+use crate::common::{ExpiredRepoArgs, TransportArgs, UNUSED_URL};
 use crate::download_root::download_root;
 use crate::error::{self, Result};
 use clap::Parser;
 use snafu::ResultExt;
 use std::num::NonZeroU64;
-use std::path::PathBuf;
-use tough::{ExpirationEnforcement, RepositoryLoader};
+use std::path::{Path, PathBuf};
+use tough::{CacheLayout, RepositoryLoader};
 use url::Url;
 
 #[derive(Debug, Parser)]
+#[allow(clippy::struct_excessive_bools)]
 pub(crate) struct CloneArgs {
-    /// Allow repo download for expired metadata (unsafe)
-    #[arg(long)]
-    allow_expired_repo: bool,
+    #[command(flatten)]
+    expired_repo: ExpiredRepoArgs,
 
     /// Allow downloading the root.json file (unsafe)
     #[arg(long)]
     allow_root_download: bool,
 
+    /// Update an existing clone, only downloading targets that are missing or changed
+    #[arg(long, conflicts_with = "metadata_only")]
+    delta: bool,
+
     /// Output directory of metadata
     #[arg(long)]
     metadata_dir: PathBuf,
@@ -49,17 +53,15 @@ pub(crate) struct CloneArgs {
     #[arg(short, long = "targets-url", required_unless_present = "metadata_only")]
     targets_base_url: Option<Url>,
 
+    /// Remove files from the targets directory that the repository no longer lists
+    #[arg(long, requires = "delta")]
+    prune: bool,
+
     /// Remote root.json version number
     #[arg(short = 'v', long, default_value = "1")]
     root_version: NonZeroU64,
-}
-
-#[rustfmt::skip]
-fn expired_repo_warning() {
-    eprintln!("\
-=================================================================
-WARNING: repo metadata is expired, meaning the owner hasn't verified its contents lately and it could be unsafe!
-=================================================================");
+    #[command(flatten)]
+    transport: TransportArgs,
 }
 
 impl CloneArgs {
@@ -88,12 +90,15 @@ impl CloneArgs {
             .clone();
 
         // Load repository
-        let expiration_enforcement = if self.allow_expired_repo {
-            expired_repo_warning();
-            ExpirationEnforcement::Unsafe
-        } else {
-            ExpirationEnforcement::Safe
-        };
+        let metadata_dir = self.expired_repo.output_path(&self.metadata_dir);
+        let targets_dir = self
+            .targets_dir
+            .as_ref()
+            .map(|dir| self.expired_repo.output_path(dir));
+        let outputs: Vec<&Path> = std::iter::once(metadata_dir.as_path())
+            .chain(targets_dir.as_deref())
+            .collect();
+        let expiration_enforcement = self.expired_repo.expiration_enforcement(&outputs);
         let repository = RepositoryLoader::new(
             &tokio::fs::read(&root_path)
                 .await
@@ -102,46 +107,59 @@ impl CloneArgs {
             targets_base_url,
         )
         .expiration_enforcement(expiration_enforcement)
+        .transport(self.transport.transport())
         .load()
         .await
         .context(error::RepoLoadSnafu)?;
 
         // Clone the repository, downloading none, all, or a subset of targets
         if self.metadata_only {
-            println!("Cloning repository metadata to {:?}", self.metadata_dir);
+            println!("Cloning repository metadata to {}", metadata_dir.display());
             repository
-                .cache_metadata(&self.metadata_dir, true)
+                .cache_metadata(&metadata_dir, true)
                 .await
                 .context(error::CloneRepositorySnafu)?;
         } else {
             // Similar to `targets_base_url, structopt's guard rails won't let us have a
             // `targets_dir` that is None when the argument is required.  We only require the user
             // to supply a targets directory if they actually plan on downloading targets.
-            let targets_dir = self.targets_dir.as_ref().expect(
+            let targets_dir = targets_dir.as_ref().expect(
                 "Developer error: `targets_dir` is required unless downloading metadata only",
             );
 
             println!(
-                "Cloning repository:\n\tmetadata location: {:?}\n\ttargets location: {targets_dir:?}",
-                self.metadata_dir
+                "Cloning repository:\n\tmetadata location: {}\n\ttargets location: {}",
+                metadata_dir.display(),
+                targets_dir.display()
             );
-            if self.target_names.is_empty() {
-                repository
-                    .cache(&self.metadata_dir, targets_dir, None::<&[&str]>, true)
+            let target_names = if self.target_names.is_empty() {
+                None
+            } else {
+                Some(self.target_names.as_slice())
+            };
+            if self.delta {
+                let layout = CacheLayout::Directories {
+                    metadata_dir,
+                    targets_dir: targets_dir.clone(),
+                    root_chain: true,
+                };
+                let delta = repository
+                    .cache_delta(&layout, target_names, self.prune)
                     .await
                     .context(error::CloneRepositorySnafu)?;
+                println!(
+                    "Downloaded {} targets, kept {} unchanged targets, pruned {} files",
+                    delta.downloaded.len(),
+                    delta.unchanged.len(),
+                    delta.pruned.len()
+                );
             } else {
                 repository
-                    .cache(
-                        &self.metadata_dir,
-                        targets_dir,
-                        Some(self.target_names.as_slice()),
-                        true,
-                    )
+                    .cache(&metadata_dir, targets_dir, target_names, true)
                     .await
                     .context(error::CloneRepositorySnafu)?;
             }
-        };
+        }
 
         Ok(())
     }
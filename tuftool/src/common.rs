@@ -1,8 +1,17 @@
 /// This module is for code that is re-used by different `tuftool` subcommands.
+use crate::datetime::parse_duration;
 use crate::error::{self, Result};
-use snafu::ResultExt;
-use std::path::Path;
-use tough::{Repository, RepositoryLoader};
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::Args;
+use snafu::{OptionExt, ResultExt};
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tough::editor::signed::SignedRepository;
+use tough::schema::RoleType;
+use tough::{
+    DefaultTransport, ExpirationEnforcement, HttpTransportBuilder, Repository, RepositoryLoader,
+};
 use url::Url;
 
 /// Some commands only deal with metadata and never use a targets directory.
@@ -10,6 +19,180 @@ use url::Url;
 /// the targets URL.
 pub(crate) const UNUSED_URL: &str = "file:///unused/url";
 
+/// Output from a repository loaded with `--allow-expired-repo` goes to directories with this
+/// prefix, so it can't be mistaken for a trusted copy of the repository.
+const UNTRUSTED_PREFIX: &str = "UNTRUSTED-";
+
+/// Options for commands that can load a repository whose metadata has expired.
+#[derive(Debug, Args)]
+pub(crate) struct ExpiredRepoArgs {
+    /// Allow repo download for expired metadata (unsafe); requires `--confirm-expired-repo`.
+    /// Output is written to `UNTRUSTED-*` directories
+    #[arg(long, requires = "confirm_expired_repo")]
+    allow_expired_repo: bool,
+
+    /// Confirm that the output of `--allow-expired-repo` is not trusted
+    #[arg(long, requires = "allow_expired_repo")]
+    confirm_expired_repo: bool,
+}
+
+impl ExpiredRepoArgs {
+    /// Where to write output that would go to `path`. When loading an expired repository is
+    /// allowed, this is `path` with its final component prefixed by `UNTRUSTED-`.
+    pub(crate) fn output_path(&self, path: &Path) -> PathBuf {
+        if !self.allow_expired_repo {
+            return path.to_owned();
+        }
+        match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.starts_with(UNTRUSTED_PREFIX) => path.to_owned(),
+            Some(name) => path.with_file_name(format!("{UNTRUSTED_PREFIX}{name}")),
+            None => path.join(format!("{UNTRUSTED_PREFIX}repo")),
+        }
+    }
+
+    /// The expiration enforcement to load the repository with. When loading an expired
+    /// repository is allowed, this prints a warning banner naming the `outputs` that will be
+    /// written.
+    pub(crate) fn expiration_enforcement(&self, outputs: &[&Path]) -> ExpirationEnforcement {
+        if !self.allow_expired_repo {
+            return ExpirationEnforcement::Safe;
+        }
+        let outputs: Vec<String> = outputs
+            .iter()
+            .map(|path| format!("  {}", path.display()))
+            .collect();
+        #[rustfmt::skip]
+        eprintln!("\
+=================================================================
+WARNING: `--allow-expired-repo` was passed. The repo metadata may be expired, meaning the owner
+hasn't verified its contents lately, and trust in it is NOT established. Use the output only for
+testing or forensics! It is being written to:
+{}
+=================================================================",
+            outputs.join("\n"));
+        ExpirationEnforcement::Unsafe
+    }
+}
+
+/// Expirations relative to now, for commands that set the expiration of each top-level role.
+/// A role's own expiration argument, such as `--targets-expires`, takes precedence over these.
+#[derive(Debug, Args)]
+#[allow(clippy::struct_field_names)]
+pub(crate) struct ExpireInArgs {
+    /// Expire targets.json, snapshot.json and timestamp.json this long from now, such as '30d',
+    /// unless a role's own expiration is given
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    all_expire_in: Option<TimeDelta>,
+
+    /// Expire snapshot.json this long from now, such as '7d' or '12h'
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        conflicts_with = "snapshot_expires"
+    )]
+    snapshot_expire_in: Option<TimeDelta>,
+
+    /// Expire targets.json this long from now, such as '4w'
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        conflicts_with = "targets_expires"
+    )]
+    targets_expire_in: Option<TimeDelta>,
+
+    /// Expire timestamp.json this long from now, such as '1d'
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        conflicts_with = "timestamp_expires"
+    )]
+    timestamp_expire_in: Option<TimeDelta>,
+}
+
+impl ExpireInArgs {
+    /// The expiration of `role`: `explicit` if it was given, otherwise `now` plus the role's
+    /// relative expiration or `--all-expire-in`, or `None` if neither was given.
+    pub(crate) fn expires(
+        &self,
+        role: RoleType,
+        explicit: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>> {
+        if explicit.is_some() {
+            return Ok(explicit);
+        }
+        let relative = match role {
+            RoleType::Snapshot => self.snapshot_expire_in,
+            RoleType::Targets => self.targets_expire_in,
+            RoleType::Timestamp => self.timestamp_expire_in,
+            _ => None,
+        };
+        relative
+            .or(self.all_expire_in)
+            .map(|delta| {
+                now.checked_add_signed(delta)
+                    .context(error::DateArgInvalidSnafu {
+                        input: delta.to_string(),
+                        msg: format!("unable to compute an expiration for {role} that far ahead"),
+                    })
+            })
+            .transpose()
+    }
+}
+
+/// Options for how commands that download a repository use the network.
+#[derive(Debug, Args)]
+pub(crate) struct TransportArgs {
+    /// Limit downloads to this many bytes per second
+    #[arg(long, value_name = "BYTES/SEC")]
+    limit_rate: Option<NonZeroU64>,
+
+    /// Number of times to retry a failed request
+    #[arg(long, value_name = "N")]
+    retries: Option<u32>,
+
+    /// Seconds to wait before each retry of a failed request
+    #[arg(long, value_name = "SECS")]
+    retry_wait: Option<u64>,
+}
+
+impl TransportArgs {
+    /// A transport for `file` and `http(s)` URLs with these settings.
+    pub(crate) fn transport(&self) -> DefaultTransport {
+        let mut builder = HttpTransportBuilder::new().max_bytes_per_second(self.limit_rate);
+        if let Some(retries) = self.retries {
+            builder = builder.tries(retries.saturating_add(1));
+        }
+        if let Some(wait) = self.retry_wait {
+            let wait = Duration::from_secs(wait);
+            builder = builder
+                .initial_backoff(wait)
+                .max_backoff(wait)
+                .backoff_factor(1.0);
+        }
+        DefaultTransport::new_with_http_settings(builder)
+    }
+}
+
+/// Writes signed metadata to `metadata_dir`, along with gzip-compressed companions if `gzip` is set.
+pub(crate) async fn write_metadata(
+    signed_repo: &SignedRepository,
+    metadata_dir: &Path,
+    gzip: bool,
+) -> Result<()> {
+    if gzip {
+        signed_repo.write_with_gzip(metadata_dir).await.map(drop)
+    } else {
+        signed_repo.write(metadata_dir).await
+    }
+    .context(error::WriteRepoSnafu {
+        directory: metadata_dir,
+    })
+}
+
 /// Load a repo for metadata processing only. Such a repo will never use the
 /// targets directory, so a dummy path is passed.
 ///
@@ -35,3 +218,25 @@ where
     .await
     .context(error::RepoLoadSnafu)
 }
+
+/// Lays `rows` out as a table, with each column as wide as its widest cell and columns separated
+/// by two spaces. The first row is usually a header.
+pub(crate) fn format_table(rows: &[Vec<String>]) -> String {
+    let mut widths = vec![0; rows.first().map_or(0, Vec::len)];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
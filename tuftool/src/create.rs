@@ -2,23 +2,35 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::build_targets;
+use crate::common::{write_metadata, ExpireInArgs};
 use crate::datetime::parse_datetime;
+use crate::delegations_spec::DelegationsSpec;
 use crate::error::{self, Result};
 use crate::source::parse_key_source;
 use chrono::{DateTime, Utc};
 use clap::Parser;
-use snafu::ResultExt;
+use snafu::{OptionExt, ResultExt};
 use std::num::{NonZeroU64, NonZeroUsize};
 use std::path::PathBuf;
-use tough::editor::signed::PathExists;
+use tough::editor::signed::{MetadataStyle, PathExists};
 use tough::editor::RepositoryEditor;
+use tough::schema::RoleType;
 
 #[derive(Debug, Parser)]
 pub(crate) struct CreateArgs {
+    /// YAML file describing delegated roles to create, with their keys, paths and thresholds
+    #[arg(long)]
+    delegations_spec: Option<PathBuf>,
+
     /// Follow symbolic links in the given directory when adding targets
     #[arg(short, long)]
     follow: bool,
 
+    /// Also write a gzip-compressed `.json.gz` copy of each metadata file, and a
+    /// `gzip-manifest.json` listing their lengths and hashes
+    #[arg(long)]
+    gzip_metadata: bool,
+
     /// Number of target hashing threads to run when adding targets
     /// (default: number of cores)
     // No default is specified in structopt here. This is because rayon
@@ -27,10 +39,18 @@ pub(crate) struct CreateArgs {
     #[arg(short, long)]
     jobs: Option<NonZeroUsize>,
 
+    #[command(flatten)]
+    expire_in: ExpireInArgs,
+
     /// Key files to sign with
     #[arg(short, long = "key", required = true)]
     keys: Vec<String>,
 
+    /// How to write the targets, snapshot and timestamp metadata: "pretty", indented with a
+    /// trailing newline, or "compact", without whitespace, which keeps large targets metadata small
+    #[arg(long, default_value = "pretty")]
+    metadata_style: MetadataStyle,
+
     /// The directory where the repository will be written
     #[arg(short, long)]
     outdir: PathBuf,
@@ -41,8 +61,12 @@ pub(crate) struct CreateArgs {
 
     /// Expiration of snapshot.json file; can be in full RFC 3339 format, or something like 'in
     /// 7 days'
-    #[arg(long, value_parser = parse_datetime)]
-    snapshot_expires: DateTime<Utc>,
+    #[arg(
+        long,
+        value_parser = parse_datetime,
+        required_unless_present_any = ["snapshot_expire_in", "all_expire_in"]
+    )]
+    snapshot_expires: Option<DateTime<Utc>>,
 
     /// Version of snapshot.json file
     #[arg(long)]
@@ -60,8 +84,12 @@ pub(crate) struct CreateArgs {
 
     /// Expiration of targets.json file; can be in full RFC 3339 format, or something like 'in
     /// 7 days'
-    #[arg(long, value_parser = parse_datetime)]
-    targets_expires: DateTime<Utc>,
+    #[arg(
+        long,
+        value_parser = parse_datetime,
+        required_unless_present_any = ["targets_expire_in", "all_expire_in"]
+    )]
+    targets_expires: Option<DateTime<Utc>>,
 
     /// Version of targets.json file
     #[arg(long)]
@@ -69,8 +97,12 @@ pub(crate) struct CreateArgs {
 
     /// Expiration of timestamp.json file; can be in full RFC 3339 format, or something like 'in
     /// 7 days'
-    #[arg(long, value_parser = parse_datetime)]
-    timestamp_expires: DateTime<Utc>,
+    #[arg(
+        long,
+        value_parser = parse_datetime,
+        required_unless_present_any = ["timestamp_expire_in", "all_expire_in"]
+    )]
+    timestamp_expires: Option<DateTime<Utc>>,
 
     /// Version of timestamp.json file
     #[arg(long)]
@@ -79,6 +111,12 @@ pub(crate) struct CreateArgs {
 
 impl CreateArgs {
     pub(crate) async fn run(&self) -> Result<()> {
+        // Read the spec first so that mistakes in it are reported before any targets are hashed
+        let delegations_spec = match &self.delegations_spec {
+            Some(path) => Some(DelegationsSpec::load(path).await?),
+            None => None,
+        };
+
         let mut keys = Vec::new();
         for source in &self.keys {
             let key_source = parse_key_source(source)?;
@@ -94,6 +132,18 @@ impl CreateArgs {
                 .context(error::InitializeThreadPoolSnafu)?;
         }
 
+        let now = Utc::now();
+        let expires = |role, explicit| -> Result<DateTime<Utc>> {
+            self.expire_in
+                .expires(role, explicit, now)?
+                .context(error::MissingSnafu {
+                    what: format!("{role} expires"),
+                })
+        };
+        let targets_expires = expires(RoleType::Targets, self.targets_expires)?;
+        let snapshot_expires = expires(RoleType::Snapshot, self.snapshot_expires)?;
+        let timestamp_expires = expires(RoleType::Timestamp, self.timestamp_expires)?;
+
         let targets = build_targets(&self.targets_indir, self.follow).await?;
         let mut editor = RepositoryEditor::new(&self.root)
             .await
@@ -102,12 +152,13 @@ impl CreateArgs {
         editor
             .targets_version(self.targets_version)
             .context(error::DelegationStructureSnafu)?
-            .targets_expires(self.targets_expires)
+            .targets_expires(targets_expires)
             .context(error::DelegationStructureSnafu)?
             .snapshot_version(self.snapshot_version)
-            .snapshot_expires(self.snapshot_expires)
+            .snapshot_expires(snapshot_expires)
             .timestamp_version(self.timestamp_version)
-            .timestamp_expires(self.timestamp_expires);
+            .timestamp_expires(timestamp_expires)
+            .metadata_style(self.metadata_style);
 
         for (target_name, target) in targets {
             editor
@@ -115,6 +166,10 @@ impl CreateArgs {
                 .context(error::DelegationStructureSnafu)?;
         }
 
+        if let Some(spec) = &delegations_spec {
+            spec.apply(&mut editor, &keys).await?;
+        }
+
         let signed_repo = editor.sign(&keys).await.context(error::SignRepoSnafu)?;
 
         let metadata_dir = &self.outdir.join("metadata");
@@ -126,12 +181,7 @@ impl CreateArgs {
                 indir: &self.targets_indir,
                 outdir: targets_outdir,
             })?;
-        signed_repo
-            .write(metadata_dir)
-            .await
-            .context(error::WriteRepoSnafu {
-                directory: metadata_dir,
-            })?;
+        write_metadata(&signed_repo, metadata_dir, self.gzip_metadata).await?;
 
         Ok(())
     }
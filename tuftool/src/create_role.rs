@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::datetime::parse_datetime;
+use crate::delegation_package::DelegationPackage;
 use crate::error::{self, Result};
 use crate::source::parse_key_source;
 use chrono::{DateTime, Utc};
@@ -15,6 +16,7 @@ use tough::key_source::KeySource;
 use tough::schema::decoded::Decoded;
 use tough::schema::decoded::Hex;
 use tough::schema::key::Key;
+use tough::schema::{PathHashPrefix, PathPattern, PathSet};
 
 #[derive(Debug, Parser)]
 pub(crate) struct CreateRoleArgs {
@@ -28,8 +30,30 @@ pub(crate) struct CreateRoleArgs {
     keys: Vec<String>,
 
     /// The directory where the repository will be written
-    #[arg(short, long)]
-    outdir: PathBuf,
+    #[arg(short, long, required_unless_present = "package")]
+    outdir: Option<PathBuf>,
+
+    /// Also write a delegation package for `add-role --package` to this file, holding the
+    /// role's metadata and public keys along with the requested paths and threshold
+    #[arg(long)]
+    package: Option<PathBuf>,
+
+    /// The paths to request for the role in the delegation package
+    #[arg(
+        short,
+        long,
+        requires = "package",
+        conflicts_with = "path_hash_prefixes"
+    )]
+    paths: Option<Vec<PathPattern>>,
+
+    /// The path hash prefixes to request for the role in the delegation package
+    #[arg(short = 'x', long, requires = "package")]
+    path_hash_prefixes: Option<Vec<PathHashPrefix>>,
+
+    /// The threshold of signatures to request for the role in the delegation package
+    #[arg(short, long, requires = "package", default_value = "1")]
+    threshold: NonZeroU64,
 
     /// Version of targets.json file
     #[arg(short, long)]
@@ -45,30 +69,48 @@ impl CreateRoleArgs {
         }
 
         // create the new role
+        let key_pairs = key_hash_map(&keys).await;
         let new_role = TargetsEditor::new(role)
             .version(self.version)
             .expires(self.expires)
-            .add_key(key_hash_map(&keys).await, None)
+            .add_key(key_pairs.clone(), None)
             .context(error::DelegationStructureSnafu)?
             .sign(&keys)
             .await
             .context(error::SignRepoSnafu)?;
         // write the new role
-        let metadata_destination_out = &self.outdir.join("metadata");
-        new_role
-            .write(metadata_destination_out, false)
-            .await
-            .context(error::WriteRolesSnafu {
-                roles: [role.to_string()].to_vec(),
-            })?;
+        if let Some(outdir) = &self.outdir {
+            let metadata_destination_out = &outdir.join("metadata");
+            new_role
+                .write(metadata_destination_out, false)
+                .await
+                .context(error::WriteRolesSnafu {
+                    roles: [role.to_string()].to_vec(),
+                })?;
+        }
+        if let Some(package) = &self.package {
+            // `sign()` puts the role being edited first
+            let (_, metadata) = new_role.roles().remove(0).signed().clone().targets();
+            DelegationPackage::new(role, self.threshold, self.paths(), key_pairs, metadata)
+                .write(package)
+                .await?;
+        }
         Ok(())
     }
+
+    fn paths(&self) -> PathSet {
+        if let Some(path_hash_prefixes) = &self.path_hash_prefixes {
+            PathSet::PathHashPrefixes(path_hash_prefixes.clone())
+        } else {
+            PathSet::Paths(self.paths.clone().unwrap_or_default())
+        }
+    }
 }
 
 async fn key_hash_map(keys: &[Box<dyn KeySource>]) -> HashMap<Decoded<Hex>, Key> {
     let mut key_pairs = HashMap::new();
     for source in keys {
-        let key_pair = source.as_sign().await.unwrap().tuf_key();
+        let key_pair = source.public_key().await.unwrap();
         key_pairs.insert(key_pair.key_id().unwrap().clone(), key_pair.clone());
     }
     key_pairs
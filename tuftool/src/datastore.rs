@@ -0,0 +1,78 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::error::{self, Result};
+use clap::Parser;
+use snafu::ResultExt;
+use std::path::{Path, PathBuf};
+use tough::{Datastore, ResetAcknowledgement};
+
+#[derive(Debug, Parser)]
+pub(crate) enum Command {
+    /// List the documents stored in a client datastore, with their versions
+    Inspect {
+        /// Path to the datastore directory
+        path: PathBuf,
+    },
+    /// Remove stored metadata so that a client accepts lower versions on its next load
+    Reset {
+        /// Path to the datastore directory
+        path: PathBuf,
+        /// Role(s) to reset, e.g. "snapshot" or the name of a delegated role
+        #[arg(short, long = "role", required_unless_present = "all")]
+        roles: Vec<String>,
+        /// Reset everything, including the latest known time
+        #[arg(long, conflicts_with = "roles")]
+        all: bool,
+        /// Acknowledge that this removes rollback protection until the next load
+        #[arg(long, required = true)]
+        i_understand_rollback_risk: bool,
+    },
+}
+
+impl Command {
+    pub(crate) async fn run(self) -> Result<()> {
+        match self {
+            Command::Inspect { path } => Command::inspect(&path).await,
+            Command::Reset {
+                path, roles, all, ..
+            } => Command::reset(&path, &roles, all).await,
+        }
+    }
+
+    async fn inspect(path: &Path) -> Result<()> {
+        let entries = Datastore::open(path)
+            .inspect()
+            .await
+            .context(error::DatastoreSnafu { path })?;
+        for entry in entries {
+            match (entry.role, entry.version) {
+                (Some(role), Some(version)) => {
+                    println!("{}\t{role}\tversion {version}", entry.name);
+                }
+                _ => println!("{}", entry.name),
+            }
+        }
+        Ok(())
+    }
+
+    async fn reset(path: &Path, roles: &[String], all: bool) -> Result<()> {
+        let datastore = Datastore::open(path);
+        // Clap requires `--i-understand-rollback-risk` before we get here.
+        let ack = ResetAcknowledgement::i_understand_this_removes_rollback_protection();
+        if all {
+            datastore
+                .reset_all(ack)
+                .await
+                .context(error::DatastoreSnafu { path })?;
+        } else {
+            for role in roles {
+                datastore
+                    .reset(role, ack)
+                    .await
+                    .context(error::DatastoreSnafu { path })?;
+            }
+        }
+        Ok(())
+    }
+}
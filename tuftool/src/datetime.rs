@@ -42,20 +42,40 @@ pub(crate) fn parse_datetime(input: &str) -> Result<DateTime<Utc>> {
         .parse()
         .context(error::DateArgCountSnafu { input })?;
 
+    let now = Utc::now();
+    let then = now + unit_duration(count, unit_str, input)?;
+    Ok(then)
+}
+
+/// Parses a user-specified length of time, a count and a unit like "30d", "12h", "2w", or "7 days"
+pub(crate) fn parse_duration(input: &str) -> Result<TimeDelta> {
+    let (count_str, unit_str) = input.split_at(
+        input
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(input.len()),
+    );
+    let count: u32 = count_str
+        .parse()
+        .context(error::DateArgCountSnafu { input })?;
+    unit_duration(count, unit_str.trim_start(), input)
+}
+
+/// The length of `count` of `unit`, which is hours, days, or weeks.
+fn unit_duration(count: u32, unit_str: &str, input: &str) -> Result<TimeDelta> {
     let duration = match unit_str {
-        "hour" | "hours" => {
+        "h" | "hour" | "hours" => {
             TimeDelta::try_hours(i64::from(count)).context(error::DateArgInvalidSnafu {
                 input: count.to_string(),
                 msg: format!("unable to convert {count} to a number of hours"),
             })?
         }
-        "day" | "days" => {
+        "d" | "day" | "days" => {
             TimeDelta::try_days(i64::from(count)).context(error::DateArgInvalidSnafu {
                 input: count.to_string(),
                 msg: format!("unable to convert {count} to a number of days"),
             })?
         }
-        "week" | "weeks" => {
+        "w" | "week" | "weeks" => {
             TimeDelta::try_weeks(i64::from(count)).context(error::DateArgInvalidSnafu {
                 input: count.to_string(),
                 msg: format!("unable to convert {count} to a number of weeks"),
@@ -69,8 +89,5 @@ pub(crate) fn parse_datetime(input: &str) -> Result<DateTime<Utc>> {
             .fail();
         }
     };
-
-    let now = Utc::now();
-    let then = now + duration;
-    Ok(then)
+    Ok(duration)
 }
@@ -0,0 +1,135 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! The `delegation_package` module owns the file format used to hand a new delegated role from
+//! the team that holds its keys to the team that delegates to it.
+//!
+//! `tuftool delegation create-role --package` writes the role's signed metadata, its public keys
+//! and the paths and threshold it asks to be delegated into one JSON file, which
+//! `tuftool delegation add-role --package` reads from a path or URL.
+
+use crate::error::{self, Result};
+use crate::{load_file, write_file};
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::path::Path;
+use tough::schema::decoded::{Decoded, Hex};
+use tough::schema::key::Key;
+use tough::schema::{DelegatedRole, DelegatedTargets, Delegations, PathSet, Signed, Targets};
+use url::Url;
+
+/// The only package format version that this `tuftool` reads and writes.
+const PACKAGE_FORMAT_VERSION: u32 = 1;
+
+/// A delegated role and the terms under which it asks to be delegated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct DelegationPackage {
+    /// The version of the package format.
+    pub(crate) format_version: u32,
+    /// The name of the delegated role.
+    pub(crate) name: String,
+    /// The number of signatures by `keys` that the role requires.
+    pub(crate) threshold: NonZeroU64,
+    /// The paths the role asks to be trusted for.
+    #[serde(flatten)]
+    pub(crate) paths: PathSet,
+    /// The public keys that sign the role, by key ID.
+    pub(crate) keys: HashMap<Decoded<Hex>, Key>,
+    /// The role's signed metadata.
+    pub(crate) metadata: Signed<Targets>,
+}
+
+impl DelegationPackage {
+    pub(crate) fn new(
+        name: &str,
+        threshold: NonZeroU64,
+        paths: PathSet,
+        keys: HashMap<Decoded<Hex>, Key>,
+        metadata: Signed<Targets>,
+    ) -> Self {
+        Self {
+            format_version: PACKAGE_FORMAT_VERSION,
+            name: name.to_owned(),
+            threshold,
+            paths,
+            keys,
+            metadata,
+        }
+    }
+
+    /// Reads a package from `source`, which is either an `http(s)` or `file` URL or a path, and
+    /// checks that its metadata is signed by at least `threshold` of its keys.
+    pub(crate) async fn load(source: &str) -> Result<Self> {
+        let package: Self = match Url::parse(source) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+                let data = reqwest::get(url.as_str())
+                    .await
+                    .context(error::ReqwestGetSnafu)?
+                    .error_for_status()
+                    .context(error::BadResponseSnafu { url: source })?
+                    .bytes()
+                    .await
+                    .context(error::ReqwestCopySnafu)?;
+                serde_json::from_slice(&data)
+                    .context(error::PackageParseSnafu { source_url: source })?
+            }
+            Ok(url) if url.scheme() == "file" => {
+                let path = url
+                    .to_file_path()
+                    .ok()
+                    .context(error::PackageSourceSnafu { source_url: source })?;
+                load_file(&path).await?
+            }
+            _ => load_file(Path::new(source)).await?,
+        };
+        package.verify(source)?;
+        Ok(package)
+    }
+
+    /// Writes the package to `path`.
+    pub(crate) async fn write(self, path: &Path) -> Result<()> {
+        write_file(path, self).await
+    }
+
+    /// The package's metadata in the form taken by the editors' `delegate_role()`.
+    pub(crate) fn delegated_targets(&self) -> Signed<DelegatedTargets> {
+        self.metadata.clone().delegated_targets(&self.name)
+    }
+
+    fn verify(&self, source: &str) -> Result<()> {
+        ensure!(
+            self.format_version == PACKAGE_FORMAT_VERSION,
+            error::PackageVersionSnafu {
+                source_url: source,
+                version: self.format_version,
+                supported: PACKAGE_FORMAT_VERSION,
+            }
+        );
+        for (keyid, key) in &self.keys {
+            let actual = key
+                .key_id()
+                .context(error::PackageVerifySnafu { source_url: source })?;
+            ensure!(
+                &actual == keyid,
+                error::PackageKeyIdSnafu {
+                    source_url: source,
+                    keyid: hex::encode(keyid),
+                }
+            );
+        }
+        let mut delegations = Delegations::new();
+        delegations.keys.clone_from(&self.keys);
+        delegations.roles.push(DelegatedRole {
+            name: self.name.clone(),
+            keyids: self.keys.keys().cloned().collect(),
+            threshold: self.threshold,
+            paths: self.paths.clone(),
+            terminating: false,
+            targets: None,
+        });
+        delegations
+            .verify_role(&self.metadata, &self.name)
+            .context(error::PackageVerifySnafu { source_url: source })
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! The `delegations_spec` module owns the YAML file read by `tuftool create --delegations-spec`,
+//! which describes a tree of delegated roles to create along with a new repository.
+//!
+//! ```yaml
+//! roles:
+//!   - name: team-a
+//!     keys: [keys/team-a.pem]
+//!     threshold: 1
+//!     paths: ["team-a/*"]
+//!     expires: in 30 days
+//!   - name: team-a-nightly
+//!     delegated_by: team-a
+//!     keys: [keys/team-a-nightly.pem]
+//!     threshold: 1
+//!     path_hash_prefixes: ["0", "1"]
+//!     expires: in 7 days
+//!     version: 3
+//! ```
+//!
+//! A role is delegated by `targets` unless `delegated_by` names a role listed before it. Each role
+//! lists exactly one of `paths` and `path_hash_prefixes`, and must give its `threshold`.
+
+use crate::datetime::parse_datetime;
+use crate::error::{self, Result};
+use crate::source::parse_key_source;
+use serde::Deserialize;
+use snafu::{ensure, ResultExt};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::num::NonZeroU64;
+use std::path::Path;
+use tough::editor::RepositoryEditor;
+use tough::key_source::KeySource;
+use tough::schema::{PathHashPrefix, PathPattern, PathSet};
+
+/// The roles to delegate, in the order they are created.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DelegationsSpec {
+    roles: Vec<RoleSpec>,
+}
+
+/// One delegated role and the terms of its delegation, as read from a [`RoleSpecFields`].
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "RoleSpecFields")]
+struct RoleSpec {
+    name: String,
+    delegated_by: String,
+    keys: Vec<String>,
+    threshold: NonZeroU64,
+    paths: PathSet,
+    expires: String,
+    version: NonZeroU64,
+}
+
+/// The fields of a role in the spec. Unknown fields are rejected, so that a misspelled field,
+/// such as `treshold`, isn't silently ignored.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RoleSpecFields {
+    /// The name of the role.
+    name: String,
+    /// The role that delegates to this one.
+    #[serde(default = "default_delegated_by")]
+    delegated_by: String,
+    /// Key sources that sign the role; their public keys are added to the delegation.
+    keys: Vec<String>,
+    /// The number of `keys` that must sign the role.
+    threshold: NonZeroU64,
+    /// The paths the role is trusted for. Exactly one of this and `path_hash_prefixes` is given.
+    paths: Option<Vec<PathPattern>>,
+    /// The hash prefixes of the paths the role is trusted for.
+    path_hash_prefixes: Option<Vec<PathHashPrefix>>,
+    /// The role's expiration, in RFC 3339 format or something like 'in 7 days'.
+    expires: String,
+    /// The role's version.
+    #[serde(default = "default_version")]
+    version: NonZeroU64,
+}
+
+impl TryFrom<RoleSpecFields> for RoleSpec {
+    type Error = String;
+
+    fn try_from(fields: RoleSpecFields) -> std::result::Result<Self, Self::Error> {
+        let paths = match (fields.paths, fields.path_hash_prefixes) {
+            (Some(paths), None) => PathSet::Paths(paths),
+            (None, Some(prefixes)) => PathSet::PathHashPrefixes(prefixes),
+            _ => {
+                return Err(format!(
+                    "role '{}' must list exactly one of `paths` and `path_hash_prefixes`",
+                    fields.name
+                ))
+            }
+        };
+        Ok(RoleSpec {
+            name: fields.name,
+            delegated_by: fields.delegated_by,
+            keys: fields.keys,
+            threshold: fields.threshold,
+            paths,
+            expires: fields.expires,
+            version: fields.version,
+        })
+    }
+}
+
+fn default_delegated_by() -> String {
+    "targets".to_owned()
+}
+
+fn default_version() -> NonZeroU64 {
+    NonZeroU64::MIN
+}
+
+impl DelegationsSpec {
+    /// Reads the spec at `path` and checks that each role is named once, isn't `targets`, and is
+    /// delegated by `targets` or a role listed before it.
+    pub(crate) async fn load(path: &Path) -> Result<Self> {
+        let data = tokio::fs::read(path)
+            .await
+            .context(error::FileOpenSnafu { path })?;
+        let spec: Self =
+            serde_yaml_ng::from_slice(&data).context(error::DelegationsSpecParseSnafu { path })?;
+
+        let mut names = HashSet::new();
+        for role in &spec.roles {
+            ensure!(
+                role.name != "targets",
+                error::DelegationsSpecRoleSnafu {
+                    path,
+                    name: &role.name,
+                    reason: "is the top-level targets role, which can't be delegated",
+                }
+            );
+            ensure!(
+                names.contains(&role.delegated_by) || role.delegated_by == "targets",
+                error::DelegationsSpecRoleSnafu {
+                    path,
+                    name: &role.name,
+                    reason: format!(
+                        "is delegated by '{}', which isn't listed before it",
+                        role.delegated_by
+                    ),
+                }
+            );
+            ensure!(
+                names.insert(role.name.clone()),
+                error::DelegationsSpecRoleSnafu {
+                    path,
+                    name: &role.name,
+                    reason: "is listed more than once",
+                }
+            );
+        }
+        Ok(spec)
+    }
+
+    /// Delegates the spec's roles in `editor`, whose top-level targets are signed with
+    /// `targets_keys`. The top-level targets are signed as part of this, so they must be complete.
+    pub(crate) async fn apply(
+        &self,
+        editor: &mut RepositoryEditor,
+        targets_keys: &[Box<dyn KeySource>],
+    ) -> Result<()> {
+        // Delegate each parent's roles together, then sign the parent. `load()` ensures that a
+        // parent comes before its roles, so every parent exists by the time it is edited.
+        let parents = std::iter::once(("targets", None)).chain(
+            self.roles
+                .iter()
+                .map(|role| (role.name.as_str(), Some(role))),
+        );
+        for (parent, parent_spec) in parents {
+            let children: Vec<&RoleSpec> = self
+                .roles
+                .iter()
+                .filter(|role| role.delegated_by == parent)
+                .collect();
+            if children.is_empty() {
+                continue;
+            }
+            let parent_keys = if let Some(role) = parent_spec {
+                editor
+                    .change_delegated_targets(parent)
+                    .context(error::DelegationStructureSnafu)?
+                    .targets_version(role.version)
+                    .context(error::DelegationStructureSnafu)?
+                    .targets_expires(parse_datetime(&role.expires)?)
+                    .context(error::DelegationStructureSnafu)?;
+                Some(key_sources(&role.keys)?)
+            } else {
+                None
+            };
+            for child in children {
+                editor
+                    .delegate_role(
+                        &child.name,
+                        &key_sources(&child.keys)?,
+                        child.paths.clone(),
+                        child.threshold,
+                        parse_datetime(&child.expires)?,
+                        child.version,
+                    )
+                    .await
+                    .context(error::DelegationStructureSnafu)?;
+            }
+            editor
+                .sign_targets_editor(parent_keys.as_deref().unwrap_or(targets_keys))
+                .await
+                .context(error::DelegationStructureSnafu)?;
+        }
+        Ok(())
+    }
+}
+
+fn key_sources(keys: &[String]) -> Result<Vec<Box<dyn KeySource>>> {
+    keys.iter().map(|key| parse_key_source(key)).collect()
+}
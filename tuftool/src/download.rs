@@ -1,20 +1,21 @@
 // Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use crate::common::{ExpiredRepoArgs, TransportArgs};
 use crate::download_root::download_root;
 use crate::error::{self, Result};
 use clap::Parser;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use snafu::{ensure, ResultExt};
-use std::num::NonZeroU64;
+use std::num::{NonZeroU64, NonZeroUsize};
 use std::path::{Path, PathBuf};
-use tough::{ExpirationEnforcement, Prefix, Repository, RepositoryLoader, TargetName};
+use tough::{Repository, RepositoryLoader, TargetName};
 use url::Url;
 
 #[derive(Debug, Parser)]
 pub(crate) struct DownloadArgs {
-    /// Allow repo download for expired metadata
-    #[arg(long)]
-    allow_expired_repo: bool,
+    #[command(flatten)]
+    expired_repo: ExpiredRepoArgs,
 
     /// Allow downloading the root.json file (unsafe)
     #[arg(long)]
@@ -28,6 +29,26 @@ pub(crate) struct DownloadArgs {
     #[arg(short = 'n', long = "target-name")]
     target_names: Vec<String>,
 
+    /// Download only targets whose names match one of these globs, e.g. 'images/*.img'
+    #[arg(long = "targets", conflicts_with = "target_names")]
+    target_globs: Vec<String>,
+
+    /// Download only targets listed by this role, e.g. a delegated role's name
+    #[arg(long, conflicts_with = "target_names")]
+    role: Option<String>,
+
+    /// Also download the targets listed by the roles that `--role` delegates to, however deeply
+    #[arg(long, requires = "role")]
+    include_delegated: bool,
+
+    /// Give downloaded targets the Unix file permissions listed in their `custom.mode`
+    #[arg(long)]
+    apply_modes: bool,
+
+    /// Number of targets to download at once
+    #[arg(short, long, default_value = "1")]
+    jobs: NonZeroUsize,
+
     /// Path to root.json file for the repository
     #[arg(short, long)]
     root: Option<PathBuf>,
@@ -42,24 +63,17 @@ pub(crate) struct DownloadArgs {
     /// Remote root.json version number
     #[arg(short = 'v', long, default_value = "1")]
     root_version: NonZeroU64,
-}
-
-fn expired_repo_warning<P: AsRef<Path>>(path: P) {
-    #[rustfmt::skip]
-    eprintln!("\
-=================================================================
-Downloading repo to {}
-WARNING: `--allow-expired-repo` was passed; this is unsafe and will not establish trust, use only for testing!
-=================================================================",
-              path.as_ref().display());
+    #[command(flatten)]
+    transport: TransportArgs,
 }
 
 impl DownloadArgs {
     pub(crate) async fn run(&self) -> Result<()> {
         // To help ensure that downloads are safe, we require that the outdir does not exist.
+        let outdir = self.expired_repo.output_path(&self.outdir);
         ensure!(
-            !self.outdir.exists(),
-            error::DownloadOutdirExistsSnafu { path: &self.outdir }
+            !outdir.exists(),
+            error::DownloadOutdirExistsSnafu { path: &outdir }
         );
 
         // use local root.json or download from repository
@@ -74,12 +88,7 @@ impl DownloadArgs {
         };
 
         // load repository
-        let expiration_enforcement = if self.allow_expired_repo {
-            expired_repo_warning(&self.outdir);
-            ExpirationEnforcement::Unsafe
-        } else {
-            ExpirationEnforcement::Safe
-        };
+        let expiration_enforcement = self.expired_repo.expiration_enforcement(&[&outdir]);
         let repository = RepositoryLoader::new(
             &tokio::fs::read(&root_path)
                 .await
@@ -88,53 +97,83 @@ impl DownloadArgs {
             self.targets_base_url.clone(),
         )
         .expiration_enforcement(expiration_enforcement)
+        .apply_target_modes(self.apply_modes)
+        .transport(self.transport.transport())
         .load()
         .await
         .context(error::RepoLoadSnafu)?;
 
         // download targets
-        handle_download(&repository, &self.outdir, &self.target_names).await
+        let targets = self.select_targets(&repository)?;
+        handle_download(&repository, &outdir, &targets, self.jobs).await
     }
+
+    /// Returns the requested targets, or the targets listed by `--role` (by default, the top-level
+    /// targets role), and with `--include-delegated` the roles it delegates to, that match
+    /// `--targets`.
+    fn select_targets(&self, repository: &Repository) -> Result<Vec<TargetName>> {
+        if !self.target_names.is_empty() {
+            return self
+                .target_names
+                .iter()
+                .map(|s| TargetName::new(s).context(error::InvalidTargetNameSnafu))
+                .collect();
+        }
+
+        let role = self.role.as_deref().unwrap_or("targets");
+        let targets = if self.include_delegated {
+            repository.role_targets_recursive(role)
+        } else {
+            repository.role_targets(role)
+        }
+        .context(error::DownloadRoleNotFoundSnafu { role })?;
+        let globs = build_glob_set(&self.target_globs)?;
+        Ok(targets
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| {
+                globs
+                    .as_ref()
+                    .is_none_or(|globs| globs.is_match(name.raw()))
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+/// Builds a matcher for any of `patterns`, or `None` if there are none.
+pub(crate) fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).context(error::TargetGlobSnafu { pattern })?);
+    }
+    builder.build().map(Some).context(error::TargetGlobSnafu {
+        pattern: patterns.join(", "),
+    })
 }
 
 async fn handle_download(
     repository: &Repository,
     outdir: &Path,
-    raw_names: &[String],
+    targets: &[TargetName],
+    jobs: NonZeroUsize,
 ) -> Result<()> {
-    let target_names: Result<Vec<TargetName>> = raw_names
-        .iter()
-        .map(|s| TargetName::new(s).context(error::InvalidTargetNameSnafu))
-        .collect();
-    let target_names = target_names?;
-    let download_target = |name: TargetName| async move {
-        println!("\t-> {}", name.raw());
-        repository
-            .save_target(&name, outdir, Prefix::None)
-            .await
-            .context(error::MetadataSnafu)?;
-        Ok(())
-    };
-
-    // copy requested targets, or all available targets if not specified
-    let targets: Vec<TargetName> = if target_names.is_empty() {
-        repository
-            .targets()
-            .signed
-            .targets
-            .keys()
-            .cloned()
-            .collect()
-    } else {
-        target_names
-    };
-
-    println!("Downloading targets to {outdir:?}");
+    println!("Downloading targets to {}", outdir.display());
     tokio::fs::create_dir_all(outdir)
         .await
         .context(error::DirCreateSnafu { path: outdir })?;
-    for target in targets {
-        download_target(target).await?;
+    let results = repository
+        .download_targets(targets, outdir, jobs, |name, result| {
+            if result.is_ok() {
+                println!("\t-> {}", name.raw());
+            }
+        })
+        .await;
+    for (_, result) in results {
+        result.context(error::MetadataSnafu)?;
     }
     Ok(())
 }
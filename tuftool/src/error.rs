@@ -46,6 +46,13 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Datastore operation failed at '{}': {}", path.display(), source))]
+    Datastore {
+        path: PathBuf,
+        source: tough::error::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Date argument '{}' is invalid: {}", input, msg))]
     DateArgInvalid { input: String, msg: String },
 
@@ -72,6 +79,26 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to parse delegations spec '{}': {}", path.display(), source))]
+    DelegationsSpecParse {
+        path: PathBuf,
+        source: serde_yaml_ng::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Invalid delegations spec '{}': role '{}' {}",
+        path.display(),
+        name,
+        reason
+    ))]
+    DelegationsSpecRole {
+        path: PathBuf,
+        name: String,
+        reason: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Couldn't find role '{}': {}", role, source))]
     DelegateeNotFound {
         role: String,
@@ -79,9 +106,23 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Couldn't find the role that delegates '{}': {}", role, source))]
+    DelegatorNotFound {
+        role: String,
+        source: tough::schema::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("A file or directory already exists at '{}'", path.display()))]
     DownloadOutdirExists { path: PathBuf, backtrace: Backtrace },
 
+    #[snafu(display("Couldn't find targets for role '{}': {}", role, source))]
+    DownloadRoleNotFound {
+        role: String,
+        source: tough::error::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display(
         "Failed to create a Repository Editor with root.json '{}': {}",
         path.display(),
@@ -156,18 +197,48 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("{} key source(s) failed their checks", failed))]
+    KeyCheck { failed: usize, backtrace: Backtrace },
+
     #[snafu(display("Duplicate key ID: {}", key_id))]
     KeyDuplicate {
         key_id: String,
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Refusing to overwrite existing key file {}; pass --force to replace it",
+        path.display()
+    ))]
+    KeyFileExists { path: PathBuf, backtrace: Backtrace },
+
+    #[snafu(display(
+        "The public key is already in root.json with different fields, as key ID {}",
+        key_id
+    ))]
+    KeyPublicDuplicate {
+        key_id: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to calculate key ID: {}", source))]
     KeyId {
         #[snafu(backtrace)]
         source: tough::schema::Error,
     },
 
+    #[snafu(display("Failed to generate key pair: {}", source))]
+    KeyGenerate {
+        source: aws_lc_rs::error::Unspecified,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to create key in AWS KMS: {}", source))]
+    KmsCreateKey {
+        source: tough_kms::error::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Unable to parse keypair: {}", source))]
     KeyPairParse {
         source: tough::error::Error,
@@ -211,9 +282,66 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to move snapshot and timestamp to online keys: {}", source))]
+    MigrateOnlineKeys {
+        source: tough::error::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to move new metadata into '{}': {}", path.display(), source))]
+    MetadataPublish {
+        path: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Missing: {}", what))]
     Missing { what: String, backtrace: Backtrace },
 
+    #[snafu(display(
+        "Delegation package '{}' lists a key under the wrong key ID {}",
+        source_url,
+        keyid
+    ))]
+    PackageKeyId {
+        source_url: String,
+        keyid: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to parse delegation package '{}': {}", source_url, source))]
+    PackageParse {
+        source_url: String,
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Delegation package source '{}' is not a path or URL", source_url))]
+    PackageSource {
+        source_url: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Delegation package '{}' failed verification: {}", source_url, source))]
+    PackageVerify {
+        source_url: String,
+        source: tough::schema::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Delegation package '{}' has format version {}, only version {} is supported",
+        source_url,
+        version,
+        supported
+    ))]
+    PackageVersion {
+        source_url: String,
+        version: u32,
+        supported: u32,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Unable to determine file name from path: '{}'", path.display()))]
     NoFileName { path: PathBuf, backtrace: Backtrace },
 
@@ -237,6 +365,16 @@ pub(crate) enum Error {
     #[snafu(display("Path {} is not valid UTF-8", path.display()))]
     PathUtf8 { path: PathBuf, backtrace: Backtrace },
 
+    #[snafu(display("Failed to open repository directory {}: {}", path.display(), source))]
+    RepoDir {
+        path: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Repository directory {} can't be expressed as a file URL", path.display()))]
+    RepoDirUrl { path: PathBuf, backtrace: Backtrace },
+
     #[snafu(display("Failed to load repository: {}", source))]
     RepoLoad {
         source: tough::error::Error,
@@ -255,6 +393,30 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Repository verification found {} error(s) and {} warning(s)",
+        errors,
+        warnings
+    ))]
+    RepoVerify {
+        errors: usize,
+        warnings: usize,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("{} check(s) failed for role '{}'", failed, role))]
+    RoleVerify {
+        role: String,
+        failed: usize,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to check the signatures on role '{}': {}", role, source))]
+    RoleSignatureReport {
+        role: String,
+        source: tough::schema::Error,
+    },
+
     #[snafu(display("Response '{}' from '{}': {}", get_status_code(source), url, source))]
     BadResponse {
         url: String,
@@ -262,6 +424,19 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to serve repository: {}", source))]
+    Serve {
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Failed to listen on {}: {}", addr, source))]
+    ServeBind {
+        addr: std::net::SocketAddr,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to sign repository: {}", source))]
     SignRepo {
         source: tough::error::Error,
@@ -269,13 +444,25 @@ pub(crate) enum Error {
     },
 
     #[snafu(display(
-        "Root was signed with {} signatures; it must be signed with at least {}",
-        signature_count,
+        "Root is signed by {} of the {} keys required by {}; still needed: {} more from {}",
+        valid,
         threshold,
+        which,
+        needed,
+        missing.join(", "),
     ))]
     SignatureRoot {
+        which: String,
         threshold: u64,
-        signature_count: usize,
+        valid: usize,
+        needed: u64,
+        missing: Vec<String>,
+    },
+
+    #[snafu(display("Failed to check the signatures on '{}': {}", path.display(), source))]
+    SignatureReport {
+        path: PathBuf,
+        source: tough::schema::Error,
     },
 
     #[snafu(display("Failed to sign '{}': {}", path.display(), source))]
@@ -285,6 +472,19 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Failed to create test root: {}", source))]
+    TestRootCreate {
+        source: tough::error::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Invalid target glob '{}': {}", pattern, source))]
+    TargetGlob {
+        pattern: String,
+        source: globset::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Unable to create Target from path '{}': {}", path.display(), source))]
     TargetFromPath {
         path: PathBuf,
@@ -292,12 +492,58 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Key source {} needs an access token in the {} environment variable",
+        scheme,
+        var
+    ))]
+    AccessTokenMissing {
+        scheme: String,
+        var: &'static str,
+        source: std::env::VarError,
+    },
+
+    #[cfg(feature = "pkcs11")]
+    #[snafu(display("PKCS#11 key source URL \"{}\" has no key-label parameter", url))]
+    Pkcs11KeyLabelMissing { url: String, backtrace: Backtrace },
+
+    #[cfg(feature = "pkcs11")]
+    #[snafu(display("Invalid slot \"{}\" in PKCS#11 key source URL: {}", slot, source))]
+    Pkcs11SlotParse {
+        slot: String,
+        source: std::num::ParseIntError,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Unrecognized URL scheme \"{}\"", scheme))]
     UnrecognizedScheme {
         scheme: String,
         backtrace: Backtrace,
     },
 
+    #[snafu(display(
+        "Unrecognized AWS KMS signing algorithm \"{}\", expected rsassa-pss-sha256 or ecdsa-sha256",
+        algorithm
+    ))]
+    UnrecognizedSigningAlgorithm {
+        algorithm: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("{} keys cannot be {} bits", key_type, bits))]
+    UnsupportedKeyBits {
+        key_type: &'static str,
+        bits: u16,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Cannot create {} keys in key source '{}'", key_type, key_source))]
+    UnsupportedKeyType {
+        key_type: &'static str,
+        key_source: String,
+        backtrace: Backtrace,
+    },
+
     /// Root creates an unloadable repo
     #[snafu(display(
         "Unstable root: '{}' role contains {} keys, threshold is {}",
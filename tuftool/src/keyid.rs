@@ -0,0 +1,44 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::error::{self, Result};
+use crate::source::parse_key_source;
+use clap::Parser;
+use serde_json::{Map, Value};
+use snafu::ResultExt;
+use std::path::PathBuf;
+
+/// Print the TUF key ID and public key of each key source
+///
+/// The output is a JSON object mapping each key ID to its public key, in the same form as the
+/// `keys` object of root.json.
+#[derive(Debug, Parser)]
+pub(crate) struct KeyIdArgs {
+    /// Key source(s) to compute key IDs for
+    #[arg(short, long = "key", required = true)]
+    key_sources: Vec<String>,
+}
+
+impl KeyIdArgs {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let stdout = PathBuf::from("<stdout>");
+        let mut keys = Map::new();
+        for source in &self.key_sources {
+            let key = parse_key_source(source)?
+                .public_key()
+                .await
+                .context(error::KeyPairFromKeySourceSnafu)?;
+            let key_id = key.key_id().context(error::KeyIdSnafu)?;
+            keys.insert(
+                hex::encode(key_id),
+                serde_json::to_value(&key).context(error::FileWriteJsonSnafu { path: &stdout })?,
+            );
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&Value::Object(keys))
+                .context(error::FileWriteJsonSnafu { path: &stdout })?
+        );
+        Ok(())
+    }
+}
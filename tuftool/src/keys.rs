@@ -0,0 +1,57 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::error::{self, Result};
+use crate::source::{parse_key_source, parse_kms_key_source};
+use clap::Parser;
+use snafu::{ensure, ResultExt};
+
+#[derive(Debug, Parser)]
+pub(crate) enum Command {
+    /// Check that key sources are usable for signing, without signing anything
+    Check {
+        /// Key source(s) to check
+        #[arg(short, long = "key", required = true)]
+        key_sources: Vec<String>,
+    },
+}
+
+impl Command {
+    pub(crate) async fn run(self) -> Result<()> {
+        match self {
+            Command::Check { key_sources } => Command::check(&key_sources).await,
+        }
+    }
+
+    /// Prints a report for each key source. AWS KMS keys are checked for existence, key usage,
+    /// algorithm support, and permissions; other sources are checked by loading the key.
+    async fn check(key_sources: &[String]) -> Result<()> {
+        let mut failed: usize = 0;
+        for source in key_sources {
+            if let Some(kms_key) = parse_kms_key_source(source)? {
+                let report = kms_key.validate().await;
+                println!("{report}");
+                if !report.is_ok() {
+                    failed += 1;
+                }
+                continue;
+            }
+
+            match parse_key_source(source)?.as_sign().await {
+                Ok(sign) => {
+                    let key_id = sign.tuf_key().key_id().context(error::KeyIdSnafu)?;
+                    println!(
+                        "{source}\n  key loaded: ok (key ID {})",
+                        hex::encode(key_id)
+                    );
+                }
+                Err(err) => {
+                    println!("{source}\n  key loaded: FAILED: {err}");
+                    failed += 1;
+                }
+            }
+        }
+        ensure!(failed == 0, error::KeyCheckSnafu { failed });
+        Ok(())
+    }
+}
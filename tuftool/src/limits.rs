@@ -0,0 +1,43 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::load_metadata_repo;
+use crate::error::Result;
+use clap::Parser;
+use std::path::PathBuf;
+use url::Url;
+
+/// Print a repository's metadata sizes and client `Limits` that fit them with room to grow
+#[derive(Debug, Parser)]
+pub(crate) struct LimitsArgs {
+    /// Path to root.json file for the repository
+    #[arg(short, long)]
+    root: PathBuf,
+
+    /// TUF repository metadata base URL
+    #[arg(short, long = "metadata-url")]
+    metadata_base_url: Url,
+}
+
+impl LimitsArgs {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let repository = load_metadata_repo(&self.root, self.metadata_base_url.clone()).await?;
+        let sizes = repository.metadata_sizes();
+        let limits = repository.recommended_limits();
+
+        println!("Observed metadata sizes:");
+        println!("  root.json: {} bytes", sizes.root);
+        println!("  timestamp.json: {} bytes", sizes.timestamp);
+        println!("  snapshot.json: {} bytes", sizes.snapshot);
+        println!("  largest targets role: {} bytes", sizes.targets);
+        println!("  delegated roles: {}", sizes.delegated_roles);
+        println!("Recommended limits:");
+        println!("  max_root_size: {}", limits.max_root_size);
+        println!("  max_targets_size: {}", limits.max_targets_size);
+        println!("  max_timestamp_size: {}", limits.max_timestamp_size);
+        println!("  max_snapshot_size: {}", limits.max_snapshot_size);
+        println!("  max_root_updates: {}", limits.max_root_updates);
+        println!("  max_delegated_roles: {}", limits.max_delegated_roles);
+        Ok(())
+    }
+}
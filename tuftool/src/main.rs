@@ -14,21 +14,35 @@
 
 mod add_key_role;
 mod add_role;
+mod bundle_trust;
+mod canonicalize;
 mod clone;
 mod common;
 mod create;
 mod create_role;
+mod datastore;
 mod datetime;
+mod delegation_package;
+mod delegations_spec;
 mod download;
 mod download_root;
 mod error;
+mod keyid;
+mod keys;
+mod limits;
+mod migrate_online_keys;
 mod remove_key_role;
 mod remove_role;
 mod root;
+mod serve;
 mod source;
+mod status;
+mod targets;
 mod transfer_metadata;
 mod update;
 mod update_targets;
+mod verify;
+mod verify_role;
 
 use crate::error::Result;
 use clap::Parser;
@@ -75,33 +89,71 @@ impl Program {
 
 #[derive(Debug, Parser)]
 enum Command {
+    /// Copy the root.json chain up to a repository's current root, and print the root to embed
+    /// as a trusted root
+    BundleTrust(bundle_trust::BundleTrustArgs),
+    /// Print the canonical JSON form and sha256 digest of a metadata file
+    Canonicalize(canonicalize::CanonicalizeArgs),
     /// Clone a TUF repository, including metadata and some or all targets
     Clone(clone::CloneArgs),
     /// Create a TUF repository
     Create(create::CreateArgs),
+    /// Inspect or reset a client datastore
+    #[command(subcommand)]
+    Datastore(datastore::Command),
     /// Delegation Commands
     Delegation(Delegation),
     /// Download a TUF repository's targets
     Download(download::DownloadArgs),
+    /// Print the TUF key IDs and public keys of key sources
+    Keyid(keyid::KeyIdArgs),
+    /// Check signing keys
+    #[command(subcommand)]
+    Keys(keys::Command),
+    /// Print metadata sizes and recommended client limits for a repository
+    Limits(limits::LimitsArgs),
+    /// Move the snapshot and timestamp roles to new keys, such as online KMS keys, re-signing
+    /// root.json, snapshot.json, and timestamp.json
+    MigrateOnlineKeys(Box<migrate_online_keys::MigrateOnlineKeysArgs>),
     /// Manipulate a root.json metadata file
     #[command(subcommand)]
     Root(root::Command),
+    /// Serve a repository directory over HTTP for local testing
+    Serve(serve::ServeArgs),
+    /// Print each role's version, expiration and signatures, as a quick health check
+    Status(status::StatusArgs),
+    /// Inspect a TUF repository's targets
+    #[command(subcommand)]
+    Targets(targets::Command),
     /// Transfer a TUF repository's metadata from a previous root to a new root
     TransferMetadata(transfer_metadata::TransferMetadataArgs),
     /// Update a TUF repository's metadata and optionally add targets
     Update(Box<update::UpdateArgs>),
+    /// Check a TUF repository for problems before publishing it, printing a JSON report
+    Verify(verify::VerifyArgs),
 }
 
 impl Command {
     async fn run(self) -> Result<()> {
         match self {
+            Command::BundleTrust(args) => args.run().await,
+            Command::Canonicalize(args) => args.run().await,
             Command::Create(args) => args.run().await,
+            Command::Keyid(args) => args.run().await,
+            Command::Keys(keys_subcommand) => keys_subcommand.run().await,
+            Command::Limits(args) => args.run().await,
+            Command::MigrateOnlineKeys(args) => args.run().await,
             Command::Root(root_subcommand) => root_subcommand.run().await,
             Command::Download(args) => args.run().await,
             Command::Update(args) => args.run().await,
+            Command::Datastore(cmd) => cmd.run().await,
             Command::Delegation(cmd) => cmd.run().await,
             Command::Clone(cmd) => cmd.run().await,
+            Command::Serve(args) => args.run().await,
+            Command::Status(args) => args.run().await,
+            Command::Targets(cmd) => cmd.run().await,
             Command::TransferMetadata(cmd) => cmd.run().await,
+            Command::Verify(args) => args.run().await,
         }
     }
 }
@@ -169,7 +221,7 @@ where
             if tx.blocking_send(entry).is_err() {
                 // Receiver error'ed out
                 break;
-            };
+            }
         }
         Ok(())
     });
@@ -227,7 +279,7 @@ async fn process_target(path: &Path) -> Result<(TargetName, Target)> {
 
 #[tokio::main]
 async fn main() -> ! {
-    std::process::exit(match Program::parse().run().await {
+    std::process::exit(match Box::pin(Program::parse().run()).await {
         Ok(()) => 0,
         Err(err) => {
             eprintln!("{err}");
@@ -273,6 +325,8 @@ enum DelegationCommand {
     RemoveKey(Box<remove_key_role::RemoveKeyArgs>),
     /// Update Delegated targets
     UpdateDelegatedTargets(Box<update_targets::UpdateTargetsArgs>),
+    /// Verify a delegated role's metadata against the delegation from its parent
+    Verify(Box<verify_role::VerifyRoleArgs>),
 }
 
 impl DelegationCommand {
@@ -284,6 +338,7 @@ impl DelegationCommand {
             DelegationCommand::AddKey(args) => args.run(role).await,
             DelegationCommand::RemoveKey(args) => args.run(role).await,
             DelegationCommand::Remove(args) => args.run(role).await,
+            DelegationCommand::Verify(args) => args.run(role).await,
         }
     }
 }
@@ -0,0 +1,158 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::load_metadata_repo;
+use crate::datetime::parse_datetime;
+use crate::error::{self, Result};
+use crate::source::parse_key_source;
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use snafu::{OptionExt, ResultExt};
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use tough::editor::RepositoryEditor;
+use tough::key_source::KeySource;
+use url::Url;
+
+#[derive(Debug, Parser)]
+pub(crate) struct MigrateOnlineKeysArgs {
+    /// Key files to sign the new root.json with; they must meet the root threshold of both the
+    /// current and the new root
+    #[arg(short, long = "key", required = true)]
+    keys: Vec<String>,
+
+    /// TUF repository metadata base URL
+    #[arg(short, long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// The directory where the migrated repository will be written; its `metadata` directory is
+    /// only changed once every file has been signed and written
+    #[arg(short, long)]
+    outdir: PathBuf,
+
+    /// Path to root.json file for the repository
+    #[arg(short, long)]
+    root: PathBuf,
+
+    /// New key for the snapshot role, such as an `aws-kms://` URL; may be given more than once
+    #[arg(long = "snapshot-key", required = true)]
+    snapshot_keys: Vec<String>,
+
+    /// Expiration of snapshot.json file; can be in full RFC 3339 format, or something like 'in
+    /// 7 days'
+    #[arg(long, value_parser = parse_datetime)]
+    snapshot_expires: DateTime<Utc>,
+
+    /// Version of snapshot.json file
+    #[arg(long)]
+    snapshot_version: NonZeroU64,
+
+    /// New key for the timestamp role, such as an `aws-kms://` URL; may be given more than once
+    #[arg(long = "timestamp-key", required = true)]
+    timestamp_keys: Vec<String>,
+
+    /// Expiration of timestamp.json file; can be in full RFC 3339 format, or something like 'in
+    /// 7 days'
+    #[arg(long, value_parser = parse_datetime)]
+    timestamp_expires: DateTime<Utc>,
+
+    /// Version of timestamp.json file
+    #[arg(long)]
+    timestamp_version: NonZeroU64,
+}
+
+impl MigrateOnlineKeysArgs {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let root_keys = key_sources(&self.keys)?;
+        let snapshot_keys = key_sources(&self.snapshot_keys)?;
+        let timestamp_keys = key_sources(&self.timestamp_keys)?;
+
+        let repository = load_metadata_repo(&self.root, self.metadata_base_url.clone()).await?;
+        let mut editor = RepositoryEditor::from_repo(&self.root, repository)
+            .await
+            .context(error::EditorFromRepoSnafu { path: &self.root })?;
+        editor
+            .snapshot_version(self.snapshot_version)
+            .snapshot_expires(self.snapshot_expires)
+            .timestamp_version(self.timestamp_version)
+            .timestamp_expires(self.timestamp_expires);
+        let signed_repo = editor
+            .migrate_online_keys(&root_keys, snapshot_keys, timestamp_keys)
+            .await
+            .context(error::MigrateOnlineKeysSnafu)?;
+
+        // Write everything next to the metadata directory first, so that a failure leaves the
+        // metadata that's already there untouched.
+        tokio::fs::create_dir_all(&self.outdir)
+            .await
+            .context(error::DirCreateSnafu { path: &self.outdir })?;
+        let staging = TempDir::new_in(&self.outdir)
+            .context(error::FileTempCreateSnafu { path: &self.outdir })?;
+        signed_repo
+            .write(staging.path())
+            .await
+            .context(error::WriteRepoSnafu {
+                directory: staging.path(),
+            })?;
+        publish(staging.path(), &self.outdir.join("metadata"))
+    }
+}
+
+fn key_sources(sources: &[String]) -> Result<Vec<Box<dyn KeySource>>> {
+    sources
+        .iter()
+        .map(|source| parse_key_source(source))
+        .collect()
+}
+
+/// Moves each file in `staging` into `metadata_dir`. If any move fails, the files that were
+/// already moved are taken back out and the files they replaced are restored.
+fn publish(staging: &Path, metadata_dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(metadata_dir).context(error::DirCreateSnafu { path: metadata_dir })?;
+    let backup = TempDir::new_in(
+        staging
+            .parent()
+            .context(error::PathParentSnafu { path: staging })?,
+    )
+    .context(error::FileTempCreateSnafu { path: staging })?;
+
+    let mut names = Vec::new();
+    for entry in
+        std::fs::read_dir(staging).context(error::MetadataPublishSnafu { path: metadata_dir })?
+    {
+        let entry = entry.context(error::MetadataPublishSnafu { path: metadata_dir })?;
+        names.push(entry.file_name());
+    }
+    names.sort();
+
+    // Each published file, and whether it replaced an existing one.
+    let mut published = Vec::new();
+    for name in names {
+        let dest = metadata_dir.join(&name);
+        let replaced = dest.exists();
+        let result = if replaced {
+            std::fs::rename(&dest, backup.path().join(&name))
+        } else {
+            Ok(())
+        }
+        .and_then(|()| std::fs::rename(staging.join(&name), &dest));
+        if let Err(source) = result {
+            if replaced && !dest.exists() {
+                // The existing file was moved out of the way but the new one wasn't moved in.
+                let _ = std::fs::rename(backup.path().join(&name), &dest);
+            }
+            for (name, replaced) in published.into_iter().rev() {
+                let dest = metadata_dir.join(&name);
+                if replaced {
+                    let _ = std::fs::rename(backup.path().join(&name), &dest);
+                } else {
+                    let _ = std::fs::remove_file(&dest);
+                }
+            }
+            return Err(source).context(error::MetadataPublishSnafu { path: metadata_dir });
+        }
+        published.push((name, replaced));
+    }
+    Ok(())
+}
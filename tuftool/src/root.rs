@@ -3,9 +3,10 @@
 
 use crate::datetime::parse_datetime;
 use crate::error::{self, Result};
-use crate::source::parse_key_source;
+use crate::source::{local_key_path, parse_key_source, parse_kms_key_source};
 use crate::{load_file, write_file};
 use aws_lc_rs::rand::SystemRandom;
+use aws_lc_rs::signature::{EcdsaKeyPair, Ed25519KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
 use chrono::{DateTime, Timelike, Utc};
 use clap::Parser;
 use log::warn;
@@ -21,6 +22,8 @@ use tough::key_source::KeySource;
 use tough::schema::decoded::{Decoded, Hex};
 use tough::schema::{key::Key, KeyHolder, RoleKeys, RoleType, Root, Signed};
 use tough::sign::{parse_keypair, Sign};
+use tough::test_helpers::DegenerateRoot;
+use tough_kms::KmsSigningAlgorithm;
 
 #[derive(Debug, Parser)]
 pub(crate) enum Command {
@@ -40,6 +43,25 @@ pub(crate) enum Command {
         /// Path to root.json
         path: PathBuf,
     },
+    /// Create a root.json for tests, in which a single new Ed25519 key signs for every role with a
+    /// threshold of 1
+    CreateTest {
+        /// Where to write root.json
+        #[arg(short, long)]
+        out: PathBuf,
+        /// Where to write the new key
+        #[arg(short, long)]
+        key: String,
+        /// Whether metadata and targets are prefixed with their versions and hashes
+        #[arg(long)]
+        consistent_snapshot: bool,
+        /// Expiration of root; can be in full RFC 3339 format, or something like 'in 7 days'
+        #[arg(short, long, value_parser = parse_datetime, default_value = "in 52 weeks")]
+        expires: DateTime<Utc>,
+        /// Overwrite the key file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
     /// Set the expiration time for root.json
     Expire {
         /// Path to root.json
@@ -65,6 +87,34 @@ pub(crate) enum Command {
         /// The role to add the key to
         #[arg(short, long = "role")]
         roles: Vec<RoleType>,
+        /// Overwrite the key file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Generate a new RSA, Ed25519 or ECDSA key pair, saving it to a key source, and add it to a
+    /// role. For an `aws-kms://` key source, a new RSA or ECDSA key is created in AWS KMS under the
+    /// alias given in the URL instead.
+    GenKey {
+        /// Path to root.json
+        path: PathBuf,
+        /// Where to write the new key
+        #[arg()]
+        key_source: String,
+        /// Type of the new key
+        #[arg(short = 't', long = "type", value_enum, default_value = "rsa")]
+        key_type: KeyType,
+        /// Bit length of new key (2048, 3072 or 4096 for RSA; 256 for ECDSA)
+        #[arg(short, long)]
+        bits: Option<u16>,
+        /// Public exponent of new RSA key
+        #[arg(short, long = "exp", default_value = "65537")]
+        exponent: u32,
+        /// The role to add the key to
+        #[arg(short, long = "role")]
+        roles: Vec<RoleType>,
+        /// Overwrite the key file if it already exists
+        #[arg(long)]
+        force: bool,
     },
     /// Create a new root.json metadata file
     Init {
@@ -84,6 +134,14 @@ pub(crate) enum Command {
         /// file)
         role: Option<RoleType>,
     },
+    /// Set whether the repository uses consistent snapshots
+    SetConsistentSnapshot {
+        /// Path to root.json
+        path: PathBuf,
+        /// Whether metadata and targets are prefixed with their versions and hashes
+        #[arg(action = clap::ArgAction::Set)]
+        consistent_snapshot: bool,
+    },
     /// Set the signature count threshold for a role
     SetThreshold {
         /// Path to root.json
@@ -110,8 +168,9 @@ pub(crate) enum Command {
         /// Optional - Path of older root.json that contains the key-id
         #[arg(short, long)]
         cross_sign: Option<PathBuf>,
-        /// Ignore the threshold when signing with fewer keys
-        #[arg(short, long)]
+        /// Write root.json even if it isn't signed by enough keys, warning about the keys that
+        /// are still needed
+        #[arg(short, long, visible_alias = "allow-partial")]
         ignore_threshold: bool,
     },
 }
@@ -136,7 +195,18 @@ impl Command {
         match self {
             Command::Init { path, version } => Command::init(&path, version).await,
             Command::BumpVersion { path } => Command::bump_version(&path).await,
+            Command::CreateTest {
+                out,
+                key,
+                consistent_snapshot,
+                expires,
+                force,
+            } => Command::create_test(&out, &key, consistent_snapshot, expires, force).await,
             Command::Expire { path, time } => Command::expire(&path, &time).await,
+            Command::SetConsistentSnapshot {
+                path,
+                consistent_snapshot,
+            } => Command::set_consistent_snapshot(&path, consistent_snapshot).await,
             Command::SetThreshold {
                 path,
                 role,
@@ -157,7 +227,19 @@ impl Command {
                 key_source,
                 bits,
                 exponent,
-            } => Command::gen_rsa_key(&path, &roles, &key_source, bits, exponent).await,
+                force,
+            } => Command::gen_rsa_key(&path, &roles, &key_source, bits, exponent, force).await,
+            Command::GenKey {
+                path,
+                key_source,
+                key_type,
+                bits,
+                exponent,
+                roles,
+                force,
+            } => {
+                Command::gen_key(&path, &roles, &key_source, key_type, bits, exponent, force).await
+            }
             Command::Sign {
                 path,
                 key_sources,
@@ -191,6 +273,7 @@ impl Command {
                         RoleType::Targets => role_keys!(),
                         RoleType::Timestamp => role_keys!(),
                     },
+                    unrecognized_roles: HashMap::new(),
                     _extra: HashMap::new(),
                 },
                 signatures: Vec::new(),
@@ -213,6 +296,36 @@ impl Command {
         write_file(path, root).await
     }
 
+    async fn create_test(
+        out: &Path,
+        key_source: &str,
+        consistent_snapshot: bool,
+        expires: DateTime<Utc>,
+        force: bool,
+    ) -> Result<()> {
+        if let Some(key_path) = local_key_path(key_source)? {
+            ensure!(
+                force || !key_path.exists(),
+                error::KeyFileExistsSnafu { path: key_path }
+            );
+        }
+        let root = DegenerateRoot::new(consistent_snapshot, round_time(expires))
+            .await
+            .context(error::TestRootCreateSnafu)?;
+        let key_id = hex::encode(
+            root.public_key()
+                .key_id()
+                .context(error::JsonSerializationSnafu)?,
+        );
+        parse_key_source(key_source)?
+            .write(root.key_pem(), &key_id)
+            .await
+            .context(error::WriteKeySourceSnafu)?;
+        write_file(out, root.root().signed().clone()).await?;
+        println!("{key_id}");
+        print_public_key(root.public_key())
+    }
+
     async fn expire(path: &Path, time: &DateTime<Utc>) -> Result<()> {
         let mut root: Signed<Root> = load_file(path).await?;
         root.signed.expires = round_time(*time);
@@ -220,6 +333,13 @@ impl Command {
         write_file(path, root).await
     }
 
+    async fn set_consistent_snapshot(path: &Path, consistent_snapshot: bool) -> Result<()> {
+        let mut root: Signed<Root> = load_file(path).await?;
+        root.signed.consistent_snapshot = consistent_snapshot;
+        clear_sigs(&mut root);
+        write_file(path, root).await
+    }
+
     async fn set_threshold(path: &Path, role: RoleType, threshold: NonZeroU64) -> Result<()> {
         let mut root: Signed<Root> = load_file(path).await?;
         root.signed
@@ -250,10 +370,9 @@ impl Command {
 
         for ks in keys {
             let key_pair = ks
-                .as_sign()
+                .public_key()
                 .await
-                .context(error::KeyPairFromKeySourceSnafu)?
-                .tuf_key();
+                .context(error::KeyPairFromKeySourceSnafu)?;
             let key_id = hex::encode(add_key(&mut root.signed, roles, key_pair)?);
             println!("Added key: {key_id}");
         }
@@ -279,7 +398,10 @@ impl Command {
                     .position(|k| k.eq(key_id))
                     .map(|pos| role_keys.keyids.remove(pos));
             }
-            root.signed.keys.remove(key_id);
+            // Roles tough doesn't manage are left unchanged, so keep any key they still list.
+            if !root.signed.unrecognized_role_lists_key(key_id) {
+                root.signed.keys.remove(key_id);
+            }
         }
         clear_sigs(&mut root);
         write_file(path, root).await
@@ -292,40 +414,118 @@ impl Command {
         key_source: &str,
         bits: u16,
         exponent: u32,
+        force: bool,
+    ) -> Result<()> {
+        let root: Signed<Root> = load_file(path).await?;
+        let pem = generate_rsa_pem(bits, exponent)?;
+        Self::add_generated_key(path, root, roles, key_source, &pem, force).await?;
+        Ok(())
+    }
+
+    async fn gen_key(
+        path: &Path,
+        roles: &[RoleType],
+        key_source: &str,
+        key_type: KeyType,
+        bits: Option<u16>,
+        exponent: u32,
+        force: bool,
     ) -> Result<()> {
         let mut root: Signed<Root> = load_file(path).await?;
 
-        // ring doesn't support RSA key generation yet
-        // https://github.com/briansmith/ring/issues/219
-        let mut command = std::process::Command::new("openssl");
-        command.args(["genpkey", "-algorithm", "RSA", "-pkeyopt"]);
-        command.arg(format!("rsa_keygen_bits:{bits}"));
-        command.arg("-pkeyopt");
-        command.arg(format!("rsa_keygen_pubexp:{exponent}"));
+        if let Some(mut kms_key) = parse_kms_key_source(key_source)? {
+            let default_bits = match key_type {
+                KeyType::Rsa => 2048,
+                KeyType::Ecdsa => {
+                    kms_key.signing_algorithm = KmsSigningAlgorithm::EcdsaSha256;
+                    256
+                }
+                KeyType::Ed25519 => {
+                    return error::UnsupportedKeyTypeSnafu {
+                        key_type: key_type.name(),
+                        key_source,
+                    }
+                    .fail()
+                }
+            };
+            let new_key_id = kms_key
+                .create_key(bits.unwrap_or(default_bits))
+                .await
+                .context(error::KmsCreateKeySnafu)?;
+            warn!("Created AWS KMS key {new_key_id}");
+            let public_key = kms_key
+                .public_key()
+                .await
+                .context(error::KeyPairFromKeySourceSnafu)?;
+            let key_id = hex::encode(add_key(&mut root.signed, roles, public_key.clone())?);
+            clear_sigs(&mut root);
+            println!("{key_id}");
+            print_public_key(&public_key)?;
+            return write_file(path, root).await;
+        }
 
-        let command_str = format!("{command:?}");
-        let output = command.output().context(error::CommandExecSnafu {
-            command_str: &command_str,
-        })?;
-        ensure!(
-            output.status.success(),
-            error::CommandStatusSnafu {
-                command_str: &command_str,
-                status: output.status
+        let pem = match key_type {
+            KeyType::Rsa => generate_rsa_pem(bits.unwrap_or(2048), exponent)?,
+            KeyType::Ed25519 => {
+                ensure!(
+                    bits.is_none(),
+                    error::UnsupportedKeyBitsSnafu {
+                        key_type: key_type.name(),
+                        bits: bits.unwrap_or_default(),
+                    }
+                );
+                let document = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                    .context(error::KeyGenerateSnafu)?;
+                pkcs8_pem(document.as_ref())
             }
-        );
-        let stdout =
-            String::from_utf8(output.stdout).context(error::CommandUtf8Snafu { command_str })?;
+            KeyType::Ecdsa => {
+                ensure!(
+                    bits.is_none() || bits == Some(256),
+                    error::UnsupportedKeyBitsSnafu {
+                        key_type: key_type.name(),
+                        bits: bits.unwrap_or_default(),
+                    }
+                );
+                let document = EcdsaKeyPair::generate_pkcs8(
+                    &ECDSA_P256_SHA256_ASN1_SIGNING,
+                    &SystemRandom::new(),
+                )
+                .context(error::KeyGenerateSnafu)?;
+                pkcs8_pem(document.as_ref())
+            }
+        };
+        let public_key =
+            Self::add_generated_key(path, root, roles, key_source, &pem, force).await?;
+        print_public_key(&public_key)
+    }
 
-        let key_pair = parse_keypair(stdout.as_bytes()).context(error::KeyPairParseSnafu)?;
-        let key_id = hex::encode(add_key(&mut root.signed, roles, key_pair.tuf_key())?);
+    /// Adds the generated private key `pem` to `roles`, writes it to `key_source`, and prints its
+    /// key ID. Returns the public key. An existing key file is only replaced if `force` is set.
+    async fn add_generated_key(
+        path: &Path,
+        mut root: Signed<Root>,
+        roles: &[RoleType],
+        key_source: &str,
+        pem: &str,
+        force: bool,
+    ) -> Result<Key> {
+        if let Some(key_path) = local_key_path(key_source)? {
+            ensure!(
+                force || !key_path.exists(),
+                error::KeyFileExistsSnafu { path: key_path }
+            );
+        }
+        let key_pair = parse_keypair(pem.as_bytes()).context(error::KeyPairParseSnafu)?;
+        let public_key = key_pair.tuf_key();
+        let key_id = hex::encode(add_key(&mut root.signed, roles, public_key.clone())?);
         let key = parse_key_source(key_source)?;
-        key.write(&stdout, &key_id)
+        key.write(pem, &key_id)
             .await
             .context(error::WriteKeySourceSnafu)?;
         clear_sigs(&mut root);
         println!("{key_id}");
-        write_file(path, root).await
+        write_file(path, root).await?;
+        Ok(public_key)
     }
 
     async fn sign(
@@ -336,10 +536,11 @@ impl Command {
     ) -> Result<()> {
         let root: Signed<Root> = load_file(path).await?;
         // get the root based on cross-sign
-        let loaded_root = match cross_sign {
-            None => root.clone(),
-            Some(cross_sign_root) => load_file(&cross_sign_root).await?,
+        let previous_root: Option<Signed<Root>> = match cross_sign {
+            None => None,
+            Some(cross_sign_root) => Some(load_file(&cross_sign_root).await?),
         };
+        let loaded_root = previous_root.clone().unwrap_or_else(|| root.clone());
         // sign the root
         let mut signed_root = SignedRole::new(
             root.signed.clone(),
@@ -381,34 +582,55 @@ impl Command {
             }
         }
 
-        // Signature check for root
-        let threshold = signed_root
-            .signed()
-            .signed
-            .roles
-            .get(&RoleType::Root)
-            .ok_or(error::Error::UnstableRoot {
-                // The code should never reach this point
-                role: RoleType::Root,
-                threshold: 0,
-                actual: 0,
-            })?
-            .threshold
-            .get();
-        let signature_count = signed_root.signed().signatures.len();
-        if threshold > signature_count as u64 {
+        // Check that root.json is signed by enough of its own root keys and, when cross-signing,
+        // enough of the previous root's keys for clients to accept the update.
+        let signed = signed_root.signed();
+        let mut requirements = vec![(
+            String::from("the root role"),
+            signed.signed.signature_report(signed),
+        )];
+        if let Some(previous) = &previous_root {
+            requirements.push((
+                format!("the root role of version {}", previous.signed.version),
+                previous.signed.signature_report(signed),
+            ));
+        }
+        for (which, report) in requirements {
+            let report = report.context(error::SignatureReportSnafu { path })?;
+            let hex = |keyids: &[Decoded<Hex>]| -> Vec<String> {
+                keyids.iter().map(hex::encode).collect()
+            };
+            println!(
+                "{}: signed by {} of {} required keys{}",
+                which,
+                report.signed.len(),
+                report.threshold,
+                if report.signed.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", hex(&report.signed).join(", "))
+                }
+            );
+            if report.is_satisfied() {
+                continue;
+            }
+            let missing = hex(&report.missing);
             // Return an error when the "ignore-threshold" flag wasn't set
             if !ignore_threshold {
                 return Err(error::Error::SignatureRoot {
-                    threshold,
-                    signature_count,
+                    which,
+                    threshold: report.threshold.get(),
+                    valid: report.signed.len(),
+                    needed: report.needed(),
+                    missing,
                 });
             }
-            // Print out a warning letting the user know that the target file isn't compliant with
-            // the threshold used for the root role.
+            // Print out a warning letting the user know which keys still need to sign
             warn!(
-                "The root.json file requires at least {} signatures, the target file contains {}",
-                threshold, signature_count
+                "{} more signatures are needed for {}, from: {}",
+                report.needed(),
+                which,
+                missing.join(", ")
             );
         }
 
@@ -426,6 +648,63 @@ impl Command {
     }
 }
 
+/// The key types that `root gen-key` can generate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum KeyType {
+    Rsa,
+    Ed25519,
+    Ecdsa,
+}
+
+impl KeyType {
+    fn name(self) -> &'static str {
+        match self {
+            KeyType::Rsa => "rsa",
+            KeyType::Ed25519 => "ed25519",
+            KeyType::Ecdsa => "ecdsa",
+        }
+    }
+}
+
+/// Generates an RSA private key, PEM-encoded.
+fn generate_rsa_pem(bits: u16, exponent: u32) -> Result<String> {
+    // ring doesn't support RSA key generation yet
+    // https://github.com/briansmith/ring/issues/219
+    let mut command = std::process::Command::new("openssl");
+    command.args(["genpkey", "-algorithm", "RSA", "-pkeyopt"]);
+    command.arg(format!("rsa_keygen_bits:{bits}"));
+    command.arg("-pkeyopt");
+    command.arg(format!("rsa_keygen_pubexp:{exponent}"));
+
+    let command_str = format!("{command:?}");
+    let output = command.output().context(error::CommandExecSnafu {
+        command_str: &command_str,
+    })?;
+    ensure!(
+        output.status.success(),
+        error::CommandStatusSnafu {
+            command_str: &command_str,
+            status: output.status
+        }
+    );
+    String::from_utf8(output.stdout).context(error::CommandUtf8Snafu { command_str })
+}
+
+/// PEM-encodes a PKCS#8 private key document, as `openssl genpkey` would.
+fn pkcs8_pem(document: &[u8]) -> String {
+    pem::encode(&pem::Pem::new("PRIVATE KEY", document))
+}
+
+/// Prints a public key in the form it takes in the `keys` object of root.json.
+fn print_public_key(key: &Key) -> Result<()> {
+    let stdout = PathBuf::from("<stdout>");
+    println!(
+        "{}",
+        serde_json::to_string_pretty(key).context(error::FileWriteJsonSnafu { path: &stdout })?
+    );
+    Ok(())
+}
+
 fn round_time(time: DateTime<Utc>) -> DateTime<Utc> {
     // `Timelike::with_nanosecond` returns None only when passed a value >= 2_000_000_000
     time.with_nanosecond(0).unwrap()
@@ -445,7 +724,18 @@ fn add_key(root: &mut Root, role: &[RoleType], key: Key) -> Result<Decoded<Hex>>
     {
         key_id.clone()
     } else {
-        // Key isn't present yet, so we need to add it
+        // Key isn't present yet, so we need to add it. The same public key under a second key ID
+        // would let one signer count twice towards a threshold.
+        if let Some((existing, _)) = root
+            .keys
+            .iter()
+            .find(|(_, candidate_key)| key.public_key() == candidate_key.public_key())
+        {
+            return error::KeyPublicDuplicateSnafu {
+                key_id: hex::encode(existing),
+            }
+            .fail();
+        }
         let key_id = key.key_id().context(error::KeyIdSnafu)?;
         ensure!(
             !root.keys.contains_key(&key_id),
@@ -461,6 +751,7 @@ fn add_key(root: &mut Root, role: &[RoleType], key: Key) -> Result<Decoded<Hex>>
         let entry = root.roles.entry(*r).or_insert_with(|| role_keys!());
         if !entry.keyids.contains(&key_id) {
             entry.keyids.push(key_id.clone());
+            entry.keyids.sort();
         }
     }
 
@@ -0,0 +1,67 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! The `serve` module serves a repository written by `tuftool` over HTTP with `tough-axum`, for
+//! previewing it with a client. The server is meant for local development only.
+
+use crate::error::{self, Result};
+use axum::http::Request;
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use clap::Parser;
+use snafu::ResultExt;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::time::Duration;
+use tough_axum::RepositoryRouter;
+
+#[derive(Debug, Parser)]
+pub(crate) struct ServeArgs {
+    /// The repository directory, holding the `metadata` and `targets` directories written by
+    /// `tuftool create` or `tuftool update`
+    #[arg(long)]
+    repo_dir: PathBuf,
+
+    /// The address to listen on; use port 0 to pick a free port
+    #[arg(long, default_value = "127.0.0.1:8081")]
+    addr: SocketAddr,
+
+    /// Delay every response by this many milliseconds, to simulate a slow server
+    #[arg(long, default_value = "0")]
+    latency_ms: u64,
+}
+
+impl ServeArgs {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let listener =
+            TcpListener::bind(self.addr).context(error::ServeBindSnafu { addr: self.addr })?;
+        let addr = listener
+            .local_addr()
+            .context(error::ServeBindSnafu { addr: self.addr })?;
+        // Printed rather than logged so that scripts can read the URLs from stdout
+        println!("metadata: http://{addr}/metadata/");
+        println!("targets: http://{addr}/targets/");
+
+        let latency = Duration::from_millis(self.latency_ms);
+        let router = RepositoryRouter::from_dir(self.repo_dir.join("metadata"))
+            .targets_dir(self.repo_dir.join("targets"))
+            .into_router()
+            .layer(middleware::from_fn_with_state(latency, delay));
+        axum::Server::from_tcp(listener)
+            .map_err(Into::into)
+            .context(error::ServeSnafu)?
+            .serve(router.into_make_service())
+            .await
+            .map_err(Into::into)
+            .context(error::ServeSnafu)
+    }
+}
+
+/// Delays a response by the configured latency.
+async fn delay<B>(
+    axum::extract::State(latency): axum::extract::State<Duration>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    tokio::time::sleep(latency).await;
+    next.run(request).await
+}
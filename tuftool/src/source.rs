@@ -9,7 +9,14 @@
 //! This module parses a key source command line parameter as a URL, relative to `file://$PWD`,
 //! then matches the URL scheme against ones we understand.
 //!
-//! Currently supported key sources are local files and AWS SSM.
+//! Currently supported key sources are local files, AWS SSM, AWS Secrets Manager, AWS KMS, and
+//! Keys in Google Cloud KMS are referred to by the resource name of a key version, and keys in
+//! Azure Key Vault by vault host, key name and, optionally, version. The access token for each
+//! service is read from GOOGLE_OAUTH_ACCESS_TOKEN or AZURE_ACCESS_TOKEN:
+//! "gcp-kms:///projects/p/locations/global/keyRings/tuf/cryptoKeys/root/cryptoKeyVersions/1"
+//! "azure-kv://my-vault.vault.azure.net/keys/tuf-root"
+//!
+//! Ed25519 keys held by an SSH agent.
 //!
 //! Examples of currently supported formats:
 //!
@@ -32,18 +39,52 @@
 //!
 //! You may also skip the profile bit and just use your local environment's default profile:
 //! "aws-ssm:///a/key" (notice the 3 slashes after the colon)
+//!
+//! Keys stored in AWS Secrets Manager are referred to by profile and secret name or ARN:
+//! "aws-secretsmanager://<aws profile>/tuf/root-key"
+//!
+//! "version-id" or "version-stage" select a version of the secret to read (the default is the
+//! AWSCURRENT version), and "kms-key-id" is used when writing the key:
+//! "aws-secretsmanager:///tuf/root-key?version-stage=AWSPREVIOUS"
+//!
+//! Keys stored in AWS KMS are referred to by profile and key ID or alias:
+//! "aws-kms://<aws profile>/alias/tuf-root"
+//!
+//! KMS keys sign with RSASSA-PSS unless "signing-algorithm" names another algorithm. ECC_NIST_P256
+//! keys need "signing-algorithm=ecdsa-sha256":
+//! "aws-kms:///alias/tuf-root?signing-algorithm=ecdsa-sha256"
+//!
+//! Keys in Google Cloud KMS are referred to by the resource name of a key version, and keys in
+//! Azure Key Vault by vault host, key name and, optionally, version. The access token for each
+//! service is read from GOOGLE_OAUTH_ACCESS_TOKEN or AZURE_ACCESS_TOKEN:
+//! "gcp-kms:///projects/p/locations/global/keyRings/tuf/cryptoKeys/root/cryptoKeyVersions/1"
+//! "azure-kv://my-vault.vault.azure.net/keys/tuf-root"
+//!
+//! Ed25519 keys held by an SSH agent are reached through SSH_AUTH_SOCK, or the "socket" parameter,
+//! and picked by their comment or TUF key ID with the "key" parameter. Without "key", the agent
+//! must hold exactly one Ed25519 key:
+//! "ssh-agent://?key=release-signing"
+//! "ssh-agent://?socket=/run/user/1000/agent.sock"
+//!
+//! When tuftool is built with the "pkcs11" feature, keys held in a PKCS#11 token, such as an HSM,
+//! are referred to by the path to the token's PKCS#11 module and the label of the key pair. "slot"
+//! picks the slot that holds the token (the default is the first slot with a token), and the user
+//! PIN is read from PKCS11_PIN:
+//! "pkcs11:///usr/lib/softhsm/libsofthsm2.so?slot=0&key-label=tuf-root"
 
 use crate::error::{self, Result};
 use snafu::ResultExt;
 use std::path::PathBuf;
+#[cfg(unix)]
+use tough::key_source::SshAgentKeySource;
 use tough::key_source::{KeySource, LocalKeySource};
-use tough_kms::{KmsKeySource, KmsSigningAlgorithm};
-use tough_ssm::SsmKeySource;
+use tough_kms::{AzureKeyVaultKeySource, GcpKmsKeySource, KmsKeySource, KmsSigningAlgorithm};
+use tough_ssm::{SecretsManagerKeySource, SsmKeySource};
 use url::Url;
 
 /// Parses a user-specified source of signing keys.
 /// Sources are passed to `tuftool` as arguments in string format:
-/// "file:///..." or "./a/path/here" or "aws-ssm://...". See above
+/// "file:///..." or "./a/path/here" or "aws-ssm://..." or "aws-secretsmanager://...". See above
 /// doc comment for more info on the appropriate format.
 ///
 /// Users are welcome to add their own sources of keys by implementing
@@ -75,23 +116,47 @@ pub(crate) fn parse_key_source(input: &str) -> Result<Box<dyn KeySource>> {
                         }
                     }),
                 })),
-                "aws-kms" => Ok(Box::new(KmsKeySource {
-                    profile: url.host_str().and_then(|s| {
-                        if s.is_empty() {
-                            None
-                        } else {
-                            Some(s.to_owned())
-                        }
-                    }),
-                    // remove first '/' from the path to get the key_id
-                    key_id: if url.path().is_empty() {
-                        String::new()
-                    } else {
-                        url.path()[1..].to_string()
-                    },
-                    client: None,
-                    signing_algorithm: KmsSigningAlgorithm::RsassaPssSha256,
+                #[cfg(any(feature = "aws-sdk-rust", feature = "aws-sdk-rust-rustls"))]
+                "aws-secretsmanager" => {
+                    let query = |name: &str| {
+                        url.query_pairs()
+                            .find_map(|(k, v)| (k == name).then(|| v.into_owned()))
+                    };
+                    Ok(Box::new(SecretsManagerKeySource {
+                        profile: url
+                            .host_str()
+                            .filter(|s| !s.is_empty())
+                            .map(ToOwned::to_owned),
+                        // remove first '/' from the path to get the secret ID
+                        secret_id: url.path().trim_start_matches('/').to_owned(),
+                        version_id: query("version-id"),
+                        version_stage: query("version-stage"),
+                        kms_key_id: query("kms-key-id"),
+                    }))
+                }
+                "aws-kms" => Ok(Box::new(kms_key_source(&url)?)),
+                "gcp-kms" => Ok(Box::new(GcpKmsKeySource {
+                    key_version: url.path().trim_start_matches('/').to_owned(),
+                    access_token: access_token(&url, "GOOGLE_OAUTH_ACCESS_TOKEN")?,
+                    endpoint: None,
                 })),
+                "azure-kv" => Ok(Box::new(AzureKeyVaultKeySource {
+                    key_id: format!("https://{}{}", url.host_str().unwrap_or(""), url.path()),
+                    access_token: access_token(&url, "AZURE_ACCESS_TOKEN")?,
+                })),
+                #[cfg(unix)]
+                "ssh-agent" => {
+                    let query = |name: &str| {
+                        url.query_pairs()
+                            .find_map(|(k, v)| (k == name).then(|| v.into_owned()))
+                    };
+                    Ok(Box::new(SshAgentKeySource {
+                        socket: query("socket").map(PathBuf::from),
+                        key: query("key"),
+                    }))
+                }
+                #[cfg(feature = "pkcs11")]
+                "pkcs11" => Ok(Box::new(pkcs11_key_source(&url)?)),
                 _ => error::UnrecognizedSchemeSnafu {
                     scheme: url.scheme(),
                 }
@@ -101,6 +166,82 @@ pub(crate) fn parse_key_source(input: &str) -> Result<Box<dyn KeySource>> {
     }
 }
 
+/// Parses a user-specified key source, returning its path if it refers to a local file.
+pub(crate) fn local_key_path(input: &str) -> Result<Option<PathBuf>> {
+    match parse_path_or_url(input)? {
+        PathOrUrl::Path(path) => Ok(Some(path)),
+        PathOrUrl::Url(_) => Ok(None),
+    }
+}
+
+/// Parses a user-specified key source, returning the `KmsKeySource` if it refers to AWS KMS.
+/// This lets commands reach KMS-specific functionality, such as `KmsKeySource::validate`, that
+/// isn't part of the `KeySource` trait.
+pub(crate) fn parse_kms_key_source(input: &str) -> Result<Option<KmsKeySource>> {
+    match parse_path_or_url(input)? {
+        PathOrUrl::Url(url) if url.scheme() == "aws-kms" => Ok(Some(kms_key_source(&url)?)),
+        _ => Ok(None),
+    }
+}
+
+fn kms_key_source(url: &Url) -> Result<KmsKeySource> {
+    let signing_algorithm = match url
+        .query_pairs()
+        .find_map(|(k, v)| (k == "signing-algorithm").then_some(v))
+        .as_deref()
+    {
+        None | Some("rsassa-pss-sha256") => KmsSigningAlgorithm::RsassaPssSha256,
+        Some("ecdsa-sha256") => KmsSigningAlgorithm::EcdsaSha256,
+        Some(algorithm) => {
+            return error::UnrecognizedSigningAlgorithmSnafu { algorithm }.fail();
+        }
+    };
+    Ok(KmsKeySource {
+        profile: url.host_str().and_then(|s| {
+            if s.is_empty() {
+                None
+            } else {
+                Some(s.to_owned())
+            }
+        }),
+        // remove first '/' from the path to get the key_id
+        key_id: if url.path().is_empty() {
+            String::new()
+        } else {
+            url.path()[1..].to_string()
+        },
+        client: None,
+        signing_algorithm,
+    })
+}
+
+/// Reads the access token for a cloud key service from the environment variable `var`.
+#[cfg(feature = "pkcs11")]
+fn pkcs11_key_source(url: &Url) -> Result<tough_pkcs11::Pkcs11KeySource> {
+    use snafu::OptionExt;
+
+    let query = |name: &str| {
+        url.query_pairs()
+            .find_map(|(k, v)| (k == name).then(|| v.into_owned()))
+    };
+    Ok(tough_pkcs11::Pkcs11KeySource {
+        module: PathBuf::from(url.path()),
+        slot: query("slot")
+            .map(|slot| slot.parse().context(error::Pkcs11SlotParseSnafu { slot }))
+            .transpose()?,
+        pin: std::env::var("PKCS11_PIN").ok(),
+        key_label: query("key-label")
+            .context(error::Pkcs11KeyLabelMissingSnafu { url: url.as_str() })?,
+    })
+}
+
+fn access_token(url: &Url, var: &'static str) -> Result<String> {
+    std::env::var(var).context(error::AccessTokenMissingSnafu {
+        scheme: url.scheme(),
+        var,
+    })
+}
+
 /// The `Url` crate does not handle relative file paths. We will only use `Url`` for known schemes.
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 enum PathOrUrl {
@@ -114,7 +255,14 @@ fn parse_path_or_url(s: &str) -> Result<PathOrUrl> {
         Ok(PathOrUrl::Path(PathBuf::from(
             s.chars().skip(7).collect::<String>(),
         )))
-    } else if s.starts_with("aws-ssm://") | s.starts_with("aws-kms://") {
+    } else if s.starts_with("aws-ssm://")
+        | s.starts_with("aws-kms://")
+        | s.starts_with("aws-secretsmanager://")
+        | s.starts_with("gcp-kms://")
+        | s.starts_with("azure-kv://")
+        | s.starts_with("ssh-agent://")
+        | s.starts_with("pkcs11://")
+    {
         // One of our know-supported schemes, parse as a Url.
         Ok(PathOrUrl::Url(
             Url::parse(s).context(error::UrlParseSnafu { url: s })?,
@@ -220,3 +368,63 @@ fn test_parse_path_or_url_path_13() {
     let actual = parse_path_or_url(input).unwrap();
     assert_eq!(expected, actual);
 }
+
+#[test]
+fn test_parse_path_or_url_path_14() {
+    let input = "aws-secretsmanager:///tuf/root-key?version-stage=AWSPREVIOUS";
+    let expected = PathOrUrl::Url(Url::parse(input).unwrap());
+    let actual = parse_path_or_url(input).unwrap();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn test_parse_path_or_url_path_15() {
+    let input = "ssh-agent://?key=release";
+    let expected = PathOrUrl::Url(Url::parse(input).unwrap());
+    let actual = parse_path_or_url(input).unwrap();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn test_parse_path_or_url_path_16() {
+    for input in [
+        "gcp-kms:///projects/p/locations/global/keyRings/tuf/cryptoKeys/root/cryptoKeyVersions/1",
+        "azure-kv://my-vault.vault.azure.net/keys/tuf-root",
+    ] {
+        let expected = PathOrUrl::Url(Url::parse(input).unwrap());
+        let actual = parse_path_or_url(input).unwrap();
+        assert_eq!(expected, actual);
+    }
+}
+
+#[test]
+fn test_parse_kms_signing_algorithm() {
+    let key = parse_kms_key_source("aws-kms://profile/alias/root")
+        .unwrap()
+        .unwrap();
+    assert_eq!(key.key_id, "alias/root");
+    assert_eq!(key.signing_algorithm, KmsSigningAlgorithm::RsassaPssSha256);
+    let key = parse_kms_key_source("aws-kms:///alias/root?signing-algorithm=ecdsa-sha256")
+        .unwrap()
+        .unwrap();
+    assert_eq!(key.key_id, "alias/root");
+    assert_eq!(key.signing_algorithm, KmsSigningAlgorithm::EcdsaSha256);
+    assert!(parse_kms_key_source("aws-kms:///alias/root?signing-algorithm=md5").is_err());
+}
+
+#[cfg(feature = "pkcs11")]
+#[test]
+fn test_pkcs11_key_source() {
+    let url =
+        Url::parse("pkcs11:///usr/lib/softhsm/libsofthsm2.so?slot=3&key-label=tuf-root").unwrap();
+    let source = pkcs11_key_source(&url).unwrap();
+    assert_eq!(
+        source.module,
+        PathBuf::from("/usr/lib/softhsm/libsofthsm2.so")
+    );
+    assert_eq!(source.slot, Some(3));
+    assert_eq!(source.key_label, "tuf-root");
+
+    let url = Url::parse("pkcs11:///usr/lib/softhsm/libsofthsm2.so?slot=3").unwrap();
+    assert!(pkcs11_key_source(&url).is_err());
+}
@@ -0,0 +1,223 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::{format_table, UNUSED_URL};
+use crate::error::{self, Result};
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use serde::Serialize;
+use snafu::ResultExt;
+use std::path::PathBuf;
+use tough::schema::{Delegations, Role, Root, SignatureReport, Signed, Targets};
+use tough::{ExpirationEnforcement, Repository, RepositoryLoader};
+use url::Url;
+
+/// Print each role's version, expiration and signatures, as a quick health check
+#[derive(Debug, Parser)]
+pub(crate) struct StatusArgs {
+    /// Path to root.json file for the repository
+    #[arg(short, long)]
+    root: PathBuf,
+
+    /// TUF repository metadata base URL
+    #[arg(short, long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// The report printed by `tuftool status`.
+#[derive(Debug, Serialize)]
+struct Status {
+    consistent_snapshot: bool,
+    /// The repository's release fingerprint, which identifies the versions of all its roles.
+    fingerprint: String,
+    roles: Vec<RoleStatus>,
+}
+
+/// The status of one role.
+#[derive(Debug, Serialize)]
+struct RoleStatus {
+    role: String,
+    version: u64,
+    expires: DateTime<Utc>,
+    /// Whole days until the role expires, negative once it has expired.
+    days_until_expiry: i64,
+    /// The number of the role's keys that validly signed it.
+    signatures: usize,
+    /// The number of keys the role has.
+    keys: usize,
+    threshold: u64,
+    threshold_met: bool,
+}
+
+impl RoleStatus {
+    fn new(
+        role: String,
+        version: u64,
+        expires: DateTime<Utc>,
+        report: &SignatureReport,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            role,
+            version,
+            expires,
+            days_until_expiry: (expires - now).num_days(),
+            signatures: report.signed.len(),
+            keys: report.signed.len() + report.missing.len(),
+            threshold: report.threshold.get(),
+            threshold_met: report.is_satisfied(),
+        }
+    }
+}
+
+impl StatusArgs {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let repository = self.load().await?;
+        let status = status(&repository, Utc::now())?;
+        if self.json {
+            let stdout = PathBuf::from("<stdout>");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&status)
+                    .context(error::FileWriteJsonSnafu { path: &stdout })?
+            );
+        } else {
+            print!("{}", table(&status));
+        }
+        Ok(())
+    }
+
+    /// Loads the repository, allowing expired metadata so that expired roles are reported rather
+    /// than failing the load.
+    async fn load(&self) -> Result<Repository> {
+        let root = tokio::fs::read(&self.root)
+            .await
+            .context(error::OpenRootSnafu { path: &self.root })?;
+        let targets_base_url = Url::parse(UNUSED_URL).with_context(|_| error::UrlParseSnafu {
+            url: UNUSED_URL.to_owned(),
+        })?;
+        RepositoryLoader::new(&root, self.metadata_base_url.clone(), targets_base_url)
+            .expiration_enforcement(ExpirationEnforcement::Unsafe)
+            .load()
+            .await
+            .context(error::RepoLoadSnafu)
+    }
+}
+
+/// The status of the top-level roles, followed by each loaded delegated role, however deeply
+/// it's delegated.
+fn status(repository: &Repository, now: DateTime<Utc>) -> Result<Status> {
+    let root = &repository.root().signed;
+    let mut roles = vec![
+        top_level(root, repository.root(), now)?,
+        top_level(root, repository.timestamp(), now)?,
+        top_level(root, repository.snapshot(), now)?,
+        top_level(root, repository.targets(), now)?,
+    ];
+    if let Some(delegations) = &repository.targets().signed.delegations {
+        push_delegated(delegations, now, &mut roles)?;
+    }
+    Ok(Status {
+        consistent_snapshot: root.consistent_snapshot,
+        fingerprint: repository.fingerprint().to_owned(),
+        roles,
+    })
+}
+
+/// The status of a top-level role, whose keys root.json lists.
+fn top_level<T: Role + Serialize>(
+    root: &Root,
+    signed: &Signed<T>,
+    now: DateTime<Utc>,
+) -> Result<RoleStatus> {
+    let role = T::TYPE.to_string();
+    let report = root
+        .signature_report(signed)
+        .context(error::RoleSignatureReportSnafu { role: &role })?;
+    Ok(RoleStatus::new(
+        role,
+        signed.signed.version().get(),
+        signed.signed.expires(),
+        &report,
+        now,
+    ))
+}
+
+/// Adds the status of each loaded role that `delegations` delegates to, and of the roles they
+/// delegate to in turn.
+fn push_delegated(
+    delegations: &Delegations,
+    now: DateTime<Utc>,
+    roles: &mut Vec<RoleStatus>,
+) -> Result<()> {
+    for delegated in &delegations.roles {
+        let Some(targets) = &delegated.targets else {
+            continue;
+        };
+        roles.push(delegated_role(delegations, &delegated.name, targets, now)?);
+        if let Some(delegations) = &targets.signed.delegations {
+            push_delegated(delegations, now, roles)?;
+        }
+    }
+    Ok(())
+}
+
+/// The status of the delegated role `name`, whose keys `delegations` lists.
+fn delegated_role(
+    delegations: &Delegations,
+    name: &str,
+    targets: &Signed<Targets>,
+    now: DateTime<Utc>,
+) -> Result<RoleStatus> {
+    let report = delegations
+        .signature_report(targets, name)
+        .context(error::RoleSignatureReportSnafu { role: name })?;
+    Ok(RoleStatus::new(
+        name.to_owned(),
+        targets.signed.version.get(),
+        targets.signed.expires,
+        &report,
+        now,
+    ))
+}
+
+fn table(status: &Status) -> String {
+    let mut rows = vec![[
+        "ROLE",
+        "VERSION",
+        "EXPIRES",
+        "DAYS LEFT",
+        "SIGNATURES",
+        "THRESHOLD",
+    ]
+    .map(str::to_owned)
+    .to_vec()];
+    for role in &status.roles {
+        rows.push(vec![
+            role.role.clone(),
+            role.version.to_string(),
+            role.expires.to_rfc3339(),
+            role.days_until_expiry.to_string(),
+            format!("{} of {} keys", role.signatures, role.keys),
+            format!(
+                "{} ({})",
+                role.threshold,
+                if role.threshold_met { "met" } else { "not met" }
+            ),
+        ]);
+    }
+    format!(
+        "Consistent snapshot: {}\nRelease fingerprint: {}\n\n{}",
+        if status.consistent_snapshot {
+            "yes"
+        } else {
+            "no"
+        },
+        status.fingerprint,
+        format_table(&rows)
+    )
+}
@@ -0,0 +1,217 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::{format_table, load_metadata_repo};
+use crate::download::build_glob_set;
+use crate::error::{self, Result};
+use clap::Parser;
+use serde::Serialize;
+use serde_json::Value;
+use snafu::ResultExt;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tough::Repository;
+use url::Url;
+
+/// How many hex digits of the sha256 digest the table shows.
+const SHA256_PREFIX_LEN: usize = 12;
+
+#[derive(Debug, Parser)]
+pub(crate) enum Command {
+    /// List the targets of a repository, with the role that lists each one
+    List(ListArgs),
+}
+
+impl Command {
+    pub(crate) async fn run(self) -> Result<()> {
+        match self {
+            Command::List(args) => args.run().await,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+pub(crate) struct ListArgs {
+    /// Path to root.json file for the repository
+    #[arg(short, long)]
+    root: PathBuf,
+
+    /// TUF repository metadata base URL
+    #[arg(short, long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// Only list targets whose names match this glob, e.g. "*.tar.gz" (may be repeated)
+    #[arg(long = "filter")]
+    filters: Vec<String>,
+
+    /// Add a column for this `custom` field, a key such as "arch" or a JSON pointer such as
+    /// "/release/channel" (may be repeated)
+    #[arg(short, long = "custom")]
+    custom_fields: Vec<String>,
+
+    /// Output format; the table shows a prefix of each sha256 digest, while json and csv show it
+    /// in full
+    #[arg(long, value_enum, default_value = "table")]
+    format: Format,
+}
+
+/// The output formats of `targets list`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+/// A target as `targets list` prints it.
+#[derive(Debug, Serialize)]
+struct Listing {
+    name: String,
+    length: u64,
+    sha256: String,
+    role: String,
+    custom: BTreeMap<String, Value>,
+}
+
+impl ListArgs {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let repository = load_metadata_repo(&self.root, self.metadata_base_url.clone()).await?;
+        let listings = self.listings(&repository)?;
+        match self.format {
+            Format::Table => print!("{}", self.table(&listings)),
+            Format::Csv => print!("{}", self.csv(&listings)),
+            Format::Json => {
+                let stdout = PathBuf::from("<stdout>");
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&listings)
+                        .context(error::FileWriteJsonSnafu { path: &stdout })?
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Lists each target that a client would read, under the role whose listing it would use,
+    /// sorted by name.
+    fn listings(&self, repository: &Repository) -> Result<Vec<Listing>> {
+        let globs = build_glob_set(&self.filters)?;
+        let mut roles = vec!["targets".to_owned()];
+        roles.extend(
+            repository
+                .targets()
+                .signed
+                .role_names()
+                .into_iter()
+                .cloned(),
+        );
+        let mut listings = Vec::new();
+        for role in roles {
+            // A delegated role whose metadata wasn't loaded lists nothing.
+            let Ok(targets) = repository.role_targets(&role) else {
+                continue;
+            };
+            for (name, target) in targets {
+                if globs
+                    .as_ref()
+                    .is_some_and(|globs| !globs.is_match(name.raw()))
+                {
+                    continue;
+                }
+                let custom = self
+                    .custom_fields
+                    .iter()
+                    .map(|field| {
+                        let value = target.custom_value(&pointer(field)).cloned();
+                        (field.clone(), value.unwrap_or(Value::Null))
+                    })
+                    .collect();
+                listings.push(Listing {
+                    name: name.raw().to_owned(),
+                    length: target.length,
+                    sha256: hex::encode(&target.hashes.sha256),
+                    role: role.clone(),
+                    custom,
+                });
+            }
+        }
+        listings.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(listings)
+    }
+
+    /// The header and a row of cells for each listing. Missing custom fields are left empty.
+    fn rows(&self, listings: &[Listing], sha256_len: usize) -> Vec<Vec<String>> {
+        let header = ["NAME", "LENGTH", "SHA256", "ROLE"]
+            .iter()
+            .map(|column| (*column).to_owned())
+            .chain(self.custom_fields.iter().cloned())
+            .collect();
+        let rows = listings.iter().map(|listing| {
+            let mut row = vec![
+                listing.name.clone(),
+                listing.length.to_string(),
+                listing.sha256.chars().take(sha256_len).collect(),
+                listing.role.clone(),
+            ];
+            row.extend(listing.custom.values().map(cell));
+            row
+        });
+        std::iter::once(header).chain(rows).collect()
+    }
+
+    fn table(&self, listings: &[Listing]) -> String {
+        format_table(&self.rows(listings, SHA256_PREFIX_LEN))
+    }
+
+    fn csv(&self, listings: &[Listing]) -> String {
+        let mut out = String::new();
+        for row in self.rows(listings, usize::MAX) {
+            let line: Vec<String> = row.iter().map(|cell| csv_field(cell)).collect();
+            out.push_str(&line.join(","));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// The JSON pointer for a `--custom` field: pointers are used as they are, and other fields name
+/// a key of `custom`.
+fn pointer(field: &str) -> String {
+    if field.starts_with('/') {
+        field.to_owned()
+    } else {
+        format!("/{}", field.replace('~', "~0").replace('/', "~1"))
+    }
+}
+
+/// Shows strings as they are and other JSON values as compact JSON.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Quotes `field` if it holds a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[test]
+fn custom_field_pointers() {
+    assert_eq!(pointer("arch"), "/arch");
+    assert_eq!(pointer("a/b"), "/a~1b");
+    assert_eq!(pointer("/release/channel"), "/release/channel");
+}
+
+#[test]
+fn csv_fields_are_quoted() {
+    assert_eq!(csv_field("plain"), "plain");
+    assert_eq!(csv_field("a,b"), "\"a,b\"");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+}
@@ -6,11 +6,13 @@ use crate::error::{self, Result};
 use crate::source::parse_key_source;
 use chrono::{DateTime, Utc};
 use clap::Parser;
+use log::warn;
 use snafu::ResultExt;
 use std::num::NonZeroU64;
 use std::path::{Path, PathBuf};
 use tough::editor::RepositoryEditor;
-use tough::{ExpirationEnforcement, RepositoryLoader};
+use tough::schema::{Root, Signed};
+use tough::{ExpirationEnforcement, Prefix, RepositoryLoader};
 use url::Url;
 
 #[derive(Debug, Parser)]
@@ -19,6 +21,12 @@ pub(crate) struct TransferMetadataArgs {
     #[arg(long)]
     allow_expired_repo: bool,
 
+    /// Copy the transferred targets from the current repository into `<outdir>/targets`, named
+    /// for the new root's consistent snapshot setting. Use this when the new root changes that
+    /// setting, since existing target file names won't match the new layout.
+    #[arg(long)]
+    copy_targets: bool,
+
     /// Key file to sign with
     #[arg(short, long = "key", required = true)]
     keys: Vec<String>,
@@ -134,6 +142,35 @@ impl TransferMetadataArgs {
 
         let signed_repo = editor.sign(&keys).await.context(error::SignRepoSnafu)?;
 
+        let was_consistent = current_repo.root().signed.consistent_snapshot;
+        let consistent = crate::load_file::<Signed<Root>>(new_root)
+            .await?
+            .signed
+            .consistent_snapshot;
+        if self.copy_targets {
+            // Fetching through the current repository verifies each target as it is copied.
+            let targets_dir = self.outdir.join("targets");
+            tokio::fs::create_dir_all(&targets_dir)
+                .await
+                .context(error::DirCreateSnafu { path: &targets_dir })?;
+            let prefix = if consistent {
+                Prefix::Digest
+            } else {
+                Prefix::None
+            };
+            for target_name in targets.signed.targets.keys() {
+                current_repo
+                    .save_target(target_name, &targets_dir, prefix)
+                    .await
+                    .context(error::MetadataSnafu)?;
+            }
+        } else if was_consistent != consistent {
+            warn!(
+                "consistent_snapshot changes from {was_consistent} to {consistent}; target files must be renamed to \
+                match, which --copy-targets does"
+            );
+        }
+
         let metadata_dir = &self.outdir.join("metadata");
         signed_repo
             .write(metadata_dir)
@@ -2,30 +2,86 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::build_targets;
-use crate::common::UNUSED_URL;
-use crate::datetime::parse_datetime;
+use crate::common::write_metadata;
+use crate::common::{ExpireInArgs, UNUSED_URL};
+use crate::datetime::{parse_datetime, parse_duration};
 use crate::error::{self, Result};
 use crate::source::parse_key_source;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use clap::Parser;
 use snafu::{OptionExt, ResultExt};
 use std::num::{NonZeroU64, NonZeroUsize};
 use std::path::{Path, PathBuf};
-use tough::editor::signed::PathExists;
+use tough::editor::signed::{MetadataStyle, PathExists};
 use tough::editor::RepositoryEditor;
-use tough::{ExpirationEnforcement, RepositoryLoader};
+use tough::schema::RoleType;
+use tough::{ExpirationEnforcement, FilesystemTransport, Repository, RepositoryLoader};
 use url::Url;
 
 #[derive(Debug, Parser)]
+#[allow(clippy::struct_excessive_bools)]
 pub(crate) struct UpdateArgs {
     /// Allow repo download for expired metadata
     #[arg(long)]
     allow_expired_repo: bool,
 
+    /// Sign even if the snapshot.json or timestamp.json version isn't greater than the current
+    /// one, which clients will reject; only a warning is printed
+    #[arg(long)]
+    allow_version_regression: bool,
+
+    /// Compute any expiration that isn't given explicitly from the automatic policy, which is set
+    /// with `--auto-expire-targets`, `--auto-expire-snapshot` and `--auto-expire-timestamp`
+    #[arg(long)]
+    auto_expire: bool,
+
+    /// How far ahead `--auto-expire` sets the expiration of snapshot.json, such as '7d'
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        default_value = "1w",
+        requires = "auto_expire"
+    )]
+    auto_expire_snapshot: TimeDelta,
+
+    /// How far ahead `--auto-expire` sets the expiration of targets.json, such as '4w'
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        default_value = "4w",
+        requires = "auto_expire"
+    )]
+    auto_expire_targets: TimeDelta,
+
+    /// How far ahead `--auto-expire` sets the expiration of timestamp.json, such as '1d'
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        default_value = "1d",
+        requires = "auto_expire"
+    )]
+    auto_expire_timestamp: TimeDelta,
+
+    #[command(flatten)]
+    expire_in: ExpireInArgs,
+
+    /// Compute any version that isn't given explicitly by incrementing the current version found
+    /// in the loaded repository
+    #[arg(long)]
+    auto_version: bool,
+
     /// Follow symbolic links in the given directory when adding targets
     #[arg(short, long)]
     follow: bool,
 
+    /// Also write a gzip-compressed `.json.gz` copy of each metadata file, and a
+    /// `gzip-manifest.json` listing their lengths and hashes
+    #[arg(long)]
+    gzip_metadata: bool,
+
     /// Incoming metadata from delegatee
     #[arg(short, long = "incoming-metadata")]
     indir: Option<Url>,
@@ -42,9 +98,14 @@ pub(crate) struct UpdateArgs {
     #[arg(short, long = "key", required = true)]
     keys: Vec<String>,
 
+    /// How to write the targets, snapshot and timestamp metadata: "pretty", indented with a
+    /// trailing newline, or "compact", without whitespace, which keeps large targets metadata small
+    #[arg(long, default_value = "pretty")]
+    metadata_style: MetadataStyle,
+
     /// TUF repository metadata base URL
-    #[arg(short, long = "metadata-url")]
-    metadata_base_url: Url,
+    #[arg(short, long = "metadata-url", required_unless_present = "repo_dir")]
+    metadata_base_url: Option<Url>,
 
     /// The directory where the updated repository will be written
     #[arg(short, long)]
@@ -54,18 +115,28 @@ pub(crate) struct UpdateArgs {
     #[arg(short, long)]
     root: PathBuf,
 
+    /// Local copy of the repository to update, such as the output of `tuftool clone` or of an
+    /// earlier `update`; its metadata is read from the `metadata` directory inside it, so no
+    /// metadata URL needs to be reachable
+    #[arg(long, conflicts_with = "metadata_base_url")]
+    repo_dir: Option<PathBuf>,
+
     /// Role of incoming metadata
     #[arg(long)]
     role: Option<String>,
 
     /// Expiration of snapshot.json file; can be in full RFC 3339 format, or something like 'in
     /// 7 days'
-    #[arg(long, value_parser = parse_datetime)]
-    snapshot_expires: DateTime<Utc>,
+    #[arg(
+        long,
+        value_parser = parse_datetime,
+        required_unless_present_any = ["auto_expire", "snapshot_expire_in", "all_expire_in"]
+    )]
+    snapshot_expires: Option<DateTime<Utc>>,
 
     /// Version of snapshot.json file
-    #[arg(long)]
-    snapshot_version: NonZeroU64,
+    #[arg(long, required_unless_present = "auto_version")]
+    snapshot_version: Option<NonZeroU64>,
 
     /// Directory of targets
     #[arg(short, long = "add-targets")]
@@ -79,21 +150,41 @@ pub(crate) struct UpdateArgs {
 
     /// Expiration of targets.json file; can be in full RFC 3339 format, or something like 'in
     /// 7 days'
-    #[arg(long, value_parser = parse_datetime)]
-    targets_expires: DateTime<Utc>,
+    #[arg(
+        long,
+        value_parser = parse_datetime,
+        required_unless_present_any = ["auto_expire", "targets_expire_in", "all_expire_in"]
+    )]
+    targets_expires: Option<DateTime<Utc>>,
 
     /// Version of targets.json file
-    #[arg(long)]
-    targets_version: NonZeroU64,
+    #[arg(long, required_unless_present = "auto_version")]
+    targets_version: Option<NonZeroU64>,
 
     /// Expiration of timestamp.json file; can be in full RFC 3339 format, or something like 'in
     /// 7 days'
-    #[arg(long, value_parser = parse_datetime)]
-    timestamp_expires: DateTime<Utc>,
+    #[arg(
+        long,
+        value_parser = parse_datetime,
+        required_unless_present_any = ["auto_expire", "timestamp_expire_in", "all_expire_in"]
+    )]
+    timestamp_expires: Option<DateTime<Utc>>,
 
     /// Version of timestamp.json file
-    #[arg(long)]
+    #[arg(long, required_unless_present = "auto_version")]
+    timestamp_version: Option<NonZeroU64>,
+}
+
+/// The versions and expirations that will be written for each role, after any `--auto-version`
+/// and `--auto-expire` policy has been applied.
+#[derive(Debug, Clone, Copy)]
+struct RoleUpdates {
+    targets_version: NonZeroU64,
+    targets_expires: DateTime<Utc>,
+    snapshot_version: NonZeroU64,
+    snapshot_expires: DateTime<Utc>,
     timestamp_version: NonZeroU64,
+    timestamp_expires: DateTime<Utc>,
 }
 
 fn expired_repo_warning<P: AsRef<Path>>(path: P) {
@@ -114,26 +205,82 @@ impl UpdateArgs {
         } else {
             ExpirationEnforcement::Safe
         };
-        let repository = RepositoryLoader::new(
-            &tokio::fs::read(&self.root)
-                .await
-                .context(error::OpenRootSnafu { path: &self.root })?,
-            self.metadata_base_url.clone(),
-            Url::parse(UNUSED_URL).context(error::UrlParseSnafu { url: UNUSED_URL })?,
-        )
-        .expiration_enforcement(expiration_enforcement)
-        .load()
-        .await
-        .context(error::RepoLoadSnafu)?;
+        let root = tokio::fs::read(&self.root)
+            .await
+            .context(error::OpenRootSnafu { path: &self.root })?;
+        let targets_base_url =
+            Url::parse(UNUSED_URL).context(error::UrlParseSnafu { url: UNUSED_URL })?;
+        let loader = if let Some(repo_dir) = &self.repo_dir {
+            RepositoryLoader::new(&root, repo_dir_metadata_url(repo_dir)?, targets_base_url)
+                .transport(FilesystemTransport)
+        } else {
+            let metadata_base_url =
+                self.metadata_base_url
+                    .clone()
+                    .context(error::MissingSnafu {
+                        what: "--metadata-url or --repo-dir",
+                    })?;
+            RepositoryLoader::new(&root, metadata_base_url, targets_base_url)
+        };
+        let repository = loader
+            .expiration_enforcement(expiration_enforcement)
+            .load()
+            .await
+            .context(error::RepoLoadSnafu)?;
+        let updates = self.role_updates(&repository)?;
         self.update_metadata(
             RepositoryEditor::from_repo(&self.root, repository)
                 .await
                 .context(error::EditorFromRepoSnafu { path: &self.root })?,
+            updates,
         )
         .await
     }
 
-    async fn update_metadata(&self, mut editor: RepositoryEditor) -> Result<()> {
+    /// Resolves the version and expiration of each role, preferring explicit arguments, then
+    /// relative expirations, and then falling back to the `--auto-version` and `--auto-expire`
+    /// policies.
+    fn role_updates(&self, repository: &Repository) -> Result<RoleUpdates> {
+        let now = Utc::now();
+        Ok(RoleUpdates {
+            targets_version: resolve_version(
+                self.targets_version,
+                repository.targets().signed.version,
+            )?,
+            targets_expires: resolve_expires(
+                self.expire_in
+                    .expires(RoleType::Targets, self.targets_expires, now)?,
+                now,
+                self.auto_expire_targets,
+            )?,
+            snapshot_version: resolve_version(
+                self.snapshot_version,
+                repository.snapshot().signed.version,
+            )?,
+            snapshot_expires: resolve_expires(
+                self.expire_in
+                    .expires(RoleType::Snapshot, self.snapshot_expires, now)?,
+                now,
+                self.auto_expire_snapshot,
+            )?,
+            timestamp_version: resolve_version(
+                self.timestamp_version,
+                repository.timestamp().signed.version,
+            )?,
+            timestamp_expires: resolve_expires(
+                self.expire_in
+                    .expires(RoleType::Timestamp, self.timestamp_expires, now)?,
+                now,
+                self.auto_expire_timestamp,
+            )?,
+        })
+    }
+
+    async fn update_metadata(
+        &self,
+        mut editor: RepositoryEditor,
+        updates: RoleUpdates,
+    ) -> Result<()> {
         let mut keys = Vec::new();
         for source in &self.keys {
             let key_source = parse_key_source(source)?;
@@ -141,14 +288,16 @@ impl UpdateArgs {
         }
 
         editor
-            .targets_version(self.targets_version)
+            .targets_version(updates.targets_version)
             .context(error::DelegationStructureSnafu)?
-            .targets_expires(self.targets_expires)
+            .targets_expires(updates.targets_expires)
             .context(error::DelegationStructureSnafu)?
-            .snapshot_version(self.snapshot_version)
-            .snapshot_expires(self.snapshot_expires)
-            .timestamp_version(self.timestamp_version)
-            .timestamp_expires(self.timestamp_expires);
+            .snapshot_version(updates.snapshot_version)
+            .snapshot_expires(updates.snapshot_expires)
+            .timestamp_version(updates.timestamp_version)
+            .timestamp_expires(updates.timestamp_expires)
+            .allow_version_regression(self.allow_version_regression)
+            .metadata_style(self.metadata_style);
 
         // If the "add-targets" argument was passed, build a list of targets
         // and add them to the repository. If a user specifies job count we
@@ -168,29 +317,17 @@ impl UpdateArgs {
                     .add_target(target_name, target)
                     .context(error::DelegationStructureSnafu)?;
             }
-        };
+        }
 
         // If a `Targets` metadata needs to be updated
-        if self.role.is_some() && self.indir.is_some() {
+        if let (Some(role), Some(indir)) = (&self.role, &self.indir) {
             editor
                 .sign_targets_editor(&keys)
                 .await
                 .context(error::DelegationStructureSnafu)?
-                .update_delegated_targets(
-                    self.role.as_ref().context(error::MissingSnafu {
-                        what: "delegated role",
-                    })?,
-                    self.indir
-                        .as_ref()
-                        .context(error::MissingSnafu {
-                            what: "delegated role metadata url",
-                        })?
-                        .as_str(),
-                )
+                .update_delegated_targets(role, indir.as_str())
                 .await
-                .context(error::DelegateeNotFoundSnafu {
-                    role: self.role.as_ref().unwrap().clone(),
-                })?;
+                .context(error::DelegateeNotFoundSnafu { role: role.clone() })?;
         }
 
         // Sign the repo
@@ -206,17 +343,52 @@ impl UpdateArgs {
                     indir: &targets_indir,
                     outdir: targets_outdir,
                 })?;
-        };
+        }
 
         // Write the metadata to the outdir
         let metadata_dir = &self.outdir.join("metadata");
-        signed_repo
-            .write(metadata_dir)
-            .await
-            .context(error::WriteRepoSnafu {
-                directory: metadata_dir,
-            })?;
+        write_metadata(&signed_repo, metadata_dir, self.gzip_metadata).await?;
 
         Ok(())
     }
 }
+
+/// The `file://` URL of the `metadata` directory in the local repository at `repo_dir`.
+fn repo_dir_metadata_url(repo_dir: &Path) -> Result<Url> {
+    let metadata_dir = std::fs::canonicalize(repo_dir.join("metadata"))
+        .context(error::RepoDirSnafu { path: repo_dir })?;
+    Url::from_directory_path(&metadata_dir)
+        .ok()
+        .context(error::RepoDirUrlSnafu { path: metadata_dir })
+}
+
+/// Uses the explicitly requested version if there is one, otherwise the current version plus one.
+/// Clap guarantees that `--auto-version` was passed if `explicit` is `None`.
+fn resolve_version(explicit: Option<NonZeroU64>, current: NonZeroU64) -> Result<NonZeroU64> {
+    match explicit {
+        Some(version) => Ok(version),
+        None => current
+            .get()
+            .checked_add(1)
+            .and_then(NonZeroU64::new)
+            .context(error::VersionOverflowSnafu),
+    }
+}
+
+/// Uses the requested expiration if there is one, otherwise `auto` from `now`. Clap guarantees
+/// that `--auto-expire` was passed if `requested` is `None`.
+fn resolve_expires(
+    requested: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    auto: TimeDelta,
+) -> Result<DateTime<Utc>> {
+    match requested {
+        Some(expires) => Ok(expires),
+        None => now
+            .checked_add_signed(auto)
+            .context(error::DateArgInvalidSnafu {
+                input: auto.to_string(),
+                msg: "unable to compute an expiration that far ahead",
+            }),
+    }
+}
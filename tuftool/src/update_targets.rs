@@ -23,6 +23,11 @@ pub(crate) struct UpdateTargetsArgs {
     #[arg(short, long, value_parser = parse_datetime)]
     expires: DateTime<Utc>,
 
+    /// Hard-link targets whose contents duplicate an already copied target instead of copying
+    /// them again
+    #[arg(long)]
+    dedup: bool,
+
     /// Follow symbolic links in the given directory when adding targets
     #[arg(short, long)]
     follow: bool,
@@ -103,7 +108,7 @@ impl UpdateTargetsArgs {
                     .add_target(target_name, target)
                     .context(error::InvalidTargetNameSnafu)?;
             }
-        };
+        }
 
         // Sign the role
         let signed_role = editor.sign(&keys).await.context(error::SignRepoSnafu)?;
@@ -111,14 +116,24 @@ impl UpdateTargetsArgs {
         // Copy any targets that were added
         if let Some(ref targets_indir) = self.targets_indir {
             let targets_outdir = &self.outdir.join("targets");
-            signed_role
-                .copy_targets(targets_indir, targets_outdir, self.target_path_exists)
-                .await
-                .context(error::LinkTargetsSnafu {
-                    indir: &targets_indir,
-                    outdir: targets_outdir,
-                })?;
-        };
+            if self.dedup {
+                signed_role
+                    .copy_targets_deduplicated(
+                        targets_indir,
+                        targets_outdir,
+                        self.target_path_exists,
+                    )
+                    .await
+            } else {
+                signed_role
+                    .copy_targets(targets_indir, targets_outdir, self.target_path_exists)
+                    .await
+            }
+            .context(error::LinkTargetsSnafu {
+                indir: &targets_indir,
+                outdir: targets_outdir,
+            })?;
+        }
 
         // Write the metadata to the outdir
         let metadata_dir = &self.outdir.join("metadata");
@@ -0,0 +1,458 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::UNUSED_URL;
+use crate::datetime::parse_duration;
+use crate::error::{self, Result};
+use chrono::{TimeDelta, Utc};
+use clap::Parser;
+use serde::Serialize;
+use snafu::{ensure, ResultExt};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use tough::schema::decoded::{Decoded, Hex};
+use tough::schema::key::Key;
+use tough::schema::{Delegations, Role, RoleKeys, RoleType, Root, Signed, Targets};
+use tough::{
+    DefaultTransport, ExpirationEnforcement, IntoVec, Repository, RepositoryLoader, TargetName,
+    Transport,
+};
+use url::Url;
+
+/// Roles are warned about this long before they expire, unless `--warn-within` says otherwise.
+const DEFAULT_WARN_WITHIN: TimeDelta = TimeDelta::days(7);
+
+/// Check a repository before it's published, printing a JSON report of any problems found and
+/// failing if there are errors
+#[derive(Debug, Parser)]
+pub(crate) struct VerifyArgs {
+    /// Path to root.json file for the repository
+    #[arg(short, long)]
+    root: PathBuf,
+
+    /// TUF repository metadata base URL
+    #[arg(short, long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// TUF repository targets base URL; if given, each target is read and checked against its
+    /// length and hashes
+    #[arg(short, long = "targets-url")]
+    targets_base_url: Option<Url>,
+
+    /// Warn about roles that expire within this long, such as '7d' (the default), or about one
+    /// role with 'ROLE=DURATION', such as 'timestamp=12h'; may be given more than once
+    #[arg(long, value_name = "[ROLE=]DURATION", value_parser = parse_warn_within)]
+    warn_within: Vec<(Option<String>, TimeDelta)>,
+}
+
+/// Parses a `--warn-within` argument.
+fn parse_warn_within(input: &str) -> Result<(Option<String>, TimeDelta)> {
+    Ok(match input.split_once('=') {
+        Some((role, duration)) => (Some(role.to_owned()), parse_duration(duration)?),
+        None => (None, parse_duration(input)?),
+    })
+}
+
+/// How serious a finding is. Only errors fail the check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found with the repository.
+#[derive(Debug, Serialize)]
+struct Finding {
+    /// The check that found the problem, such as `thresholds` or `expiration`.
+    check: &'static str,
+    /// The role the problem is with, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    severity: Severity,
+    message: String,
+}
+
+/// The report printed by `tuftool verify`.
+#[derive(Debug, Default, Serialize)]
+struct Report {
+    passed: bool,
+    errors: usize,
+    warnings: usize,
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    fn add(
+        &mut self,
+        check: &'static str,
+        role: Option<&str>,
+        severity: Severity,
+        message: String,
+    ) {
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+        self.findings.push(Finding {
+            check,
+            role: role.map(str::to_owned),
+            severity,
+            message,
+        });
+    }
+
+    fn error(&mut self, check: &'static str, role: Option<&str>, message: String) {
+        self.add(check, role, Severity::Error, message);
+    }
+
+    fn warning(&mut self, check: &'static str, role: Option<&str>, message: String) {
+        self.add(check, role, Severity::Warning, message);
+    }
+}
+
+impl VerifyArgs {
+    pub(crate) async fn run(&self) -> Result<()> {
+        let mut report = Report::default();
+        match self.load().await? {
+            Ok(repository) => self.check(&repository, &mut report).await,
+            Err(err) => report.error("load", None, err.to_string()),
+        }
+        report.passed = report.errors == 0;
+        let stdout = PathBuf::from("<stdout>");
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report)
+                .context(error::FileWriteJsonSnafu { path: &stdout })?
+        );
+        ensure!(
+            report.passed,
+            error::RepoVerifySnafu {
+                errors: report.errors,
+                warnings: report.warnings,
+            }
+        );
+        Ok(())
+    }
+
+    /// Loads the repository, allowing expired metadata so that expirations are reported as
+    /// findings. The inner result is the outcome of the load itself.
+    async fn load(&self) -> Result<std::result::Result<Repository, tough::error::Error>> {
+        let root = tokio::fs::read(&self.root)
+            .await
+            .context(error::OpenRootSnafu { path: &self.root })?;
+        let targets_base_url = match &self.targets_base_url {
+            Some(url) => url.clone(),
+            None => Url::parse(UNUSED_URL).with_context(|_| error::UrlParseSnafu {
+                url: UNUSED_URL.to_owned(),
+            })?,
+        };
+        Ok(
+            RepositoryLoader::new(&root, self.metadata_base_url.clone(), targets_base_url)
+                .expiration_enforcement(ExpirationEnforcement::Unsafe)
+                .load()
+                .await,
+        )
+    }
+
+    async fn check(&self, repository: &Repository, report: &mut Report) {
+        let root = &repository.root().signed;
+        check_root_thresholds(root, report);
+        check_signatures(root, repository.timestamp(), report);
+        check_signatures(root, repository.snapshot(), report);
+        check_signatures(root, repository.targets(), report);
+        check_signatures(root, repository.root(), report);
+        if let Some(delegations) = &repository.targets().signed.delegations {
+            check_delegations(delegations, report);
+        }
+        self.check_expirations(repository, report);
+        self.check_filenames(repository, report).await;
+        if self.targets_base_url.is_some() {
+            check_targets(repository, report).await;
+        }
+        check_orphans(repository, report);
+    }
+
+    /// How long before `role` expires to warn about it.
+    fn warn_within(&self, role: &str) -> TimeDelta {
+        let find = |wanted: Option<&str>| {
+            self.warn_within
+                .iter()
+                .rev()
+                .find(|(role, _)| role.as_deref() == wanted)
+                .map(|(_, delta)| *delta)
+        };
+        find(Some(role))
+            .or_else(|| find(None))
+            .unwrap_or(DEFAULT_WARN_WITHIN)
+    }
+
+    fn check_expirations(&self, repository: &Repository, report: &mut Report) {
+        let mut expirations = vec![
+            ("root".to_owned(), repository.root().signed.expires),
+            (
+                "timestamp".to_owned(),
+                repository.timestamp().signed.expires,
+            ),
+            ("snapshot".to_owned(), repository.snapshot().signed.expires),
+            ("targets".to_owned(), repository.targets().signed.expires),
+        ];
+        for (name, role) in delegated_roles(&repository.targets().signed) {
+            expirations.push((name.to_owned(), role.signed.expires));
+        }
+
+        let now = Utc::now();
+        for (role, expires) in expirations {
+            if expires <= now {
+                report.error(
+                    "expiration",
+                    Some(&role),
+                    format!("expired {}", expires.to_rfc3339()),
+                );
+            } else if expires <= now + self.warn_within(&role) {
+                report.warning(
+                    "expiration",
+                    Some(&role),
+                    format!("expires {}", expires.to_rfc3339()),
+                );
+            }
+        }
+    }
+
+    /// Checks that the current root can be fetched as `N.root.json`, which is how clients that
+    /// trust an older root find it, whether or not the repository uses consistent snapshots.
+    async fn check_filenames(&self, repository: &Repository, report: &mut Report) {
+        let root = repository.root();
+        let path = format!("{}.root.json", root.signed.version);
+        let message = match self.fetch_metadata(&path).await {
+            Ok(data) => match serde_json::from_slice::<Signed<Root>>(&data) {
+                Ok(fetched) if fetched == *root => return,
+                Ok(fetched) => format!(
+                    "{path} is not the current root; it holds version {}",
+                    fetched.signed.version
+                ),
+                Err(err) => format!("{path} is not valid root metadata: {err}"),
+            },
+            Err(err) => format!("{path} could not be fetched: {err}"),
+        };
+        report.error("filenames", Some("root"), message);
+    }
+
+    async fn fetch_metadata(&self, path: &str) -> std::result::Result<Vec<u8>, String> {
+        let url = self
+            .metadata_base_url
+            .join(path)
+            .map_err(|err| err.to_string())?;
+        let stream = DefaultTransport::new()
+            .fetch(url)
+            .await
+            .map_err(|err| err.to_string())?;
+        stream.into_vec().await.map_err(|err| err.to_string())
+    }
+}
+
+/// Checks that each role that root.json lists has at least as many keys as its threshold, and
+/// that root.json lists each of them.
+fn check_root_thresholds(root: &Root, report: &mut Report) {
+    for role in [
+        RoleType::Root,
+        RoleType::Timestamp,
+        RoleType::Snapshot,
+        RoleType::Targets,
+    ] {
+        match root.roles.get(&role) {
+            Some(keys) => check_threshold(&role.to_string(), keys, &root.keys, report),
+            None => report.error(
+                "thresholds",
+                Some(&role.to_string()),
+                "root.json doesn't list the role".to_owned(),
+            ),
+        }
+    }
+}
+
+/// Checks that `role` can be signed by a threshold of the keys in `keys`.
+fn check_threshold(
+    role: &str,
+    role_keys: &RoleKeys,
+    keys: &HashMap<Decoded<Hex>, Key>,
+    report: &mut Report,
+) {
+    let mut known = BTreeSet::new();
+    for keyid in &role_keys.keyids {
+        if keys.contains_key(keyid) {
+            known.insert(keyid);
+        } else {
+            report.error(
+                "thresholds",
+                Some(role),
+                format!("key ID {} isn't listed", hex::encode(keyid)),
+            );
+        }
+    }
+    if (known.len() as u64) < role_keys.threshold.get() {
+        report.error(
+            "thresholds",
+            Some(role),
+            format!(
+                "threshold {} can't be met by the role's {} key(s)",
+                role_keys.threshold,
+                known.len()
+            ),
+        );
+    }
+}
+
+/// Checks that `signed` is signed by a threshold of the role's keys, and warns about signatures
+/// that don't count towards it.
+fn check_signatures<T: Role + Serialize>(root: &Root, signed: &Signed<T>, report: &mut Report) {
+    let role = T::TYPE.to_string();
+    match root.signature_report(signed) {
+        Ok(signature_report) => {
+            if !signature_report.is_satisfied() {
+                report.error(
+                    "signatures",
+                    Some(&role),
+                    format!(
+                        "signed by {} of {} required keys",
+                        signature_report.signed.len(),
+                        signature_report.threshold
+                    ),
+                );
+            }
+            let unused = signed.signatures.len() - signature_report.signed.len();
+            if unused > 0 {
+                report.warning(
+                    "signatures",
+                    Some(&role),
+                    format!("{unused} signature(s) aren't valid signatures by the role's keys"),
+                );
+            }
+        }
+        Err(err) => report.error("signatures", Some(&role), err.to_string()),
+    }
+}
+
+/// Checks the thresholds and signatures of each delegated role, however deeply it's delegated,
+/// and that each lists only targets in the paths delegated to it.
+fn check_delegations(delegations: &Delegations, report: &mut Report) {
+    for delegated in &delegations.roles {
+        let role = Some(delegated.name.as_str());
+        check_threshold(
+            &delegated.name,
+            &delegated.keys(),
+            &delegations.keys,
+            report,
+        );
+        let Some(targets) = &delegated.targets else {
+            continue;
+        };
+        if let Err(err) = delegations.verify_role(targets, &delegated.name) {
+            report.error("signatures", role, err.to_string());
+        }
+        let mut outside: Vec<&str> = targets
+            .signed
+            .targets
+            .keys()
+            .filter(|name| !delegated.target_is_delegated(name))
+            .map(TargetName::raw)
+            .collect();
+        outside.sort_unstable();
+        for name in outside {
+            report.error(
+                "paths",
+                role,
+                format!("target '{name}' isn't in the paths delegated to the role"),
+            );
+        }
+        if let Some(delegations) = &targets.signed.delegations {
+            check_delegations(delegations, report);
+        }
+    }
+}
+
+/// Reads each target, which fetches it by its consistent snapshot filename if the repository uses
+/// consistent snapshots, and checks it against its listed length and hashes.
+async fn check_targets(repository: &Repository, report: &mut Report) {
+    let names: BTreeSet<&TargetName> = repository.all_targets().map(|(name, _)| name).collect();
+    for name in names {
+        let result = match repository.read_target(name).await {
+            Ok(Some(stream)) => stream.into_vec().await.map(drop),
+            Ok(None) => continue,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            report.error(
+                "targets",
+                None,
+                format!("target '{}' could not be read: {err}", name.raw()),
+            );
+        }
+    }
+}
+
+/// Warns about targets that no client would download, because a role that takes precedence lists
+/// the same name, and about metadata that snapshot.json lists for roles that aren't delegated.
+fn check_orphans(repository: &Repository, report: &mut Report) {
+    let targets = &repository.targets().signed;
+    let mut roles = vec![("targets", targets)];
+    roles.extend(
+        delegated_roles(targets)
+            .into_iter()
+            .map(|(name, role)| (name, &role.signed)),
+    );
+    for (role, listing) in &roles {
+        let mut shadowed: Vec<&str> = listing
+            .targets
+            .iter()
+            .filter(|(name, target)| {
+                targets
+                    .find_target(name)
+                    .is_ok_and(|found| !std::ptr::eq(found, *target))
+            })
+            .map(|(name, _)| name.raw())
+            .collect();
+        shadowed.sort_unstable();
+        for name in shadowed {
+            report.warning(
+                "orphaned-targets",
+                Some(role),
+                format!("target '{name}' is listed by a role that takes precedence"),
+            );
+        }
+    }
+
+    // Older versions of the specification had snapshot.json list root.json too.
+    let known: BTreeSet<String> = roles
+        .iter()
+        .map(|(name, _)| format!("{name}.json"))
+        .chain(std::iter::once("root.json".to_owned()))
+        .collect();
+    let mut orphaned: Vec<&String> = repository
+        .snapshot()
+        .signed
+        .meta
+        .keys()
+        .filter(|file| !known.contains(*file))
+        .collect();
+    orphaned.sort_unstable();
+    for file in orphaned {
+        report.warning(
+            "orphaned-metadata",
+            None,
+            format!("snapshot.json lists {file}, but no role delegates to it"),
+        );
+    }
+}
+
+/// The loaded delegated roles under `targets`, however deeply delegated, with their names.
+fn delegated_roles(targets: &Targets) -> Vec<(&str, &Signed<Targets>)> {
+    let mut roles = Vec::new();
+    for delegated in targets.delegations.iter().flat_map(|d| &d.roles) {
+        if let Some(role) = &delegated.targets {
+            roles.push((delegated.name.as_str(), role));
+            roles.extend(delegated_roles(&role.signed));
+        }
+    }
+    roles
+}
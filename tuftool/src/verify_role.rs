@@ -0,0 +1,116 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::common::load_metadata_repo;
+use crate::error::{self, Result};
+use crate::load_file;
+use chrono::Utc;
+use clap::Parser;
+use snafu::{ensure, OptionExt, ResultExt};
+use std::path::PathBuf;
+use tough::schema::{Signed, Targets};
+use tough::TargetName;
+use url::Url;
+
+#[derive(Debug, Parser)]
+pub(crate) struct VerifyRoleArgs {
+    /// A newly signed copy of the role's metadata to check before submitting it; if not given,
+    /// the role as published at the metadata URL is checked
+    #[arg(long = "role-file")]
+    role_file: Option<PathBuf>,
+
+    /// TUF repository metadata base URL
+    #[arg(short, long = "metadata-url")]
+    metadata_base_url: Url,
+
+    /// Path to root.json file for the repository
+    #[arg(short, long)]
+    root: PathBuf,
+}
+
+impl VerifyRoleArgs {
+    /// Checks the role's metadata against the delegation from its parent and prints a report with
+    /// a line for each check: signatures and threshold, version, paths, and expiration.
+    pub(crate) async fn run(&self, role: &str) -> Result<()> {
+        let repository = load_metadata_repo(&self.root, self.metadata_base_url.clone()).await?;
+        let parent = repository
+            .targets()
+            .signed
+            .parent_of(role)
+            .context(error::DelegatorNotFoundSnafu { role })?;
+        let delegation = parent
+            .roles
+            .iter()
+            .find(|delegated| delegated.name == role)
+            .context(error::MissingSnafu {
+                what: format!("delegation of role '{role}'"),
+            })?;
+        let published = delegation.targets.as_ref();
+        let metadata: Signed<Targets> = match &self.role_file {
+            Some(path) => load_file(path).await?,
+            None => published.cloned().context(error::MissingSnafu {
+                what: format!("metadata for role '{role}'"),
+            })?,
+        };
+
+        let mut failed: usize = 0;
+        let mut report = |check: &str, result: std::result::Result<String, String>| match result {
+            Ok(detail) => println!("  {check}: ok ({detail})"),
+            Err(reason) => {
+                println!("  {check}: FAILED: {reason}");
+                failed += 1;
+            }
+        };
+        println!("{role}");
+
+        report(
+            "signatures",
+            parent
+                .verify_role(&metadata, role)
+                .map(|()| format!("threshold {}", delegation.threshold))
+                .map_err(|err| err.to_string()),
+        );
+
+        let version = metadata.signed.version;
+        report(
+            "version",
+            match (&self.role_file, published) {
+                (Some(_), Some(published)) if version <= published.signed.version => Err(format!(
+                    "version {version} isn't greater than the published version {}",
+                    published.signed.version
+                )),
+                _ => Ok(format!("version {version}")),
+            },
+        );
+
+        let mut outside: Vec<&str> = metadata
+            .signed
+            .targets
+            .keys()
+            .filter(|name| !delegation.target_is_delegated(name))
+            .map(TargetName::raw)
+            .collect();
+        outside.sort_unstable();
+        report(
+            "paths",
+            if outside.is_empty() {
+                Ok(format!("{} target(s)", metadata.signed.targets.len()))
+            } else {
+                Err(format!("not delegated to the role: {}", outside.join(", ")))
+            },
+        );
+
+        let expires = metadata.signed.expires;
+        report(
+            "expiration",
+            if expires > Utc::now() {
+                Ok(format!("expires {}", expires.to_rfc3339()))
+            } else {
+                Err(format!("expired {}", expires.to_rfc3339()))
+            },
+        );
+
+        ensure!(failed == 0, error::RoleVerifySnafu { role, failed });
+        Ok(())
+    }
+}
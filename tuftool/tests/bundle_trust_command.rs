@@ -0,0 +1,47 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+use aws_lc_rs::digest::{digest, SHA256};
+use tempfile::TempDir;
+
+#[test]
+// Ensure the root chain is copied, the current root is printed for embedding, and an expired
+// embedded root is warned about
+fn bundle_trust_rotated_root() {
+    let base = test_utils::test_data().join("rotated-root");
+    let out = TempDir::new().unwrap();
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "bundle-trust",
+            "-r",
+            base.join("1.root.json").to_str().unwrap(),
+            "-m",
+            test_utils::dir_url(&base).as_str(),
+            "-o",
+            out.path().to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    for name in ["1.root.json", "2.root.json"] {
+        assert_eq!(
+            std::fs::read(out.path().join(name)).unwrap(),
+            std::fs::read(base.join(name)).unwrap()
+        );
+    }
+    let current = std::fs::read(base.join("2.root.json")).unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Copied root.json versions 1 through 2"));
+    assert!(stdout.contains(&format!("length: {} bytes", current.len())));
+    assert!(stdout.contains(&format!(
+        "sha256: {}",
+        hex::encode(digest(&SHA256, &current))
+    )));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Warning: the embedded root expires at 1998-01-01"));
+}
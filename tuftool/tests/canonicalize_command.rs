@@ -0,0 +1,54 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn canonicalize(input: &Path, outfile: &Path, signed: bool) {
+    let mut cmd = Command::cargo_bin("tuftool").unwrap();
+    cmd.args([
+        "canonicalize",
+        input.to_str().unwrap(),
+        "-o",
+        outfile.to_str().unwrap(),
+    ]);
+    if signed {
+        cmd.arg("--signed");
+    }
+    cmd.assert().success();
+}
+
+#[test]
+// Ensure formatting differences in the input don't change the canonical output
+fn canonicalize_ignores_formatting() {
+    let outdir = TempDir::new().unwrap();
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let compact_json = outdir.path().join("compact.json");
+    let value: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&root_json).unwrap()).unwrap();
+    std::fs::write(&compact_json, serde_json::to_vec(&value).unwrap()).unwrap();
+
+    let from_pretty = outdir.path().join("from_pretty.json");
+    let from_compact = outdir.path().join("from_compact.json");
+    canonicalize(&root_json, &from_pretty, false);
+    canonicalize(&compact_json, &from_compact, false);
+
+    let canonical = std::fs::read(&from_pretty).unwrap();
+    assert!(canonical.starts_with(b"{\"signatures\":"));
+    assert_eq!(canonical, std::fs::read(&from_compact).unwrap());
+}
+
+#[test]
+// Ensure --signed emits only the signed portion of the role
+fn canonicalize_signed_portion() {
+    let outdir = TempDir::new().unwrap();
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let outfile = outdir.path().join("signed.json");
+    canonicalize(&root_json, &outfile, true);
+
+    let canonical = std::fs::read(&outfile).unwrap();
+    assert!(canonical.starts_with(b"{\"_type\":\"root\""));
+}
@@ -196,3 +196,64 @@ fn clone_full_repo() {
         assert_target_match(&repo_paths.targets_outdir, f)
     }
 }
+
+#[test]
+// Ensure a delta clone keeps unchanged targets, replaces changed ones, and prunes unlisted files
+fn clone_delta_prune() {
+    let repo_paths = RepoPaths::new();
+    let targets_dir = repo_paths.targets_outdir.path();
+    let delta_clone = || {
+        let mut cmd = Command::cargo_bin("tuftool").unwrap();
+        let output = clone_base_command(&mut cmd, &repo_paths)
+            .args([
+                "--targets-url",
+                repo_paths.targets_base_url.as_str(),
+                "--targets-dir",
+                targets_dir.to_str().unwrap(),
+                "--delta",
+                "--prune",
+            ])
+            .assert()
+            .success();
+        String::from_utf8(output.get_output().stdout.clone()).unwrap()
+    };
+
+    assert!(
+        delta_clone().contains("Downloaded 3 targets, kept 0 unchanged targets, pruned 0 files")
+    );
+
+    std::fs::write(targets_dir.join("file2.txt"), "changed").unwrap();
+    std::fs::write(targets_dir.join("stale.txt"), "no longer listed").unwrap();
+    assert!(
+        delta_clone().contains("Downloaded 1 targets, kept 2 unchanged targets, pruned 1 files")
+    );
+
+    assert_all_metadata(&repo_paths.metadata_outdir);
+    for f in &["file1.txt", "file2.txt", "file3.txt"] {
+        assert_target_match(&repo_paths.targets_outdir, f)
+    }
+    assert!(!targets_dir.join("stale.txt").exists());
+}
+
+#[test]
+// Ensure `--prune` requires `--delta`, and `--delta` collides with `--metadata-only`
+fn clone_delta_args_failure() {
+    let repo_paths = RepoPaths::new();
+    let mut cmd = Command::cargo_bin("tuftool").unwrap();
+    clone_base_command(&mut cmd, &repo_paths)
+        .args([
+            "--targets-url",
+            repo_paths.targets_base_url.as_str(),
+            "--targets-dir",
+            repo_paths.targets_outdir.path().to_str().unwrap(),
+            "--prune",
+        ])
+        .assert()
+        .failure();
+
+    let mut cmd = Command::cargo_bin("tuftool").unwrap();
+    clone_base_command(&mut cmd, &repo_paths)
+        .args(["--metadata-only", "--delta"])
+        .assert()
+        .failure();
+}
@@ -112,6 +112,109 @@ async fn create_command() {
     assert_eq!(repo.snapshot().signatures.len(), 1);
 }
 
+#[test]
+// Ensure that `--gzip-metadata` writes a compressed companion of each metadata file and a manifest
+fn create_with_gzip_metadata() {
+    let targets_input_dir = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("targets");
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let repo_dir = TempDir::new().unwrap();
+
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "create",
+            "-t",
+            targets_input_dir.to_str().unwrap(),
+            "-o",
+            repo_dir.path().to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--targets-expires",
+            "in 7 days",
+            "--targets-version",
+            "1",
+            "--snapshot-expires",
+            "in 7 days",
+            "--snapshot-version",
+            "1",
+            "--timestamp-expires",
+            "in 7 days",
+            "--timestamp-version",
+            "1",
+            "--gzip-metadata",
+        ])
+        .assert()
+        .success();
+
+    let metadata_dir = repo_dir.path().join("metadata");
+    for filename in [
+        "1.root.json.gz",
+        "1.targets.json.gz",
+        "1.snapshot.json.gz",
+        "timestamp.json.gz",
+        "gzip-manifest.json",
+    ] {
+        assert!(metadata_dir.join(filename).is_file(), "{}", filename);
+    }
+}
+
+#[tokio::test]
+// Ensure that `--metadata-style compact` writes metadata without whitespace that still loads
+async fn create_with_compact_metadata() {
+    let targets_input_dir = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("targets");
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let repo_dir = TempDir::new().unwrap();
+
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "create",
+            "-t",
+            targets_input_dir.to_str().unwrap(),
+            "-o",
+            repo_dir.path().to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--all-expire-in",
+            "7d",
+            "--targets-version",
+            "1",
+            "--snapshot-version",
+            "1",
+            "--timestamp-version",
+            "1",
+            "--metadata-style",
+            "compact",
+        ])
+        .assert()
+        .success();
+
+    let metadata_dir = repo_dir.path().join("metadata");
+    for filename in ["1.targets.json", "1.snapshot.json", "timestamp.json"] {
+        let data = std::fs::read(metadata_dir.join(filename)).unwrap();
+        assert!(!data.contains(&b'\n'), "{}", filename);
+    }
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(&root_json).await.unwrap(),
+        dir_url(&metadata_dir),
+        dir_url(repo_dir.path().join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    assert_eq!(repo.targets().signed.targets.len(), 3);
+}
+
 #[test]
 // Ensure that the create command fails if none of the keys we give it match up with root.json.
 fn create_with_incorrect_key() {
@@ -150,6 +253,99 @@ fn create_with_incorrect_key() {
         .failure();
 }
 
+#[tokio::test]
+// Ensure `--all-expire-in` sets every expiration that isn't given more specifically
+async fn create_with_expire_in() {
+    let snapshot_expiration = Utc::now().checked_add_signed(days(21)).unwrap();
+    let targets_input_dir = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("targets");
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let repo_dir = TempDir::new().unwrap();
+
+    let before = Utc::now();
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "create",
+            "-t",
+            targets_input_dir.to_str().unwrap(),
+            "-o",
+            repo_dir.path().to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--all-expire-in",
+            "30d",
+            "--timestamp-expire-in",
+            "12 hours",
+            "--snapshot-expires",
+            snapshot_expiration.to_rfc3339().as_str(),
+            "--targets-version",
+            "1",
+            "--snapshot-version",
+            "1",
+            "--timestamp-version",
+            "1",
+        ])
+        .assert()
+        .success();
+    let after = Utc::now();
+
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_json).await.unwrap(),
+        dir_url(repo_dir.path().join("metadata")),
+        dir_url(repo_dir.path().join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    let targets_expires = repo.targets().signed.expires;
+    assert!(before + days(30) <= targets_expires && targets_expires <= after + days(30));
+    let timestamp_expires = repo.timestamp().signed.expires;
+    let half_day = chrono::TimeDelta::try_hours(12).unwrap();
+    assert!(before + half_day <= timestamp_expires && timestamp_expires <= after + half_day);
+    assert_eq!(repo.snapshot().signed.expires, snapshot_expiration);
+}
+
+#[test]
+// Ensure a role's expiration can't be given both absolutely and relatively
+fn create_with_conflicting_expirations() {
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "create",
+            "-t",
+            "/input/dir/does/not/matter",
+            "-o",
+            "/output/dir/does/not/matter",
+            "-k",
+            "/key/does/not/matter",
+            "--root",
+            "/root/does/not/matter",
+            "--all-expire-in",
+            "30d",
+            "--targets-expires",
+            "in 7 days",
+            "--targets-expire-in",
+            "7d",
+            "--targets-version",
+            "1234",
+            "--snapshot-version",
+            "1234",
+            "--timestamp-version",
+            "1234",
+        ])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    assert!(String::from_utf8_lossy(&output).contains("cannot be used with"));
+}
+
 #[test]
 // Ensure we fail if no key is provided
 fn create_with_no_key() {
@@ -180,3 +376,252 @@ fn create_with_no_key() {
         .assert()
         .failure();
 }
+
+#[tokio::test]
+// Ensure that the create command delegates the roles described in a delegations spec, including
+// roles delegated by other roles in the spec.
+async fn create_with_delegations_spec() {
+    let targets_input_dir = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("targets");
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let team_key = test_utils::test_data().join("targetskey");
+    let nightly_key = test_utils::test_data().join("targetskey-1");
+    let repo_dir = TempDir::new().unwrap();
+    let spec_dir = TempDir::new().unwrap();
+    let spec_path = spec_dir.path().join("delegations.yaml");
+    let spec = format!(
+        r#"roles:
+  - name: team-a
+    keys: ["{}"]
+    threshold: 1
+    paths: ["team-a/*"]
+    expires: in 7 days
+    version: 2
+  - name: team-a-nightly
+    delegated_by: team-a
+    keys: ["{}"]
+    threshold: 1
+    path_hash_prefixes: ["0", "1"]
+    expires: in 3 days
+"#,
+        team_key.display(),
+        nightly_key.display()
+    );
+    std::fs::write(&spec_path, spec).unwrap();
+
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "create",
+            "-t",
+            targets_input_dir.to_str().unwrap(),
+            "-o",
+            repo_dir.path().to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--delegations-spec",
+            spec_path.to_str().unwrap(),
+            "--targets-expires",
+            "in 7 days",
+            "--targets-version",
+            "1",
+            "--snapshot-expires",
+            "in 7 days",
+            "--snapshot-version",
+            "1",
+            "--timestamp-expires",
+            "in 7 days",
+            "--timestamp-version",
+            "1",
+        ])
+        .assert()
+        .success();
+
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_json).await.unwrap(),
+        dir_url(repo_dir.path().join("metadata")),
+        dir_url(repo_dir.path().join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+
+    let team = repo.delegated_role("team-a").unwrap();
+    assert_eq!(team.targets.as_ref().unwrap().signed.version.get(), 2);
+    let nightly = repo.delegated_role("team-a-nightly").unwrap();
+    assert_eq!(nightly.threshold.get(), 1);
+    assert!(matches!(
+        nightly.paths,
+        tough::schema::PathSet::PathHashPrefixes(_)
+    ));
+    assert_eq!(repo.snapshot().signed.meta.len(), 3);
+}
+
+#[test]
+// Ensure that the create command rejects a delegations spec whose role is delegated by a role
+// that isn't listed before it, is named `targets`, omits its threshold, or has a misspelled field.
+fn create_with_invalid_delegations_spec() {
+    let role = "keys: []\n    paths: []\n    expires: in 7 days\n";
+    for (spec, expected) in [
+        (
+            format!("roles:\n  - name: orphan\n    delegated_by: nobody\n    threshold: 1\n    {role}"),
+            "isn't listed before it",
+        ),
+        (
+            format!("roles:\n  - name: targets\n    threshold: 1\n    {role}"),
+            "is the top-level targets role",
+        ),
+        (
+            format!("roles:\n  - name: team-a\n    {role}"),
+            "missing field `threshold`",
+        ),
+        (
+            format!("roles:\n  - name: team-a\n    treshold: 1\n    {role}"),
+            "unknown field `treshold`",
+        ),
+        (
+            format!("roles:\n  - name: team-a\n    threshold: 1\n    path_hash_prefixes: []\n    {role}"),
+            "exactly one of `paths` and `path_hash_prefixes`",
+        ),
+    ] {
+        let spec_dir = TempDir::new().unwrap();
+        let spec_path = spec_dir.path().join("delegations.yaml");
+        std::fs::write(&spec_path, spec).unwrap();
+
+        let output = Command::cargo_bin("tuftool")
+            .unwrap()
+            .args([
+                "create",
+                "-t",
+                "input/dir/does/not/matter",
+                "-o",
+                "output/dir/does/not/matter",
+                "-k",
+                "key/does/not/matter",
+                "--root",
+                "root/does/not/matter",
+                "--delegations-spec",
+                spec_path.to_str().unwrap(),
+                "--targets-expires",
+                "in 7 days",
+                "--targets-version",
+                "1",
+                "--snapshot-expires",
+                "in 7 days",
+                "--snapshot-version",
+                "1",
+                "--timestamp-expires",
+                "in 7 days",
+                "--timestamp-version",
+                "1",
+            ])
+            .assert()
+            .failure()
+            .get_output()
+            .stderr
+            .clone();
+        let stderr = String::from_utf8(output).unwrap();
+        assert!(stderr.contains(expected), "{}", stderr);
+    }
+}
+
+#[tokio::test]
+// Ensure that a root from `root create-test` and its key are enough to create a repo that loads
+async fn create_with_test_root() {
+    let work_dir = TempDir::new().unwrap();
+    let root_json = work_dir.path().join("root.json");
+    let root_key = work_dir.path().join("root.pem");
+    let repo_dir = work_dir.path().join("repo");
+    let targets_input_dir = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("targets");
+
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "root",
+            "create-test",
+            "--out",
+            root_json.to_str().unwrap(),
+            "--key",
+            root_key.to_str().unwrap(),
+            "--consistent-snapshot",
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let key_id = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .next()
+        .unwrap()
+        .to_owned();
+
+    // The key is only written once
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "root",
+            "create-test",
+            "--out",
+            root_json.to_str().unwrap(),
+            "--key",
+            root_key.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "create",
+            "-t",
+            targets_input_dir.to_str().unwrap(),
+            "-o",
+            repo_dir.to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--targets-expires",
+            "in 7 days",
+            "--targets-version",
+            "1",
+            "--snapshot-expires",
+            "in 7 days",
+            "--snapshot-version",
+            "1",
+            "--timestamp-expires",
+            "in 7 days",
+            "--timestamp-version",
+            "1",
+        ])
+        .assert()
+        .success();
+
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(&root_json).await.unwrap(),
+        dir_url(repo_dir.join("metadata")),
+        dir_url(repo_dir.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    assert!(repo.root().signed.consistent_snapshot);
+    assert_eq!(repo.root().signed.keys.len(), 1);
+    assert_eq!(
+        hex::encode(repo.root().signed.keys.keys().next().unwrap()),
+        key_id
+    );
+    assert!(repo
+        .read_target(&TargetName::new("file1.txt").unwrap())
+        .await
+        .unwrap()
+        .is_some());
+}
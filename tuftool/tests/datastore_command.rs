@@ -0,0 +1,56 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use assert_cmd::Command;
+use tempfile::TempDir;
+
+fn datastore_with_timestamp() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("timestamp.json"),
+        r#"{"signed":{"_type":"timestamp","version":7},"signatures":[]}"#,
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+// Ensure inspect lists stored documents with their versions
+fn datastore_inspect() {
+    let dir = datastore_with_timestamp();
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["datastore", "inspect", dir.path().to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("timestamp.json\ttimestamp\tversion 7"));
+}
+
+#[test]
+// Ensure reset refuses to run without the acknowledgement flag and removes the role with it
+fn datastore_reset_requires_acknowledgement() {
+    let dir = datastore_with_timestamp();
+    let path = dir.path().to_str().unwrap();
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["datastore", "reset", path, "--role", "timestamp"])
+        .assert()
+        .failure();
+    assert!(dir.path().join("timestamp.json").exists());
+
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "datastore",
+            "reset",
+            path,
+            "--role",
+            "timestamp",
+            "--i-understand-rollback-risk",
+        ])
+        .assert()
+        .success();
+    assert!(!dir.path().join("timestamp.json").exists());
+}
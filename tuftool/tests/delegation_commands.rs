@@ -9,6 +9,7 @@ use chrono::Utc;
 use std::path::Path;
 use tempfile::TempDir;
 use test_utils::dir_url;
+use tough::schema::{PathPattern, PathSet};
 use tough::{RepositoryLoader, TargetName};
 
 fn create_repo<P: AsRef<Path>>(repo_dir: P) {
@@ -126,7 +127,7 @@ async fn create_add_role_command() {
             "-t",
             "1",
             "-v",
-            "2",
+            "18",
             "--sign-all",
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
@@ -230,11 +231,11 @@ async fn create_add_role_command() {
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            format!("{}", new_snapshot_version).as_str(),
+            format!("{}", new_snapshot_version + 1).as_str(),
             "--timestamp-expires",
             new_timestamp_expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            format!("{}", new_timestamp_version).as_str(),
+            format!("{}", new_timestamp_version + 1).as_str(),
             "--role",
             "A",
             "-i",
@@ -256,6 +257,104 @@ async fn create_add_role_command() {
     // Make sure `B` is added as a role
     assert!(repo.delegated_role("B").is_some());
 }
+#[tokio::test]
+// Ensure a role can be handed off in a delegation package and that a tampered package is rejected
+async fn create_add_role_package_command() {
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let targets_key = test_utils::test_data().join("targetskey");
+    let repo_dir = TempDir::new().unwrap();
+    create_repo(repo_dir.path());
+    let expiration = Utc::now().checked_add_signed(days(4)).unwrap();
+    let metadata_base_url = &dir_url(repo_dir.path().join("metadata"));
+
+    // create role A as a package
+    let package_dir = TempDir::new().unwrap();
+    let package = package_dir.path().join("A.json");
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "delegation",
+            "--signing-role",
+            "A",
+            "create-role",
+            "--package",
+            package.to_str().unwrap(),
+            "-p",
+            "*.txt",
+            "-k",
+            targets_key.to_str().unwrap(),
+            "-e",
+            expiration.to_rfc3339().as_str(),
+            "-v",
+            "1",
+        ])
+        .assert()
+        .success();
+
+    let add_role = |package: &Path, outdir: &Path| {
+        let mut cmd = Command::cargo_bin("tuftool").unwrap();
+        cmd.args([
+            "delegation",
+            "--signing-role",
+            "targets",
+            "add-role",
+            "-o",
+            outdir.to_str().unwrap(),
+            "--package",
+            package.to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            metadata_base_url.as_str(),
+            "-e",
+            expiration.to_rfc3339().as_str(),
+            "-v",
+            "18",
+            "--sign-all",
+            "--snapshot-expires",
+            expiration.to_rfc3339().as_str(),
+            "--snapshot-version",
+            "100",
+            "--timestamp-expires",
+            expiration.to_rfc3339().as_str(),
+            "--timestamp-version",
+            "100",
+        ]);
+        cmd
+    };
+
+    // add role A from the package and sign the entire repo
+    let new_repo_dir = TempDir::new().unwrap();
+    add_role(&package, new_repo_dir.path()).assert().success();
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(&root_json).await.unwrap(),
+        dir_url(new_repo_dir.path().join("metadata")),
+        dir_url(new_repo_dir.path().join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    let role = repo.delegated_role("A").unwrap();
+    assert_eq!(role.threshold.get(), 1);
+    assert_eq!(
+        role.paths,
+        PathSet::Paths(vec![PathPattern::new("*.txt").unwrap()])
+    );
+
+    // a package whose threshold isn't met by its own signatures is rejected
+    let tampered = package_dir.path().join("tampered.json");
+    let mut json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&package).unwrap()).unwrap();
+    json["threshold"] = 2.into();
+    std::fs::write(&tampered, serde_json::to_vec(&json).unwrap()).unwrap();
+    add_role(&tampered, TempDir::new().unwrap().path())
+        .assert()
+        .failure();
+}
+
 #[tokio::test]
 // Ensure we can update targets of delegated roles
 async fn update_target_command() {
@@ -324,7 +423,7 @@ async fn update_target_command() {
             "-t",
             "1",
             "-v",
-            "2",
+            "18",
             "--sign-all",
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
@@ -399,11 +498,11 @@ async fn update_target_command() {
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            format!("{}", new_snapshot_version).as_str(),
+            format!("{}", new_snapshot_version + 1).as_str(),
             "--timestamp-expires",
             new_timestamp_expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            format!("{}", new_timestamp_version).as_str(),
+            format!("{}", new_timestamp_version + 1).as_str(),
             "--role",
             "A",
             "-i",
@@ -501,7 +600,7 @@ async fn add_key_command() {
             "-t",
             "1",
             "-v",
-            "2",
+            "18",
             "--sign-all",
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
@@ -538,7 +637,7 @@ async fn add_key_command() {
             "-e",
             expiration.to_rfc3339().as_str(),
             "-v",
-            "1",
+            "19",
             "--delegated-role",
             "A",
         ])
@@ -570,11 +669,11 @@ async fn add_key_command() {
             "--snapshot-expires",
             expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            "1",
+            format!("{}", new_snapshot_version + 1).as_str(),
             "--timestamp-expires",
             expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            "1",
+            format!("{}", new_timestamp_version + 1).as_str(),
         ])
         .assert()
         .success();
@@ -657,11 +756,11 @@ async fn add_key_command() {
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            format!("{}", new_snapshot_version).as_str(),
+            format!("{}", new_snapshot_version + 2).as_str(),
             "--timestamp-expires",
             new_timestamp_expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            format!("{}", new_timestamp_version).as_str(),
+            format!("{}", new_timestamp_version + 2).as_str(),
             "--role",
             "A",
             "-i",
@@ -751,7 +850,7 @@ fn remove_key_command() {
             "-t",
             "1",
             "-v",
-            "2",
+            "18",
             "--sign-all",
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
@@ -781,7 +880,7 @@ fn remove_key_command() {
             "-e",
             expiration.to_rfc3339().as_str(),
             "-v",
-            "1",
+            "19",
             "--keyid",
             "9d25bd7d096386713d823447e9920ea4b807bd95d1bf7a0d05a00979ab5eec00",
             "-k",
@@ -821,11 +920,11 @@ fn remove_key_command() {
             "--snapshot-expires",
             expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            "1",
+            format!("{}", new_snapshot_version + 1).as_str(),
             "--timestamp-expires",
             expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            "1",
+            format!("{}", new_timestamp_version + 1).as_str(),
         ])
         .assert()
         .success();
@@ -952,7 +1051,7 @@ async fn remove_role_command() {
             "-t",
             "1",
             "-v",
-            "2",
+            "18",
             "--sign-all",
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
@@ -1062,11 +1161,11 @@ async fn remove_role_command() {
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            format!("{}", new_snapshot_version).as_str(),
+            format!("{}", new_snapshot_version + 1).as_str(),
             "--timestamp-expires",
             new_timestamp_expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            format!("{}", new_timestamp_version).as_str(),
+            format!("{}", new_timestamp_version + 1).as_str(),
             "--role",
             "A",
             "-i",
@@ -1112,7 +1211,7 @@ async fn remove_role_command() {
     let new_snapshot_expiration = Utc::now().checked_add_signed(days(5)).unwrap();
     let new_snapshot_version: u64 = 250;
     let new_targets_expiration = Utc::now().checked_add_signed(days(6)).unwrap();
-    let new_targets_version: u64 = 170;
+    let new_targets_version: u64 = 171;
     let update_out = TempDir::new().unwrap();
 
     // Update the repo we just created
@@ -1135,11 +1234,11 @@ async fn remove_role_command() {
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            format!("{}", new_snapshot_version).as_str(),
+            format!("{}", new_snapshot_version + 2).as_str(),
             "--timestamp-expires",
             new_timestamp_expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            format!("{}", new_timestamp_version).as_str(),
+            format!("{}", new_timestamp_version + 2).as_str(),
             "--role",
             "A",
             "-i",
@@ -1229,7 +1328,7 @@ async fn remove_role_recursive_command() {
             "-t",
             "1",
             "-v",
-            "2",
+            "18",
             "--sign-all",
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
@@ -1338,11 +1437,11 @@ async fn remove_role_recursive_command() {
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            format!("{}", new_snapshot_version).as_str(),
+            format!("{}", new_snapshot_version + 1).as_str(),
             "--timestamp-expires",
             new_timestamp_expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            format!("{}", new_timestamp_version).as_str(),
+            format!("{}", new_timestamp_version + 1).as_str(),
             "--role",
             "A",
             "-i",
@@ -1412,11 +1511,11 @@ async fn remove_role_recursive_command() {
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            format!("{}", new_snapshot_version).as_str(),
+            format!("{}", new_snapshot_version + 2).as_str(),
             "--timestamp-expires",
             new_timestamp_expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            format!("{}", new_timestamp_version).as_str(),
+            format!("{}", new_timestamp_version + 2).as_str(),
             "--role",
             "targets",
             "-i",
@@ -1517,7 +1616,7 @@ async fn dubious_role_name() {
             "-t",
             "1",
             "-v",
-            "2",
+            "18",
             "--sign-all",
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
@@ -1633,11 +1732,11 @@ async fn dubious_role_name() {
             "--snapshot-expires",
             new_snapshot_expiration.to_rfc3339().as_str(),
             "--snapshot-version",
-            format!("{}", new_snapshot_version).as_str(),
+            format!("{}", new_snapshot_version + 1).as_str(),
             "--timestamp-expires",
             new_timestamp_expiration.to_rfc3339().as_str(),
             "--timestamp-version",
-            format!("{}", new_timestamp_version).as_str(),
+            format!("{}", new_timestamp_version + 1).as_str(),
             "--role",
             dubious_role_name,
             "-i",
@@ -1671,3 +1770,154 @@ async fn dubious_role_name() {
         .join(format!("{}.{}.json", 1, funny_name_encoded))
         .is_file());
 }
+
+#[test]
+// Ensure a delegated role can be checked against its delegation, both as published and as a newly
+// signed copy that hasn't been submitted yet
+fn verify_role_command() {
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let targets_key = test_utils::test_data().join("targetskey");
+    let repo_dir = TempDir::new().unwrap();
+    create_repo(repo_dir.path());
+    let metadata_base_url = &dir_url(repo_dir.path().join("metadata"));
+    let meta_out = TempDir::new().unwrap();
+
+    // create role A and delegate file4.txt and file5.txt to it
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "delegation",
+            "--signing-role",
+            "A",
+            "create-role",
+            "-o",
+            meta_out.path().to_str().unwrap(),
+            "-k",
+            targets_key.to_str().unwrap(),
+            "-e",
+            "in 4 days",
+            "-v",
+            "1",
+        ])
+        .assert()
+        .success();
+    let new_repo_dir = TempDir::new().unwrap();
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "delegation",
+            "--signing-role",
+            "targets",
+            "add-role",
+            "-o",
+            new_repo_dir.path().to_str().unwrap(),
+            "-i",
+            dir_url(meta_out.path().join("metadata")).as_str(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            metadata_base_url.as_str(),
+            "-e",
+            "in 4 days",
+            "--delegated-role",
+            "A",
+            "-t",
+            "1",
+            "-v",
+            "18",
+            "-p",
+            "file4.txt",
+            "-p",
+            "file5.txt",
+            "--sign-all",
+            "--snapshot-expires",
+            "in 5 days",
+            "--snapshot-version",
+            "250",
+            "--timestamp-expires",
+            "in 4 days",
+            "--timestamp-version",
+            "310",
+        ])
+        .assert()
+        .success();
+    let new_metadata_base_url = &dir_url(new_repo_dir.path().join("metadata"));
+
+    // The published role passes every check
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "delegation",
+            "--signing-role",
+            "A",
+            "verify",
+            "--root",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            new_metadata_base_url.as_str(),
+        ])
+        .assert()
+        .success();
+
+    // Sign a new version of A that also lists file6.txt, which isn't delegated to it
+    let ut_out = TempDir::new().unwrap();
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "delegation",
+            "--signing-role",
+            "A",
+            "update-delegated-targets",
+            "-o",
+            ut_out.path().to_str().unwrap(),
+            "-k",
+            targets_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            new_metadata_base_url.as_str(),
+            "-t",
+            test_utils::test_data().join("targets").to_str().unwrap(),
+            "-e",
+            "in 5 days",
+            "-v",
+            "2",
+        ])
+        .assert()
+        .success();
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "delegation",
+            "--signing-role",
+            "A",
+            "verify",
+            "--root",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            new_metadata_base_url.as_str(),
+            "--role-file",
+            ut_out
+                .path()
+                .join("metadata")
+                .join("A.json")
+                .to_str()
+                .unwrap(),
+        ])
+        .assert()
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("signatures: ok"), "{}", stdout);
+    assert!(stdout.contains("version: ok (version 2)"), "{}", stdout);
+    assert!(
+        stdout.contains("paths: FAILED: not delegated to the role: file6.txt"),
+        "{}",
+        stdout
+    );
+}
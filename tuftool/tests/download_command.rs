@@ -108,6 +108,50 @@ fn download_http_transport() {
     download_command(metadata_base_url, targets_base_url);
 }
 
+#[test]
+// Ensure that the download command accepts the rate limit and retry settings for http transport.
+fn download_http_transport_settings() {
+    let server = Server::run();
+    server.expect(create_successful_get("metadata/role1.json"));
+    server.expect(create_successful_get("metadata/role2.json"));
+    server.expect(create_successful_get("metadata/snapshot.json"));
+    server.expect(create_successful_get("metadata/targets.json"));
+    server.expect(create_successful_get("metadata/timestamp.json"));
+    server.expect(create_successful_get("targets/file1.txt"));
+    server.expect(create_successful_get("targets/file2.txt"));
+    server.expect(create_unsuccessful_get("metadata/2.root.json"));
+    let tempdir = TempDir::new().unwrap();
+    let outdir = tempdir.path().join("outdir");
+    let root_json = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("metadata")
+        .join("root.json");
+
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "download",
+            "-r",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            server.url_str("/metadata").as_str(),
+            "--targets-url",
+            server.url_str("/targets").as_str(),
+            "--limit-rate",
+            "1000000",
+            "--retries",
+            "2",
+            "--retry-wait",
+            "1",
+            outdir.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    assert_file_match(&outdir, "file1.txt");
+    assert_file_match(&outdir, "file2.txt");
+}
+
 #[test]
 // Ensure that the download command works with file transport, and that we require outdir to
 // not-exist.
@@ -134,7 +178,8 @@ fn download_expired_repo(outdir: &Path, repo_dir: &TempDir, allow_expired_repo:
         outdir.to_str().unwrap(),
     ]);
     if allow_expired_repo {
-        cmd.arg("--allow-expired-repo").assert()
+        cmd.args(["--allow-expired-repo", "--confirm-expired-repo"])
+            .assert()
     } else {
         cmd.assert()
     }
@@ -160,10 +205,54 @@ fn download_command_expired_repo_allow() {
     // Create a expired repo using tuftool
     test_utils::create_expired_repo(repo_dir.path());
     // assert success for download command
-    download_expired_repo(&outdir, &repo_dir, true).success();
-    // Assert the files are exactly correct
-    assert_file_match(&outdir, "file1.txt");
-    assert_file_match(&outdir, "file2.txt");
+    let output = download_expired_repo(&outdir, &repo_dir, true)
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    assert!(
+        stderr.contains("WARNING: `--allow-expired-repo` was passed"),
+        "{}",
+        stderr
+    );
+    // The targets are written to a directory marked as untrusted, not the one requested
+    assert!(!outdir.exists());
+    let untrusted_outdir = tempdir.path().join("UNTRUSTED-outdir");
+    assert_file_match(&untrusted_outdir, "file1.txt");
+    assert_file_match(&untrusted_outdir, "file2.txt");
+}
+
+#[test]
+// Ensure --allow-expired-repo must be confirmed with --confirm-expired-repo
+fn download_command_expired_repo_requires_confirmation() {
+    let tempdir = TempDir::new().unwrap();
+    let outdir = tempdir.path().join("outdir");
+    let repo_dir = TempDir::new().unwrap();
+    test_utils::create_expired_repo(repo_dir.path());
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "download",
+            "-r",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            test_utils::dir_url(repo_dir.path().join("metadata")).as_str(),
+            "--targets-url",
+            test_utils::dir_url(repo_dir.path().join("targets")).as_str(),
+            "--allow-expired-repo",
+            outdir.to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    assert!(stderr.contains("--confirm-expired-repo"), "{}", stderr);
+    assert!(!outdir.exists());
+    assert!(!tempdir.path().join("UNTRUSTED-outdir").exists());
 }
 
 #[test]
@@ -190,3 +279,81 @@ fn download_safe_target_paths() {
     assert!(outdir.join("data1.txt").is_file());
     assert!(outdir.join("foo/bar/data2.txt").is_file())
 }
+
+/// Runs `tuftool download` against `tuf-reference-impl` with `extra_args`, returning the output
+/// directory and the result.
+fn download_reference_impl(tempdir: &TempDir, extra_args: &[&str]) -> (std::path::PathBuf, Assert) {
+    let repo_dir = test_utils::test_data().join("tuf-reference-impl");
+    let root_json = repo_dir.join("metadata").join("root.json");
+    let outdir = tempdir.path().join("outdir");
+    let assert = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "download",
+            "-r",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            test_utils::dir_url(repo_dir.join("metadata")).as_str(),
+            "--targets-url",
+            test_utils::dir_url(repo_dir.join("targets")).as_str(),
+            outdir.to_str().unwrap(),
+        ])
+        .args(extra_args)
+        .assert();
+    (outdir, assert)
+}
+
+#[test]
+// Ensure --targets only downloads the targets matching one of its globs.
+fn download_targets_glob() {
+    let tempdir = TempDir::new().unwrap();
+    let (outdir, assert) =
+        download_reference_impl(&tempdir, &["--targets", "*1.txt", "--jobs", "2"]);
+    assert.success();
+    assert_file_match(&outdir, "file1.txt");
+    assert!(!outdir.join("file2.txt").exists());
+
+    let tempdir = TempDir::new().unwrap();
+    let (outdir, assert) = download_reference_impl(&tempdir, &["--targets", "file[", "-j", "2"]);
+    assert.failure();
+    assert!(!outdir.exists());
+}
+
+#[test]
+// Ensure --role only downloads the targets listed by that delegated role.
+fn download_role() {
+    let tempdir = TempDir::new().unwrap();
+    let (outdir, assert) = download_reference_impl(&tempdir, &["--role", "role1"]);
+    assert.success();
+    assert_file_match(&outdir, "file3.txt");
+    assert!(!outdir.join("file1.txt").exists());
+    assert!(!outdir.join("file2.txt").exists());
+
+    let tempdir = TempDir::new().unwrap();
+    let (outdir, assert) =
+        download_reference_impl(&tempdir, &["--role", "role1", "--targets", "*.img"]);
+    assert.success();
+    assert_eq!(std::fs::read_dir(&outdir).unwrap().count(), 0);
+
+    let tempdir = TempDir::new().unwrap();
+    let (outdir, assert) = download_reference_impl(&tempdir, &["--role", "no-such-role"]);
+    assert.failure();
+    assert!(!outdir.exists());
+}
+
+#[test]
+// Ensure --include-delegated also downloads the targets of the roles that --role delegates to.
+fn download_role_include_delegated() {
+    let tempdir = TempDir::new().unwrap();
+    let (outdir, assert) =
+        download_reference_impl(&tempdir, &["--role", "targets", "--include-delegated"]);
+    assert.success();
+    assert_file_match(&outdir, "file1.txt");
+    assert_file_match(&outdir, "file2.txt");
+    assert_file_match(&outdir, "file3.txt");
+
+    let tempdir = TempDir::new().unwrap();
+    let (outdir, assert) = download_reference_impl(&tempdir, &["--include-delegated"]);
+    assert.failure();
+    assert!(!outdir.exists());
+}
@@ -0,0 +1,55 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+use serde_json::Value;
+use tempfile::TempDir;
+
+#[test]
+// Ensure the key ID and public key printed for a local key match what `root add-key` records
+fn keyid_matches_root_add_key() {
+    let key = test_utils::test_data().join("snakeoil.pem");
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["keyid", "-k", key.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let printed: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    let root_dir = TempDir::new().unwrap();
+    let root_json = root_dir.path().join("root.json");
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["root", "init", root_json.to_str().unwrap()])
+        .assert()
+        .success();
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "root",
+            "add-key",
+            root_json.to_str().unwrap(),
+            "-k",
+            key.to_str().unwrap(),
+            "--role",
+            "root",
+        ])
+        .assert()
+        .success();
+    let root: Value = serde_json::from_slice(&std::fs::read(&root_json).unwrap()).unwrap();
+    assert_eq!(root["signed"]["keys"], printed);
+}
+
+#[test]
+// Ensure a key that can't be loaded fails
+fn keyid_missing_key() {
+    let missing_key = test_utils::test_data().join("does-not-exist.pem");
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["keyid", "-k", missing_key.to_str().unwrap()])
+        .assert()
+        .failure();
+}
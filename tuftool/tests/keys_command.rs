@@ -0,0 +1,36 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+
+#[test]
+// Ensure a local key that can be loaded passes the check
+fn keys_check_local_key() {
+    let key = test_utils::test_data().join("snakeoil.pem");
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["keys", "check", "-k", key.to_str().unwrap()])
+        .assert()
+        .success();
+}
+
+#[test]
+// Ensure a key that can't be loaded fails the check
+fn keys_check_missing_key() {
+    let good_key = test_utils::test_data().join("snakeoil.pem");
+    let missing_key = test_utils::test_data().join("does-not-exist.pem");
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "keys",
+            "check",
+            "-k",
+            good_key.to_str().unwrap(),
+            "-k",
+            missing_key.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+}
@@ -0,0 +1,29 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+
+#[test]
+// Ensure the limits command reports observed sizes and recommended limits
+fn limits_reference_impl() {
+    let base = test_utils::test_data().join("tuf-reference-impl");
+    let root_json = base.join("metadata").join("1.root.json");
+    let metadata_url = test_utils::dir_url(base.join("metadata"));
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "limits",
+            "-r",
+            root_json.to_str().unwrap(),
+            "-m",
+            metadata_url.as_str(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("delegated roles: 2"));
+    assert!(stdout.contains("max_delegated_roles: 16"));
+}
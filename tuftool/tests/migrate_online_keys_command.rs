@@ -0,0 +1,154 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tempfile::TempDir;
+use test_utils::{days, dir_url, test_data};
+use tough::schema::RoleType;
+use tough::{RepositoryLoader, TargetName};
+
+fn create_repo(repo_dir: &Path) {
+    let expires = Utc::now().checked_add_signed(days(3)).unwrap().to_rfc3339();
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "create",
+            "-t",
+            test_data()
+                .join("tuf-reference-impl")
+                .join("targets")
+                .to_str()
+                .unwrap(),
+            "-o",
+            repo_dir.to_str().unwrap(),
+            "-k",
+            test_data().join("snakeoil.pem").to_str().unwrap(),
+            "--root",
+            test_data()
+                .join("simple-rsa")
+                .join("root.json")
+                .to_str()
+                .unwrap(),
+            "--targets-expires",
+            &expires,
+            "--targets-version",
+            "1",
+            "--snapshot-expires",
+            &expires,
+            "--snapshot-version",
+            "1",
+            "--timestamp-expires",
+            &expires,
+            "--timestamp-version",
+            "1",
+        ])
+        .assert()
+        .success();
+}
+
+/// Runs `tuftool migrate-online-keys` on the repository in `repo_dir`, writing the result back
+/// into it, with `root_key` as the root key and snakeoil_2.pem as the new online key.
+fn migrate(repo_dir: &Path, root_key: &str) -> Command {
+    let expires = Utc::now().checked_add_signed(days(1)).unwrap().to_rfc3339();
+    let online_key = test_data().join("snakeoil_2.pem");
+    let mut command = Command::cargo_bin("tuftool").unwrap();
+    command.args([
+        "migrate-online-keys",
+        "--root",
+        test_data()
+            .join("simple-rsa")
+            .join("root.json")
+            .to_str()
+            .unwrap(),
+        "--metadata-url",
+        dir_url(repo_dir.join("metadata")).as_str(),
+        "--outdir",
+        repo_dir.to_str().unwrap(),
+        "-k",
+        test_data().join(root_key).to_str().unwrap(),
+        "--snapshot-key",
+        online_key.to_str().unwrap(),
+        "--timestamp-key",
+        online_key.to_str().unwrap(),
+        "--snapshot-version",
+        "2",
+        "--snapshot-expires",
+        &expires,
+        "--timestamp-version",
+        "2",
+        "--timestamp-expires",
+        &expires,
+    ]);
+    command
+}
+
+fn metadata_files(repo_dir: &Path) -> BTreeMap<String, Vec<u8>> {
+    std::fs::read_dir(repo_dir.join("metadata"))
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (
+                entry.file_name().into_string().unwrap(),
+                std::fs::read(entry.path()).unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+// Ensure a migrated repository loads with the original root and lists the new online key
+async fn migrate_online_keys_in_place() {
+    let repo_dir = TempDir::new().unwrap();
+    create_repo(repo_dir.path());
+    migrate(repo_dir.path(), "snakeoil.pem").assert().success();
+
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(test_data().join("simple-rsa").join("root.json"))
+            .await
+            .unwrap(),
+        dir_url(repo_dir.path().join("metadata")),
+        dir_url(repo_dir.path().join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    let root = &repo.root().signed;
+    assert_eq!(root.version.get(), 2);
+    assert_eq!(
+        root.roles[&RoleType::Snapshot].keyids,
+        root.roles[&RoleType::Timestamp].keyids
+    );
+    assert_ne!(
+        root.roles[&RoleType::Snapshot].keyids,
+        root.roles[&RoleType::Root].keyids
+    );
+    assert_eq!(repo.timestamp().signed.version.get(), 2);
+    assert!(repo
+        .read_target(&TargetName::new("file1.txt").unwrap())
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+// Ensure a failed migration leaves the existing metadata untouched
+fn migrate_online_keys_failure_leaves_metadata() {
+    let repo_dir = TempDir::new().unwrap();
+    create_repo(repo_dir.path());
+    let before = metadata_files(repo_dir.path());
+    migrate(repo_dir.path(), "snakeoil_2.pem")
+        .assert()
+        .failure();
+    assert_eq!(metadata_files(repo_dir.path()), before);
+    // No staging directories are left behind.
+    let entries: Vec<_> = std::fs::read_dir(repo_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(entries.len(), 2, "{:?}", entries);
+}
@@ -418,6 +418,69 @@ fn below_threshold_failure() {
     sign_root_json_failure(key_1.to_str().unwrap(), root_json.to_str().unwrap());
 }
 
+#[test]
+fn below_threshold_reports_missing_keys() {
+    let out_dir = TempDir::new().unwrap();
+    let root_json = out_dir.path().join("root.json");
+    let root_json = root_json.to_str().unwrap();
+    let key_1 = test_utils::test_data().join("snakeoil.pem");
+    let key_2 = test_utils::test_data().join("snakeoil_2.pem");
+    initialize_root_json(root_json);
+    add_keys_all_roles(vec![key_1.to_str().unwrap()], root_json);
+    add_key_root(&vec![key_2.to_str().unwrap()], root_json);
+
+    // The failure names the root key that still has to sign
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["root", "sign", root_json, "-k", key_1.to_str().unwrap()])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("still needed: 1 more from"), "{}", stderr);
+
+    // With `--allow-partial` the partially signed root is written with a warning, and the key IDs
+    // reported match its signature and the key that's still missing
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "root",
+            "sign",
+            root_json,
+            "--allow-partial",
+            "-k",
+            key_1.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let root = get_signed_root(root_json);
+    assert_eq!(root.signatures.len(), 1);
+    let signed = hex::encode(&root.signatures[0].keyid);
+    let missing = root.signed.roles[&tough::schema::RoleType::Root]
+        .keyids
+        .iter()
+        .map(hex::encode)
+        .find(|keyid| *keyid != signed)
+        .unwrap();
+    assert!(
+        stdout.contains(&format!(
+            "the root role: signed by 1 of 2 required keys ({signed})"
+        )),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(&format!("needed for the root role, from: {missing}")),
+        "{}",
+        stdout
+    );
+    assert!(stderr.contains(&missing), "{}", stderr);
+}
+
 #[test]
 fn set_version_root() {
     let out_dir = TempDir::new().unwrap();
@@ -437,3 +500,218 @@ fn set_version_root() {
     // validate version number
     assert_eq!(get_version(root_json.to_str().unwrap()), version);
 }
+
+fn gen_key(root_json: &str, key: &str, key_type: &str, role: &str) -> String {
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "root", "gen-key", root_json, key, "--type", key_type, "--role", role,
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    stdout.lines().next().unwrap().to_owned()
+}
+
+#[test]
+fn gen_key_ed25519_and_ecdsa() {
+    let out_dir = TempDir::new().unwrap();
+    let root_json = out_dir.path().join("root.json");
+    let root_json = root_json.to_str().unwrap();
+    let ed25519_key = out_dir.path().join("ed25519.pem");
+    let ecdsa_key = out_dir.path().join("ecdsa.pem");
+
+    initialize_root_json(root_json);
+    let ed25519_key_id = gen_key(root_json, ed25519_key.to_str().unwrap(), "ed25519", "root");
+    let ecdsa_key_id = gen_key(root_json, ecdsa_key.to_str().unwrap(), "ecdsa", "root");
+
+    // Both keys are listed for the root role under the key IDs that were printed
+    let root = get_signed_root(root_json);
+    let root_keys = &root.signed.roles[&tough::schema::RoleType::Root].keyids;
+    for key_id in [&ed25519_key_id, &ecdsa_key_id] {
+        assert!(root_keys.iter().any(|id| &hex::encode(id) == key_id));
+    }
+
+    // The written keys can be loaded back and used to sign
+    add_keys_all_roles(vec![ecdsa_key.to_str().unwrap()], root_json);
+    sign_root_json_two_keys(
+        ed25519_key.to_str().unwrap(),
+        ecdsa_key.to_str().unwrap(),
+        root_json,
+    );
+    assert_eq!(get_sign_len(root_json), 2);
+}
+
+#[test]
+fn gen_key_rejects_unsupported_bits() {
+    let out_dir = TempDir::new().unwrap();
+    let root_json = out_dir.path().join("root.json");
+    let key = out_dir.path().join("ecdsa.pem");
+
+    initialize_root_json(root_json.to_str().unwrap());
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "root",
+            "gen-key",
+            root_json.to_str().unwrap(),
+            key.to_str().unwrap(),
+            "--type",
+            "ecdsa",
+            "--bits",
+            "384",
+            "--role",
+            "root",
+        ])
+        .assert()
+        .failure();
+    assert!(!key.exists());
+}
+
+#[test]
+fn gen_key_refuses_to_overwrite_key_file() {
+    let out_dir = TempDir::new().unwrap();
+    let root_json = out_dir.path().join("root.json");
+    let root_json = root_json.to_str().unwrap();
+    let key = out_dir.path().join("ed25519.pem");
+
+    initialize_root_json(root_json);
+    gen_key(root_json, key.to_str().unwrap(), "ed25519", "root");
+    let original = std::fs::read(&key).unwrap();
+
+    let args = [
+        "root",
+        "gen-key",
+        root_json,
+        key.to_str().unwrap(),
+        "--type",
+        "ed25519",
+        "--role",
+        "root",
+    ];
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(args)
+        .assert()
+        .failure();
+    assert_eq!(std::fs::read(&key).unwrap(), original);
+    let root = get_signed_root(root_json);
+    assert_eq!(
+        root.signed.roles[&tough::schema::RoleType::Root]
+            .keyids
+            .len(),
+        1
+    );
+
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(args)
+        .arg("--force")
+        .assert()
+        .success();
+    assert_ne!(std::fs::read(&key).unwrap(), original);
+}
+
+#[test]
+fn add_key_rejects_same_public_key_under_new_key_id() {
+    let out_dir = TempDir::new().unwrap();
+    let root_json = out_dir.path().join("root.json");
+    let key = test_utils::test_data().join("snakeoil.pem");
+
+    initialize_root_json(root_json.to_str().unwrap());
+    add_key_timestamp(key.to_str().unwrap(), root_json.to_str().unwrap());
+
+    // Give the key an extra field, which changes its key ID but not its public key
+    let mut root: serde_json::Value =
+        serde_json::from_reader(File::open(&root_json).unwrap()).unwrap();
+    let keys = root["signed"]["keys"].as_object_mut().unwrap();
+    let old_key_id = keys.keys().next().unwrap().clone();
+    let mut tuf_key = keys.remove(&old_key_id).unwrap();
+    tuf_key["x-note"] = "rotated".into();
+    let new_key_id = serde_json::from_value::<tough::schema::key::Key>(tuf_key.clone())
+        .unwrap()
+        .key_id()
+        .unwrap();
+    keys.insert(hex::encode(&new_key_id), tuf_key);
+    root["signed"]["roles"]["timestamp"]["keyids"] = serde_json::json!([hex::encode(&new_key_id)]);
+    serde_json::to_writer(File::create(&root_json).unwrap(), &root).unwrap();
+
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "root",
+            "add-key",
+            root_json.to_str().unwrap(),
+            "-k",
+            key.to_str().unwrap(),
+            "--role",
+            "root",
+        ])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    assert!(stderr.contains("already in root.json"), "{}", stderr);
+    assert!(get_signed_root(root_json.to_str().unwrap()).signed.roles
+        [&tough::schema::RoleType::Root]
+        .keyids
+        .is_empty());
+}
+
+#[test]
+// Ensure a role tuftool doesn't manage, and the key it lists, survive editing and signing root.json
+fn unrecognized_role_preserved() {
+    let out_dir = TempDir::new().unwrap();
+    let root_json = out_dir.path().join("root.json");
+    let root_path = root_json.to_str().unwrap();
+    let key_1 = test_utils::test_data().join("snakeoil.pem");
+    let key_2 = test_utils::test_data().join("snakeoil_2.pem");
+
+    initialize_root_json(root_path);
+    add_keys_all_roles(
+        vec![key_1.to_str().unwrap(), key_2.to_str().unwrap()],
+        root_path,
+    );
+    let mut root: serde_json::Value =
+        serde_json::from_reader(File::open(&root_json).unwrap()).unwrap();
+    let key_1_id = root["signed"]["roles"]["timestamp"]["keyids"][0].clone();
+    let mirrors = serde_json::json!({
+        "keyids": [key_1_id],
+        "threshold": 1,
+        "mirror_hint": {"region": "us-west-2"}
+    });
+    root["signed"]["roles"]["mirrors"] = mirrors.clone();
+    serde_json::to_writer(File::create(&root_json).unwrap(), &root).unwrap();
+
+    let key_1_id = key_1_id.as_str().unwrap();
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["root", "remove-key", root_path, key_1_id])
+        .assert()
+        .success();
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["root", "set-threshold", root_path, "root", "1"])
+        .assert()
+        .success();
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args(["root", "bump-version", root_path])
+        .assert()
+        .success();
+    sign_root_json(key_2.to_str().unwrap(), root_path);
+
+    let root = get_signed_root(root_path);
+    assert_eq!(root.signed.unrecognized_roles["mirrors"], mirrors);
+    let key_1_id: Decoded<Hex> = key_1_id.parse().unwrap();
+    assert!(root.signed.keys.contains_key(&key_1_id));
+    assert!(!root.signed.roles[&tough::schema::RoleType::Root]
+        .keyids
+        .contains(&key_1_id));
+    root.signed.verify_role(&root).unwrap();
+}
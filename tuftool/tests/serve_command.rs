@@ -0,0 +1,64 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use test_utils::read_to_end;
+use tough::{RepositoryLoader, TargetName};
+use url::Url;
+
+/// Stops the server when the test ends, whether or not it passed.
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[tokio::test]
+// Ensure a client can load a repository and its targets from the preview server
+async fn serve_reference_impl() {
+    let base = test_utils::test_data().join("tuf-reference-impl");
+    let mut server = Server(
+        Command::new(env!("CARGO_BIN_EXE_tuftool"))
+            .args([
+                "serve",
+                "--repo-dir",
+                base.to_str().unwrap(),
+                "--addr",
+                "127.0.0.1:0",
+            ])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap(),
+    );
+    let mut lines = BufReader::new(server.0.stdout.take().unwrap()).lines();
+    let mut url = |name: &str| {
+        let line = lines.next().unwrap().unwrap();
+        Url::parse(line.strip_prefix(name).unwrap().trim()).unwrap()
+    };
+    let metadata_url = url("metadata:");
+    let targets_url = url("targets:");
+
+    let root = tokio::fs::read(base.join("metadata").join("1.root.json"))
+        .await
+        .unwrap();
+    let repo = RepositoryLoader::new(&root, metadata_url.clone(), targets_url)
+        .load()
+        .await
+        .unwrap();
+    let file1 = TargetName::new("file1.txt").unwrap();
+    assert_eq!(
+        read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await,
+        &b"This is an example target file."[..]
+    );
+
+    let missing = reqwest::get(metadata_url.join("missing.json").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+}
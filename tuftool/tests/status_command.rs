@@ -0,0 +1,76 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+use serde_json::Value;
+
+/// Runs `status` against the TUF reference implementation's repository with `args`, and returns
+/// its output.
+fn status_reference_impl(args: &[&str]) -> String {
+    let base = test_utils::test_data().join("tuf-reference-impl");
+    let root_json = base.join("metadata").join("1.root.json");
+    let metadata_url = test_utils::dir_url(base.join("metadata"));
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "status",
+            "-r",
+            root_json.to_str().unwrap(),
+            "-m",
+            metadata_url.as_str(),
+        ])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+// Ensure every role, including delegated roles, is reported with its version and signatures
+fn status_json() {
+    let status: Value = serde_json::from_str(&status_reference_impl(&["--json"])).unwrap();
+    assert_eq!(status["consistent_snapshot"], false);
+    assert_eq!(status["fingerprint"].as_str().unwrap().len(), 64);
+    let roles = status["roles"].as_array().unwrap();
+    let names: Vec<&str> = roles
+        .iter()
+        .map(|role| role["role"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        ["root", "timestamp", "snapshot", "targets", "role1", "role2"]
+    );
+    for role in roles {
+        assert_eq!(role["version"], 1, "{}", role);
+        assert_eq!(role["threshold_met"], true, "{}", role);
+        assert!(role["days_until_expiry"].as_i64().unwrap() > 0, "{}", role);
+        assert!(
+            role["signatures"].as_u64().unwrap() >= role["threshold"].as_u64().unwrap(),
+            "{}",
+            role
+        );
+    }
+}
+
+#[test]
+// Ensure the table has a header and a row for each role
+fn status_table() {
+    let table = status_reference_impl(&[]);
+    let mut lines = table.lines();
+    assert_eq!(lines.next(), Some("Consistent snapshot: no"));
+    let fingerprint = lines.next().unwrap();
+    assert!(
+        fingerprint.starts_with("Release fingerprint: "),
+        "{}",
+        fingerprint
+    );
+    assert_eq!(lines.next(), Some(""));
+    assert!(lines.next().unwrap().starts_with("ROLE"));
+    let rows: Vec<&str> = lines.collect();
+    assert_eq!(rows.len(), 6);
+    assert!(rows[4].starts_with("role1 "), "{}", rows[4]);
+    assert!(rows[4].ends_with("1 (met)"), "{}", rows[4]);
+}
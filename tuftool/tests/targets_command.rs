@@ -0,0 +1,79 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+use serde_json::Value;
+
+/// Runs `targets list` against the TUF reference implementation's repository with `args`, and
+/// returns its output.
+fn list_reference_impl(args: &[&str]) -> String {
+    let base = test_utils::test_data().join("tuf-reference-impl");
+    let root_json = base.join("metadata").join("1.root.json");
+    let metadata_url = test_utils::dir_url(base.join("metadata"));
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "targets",
+            "list",
+            "-r",
+            root_json.to_str().unwrap(),
+            "-m",
+            metadata_url.as_str(),
+        ])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+// Ensure targets are listed with the role that lists them, as JSON
+fn list_json() {
+    let listings: Value = serde_json::from_str(&list_reference_impl(&[
+        "--format",
+        "json",
+        "-c",
+        "file_permissions",
+    ]))
+    .unwrap();
+    let listings = listings.as_array().unwrap();
+    let names: Vec<&str> = listings
+        .iter()
+        .map(|listing| listing["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["file1.txt", "file2.txt", "file3.txt"]);
+    assert_eq!(listings[0]["role"], "targets");
+    assert_eq!(listings[0]["length"], 31);
+    assert_eq!(
+        listings[0]["sha256"],
+        "65b8c67f51c993d898250f40aa57a317d854900b3a04895464313e48785440da"
+    );
+    assert_eq!(listings[0]["custom"]["file_permissions"], "0644");
+    assert_eq!(listings[1]["custom"]["file_permissions"], Value::Null);
+    assert_eq!(listings[2]["role"], "role1");
+}
+
+#[test]
+// Ensure the filter and custom columns apply to the table and CSV formats
+fn list_table_and_csv() {
+    let table = list_reference_impl(&["--filter", "file1.*", "-c", "file_permissions"]);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("NAME"));
+    assert!(lines[0].ends_with("file_permissions"));
+    assert!(lines[1].starts_with("file1.txt"));
+    assert!(lines[1].contains("  65b8c67f51c9  targets"));
+    assert!(lines[1].ends_with("0644"));
+
+    let csv = list_reference_impl(&["--format", "csv", "--filter", "file[23].txt"]);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "NAME,LENGTH,SHA256,ROLE");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with(
+        "file2.txt,39,452ce8308500d83ef44248d8e6062359211992fd837ea9e370e561efb1a4ca99,targets"
+    ));
+    assert!(lines[2].ends_with(",role1"));
+}
@@ -7,7 +7,7 @@ use std::path::{Path, PathBuf};
 use tough::IntoVec;
 use url::Url;
 
-/// Utilities for tests. Not every test module uses every function, so we suppress unused warnings.
+// Utilities for tests. Not every test module uses every function, so we suppress unused warnings.
 
 /// Returns the path to our test data directory
 #[allow(unused)]
@@ -0,0 +1,128 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use crate::test_utils::days;
+use assert_cmd::Command;
+use chrono::Utc;
+use tempfile::TempDir;
+use test_utils::dir_url;
+use tough::{RepositoryLoader, TargetName};
+
+#[tokio::test]
+// Ensure transferring to a root that turns off consistent snapshots rewrites the layout
+async fn transfer_metadata_disables_consistent_snapshot() {
+    let expiration = Utc::now().checked_add_signed(days(7)).unwrap();
+    let targets_input_dir = test_utils::test_data()
+        .join("tuf-reference-impl")
+        .join("targets");
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let repo_dir = TempDir::new().unwrap();
+    let new_repo_dir = TempDir::new().unwrap();
+
+    // Create a repo with consistent snapshots, as simple-rsa's root.json specifies
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "create",
+            "-t",
+            targets_input_dir.to_str().unwrap(),
+            "-o",
+            repo_dir.path().to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--targets-expires",
+            expiration.to_rfc3339().as_str(),
+            "--targets-version",
+            "1",
+            "--snapshot-expires",
+            expiration.to_rfc3339().as_str(),
+            "--snapshot-version",
+            "1",
+            "--timestamp-expires",
+            expiration.to_rfc3339().as_str(),
+            "--timestamp-version",
+            "1",
+        ])
+        .assert()
+        .success();
+
+    // Make a new root that doesn't use consistent snapshots
+    let new_root = new_repo_dir.path().join("root.json");
+    std::fs::copy(&root_json, &new_root).unwrap();
+    for args in [
+        vec![
+            "set-consistent-snapshot",
+            new_root.to_str().unwrap(),
+            "false",
+        ],
+        vec![
+            "sign",
+            new_root.to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+        ],
+    ] {
+        Command::cargo_bin("tuftool")
+            .unwrap()
+            .arg("root")
+            .args(args)
+            .assert()
+            .success();
+    }
+
+    let outdir = new_repo_dir.path().join("repo");
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "transfer-metadata",
+            "--copy-targets",
+            "-k",
+            root_key.to_str().unwrap(),
+            "-m",
+            dir_url(repo_dir.path().join("metadata")).as_str(),
+            "-t",
+            dir_url(repo_dir.path().join("targets")).as_str(),
+            "-r",
+            root_json.to_str().unwrap(),
+            "--new-root",
+            new_root.to_str().unwrap(),
+            "-o",
+            outdir.to_str().unwrap(),
+            "--targets-expires",
+            expiration.to_rfc3339().as_str(),
+            "--targets-version",
+            "2",
+            "--snapshot-expires",
+            expiration.to_rfc3339().as_str(),
+            "--snapshot-version",
+            "2",
+            "--timestamp-expires",
+            expiration.to_rfc3339().as_str(),
+            "--timestamp-version",
+            "2",
+        ])
+        .assert()
+        .success();
+
+    assert!(outdir.join("metadata").join("targets.json").exists());
+    assert!(outdir.join("targets").join("file1.txt").exists());
+
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(&new_root).await.unwrap(),
+        dir_url(outdir.join("metadata")),
+        dir_url(outdir.join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    let file1 = TargetName::new("file1.txt").unwrap();
+    assert_eq!(
+        test_utils::read_to_end(repo.read_target(&file1).await.unwrap().unwrap()).await,
+        &b"This is an example target file."[..]
+    );
+}
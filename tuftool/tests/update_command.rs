@@ -218,6 +218,197 @@ async fn update_command_with_new_targets() {
     assert_eq!(repo.timestamp().signed.expires, new_timestamp_expiration);
 }
 
+#[tokio::test]
+// Ensure `--auto-version` and `--auto-expire` derive the new metadata from the existing repo
+async fn update_command_auto_version_and_expire() {
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let repo_dir = TempDir::new().unwrap();
+
+    // Create a repo using tuftool and the reference tuf implementation data
+    create_repo(repo_dir.path());
+
+    // Explicit arguments still take precedence over the automatic policy
+    let new_timestamp_expiration = Utc::now().checked_add_signed(days(4)).unwrap();
+    let new_snapshot_version: u64 = 250;
+    let metadata_base_url = &dir_url(repo_dir.path().join("metadata"));
+    let update_out = TempDir::new().unwrap();
+    let before = Utc::now();
+
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "update",
+            "-o",
+            update_out.path().to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            metadata_base_url.as_str(),
+            "--auto-version",
+            "--auto-expire",
+            "--snapshot-version",
+            format!("{}", new_snapshot_version).as_str(),
+            "--timestamp-expires",
+            new_timestamp_expiration.to_rfc3339().as_str(),
+        ])
+        .assert()
+        .success();
+
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_json).await.unwrap(),
+        dir_url(update_out.path().join("metadata")),
+        dir_url(update_out.path().join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+
+    assert_eq!(repo.targets().signed.version.get(), 18);
+    assert_eq!(repo.snapshot().signed.version.get(), new_snapshot_version);
+    assert_eq!(repo.timestamp().signed.version.get(), 32);
+    assert_eq!(repo.timestamp().signed.expires, new_timestamp_expiration);
+    assert!(repo.targets().signed.expires >= before.checked_add_signed(days(28)).unwrap());
+    assert!(repo.snapshot().signed.expires >= before.checked_add_signed(days(7)).unwrap());
+    assert!(repo.snapshot().signed.expires < before.checked_add_signed(days(8)).unwrap());
+}
+
+#[tokio::test]
+// Ensure the durations `--auto-expire` uses can be changed, and only together with it
+async fn update_command_auto_expire_durations() {
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let repo_dir = TempDir::new().unwrap();
+    create_repo(repo_dir.path());
+    let metadata_base_url = &dir_url(repo_dir.path().join("metadata"));
+    let update_out = TempDir::new().unwrap();
+    let update = |extra: &[&str]| {
+        let mut command = Command::cargo_bin("tuftool").unwrap();
+        command.args([
+            "update",
+            "-o",
+            update_out.path().to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--metadata-url",
+            metadata_base_url.as_str(),
+            "--auto-version",
+            "--targets-expires",
+            "in 7 days",
+            "--snapshot-expires",
+            "in 7 days",
+        ]);
+        command.args(extra).assert()
+    };
+
+    update(&[
+        "--timestamp-expires",
+        "in 7 days",
+        "--auto-expire-timestamp",
+        "3d",
+    ])
+    .failure();
+
+    let before = Utc::now();
+    update(&["--auto-expire", "--auto-expire-timestamp", "3d"]).success();
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(root_json).await.unwrap(),
+        dir_url(update_out.path().join("metadata")),
+        dir_url(update_out.path().join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    assert!(repo.timestamp().signed.expires >= before.checked_add_signed(days(3)).unwrap());
+    assert!(repo.timestamp().signed.expires < before.checked_add_signed(days(4)).unwrap());
+}
+
+#[tokio::test]
+// Ensure a local copy of a repo can be updated without a metadata URL
+async fn update_command_from_repo_dir() {
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let repo_dir = TempDir::new().unwrap();
+    create_repo(repo_dir.path());
+    let update_out = TempDir::new().unwrap();
+
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "update",
+            "-o",
+            update_out.path().to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--repo-dir",
+            repo_dir.path().to_str().unwrap(),
+            "--auto-version",
+            "--auto-expire",
+        ])
+        .assert()
+        .success();
+
+    let repo = RepositoryLoader::new(
+        &tokio::fs::read(&root_json).await.unwrap(),
+        dir_url(update_out.path().join("metadata")),
+        dir_url(repo_dir.path().join("targets")),
+    )
+    .load()
+    .await
+    .unwrap();
+    assert_eq!(repo.targets().signed.targets.len(), 3);
+    assert_eq!(repo.targets().signed.version.get(), 18);
+    assert_eq!(repo.timestamp().signed.version.get(), 32);
+
+    // Only one source of metadata may be given.
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "update",
+            "-o",
+            update_out.path().to_str().unwrap(),
+            "-k",
+            root_key.to_str().unwrap(),
+            "--root",
+            root_json.to_str().unwrap(),
+            "--repo-dir",
+            repo_dir.path().to_str().unwrap(),
+            "--metadata-url",
+            dir_url(repo_dir.path().join("metadata")).as_str(),
+            "--auto-version",
+            "--auto-expire",
+        ])
+        .assert()
+        .failure();
+}
+
+#[test]
+// Ensure versions and expirations are still required without the automatic flags
+fn update_without_versions_requires_auto_version() {
+    Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "update",
+            "--outdir",
+            "/outdir/does/not/matter",
+            "-k",
+            "/key/does/not/matter",
+            "--root",
+            "/root/does/not/matter",
+            "--metadata-url",
+            "https://metadata.url.does.not.matter",
+            "--auto-expire",
+        ])
+        .assert()
+        .failure();
+}
+
 #[test]
 // Ensure that the update command fails if none of the keys we give it match up with root.json.
 fn update_with_incorrect_key() {
@@ -389,3 +580,49 @@ async fn update_command_expired_repo_allow() {
     assert_eq!(repo.targets().signed.expires, update_expected.5);
     assert_eq!(repo.targets().signed.version.get(), update_expected.6);
 }
+
+#[test]
+// Ensure `tuftool update` refuses to re-sign with the snapshot version it loaded, unless told to
+fn update_command_version_regression() {
+    let root_json = test_utils::test_data().join("simple-rsa").join("root.json");
+    let root_key = test_utils::test_data().join("snakeoil.pem");
+    let repo_dir = TempDir::new().unwrap();
+    create_repo(repo_dir.path());
+    let metadata_base_url = dir_url(repo_dir.path().join("metadata"));
+    let expiration = Utc::now().checked_add_signed(days(4)).unwrap().to_rfc3339();
+
+    let update = |extra_args: &[&str]| {
+        let update_out = TempDir::new().unwrap();
+        Command::cargo_bin("tuftool")
+            .unwrap()
+            .args([
+                "update",
+                "-o",
+                update_out.path().to_str().unwrap(),
+                "-k",
+                root_key.to_str().unwrap(),
+                "--root",
+                root_json.to_str().unwrap(),
+                "--metadata-url",
+                metadata_base_url.as_str(),
+                "--targets-expires",
+                expiration.as_str(),
+                "--targets-version",
+                "18",
+                "--snapshot-expires",
+                expiration.as_str(),
+                // The same version `create_repo` used
+                "--snapshot-version",
+                "25",
+                "--timestamp-expires",
+                expiration.as_str(),
+                "--timestamp-version",
+                "32",
+            ])
+            .args(extra_args)
+            .assert()
+    };
+
+    update(&[]).failure();
+    update(&["--allow-version-regression"]).success();
+}
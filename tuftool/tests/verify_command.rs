@@ -0,0 +1,134 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+mod test_utils;
+
+use assert_cmd::Command;
+use serde_json::Value;
+use std::path::Path;
+use tempfile::TempDir;
+use test_utils::{create_expired_repo, dir_url, test_data};
+
+/// Runs `tuftool verify` with `args` and returns whether it succeeded and the report it printed.
+fn verify(root: &Path, metadata_dir: &Path, args: &[&str]) -> (bool, Value) {
+    let output = Command::cargo_bin("tuftool")
+        .unwrap()
+        .args([
+            "verify",
+            "--root",
+            root.to_str().unwrap(),
+            "--metadata-url",
+            dir_url(metadata_dir).as_str(),
+        ])
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        serde_json::from_slice(&output.stdout).unwrap(),
+    )
+}
+
+/// The `(check, role, severity)` of each finding in `report`.
+fn findings(report: &Value) -> Vec<(String, String, String)> {
+    report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|finding| {
+            let field = |name: &str| finding[name].as_str().unwrap_or_default().to_owned();
+            (field("check"), field("role"), field("severity"))
+        })
+        .collect()
+}
+
+fn finding(check: &str, role: &str, severity: &str) -> (String, String, String) {
+    (check.to_owned(), role.to_owned(), severity.to_owned())
+}
+
+#[test]
+// Ensure a valid repository passes, including reading each of its targets
+fn verify_reference_repo() {
+    let base = test_data().join("tuf-reference-impl");
+    let metadata_dir = base.join("metadata");
+    let (passed, report) = verify(
+        &metadata_dir.join("root.json"),
+        &metadata_dir,
+        &["--targets-url", dir_url(base.join("targets")).as_str()],
+    );
+    assert!(passed, "{}", report);
+    assert_eq!(report["passed"], true);
+    assert!(findings(&report).is_empty(), "{}", report);
+
+    // A wide enough warning window reports roles that expire within it, without failing.
+    let (passed, report) = verify(
+        &metadata_dir.join("root.json"),
+        &metadata_dir,
+        &["--warn-within", "1h", "--warn-within", "role1=5200w"],
+    );
+    assert!(passed, "{}", report);
+    assert_eq!(
+        findings(&report),
+        [finding("expiration", "role1", "warning")]
+    );
+}
+
+#[test]
+// Ensure expired metadata is an error, and metadata that expires soon is a warning
+fn verify_expired_repo() {
+    let repo_dir = TempDir::new().unwrap();
+    create_expired_repo(repo_dir.path());
+    let (passed, report) = verify(
+        &test_data().join("simple-rsa").join("root.json"),
+        &repo_dir.path().join("metadata"),
+        &[],
+    );
+    assert!(!passed);
+    assert_eq!(report["passed"], false);
+    assert_eq!(report["errors"], 1);
+    assert_eq!(
+        findings(&report),
+        [
+            finding("expiration", "timestamp", "error"),
+            finding("expiration", "snapshot", "warning"),
+            finding("expiration", "targets", "warning"),
+        ]
+    );
+}
+
+#[test]
+// Ensure a repository whose current root can't be fetched by its versioned filename fails
+fn verify_missing_versioned_root() {
+    let metadata_dir = TempDir::new().unwrap();
+    let source = test_data().join("tuf-reference-impl").join("metadata");
+    // Everything but `1.root.json`.
+    for file in [
+        "root.json",
+        "timestamp.json",
+        "snapshot.json",
+        "targets.json",
+        "role1.json",
+        "role2.json",
+    ] {
+        std::fs::copy(source.join(file), metadata_dir.path().join(file)).unwrap();
+    }
+    let (passed, report) = verify(&source.join("root.json"), metadata_dir.path(), &[]);
+    assert!(!passed);
+    assert_eq!(findings(&report), [finding("filenames", "root", "error")]);
+}
+
+#[test]
+// Ensure a repository that can't be loaded is reported as such
+fn verify_load_failure() {
+    let metadata_dir = TempDir::new().unwrap();
+    let (passed, report) = verify(
+        &test_data()
+            .join("tuf-reference-impl")
+            .join("metadata")
+            .join("root.json"),
+        metadata_dir.path(),
+        &[],
+    );
+    assert!(!passed);
+    assert_eq!(findings(&report), [finding("load", "", "error")]);
+}